@@ -0,0 +1,319 @@
+// src-tauri/src/accounts.rs
+
+use rusqlite::{params, Connection, OptionalExtension, Row};
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use tauri::{AppHandle, State};
+use log::{debug, error, info};
+
+use crate::audit;
+use crate::currencies::RateTable;
+use crate::error::AppError;
+use crate::events::{self, ChangeAction};
+use crate::money::round_money;
+use crate::periods;
+use crate::reports::GroupTotals;
+use crate::storage::{db_error, decimal_column, TransactionRepository};
+use crate::stores;
+use crate::validation;
+use crate::{AppState, Transaction, TransactionType};
+
+/// Nombre del grupo de transacciones sin cuenta en los saldos por cuenta.
+const NO_ACCOUNT: &str = "Sin cuenta";
+
+/// Dónde está el dinero: la caja, una cuenta bancaria o una tarjeta.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum AccountKind {
+    Efectivo,
+    Banco,
+    Tarjeta,
+}
+
+impl ToString for AccountKind {
+    fn to_string(&self) -> String {
+        match self {
+            AccountKind::Efectivo => "Efectivo".to_string(),
+            AccountKind::Banco => "Banco".to_string(),
+            AccountKind::Tarjeta => "Tarjeta".to_string(),
+        }
+    }
+}
+
+/// Cuenta de dinero. Las transacciones la referencian por `id`.
+#[derive(Debug, Clone, Serialize)]
+pub struct Account {
+    pub id: String,
+    pub name: String,
+    pub kind: AccountKind,
+    /// Saldo al empezar a usar la aplicación, en la moneda base. Puede ser negativo
+    /// (p. ej. una tarjeta con deuda).
+    pub opening_balance: Decimal,
+    pub created_at: u64,
+    pub updated_at: u64,
+}
+
+/// Datos de `create_account_command` y `update_account_command`.
+#[derive(Debug, Clone, Deserialize)]
+pub struct AccountInput {
+    pub name: String,
+    pub kind: AccountKind,
+    #[serde(default)]
+    pub opening_balance: Decimal,
+}
+
+/// Saldo de una cuenta en la moneda base.
+#[derive(Debug, Clone, Serialize)]
+pub struct AccountBalance {
+    /// `None` para las transacciones sin cuenta.
+    pub account_id: Option<String>,
+    pub name: String,
+    pub kind: Option<AccountKind>,
+    pub opening_balance: Decimal,
+    #[serde(flatten)]
+    pub totals: GroupTotals,
+    /// Saldo inicial más el resultado de sus transacciones.
+    pub balance: Decimal,
+}
+
+const ACCOUNT_COLUMNS: &str = "id, name, kind, opening_balance, created_at, updated_at";
+
+fn row_to_account(row: &Row) -> rusqlite::Result<Account> {
+    let kind = match row.get::<_, String>(2)?.as_str() {
+        "Banco" => AccountKind::Banco,
+        "Tarjeta" => AccountKind::Tarjeta,
+        _ => AccountKind::Efectivo,
+    };
+    Ok(Account {
+        id: row.get(0)?,
+        name: row.get(1)?,
+        kind,
+        opening_balance: decimal_column(row, 3)?,
+        created_at: row.get::<_, i64>(4)? as u64,
+        updated_at: row.get::<_, i64>(5)? as u64,
+    })
+}
+
+pub fn get_account(conn: &Connection, id: &str) -> Result<Option<Account>, AppError> {
+    conn.query_row(
+        &format!("SELECT {} FROM accounts WHERE id = ?1", ACCOUNT_COLUMNS),
+        params![id],
+        row_to_account,
+    )
+    .optional()
+    .map_err(db_error)
+}
+
+/// Cuentas ordenadas por nombre.
+pub fn list_accounts(conn: &Connection) -> Result<Vec<Account>, AppError> {
+    let mut stmt = conn
+        .prepare(&format!("SELECT {} FROM accounts ORDER BY name COLLATE NOCASE", ACCOUNT_COLUMNS))
+        .map_err(db_error)?;
+    let rows = stmt.query_map([], row_to_account).map_err(db_error)?;
+    rows.collect::<rusqlite::Result<Vec<_>>>().map_err(db_error)
+}
+
+/// Valida el `account_id` de una transacción. Un ID vacío equivale a ninguno; `field`
+/// es el campo al que se atribuye el error.
+pub fn resolve_account_id(conn: &Connection, field: &str, account_id: Option<String>) -> Result<Option<String>, AppError> {
+    match stores::non_empty(account_id) {
+        Some(id) => match get_account(conn, &id)? {
+            Some(account) => Ok(Some(account.id)),
+            None => {
+                error!("Account {} not found.", id);
+                Err(AppError::field_error(field, validation::NOT_FOUND, format!("La cuenta con ID {} no existe.", id)))
+            }
+        },
+        None => Ok(None),
+    }
+}
+
+/// Valida y normaliza los datos de una cuenta.
+fn resolve_input(conn: &Connection, input: AccountInput, except_id: Option<&str>) -> Result<AccountInput, AppError> {
+    let name = input.name.trim().to_owned();
+    if name.is_empty() {
+        return Err(AppError::invalid_field("name", "El nombre de la cuenta no puede estar vacío."));
+    }
+    let exists: bool = conn
+        .query_row(
+            "SELECT EXISTS(SELECT 1 FROM accounts WHERE name = ?1 COLLATE NOCASE AND id IS NOT ?2)",
+            params![name, except_id],
+            |row| row.get(0),
+        )
+        .map_err(db_error)?;
+    if exists {
+        return Err(AppError::Conflict(format!("Ya existe una cuenta llamada '{}'.", name)));
+    }
+    Ok(AccountInput { name, kind: input.kind, opening_balance: round_money(input.opening_balance) })
+}
+
+/// Saldo de cada cuenta con las transacciones hasta `to` (segundos Unix, inclusivo),
+/// o todas sin límite. Las transferencias restan en la cuenta de origen y suman en la
+/// de destino. Al final va el grupo de transacciones sin cuenta, si hay alguna.
+pub fn build_account_balances(
+    transactions: &[Transaction],
+    accounts: &[Account],
+    rates: &RateTable,
+    to: Option<u64>,
+) -> Result<Vec<AccountBalance>, AppError> {
+    let known: HashMap<&str, &Account> = accounts.iter().map(|a| (a.id.as_str(), a)).collect();
+    // Una cuenta eliminada cuenta como ninguna.
+    let key = |id: &Option<String>| id.as_deref().filter(|id| known.contains_key(id)).map(str::to_owned);
+    let mut by_account: BTreeMap<Option<String>, GroupTotals> = BTreeMap::new();
+    for transaction in transactions.iter().filter(|t| to.map_or(true, |to| t.timestamp <= to)) {
+        let amount = rates.to_base(transaction.amount, &transaction.currency)?;
+        if transaction.transaction_type == TransactionType::Transferencia {
+            let (source, destination) = (key(&transaction.account_id), key(&transaction.transfer_account_id));
+            // Entre tiendas de la misma cuenta (o sin cuenta) el saldo no cambia.
+            if source != destination {
+                by_account.entry(source).or_default().add_transfer(amount, false);
+                by_account.entry(destination).or_default().add_transfer(amount, true);
+            }
+            continue;
+        }
+        by_account
+            .entry(key(&transaction.account_id))
+            .or_default()
+            .add(&transaction.transaction_type, amount);
+    }
+
+    let mut balances: Vec<AccountBalance> = accounts
+        .iter()
+        .map(|account| {
+            let totals = by_account.remove(&Some(account.id.clone())).unwrap_or_default();
+            AccountBalance {
+                account_id: Some(account.id.clone()),
+                name: account.name.clone(),
+                kind: Some(account.kind),
+                opening_balance: account.opening_balance,
+                balance: account.opening_balance + totals.net,
+                totals,
+            }
+        })
+        .collect();
+    if let Some(totals) = by_account.remove(&None) {
+        balances.push(AccountBalance {
+            account_id: None,
+            name: NO_ACCOUNT.to_string(),
+            kind: None,
+            opening_balance: Decimal::ZERO,
+            balance: totals.net,
+            totals,
+        });
+    }
+    Ok(balances)
+}
+
+// --- Comandos Tauri ---
+
+/// Comando para listar las cuentas (caja, banco, tarjeta).
+#[tauri::command]
+pub async fn list_accounts_command(state: State<'_, AppState>) -> Result<Vec<Account>, AppError> {
+    debug!("Received list_accounts_command.");
+    list_accounts(state.db().await?.connection())
+}
+
+/// Comando para añadir una cuenta.
+#[tauri::command]
+pub async fn create_account_command(
+    state: State<'_, AppState>,
+    app: AppHandle,
+    account: AccountInput,
+) -> Result<Account, AppError> {
+    debug!("Received create_account_command: {:?}", account);
+    let db = state.db().await?;
+    let account = resolve_input(db.connection(), account, None)?;
+    let id = uuid::Uuid::new_v4().to_string();
+    let now = periods::now_timestamp() as i64;
+    db.connection()
+        .execute(
+            &format!("INSERT INTO accounts ({}) VALUES (?1, ?2, ?3, ?4, ?5, ?5)", ACCOUNT_COLUMNS),
+            params![id, account.name, account.kind.to_string(), account.opening_balance.to_string(), now],
+        )
+        .map_err(db_error)?;
+    let created = get_account(db.connection(), &id)?
+        .ok_or_else(|| AppError::Internal(format!("La cuenta {} no se guardó.", id)))?;
+    audit::record(db.connection(), "create_account_command", Some(&id), None, audit::snapshot(&created));
+    events::emit_entity(&app, events::ACCOUNT_CHANGED_EVENT, ChangeAction::Created, &id);
+    info!("Account '{}' created.", created.name);
+    Ok(created)
+}
+
+/// Comando para cambiar el nombre, el tipo o el saldo inicial de una cuenta.
+#[tauri::command]
+pub async fn update_account_command(
+    state: State<'_, AppState>,
+    app: AppHandle,
+    id: String,
+    account: AccountInput,
+) -> Result<Account, AppError> {
+    debug!("Received update_account_command for ID {}: {:?}", id, account);
+    let db = state.db().await?;
+    let Some(before) = get_account(db.connection(), &id)? else {
+        error!("Account {} not found for update.", id);
+        return Err(AppError::NotFound(format!("Cuenta con ID {} no encontrada.", id)));
+    };
+    let account = resolve_input(db.connection(), account, Some(&id))?;
+    db.connection()
+        .execute(
+            "UPDATE accounts SET name = ?2, kind = ?3, opening_balance = ?4, updated_at = ?5 WHERE id = ?1",
+            params![
+                id,
+                account.name,
+                account.kind.to_string(),
+                account.opening_balance.to_string(),
+                periods::now_timestamp() as i64
+            ],
+        )
+        .map_err(db_error)?;
+    let updated = get_account(db.connection(), &id)?
+        .ok_or_else(|| AppError::NotFound(format!("Cuenta con ID {} no encontrada.", id)))?;
+    audit::record(
+        db.connection(),
+        "update_account_command",
+        Some(&id),
+        audit::snapshot(&before),
+        audit::snapshot(&updated),
+    );
+    events::emit_entity(&app, events::ACCOUNT_CHANGED_EVENT, ChangeAction::Updated, &id);
+    Ok(updated)
+}
+
+/// Comando para eliminar una cuenta. Sus transacciones se conservan, sin cuenta.
+#[tauri::command]
+pub async fn delete_account_command(state: State<'_, AppState>, app: AppHandle, id: String) -> Result<(), AppError> {
+    debug!("Received delete_account_command: {}", id);
+    let db = state.db().await?;
+    let conn = db.connection();
+    let Some(before) = get_account(conn, &id)? else {
+        error!("Account {} not found for deletion.", id);
+        return Err(AppError::NotFound(format!("Cuenta con ID {} no encontrada.", id)));
+    };
+
+    let tx = conn.unchecked_transaction().map_err(db_error)?;
+    tx.execute("DELETE FROM accounts WHERE id = ?1", params![id]).map_err(db_error)?;
+    tx.execute("UPDATE transactions SET account_id = NULL WHERE account_id = ?1", params![id])
+        .map_err(db_error)?;
+    tx.execute("UPDATE transactions SET transfer_account_id = NULL WHERE transfer_account_id = ?1", params![id])
+        .map_err(db_error)?;
+    tx.commit().map_err(db_error)?;
+    audit::record(conn, "delete_account_command", Some(&id), audit::snapshot(&before), None);
+    events::emit_entity(&app, events::ACCOUNT_CHANGED_EVENT, ChangeAction::Deleted, &id);
+    info!("Account '{}' deleted.", before.name);
+    Ok(())
+}
+
+/// Comando para obtener el saldo de cada cuenta en la moneda base, con las
+/// transacciones hasta `to` (segundos Unix, inclusivo y opcional): lo que hay en la
+/// caja, en el banco y en cada tarjeta.
+#[tauri::command]
+pub async fn get_account_balances_command(
+    state: State<'_, AppState>,
+    to: Option<u64>,
+) -> Result<Vec<AccountBalance>, AppError> {
+    debug!("Received get_account_balances_command: to={:?}", to);
+    let db = state.db().await?;
+    let rates = RateTable::load(&db)?;
+    let accounts = list_accounts(db.connection())?;
+    build_account_balances(&db.list_transactions()?, &accounts, &rates, to)
+}
@@ -0,0 +1,200 @@
+// src-tauri/src/aggregates.rs
+
+use chrono::NaiveDate;
+use rust_decimal::Decimal;
+use std::collections::{BTreeMap, HashMap};
+use std::ops::Bound;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+use log::debug;
+
+use crate::error::AppError;
+use crate::history::Change;
+use crate::periods;
+use crate::reports::{self, GroupBy};
+use crate::storage::{SqliteStorage, TransactionRepository};
+use crate::{Transaction, TransactionType};
+
+// Totales por día de las transacciones activas, para que el panel, los balances por
+// tienda y la cuenta de resultados no recorran todas las transacciones en cada
+// llamada. Se construyen la primera vez que se piden y después se actualizan con cada
+// cambio que pasa por `events::emit_transaction_changes`; `events::emit_data_reloaded`
+// los descarta. Los importes se guardan en su moneda y se convierten al leerlos, así
+// que un cambio de tipos de cambio no los invalida.
+
+/// Grupo de transacciones del mismo día con los mismos datos de agrupación.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct BucketKey {
+    pub store_name: String,
+    /// Categoría tal como la agrupan los informes (`reports::group_key`).
+    pub category: String,
+    pub transaction_type: TransactionType,
+    pub currency: String,
+    /// Tienda de destino de las transferencias.
+    pub transfer_store: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Bucket {
+    pub amount: Decimal,
+    pub count: usize,
+}
+
+/// Totales de todas las transacciones activas. `version` es la de los datos de los
+/// que se calcularon (ver `VERSION`).
+#[derive(Debug, Default)]
+pub struct Aggregates {
+    version: u64,
+    days: BTreeMap<NaiveDate, HashMap<BucketKey, Bucket>>,
+}
+
+/// Versión de los datos: sube con cada cambio de transacciones y con cada recarga.
+/// Unos totales con otra versión no se usan.
+static VERSION: AtomicU64 = AtomicU64::new(0);
+static CACHE: Mutex<Option<Aggregates>> = Mutex::new(None);
+
+impl Aggregates {
+    fn build(transactions: &[Transaction], version: u64) -> Self {
+        let mut aggregates = Aggregates { version, days: BTreeMap::new() };
+        for transaction in transactions {
+            aggregates.apply(transaction, true);
+        }
+        aggregates
+    }
+
+    /// Suma (`add`) o resta una transacción. Las de la papelera no cuentan.
+    fn apply(&mut self, transaction: &Transaction, add: bool) {
+        if transaction.deleted_at.is_some() {
+            return;
+        }
+        let key = BucketKey {
+            store_name: transaction.store_name.clone(),
+            category: reports::group_key(transaction, GroupBy::Category),
+            transaction_type: transaction.transaction_type.clone(),
+            currency: transaction.currency.clone(),
+            transfer_store: transaction.transfer_store.clone(),
+        };
+        let date = periods::local_date(transaction.timestamp);
+        let day = self.days.entry(date).or_default();
+        let bucket = day.entry(key.clone()).or_default();
+        if add {
+            bucket.amount += transaction.amount;
+            bucket.count += 1;
+            return;
+        }
+        bucket.amount -= transaction.amount;
+        bucket.count = bucket.count.saturating_sub(1);
+        if bucket.count == 0 {
+            day.remove(&key);
+            if day.is_empty() {
+                self.days.remove(&date);
+            }
+        }
+    }
+
+    /// Grupos de los días de `[from, to)`; sin límite si falta alguno.
+    pub fn range(
+        &self,
+        from: Option<NaiveDate>,
+        to: Option<NaiveDate>,
+    ) -> impl Iterator<Item = (NaiveDate, &BucketKey, &Bucket)> {
+        let start = from.map_or(Bound::Unbounded, Bound::Included);
+        let end = to.map_or(Bound::Unbounded, Bound::Excluded);
+        self.days
+            .range((start, end))
+            .flat_map(|(date, day)| day.iter().map(move |(key, bucket)| (*date, key, bucket)))
+    }
+}
+
+/// Días `[desde, hasta)` equivalentes al rango de segundos `[from, to]`, si empieza y
+/// termina en un cambio de día (hora local). Si no, los totales por día no sirven.
+pub fn day_range(from: Option<u64>, to: Option<u64>) -> Option<(Option<NaiveDate>, Option<NaiveDate>)> {
+    let start = match from {
+        Some(from) => {
+            let date = periods::local_date(from);
+            (periods::local_midnight_timestamp(date) == from).then_some(Some(date))?
+        }
+        None => None,
+    };
+    let end = match to {
+        Some(to) => {
+            let date = periods::local_date(to + 1);
+            (periods::local_midnight_timestamp(date) == to + 1).then_some(Some(date))?
+        }
+        None => None,
+    };
+    Some((start, end))
+}
+
+/// Totales que se calculan por bloques, soltando la base de datos entre uno y otro
+/// (ver `startup`). Solo se guardan si los datos no cambiaron mientras tanto.
+pub struct AggregatesBuilder(Aggregates);
+
+impl AggregatesBuilder {
+    pub fn start() -> Self {
+        AggregatesBuilder(Aggregates { version: VERSION.load(Ordering::SeqCst), days: BTreeMap::new() })
+    }
+
+    pub fn add<'a>(&mut self, transactions: impl IntoIterator<Item = &'a Transaction>) {
+        for transaction in transactions {
+            self.0.apply(transaction, true);
+        }
+    }
+
+    /// Guarda los totales si siguen al día; si no, se recalcularán cuando se pidan.
+    /// Devuelve si se guardaron.
+    pub fn finish(self) -> bool {
+        let mut cache = CACHE.lock().unwrap();
+        if VERSION.load(Ordering::SeqCst) != self.0.version {
+            return false;
+        }
+        *cache = Some(self.0);
+        true
+    }
+}
+
+/// Ejecuta `f` con los totales actuales, calculándolos si no los hay o son de otra
+/// versión. Se llama con la base de datos bloqueada, así que nadie la cambia mientras.
+pub fn with_aggregates<R>(db: &SqliteStorage, f: impl FnOnce(&Aggregates) -> R) -> Result<R, AppError> {
+    let mut cache = CACHE.lock().unwrap();
+    let version = VERSION.load(Ordering::SeqCst);
+    if cache.as_ref().map_or(true, |a| a.version != version) {
+        let transactions = db.list_transactions()?;
+        debug!("Building aggregates from {} transactions (version {}).", transactions.len(), version);
+        *cache = Some(Aggregates::build(&transactions, version));
+    }
+    Ok(f(cache.as_ref().expect("totales calculados")))
+}
+
+/// Actualiza los totales con `changes`, ya guardados. Si no estaban al día se
+/// descartan y se recalcularán en la próxima consulta.
+pub fn apply_changes(changes: &[Change]) {
+    let mut cache = CACHE.lock().unwrap();
+    let previous = VERSION.fetch_add(1, Ordering::SeqCst);
+    let Some(aggregates) = cache.as_mut().filter(|a| a.version == previous) else {
+        *cache = None;
+        return;
+    };
+    for change in changes {
+        match change {
+            Change::Insert(t) => aggregates.apply(t, true),
+            Change::Update { before, after } => {
+                aggregates.apply(before, false);
+                aggregates.apply(after, true);
+            }
+            Change::Delete(t) => aggregates.apply(t, false),
+            // Los totales cuentan la transacción entera; el reparto se aplica al
+            // leerlos (ver `splits::split_transactions`).
+            Change::Splits { .. } => {}
+        }
+    }
+    aggregates.version = previous + 1;
+}
+
+/// Descarta los totales: los datos cambiaron por completo (copia restaurada, otro
+/// espacio de trabajo, sincronización) o sin pasar por `apply_changes`.
+pub fn invalidate() {
+    let mut cache = CACHE.lock().unwrap();
+    VERSION.fetch_add(1, Ordering::SeqCst);
+    *cache = None;
+}
@@ -0,0 +1,434 @@
+// src-tauri/src/ai.rs
+
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::future::Future;
+use tauri::{AppHandle, Emitter, State};
+use log::{debug, error, warn};
+
+use crate::ai_cache;
+use crate::ai_usage;
+use crate::error::AppError;
+use crate::gemini::{self, GeminiChunk, GeminiConfig, GeminiDone, GEMINI_CHUNK_EVENT, GEMINI_DONE_EVENT};
+use crate::local_ai::LocalAiConfig;
+use crate::settings::{self, Settings};
+use crate::AppState;
+
+const MAX_OUTPUT_TOKENS: u32 = 65_536;
+/// Veces que `generate_json` pide la respuesta antes de darla por no válida.
+const MAX_JSON_ATTEMPTS: usize = 3;
+
+/// Categorías de contenido que admiten un umbral de bloqueo propio (solo Gemini).
+const SAFETY_CATEGORIES: [&str; 5] = [
+    "HARM_CATEGORY_HARASSMENT",
+    "HARM_CATEGORY_HATE_SPEECH",
+    "HARM_CATEGORY_SEXUALLY_EXPLICIT",
+    "HARM_CATEGORY_DANGEROUS_CONTENT",
+    "HARM_CATEGORY_CIVIC_INTEGRITY",
+];
+const SAFETY_THRESHOLDS: [&str; 5] = [
+    "BLOCK_NONE",
+    "BLOCK_ONLY_HIGH",
+    "BLOCK_MEDIUM_AND_ABOVE",
+    "BLOCK_LOW_AND_ABOVE",
+    "OFF",
+];
+
+/// Proveedor de IA elegido en los ajustes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AiProvider {
+    /// API de Google Gemini.
+    Gemini,
+    /// Servidor local con API compatible con OpenAI (Ollama, llama.cpp): los datos no
+    /// salen del equipo.
+    Local,
+}
+
+/// Funciones de la aplicación que usan la IA. Cada una se puede desactivar en los
+/// ajustes, además de la IA entera (ver `ensure_enabled`).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AiFeature {
+    /// Chat, preguntas sobre la contabilidad y prompts libres.
+    Chat,
+    /// Lectura de transacciones escritas o dictadas, con su tienda y su categoría.
+    Categorization,
+    /// Lectura de tickets escaneados.
+    Ocr,
+    /// Resúmenes mensuales, explicación de gastos inusuales y consejos de IVA.
+    Insights,
+}
+
+impl AiFeature {
+    fn label(self) -> &'static str {
+        match self {
+            AiFeature::Chat => "el chat con la IA",
+            AiFeature::Categorization => "la lectura de transacciones escritas o dictadas",
+            AiFeature::Ocr => "la lectura de tickets",
+            AiFeature::Insights => "los resúmenes y análisis de la IA",
+        }
+    }
+}
+
+/// Comprueba que los ajustes permiten usar la IA y, si se indica, la función
+/// `feature`. Los comandos lo llaman antes de preparar nada para enviar.
+pub fn ensure_enabled(settings: &Settings, feature: Option<AiFeature>) -> Result<(), AppError> {
+    if !settings.ai_enabled {
+        warn!("AI request blocked: AI is disabled in the settings.");
+        return Err(AppError::AiDisabled {
+            message: "La IA está desactivada en los ajustes.".to_string(),
+            feature,
+        });
+    }
+    let Some(feature) = feature else { return Ok(()) };
+    let allowed = match feature {
+        AiFeature::Chat => settings.ai_chat_enabled,
+        AiFeature::Categorization => settings.ai_categorization_enabled,
+        AiFeature::Ocr => settings.ai_ocr_enabled,
+        AiFeature::Insights => settings.ai_insights_enabled,
+    };
+    if !allowed {
+        warn!("AI request blocked: {:?} is disabled in the settings.", feature);
+        return Err(AppError::AiDisabled {
+            message: format!("Has desactivado {} en los ajustes.", feature.label()),
+            feature: Some(feature),
+        });
+    }
+    Ok(())
+}
+
+/// Autor de un mensaje de la conversación con la IA.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AiRole {
+    User,
+    Model,
+}
+
+/// Imagen en línea, codificada en base64.
+#[derive(Debug, Clone)]
+pub struct AiImage {
+    pub mime_type: String,
+    pub data: String,
+}
+
+/// Mensaje que se envía a la IA.
+#[derive(Debug, Clone)]
+pub struct AiMessage {
+    pub role: AiRole,
+    pub text: String,
+    pub images: Vec<AiImage>,
+}
+
+impl AiMessage {
+    pub fn user(text: impl Into<String>) -> Self {
+        AiMessage { role: AiRole::User, text: text.into(), images: Vec::new() }
+    }
+
+    pub fn model(text: impl Into<String>) -> Self {
+        AiMessage { role: AiRole::Model, text: text.into(), images: Vec::new() }
+    }
+
+    pub fn with_image(mut self, mime_type: &str, data: String) -> Self {
+        self.images.push(AiImage { mime_type: mime_type.to_owned(), data });
+        self
+    }
+}
+
+/// Tokens consumidos por una llamada.
+#[derive(Debug, Clone, Copy, Default, Serialize)]
+pub struct TokenUsage {
+    pub prompt_tokens: u64,
+    pub response_tokens: u64,
+}
+
+/// Respuesta completa de la IA.
+#[derive(Debug, Clone)]
+pub struct AiReply {
+    pub text: String,
+    pub usage: TokenUsage,
+}
+
+/// Umbral de bloqueo para una categoría de contenido (valores de la API de Gemini).
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SafetySetting {
+    pub category: String,
+    pub threshold: String,
+}
+
+/// Parámetros de generación de una llamada. Los que falten usan los del proveedor;
+/// el modelo local ignora `safety_settings`.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct GenerationParams {
+    /// Entre 0 y 2; más alto, respuestas más variadas.
+    pub temperature: Option<f32>,
+    pub max_output_tokens: Option<u32>,
+    pub safety_settings: Vec<SafetySetting>,
+    /// Esquema de la respuesta JSON (formato `responseSchema` de Gemini). Solo lo fija
+    /// el backend (ver `generate_json`); el modelo local solo recibe la orden de
+    /// responder JSON.
+    #[serde(skip)]
+    pub response_schema: Option<Value>,
+}
+
+impl GenerationParams {
+    pub fn validate(&self) -> Result<(), AppError> {
+        if let Some(temperature) = self.temperature {
+            if !(0.0..=2.0).contains(&temperature) {
+                return Err(AppError::invalid_field("temperature", "La temperatura debe estar entre 0 y 2."));
+            }
+        }
+        if let Some(max_tokens) = self.max_output_tokens {
+            if max_tokens == 0 || max_tokens > MAX_OUTPUT_TOKENS {
+                return Err(AppError::invalid_field(
+                    "max_output_tokens",
+                    format!("El máximo de tokens debe estar entre 1 y {}.", MAX_OUTPUT_TOKENS),
+                ));
+            }
+        }
+        for setting in &self.safety_settings {
+            if !SAFETY_CATEGORIES.contains(&setting.category.as_str())
+                || !SAFETY_THRESHOLDS.contains(&setting.threshold.as_str())
+            {
+                error!("Invalid safety setting: {:?}", setting);
+                return Err(AppError::invalid_field(
+                    "safety_settings",
+                    format!("Ajuste de seguridad no válido: {} = {}.", setting.category, setting.threshold),
+                ));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Modelo disponible para generar texto.
+#[derive(Debug, Clone, Serialize)]
+pub struct ModelInfo {
+    /// Nombre con el que se usa en los ajustes (en Gemini, sin el prefijo `models/`).
+    pub name: String,
+    pub display_name: String,
+    pub description: String,
+    pub input_token_limit: Option<u64>,
+    pub output_token_limit: Option<u64>,
+}
+
+/// Proveedor de IA capaz de generar texto a partir de una conversación.
+pub trait AiBackend {
+    fn model(&self) -> &str;
+
+    /// Envía `messages` y devuelve la respuesta completa.
+    fn generate(
+        &self,
+        messages: &[AiMessage],
+        params: &GenerationParams,
+    ) -> impl Future<Output = Result<AiReply, AppError>> + Send;
+
+    /// Como `generate`, pero llamando a `on_chunk` con cada fragmento según llega.
+    fn stream<F>(
+        &self,
+        messages: &[AiMessage],
+        params: &GenerationParams,
+        on_chunk: F,
+    ) -> impl Future<Output = Result<AiReply, AppError>> + Send
+    where
+        F: FnMut(&str) + Send;
+
+    /// Modelos que se pueden elegir en los ajustes.
+    fn list_models(&self) -> impl Future<Output = Result<Vec<ModelInfo>, AppError>> + Send;
+}
+
+/// Proveedor configurado en los ajustes (ver `Settings::ai_client`).
+#[derive(Debug, Clone)]
+pub enum AiClient {
+    Gemini(GeminiConfig),
+    Local(LocalAiConfig),
+}
+
+impl AiClient {
+    /// Usa `model` en lugar del de los ajustes.
+    pub fn set_model(&mut self, model: String) -> Result<(), AppError> {
+        match self {
+            AiClient::Gemini(config) => {
+                gemini::validate_model(&model)?;
+                config.model = model;
+            }
+            AiClient::Local(config) => config.model = model,
+        }
+        Ok(())
+    }
+}
+
+impl AiBackend for AiClient {
+    fn model(&self) -> &str {
+        match self {
+            AiClient::Gemini(config) => config.model(),
+            AiClient::Local(config) => config.model(),
+        }
+    }
+
+    async fn generate(&self, messages: &[AiMessage], params: &GenerationParams) -> Result<AiReply, AppError> {
+        match self {
+            AiClient::Gemini(config) => config.generate(messages, params).await,
+            AiClient::Local(config) => config.generate(messages, params).await,
+        }
+    }
+
+    async fn stream<F>(&self, messages: &[AiMessage], params: &GenerationParams, on_chunk: F) -> Result<AiReply, AppError>
+    where
+        F: FnMut(&str) + Send,
+    {
+        match self {
+            AiClient::Gemini(config) => config.stream(messages, params, on_chunk).await,
+            AiClient::Local(config) => config.stream(messages, params, on_chunk).await,
+        }
+    }
+
+    async fn list_models(&self) -> Result<Vec<ModelInfo>, AppError> {
+        match self {
+            AiClient::Gemini(config) => config.list_models().await,
+            AiClient::Local(config) => config.list_models().await,
+        }
+    }
+}
+
+/// Envía `messages` y devuelve el texto de la respuesta. Si una petición idéntica está
+/// en la caché (ver `ai_cache`), se devuelve su respuesta sin llamar a la API. Si no,
+/// antes comprueba el límite de gasto mensual y después anota el consumo y guarda la
+/// respuesta en la caché.
+pub async fn generate(
+    state: &AppState,
+    client: &AiClient,
+    messages: &[AiMessage],
+    params: &GenerationParams,
+) -> Result<String, AppError> {
+    let key = ai_cache::cache_key(client, messages, params);
+    if let Some(text) = ai_cache::get(state, client, &key).await {
+        return Ok(text);
+    }
+    debug!("Generating text with {}", client.model());
+    ai_usage::check_spend_cap(state, client).await?;
+    let reply = client.generate(messages, params).await?;
+    ai_usage::record_usage(state, client, &reply.usage).await;
+    ai_cache::put(state, client, &key, &reply).await;
+    Ok(reply.text)
+}
+
+/// Envía `messages` pidiendo una respuesta JSON que cumpla `schema` y la convierte a
+/// `T`. Si la respuesta no se puede leer, se le devuelve a la IA con el error y se
+/// vuelve a pedir, hasta `MAX_JSON_ATTEMPTS` veces; después falla con
+/// `invalid_message`.
+pub async fn generate_json<T: DeserializeOwned>(
+    state: &AppState,
+    client: &AiClient,
+    messages: &[AiMessage],
+    schema: Value,
+    invalid_message: &str,
+) -> Result<T, AppError> {
+    let params = GenerationParams { response_schema: Some(schema), ..GenerationParams::default() };
+    let mut conversation = messages.to_vec();
+    for attempt in 1..=MAX_JSON_ATTEMPTS {
+        let text = generate(state, client, &conversation, &params).await?;
+        let error = match serde_json::from_str(strip_code_fences(&text)) {
+            Ok(value) => return Ok(value),
+            Err(e) => e,
+        };
+        warn!("Invalid JSON from AI (attempt {}/{}): {}", attempt, MAX_JSON_ATTEMPTS, error);
+        // Que la próxima petición igual no reciba la misma respuesta inservible.
+        ai_cache::forget(state, &ai_cache::cache_key(client, &conversation, &params)).await;
+        // La respuesta puede incluir datos del usuario: solo en el nivel `debug`.
+        debug!("Rejected AI response: {}", text);
+        conversation.push(AiMessage::model(text));
+        conversation.push(AiMessage::user(format!(
+            "La respuesta anterior no es válida ({}). Devuelve SOLO el JSON con el esquema indicado, sin texto adicional.",
+            error
+        )));
+    }
+    error!("AI did not return valid JSON after {} attempts.", MAX_JSON_ATTEMPTS);
+    Err(AppError::Ai(invalid_message.to_owned()))
+}
+
+/// Envía un único prompt de texto y devuelve el texto de la respuesta.
+pub async fn generate_text(state: &AppState, client: &AiClient, prompt: &str) -> Result<String, AppError> {
+    generate(state, client, &[AiMessage::user(prompt)], &GenerationParams::default()).await
+}
+
+/// Envía `messages` en streaming y reenvía cada fragmento al frontend como
+/// `gemini://chunk`, terminando siempre con `gemini://done`. Como `generate`, respeta
+/// el límite de gasto y anota el consumo. `request_id` permite
+/// al frontend saber a qué petición pertenece cada evento.
+pub async fn stream_to_frontend(
+    app: &AppHandle,
+    state: &AppState,
+    request_id: &str,
+    client: &AiClient,
+    messages: &[AiMessage],
+    params: &GenerationParams,
+) -> Result<String, AppError> {
+    debug!("Streaming request {} with {}", request_id, client.model());
+    ai_usage::check_spend_cap(state, client).await?;
+    let result = client
+        .stream(messages, params, |text| {
+            let chunk = GeminiChunk { request_id: request_id.to_owned(), text: text.to_owned() };
+            if let Err(e) = app.emit(GEMINI_CHUNK_EVENT, chunk) {
+                warn!("Failed to emit AI chunk: {}", e);
+            }
+        })
+        .await;
+    if let Ok(reply) = &result {
+        ai_usage::record_usage(state, client, &reply.usage).await;
+    }
+    let result = result.map(|reply| reply.text);
+
+    let done = GeminiDone {
+        request_id: request_id.to_owned(),
+        full_text: result.as_ref().ok().cloned(),
+        error: result.as_ref().err().map(|e| e.to_string()),
+    };
+    if let Err(e) = app.emit(GEMINI_DONE_EVENT, done) {
+        warn!("Failed to emit AI done event: {}", e);
+    }
+    result
+}
+
+/// Posición del primer separador de eventos SSE (`\n\n` o `\r\n\r\n`) y su longitud.
+pub fn find_event_boundary(buffer: &[u8]) -> Option<(usize, usize)> {
+    let lf = buffer.windows(2).position(|w| w == b"\n\n").map(|i| (i, 2));
+    let crlf = buffer.windows(4).position(|w| w == b"\r\n\r\n").map(|i| (i, 4));
+    match (lf, crlf) {
+        (Some(a), Some(b)) => Some(if a.0 <= b.0 { a } else { b }),
+        (a, b) => a.or(b),
+    }
+}
+
+/// Datos de un evento SSE: el contenido de sus líneas `data:`.
+pub fn sse_event_data(event: &str) -> String {
+    event
+        .lines()
+        .filter_map(|line| line.strip_prefix("data:"))
+        .map(str::trim_start)
+        .collect()
+}
+
+/// Quita las vallas de código Markdown (```json ... ```) que los modelos añaden a
+/// veces alrededor de una respuesta JSON.
+pub fn strip_code_fences(text: &str) -> &str {
+    let trimmed = text.trim();
+    let Some(rest) = trimmed.strip_prefix("```") else {
+        return trimmed;
+    };
+    let rest = rest.strip_prefix("json").unwrap_or(rest);
+    rest.strip_suffix("```").unwrap_or(rest).trim()
+}
+
+// --- Comandos Tauri ---
+
+/// Comando para listar los modelos del proveedor de IA de los ajustes.
+#[tauri::command]
+pub async fn list_available_models_command(state: State<'_, AppState>) -> Result<Vec<ModelInfo>, AppError> {
+    debug!("Received list_available_models_command.");
+    let settings = settings::load_settings(&state.db().await?);
+    ensure_enabled(&settings, None)?;
+    settings.ai_client().list_models().await
+}
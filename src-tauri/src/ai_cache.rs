@@ -0,0 +1,251 @@
+// src-tauri/src/ai_cache.rs
+
+use rusqlite::{params, Connection, OptionalExtension};
+use rust_decimal::Decimal;
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+use std::str::FromStr;
+use tauri::State;
+use log::{debug, info, warn};
+
+use crate::ai::{AiBackend, AiClient, AiMessage, AiReply, AiRole, GenerationParams, TokenUsage};
+use crate::ai_usage::{self, USAGE_CURRENCY};
+use crate::error::AppError;
+use crate::periods;
+use crate::settings;
+use crate::storage::db_error;
+use crate::sync::hex;
+use crate::AppState;
+
+/// Clave de la caché: hash SHA-256 de todo lo que determina la respuesta (proveedor,
+/// modelo, mensajes con sus imágenes y parámetros de generación). Cada campo va
+/// precedido de su longitud para que dos peticiones distintas no den el mismo texto.
+pub fn cache_key(client: &AiClient, messages: &[AiMessage], params: &GenerationParams) -> String {
+    let mut hasher = Sha256::new();
+    let mut field = |value: &[u8]| {
+        hasher.update((value.len() as u64).to_le_bytes());
+        hasher.update(value);
+    };
+    match client {
+        AiClient::Gemini(_) => field(b"gemini"),
+        AiClient::Local(config) => {
+            field(b"local");
+            field(config.base_url.as_bytes());
+        }
+    }
+    field(client.model().as_bytes());
+    for message in messages {
+        let role = match message.role {
+            AiRole::User => "user",
+            AiRole::Model => "model",
+        };
+        field(role.as_bytes());
+        field(message.text.as_bytes());
+        for image in &message.images {
+            field(image.mime_type.as_bytes());
+            field(image.data.as_bytes());
+        }
+    }
+    field(format!("{:?}", params.temperature).as_bytes());
+    field(format!("{:?}", params.max_output_tokens).as_bytes());
+    for setting in &params.safety_settings {
+        field(setting.category.as_bytes());
+        field(setting.threshold.as_bytes());
+    }
+    field(params.response_schema.as_ref().map(|s| s.to_string()).unwrap_or_default().as_bytes());
+    hex(&hasher.finalize())
+}
+
+fn provider_name(client: &AiClient) -> &'static str {
+    match client {
+        AiClient::Gemini(_) => "gemini",
+        AiClient::Local(_) => "local",
+    }
+}
+
+/// Respuesta guardada para `key` si no ha caducado. Anota el acierto o el fallo y,
+/// en un acierto, el coste que se ha ahorrado.
+fn lookup(conn: &Connection, client: &AiClient, key: &str) -> Result<Option<String>, AppError> {
+    let cached = conn
+        .query_row(
+            "SELECT response, prompt_tokens, response_tokens FROM ai_cache WHERE key = ?1 AND expires_at > ?2",
+            params![key, periods::now_timestamp() as i64],
+            |row| {
+                Ok((
+                    row.get::<_, String>(0)?,
+                    TokenUsage { prompt_tokens: row.get::<_, i64>(1)? as u64, response_tokens: row.get::<_, i64>(2)? as u64 },
+                ))
+            },
+        )
+        .optional()
+        .map_err(db_error)?;
+    let Some((response, usage)) = cached else {
+        conn.execute("UPDATE ai_cache_stats SET misses = misses + 1 WHERE id = 1", []).map_err(db_error)?;
+        return Ok(None);
+    };
+    conn.execute("UPDATE ai_cache SET hits = hits + 1 WHERE key = ?1", params![key]).map_err(db_error)?;
+    let saved: String = conn
+        .query_row("SELECT saved_cost FROM ai_cache_stats WHERE id = 1", [], |row| row.get(0))
+        .map_err(db_error)?;
+    let saved = Decimal::from_str(&saved).unwrap_or_default() + ai_usage::estimate_cost(client, &usage);
+    conn.execute(
+        "UPDATE ai_cache_stats SET hits = hits + 1, saved_cost = ?1 WHERE id = 1",
+        params![saved.to_string()],
+    )
+    .map_err(db_error)?;
+    Ok(Some(response))
+}
+
+/// Guarda la respuesta durante `ttl_secs` y borra de paso las caducadas.
+fn store(conn: &Connection, client: &AiClient, key: &str, reply: &AiReply, ttl_secs: u64) -> Result<(), AppError> {
+    let now = periods::now_timestamp();
+    conn.execute("DELETE FROM ai_cache WHERE expires_at <= ?1", params![now as i64]).map_err(db_error)?;
+    conn.execute(
+        "INSERT OR REPLACE INTO ai_cache
+            (key, provider, model, response, prompt_tokens, response_tokens, hits, created_at, expires_at)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, 0, ?7, ?8)",
+        params![
+            key,
+            provider_name(client),
+            client.model(),
+            reply.text,
+            reply.usage.prompt_tokens as i64,
+            reply.usage.response_tokens as i64,
+            now as i64,
+            (now + ttl_secs) as i64
+        ],
+    )
+    .map_err(db_error)?;
+    Ok(())
+}
+
+/// Respuesta guardada de una petición idéntica, si la caché está activada en los
+/// ajustes (`ai_cache_ttl_hours` mayor que 0). Un fallo al leer la caché no impide
+/// hacer la llamada: se trata como un fallo de caché.
+pub async fn get(state: &AppState, client: &AiClient, key: &str) -> Option<String> {
+    let db = match state.db().await {
+        Ok(db) => db,
+        Err(e) => {
+            warn!("Could not read the AI cache: {}", e);
+            return None;
+        }
+    };
+    if settings::load_settings(&db).ai_cache_ttl_hours == 0 {
+        return None;
+    }
+    match lookup(db.connection(), client, key) {
+        Ok(Some(response)) => {
+            debug!("AI cache hit for {}.", &key[..12]);
+            Some(response)
+        }
+        Ok(None) => None,
+        Err(e) => {
+            warn!("Could not read the AI cache: {}", e);
+            None
+        }
+    }
+}
+
+/// Guarda la respuesta de la petición `key` con la duración de los ajustes. Un fallo
+/// al guardarla no invalida la respuesta.
+pub async fn put(state: &AppState, client: &AiClient, key: &str, reply: &AiReply) {
+    let result = match state.db().await {
+        Ok(db) => {
+            let ttl_hours = settings::load_settings(&db).ai_cache_ttl_hours;
+            if ttl_hours == 0 {
+                return;
+            }
+            store(db.connection(), client, key, reply, ttl_hours * 3600)
+        }
+        Err(e) => Err(e),
+    };
+    if let Err(e) = result {
+        warn!("Could not store the AI response in the cache: {}", e);
+    }
+}
+
+/// Borra la respuesta guardada de la petición `key` (p. ej. porque no era válida).
+pub async fn forget(state: &AppState, key: &str) {
+    let result = match state.db().await {
+        Ok(db) => db
+            .connection()
+            .execute("DELETE FROM ai_cache WHERE key = ?1", params![key])
+            .map(|_| ())
+            .map_err(db_error),
+        Err(e) => Err(e),
+    };
+    if let Err(e) = result {
+        warn!("Could not remove the AI response from the cache: {}", e);
+    }
+}
+
+/// Estado y rendimiento de la caché de respuestas de la IA.
+#[derive(Debug, Clone, Serialize)]
+pub struct AiCacheStats {
+    /// Respuestas guardadas que aún no han caducado.
+    pub entries: u64,
+    pub size_bytes: u64,
+    pub ttl_hours: u64,
+    pub hits: u64,
+    pub misses: u64,
+    /// Porcentaje de peticiones respondidas desde la caché; `None` si aún no hay ninguna.
+    pub hit_rate_percent: Option<Decimal>,
+    /// Coste estimado que se ha ahorrado con los aciertos, en `currency`.
+    pub saved_cost: Decimal,
+    pub currency: String,
+}
+
+fn build_stats(conn: &Connection, ttl_hours: u64) -> Result<AiCacheStats, AppError> {
+    let (entries, size_bytes): (i64, i64) = conn
+        .query_row(
+            "SELECT COUNT(*), COALESCE(SUM(LENGTH(response)), 0) FROM ai_cache WHERE expires_at > ?1",
+            params![periods::now_timestamp() as i64],
+            |row| Ok((row.get(0)?, row.get(1)?)),
+        )
+        .map_err(db_error)?;
+    let (hits, misses, saved_cost): (i64, i64, String) = conn
+        .query_row("SELECT hits, misses, saved_cost FROM ai_cache_stats WHERE id = 1", [], |row| {
+            Ok((row.get(0)?, row.get(1)?, row.get(2)?))
+        })
+        .map_err(db_error)?;
+    let total = hits + misses;
+    Ok(AiCacheStats {
+        entries: entries as u64,
+        size_bytes: size_bytes as u64,
+        ttl_hours,
+        hits: hits as u64,
+        misses: misses as u64,
+        hit_rate_percent: (total > 0).then(|| (Decimal::from(hits * 100) / Decimal::from(total)).round_dp(1)),
+        saved_cost: Decimal::from_str(&saved_cost).unwrap_or_default().round_dp(4),
+        currency: USAGE_CURRENCY.to_string(),
+    })
+}
+
+// --- Comandos Tauri ---
+
+/// Comando para consultar la caché de respuestas de la IA: respuestas guardadas,
+/// aciertos y fallos, y el coste ahorrado.
+#[tauri::command]
+pub async fn get_ai_cache_stats_command(state: State<'_, AppState>) -> Result<AiCacheStats, AppError> {
+    debug!("Received get_ai_cache_stats_command.");
+    let db = state.db().await?;
+    let ttl_hours = settings::load_settings(&db).ai_cache_ttl_hours;
+    build_stats(db.connection(), ttl_hours)
+}
+
+/// Comando para vaciar la caché de respuestas de la IA. Con `reset_stats` también se
+/// ponen a cero los aciertos, los fallos y el coste ahorrado. Devuelve cuántas
+/// respuestas se han borrado.
+#[tauri::command]
+pub async fn clear_ai_cache_command(state: State<'_, AppState>, reset_stats: Option<bool>) -> Result<u64, AppError> {
+    debug!("Received clear_ai_cache_command (reset_stats: {:?}).", reset_stats);
+    let db = state.db().await?;
+    let conn = db.connection();
+    let deleted = conn.execute("DELETE FROM ai_cache", []).map_err(db_error)?;
+    if reset_stats.unwrap_or(false) {
+        conn.execute("UPDATE ai_cache_stats SET hits = 0, misses = 0, saved_cost = '0' WHERE id = 1", [])
+            .map_err(db_error)?;
+    }
+    info!("AI cache cleared ({} responses).", deleted);
+    Ok(deleted as u64)
+}
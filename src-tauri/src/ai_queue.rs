@@ -0,0 +1,347 @@
+// src-tauri/src/ai_queue.rs
+
+use rusqlite::{params, Connection, Row};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Duration;
+use tauri::{AppHandle, Emitter, Manager, State};
+use log::{debug, error, info, warn};
+
+use crate::ai::{self, AiFeature, AiProvider};
+use crate::error::AppError;
+use crate::periods;
+use crate::receipts;
+use crate::settings;
+use crate::storage::db_error;
+use crate::summaries;
+use crate::AppState;
+
+/// Cada cuánto se comprueba la conexión y se reenvían las peticiones pendientes.
+const WORKER_INTERVAL: Duration = Duration::from_secs(30);
+/// Tiempo máximo para comprobar si hay conexión.
+const CONNECTIVITY_TIMEOUT: Duration = Duration::from_secs(5);
+/// Dirección con la que se comprueba la conexión: cualquier respuesta vale.
+const CONNECTIVITY_URL: &str = "https://generativelanguage.googleapis.com/";
+
+/// Evento con el resultado de una petición encolada, al enviarse por fin.
+pub const AI_QUEUE_COMPLETED_EVENT: &str = "ai-queue://completed";
+/// Evento emitido cuando se pierde o se recupera la conexión.
+pub const CONNECTIVITY_EVENT: &str = "connectivity://changed";
+
+/// Última conexión conocida. Se supone que hay conexión hasta que falla algo.
+static ONLINE: AtomicBool = AtomicBool::new(true);
+/// `true` mientras se reenvían las peticiones pendientes, para no hacerlo dos veces.
+static PROCESSING: AtomicBool = AtomicBool::new(false);
+
+/// Petición a la IA que se puede guardar y repetir más tarde.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum AiRequest {
+    MonthlySummary { month: String },
+    Receipt { image_path: String },
+}
+
+impl AiRequest {
+    fn kind(&self) -> &'static str {
+        match self {
+            AiRequest::MonthlySummary { .. } => "monthly_summary",
+            AiRequest::Receipt { .. } => "receipt",
+        }
+    }
+}
+
+/// Petición guardada en la cola.
+#[derive(Debug, Clone, Serialize)]
+pub struct QueuedAiRequest {
+    pub id: String,
+    pub request: AiRequest,
+    /// `pending` mientras se espera a tener conexión; `failed` si al enviarla la IA
+    /// devolvió un error distinto de la falta de conexión.
+    pub status: String,
+    pub attempts: u32,
+    pub last_error: Option<String>,
+    pub created_at: u64,
+    pub updated_at: u64,
+}
+
+/// Carga útil de `ai-queue://completed`. `result` es lo que habría devuelto el comando
+/// original (p. ej. un `MonthlySummary`).
+#[derive(Debug, Clone, Serialize)]
+pub struct AiQueueCompleted {
+    pub id: String,
+    pub request: AiRequest,
+    pub result: Option<Value>,
+    pub error: Option<String>,
+}
+
+/// Carga útil de `connectivity://changed`.
+#[derive(Debug, Clone, Serialize)]
+pub struct ConnectivityChanged {
+    pub online: bool,
+}
+
+pub fn is_online() -> bool {
+    ONLINE.load(Ordering::SeqCst)
+}
+
+/// Errores que indican que no hay conexión (y no un fallo de la propia petición).
+fn is_offline_error(error: &AppError) -> bool {
+    matches!(error, AppError::Network(_))
+}
+
+fn row_to_request(row: &Row) -> rusqlite::Result<QueuedAiRequest> {
+    let request: String = row.get(1)?;
+    Ok(QueuedAiRequest {
+        id: row.get(0)?,
+        request: serde_json::from_str(&request).map_err(|e| {
+            rusqlite::Error::FromSqlConversionFailure(1, rusqlite::types::Type::Text, Box::new(e))
+        })?,
+        status: row.get(2)?,
+        attempts: row.get(3)?,
+        last_error: row.get(4)?,
+        created_at: row.get::<_, i64>(5)? as u64,
+        updated_at: row.get::<_, i64>(6)? as u64,
+    })
+}
+
+fn list_requests(conn: &Connection, status: Option<&str>) -> Result<Vec<QueuedAiRequest>, AppError> {
+    let mut stmt = conn
+        .prepare(
+            "SELECT id, request, status, attempts, last_error, created_at, updated_at FROM ai_outbox
+             WHERE ?1 IS NULL OR status = ?1 ORDER BY created_at",
+        )
+        .map_err(db_error)?;
+    let requests = stmt
+        .query_map(params![status], row_to_request)
+        .map_err(db_error)?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(db_error)?;
+    Ok(requests)
+}
+
+/// Guarda `request` en la cola y devuelve el `AppError::Queued` con el que responde
+/// el comando.
+async fn enqueue(state: &AppState, request: &AiRequest) -> Result<AppError, AppError> {
+    let id = uuid::Uuid::new_v4().to_string();
+    let json = serde_json::to_string(request)
+        .map_err(|e| AppError::Internal(format!("Error al guardar la petición a la IA: {}", e)))?;
+    let now = periods::now_timestamp() as i64;
+    state
+        .db()
+        .await?
+        .connection()
+        .execute(
+            "INSERT INTO ai_outbox (id, kind, request, created_at, updated_at) VALUES (?1, ?2, ?3, ?4, ?4)",
+            params![id, request.kind(), json, now],
+        )
+        .map_err(db_error)?;
+    info!("AI request {} queued ({}).", id, request.kind());
+    Ok(AppError::Queued {
+        message: "No hay conexión. La petición se enviará a la IA en cuanto vuelva la conexión.".to_string(),
+        request_id: id,
+    })
+}
+
+/// La cola solo tiene sentido con Gemini: la IA local no necesita Internet.
+async fn uses_remote_ai(state: &AppState) -> Result<bool, AppError> {
+    Ok(settings::load_settings(&state.db().await?).ai_provider == AiProvider::Gemini)
+}
+
+/// Encola `request` sin intentarlo si se sabe que no hay conexión.
+pub async fn ensure_online(state: &AppState, request: &AiRequest) -> Result<(), AppError> {
+    if is_online() || !uses_remote_ai(state).await? {
+        return Ok(());
+    }
+    Err(enqueue(state, request).await?)
+}
+
+/// Devuelve `result` tal cual salvo si falló por falta de conexión con Gemini: entonces
+/// encola `request` y devuelve `AppError::Queued`.
+pub async fn queue_if_offline<T>(state: &AppState, request: AiRequest, result: Result<T, AppError>) -> Result<T, AppError> {
+    match result {
+        Err(e) if is_offline_error(&e) && uses_remote_ai(state).await? => {
+            warn!("AI request failed without connection, queueing it: {}", e);
+            ONLINE.store(false, Ordering::SeqCst);
+            Err(enqueue(state, &request).await?)
+        }
+        other => other,
+    }
+}
+
+/// Comprueba si hay conexión con la API y emite `connectivity://changed` si cambió.
+pub async fn check_connectivity(app: &AppHandle) -> bool {
+    let online = match reqwest::Client::builder().timeout(CONNECTIVITY_TIMEOUT).build() {
+        Ok(client) => client.head(CONNECTIVITY_URL).send().await.is_ok(),
+        Err(e) => {
+            warn!("Could not build connectivity client: {}", e);
+            false
+        }
+    };
+    if ONLINE.swap(online, Ordering::SeqCst) != online {
+        info!("Connectivity changed: {}", if online { "online" } else { "offline" });
+        if let Err(e) = app.emit(CONNECTIVITY_EVENT, &ConnectivityChanged { online }) {
+            warn!("Failed to emit connectivity change: {}", e);
+        }
+    }
+    online
+}
+
+/// Repite una petición encolada y devuelve su resultado como JSON.
+/// Si el usuario ha desactivado la función mientras esperaba, falla con
+/// `AppError::AiDisabled` y no se envía.
+async fn execute(state: &AppState, request: &AiRequest) -> Result<Value, AppError> {
+    let settings = settings::load_settings(&state.db().await?);
+    let result = match request {
+        AiRequest::MonthlySummary { month } => {
+            ai::ensure_enabled(&settings, Some(AiFeature::Insights))?;
+            serde_json::to_value(summaries::generate_monthly_summary(state, month, false).await?)
+        }
+        AiRequest::Receipt { image_path } => {
+            ai::ensure_enabled(&settings, Some(AiFeature::Ocr))?;
+            serde_json::to_value(receipts::extract_receipt(state, &settings.ai_client(), image_path).await?)
+        }
+    };
+    result.map_err(|e| AppError::Internal(format!("Error al preparar el resultado de la IA: {}", e)))
+}
+
+/// Envía las peticiones pendientes en orden. Se detiene en cuanto falla una por falta
+/// de conexión; las que fallan por otro motivo se marcan como `failed`. Devuelve
+/// cuántas se completaron.
+async fn process_queue(app: &AppHandle) -> Result<usize, AppError> {
+    if PROCESSING.swap(true, Ordering::SeqCst) {
+        debug!("AI queue already being processed.");
+        return Ok(0);
+    }
+    let result = process_pending(app).await;
+    PROCESSING.store(false, Ordering::SeqCst);
+    result
+}
+
+async fn process_pending(app: &AppHandle) -> Result<usize, AppError> {
+    let state = app.state::<AppState>();
+    let pending = list_requests(state.db().await?.connection(), Some("pending"))?;
+    let mut completed = 0;
+    for queued in pending {
+        let result = execute(&state, &queued.request).await;
+        if let Err(e) = &result {
+            if is_offline_error(e) {
+                warn!("Still offline, stopping AI queue: {}", e);
+                ONLINE.store(false, Ordering::SeqCst);
+                state.db().await?.connection().execute(
+                    "UPDATE ai_outbox SET attempts = attempts + 1, last_error = ?2, updated_at = ?3 WHERE id = ?1",
+                    params![queued.id, e.to_string(), periods::now_timestamp() as i64],
+                ).map_err(db_error)?;
+                break;
+            }
+        }
+
+        {
+            let db = state.db().await?;
+            match &result {
+                Ok(_) => db.connection().execute("DELETE FROM ai_outbox WHERE id = ?1", params![queued.id]),
+                Err(e) => db.connection().execute(
+                    "UPDATE ai_outbox SET status = 'failed', attempts = attempts + 1, last_error = ?2, updated_at = ?3
+                     WHERE id = ?1",
+                    params![queued.id, e.to_string(), periods::now_timestamp() as i64],
+                ),
+            }
+            .map_err(db_error)?;
+        }
+        match &result {
+            Ok(_) => {
+                completed += 1;
+                info!("Queued AI request {} completed.", queued.id);
+            }
+            Err(e) => error!("Queued AI request {} failed: {}", queued.id, e),
+        }
+        let payload = AiQueueCompleted {
+            id: queued.id,
+            request: queued.request,
+            error: result.as_ref().err().map(|e| e.to_string()),
+            result: result.ok(),
+        };
+        if let Err(e) = app.emit(AI_QUEUE_COMPLETED_EVENT, &payload) {
+            warn!("Failed to emit AI queue result: {}", e);
+        }
+    }
+    Ok(completed)
+}
+
+/// Lanza la tarea que comprueba la conexión periódicamente y, cuando la hay, envía
+/// las peticiones a la IA que quedaron pendientes.
+pub fn spawn_worker(app: AppHandle) {
+    tauri::async_runtime::spawn(async move {
+        loop {
+            tokio::time::sleep(WORKER_INTERVAL).await;
+            let state = app.state::<AppState>();
+            if state.is_locked() {
+                continue;
+            }
+            let has_pending = match state.db().await {
+                Ok(db) => db
+                    .connection()
+                    .query_row("SELECT EXISTS (SELECT 1 FROM ai_outbox WHERE status = 'pending')", [], |row| row.get(0))
+                    .unwrap_or(false),
+                Err(_) => false,
+            };
+            // Sin nada pendiente solo hace falta comprobar la conexión si se había perdido.
+            if !has_pending && is_online() {
+                continue;
+            }
+            if check_connectivity(&app).await && has_pending {
+                if let Err(e) = process_queue(&app).await {
+                    warn!("No se pudo procesar la cola de peticiones a la IA: {}", e);
+                }
+            }
+        }
+    });
+}
+
+// --- Comandos Tauri ---
+
+/// Comando para ver las peticiones a la IA pendientes o fallidas.
+#[tauri::command]
+pub async fn list_ai_queue_command(state: State<'_, AppState>) -> Result<Vec<QueuedAiRequest>, AppError> {
+    debug!("Received list_ai_queue_command.");
+    list_requests(state.db().await?.connection(), None)
+}
+
+/// Comando para enviar ya las peticiones pendientes (las fallidas se reintentan
+/// también) si hay conexión. Devuelve cuántas se completaron.
+#[tauri::command]
+pub async fn process_ai_queue_command(state: State<'_, AppState>, app: AppHandle) -> Result<usize, AppError> {
+    debug!("Received process_ai_queue_command.");
+    state
+        .db()
+        .await?
+        .connection()
+        .execute("UPDATE ai_outbox SET status = 'pending' WHERE status = 'failed'", [])
+        .map_err(db_error)?;
+    if !check_connectivity(&app).await {
+        return Err(AppError::Network("No hay conexión. Las peticiones se enviarán cuando vuelva.".to_string()));
+    }
+    process_queue(&app).await
+}
+
+/// Comando para descartar una petición de la cola.
+#[tauri::command]
+pub async fn delete_ai_request_command(state: State<'_, AppState>, id: String) -> Result<(), AppError> {
+    debug!("Received delete_ai_request_command: {}", id);
+    let deleted = state
+        .db()
+        .await?
+        .connection()
+        .execute("DELETE FROM ai_outbox WHERE id = ?1", params![id])
+        .map_err(db_error)?;
+    if deleted == 0 {
+        return Err(AppError::NotFound(format!("Petición a la IA {} no encontrada.", id)));
+    }
+    Ok(())
+}
+
+/// Comando para saber si hay conexión con la IA. Comprueba la conexión en el momento.
+#[tauri::command]
+pub async fn get_connectivity_command(app: AppHandle) -> Result<bool, AppError> {
+    Ok(check_connectivity(&app).await)
+}
@@ -0,0 +1,224 @@
+// src-tauri/src/ai_usage.rs
+
+use chrono::NaiveDate;
+use rusqlite::{params, Connection};
+use rust_decimal::Decimal;
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::str::FromStr;
+use tauri::State;
+use log::{debug, error, warn};
+
+use crate::ai::{AiBackend, AiClient, TokenUsage};
+use crate::error::AppError;
+use crate::money::round_money;
+use crate::periods;
+use crate::settings;
+use crate::storage::db_error;
+use crate::AppState;
+
+/// Moneda de los precios de la API y, por tanto, del coste y del límite de gasto.
+pub const USAGE_CURRENCY: &str = "USD";
+
+/// Precio por millón de tokens (entrada, salida) en USD, por prefijo del modelo. Los
+/// prefijos más largos van antes para que `gemini-1.5-flash-8b` no cuente como
+/// `gemini-1.5-flash`. Los modelos que no aparecen se estiman como `gemini-1.5-flash`.
+const PRICES: &[(&str, (i64, u32), (i64, u32))] = &[
+    ("gemini-2.5-flash-lite", (10, 2), (40, 2)),
+    ("gemini-2.5-flash", (30, 2), (250, 2)),
+    ("gemini-2.5-pro", (125, 2), (1000, 2)),
+    ("gemini-2.0-flash-lite", (75, 3), (30, 2)),
+    ("gemini-2.0-flash", (10, 2), (40, 2)),
+    ("gemini-1.5-flash-8b", (375, 4), (15, 2)),
+    ("gemini-1.5-flash", (75, 3), (30, 2)),
+    ("gemini-1.5-pro", (125, 2), (500, 2)),
+];
+const FALLBACK_PRICE: ((i64, u32), (i64, u32)) = ((75, 3), (30, 2));
+
+/// Consumo de un modelo en un mes.
+#[derive(Debug, Clone, Serialize)]
+pub struct ModelUsage {
+    pub provider: String,
+    pub model: String,
+    pub calls: u64,
+    pub prompt_tokens: u64,
+    pub response_tokens: u64,
+    pub cost: Decimal,
+}
+
+/// Consumo de la IA en un mes, con el coste estimado en `currency`.
+#[derive(Debug, Clone, Serialize)]
+pub struct AiUsageReport {
+    pub month: String,
+    pub currency: String,
+    pub calls: u64,
+    pub prompt_tokens: u64,
+    pub response_tokens: u64,
+    pub cost: Decimal,
+    /// Límite de gasto mensual de los ajustes, si hay.
+    pub spend_cap: Option<Decimal>,
+    pub by_model: Vec<ModelUsage>,
+}
+
+fn provider_name(client: &AiClient) -> &'static str {
+    match client {
+        AiClient::Gemini(_) => "gemini",
+        AiClient::Local(_) => "local",
+    }
+}
+
+/// Coste estimado en USD de una llamada. La IA local no cuesta nada.
+pub fn estimate_cost(client: &AiClient, usage: &TokenUsage) -> Decimal {
+    let AiClient::Gemini(config) = client else {
+        return Decimal::ZERO;
+    };
+    let (input, output) = PRICES
+        .iter()
+        .find(|(prefix, _, _)| config.model.starts_with(prefix))
+        .map(|(_, input, output)| (*input, *output))
+        .unwrap_or(FALLBACK_PRICE);
+    let per_token = |(mantissa, scale): (i64, u32), tokens: u64| {
+        Decimal::new(mantissa, scale) * Decimal::from(tokens) / Decimal::from(1_000_000)
+    };
+    per_token(input, usage.prompt_tokens) + per_token(output, usage.response_tokens)
+}
+
+fn current_month() -> String {
+    periods::today().format("%Y-%m").to_string()
+}
+
+/// Gasto estimado de `month`.
+fn month_cost(conn: &Connection, month: &str) -> Result<Decimal, AppError> {
+    let mut stmt = conn.prepare("SELECT cost FROM ai_usage WHERE month = ?1").map_err(db_error)?;
+    let costs = stmt
+        .query_map(params![month], |row| row.get::<_, String>(0))
+        .map_err(db_error)?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(db_error)?;
+    Ok(costs.iter().filter_map(|c| Decimal::from_str(c).ok()).sum())
+}
+
+/// Rechaza la llamada si el gasto del mes ya alcanzó el límite de los ajustes. La IA
+/// local no tiene límite.
+pub async fn check_spend_cap(state: &AppState, client: &AiClient) -> Result<(), AppError> {
+    if matches!(client, AiClient::Local(_)) {
+        return Ok(());
+    }
+    let db = state.db().await?;
+    let Some(cap) = settings::load_settings(&db).ai_monthly_spend_cap else {
+        return Ok(());
+    };
+    let spent = month_cost(db.connection(), &current_month())?;
+    if spent >= cap {
+        warn!("AI monthly spend cap reached: {} >= {}", spent, cap);
+        return Err(AppError::LimitReached(format!(
+            "Se ha alcanzado el límite de gasto mensual en IA ({} {} de {} {}). Súbelo en los ajustes o espera al mes que viene.",
+            round_money(spent),
+            USAGE_CURRENCY,
+            cap,
+            USAGE_CURRENCY
+        )));
+    }
+    Ok(())
+}
+
+/// Anota el consumo de una llamada. Un fallo al guardarlo no invalida la respuesta.
+pub async fn record_usage(state: &AppState, client: &AiClient, usage: &TokenUsage) {
+    let cost = estimate_cost(client, usage);
+    let result = match state.db().await {
+        Ok(db) => db
+            .connection()
+            .execute(
+                "INSERT INTO ai_usage (month, timestamp, provider, model, prompt_tokens, response_tokens, cost)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+                params![
+                    current_month(),
+                    periods::now_timestamp() as i64,
+                    provider_name(client),
+                    client.model(),
+                    usage.prompt_tokens as i64,
+                    usage.response_tokens as i64,
+                    cost.to_string()
+                ],
+            )
+            .map(|_| ())
+            .map_err(db_error),
+        Err(e) => Err(e),
+    };
+    match result {
+        Ok(()) => debug!("AI usage recorded: {:?}, cost {} {}", usage, cost, USAGE_CURRENCY),
+        Err(e) => warn!("No se pudo anotar el consumo de la IA: {}", e),
+    }
+}
+
+fn build_report(conn: &Connection, month: &str, spend_cap: Option<Decimal>) -> Result<AiUsageReport, AppError> {
+    let mut stmt = conn
+        .prepare("SELECT provider, model, prompt_tokens, response_tokens, cost FROM ai_usage WHERE month = ?1")
+        .map_err(db_error)?;
+    let rows = stmt
+        .query_map(params![month], |row| {
+            Ok((
+                row.get::<_, String>(0)?,
+                row.get::<_, String>(1)?,
+                row.get::<_, i64>(2)? as u64,
+                row.get::<_, i64>(3)? as u64,
+                row.get::<_, String>(4)?,
+            ))
+        })
+        .map_err(db_error)?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(db_error)?;
+
+    let mut by_model: BTreeMap<(String, String), ModelUsage> = BTreeMap::new();
+    for (provider, model, prompt_tokens, response_tokens, cost) in rows {
+        let entry = by_model.entry((provider.clone(), model.clone())).or_insert_with(|| ModelUsage {
+            provider,
+            model,
+            calls: 0,
+            prompt_tokens: 0,
+            response_tokens: 0,
+            cost: Decimal::ZERO,
+        });
+        entry.calls += 1;
+        entry.prompt_tokens += prompt_tokens;
+        entry.response_tokens += response_tokens;
+        entry.cost += Decimal::from_str(&cost).unwrap_or_default();
+    }
+    let by_model: Vec<ModelUsage> = by_model
+        .into_values()
+        .map(|m| ModelUsage { cost: m.cost.round_dp(4), ..m })
+        .collect();
+
+    Ok(AiUsageReport {
+        month: month.to_owned(),
+        currency: USAGE_CURRENCY.to_string(),
+        calls: by_model.iter().map(|m| m.calls).sum(),
+        prompt_tokens: by_model.iter().map(|m| m.prompt_tokens).sum(),
+        response_tokens: by_model.iter().map(|m| m.response_tokens).sum(),
+        cost: by_model.iter().map(|m| m.cost).sum(),
+        spend_cap,
+        by_model,
+    })
+}
+
+// --- Comandos Tauri ---
+
+/// Comando para consultar el consumo de la IA de un mes (`AAAA-MM`, por defecto el
+/// actual), en total y por modelo.
+#[tauri::command]
+pub async fn get_ai_usage_command(state: State<'_, AppState>, month: Option<String>) -> Result<AiUsageReport, AppError> {
+    debug!("Received get_ai_usage_command: {:?}", month);
+    let month = match month.map(|m| m.trim().to_owned()).filter(|m| !m.is_empty()) {
+        Some(month) => {
+            if NaiveDate::parse_from_str(&format!("{}-01", month), "%Y-%m-%d").is_err() {
+                error!("Invalid month: '{}'", month);
+                return Err(AppError::invalid_field("month", "El mes debe tener el formato AAAA-MM."));
+            }
+            month
+        }
+        None => current_month(),
+    };
+    let db = state.db().await?;
+    let spend_cap = settings::load_settings(&db).ai_monthly_spend_cap;
+    build_report(db.connection(), &month, spend_cap)
+}
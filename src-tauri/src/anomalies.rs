@@ -0,0 +1,240 @@
+// src-tauri/src/anomalies.rs
+
+use rust_decimal::prelude::ToPrimitive;
+use rusqlite::Connection;
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use tauri::State;
+use log::{debug, info, warn};
+
+use crate::ai::{self, AiClient, AiFeature, AiMessage};
+use crate::currencies::RateTable;
+use crate::error::AppError;
+use crate::periods::{self, Period};
+use crate::privacy::Redactor;
+use crate::prompts;
+use crate::reports::{self, GroupBy};
+use crate::settings::{self, Settings};
+use crate::storage::{SqliteStorage, TransactionRepository};
+use crate::{AppState, Transaction, TransactionType};
+
+/// Gastos anteriores de la misma tienda y categoría necesarios para juzgar uno nuevo.
+const MIN_HISTORY: usize = 5;
+/// Puntuación a partir de la cual un gasto se considera anómalo (Iglesias y Hoaglin).
+const ANOMALY_THRESHOLD: f64 = 3.5;
+/// Factor que hace la MAD comparable a la desviación típica de una normal.
+const MAD_SCALE: f64 = 0.6745;
+/// Anomalías que se envían a la IA para que las explique.
+const MAX_EXPLAINED: usize = 10;
+
+/// Gasto inusual para su tienda y categoría.
+#[derive(Debug, Clone, Serialize)]
+pub struct Anomaly {
+    pub transaction: Transaction,
+    /// Puntuación z robusta (basada en la mediana y la MAD). Positiva si el gasto es
+    /// mayor de lo habitual.
+    pub score: f64,
+    /// Importe habitual (mediana) de la tienda y categoría, en la moneda base.
+    pub median: Decimal,
+    pub base_currency: String,
+    pub reason: String,
+    /// Explicación de la IA, si se pidió.
+    pub explanation: Option<String>,
+}
+
+/// Mediana de importes ya ordenados. `sorted` no puede estar vacío.
+pub fn median(sorted: &[Decimal]) -> Decimal {
+    let mid = sorted.len() / 2;
+    if sorted.len() % 2 == 0 {
+        (sorted[mid - 1] + sorted[mid]) / Decimal::TWO
+    } else {
+        sorted[mid]
+    }
+}
+
+/// Mediana y desviación absoluta mediana (MAD) de los importes.
+fn median_and_mad(amounts: &[Decimal]) -> (Decimal, Decimal) {
+    let mut sorted = amounts.to_vec();
+    sorted.sort();
+    let center = median(&sorted);
+    let mut deviations: Vec<Decimal> = sorted.iter().map(|a| (*a - center).abs()).collect();
+    deviations.sort();
+    (center, median(&deviations))
+}
+
+/// Busca los gastos de `[start, end)` inusuales respecto al resto de gastos de su
+/// misma tienda y categoría, de mayor a menor puntuación.
+pub fn detect_anomalies(
+    transactions: &[Transaction],
+    rates: &RateTable,
+    settings: &Settings,
+    (start, end): (u64, u64),
+) -> Result<Vec<Anomaly>, AppError> {
+    let mut groups: HashMap<(String, String), Vec<(&Transaction, Decimal)>> = HashMap::new();
+    for transaction in transactions.iter().filter(|t| t.transaction_type == TransactionType::Gasto) {
+        let amount = rates.to_base(transaction.amount, &transaction.currency)?;
+        groups
+            .entry((transaction.store_name.clone(), reports::group_key(transaction, GroupBy::Category)))
+            .or_default()
+            .push((transaction, amount));
+    }
+
+    let mut anomalies = Vec::new();
+    for ((store, category), members) in groups {
+        for (index, (transaction, amount)) in members.iter().enumerate() {
+            if transaction.timestamp < start || transaction.timestamp >= end {
+                continue;
+            }
+            // Cada gasto se compara con el resto, sin él.
+            let others: Vec<Decimal> = members
+                .iter()
+                .enumerate()
+                .filter(|(i, _)| *i != index)
+                .map(|(_, (_, a))| *a)
+                .collect();
+            if others.len() < MIN_HISTORY {
+                continue;
+            }
+            let (center, mad) = median_and_mad(&others);
+            if mad.is_zero() {
+                continue;
+            }
+            let score = MAD_SCALE * ((*amount - center) / mad).to_f64().unwrap_or(0.0);
+            if score.abs() < ANOMALY_THRESHOLD {
+                continue;
+            }
+            let ratio = if center.is_zero() { None } else { (*amount / center).to_f64() };
+            let base = &rates.base_currency;
+            let reason = format!(
+                "{} en '{}' ({}), cuando lo habitual es {}{}.",
+                settings.format_money(*amount, base, base),
+                store,
+                category,
+                settings.format_money(center, base, base),
+                ratio.map_or_else(String::new, |r| format!(": {:.1} veces lo normal", r)),
+            );
+            anomalies.push(Anomaly {
+                transaction: (*transaction).clone(),
+                score,
+                median: center.round_dp(2),
+                base_currency: rates.base_currency.clone(),
+                reason,
+                explanation: None,
+            });
+        }
+    }
+    anomalies.sort_by(|a, b| b.score.abs().total_cmp(&a.score.abs()));
+    Ok(anomalies)
+}
+
+/// Explicación de la IA para una anomalía, tal como se pide en el prompt.
+#[derive(Debug, Deserialize)]
+struct Explanation {
+    id: String,
+    explanation: String,
+}
+
+/// Esquema de la lista de `Explanation` para la salida estructurada de la IA.
+fn explanations_schema() -> Value {
+    json!({
+        "type": "ARRAY",
+        "items": {
+            "type": "OBJECT",
+            "properties": {
+                "id": {"type": "STRING"},
+                "explanation": {"type": "STRING"},
+            },
+            "required": ["id", "explanation"],
+        },
+    })
+}
+
+/// Gastos inusuales del periodo actual, de más a menos inusual.
+pub fn load_anomalies(db: &SqliteStorage, settings: &Settings, period: Period) -> Result<Vec<Anomaly>, AppError> {
+    let rates = RateTable::load(db)?;
+    let bounds = period.bounds_containing(periods::now_timestamp());
+    detect_anomalies(&db.list_transactions()?, &rates, settings, bounds)
+}
+
+/// Prompt con las primeras `MAX_EXPLAINED` anomalías, con las tiendas y las
+/// descripciones ocultadas por `redactor`.
+pub fn build_explanation_prompt(conn: &Connection, anomalies: &[Anomaly], redactor: &mut Redactor) -> Result<String, AppError> {
+    let items: Vec<_> = anomalies
+        .iter()
+        .take(MAX_EXPLAINED)
+        .map(|a| {
+            // El motivo cita la tienda: se registra antes para ocultarla también ahí.
+            redactor.store(&a.transaction.store_name);
+            json!({
+                "id": a.transaction.id,
+                "descripcion": redactor.description(&a.transaction.description),
+                "fecha": a.transaction.transaction_date.to_string(),
+                "motivo": redactor.text(&a.reason),
+            })
+        })
+        .collect();
+    let items_json = serde_json::to_string_pretty(&items).unwrap_or_default();
+    prompts::render_prompt(conn, prompts::ANOMALY_EXPLANATION, &[("items_json", &items_json)])
+}
+
+/// Pide a la IA una explicación breve para las primeras `MAX_EXPLAINED` anomalías.
+async fn explain_anomalies(
+    state: &AppState,
+    client: &AiClient,
+    settings: &Settings,
+    anomalies: &mut [Anomaly],
+) -> Result<(), AppError> {
+    let (prompt, redactor) = {
+        let db = state.db().await?;
+        let mut redactor = Redactor::new(db.connection(), settings)?;
+        (build_explanation_prompt(db.connection(), anomalies, &mut redactor)?, redactor)
+    };
+    let explanations: Vec<Explanation> = ai::generate_json(
+        state,
+        client,
+        &[AiMessage::user(prompt)],
+        explanations_schema(),
+        "La IA no devolvió explicaciones válidas.",
+    )
+    .await?;
+    for explanation in explanations {
+        if let Some(anomaly) = anomalies.iter_mut().find(|a| a.transaction.id == explanation.id) {
+            anomaly.explanation = Some(redactor.restore(explanation.explanation.trim()));
+        }
+    }
+    Ok(())
+}
+
+// --- Comandos Tauri ---
+
+/// Comando para buscar gastos inusuales del periodo actual (por defecto, `Mensual`),
+/// de más a menos inusual. Con `explain`, la IA añade una explicación a los primeros;
+/// si falla, se devuelven sin ella. Pedir la explicación con las funciones de análisis
+/// de la IA desactivadas devuelve `AppError::AiDisabled`.
+#[tauri::command]
+pub async fn detect_anomalies_command(
+    state: State<'_, AppState>,
+    period: Option<Period>,
+    explain: Option<bool>,
+) -> Result<Vec<Anomaly>, AppError> {
+    let period = period.unwrap_or(Period::Mensual);
+    debug!("Received detect_anomalies_command: {:?} (explain: {:?})", period, explain);
+    let (mut anomalies, settings) = {
+        let db = state.db().await?;
+        let settings = settings::load_settings(&db);
+        if explain.unwrap_or(false) {
+            ai::ensure_enabled(&settings, Some(AiFeature::Insights))?;
+        }
+        (load_anomalies(&db, &settings, period)?, settings)
+    };
+    info!("Found {} anomalous expenses.", anomalies.len());
+
+    if explain.unwrap_or(false) && !anomalies.is_empty() {
+        if let Err(e) = explain_anomalies(&state, &settings.ai_client(), &settings, &mut anomalies).await {
+            warn!("Could not explain anomalies: {}", e);
+        }
+    }
+    Ok(anomalies)
+}
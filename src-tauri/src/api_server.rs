@@ -0,0 +1,368 @@
+// src-tauri/src/api_server.rs
+
+use aes_gcm::aead::rand_core::RngCore;
+use aes_gcm::aead::OsRng;
+use axum::extract::{Query, Request, State as AxumState};
+use axum::http::{header, StatusCode};
+use axum::middleware::{self, Next};
+use axum::response::{IntoResponse, Response};
+use axum::routing::get;
+use axum::{Json, Router};
+use base64::Engine;
+use serde::{Deserialize, Serialize};
+use std::net::Ipv4Addr;
+use std::sync::{Mutex, OnceLock};
+use std::time::Duration;
+use tauri::{AppHandle, Manager};
+use tokio::net::TcpListener;
+use tokio::sync::Notify;
+use log::{debug, error, info, warn};
+
+use crate::audit;
+use crate::budgets;
+use crate::error::AppError;
+use crate::events;
+use crate::history::{Change, HistoryEntry};
+use crate::keychain;
+use crate::reports::{self, GroupBy, ProfitLossReport};
+use crate::settings;
+use crate::storage::TransactionRepository;
+use crate::{build_new_transaction, AppState, NewTransaction, Transaction};
+
+/// Nombre del token de acceso en el llavero.
+const TOKEN_SECRET: &str = "api_server_token";
+/// Prefijo de `external_id` de las transacciones creadas por la API, para no chocar
+/// con los identificadores de los extractos importados.
+const EXTERNAL_ID_PREFIX: &str = "api:";
+/// Cada cuánto se revisa si el servidor debe estar activo, además de cada vez que
+/// cambian los ajustes (ver `settings_changed`).
+const SERVICE_TICK: Duration = Duration::from_secs(60);
+
+/// Servidor activo.
+struct Server {
+    task: tauri::async_runtime::JoinHandle<()>,
+    port: u16,
+}
+
+static SERVER: Mutex<Option<Server>> = Mutex::new(None);
+/// Token leído del llavero, para no consultarlo en cada petición.
+static TOKEN: Mutex<Option<String>> = Mutex::new(None);
+
+/// Despierta a `spawn_service` antes de `SERVICE_TICK`.
+fn wake() -> &'static Notify {
+    static WAKE: OnceLock<Notify> = OnceLock::new();
+    WAKE.get_or_init(Notify::new)
+}
+
+/// Estado del servidor para la pantalla de ajustes.
+#[derive(Debug, Clone, Serialize)]
+pub struct ApiServerStatus {
+    pub enabled: bool,
+    pub running: bool,
+    /// `http://127.0.0.1:<puerto>` si está activo.
+    pub url: Option<String>,
+    /// Token que hay que enviar como `Authorization: Bearer <token>`.
+    pub token: String,
+}
+
+/// Error de la API: el `AppError` serializado con el código HTTP que le corresponde.
+struct ApiError(AppError);
+
+impl From<AppError> for ApiError {
+    fn from(e: AppError) -> Self {
+        ApiError(e)
+    }
+}
+
+impl IntoResponse for ApiError {
+    fn into_response(self) -> Response {
+        let status = match &self.0 {
+            AppError::Validation { .. } => StatusCode::BAD_REQUEST,
+            AppError::NotFound(_) => StatusCode::NOT_FOUND,
+            AppError::Conflict(_) | AppError::Duplicate { .. } | AppError::PeriodClosed(_) => StatusCode::CONFLICT,
+            AppError::Locked => StatusCode::LOCKED,
+            AppError::RateLimited { .. } => StatusCode::TOO_MANY_REQUESTS,
+            _ => StatusCode::INTERNAL_SERVER_ERROR,
+        };
+        (status, Json(self.0)).into_response()
+    }
+}
+
+/// Filtros de `GET /transactions`: rango en segundos Unix (inclusivo) y tienda.
+#[derive(Debug, Deserialize)]
+struct TransactionsQuery {
+    from: Option<u64>,
+    to: Option<u64>,
+    store: Option<String>,
+}
+
+/// Parámetros de `GET /reports/pnl`. Sin `group_by` se agrupa por mes.
+#[derive(Debug, Deserialize)]
+struct ProfitLossQuery {
+    from: Option<u64>,
+    to: Option<u64>,
+    group_by: Option<GroupBy>,
+}
+
+/// Cuerpo de `POST /transactions`: los campos de `add_transactions_batch_command` y,
+/// opcionalmente, el identificador de la venta en el sistema de origen. Si se envía,
+/// una segunda petición con el mismo identificador devuelve `409` sin duplicarla.
+#[derive(Deserialize)]
+struct ApiNewTransaction {
+    #[serde(default)]
+    external_id: Option<String>,
+    #[serde(flatten)]
+    transaction: NewTransaction,
+}
+
+fn generate_token() -> String {
+    let mut bytes = [0u8; 32];
+    OsRng.fill_bytes(&mut bytes);
+    base64::engine::general_purpose::URL_SAFE_NO_PAD.encode(bytes)
+}
+
+/// Token actual; se genera y se guarda en el llavero la primera vez.
+fn current_token() -> Result<String, AppError> {
+    let mut cached = TOKEN.lock().unwrap();
+    if let Some(token) = cached.as_ref() {
+        return Ok(token.clone());
+    }
+    let token = match keychain::get_secret(TOKEN_SECRET)? {
+        Some(token) if !token.is_empty() => token,
+        _ => {
+            let token = generate_token();
+            keychain::set_secret(TOKEN_SECRET, &token)?;
+            info!("API server token generated.");
+            token
+        }
+    };
+    *cached = Some(token.clone());
+    Ok(token)
+}
+
+/// Compara sin salir antes en el primer byte distinto.
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0u8, |acc, (x, y)| acc | (x ^ y)) == 0
+}
+
+async fn require_token(request: Request, next: Next) -> Response {
+    let provided = request
+        .headers()
+        .get(header::AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "))
+        .map(str::trim);
+    let token = match current_token() {
+        Ok(token) => token,
+        Err(e) => return ApiError(e).into_response(),
+    };
+    match provided {
+        Some(provided) if constant_time_eq(provided.as_bytes(), token.as_bytes()) => next.run(request).await,
+        _ => {
+            warn!("API request to {} rejected: missing or invalid token.", request.uri().path());
+            let body = serde_json::json!({ "code": "unauthorized", "message": "Token de acceso no válido.", "field": null });
+            (StatusCode::UNAUTHORIZED, Json(body)).into_response()
+        }
+    }
+}
+
+// --- Rutas ---
+
+async fn list_transactions(
+    AxumState(app): AxumState<AppHandle>,
+    Query(query): Query<TransactionsQuery>,
+) -> Result<Json<Vec<Transaction>>, ApiError> {
+    debug!("API GET /transactions: {:?}", query);
+    let state = app.state::<AppState>();
+    let db = state.db().await?;
+    let store = query.store.map(|s| s.trim().to_lowercase()).filter(|s| !s.is_empty());
+    let transactions = db
+        .list_transactions()?
+        .into_iter()
+        .filter(|t| reports::in_range(t.timestamp, query.from, query.to))
+        .filter(|t| store.as_ref().map_or(true, |s| t.store_name.to_lowercase() == *s))
+        .collect();
+    Ok(Json(transactions))
+}
+
+async fn profit_loss(
+    AxumState(app): AxumState<AppHandle>,
+    Query(query): Query<ProfitLossQuery>,
+) -> Result<Json<ProfitLossReport>, ApiError> {
+    debug!("API GET /reports/pnl: {:?}", query);
+    let state = app.state::<AppState>();
+    let db = state.db().await?;
+    let report = reports::profit_loss_report(&db, query.from, query.to, query.group_by.unwrap_or(GroupBy::Month), None)?;
+    Ok(Json(report))
+}
+
+async fn create_transaction(
+    AxumState(app): AxumState<AppHandle>,
+    Json(input): Json<ApiNewTransaction>,
+) -> Result<(StatusCode, Json<Transaction>), ApiError> {
+    let state = app.state::<AppState>();
+    let db = state.db().await?;
+    let external_id = match input.external_id.as_deref().map(str::trim) {
+        Some("") | None => None,
+        Some(id) => Some(format!("{}{}", EXTERNAL_ID_PREFIX, id)),
+    };
+    if let Some(id) = &external_id {
+        if db.all_external_ids()?.contains(id) {
+            return Err(AppError::Conflict(format!("La venta {} ya se registró.", &id[EXTERNAL_ID_PREFIX.len()..])).into());
+        }
+    }
+    let mut transaction = build_new_transaction(&db, input.transaction)?;
+    transaction.external_id = external_id;
+    db.insert_transaction(&transaction)?;
+
+    let changes = vec![Change::Insert(transaction.clone())];
+    audit::record_changes(db.connection(), "api_server", &changes);
+    events::emit_transaction_changes(&app, &changes);
+    state.history.lock().unwrap().record(HistoryEntry::new("Añadir transacción (API)", changes));
+    budgets::check_budget_alerts(&db, &app);
+    info!("API created transaction {} ({:?}).", transaction.id, transaction.external_id);
+    Ok((StatusCode::CREATED, Json(transaction)))
+}
+
+// --- Servicio ---
+
+async fn start_server(app: &AppHandle, port: u16) -> Result<Server, AppError> {
+    let listener = TcpListener::bind((Ipv4Addr::LOCALHOST, port))
+        .await
+        .map_err(|e| AppError::Network(format!("No se pudo abrir el puerto {}: {}", port, e)))?;
+    let router = Router::new()
+        .route("/transactions", get(list_transactions).post(create_transaction))
+        .route("/reports/pnl", get(profit_loss))
+        .layer(middleware::from_fn(require_token))
+        .with_state(app.clone());
+    let task = tauri::async_runtime::spawn(async move {
+        if let Err(e) = axum::serve(listener, router).await {
+            error!("API server stopped: {}", e);
+        }
+    });
+    info!("API server listening on 127.0.0.1:{}.", port);
+    Ok(Server { task, port })
+}
+
+fn stop_server() {
+    if let Some(server) = SERVER.lock().unwrap().take() {
+        server.task.abort();
+        info!("API server stopped.");
+    }
+}
+
+/// Arranca, para o reinicia el servidor según los ajustes. Devuelve el puerto si
+/// está activo.
+async fn apply_settings(app: &AppHandle) -> Result<Option<u16>, AppError> {
+    let state = app.state::<AppState>();
+    if state.is_locked() {
+        stop_server();
+        return Ok(None);
+    }
+    let settings = settings::load_settings(&*state.db().await?);
+
+    let running_port = SERVER.lock().unwrap().as_ref().map(|s| s.port);
+    match (settings.api_server_enabled, running_port) {
+        (false, _) => {
+            stop_server();
+            Ok(None)
+        }
+        (true, Some(port)) if port == settings.api_server_port => Ok(Some(port)),
+        (true, _) => {
+            stop_server();
+            current_token()?;
+            let server = start_server(app, settings.api_server_port).await?;
+            *SERVER.lock().unwrap() = Some(server);
+            Ok(Some(settings.api_server_port))
+        }
+    }
+}
+
+/// Mantiene el servidor HTTP local de acuerdo con los ajustes. Solo escucha en
+/// 127.0.0.1 y exige el token en cada petición, para que otros programas del mismo
+/// equipo (p. ej. el TPV) consulten transacciones e informes y registren ventas.
+pub fn spawn_service(app: AppHandle) {
+    tauri::async_runtime::spawn(async move {
+        loop {
+            if let Err(e) = apply_settings(&app).await {
+                warn!("No se pudo actualizar el servidor de la API: {}", e);
+            }
+            let _ = tokio::time::timeout(SERVICE_TICK, wake().notified()).await;
+        }
+    });
+}
+
+/// Se llama al guardar los ajustes, para que activar, desactivar o cambiar el puerto
+/// del servidor surta efecto enseguida.
+pub fn settings_changed() {
+    wake().notify_one();
+}
+
+// --- Comandos Tauri ---
+
+/// Comando para obtener el estado del servidor de la API y su token. Aplica antes
+/// los ajustes, así que refleja enseguida un cambio de puerto o de activación.
+#[tauri::command]
+pub async fn get_api_server_status_command(app: AppHandle) -> Result<ApiServerStatus, AppError> {
+    debug!("Received get_api_server_status_command.");
+    let port = apply_settings(&app).await?;
+    let enabled = {
+        let state = app.state::<AppState>();
+        let db = state.db().await?;
+        settings::load_settings(&db).api_server_enabled
+    };
+    Ok(ApiServerStatus {
+        enabled,
+        running: port.is_some(),
+        url: port.map(|port| format!("http://127.0.0.1:{}", port)),
+        token: current_token()?,
+    })
+}
+
+/// Comando para sustituir el token de la API por uno nuevo. El anterior deja de
+/// valer al instante; hay que actualizarlo en los programas que lo usen.
+#[tauri::command]
+pub async fn regenerate_api_token_command() -> Result<String, AppError> {
+    debug!("Received regenerate_api_token_command.");
+    let token = generate_token();
+    keychain::set_secret(TOKEN_SECRET, &token)?;
+    *TOKEN.lock().unwrap() = Some(token.clone());
+    info!("API server token regenerated.");
+    Ok(token)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn constant_time_eq_compares_the_whole_token() {
+        assert!(constant_time_eq(b"abc", b"abc"));
+        assert!(!constant_time_eq(b"abc", b"abd"));
+        assert!(!constant_time_eq(b"abc", b"abcd"));
+    }
+
+    #[tokio::test]
+    async fn require_token_rejects_requests_without_the_token() {
+        *TOKEN.lock().unwrap() = Some("token-de-prueba".to_string());
+        let router = Router::new()
+            .route("/ping", get(|| async { "ok" }))
+            .layer(middleware::from_fn(require_token));
+        let listener = TcpListener::bind((Ipv4Addr::LOCALHOST, 0)).await.unwrap();
+        let url = format!("http://{}/ping", listener.local_addr().unwrap());
+        tokio::spawn(async move { axum::serve(listener, router).await.unwrap() });
+
+        let client = reqwest::Client::new();
+        let status = |authorization: Option<&str>| {
+            let mut request = client.get(&url);
+            if let Some(value) = authorization {
+                request = request.header(header::AUTHORIZATION, value);
+            }
+            async move { request.send().await.unwrap().status().as_u16() }
+        };
+        assert_eq!(status(None).await, 401);
+        assert_eq!(status(Some("Bearer otro-token")).await, 401);
+        assert_eq!(status(Some("token-de-prueba")).await, 401);
+        assert_eq!(status(Some("Bearer token-de-prueba")).await, 200);
+    }
+}
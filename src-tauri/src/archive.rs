@@ -0,0 +1,393 @@
+// src-tauri/src/archive.rs
+
+use base64::Engine;
+use rusqlite::types::{Value, ValueRef};
+use rusqlite::{params_from_iter, Connection};
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value as JsonValue};
+use std::collections::HashSet;
+use std::path::Path;
+use tauri::{AppHandle, Emitter, State};
+use log::{debug, error, info, warn};
+
+use crate::api_server;
+use crate::attachments;
+use crate::audit;
+use crate::backup;
+use crate::error::AppError;
+use crate::events;
+use crate::i18n;
+use crate::migrations;
+use crate::periods;
+use crate::settings::{self, Settings, SETTINGS_CHANGED_EVENT};
+use crate::storage::{self, db_error, SqliteStorage, TransactionRepository};
+use crate::{AppState, Transaction};
+
+/// Identifica los archivos de exportación de la aplicación.
+const ARCHIVE_FORMAT: &str = "contabilidad-ia-workspace";
+/// Versión del formato del archivo. Cambia si cambia su estructura, no el esquema.
+const ARCHIVE_VERSION: u32 = 1;
+/// Tablas que se exportan fila a fila, tal como están en la base de datos.
+const ARCHIVE_TABLES: &[&str] = &["stores", "categories", "budgets", "goals", "prompt_templates"];
+
+/// Qué hacer con los datos que ya existen al importar.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MergeStrategy {
+    /// Se borra todo lo actual y se deja solo lo del archivo.
+    Replace,
+    /// Se añade lo nuevo y, si algo ya existe, gana la versión del archivo.
+    Merge,
+    /// Se añade lo nuevo y, si algo ya existe, se conserva la versión actual.
+    Skip,
+}
+
+/// Justificante adjunto, con su contenido en base64.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ArchiveAttachment {
+    /// Ruta relativa al directorio de datos, como en `Transaction::receipt_paths`.
+    path: String,
+    data: String,
+}
+
+/// Archivo JSON con todo un espacio de trabajo.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct WorkspaceArchive {
+    format: String,
+    version: u32,
+    /// Versión del esquema de la base de datos de la que se exportó.
+    schema_version: usize,
+    pub exported_at: u64,
+    /// Incluye las de la papelera.
+    pub transactions: Vec<Transaction>,
+    /// Filas de cada tabla de `ARCHIVE_TABLES`, como objetos columna → valor.
+    tables: Map<String, JsonValue>,
+    settings: Settings,
+    pub attachments: Vec<ArchiveAttachment>,
+}
+
+/// Resultado de `export_workspace_command`.
+#[derive(Debug, Serialize)]
+pub struct ExportWorkspaceSummary {
+    pub path: String,
+    pub transactions: usize,
+    pub attachments: usize,
+}
+
+/// Resultado de `import_workspace_command`.
+#[derive(Debug, Default, Serialize)]
+pub struct ImportWorkspaceSummary {
+    pub transactions_added: usize,
+    pub transactions_updated: usize,
+    /// Transacciones que ya existían y se conservaron (`skip`).
+    pub transactions_skipped: usize,
+    /// Tiendas, categorías y presupuestos guardados.
+    pub rows_imported: usize,
+    pub attachments_written: usize,
+    pub settings_imported: bool,
+}
+
+fn archive_error(path: &Path, e: impl std::fmt::Display) -> AppError {
+    error!("Invalid workspace archive {}: {}", path.display(), e);
+    AppError::invalid_field("path", format!("El archivo {} no es una exportación válida: {}", path.display(), e))
+}
+
+fn sql_to_json(value: ValueRef) -> JsonValue {
+    match value {
+        ValueRef::Null => JsonValue::Null,
+        ValueRef::Integer(i) => JsonValue::from(i),
+        ValueRef::Real(f) => JsonValue::from(f),
+        ValueRef::Text(t) => JsonValue::from(String::from_utf8_lossy(t).into_owned()),
+        ValueRef::Blob(b) => JsonValue::from(base64::engine::general_purpose::STANDARD.encode(b)),
+    }
+}
+
+fn json_to_sql(value: &JsonValue) -> Value {
+    match value {
+        JsonValue::Null => Value::Null,
+        JsonValue::Bool(b) => Value::Integer(*b as i64),
+        JsonValue::Number(n) => n.as_i64().map(Value::Integer).unwrap_or_else(|| Value::Real(n.as_f64().unwrap_or(0.0))),
+        JsonValue::String(s) => Value::Text(s.clone()),
+        other => Value::Text(other.to_string()),
+    }
+}
+
+fn table_columns(conn: &Connection, table: &str) -> Result<Vec<String>, AppError> {
+    let mut stmt = conn.prepare(&format!("PRAGMA table_info({})", table)).map_err(db_error)?;
+    let rows = stmt.query_map([], |row| row.get::<_, String>(1)).map_err(db_error)?;
+    rows.collect::<rusqlite::Result<Vec<_>>>().map_err(db_error)
+}
+
+fn export_table(conn: &Connection, table: &str) -> Result<JsonValue, AppError> {
+    let columns = table_columns(conn, table)?;
+    let mut stmt = conn.prepare(&format!("SELECT {} FROM {}", columns.join(", "), table)).map_err(db_error)?;
+    let rows = stmt
+        .query_map([], |row| {
+            let mut object = Map::new();
+            for (index, column) in columns.iter().enumerate() {
+                object.insert(column.clone(), sql_to_json(row.get_ref(index)?));
+            }
+            Ok(JsonValue::Object(object))
+        })
+        .map_err(db_error)?;
+    Ok(JsonValue::Array(rows.collect::<rusqlite::Result<Vec<_>>>().map_err(db_error)?))
+}
+
+/// Inserta las filas de `table`. Solo se usan las columnas que existen en la tabla,
+/// así que un archivo de un esquema anterior se importa con los valores por defecto
+/// en las columnas nuevas. Con `Merge` las filas en conflicto se sustituyen y con
+/// `Skip` se conservan las actuales.
+fn import_table(conn: &Connection, table: &str, rows: &[JsonValue], strategy: MergeStrategy) -> Result<usize, AppError> {
+    let columns: HashSet<String> = table_columns(conn, table)?.into_iter().collect();
+    let verb = match strategy {
+        MergeStrategy::Skip => "INSERT OR IGNORE",
+        MergeStrategy::Replace | MergeStrategy::Merge => "INSERT OR REPLACE",
+    };
+    let mut imported = 0;
+    for row in rows {
+        let Some(object) = row.as_object() else {
+            return Err(AppError::validation(format!("Fila no válida en la tabla {} del archivo.", table)));
+        };
+        let (names, values): (Vec<&String>, Vec<Value>) =
+            object.iter().filter(|(name, _)| columns.contains(*name)).map(|(name, value)| (name, json_to_sql(value))).unzip();
+        if names.is_empty() {
+            continue;
+        }
+        let placeholders: Vec<String> = (1..=names.len()).map(|i| format!("?{}", i)).collect();
+        let sql = format!(
+            "{} INTO {} ({}) VALUES ({})",
+            verb,
+            table,
+            names.iter().map(|n| n.as_str()).collect::<Vec<_>>().join(", "),
+            placeholders.join(", ")
+        );
+        imported += conn.execute(&sql, params_from_iter(values)).map_err(db_error)?;
+    }
+    Ok(imported)
+}
+
+/// Comprueba que la ruta de un adjunto sea `attachments/<id>/<archivo>`, sin salir
+/// del directorio de adjuntos.
+fn validate_attachment_path(path: &str) -> Result<(), AppError> {
+    let parts: Vec<&str> = path.split('/').collect();
+    match parts.as_slice() {
+        [root, transaction_id, file_name] if *root == attachments::ATTACHMENTS_DIR_NAME => {
+            attachments::validate_path_component(transaction_id)?;
+            attachments::validate_path_component(file_name)
+        }
+        _ => Err(AppError::validation(format!("Ruta de adjunto no válida: '{}'.", path))),
+    }
+}
+
+/// Reúne todo el espacio de trabajo activo en un archivo.
+pub fn build_archive(db: &SqliteStorage) -> Result<WorkspaceArchive, AppError> {
+    let mut transactions = db.list_transactions()?;
+    transactions.extend(db.list_deleted_transactions()?);
+
+    let mut tables = Map::new();
+    for table in ARCHIVE_TABLES {
+        tables.insert(table.to_string(), export_table(db.connection(), table)?);
+    }
+
+    let mut archive_attachments = Vec::new();
+    for path in transactions.iter().flat_map(|t| t.receipt_paths.iter()) {
+        match std::fs::read(attachments::resolve_receipt_path(path)) {
+            Ok(bytes) => archive_attachments.push(ArchiveAttachment {
+                path: path.clone(),
+                data: base64::engine::general_purpose::STANDARD.encode(bytes),
+            }),
+            Err(e) => warn!("Attachment {} not exported: {}", path, e),
+        }
+    }
+
+    Ok(WorkspaceArchive {
+        format: ARCHIVE_FORMAT.to_string(),
+        version: ARCHIVE_VERSION,
+        schema_version: migrations::schema_version(),
+        exported_at: periods::now_timestamp(),
+        transactions,
+        tables,
+        settings: settings::load_settings(db),
+        attachments: archive_attachments,
+    })
+}
+
+fn read_archive(path: &Path) -> Result<WorkspaceArchive, AppError> {
+    let contents = std::fs::read(path).map_err(|e| {
+        error!("Could not read workspace archive {}: {}", path.display(), e);
+        AppError::Io(format!("Error al leer {}: {}", path.display(), e))
+    })?;
+    let archive: WorkspaceArchive = serde_json::from_slice(&contents).map_err(|e| archive_error(path, e))?;
+    if archive.format != ARCHIVE_FORMAT {
+        return Err(archive_error(path, "no es un archivo de espacio de trabajo"));
+    }
+    validate_archive(&archive)?;
+    Ok(archive)
+}
+
+/// Comprueba que la aplicación sepa importar el archivo y que sus rutas de adjuntos
+/// no salgan del directorio de adjuntos.
+pub fn validate_archive(archive: &WorkspaceArchive) -> Result<(), AppError> {
+    if archive.format != ARCHIVE_FORMAT {
+        return Err(AppError::validation("El contenido no es un archivo de espacio de trabajo."));
+    }
+    if archive.version > ARCHIVE_VERSION || archive.schema_version > migrations::schema_version() {
+        return Err(AppError::invalid_field(
+            "path",
+            "El archivo se exportó con una versión más reciente de la aplicación. Actualízala para importarlo.",
+        ));
+    }
+    for transaction in &archive.transactions {
+        attachments::validate_path_component(&transaction.id)?;
+        for path in &transaction.receipt_paths {
+            validate_attachment_path(path)?;
+        }
+    }
+    for attachment in &archive.attachments {
+        validate_attachment_path(&attachment.path)?;
+    }
+    Ok(())
+}
+
+/// Aplica el archivo a la base de datos en una única transacción SQL.
+fn import_into_db(
+    db: &SqliteStorage,
+    archive: &WorkspaceArchive,
+    strategy: MergeStrategy,
+) -> Result<ImportWorkspaceSummary, AppError> {
+    let mut summary = ImportWorkspaceSummary::default();
+    let tx = db.connection().unchecked_transaction().map_err(db_error)?;
+    if strategy == MergeStrategy::Replace {
+        tx.execute("DELETE FROM transactions", []).map_err(db_error)?;
+        for table in ARCHIVE_TABLES {
+            tx.execute(&format!("DELETE FROM {}", table), []).map_err(db_error)?;
+        }
+    }
+
+    let existing = db.all_transaction_ids()?;
+    for transaction in &archive.transactions {
+        if !existing.contains(&transaction.id) {
+            db.insert_transaction(transaction)?;
+            summary.transactions_added += 1;
+        } else if strategy == MergeStrategy::Skip {
+            summary.transactions_skipped += 1;
+        } else {
+            db.update_transaction(transaction)?;
+            summary.transactions_updated += 1;
+        }
+    }
+
+    for table in ARCHIVE_TABLES {
+        let rows = archive.tables.get(*table).and_then(|rows| rows.as_array()).map(Vec::as_slice).unwrap_or_default();
+        summary.rows_imported += import_table(&tx, table, rows, strategy)?;
+    }
+
+    if strategy != MergeStrategy::Skip {
+        settings::save_settings(db, archive.settings.clone())?;
+        summary.settings_imported = true;
+    }
+    tx.commit().map_err(db_error)?;
+    Ok(summary)
+}
+
+/// Escribe los justificantes del archivo. Con `Skip` no se sobrescriben los que ya existen.
+fn write_attachments(archive: &WorkspaceArchive, strategy: MergeStrategy) -> Result<usize, AppError> {
+    let mut written = 0;
+    for attachment in &archive.attachments {
+        let target = storage::get_data_dir().join(&attachment.path);
+        if strategy == MergeStrategy::Skip && target.exists() {
+            continue;
+        }
+        let bytes = base64::engine::general_purpose::STANDARD
+            .decode(&attachment.data)
+            .map_err(|e| AppError::validation(format!("Adjunto {} no válido: {}", attachment.path, e)))?;
+        if let Some(parent) = target.parent() {
+            std::fs::create_dir_all(parent)
+                .map_err(|e| AppError::Io(format!("Error al crear {}: {}", parent.display(), e)))?;
+        }
+        storage::write_atomic(&target, &bytes)?;
+        written += 1;
+    }
+    Ok(written)
+}
+
+/// Importa un archivo ya validado en el espacio de trabajo activo: crea antes una
+/// copia de seguridad, escribe los adjuntos, vacía el historial de deshacer y avisa al
+/// frontend de que los datos (y, si cambiaron, los ajustes) se han recargado.
+pub fn apply_archive(
+    state: &AppState,
+    db: &SqliteStorage,
+    app: &AppHandle,
+    archive: &WorkspaceArchive,
+    strategy: MergeStrategy,
+) -> Result<ImportWorkspaceSummary, AppError> {
+    backup::snapshot_before(db, backup::REASON_IMPORT)?;
+    let mut summary = import_into_db(db, archive, strategy)?;
+    summary.attachments_written = write_attachments(archive, strategy)?;
+    if strategy == MergeStrategy::Replace {
+        attachments::cleanup_orphan_attachments(db)?;
+    }
+    state.history.lock().unwrap().clear();
+    events::emit_data_reloaded(app);
+    if summary.settings_imported {
+        i18n::apply_settings(db);
+        if let Err(e) = app.emit(SETTINGS_CHANGED_EVENT, settings::load_settings(db)) {
+            warn!("Failed to emit settings change: {}", e);
+        }
+        api_server::settings_changed();
+    }
+    Ok(summary)
+}
+
+// --- Comandos Tauri ---
+
+/// Comando para exportar todo el espacio de trabajo activo a un archivo JSON en
+/// `path`: transacciones (también las de la papelera), tiendas, categorías,
+/// presupuestos, ajustes y justificantes. Sirve para llevar los datos a otro equipo
+/// con `import_workspace_command`.
+#[tauri::command]
+pub async fn export_workspace_command(state: State<'_, AppState>, path: String) -> Result<ExportWorkspaceSummary, AppError> {
+    info!("Received export_workspace_command: {}", path);
+    let db = state.db().await?;
+    let archive = build_archive(&db)?;
+    drop(db);
+
+    let json = serde_json::to_vec(&archive)
+        .map_err(|e| AppError::Internal(format!("Error al generar la exportación: {}", e)))?;
+    storage::write_atomic(Path::new(&path), &json)?;
+    info!(
+        "Workspace exported to {}: {} transactions, {} attachments.",
+        path,
+        archive.transactions.len(),
+        archive.attachments.len()
+    );
+    Ok(ExportWorkspaceSummary { path, transactions: archive.transactions.len(), attachments: archive.attachments.len() })
+}
+
+/// Comando para importar un archivo de `export_workspace_command` en el espacio de
+/// trabajo activo. `merge_strategy` decide qué pasa con lo que ya existe: `replace`
+/// lo sustituye todo, `merge` da prioridad al archivo y `skip` a los datos actuales.
+/// Antes se crea una copia de seguridad, y el historial de deshacer se vacía.
+#[tauri::command]
+pub async fn import_workspace_command(
+    state: State<'_, AppState>,
+    app: AppHandle,
+    path: String,
+    merge_strategy: MergeStrategy,
+) -> Result<ImportWorkspaceSummary, AppError> {
+    info!("Received import_workspace_command: {} ({:?})", path, merge_strategy);
+    let archive = read_archive(Path::new(&path))?;
+    debug!("Archive with {} transactions and {} attachments.", archive.transactions.len(), archive.attachments.len());
+
+    let db = state.db().await?;
+    let summary = apply_archive(&state, &db, &app, &archive, merge_strategy)?;
+    audit::record(
+        db.connection(),
+        "import_workspace_command",
+        None,
+        None,
+        Some(serde_json::json!({ "path": path, "strategy": format!("{:?}", merge_strategy) })),
+    );
+    info!("Workspace archive {} imported: {:?}", path, summary);
+    Ok(summary)
+}
@@ -0,0 +1,141 @@
+// src-tauri/src/assistant.rs
+
+use serde::Serialize;
+use std::collections::BTreeMap;
+use tauri::State;
+use log::{debug, error, info};
+
+use crate::ai::{self, AiFeature};
+use crate::currencies::RateTable;
+use crate::error::AppError;
+use crate::periods;
+use crate::privacy::Redactor;
+use crate::reports::{self, GroupBy, GroupTotals};
+use crate::settings;
+use crate::storage::{SqliteStorage, TransactionRepository};
+use crate::{AppState, Transaction};
+
+/// Número máximo de meses recientes que se envían como contexto.
+const MAX_CONTEXT_MONTHS: usize = 24;
+
+/// Resumen de la contabilidad que se inyecta en el prompt como contexto estructurado.
+#[derive(Debug, Serialize)]
+pub struct BooksContext {
+    pub base_currency: String,
+    pub first_date: Option<String>,
+    pub last_date: Option<String>,
+    pub totals: GroupTotals,
+    pub by_month: BTreeMap<String, GroupTotals>,
+    pub by_store: BTreeMap<String, GroupTotals>,
+    pub by_category: BTreeMap<String, GroupTotals>,
+}
+
+/// Calcula los agregados de la contabilidad por mes, tienda y categoría.
+pub fn build_books_context(transactions: &[Transaction], rates: &RateTable) -> Result<BooksContext, AppError> {
+    let mut context = BooksContext {
+        base_currency: rates.base_currency.clone(),
+        first_date: None,
+        last_date: None,
+        totals: GroupTotals::default(),
+        by_month: BTreeMap::new(),
+        by_store: BTreeMap::new(),
+        by_category: BTreeMap::new(),
+    };
+
+    let mut first_ts: Option<u64> = None;
+    let mut last_ts: Option<u64> = None;
+    for transaction in transactions {
+        let amount = rates.to_base(transaction.amount, &transaction.currency)?;
+        let month = reports::group_key(transaction, GroupBy::Month);
+        let category = reports::group_key(transaction, GroupBy::Category);
+
+        context.totals.add(&transaction.transaction_type, amount);
+        context.by_month.entry(month).or_default().add(&transaction.transaction_type, amount);
+        context.by_store.entry(transaction.store_name.clone()).or_default().add(&transaction.transaction_type, amount);
+        context.by_category.entry(category).or_default().add(&transaction.transaction_type, amount);
+
+        first_ts = Some(first_ts.map_or(transaction.timestamp, |t| t.min(transaction.timestamp)));
+        last_ts = Some(last_ts.map_or(transaction.timestamp, |t| t.max(transaction.timestamp)));
+    }
+
+    // Limitamos el desglose mensual a los meses más recientes para acotar el prompt.
+    while context.by_month.len() > MAX_CONTEXT_MONTHS {
+        let oldest = context.by_month.keys().next().cloned();
+        if let Some(key) = oldest {
+            context.by_month.remove(&key);
+        }
+    }
+
+    context.first_date = first_ts.map(|t| periods::local_date(t).format("%Y-%m-%d").to_string());
+    context.last_date = last_ts.map(|t| periods::local_date(t).format("%Y-%m-%d").to_string());
+    Ok(context)
+}
+
+/// Carga las transacciones y los tipos de cambio y devuelve el contexto agregado.
+pub fn load_books_context(db: &SqliteStorage) -> Result<BooksContext, AppError> {
+    let rates = RateTable::load(db)?;
+    let transactions = db.list_transactions()?;
+    build_books_context(&transactions, &rates)
+}
+
+/// Prompt de la pregunta con el contexto, con las tiendas ocultadas por `redactor`.
+pub fn build_question_prompt(question: &str, context: &BooksContext, redactor: &mut Redactor) -> Result<String, AppError> {
+    let by_store: BTreeMap<String, GroupTotals> =
+        context.by_store.iter().map(|(store, totals)| (redactor.store(store), totals.clone())).collect();
+    let redacted = BooksContext {
+        base_currency: context.base_currency.clone(),
+        first_date: context.first_date.clone(),
+        last_date: context.last_date.clone(),
+        totals: context.totals.clone(),
+        by_month: context.by_month.clone(),
+        by_store,
+        by_category: context.by_category.clone(),
+    };
+    let question = redactor.text(question);
+    let context_json = serde_json::to_string_pretty(&redacted)
+        .map_err(|e| AppError::Internal(format!("Error al preparar el contexto para la IA: {}", e)))?;
+    let today = periods::local_date(periods::now_timestamp()).format("%Y-%m-%d");
+    Ok(format!(
+        "Eres un asistente contable para un pequeño negocio. Hoy es {today}.\n\
+         A continuación tienes los datos agregados reales de la contabilidad del usuario \
+         en formato JSON (importes en {currency}; \"net\" = ingresos - gastos):\n\
+         ```json\n{context_json}\n```\n\
+         Responde en español a la pregunta del usuario basándote ÚNICAMENTE en estos datos. \
+         Cita las cifras concretas que uses. Si los datos no permiten responder, dilo \
+         claramente en lugar de inventar.\n\n\
+         Pregunta: {question}",
+        today = today,
+        currency = context.base_currency,
+        context_json = context_json,
+        question = question,
+    ))
+}
+
+/// Comando para hacer una pregunta a la IA sobre la propia contabilidad.
+/// Los agregados se calculan en Rust y se envían como contexto junto a la pregunta.
+#[tauri::command]
+pub async fn ask_accounting_question_command(
+    state: State<'_, AppState>,
+    question: String,
+) -> Result<String, AppError> {
+    info!("Received ask_accounting_question_command.");
+    let question = question.trim();
+    if question.is_empty() {
+        error!("Empty accounting question.");
+        return Err(AppError::invalid_field("question", "La pregunta no puede estar vacía."));
+    }
+
+    let (prompt, redactor, client) = {
+        let db = state.db().await?;
+        let settings = settings::load_settings(&db);
+        ai::ensure_enabled(&settings, Some(AiFeature::Chat))?;
+        let context = load_books_context(&db)?;
+        debug!("Books context for question: {} months, {} stores, {} categories",
+               context.by_month.len(), context.by_store.len(), context.by_category.len());
+        let mut redactor = Redactor::new(db.connection(), &settings)?;
+        let prompt = build_question_prompt(question, &context, &mut redactor)?;
+        (prompt, redactor, settings.ai_client())
+    };
+    let answer = ai::generate_text(&state, &client, &prompt).await?;
+    Ok(redactor.restore(&answer))
+}
@@ -0,0 +1,241 @@
+// src-tauri/src/attachments.rs
+
+use serde::Serialize;
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+use tauri::{AppHandle, State};
+use tokio::fs;
+use log::{debug, error, info, warn};
+
+use crate::audit;
+use crate::error::AppError;
+use crate::events;
+use crate::history::Change;
+use crate::storage::{self, SqliteStorage, TransactionRepository};
+use crate::AppState;
+
+pub const ATTACHMENTS_DIR_NAME: &str = "attachments";
+
+/// Extensiones admitidas para justificantes escaneados.
+const ALLOWED_EXTENSIONS: &[&str] = &["jpg", "jpeg", "png", "webp", "heic", "pdf"];
+
+/// Información de un justificante adjunto a una transacción.
+#[derive(Debug, Clone, Serialize)]
+pub struct ReceiptInfo {
+    pub file_name: String,
+    /// Ruta relativa al directorio de datos, tal y como se guarda en la transacción.
+    pub relative_path: String,
+    /// Ruta absoluta en disco, para que el frontend pueda mostrarlo.
+    pub absolute_path: String,
+    pub size_bytes: u64,
+}
+
+/// Directorio raíz donde se guardan todos los justificantes.
+pub fn get_attachments_root() -> PathBuf {
+    let mut path = storage::get_data_dir();
+    path.push(ATTACHMENTS_DIR_NAME);
+    path
+}
+
+fn transaction_attachments_dir(transaction_id: &str) -> PathBuf {
+    get_attachments_root().join(transaction_id)
+}
+
+/// Convierte una ruta relativa guardada en la transacción en ruta absoluta.
+pub fn resolve_receipt_path(relative_path: &str) -> PathBuf {
+    storage::get_data_dir().join(relative_path)
+}
+
+/// Los IDs de transacción se usan como nombre de carpeta: rechazamos cualquier cosa
+/// que pueda salir del directorio de adjuntos.
+pub fn validate_path_component(value: &str) -> Result<(), AppError> {
+    if value.is_empty() || value.contains(['/', '\\']) || value == "." || value == ".." {
+        error!("Invalid path component: '{}'", value);
+        return Err(AppError::validation(format!("Nombre no válido: '{}'.", value)));
+    }
+    Ok(())
+}
+
+fn receipt_info(relative_path: &str) -> ReceiptInfo {
+    let absolute = resolve_receipt_path(relative_path);
+    let size_bytes = std::fs::metadata(&absolute).map(|m| m.len()).unwrap_or(0);
+    ReceiptInfo {
+        file_name: absolute
+            .file_name()
+            .map(|n| n.to_string_lossy().into_owned())
+            .unwrap_or_default(),
+        relative_path: relative_path.to_owned(),
+        absolute_path: absolute.display().to_string(),
+        size_bytes,
+    }
+}
+
+/// Elimina la carpeta de adjuntos de una transacción borrada.
+pub fn remove_transaction_attachments(transaction_id: &str) {
+    if validate_path_component(transaction_id).is_err() {
+        return;
+    }
+    let dir = transaction_attachments_dir(transaction_id);
+    if dir.exists() {
+        match std::fs::remove_dir_all(&dir) {
+            Ok(_) => debug!("Removed attachments for transaction {}", transaction_id),
+            Err(e) => warn!("No se pudieron eliminar los adjuntos de {}: {}", dir.display(), e),
+        }
+    }
+}
+
+/// Carpetas de adjuntos (por ID de transacción) cuyas transacciones ya no existen.
+pub fn find_orphan_attachments(db: &SqliteStorage) -> Result<Vec<String>, AppError> {
+    let root = get_attachments_root();
+    if !root.exists() {
+        return Ok(Vec::new());
+    }
+    // Las transacciones de la papelera conservan sus adjuntos hasta que se purgan.
+    let known_ids: HashSet<String> = db.all_transaction_ids()?;
+    let entries = std::fs::read_dir(&root)
+        .map_err(|e| AppError::Io(format!("Error al leer el directorio de adjuntos: {}", e)))?;
+    let mut orphans: Vec<String> = entries
+        .flatten()
+        .filter(|entry| entry.path().is_dir())
+        .map(|entry| entry.file_name().to_string_lossy().into_owned())
+        .filter(|name| !known_ids.contains(name))
+        .collect();
+    orphans.sort();
+    Ok(orphans)
+}
+
+/// Borra las carpetas de adjuntos cuyas transacciones ya no existen.
+/// Devuelve el número de carpetas eliminadas.
+pub fn cleanup_orphan_attachments(db: &SqliteStorage) -> Result<usize, AppError> {
+    let orphans = find_orphan_attachments(db)?;
+    for name in orphans.iter() {
+        remove_transaction_attachments(name);
+    }
+    let removed = orphans.len();
+    if removed > 0 {
+        info!("Eliminadas {} carpetas de adjuntos huérfanas.", removed);
+    }
+    Ok(removed)
+}
+
+// --- Comandos Tauri ---
+
+/// Comando para adjuntar un justificante (imagen o PDF) a una transacción.
+/// El archivo se copia a `attachments/{transaction_id}/` dentro del directorio de datos.
+#[tauri::command]
+pub async fn attach_receipt_command(
+    state: State<'_, AppState>,
+    app: AppHandle,
+    transaction_id: String,
+    source_path: String,
+) -> Result<ReceiptInfo, AppError> {
+    debug!("Received attach_receipt_command: transaction={}, source='{}'", transaction_id, source_path);
+    validate_path_component(&transaction_id)?;
+
+    let source = Path::new(&source_path);
+    let extension = source
+        .extension()
+        .map(|e| e.to_string_lossy().to_lowercase())
+        .unwrap_or_default();
+    if !ALLOWED_EXTENSIONS.contains(&extension.as_str()) {
+        error!("Unsupported receipt extension: '{}'", extension);
+        return Err(AppError::invalid_field(
+            "source_path",
+            format!("Tipo de archivo no admitido. Usa uno de: {}.", ALLOWED_EXTENSIONS.join(", ")),
+        ));
+    }
+    if !source.is_file() {
+        error!("Receipt source not found: {}", source.display());
+        return Err(AppError::NotFound(format!("No se encontró el archivo {}.", source.display())));
+    }
+
+    if state.db().await?.get_transaction(&transaction_id)?.is_none() {
+        error!("Transaction with ID {} not found for attachment.", transaction_id);
+        return Err(AppError::NotFound(format!("Transacción con ID {} no encontrada.", transaction_id)));
+    }
+
+    let original_name = source
+        .file_name()
+        .map(|n| n.to_string_lossy().into_owned())
+        .unwrap_or_else(|| format!("justificante.{}", extension));
+    let unique_prefix = uuid::Uuid::new_v4().simple().to_string();
+    let file_name = format!("{}-{}", &unique_prefix[..8], original_name);
+
+    let target_dir = transaction_attachments_dir(&transaction_id);
+    fs::create_dir_all(&target_dir).await
+        .map_err(|e| {
+            AppError::Io(format!("Falló la creación del directorio de adjuntos ({}): {}", target_dir.display(), e))
+        })?;
+    let target = target_dir.join(&file_name);
+    fs::copy(source, &target).await.map_err(|e| {
+        error!("Failed to copy receipt to {}: {}", target.display(), e);
+        AppError::Io(format!("Error al copiar el justificante: {}", e))
+    })?;
+
+    let relative_path = format!("{}/{}/{}", ATTACHMENTS_DIR_NAME, transaction_id, file_name);
+
+    let db = state.db().await?;
+    let mut transaction = match db.get_transaction(&transaction_id)? {
+        Some(t) => t,
+        None => {
+            // La transacción se borró mientras copiábamos el archivo.
+            let _ = std::fs::remove_file(&target);
+            return Err(AppError::NotFound(format!("Transacción con ID {} no encontrada.", transaction_id)));
+        }
+    };
+    let before = transaction.clone();
+    transaction.receipt_paths.push(relative_path.clone());
+    db.update_transaction(&transaction)?;
+    let changes = [Change::Update { before, after: transaction }];
+    audit::record_changes(db.connection(), "attach_receipt_command", &changes);
+    events::emit_transaction_changes(&app, &changes);
+
+    info!("Receipt attached to transaction {}: {}", transaction_id, relative_path);
+    Ok(receipt_info(&relative_path))
+}
+
+/// Comando para listar los justificantes de una transacción.
+#[tauri::command]
+pub async fn list_receipts_command(
+    state: State<'_, AppState>,
+    transaction_id: String,
+) -> Result<Vec<ReceiptInfo>, AppError> {
+    debug!("Received list_receipts_command for transaction {}", transaction_id);
+    let transaction = state.db().await?
+        .get_transaction(&transaction_id)?
+        .ok_or_else(|| AppError::NotFound(format!("Transacción con ID {} no encontrada.", transaction_id)))?;
+    Ok(transaction.receipt_paths.iter().map(|p| receipt_info(p)).collect())
+}
+
+/// Comando para eliminar un justificante de una transacción y borrar el archivo.
+#[tauri::command]
+pub async fn delete_receipt_command(
+    state: State<'_, AppState>,
+    app: AppHandle,
+    transaction_id: String,
+    relative_path: String,
+) -> Result<(), AppError> {
+    debug!("Received delete_receipt_command: transaction={}, path='{}'", transaction_id, relative_path);
+    let db = state.db().await?;
+    let mut transaction = db
+        .get_transaction(&transaction_id)?
+        .ok_or_else(|| AppError::NotFound(format!("Transacción con ID {} no encontrada.", transaction_id)))?;
+
+    let before = transaction.clone();
+    transaction.receipt_paths.retain(|p| p != &relative_path);
+    if transaction.receipt_paths.len() == before.receipt_paths.len() {
+        error!("Receipt '{}' not found on transaction {}", relative_path, transaction_id);
+        return Err(AppError::NotFound("El justificante no pertenece a esta transacción.".to_string()));
+    }
+    db.update_transaction(&transaction)?;
+    let changes = [Change::Update { before, after: transaction }];
+    audit::record_changes(db.connection(), "delete_receipt_command", &changes);
+    events::emit_transaction_changes(&app, &changes);
+
+    let absolute = resolve_receipt_path(&relative_path);
+    if let Err(e) = std::fs::remove_file(&absolute) {
+        warn!("No se pudo borrar el archivo {}: {}", absolute.display(), e);
+    }
+    debug!("Receipt removed from transaction {}", transaction_id);
+    Ok(())
+}
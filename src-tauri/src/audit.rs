@@ -0,0 +1,184 @@
+// src-tauri/src/audit.rs
+
+use rusqlite::types::Value as SqlValue;
+use rusqlite::{params, params_from_iter, Connection};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use tauri::State;
+use log::{debug, error};
+
+use crate::error::AppError;
+use crate::history::Change;
+use crate::periods;
+use crate::storage::db_error;
+use crate::users;
+use crate::AppState;
+
+const DEFAULT_AUDIT_LIMIT: usize = 200;
+const MAX_AUDIT_LIMIT: usize = 5000;
+
+/// Registro inmutable de un cambio sobre los datos contables. `before` y `after`
+/// contienen la entidad serializada tal como estaba antes y después del cambio.
+#[derive(Debug, Clone, Serialize)]
+pub struct AuditRecord {
+    pub id: i64,
+    pub timestamp: u64,
+    pub command: String,
+    pub entity_id: Option<String>,
+    pub before: Option<Value>,
+    pub after: Option<Value>,
+    pub actor: String,
+    /// Usuario de la aplicación (`users::User`) con la sesión abierta, si la había.
+    pub user_id: Option<String>,
+}
+
+/// Filtros de `get_audit_log_command`. Todos son opcionales.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct AuditFilter {
+    /// Inicio del rango (timestamp Unix, inclusivo).
+    pub from: Option<u64>,
+    /// Fin del rango (timestamp Unix, inclusivo).
+    pub to: Option<u64>,
+    pub command: Option<String>,
+    pub entity_id: Option<String>,
+    pub user_id: Option<String>,
+    pub limit: Option<usize>,
+}
+
+/// Usuario con la sesión abierta en la aplicación o, si no hay ninguno, el del
+/// sistema operativo que la ejecuta.
+pub fn current_actor() -> String {
+    if let Some(name) = users::current_user_name() {
+        return name;
+    }
+    std::env::var("USER")
+        .or_else(|_| std::env::var("USERNAME"))
+        .ok()
+        .filter(|u| !u.trim().is_empty())
+        .unwrap_or_else(|| "desconocido".to_string())
+}
+
+/// Serializa una entidad para guardarla como instantánea.
+pub fn snapshot<T: Serialize>(value: &T) -> Option<Value> {
+    serde_json::to_value(value).ok()
+}
+
+fn insert_record(
+    conn: &Connection,
+    command: &str,
+    entity_id: Option<&str>,
+    before: Option<&Value>,
+    after: Option<&Value>,
+) -> Result<(), AppError> {
+    conn.execute(
+        "INSERT INTO audit_log (timestamp, command, entity_id, before_json, after_json, actor, user_id)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+        params![
+            periods::now_timestamp() as i64,
+            command,
+            entity_id,
+            before.map(Value::to_string),
+            after.map(Value::to_string),
+            current_actor(),
+            users::current_user_id(),
+        ],
+    )
+    .map_err(db_error)?;
+    Ok(())
+}
+
+/// Añade un registro al log de auditoría. Se llama después de que el cambio se haya
+/// guardado, así que un fallo aquí solo se registra en el log de la aplicación.
+pub fn record(conn: &Connection, command: &str, entity_id: Option<&str>, before: Option<Value>, after: Option<Value>) {
+    if let Err(e) = insert_record(conn, command, entity_id, before.as_ref(), after.as_ref()) {
+        error!("No se pudo registrar '{}' en el log de auditoría: {}", command, e);
+    }
+}
+
+/// Añade un registro por cada cambio sobre transacciones.
+pub fn record_changes(conn: &Connection, command: &str, changes: &[Change]) {
+    for change in changes {
+        match change {
+            Change::Insert(t) => record(conn, command, Some(&t.id), None, snapshot(t)),
+            Change::Update { before, after } => record(conn, command, Some(&after.id), snapshot(before), snapshot(after)),
+            Change::Delete(t) => record(conn, command, Some(&t.id), snapshot(t), None),
+            Change::Splits { transaction, before, after } => {
+                record(conn, command, Some(&transaction.id), snapshot(before), snapshot(after))
+            }
+        }
+    }
+}
+
+fn parse_snapshot(text: Option<String>) -> Option<Value> {
+    text.and_then(|t| serde_json::from_str(&t).ok())
+}
+
+pub fn query_audit_log(conn: &Connection, filter: &AuditFilter) -> Result<Vec<AuditRecord>, AppError> {
+    let mut clauses: Vec<&str> = Vec::new();
+    let mut values: Vec<SqlValue> = Vec::new();
+    if let Some(from) = filter.from {
+        clauses.push("timestamp >= ?");
+        values.push(SqlValue::Integer(from as i64));
+    }
+    if let Some(to) = filter.to {
+        clauses.push("timestamp <= ?");
+        values.push(SqlValue::Integer(to as i64));
+    }
+    if let Some(command) = filter.command.as_deref().map(str::trim).filter(|c| !c.is_empty()) {
+        clauses.push("command = ?");
+        values.push(SqlValue::Text(command.to_owned()));
+    }
+    if let Some(entity_id) = filter.entity_id.as_deref().map(str::trim).filter(|e| !e.is_empty()) {
+        clauses.push("entity_id = ?");
+        values.push(SqlValue::Text(entity_id.to_owned()));
+    }
+    if let Some(user_id) = filter.user_id.as_deref().map(str::trim).filter(|u| !u.is_empty()) {
+        clauses.push("user_id = ?");
+        values.push(SqlValue::Text(user_id.to_owned()));
+    }
+    let where_clause = if clauses.is_empty() {
+        String::new()
+    } else {
+        format!("WHERE {}", clauses.join(" AND "))
+    };
+    let limit = filter.limit.unwrap_or(DEFAULT_AUDIT_LIMIT).clamp(1, MAX_AUDIT_LIMIT);
+    values.push(SqlValue::Integer(limit as i64));
+
+    let mut stmt = conn
+        .prepare(&format!(
+            "SELECT id, timestamp, command, entity_id, before_json, after_json, actor, user_id
+             FROM audit_log {} ORDER BY id DESC LIMIT ?",
+            where_clause
+        ))
+        .map_err(db_error)?;
+    let rows = stmt
+        .query_map(params_from_iter(values), |row| {
+            Ok(AuditRecord {
+                id: row.get(0)?,
+                timestamp: row.get::<_, i64>(1)? as u64,
+                command: row.get(2)?,
+                entity_id: row.get(3)?,
+                before: parse_snapshot(row.get(4)?),
+                after: parse_snapshot(row.get(5)?),
+                actor: row.get(6)?,
+                user_id: row.get(7)?,
+            })
+        })
+        .map_err(db_error)?;
+    rows.collect::<rusqlite::Result<Vec<_>>>().map_err(db_error)
+}
+
+// --- Comandos Tauri ---
+
+/// Comando para consultar el log de auditoría, del registro más reciente al más antiguo.
+#[tauri::command]
+pub async fn get_audit_log_command(
+    state: State<'_, AppState>,
+    filter: Option<AuditFilter>,
+) -> Result<Vec<AuditRecord>, AppError> {
+    let filter = filter.unwrap_or_default();
+    debug!("Received get_audit_log_command: {:?}", filter);
+    let db = state.db().await?;
+    query_audit_log(db.connection(), &filter)
+}
@@ -0,0 +1,72 @@
+// src-tauri/src/autosave.rs
+
+use std::time::Duration;
+use tauri::{AppHandle, Manager, State};
+use log::{debug, warn};
+
+use crate::error::AppError;
+use crate::AppState;
+
+/// Cada cuánto se comprueba si hay cambios cifrados pendientes de escribir.
+const AUTOSAVE_TICK: Duration = Duration::from_millis(500);
+/// Tiempo sin commits tras el cual se escriben los cambios pendientes.
+const AUTOSAVE_DEBOUNCE: Duration = Duration::from_secs(2);
+/// Tiempo máximo que un cambio puede quedar sin escribir aunque sigan llegando otros.
+const AUTOSAVE_MAX_DELAY: Duration = Duration::from_secs(30);
+
+/// Escribe la imagen cifrada si toca según `AUTOSAVE_DEBOUNCE` y `AUTOSAVE_MAX_DELAY`.
+/// Las bases de datos sin cifrar no tienen cambios pendientes: SQLite guarda cada commit.
+async fn save_if_due(state: &AppState) {
+    if state.is_locked() {
+        return;
+    }
+    let db = state.db.lock().await;
+    let Some((dirty_for, idle_for)) = db.pending_changes_age() else { return };
+    if idle_for < AUTOSAVE_DEBOUNCE && dirty_for < AUTOSAVE_MAX_DELAY {
+        return;
+    }
+    match db.flush() {
+        Ok(()) => debug!("Encrypted database saved after {:?} of pending changes.", dirty_for),
+        Err(e) => warn!("No se pudieron guardar los datos cifrados: {}", e),
+    }
+}
+
+/// Lanza la tarea que agrupa las escrituras de la base de datos cifrada: en lugar de
+/// reescribir el archivo completo tras cada cambio, se escribe cuando deja de haber
+/// cambios durante un momento.
+pub fn spawn_autosave(app: AppHandle) {
+    tauri::async_runtime::spawn(async move {
+        loop {
+            tokio::time::sleep(AUTOSAVE_TICK).await;
+            save_if_due(&app.state::<AppState>()).await;
+        }
+    });
+}
+
+/// Escribe los cambios pendientes si la aplicación sale sin el cierre ordenado de
+/// `shutdown`. Se llama desde el bucle de eventos, donde no se puede esperar al mutex:
+/// si un comando aún tiene la base de datos, se omite y solo se pierden los cambios de
+/// los últimos segundos. Devuelve `true` si no quedó nada sin escribir.
+pub fn flush_on_exit(state: &AppState) -> bool {
+    if state.is_locked() {
+        return true;
+    }
+    let Ok(db) = state.db.try_lock() else {
+        warn!("Database busy at exit; pending changes are written when it is closed.");
+        return false;
+    };
+    if let Err(e) = db.flush() {
+        warn!("No se pudieron guardar los datos cifrados al salir: {}", e);
+        return false;
+    }
+    true
+}
+
+// --- Comandos Tauri ---
+
+/// Comando para escribir en disco inmediatamente los cambios pendientes.
+#[tauri::command]
+pub async fn flush_command(state: State<'_, AppState>) -> Result<(), AppError> {
+    debug!("Received flush_command.");
+    state.db().await?.flush()
+}
@@ -0,0 +1,280 @@
+// src-tauri/src/backup.rs
+
+use chrono::{Datelike, NaiveDateTime, Utc};
+use serde::Serialize;
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+use tauri::{AppHandle, State};
+use log::{debug, error, info, warn};
+
+use crate::audit;
+use crate::encryption;
+use crate::error::AppError;
+use crate::events;
+use crate::i18n;
+use crate::periods;
+use crate::settings;
+use crate::storage::{self, SqliteStorage, TransactionRepository};
+use crate::AppState;
+
+const BACKUPS_DIR_NAME: &str = "backups";
+const BACKUP_EXTENSION: &str = "db";
+/// Formato de la fecha (UTC) con la que empieza el nombre de cada copia.
+const BACKUP_TIME_FORMAT: &str = "%Y%m%d-%H%M%S";
+
+/// Días distintos de los que se conserva la copia más reciente.
+const DAILY_BACKUPS_KEPT: usize = 7;
+/// Semanas distintas de las que se conserva la copia más reciente.
+const WEEKLY_BACKUPS_KEPT: usize = 4;
+
+/// Motivos con los que se etiquetan las copias.
+pub const REASON_AUTO: &str = "auto";
+pub const REASON_MANUAL: &str = "manual";
+pub const REASON_DELETE_STORE: &str = "delete_store";
+pub const REASON_IMPORT: &str = "import";
+pub const REASON_REPAIR: &str = "repair";
+const REASON_RESTORE: &str = "restore";
+
+/// Copia de seguridad guardada en `backups/`. `id` es el nombre del archivo sin extensión.
+#[derive(Debug, Clone, Serialize)]
+pub struct BackupInfo {
+    pub id: String,
+    pub created_at: u64,
+    pub reason: String,
+    pub size_bytes: u64,
+    pub encrypted: bool,
+}
+
+/// Directorio de las copias de seguridad dentro del directorio de datos.
+pub fn get_backups_dir() -> PathBuf {
+    storage::get_data_dir().join(BACKUPS_DIR_NAME)
+}
+
+fn backup_path(id: &str) -> PathBuf {
+    get_backups_dir().join(format!("{}.{}", id, BACKUP_EXTENSION))
+}
+
+/// Interpreta el nombre de un archivo de copia (`<fecha>-<motivo>`).
+fn parse_backup_id(id: &str) -> Option<(u64, String)> {
+    let (date, reason) = (id.get(..15)?, id.get(16..)?);
+    let created = NaiveDateTime::parse_from_str(date, BACKUP_TIME_FORMAT).ok()?;
+    Some((created.and_utc().timestamp() as u64, reason.to_owned()))
+}
+
+/// Copias existentes, de la más reciente a la más antigua.
+pub fn list_backups() -> Result<Vec<BackupInfo>, AppError> {
+    let dir = get_backups_dir();
+    if !dir.exists() {
+        return Ok(Vec::new());
+    }
+    let entries = std::fs::read_dir(&dir)
+        .map_err(|e| AppError::Io(format!("Error al leer el directorio de copias de seguridad: {}", e)))?;
+
+    let mut backups = Vec::new();
+    for entry in entries.flatten() {
+        let path = entry.path();
+        if path.extension().and_then(|e| e.to_str()) != Some(BACKUP_EXTENSION) {
+            continue;
+        }
+        let Some(id) = path.file_stem().and_then(|s| s.to_str()) else { continue };
+        let Some((created_at, reason)) = parse_backup_id(id) else { continue };
+        backups.push(BackupInfo {
+            id: id.to_owned(),
+            created_at,
+            reason,
+            size_bytes: entry.metadata().map(|m| m.len()).unwrap_or(0),
+            encrypted: encryption::is_encrypted_file(&path),
+        });
+    }
+    backups.sort_by(|a, b| b.id.cmp(&a.id));
+    Ok(backups)
+}
+
+/// Guarda una copia de la base de datos en `backups/`. Si la base de datos está
+/// cifrada, la copia también lo está. Las copias sobrantes las borra el planificador.
+pub fn create_backup(db: &SqliteStorage, reason: &str) -> Result<BackupInfo, AppError> {
+    let dir = get_backups_dir();
+    std::fs::create_dir_all(&dir).map_err(|e| {
+        AppError::Io(format!("Falló la creación del directorio de copias de seguridad ({}): {}", dir.display(), e))
+    })?;
+
+    let now = Utc::now();
+    let mut id = format!("{}-{}", now.format(BACKUP_TIME_FORMAT), reason);
+    // Dos copias en el mismo segundo (p. ej. una automática y otra previa a una operación).
+    let mut suffix = 1;
+    while backup_path(&id).exists() {
+        suffix += 1;
+        id = format!("{}-{}-{}", now.format(BACKUP_TIME_FORMAT), reason, suffix);
+    }
+
+    let path = backup_path(&id);
+    db.write_backup(&path)?;
+    info!("Copia de seguridad creada: {}", path.display());
+    let size_bytes = std::fs::metadata(&path).map(|m| m.len()).unwrap_or(0);
+    Ok(BackupInfo {
+        id,
+        created_at: now.timestamp() as u64,
+        reason: reason.to_owned(),
+        size_bytes,
+        encrypted: db.encryption_key().is_some(),
+    })
+}
+
+/// Crea una copia antes de una operación arriesgada. Si no se puede crear, la
+/// operación no debe continuar.
+pub fn snapshot_before(db: &SqliteStorage, reason: &str) -> Result<(), AppError> {
+    create_backup(db, reason).map(|_| ()).map_err(|e| {
+        error!("Pre-operation backup ({}) failed: {}", reason, e);
+        AppError::Io(format!("No se pudo crear la copia de seguridad previa a la operación: {}", e))
+    })
+}
+
+/// Conserva la copia más reciente de cada uno de los últimos `DAILY_BACKUPS_KEPT`
+/// días y de cada una de las últimas `WEEKLY_BACKUPS_KEPT` semanas; borra el resto.
+fn apply_retention() -> Result<usize, AppError> {
+    let backups = list_backups()?;
+    let mut days = HashSet::new();
+    let mut weeks = HashSet::new();
+    let mut removed = 0;
+    for backup in backups.iter() {
+        let date = periods::local_date(backup.created_at);
+        let week = (date.iso_week().year(), date.iso_week().week());
+        let mut keep = false;
+        if !days.contains(&date) && days.len() < DAILY_BACKUPS_KEPT {
+            days.insert(date);
+            keep = true;
+        }
+        if !weeks.contains(&week) && weeks.len() < WEEKLY_BACKUPS_KEPT {
+            weeks.insert(week);
+            keep = true;
+        }
+        if keep {
+            continue;
+        }
+        match std::fs::remove_file(backup_path(&backup.id)) {
+            Ok(()) => {
+                debug!("Backup {} removed by retention policy.", backup.id);
+                removed += 1;
+            }
+            Err(e) => warn!("No se pudo borrar la copia {}: {}", backup.id, e),
+        }
+    }
+    Ok(removed)
+}
+
+/// Crea una copia automática si la más reciente es más antigua que el intervalo de
+/// los ajustes (`backup_interval_hours`), o siempre con `force`, y después aplica la
+/// política de retención. Es la tarea `backup` del planificador (ver `scheduler`).
+pub async fn run_scheduled_backup(state: &AppState, force: bool) -> Result<String, AppError> {
+    if state.is_locked() {
+        return Ok("Datos bloqueados: no se ha hecho la copia.".to_string());
+    }
+    let db = state.db().await?;
+    let interval_secs = settings::load_settings(&db).backup_interval_hours * 60 * 60;
+    let latest = list_backups()?.first().map(|b| b.created_at).unwrap_or(0);
+    let created = if force || (interval_secs > 0 && periods::now_timestamp().saturating_sub(latest) >= interval_secs) {
+        Some(create_backup(&db, REASON_AUTO)?)
+    } else {
+        None
+    };
+    drop(db);
+    let removed = apply_retention()?;
+    if removed > 0 {
+        info!("Retention policy removed {} backups.", removed);
+    }
+    Ok(match created {
+        Some(backup) => format!("Copia {} creada; {} copias antiguas borradas.", backup.id, removed),
+        None => format!("Aún no tocaba hacer copia; {} copias antiguas borradas.", removed),
+    })
+}
+
+/// Rechaza identificadores que no correspondan a una copia de `backups/`.
+fn validate_backup_id(id: &str) -> Result<PathBuf, AppError> {
+    if id.contains(['/', '\\']) || parse_backup_id(id).is_none() {
+        error!("Invalid backup id: '{}'", id);
+        return Err(AppError::invalid_field("backup_id", format!("Identificador de copia no válido: '{}'.", id)));
+    }
+    let path = backup_path(id);
+    if !path.is_file() {
+        return Err(AppError::NotFound(format!("Copia de seguridad '{}' no encontrada.", id)));
+    }
+    Ok(path)
+}
+
+/// Sustituye la base de datos por la copia `path`. La conexión de `db` debe poder cerrarse.
+fn restore_into(db: &mut SqliteStorage, path: &Path, key: Option<encryption::DataKey>) -> Result<(), AppError> {
+    let backup_key = if encryption::is_encrypted_file(path) {
+        Some(key.clone().ok_or_else(|| {
+            AppError::validation("La copia está cifrada. Activa el cifrado con la misma contraseña antes de restaurarla.")
+        })?)
+    } else {
+        None
+    };
+    if !storage::backup_is_healthy(path, backup_key.as_ref())? {
+        error!("Backup {} is damaged.", path.display());
+        return Err(AppError::Database("La copia de seguridad está dañada; no se puede restaurar.".to_string()));
+    }
+    let contents = std::fs::read(path)
+        .map_err(|e| AppError::Io(format!("Error al leer la copia de seguridad: {}", e)))?;
+
+    // Cerramos la conexión actual antes de sobrescribir el archivo.
+    let db_path = storage::get_database_path();
+    drop(std::mem::replace(db, SqliteStorage::open_in_memory()?));
+    if let Err(e) = storage::write_atomic(&db_path, &contents) {
+        *db = SqliteStorage::open_path(&db_path, key)?;
+        return Err(e);
+    }
+    *db = SqliteStorage::open_path(&db_path, backup_key.clone())?;
+    // Una copia sin cifrar restaurada sobre datos cifrados se vuelve a cifrar.
+    if key.is_some() && backup_key.is_none() {
+        db.set_encryption_key(key)?;
+    }
+    Ok(())
+}
+
+// --- Comandos Tauri ---
+
+/// Comando para listar las copias de seguridad, de la más reciente a la más antigua.
+#[tauri::command]
+pub async fn list_backups_command() -> Result<Vec<BackupInfo>, AppError> {
+    debug!("Received list_backups_command.");
+    list_backups()
+}
+
+/// Comando para crear una copia de seguridad manual.
+#[tauri::command]
+pub async fn create_backup_command(state: State<'_, AppState>) -> Result<BackupInfo, AppError> {
+    debug!("Received create_backup_command.");
+    let db = state.db().await?;
+    create_backup(&db, REASON_MANUAL)
+}
+
+/// Comando para restaurar una copia de `backups/`. Antes se guarda una copia del
+/// estado actual, así que la restauración también se puede deshacer restaurando esa.
+#[tauri::command]
+pub async fn restore_backup_command(
+    state: State<'_, AppState>,
+    app: AppHandle,
+    backup_id: String,
+) -> Result<usize, AppError> {
+    info!("Received restore_backup_command: {}", backup_id);
+    let path = validate_backup_id(&backup_id)?;
+    let mut db = state.db().await?;
+    snapshot_before(&db, REASON_RESTORE)?;
+
+    let key = db.encryption_key().cloned();
+    restore_into(&mut db, &path, key)?;
+    i18n::apply_settings(&db);
+    state.history.lock().unwrap().clear();
+    audit::record(
+        db.connection(),
+        "restore_backup_command",
+        Some(&backup_id),
+        None,
+        Some(serde_json::json!({ "backup_id": backup_id })),
+    );
+    events::emit_data_reloaded(&app);
+    let count = db.count_transactions()?;
+    info!("Backup {} restored ({} transactions).", backup_id, count);
+    Ok(count)
+}
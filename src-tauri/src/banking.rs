@@ -0,0 +1,887 @@
+// src-tauri/src/banking.rs
+
+use chrono::{Duration as ChronoDuration, NaiveDate};
+use reqwest::{Client, Method, StatusCode};
+use rusqlite::{params, Connection, OptionalExtension, Row};
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::str::FromStr;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use tauri::{AppHandle, Manager, State, Url, WebviewUrl, WebviewWindowBuilder};
+use log::{debug, error, info, warn};
+
+use crate::audit;
+use crate::backup;
+use crate::budgets;
+use crate::categories;
+use crate::currencies;
+use crate::error::AppError;
+use crate::events;
+use crate::history::{Change, HistoryEntry};
+use crate::import::{self, StatementEntry};
+use crate::keychain;
+use crate::periods;
+use crate::rules::{self, ApplyMode};
+use crate::storage::{db_error, SqliteStorage, TransactionRepository};
+use crate::windows;
+use crate::AppState;
+
+const API_BASE: &str = "https://bankaccountdata.gocardless.com/api/v2";
+const SECRET_ID_NAME: &str = "gocardless_secret_id";
+const SECRET_KEY_NAME: &str = "gocardless_secret_key";
+/// Dirección a la que el banco devuelve al usuario al terminar la autorización. No
+/// se llega a cargar: la ventana la intercepta y se cierra.
+const REDIRECT_URL: &str = "https://localhost/contabilidad-ia/bank-linked";
+/// Etiqueta de la ventana en la que el usuario autoriza el acceso en su banco.
+pub const BANK_LINK_WINDOW_LABEL: &str = "bank-link";
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(30);
+/// Margen con el que se renueva el token antes de que caduque.
+const TOKEN_MARGIN_SECS: u64 = 60;
+/// Días hacia atrás que se piden en la primera descarga de una cuenta.
+const INITIAL_HISTORY_DAYS: i64 = 90;
+/// Clave de `app_settings` con las reglas de conversión.
+const MAPPING_RULES_KEY: &str = "bank_mapping_rules";
+
+/// Evento emitido al terminar (bien o mal) la vinculación con un banco. Carga:
+/// `BankLinkResult`.
+pub const BANK_LINKED_EVENT: &str = "banking://linked";
+
+/// Token de acceso a la API y cuándo caduca.
+static ACCESS_TOKEN: Mutex<Option<(String, Instant)>> = Mutex::new(None);
+
+/// Banco disponible para vincular.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BankInstitution {
+    pub id: String,
+    pub name: String,
+    #[serde(default)]
+    pub bic: Option<String>,
+    #[serde(default)]
+    pub logo: Option<String>,
+}
+
+/// Autorización dada a un banco (una «requisition» de GoCardless). `status` es
+/// `pending` hasta que el usuario termina en la web del banco, y luego `linked`.
+#[derive(Debug, Clone, Serialize)]
+pub struct BankConnection {
+    pub id: String,
+    pub institution_id: String,
+    pub institution_name: String,
+    pub status: String,
+    /// Dirección de la web del banco en la que autorizar el acceso.
+    pub link: String,
+    pub created_at: u64,
+}
+
+/// Cuenta bancaria vinculada.
+#[derive(Debug, Clone, Serialize)]
+pub struct BankAccount {
+    pub id: String,
+    pub connection_id: String,
+    pub name: String,
+    pub iban: Option<String>,
+    pub currency: Option<String>,
+    /// Tienda en la que se importan sus movimientos.
+    pub target_store: String,
+    pub last_fetched_at: Option<u64>,
+}
+
+/// Carga de `banking://linked`.
+#[derive(Debug, Clone, Serialize)]
+pub struct BankLinkResult {
+    pub connection_id: String,
+    pub accounts: Vec<BankAccount>,
+    pub error: Option<String>,
+}
+
+/// Estado de un movimiento descargado.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum StagedStatus {
+    /// A la espera de revisarlo e importarlo.
+    Pending,
+    Imported,
+    /// Descartado a mano o por una regla.
+    Ignored,
+}
+
+impl StagedStatus {
+    fn as_str(self) -> &'static str {
+        match self {
+            StagedStatus::Pending => "pending",
+            StagedStatus::Imported => "imported",
+            StagedStatus::Ignored => "ignored",
+        }
+    }
+
+    fn parse(s: &str) -> StagedStatus {
+        match s {
+            "imported" => StagedStatus::Imported,
+            "ignored" => StagedStatus::Ignored,
+            _ => StagedStatus::Pending,
+        }
+    }
+}
+
+/// Movimiento descargado del banco, en el área de revisión. `amount` lleva signo:
+/// negativo para los cargos.
+#[derive(Debug, Clone, Serialize)]
+pub struct StagedBankTransaction {
+    pub id: String,
+    pub account_id: String,
+    pub booking_date: NaiveDate,
+    pub amount: Decimal,
+    pub currency: String,
+    pub payee: Option<String>,
+    pub memo: Option<String>,
+    pub status: StagedStatus,
+    /// Transacción creada al importarlo.
+    pub transaction_id: Option<String>,
+    pub fetched_at: u64,
+    /// Posición de la primera regla de conversión que le aplica, si hay alguna.
+    pub matched_rule: Option<usize>,
+}
+
+/// Regla para convertir movimientos en transacciones: si la descripción contiene
+/// `pattern` (sin distinguir mayúsculas), se usan sus campos. Se aplica la primera
+/// regla que coincide.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BankMappingRule {
+    pub pattern: String,
+    #[serde(default)]
+    pub category: Option<String>,
+    #[serde(default)]
+    pub subcategory: Option<String>,
+    /// Tienda en lugar de la de la cuenta.
+    #[serde(default)]
+    pub store_name: Option<String>,
+    /// Descarta el movimiento en vez de importarlo (p. ej. traspasos entre cuentas).
+    #[serde(default)]
+    pub ignore: bool,
+}
+
+/// Resultado de `fetch_bank_transactions_command`.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct BankFetchSummary {
+    pub accounts: usize,
+    /// Movimientos nuevos en el área de revisión.
+    pub staged: usize,
+    /// Movimientos que ya se habían descargado o importado.
+    pub skipped_existing: usize,
+}
+
+/// Resultado de `import_staged_bank_transactions_command`.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct BankImportSummary {
+    pub imported: usize,
+    pub ignored: usize,
+    pub transaction_ids: Vec<String>,
+}
+
+// --- API de GoCardless ---
+
+fn http_client() -> Result<Client, AppError> {
+    Client::builder()
+        .timeout(REQUEST_TIMEOUT)
+        .build()
+        .map_err(|e| AppError::Internal(format!("No se pudo crear el cliente HTTP: {}", e)))
+}
+
+fn network_error(e: reqwest::Error) -> AppError {
+    warn!("GoCardless request failed: {}", e);
+    AppError::Network(format!("No se pudo conectar con el servicio bancario: {}", e))
+}
+
+/// Error que corresponde a una respuesta de la API que no es un éxito.
+fn status_error(status: StatusCode, body: &Value, retry_after: Option<u64>) -> AppError {
+    let detail = body
+        .get("detail")
+        .or_else(|| body.get("summary"))
+        .and_then(|d| d.as_str())
+        .unwrap_or("desconocido");
+    error!("GoCardless request failed with {}: {}", status, detail);
+    match status {
+        StatusCode::TOO_MANY_REQUESTS => AppError::RateLimited {
+            message: "El banco limita las descargas de movimientos por día. Vuelve a intentarlo más tarde.".to_string(),
+            retry_after_secs: retry_after,
+        },
+        StatusCode::UNAUTHORIZED | StatusCode::FORBIDDEN => {
+            AppError::validation(format!("El servicio bancario rechazó el acceso: {}", detail))
+        }
+        StatusCode::NOT_FOUND => AppError::NotFound(format!("No encontrado en el servicio bancario: {}", detail)),
+        s if s.is_server_error() => AppError::Network(format!("El servicio bancario no está disponible (estado {}).", s)),
+        _ => AppError::validation(format!("Error del servicio bancario: {}", detail)),
+    }
+}
+
+async fn send(client: &Client, method: Method, path: &str, token: Option<&str>, body: Option<&Value>) -> Result<Value, AppError> {
+    let mut request = client.request(method, format!("{}{}", API_BASE, path)).header("Accept", "application/json");
+    if let Some(token) = token {
+        request = request.bearer_auth(token);
+    }
+    if let Some(body) = body {
+        request = request.json(body);
+    }
+    let response = request.send().await.map_err(network_error)?;
+    let status = response.status();
+    let retry_after = response
+        .headers()
+        .get(reqwest::header::RETRY_AFTER)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.trim().parse::<u64>().ok());
+    let body: Value = response.json().await.unwrap_or(Value::Null);
+    if status.is_success() {
+        Ok(body)
+    } else {
+        Err(status_error(status, &body, retry_after))
+    }
+}
+
+/// Token de acceso, pedido con las credenciales del llavero y guardado en memoria
+/// hasta poco antes de que caduque.
+async fn access_token(client: &Client) -> Result<String, AppError> {
+    if let Some((token, expires)) = ACCESS_TOKEN.lock().unwrap().as_ref() {
+        if Instant::now() < *expires {
+            return Ok(token.clone());
+        }
+    }
+    let missing = || AppError::validation("Configura el Secret ID y la Secret Key de GoCardless en los ajustes.");
+    let secret_id = keychain::get_secret(SECRET_ID_NAME)?.ok_or_else(missing)?;
+    let secret_key = keychain::get_secret(SECRET_KEY_NAME)?.ok_or_else(missing)?;
+    let body = serde_json::json!({ "secret_id": secret_id, "secret_key": secret_key });
+    let response = send(client, Method::POST, "/token/new/", None, Some(&body)).await?;
+    let token = response
+        .get("access")
+        .and_then(|t| t.as_str())
+        .ok_or_else(|| AppError::Network("Respuesta inesperada al pedir el token bancario.".to_string()))?
+        .to_owned();
+    let expires_in = response.get("access_expires").and_then(|v| v.as_u64()).unwrap_or(3600);
+    let valid_for = Duration::from_secs(expires_in.saturating_sub(TOKEN_MARGIN_SECS));
+    *ACCESS_TOKEN.lock().unwrap() = Some((token.clone(), Instant::now() + valid_for));
+    debug!("GoCardless access token refreshed.");
+    Ok(token)
+}
+
+async fn api(client: &Client, method: Method, path: &str, body: Option<&Value>) -> Result<Value, AppError> {
+    let token = access_token(client).await?;
+    send(client, method, path, Some(&token), body).await
+}
+
+fn str_field(value: &Value, pointer: &str) -> Option<String> {
+    value.pointer(pointer).and_then(|v| v.as_str()).map(str::trim).filter(|s| !s.is_empty()).map(str::to_owned)
+}
+
+/// Convierte un movimiento de la API en un `StatementEntry`. Sin `transactionId` se
+/// usa el identificador interno y, si tampoco lo hay, uno derivado de sus datos.
+fn parse_api_transaction(account_id: &str, value: &Value) -> Option<StatementEntry> {
+    let date = str_field(value, "/bookingDate")
+        .or_else(|| str_field(value, "/valueDate"))
+        .and_then(|d| NaiveDate::parse_from_str(&d, "%Y-%m-%d").ok())?;
+    let amount = Decimal::from_str(&str_field(value, "/transactionAmount/amount")?).ok()?;
+    let currency = str_field(value, "/transactionAmount/currency");
+    let payee = if amount.is_sign_negative() {
+        str_field(value, "/creditorName")
+    } else {
+        str_field(value, "/debtorName")
+    };
+    let memo = str_field(value, "/remittanceInformationUnstructured").or_else(|| {
+        value
+            .get("remittanceInformationUnstructuredArray")
+            .and_then(|a| a.as_array())
+            .map(|lines| lines.iter().filter_map(|l| l.as_str()).collect::<Vec<_>>().join(" "))
+            .filter(|s| !s.trim().is_empty())
+    });
+    let transaction_id = str_field(value, "/transactionId")
+        .or_else(|| str_field(value, "/internalTransactionId"))
+        .unwrap_or_else(|| format!("{}:{}:{}", date, amount.normalize(), memo.as_deref().unwrap_or_default()));
+    Some(StatementEntry {
+        date,
+        amount,
+        payee,
+        memo,
+        external_id: format!("gocardless:{}:{}", account_id, transaction_id),
+        currency,
+    })
+}
+
+// --- Base de datos ---
+
+fn row_to_connection(row: &Row) -> rusqlite::Result<BankConnection> {
+    Ok(BankConnection {
+        id: row.get(0)?,
+        institution_id: row.get(1)?,
+        institution_name: row.get(2)?,
+        status: row.get(3)?,
+        link: row.get(4)?,
+        created_at: row.get::<_, i64>(5)? as u64,
+    })
+}
+
+fn get_connection(conn: &Connection, id: &str) -> Result<Option<BankConnection>, AppError> {
+    conn.query_row(
+        "SELECT id, institution_id, institution_name, status, link, created_at FROM bank_connections WHERE id = ?1",
+        params![id],
+        row_to_connection,
+    )
+    .optional()
+    .map_err(db_error)
+}
+
+fn row_to_account(row: &Row) -> rusqlite::Result<BankAccount> {
+    Ok(BankAccount {
+        id: row.get(0)?,
+        connection_id: row.get(1)?,
+        name: row.get(2)?,
+        iban: row.get(3)?,
+        currency: row.get(4)?,
+        target_store: row.get(5)?,
+        last_fetched_at: row.get::<_, Option<i64>>(6)?.map(|t| t as u64),
+    })
+}
+
+const ACCOUNT_COLUMNS: &str = "id, connection_id, name, iban, currency, target_store, last_fetched_at";
+
+pub fn list_accounts(conn: &Connection) -> Result<Vec<BankAccount>, AppError> {
+    let mut stmt = conn
+        .prepare(&format!("SELECT {} FROM bank_accounts ORDER BY name, id", ACCOUNT_COLUMNS))
+        .map_err(db_error)?;
+    let rows = stmt.query_map([], row_to_account).map_err(db_error)?;
+    rows.collect::<rusqlite::Result<Vec<_>>>().map_err(db_error)
+}
+
+fn row_to_staged(row: &Row) -> rusqlite::Result<StagedBankTransaction> {
+    let date: String = row.get(2)?;
+    let amount: String = row.get(3)?;
+    Ok(StagedBankTransaction {
+        id: row.get(0)?,
+        account_id: row.get(1)?,
+        booking_date: NaiveDate::parse_from_str(&date, "%Y-%m-%d").unwrap_or_default(),
+        amount: Decimal::from_str(&amount).unwrap_or_default(),
+        currency: row.get(4)?,
+        payee: row.get(5)?,
+        memo: row.get(6)?,
+        status: StagedStatus::parse(&row.get::<_, String>(7)?),
+        transaction_id: row.get(8)?,
+        fetched_at: row.get::<_, i64>(9)? as u64,
+        matched_rule: None,
+    })
+}
+
+fn list_staged(conn: &Connection, status: Option<StagedStatus>) -> Result<Vec<StagedBankTransaction>, AppError> {
+    let mut stmt = conn
+        .prepare(
+            "SELECT id, account_id, booking_date, amount, currency, payee, memo, status, transaction_id, fetched_at
+             FROM bank_staging WHERE ?1 IS NULL OR status = ?1 ORDER BY booking_date DESC, id",
+        )
+        .map_err(db_error)?;
+    let rows = stmt.query_map(params![status.map(StagedStatus::as_str)], row_to_staged).map_err(db_error)?;
+    rows.collect::<rusqlite::Result<Vec<_>>>().map_err(db_error)
+}
+
+pub fn load_mapping_rules(db: &SqliteStorage) -> Vec<BankMappingRule> {
+    match db.get_setting(MAPPING_RULES_KEY) {
+        Ok(Some(json)) => serde_json::from_str(&json).unwrap_or_else(|e| {
+            warn!("Invalid bank mapping rules, ignoring them: {}", e);
+            Vec::new()
+        }),
+        Ok(None) => Vec::new(),
+        Err(e) => {
+            warn!("Could not load bank mapping rules: {}", e);
+            Vec::new()
+        }
+    }
+}
+
+fn staged_entry(staged: &StagedBankTransaction) -> StatementEntry {
+    StatementEntry {
+        date: staged.booking_date,
+        amount: staged.amount,
+        payee: staged.payee.clone(),
+        memo: staged.memo.clone(),
+        external_id: staged.id.clone(),
+        currency: Some(staged.currency.clone()),
+    }
+}
+
+/// Primera regla cuyo patrón aparece en el beneficiario o el concepto.
+fn matching_rule(rules: &[BankMappingRule], staged: &StagedBankTransaction) -> Option<usize> {
+    let text = format!("{} {}", staged.payee.as_deref().unwrap_or_default(), staged.memo.as_deref().unwrap_or_default())
+        .to_lowercase();
+    rules.iter().position(|rule| text.contains(&rule.pattern.to_lowercase()))
+}
+
+/// Pide a la API los datos de las cuentas autorizadas en `connection` y las guarda.
+async fn complete_link(app: &AppHandle, connection_id: &str) -> Result<Vec<BankAccount>, AppError> {
+    let client = http_client()?;
+    let requisition = api(&client, Method::GET, &format!("/requisitions/{}/", connection_id), None).await?;
+    let account_ids: Vec<String> = requisition
+        .get("accounts")
+        .and_then(|a| a.as_array())
+        .map(|ids| ids.iter().filter_map(|id| id.as_str().map(str::to_owned)).collect())
+        .unwrap_or_default();
+    if account_ids.is_empty() {
+        return Err(AppError::validation("El banco no autorizó ninguna cuenta. Vuelve a intentarlo."));
+    }
+
+    let mut details = Vec::new();
+    for account_id in &account_ids {
+        let value = api(&client, Method::GET, &format!("/accounts/{}/details/", account_id), None)
+            .await
+            .unwrap_or_else(|e| {
+                warn!("Could not load details of bank account {}: {}", account_id, e);
+                Value::Null
+            });
+        details.push((account_id.clone(), value));
+    }
+
+    let state = app.state::<AppState>();
+    let db = state.db().await?;
+    let connection = get_connection(db.connection(), connection_id)?
+        .ok_or_else(|| AppError::NotFound(format!("Vinculación {} no encontrada.", connection_id)))?;
+    let tx = db.connection().unchecked_transaction().map_err(db_error)?;
+    for (account_id, value) in &details {
+        let iban = str_field(value, "/account/iban");
+        let name = str_field(value, "/account/name")
+            .or_else(|| str_field(value, "/account/ownerName"))
+            .or_else(|| iban.clone())
+            .unwrap_or_else(|| connection.institution_name.clone());
+        tx.execute(
+            "INSERT INTO bank_accounts (id, connection_id, name, iban, currency, target_store)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6)
+             ON CONFLICT(id) DO UPDATE SET connection_id = excluded.connection_id, name = excluded.name,
+                 iban = excluded.iban, currency = excluded.currency",
+            params![account_id, connection_id, name, iban, str_field(value, "/account/currency"), connection.institution_name],
+        )
+        .map_err(db_error)?;
+    }
+    tx.execute("UPDATE bank_connections SET status = 'linked' WHERE id = ?1", params![connection_id])
+        .map_err(db_error)?;
+    tx.commit().map_err(db_error)?;
+    audit::record(db.connection(), "link_bank_account_command", Some(connection_id), None, audit::snapshot(&account_ids));
+    let accounts = list_accounts(db.connection())?.into_iter().filter(|a| a.connection_id == connection_id).collect();
+    Ok(accounts)
+}
+
+/// Abre la web del banco en una ventana propia. Cuando el banco redirige a
+/// `REDIRECT_URL` se cierra la ventana, se guardan las cuentas y se emite
+/// `banking://linked`.
+fn open_link_window(app: &AppHandle, connection: &BankConnection) -> Result<(), AppError> {
+    if let Some(window) = app.get_webview_window(BANK_LINK_WINDOW_LABEL) {
+        let _ = window.close();
+    }
+    let url = Url::parse(&connection.link)
+        .map_err(|e| AppError::Network(format!("El servicio bancario devolvió un enlace no válido: {}", e)))?;
+    let handle = app.clone();
+    let connection_id = connection.id.clone();
+    WebviewWindowBuilder::new(app, BANK_LINK_WINDOW_LABEL, WebviewUrl::External(url))
+        .title(format!("Vincular {}", connection.institution_name))
+        .inner_size(520.0, 720.0)
+        .on_navigation(move |url| {
+            if !url.as_str().starts_with(REDIRECT_URL) {
+                return true;
+            }
+            let app = handle.clone();
+            let connection_id = connection_id.clone();
+            tauri::async_runtime::spawn(async move {
+                if let Some(window) = app.get_webview_window(BANK_LINK_WINDOW_LABEL) {
+                    let _ = window.close();
+                }
+                let result = complete_link(&app, &connection_id).await;
+                let payload = match result {
+                    Ok(accounts) => {
+                        info!("Bank connection {} linked with {} accounts.", connection_id, accounts.len());
+                        BankLinkResult { connection_id, accounts, error: None }
+                    }
+                    Err(e) => {
+                        warn!("Bank connection {} failed: {}", connection_id, e);
+                        BankLinkResult { connection_id, accounts: Vec::new(), error: Some(e.to_string()) }
+                    }
+                };
+                events::emit(&app, BANK_LINKED_EVENT, payload);
+            });
+            false
+        })
+        .build()
+        .map_err(|e| AppError::Internal(format!("Error al abrir la ventana: {}", e)))?;
+    windows::focus_window(app, BANK_LINK_WINDOW_LABEL)?;
+    Ok(())
+}
+
+/// Descarga los movimientos contabilizados de `account` desde su última descarga
+/// (o de los últimos 90 días) y guarda los nuevos en el área de revisión.
+async fn fetch_account(
+    state: &AppState,
+    client: &Client,
+    account: &BankAccount,
+    summary: &mut BankFetchSummary,
+) -> Result<(), AppError> {
+    let since = account
+        .last_fetched_at
+        .map(|t| periods::local_date(t) - ChronoDuration::days(3))
+        .unwrap_or_else(|| periods::today() - ChronoDuration::days(INITIAL_HISTORY_DAYS));
+    let path = format!("/accounts/{}/transactions/?date_from={}", account.id, since.format("%Y-%m-%d"));
+    let response = api(client, Method::GET, &path, None).await?;
+    let entries: Vec<StatementEntry> = response
+        .pointer("/transactions/booked")
+        .and_then(|b| b.as_array())
+        .map(|booked| booked.iter().filter_map(|t| parse_api_transaction(&account.id, t)).collect())
+        .unwrap_or_default();
+    debug!("Fetched {} booked movements for bank account {}.", entries.len(), account.id);
+
+    let db = state.db().await?;
+    let base_currency = currencies::get_base_currency(&db)?;
+    let imported = db.all_external_ids()?;
+    let now = periods::now_timestamp();
+    let tx = db.connection().unchecked_transaction().map_err(db_error)?;
+    for entry in entries.iter().filter(|e| !e.amount.is_zero()) {
+        if imported.contains(&entry.external_id) {
+            summary.skipped_existing += 1;
+            continue;
+        }
+        let currency = entry.currency.clone().or_else(|| account.currency.clone()).unwrap_or_else(|| base_currency.clone());
+        let inserted = tx
+            .execute(
+                "INSERT OR IGNORE INTO bank_staging (id, account_id, booking_date, amount, currency, payee, memo, status, fetched_at)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, 'pending', ?8)",
+                params![
+                    entry.external_id,
+                    account.id,
+                    entry.date.format("%Y-%m-%d").to_string(),
+                    entry.amount.to_string(),
+                    currency,
+                    entry.payee,
+                    entry.memo,
+                    now as i64
+                ],
+            )
+            .map_err(db_error)?;
+        if inserted > 0 {
+            summary.staged += 1;
+        } else {
+            summary.skipped_existing += 1;
+        }
+    }
+    tx.execute("UPDATE bank_accounts SET last_fetched_at = ?2 WHERE id = ?1", params![account.id, now as i64])
+        .map_err(db_error)?;
+    tx.commit().map_err(db_error)?;
+    summary.accounts += 1;
+    Ok(())
+}
+
+// --- Comandos Tauri ---
+
+/// Comando para guardar en el llavero el Secret ID y la Secret Key de GoCardless
+/// Bank Account Data. Vacíos, se borran.
+#[tauri::command]
+pub async fn set_banking_credentials_command(secret_id: String, secret_key: String) -> Result<(), AppError> {
+    debug!("Received set_banking_credentials_command.");
+    for (name, value) in [(SECRET_ID_NAME, secret_id), (SECRET_KEY_NAME, secret_key)] {
+        match value.trim() {
+            "" => keychain::delete_secret(name)?,
+            value => keychain::set_secret(name, value)?,
+        }
+    }
+    *ACCESS_TOKEN.lock().unwrap() = None;
+    Ok(())
+}
+
+/// Comando para obtener los bancos disponibles en un país (código ISO de dos
+/// letras, p. ej. `ES`).
+#[tauri::command]
+pub async fn list_bank_institutions_command(country: String) -> Result<Vec<BankInstitution>, AppError> {
+    debug!("Received list_bank_institutions_command: {}", country);
+    let country = country.trim().to_uppercase();
+    if country.len() != 2 || !country.chars().all(|c| c.is_ascii_alphabetic()) {
+        return Err(AppError::invalid_field("country", "El país debe ser un código de dos letras, como ES."));
+    }
+    let client = http_client()?;
+    let response = api(&client, Method::GET, &format!("/institutions/?country={}", country), None).await?;
+    serde_json::from_value(response)
+        .map_err(|e| AppError::Network(format!("Respuesta inesperada del servicio bancario: {}", e)))
+}
+
+/// Comando para vincular las cuentas de un banco: crea la autorización y abre una
+/// ventana con la web del banco para que el usuario la confirme. Al terminar se
+/// emite `banking://linked` con las cuentas vinculadas.
+#[tauri::command]
+pub async fn link_bank_account_command(
+    state: State<'_, AppState>,
+    app: AppHandle,
+    institution_id: String,
+    institution_name: Option<String>,
+) -> Result<BankConnection, AppError> {
+    info!("Received link_bank_account_command: {}", institution_id);
+    let institution_id = institution_id.trim().to_owned();
+    if institution_id.is_empty() {
+        return Err(AppError::invalid_field("institution_id", "Elige un banco."));
+    }
+    let client = http_client()?;
+    let body = serde_json::json!({
+        "institution_id": institution_id,
+        "redirect": REDIRECT_URL,
+        "reference": uuid::Uuid::new_v4().to_string(),
+        "user_language": "ES",
+    });
+    let response = api(&client, Method::POST, "/requisitions/", Some(&body)).await?;
+    let (Some(id), Some(link)) = (str_field(&response, "/id"), str_field(&response, "/link")) else {
+        return Err(AppError::Network("Respuesta inesperada del servicio bancario.".to_string()));
+    };
+    let connection = BankConnection {
+        id,
+        institution_name: institution_name.map(|n| n.trim().to_owned()).filter(|n| !n.is_empty()).unwrap_or_else(|| institution_id.clone()),
+        institution_id,
+        status: "pending".to_string(),
+        link,
+        created_at: periods::now_timestamp(),
+    };
+    {
+        let db = state.db().await?;
+        db.connection()
+            .execute(
+                "INSERT INTO bank_connections (id, institution_id, institution_name, status, link, created_at)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+                params![
+                    connection.id,
+                    connection.institution_id,
+                    connection.institution_name,
+                    connection.status,
+                    connection.link,
+                    connection.created_at as i64
+                ],
+            )
+            .map_err(db_error)?;
+    }
+    open_link_window(&app, &connection)?;
+    Ok(connection)
+}
+
+/// Comando para obtener las cuentas bancarias vinculadas.
+#[tauri::command]
+pub async fn list_bank_accounts_command(state: State<'_, AppState>) -> Result<Vec<BankAccount>, AppError> {
+    debug!("Received list_bank_accounts_command.");
+    let db = state.db().await?;
+    list_accounts(db.connection())
+}
+
+/// Comando para cambiar la tienda en la que se importan los movimientos de una cuenta.
+#[tauri::command]
+pub async fn set_bank_account_store_command(
+    state: State<'_, AppState>,
+    account_id: String,
+    target_store: String,
+) -> Result<BankAccount, AppError> {
+    debug!("Received set_bank_account_store_command: {} -> '{}'", account_id, target_store);
+    let target_store = target_store.trim();
+    if target_store.is_empty() {
+        return Err(AppError::invalid_field("target_store", "Indica la tienda en la que importar la cuenta."));
+    }
+    let db = state.db().await?;
+    let updated = db
+        .connection()
+        .execute("UPDATE bank_accounts SET target_store = ?2 WHERE id = ?1", params![account_id, target_store])
+        .map_err(db_error)?;
+    if updated == 0 {
+        return Err(AppError::NotFound(format!("Cuenta bancaria {} no encontrada.", account_id)));
+    }
+    list_accounts(db.connection())?
+        .into_iter()
+        .find(|a| a.id == account_id)
+        .ok_or_else(|| AppError::NotFound(format!("Cuenta bancaria {} no encontrada.", account_id)))
+}
+
+/// Comando para desvincular un banco: revoca la autorización en GoCardless y borra
+/// sus cuentas y los movimientos pendientes de revisar. Las transacciones ya
+/// importadas se conservan.
+#[tauri::command]
+pub async fn unlink_bank_command(state: State<'_, AppState>, connection_id: String) -> Result<(), AppError> {
+    info!("Received unlink_bank_command: {}", connection_id);
+    let client = http_client()?;
+    match api(&client, Method::DELETE, &format!("/requisitions/{}/", connection_id), None).await {
+        Ok(_) | Err(AppError::NotFound(_)) => {}
+        Err(e) => return Err(e),
+    }
+    let db = state.db().await?;
+    let tx = db.connection().unchecked_transaction().map_err(db_error)?;
+    tx.execute(
+        "DELETE FROM bank_staging WHERE status = 'pending'
+         AND account_id IN (SELECT id FROM bank_accounts WHERE connection_id = ?1)",
+        params![connection_id],
+    )
+    .map_err(db_error)?;
+    tx.execute("DELETE FROM bank_accounts WHERE connection_id = ?1", params![connection_id]).map_err(db_error)?;
+    tx.execute("DELETE FROM bank_connections WHERE id = ?1", params![connection_id]).map_err(db_error)?;
+    tx.commit().map_err(db_error)?;
+    audit::record(db.connection(), "unlink_bank_command", Some(&connection_id), None, None);
+    Ok(())
+}
+
+/// Comando para descargar los movimientos nuevos de una cuenta vinculada (o de
+/// todas) al área de revisión. No crea transacciones: eso lo hace
+/// `import_staged_bank_transactions_command`. Los bancos suelen permitir solo unas
+/// pocas descargas al día; al superarlas devuelve un error `rate_limited`.
+#[tauri::command]
+pub async fn fetch_bank_transactions_command(
+    state: State<'_, AppState>,
+    account_id: Option<String>,
+) -> Result<BankFetchSummary, AppError> {
+    info!("Received fetch_bank_transactions_command: {:?}", account_id);
+    let accounts: Vec<BankAccount> = list_accounts(state.db().await?.connection())?
+        .into_iter()
+        .filter(|a| account_id.as_ref().map_or(true, |id| &a.id == id))
+        .collect();
+    if accounts.is_empty() {
+        return Err(AppError::NotFound("No hay cuentas bancarias vinculadas.".to_string()));
+    }
+    let client = http_client()?;
+    let mut summary = BankFetchSummary::default();
+    for account in &accounts {
+        fetch_account(&state, &client, account, &mut summary).await?;
+    }
+    info!(
+        "Bank fetch: {} accounts, {} new movements, {} already known.",
+        summary.accounts, summary.staged, summary.skipped_existing
+    );
+    Ok(summary)
+}
+
+/// Comando para obtener los movimientos del área de revisión (por defecto, los
+/// pendientes), con la regla de conversión que les aplicaría.
+#[tauri::command]
+pub async fn list_staged_bank_transactions_command(
+    state: State<'_, AppState>,
+    status: Option<StagedStatus>,
+) -> Result<Vec<StagedBankTransaction>, AppError> {
+    debug!("Received list_staged_bank_transactions_command: {:?}", status);
+    let db = state.db().await?;
+    let rules = load_mapping_rules(&db);
+    let mut staged = list_staged(db.connection(), Some(status.unwrap_or(StagedStatus::Pending)))?;
+    for item in staged.iter_mut() {
+        item.matched_rule = matching_rule(&rules, item);
+    }
+    Ok(staged)
+}
+
+/// Comando para obtener las reglas de conversión de movimientos bancarios.
+#[tauri::command]
+pub async fn list_bank_mapping_rules_command(state: State<'_, AppState>) -> Result<Vec<BankMappingRule>, AppError> {
+    debug!("Received list_bank_mapping_rules_command.");
+    let db = state.db().await?;
+    Ok(load_mapping_rules(&db))
+}
+
+/// Comando para sustituir las reglas de conversión. El orden importa: a cada
+/// movimiento se le aplica la primera que coincide.
+#[tauri::command]
+pub async fn set_bank_mapping_rules_command(
+    state: State<'_, AppState>,
+    rules: Vec<BankMappingRule>,
+) -> Result<Vec<BankMappingRule>, AppError> {
+    debug!("Received set_bank_mapping_rules_command: {} rules", rules.len());
+    let db = state.db().await?;
+    let mut validated = Vec::with_capacity(rules.len());
+    for rule in rules {
+        let pattern = rule.pattern.trim().to_owned();
+        if pattern.is_empty() {
+            return Err(AppError::invalid_field("rules", "Cada regla necesita un texto que buscar."));
+        }
+        let (category, subcategory) =
+            categories::resolve_transaction_category(db.connection(), rule.category, rule.subcategory)?;
+        let store_name = rule.store_name.map(|s| s.trim().to_owned()).filter(|s| !s.is_empty());
+        validated.push(BankMappingRule { pattern, category, subcategory, store_name, ignore: rule.ignore });
+    }
+    let before = load_mapping_rules(&db);
+    let json = serde_json::to_string(&validated).map_err(|e| AppError::Internal(e.to_string()))?;
+    db.set_setting(MAPPING_RULES_KEY, &json)?;
+    audit::record(db.connection(), "set_bank_mapping_rules_command", None, audit::snapshot(&before), audit::snapshot(&validated));
+    Ok(validated)
+}
+
+/// Comando para convertir en transacciones los movimientos pendientes indicados (o
+/// todos) aplicando las reglas de conversión, y descartar los de `ignore_ids`. Los
+/// abonos son ingresos y los cargos gastos, en la tienda de su cuenta salvo que la
+/// regla diga otra. Se deshace como un único paso.
+#[tauri::command]
+pub async fn import_staged_bank_transactions_command(
+    state: State<'_, AppState>,
+    app: AppHandle,
+    ids: Option<Vec<String>>,
+    ignore_ids: Option<Vec<String>>,
+) -> Result<BankImportSummary, AppError> {
+    info!("Received import_staged_bank_transactions_command.");
+    let ignore_ids = ignore_ids.unwrap_or_default();
+    let db = state.db().await?;
+    let rules = load_mapping_rules(&db);
+    let classification_rules = rules::load_enabled(db.connection())?;
+    let accounts = list_accounts(db.connection())?;
+    let pending: Vec<StagedBankTransaction> = list_staged(db.connection(), Some(StagedStatus::Pending))?
+        .into_iter()
+        .filter(|s| ignore_ids.contains(&s.id) || ids.as_ref().map_or(true, |ids| ids.contains(&s.id)))
+        .collect();
+
+    let mut summary = BankImportSummary::default();
+    let mut transactions = Vec::new();
+    let mut ignored = Vec::new();
+    for staged in &pending {
+        let rule = matching_rule(&rules, staged).map(|i| &rules[i]);
+        if ignore_ids.contains(&staged.id) || rule.is_some_and(|r| r.ignore) {
+            ignored.push(staged.id.clone());
+            continue;
+        }
+        let account_store = accounts
+            .iter()
+            .find(|a| a.id == staged.account_id)
+            .map(|a| a.target_store.clone())
+            .unwrap_or_else(|| "Banco".to_string());
+        let store = rule.and_then(|r| r.store_name.clone()).unwrap_or(account_store);
+        let currency = currencies::normalize_currency_code(&staged.currency)?;
+        let mut transaction = import::entry_to_transaction(&staged_entry(staged), &store, &currency)?;
+        match rule {
+            Some(rule) => {
+                // La categoría pudo borrarse después de crear la regla.
+                let (category, subcategory) =
+                    categories::resolve_transaction_category(db.connection(), rule.category.clone(), rule.subcategory.clone())
+                        .unwrap_or((None, None));
+                transaction.category = category;
+                transaction.subcategory = subcategory;
+            }
+            // Sin regla bancaria se prueban las de clasificación.
+            None => {
+                rules::apply(db.connection(), &classification_rules, &mut transaction, ApplyMode::Overwrite);
+            }
+        }
+        transactions.push((staged.id.clone(), transaction));
+    }
+
+    if !transactions.is_empty() {
+        backup::snapshot_before(&db, backup::REASON_IMPORT)?;
+    }
+    let tx = db.connection().unchecked_transaction().map_err(db_error)?;
+    for (staged_id, transaction) in &transactions {
+        db.insert_transaction(transaction)?;
+        tx.execute(
+            "UPDATE bank_staging SET status = 'imported', transaction_id = ?2 WHERE id = ?1",
+            params![staged_id, transaction.id],
+        )
+        .map_err(db_error)?;
+    }
+    for staged_id in &ignored {
+        tx.execute("UPDATE bank_staging SET status = 'ignored' WHERE id = ?1", params![staged_id]).map_err(db_error)?;
+    }
+    tx.commit().map_err(db_error)?;
+
+    let inserted: Vec<_> = transactions.into_iter().map(|(_, t)| t).collect();
+    if !inserted.is_empty() {
+        let changes: Vec<Change> = inserted.iter().cloned().map(Change::Insert).collect();
+        audit::record_changes(db.connection(), "import_staged_bank_transactions_command", &changes);
+        events::emit_transaction_changes(&app, &changes);
+        state.history.lock().unwrap().record(HistoryEntry::new("Importar movimientos bancarios", changes));
+        budgets::check_budget_alerts(&db, &app);
+    }
+    summary.imported = inserted.len();
+    summary.ignored = ignored.len();
+    summary.transaction_ids = inserted.into_iter().map(|t| t.id).collect();
+    info!("Bank movements imported: {}, ignored: {}.", summary.imported, summary.ignored);
+    Ok(summary)
+}
@@ -0,0 +1,325 @@
+// src-tauri/src/budgets.rs
+
+use rusqlite::{params, Connection, Row};
+use rust_decimal::prelude::ToPrimitive;
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, State};
+use log::{debug, error, info, warn};
+
+use crate::audit;
+use crate::currencies::RateTable;
+use crate::error::AppError;
+use crate::events::{self, ChangeAction};
+use crate::money;
+use crate::periods::{self, Period};
+use crate::splits;
+use crate::storage::{self, db_error, SqliteStorage, TransactionRepository};
+use crate::{AppState, TransactionType};
+
+/// Evento emitido al frontend cuando un presupuesto alcanza el 80 % o el 100 %.
+pub const BUDGET_ALERT_EVENT: &str = "budget://alert";
+
+/// Umbrales (en % del límite) que generan una alerta, de menor a mayor.
+const ALERT_THRESHOLDS: [u8; 2] = [80, 100];
+
+/// A qué se aplica un presupuesto.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum BudgetScope {
+    Categoria,
+    Tienda,
+}
+
+impl ToString for BudgetScope {
+    fn to_string(&self) -> String {
+        match self {
+            BudgetScope::Categoria => "Categoria".to_string(),
+            BudgetScope::Tienda => "Tienda".to_string(),
+        }
+    }
+}
+
+/// Límite de gasto para una categoría o tienda durante un periodo.
+/// El límite se expresa en la moneda base.
+#[derive(Debug, Clone, Serialize)]
+pub struct Budget {
+    pub id: String,
+    pub scope: BudgetScope,
+    pub scope_name: String,
+    pub period: Period,
+    pub limit_amount: Decimal,
+}
+
+/// Gasto acumulado frente al presupuesto en el periodo actual.
+#[derive(Debug, Clone, Serialize)]
+pub struct BudgetStatus {
+    pub budget: Budget,
+    pub period_start: u64,
+    pub period_end: u64,
+    pub spent: Decimal,
+    pub remaining: Decimal,
+    pub percent_used: f64,
+    pub currency: String,
+}
+
+/// Carga útil del evento `budget://alert`.
+#[derive(Debug, Clone, Serialize)]
+pub struct BudgetAlert {
+    pub budget_id: String,
+    pub scope: BudgetScope,
+    pub scope_name: String,
+    pub threshold: u8,
+    pub spent: Decimal,
+    pub limit_amount: Decimal,
+    pub percent_used: f64,
+    pub currency: String,
+}
+
+/// Fila de la tabla `budgets` con el estado de la última alerta enviada.
+struct BudgetRow {
+    budget: Budget,
+    alert_level: u8,
+    alert_period_start: u64,
+}
+
+fn row_to_budget(row: &Row) -> rusqlite::Result<BudgetRow> {
+    let scope = match row.get::<_, String>(1)?.as_str() {
+        "Tienda" => BudgetScope::Tienda,
+        _ => BudgetScope::Categoria,
+    };
+    let period_str: String = row.get(3)?;
+    let period = Period::parse(&period_str).unwrap_or(Period::Mensual);
+    Ok(BudgetRow {
+        budget: Budget {
+            id: row.get(0)?,
+            scope,
+            scope_name: row.get(2)?,
+            period,
+            limit_amount: storage::decimal_column(row, 4)?,
+        },
+        alert_level: row.get::<_, i64>(5)? as u8,
+        alert_period_start: row.get::<_, i64>(6)? as u64,
+    })
+}
+
+fn list_budget_rows(conn: &Connection) -> Result<Vec<BudgetRow>, AppError> {
+    let mut stmt = conn
+        .prepare(
+            "SELECT id, scope, scope_name, period, limit_amount, alert_level, alert_period_start
+             FROM budgets ORDER BY scope, scope_name, period",
+        )
+        .map_err(db_error)?;
+    let rows = stmt.query_map([], row_to_budget).map_err(db_error)?;
+    rows.collect::<rusqlite::Result<Vec<_>>>().map_err(db_error)
+}
+
+/// Calcula el gasto de cada presupuesto en el periodo que contiene `now`.
+fn compute_statuses(db: &SqliteStorage, budgets: Vec<Budget>, now: u64) -> Result<Vec<BudgetStatus>, AppError> {
+    if budgets.is_empty() {
+        return Ok(Vec::new());
+    }
+    let rates = RateTable::load(db)?;
+    // Las transacciones repartidas cuentan en el presupuesto de cada categoría por su parte.
+    let expenses: Vec<_> = splits::expand(db.connection(), db.list_transactions()?)?
+        .into_iter()
+        .filter(|t| t.transaction_type == TransactionType::Gasto)
+        .collect();
+
+    let mut statuses = Vec::with_capacity(budgets.len());
+    for budget in budgets {
+        let (period_start, period_end) = budget.period.bounds_containing(now);
+        let mut spent = Decimal::ZERO;
+        for transaction in expenses.iter() {
+            if transaction.timestamp < period_start || transaction.timestamp >= period_end {
+                continue;
+            }
+            let matches = match budget.scope {
+                BudgetScope::Categoria => transaction.category.as_deref() == Some(budget.scope_name.as_str()),
+                BudgetScope::Tienda => transaction.store_name == budget.scope_name,
+            };
+            if matches {
+                spent += rates.to_base(transaction.amount, &transaction.currency)?;
+            }
+        }
+        let percent_used = if budget.limit_amount > Decimal::ZERO {
+            (spent / budget.limit_amount * Decimal::ONE_HUNDRED).to_f64().unwrap_or(0.0)
+        } else {
+            0.0
+        };
+        statuses.push(BudgetStatus {
+            remaining: budget.limit_amount - spent,
+            budget,
+            period_start,
+            period_end,
+            spent,
+            percent_used,
+            currency: rates.base_currency.clone(),
+        });
+    }
+    Ok(statuses)
+}
+
+/// Gasto de todos los presupuestos en su periodo actual.
+pub fn current_statuses(db: &SqliteStorage) -> Result<Vec<BudgetStatus>, AppError> {
+    let budgets = list_budget_rows(db.connection())?.into_iter().map(|r| r.budget).collect();
+    compute_statuses(db, budgets, periods::now_timestamp())
+}
+
+/// Mayor umbral de alerta alcanzado con el porcentaje indicado (0 si ninguno).
+fn reached_threshold(percent_used: f64) -> u8 {
+    ALERT_THRESHOLDS
+        .iter()
+        .copied()
+        .filter(|t| percent_used >= *t as f64)
+        .max()
+        .unwrap_or(0)
+}
+
+/// Recalcula los presupuestos y devuelve las alertas de umbrales recién cruzados.
+/// Cada umbral se notifica una sola vez por periodo; el estado queda guardado en la tabla.
+pub fn collect_new_alerts(db: &SqliteStorage) -> Result<Vec<BudgetAlert>, AppError> {
+    let rows = list_budget_rows(db.connection())?;
+    let previous: Vec<(u8, u64)> = rows.iter().map(|r| (r.alert_level, r.alert_period_start)).collect();
+    let statuses = compute_statuses(db, rows.into_iter().map(|r| r.budget).collect(), periods::now_timestamp())?;
+
+    let mut alerts = Vec::new();
+    for (status, (alert_level, alert_period_start)) in statuses.into_iter().zip(previous) {
+        let notified = if alert_period_start == status.period_start { alert_level } else { 0 };
+        let reached = reached_threshold(status.percent_used);
+        if reached == notified && alert_period_start == status.period_start {
+            continue;
+        }
+
+        db.connection()
+            .execute(
+                "UPDATE budgets SET alert_level = ?1, alert_period_start = ?2 WHERE id = ?3",
+                params![reached as i64, status.period_start as i64, status.budget.id],
+            )
+            .map_err(db_error)?;
+
+        if reached > notified {
+            alerts.push(BudgetAlert {
+                budget_id: status.budget.id.clone(),
+                scope: status.budget.scope,
+                scope_name: status.budget.scope_name.clone(),
+                threshold: reached,
+                spent: status.spent,
+                limit_amount: status.budget.limit_amount,
+                percent_used: status.percent_used,
+                currency: status.currency,
+            });
+        }
+    }
+    Ok(alerts)
+}
+
+/// Comprueba los presupuestos tras una modificación y emite `budget://alert` por cada
+/// umbral cruzado. Los errores se registran pero no interrumpen el comando que la llama.
+pub fn check_budget_alerts(db: &SqliteStorage, app: &AppHandle) {
+    match collect_new_alerts(db) {
+        Ok(alerts) => {
+            for alert in alerts {
+                info!("Budget alert: {:?}", alert);
+                events::emit(app, BUDGET_ALERT_EVENT, &alert);
+            }
+        }
+        Err(e) => warn!("No se pudieron comprobar los presupuestos: {}", e),
+    }
+}
+
+// --- Comandos Tauri ---
+
+/// Comando para crear o actualizar un presupuesto. Si ya existe uno para el mismo
+/// ámbito y periodo, se actualiza su límite.
+#[tauri::command]
+pub async fn set_budget_command(
+    state: State<'_, AppState>,
+    app: AppHandle,
+    scope: BudgetScope,
+    scope_name: String,
+    period: Period,
+    limit_amount: Decimal,
+) -> Result<Budget, AppError> {
+    debug!("Received set_budget_command: scope={:?}, name='{}', period={:?}, limit={}",
+           scope, scope_name, period, limit_amount);
+    let scope_name = scope_name.trim();
+
+    if scope_name.is_empty() {
+        error!("Set budget: Empty scope name.");
+        return Err(AppError::invalid_field("scope_name", "El nombre de la categoría o tienda no puede estar vacío."));
+    }
+    money::validate_amount("limit_amount", limit_amount, "El límite del presupuesto debe ser positivo.")?;
+
+    let db = state.db().await?;
+    let previous = list_budget_rows(db.connection())?
+        .into_iter()
+        .map(|r| r.budget)
+        .find(|b| b.scope == scope && b.scope_name == scope_name && b.period == period);
+    let budget = Budget {
+        id: uuid::Uuid::new_v4().to_string(),
+        scope,
+        scope_name: scope_name.to_owned(),
+        period,
+        limit_amount,
+    };
+    db.connection()
+        .execute(
+            "INSERT INTO budgets (id, scope, scope_name, period, limit_amount) VALUES (?1, ?2, ?3, ?4, ?5)
+             ON CONFLICT(scope, scope_name, period) DO UPDATE SET limit_amount = excluded.limit_amount",
+            params![budget.id, scope.to_string(), budget.scope_name, period.to_string(), limit_amount.to_string()],
+        )
+        .map_err(db_error)?;
+
+    let saved = db
+        .connection()
+        .query_row(
+            "SELECT id, scope, scope_name, period, limit_amount, alert_level, alert_period_start
+             FROM budgets WHERE scope = ?1 AND scope_name = ?2 AND period = ?3",
+            params![scope.to_string(), budget.scope_name, period.to_string()],
+            row_to_budget,
+        )
+        .map_err(db_error)?
+        .budget;
+
+    audit::record(
+        db.connection(),
+        "set_budget_command",
+        Some(&saved.id),
+        previous.as_ref().and_then(audit::snapshot),
+        audit::snapshot(&saved),
+    );
+
+    let action = if previous.is_some() { ChangeAction::Updated } else { ChangeAction::Created };
+    events::emit_entity(&app, events::BUDGET_CHANGED_EVENT, action, &saved.id);
+    // Un nuevo límite puede hacer que el gasto actual ya supere algún umbral.
+    check_budget_alerts(&db, &app);
+    debug!("Budget saved: {:?}", saved);
+    Ok(saved)
+}
+
+/// Comando para obtener el gasto frente al presupuesto en el periodo actual.
+#[tauri::command]
+pub async fn get_budget_status_command(state: State<'_, AppState>) -> Result<Vec<BudgetStatus>, AppError> {
+    debug!("Received get_budget_status_command.");
+    let db = state.db().await?;
+    current_statuses(&db)
+}
+
+/// Comando para eliminar un presupuesto.
+#[tauri::command]
+pub async fn delete_budget_command(state: State<'_, AppState>, app: AppHandle, id: String) -> Result<(), AppError> {
+    debug!("Received delete_budget_command for ID: {}", id);
+    let db = state.db().await?;
+    let existing = list_budget_rows(db.connection())?.into_iter().map(|r| r.budget).find(|b| b.id == id);
+    let changed = db
+        .connection()
+        .execute("DELETE FROM budgets WHERE id = ?1", params![id])
+        .map_err(db_error)?;
+    if changed == 0 {
+        error!("Budget with ID {} not found for deletion.", id);
+        return Err(AppError::NotFound(format!("Presupuesto con ID {} no encontrado.", id)));
+    }
+    audit::record(db.connection(), "delete_budget_command", Some(&id), existing.as_ref().and_then(audit::snapshot), None);
+    events::emit_entity(&app, events::BUDGET_CHANGED_EVENT, ChangeAction::Deleted, &id);
+    Ok(())
+}
@@ -0,0 +1,256 @@
+// src-tauri/src/bulk.rs
+
+use chrono::NaiveDate;
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, State};
+use log::{debug, info, warn};
+
+use crate::attachments;
+use crate::audit;
+use crate::budgets;
+use crate::categories;
+use crate::contacts;
+use crate::error::AppError;
+use crate::events;
+use crate::history::{Change, HistoryEntry};
+use crate::payments::PaymentMethod;
+use crate::periods;
+use crate::storage::{db_error, SqliteStorage, TransactionRepository};
+use crate::tags;
+use crate::trash;
+use crate::validation::{self, validate_transaction_date};
+use crate::{AppState, Transaction, TransactionType};
+
+/// Número máximo de transacciones por operación masiva.
+const MAX_BULK_IDS: usize = 5000;
+
+/// Cambios a aplicar a varias transacciones. Los campos que falten no se tocan.
+/// Una categoría o un contacto vacíos se quitan.
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+pub struct TransactionPatch {
+    pub transaction_type: Option<TransactionType>,
+    pub store_name: Option<String>,
+    /// Con subcategoría y sin categoría, la subcategoría se busca en la categoría que
+    /// ya tiene cada transacción.
+    pub category: Option<String>,
+    pub subcategory: Option<String>,
+    pub payment_method: Option<PaymentMethod>,
+    pub contact_id: Option<String>,
+    pub transaction_date: Option<NaiveDate>,
+    pub add_tags: Vec<String>,
+    pub remove_tags: Vec<String>,
+}
+
+impl TransactionPatch {
+    fn is_empty(&self) -> bool {
+        self.transaction_type.is_none()
+            && self.store_name.is_none()
+            && self.category.is_none()
+            && self.subcategory.is_none()
+            && self.payment_method.is_none()
+            && self.contact_id.is_none()
+            && self.transaction_date.is_none()
+            && self.add_tags.is_empty()
+            && self.remove_tags.is_empty()
+    }
+
+    /// Aplica los cambios a una copia de `transaction`, validándolos como al editarla.
+    fn apply(&self, db: &SqliteStorage, transaction: &Transaction) -> Result<Transaction, AppError> {
+        let mut patched = transaction.clone();
+        if let Some(transaction_type) = &self.transaction_type {
+            if *transaction_type == TransactionType::Transferencia {
+                return Err(AppError::invalid_field(
+                    "transaction_type",
+                    "Una transacción no se puede convertir en transferencia; regístrala con su origen y su destino.",
+                ));
+            }
+            patched.transaction_type = transaction_type.clone();
+            patched.transfer_store = None;
+            patched.transfer_account_id = None;
+        }
+        if let Some(store_name) = &self.store_name {
+            let store_name = store_name.trim();
+            if store_name.is_empty() {
+                return Err(AppError::invalid_field("store_name", "El nombre de la tienda no puede estar vacío."));
+            }
+            patched.store_name = store_name.to_owned();
+        }
+        if self.category.is_some() || self.subcategory.is_some() {
+            let category = self.category.clone().or_else(|| transaction.category.clone());
+            (patched.category, patched.subcategory) =
+                categories::resolve_transaction_category(db.connection(), category, self.subcategory.clone())?;
+        }
+        if let Some(method) = self.payment_method {
+            patched.payment_method = method;
+        }
+        if let Some(contact_id) = &self.contact_id {
+            patched.contact_id = contacts::resolve_contact_id(db.connection(), Some(contact_id.clone()))?;
+        }
+        if let Some(date) = self.transaction_date.filter(|d| *d != transaction.transaction_date) {
+            patched.transaction_date = validate_transaction_date(date)?;
+            patched.timestamp = periods::timestamp_for_date(date);
+        }
+        if !self.add_tags.is_empty() || !self.remove_tags.is_empty() {
+            let remove = tags::resolve_tags(self.remove_tags.clone())?;
+            let mut merged = patched.tags.clone();
+            merged.extend(self.add_tags.iter().cloned());
+            patched.tags = tags::resolve_tags(merged)?.into_iter().filter(|t| !remove.contains(t)).collect();
+        }
+        patched.updated_at = periods::now_timestamp();
+        validation::enforce_policy(db, &patched)?;
+        Ok(patched)
+    }
+}
+
+/// Transacción que no se pudo modificar o eliminar, con el motivo.
+#[derive(Debug, Serialize)]
+pub struct BulkFailure {
+    pub id: String,
+    pub error: AppError,
+}
+
+/// Resultado de una operación masiva: IDs modificados y los que fallaron. Con
+/// `all_or_nothing`, si algo falla `changed` queda vacío.
+#[derive(Debug, Serialize)]
+pub struct BulkResult {
+    pub changed: Vec<String>,
+    pub failed: Vec<BulkFailure>,
+}
+
+/// Quita espacios y repetidos conservando el orden, y limita el tamaño de la lista.
+fn normalize_ids(ids: Vec<String>) -> Result<Vec<String>, AppError> {
+    let mut unique: Vec<String> = Vec::new();
+    for id in ids.into_iter().map(|id| id.trim().to_owned()).filter(|id| !id.is_empty()) {
+        if !unique.contains(&id) {
+            unique.push(id);
+        }
+    }
+    if unique.is_empty() {
+        return Err(AppError::invalid_field("ids", "Selecciona al menos una transacción."));
+    }
+    if unique.len() > MAX_BULK_IDS {
+        return Err(AppError::invalid_field(
+            "ids",
+            format!("Se pueden modificar como máximo {} transacciones a la vez.", MAX_BULK_IDS),
+        ));
+    }
+    Ok(unique)
+}
+
+fn not_found(id: &str) -> AppError {
+    AppError::NotFound(format!("Transacción con ID {} no encontrada.", id))
+}
+
+// --- Comandos Tauri ---
+
+/// Comando para aplicar los mismos cambios (`patch`) a varias transacciones en una
+/// sola transacción de la base de datos, que se deshace como un único paso. Por
+/// defecto se guardan las que se pueden modificar y se informa de las demás; con
+/// `all_or_nothing`, si alguna falla no se modifica ninguna.
+#[tauri::command]
+pub async fn bulk_update_transactions_command(
+    state: State<'_, AppState>,
+    app: AppHandle,
+    ids: Vec<String>,
+    patch: TransactionPatch,
+    all_or_nothing: Option<bool>,
+) -> Result<BulkResult, AppError> {
+    debug!("Received bulk_update_transactions_command for {} IDs: {:?}", ids.len(), patch);
+    let ids = normalize_ids(ids)?;
+    if patch.is_empty() {
+        return Err(AppError::validation("Indica al menos un cambio."));
+    }
+
+    let db = state.db().await?;
+    let tx = db.connection().unchecked_transaction().map_err(db_error)?;
+    let mut changes = Vec::new();
+    let mut failed = Vec::new();
+    for id in &ids {
+        let result = db.get_transaction(id)?.ok_or_else(|| not_found(id)).and_then(|before| {
+            let after = patch.apply(&db, &before)?;
+            if !db.update_transaction(&after)? {
+                return Err(not_found(id));
+            }
+            Ok(Change::Update { before, after })
+        });
+        match result {
+            Ok(change) => changes.push(change),
+            Err(error) => failed.push(BulkFailure { id: id.clone(), error }),
+        }
+    }
+
+    if all_or_nothing.unwrap_or(false) && !failed.is_empty() {
+        warn!("Bulk update rolled back: {} of {} transactions failed.", failed.len(), ids.len());
+        drop(tx);
+        return Ok(BulkResult { changed: Vec::new(), failed });
+    }
+    tx.commit().map_err(db_error)?;
+
+    let changed = ids.iter().filter(|id| !failed.iter().any(|f| &f.id == *id)).cloned().collect();
+    if !changes.is_empty() {
+        audit::record_changes(db.connection(), "bulk_update_transactions_command", &changes);
+        events::emit_transaction_changes(&app, &changes);
+        state.history.lock().unwrap().record(HistoryEntry::new("Editar transacciones", changes));
+        budgets::check_budget_alerts(&db, &app);
+    }
+    info!("Bulk update: {} transactions changed, {} failed.", ids.len() - failed.len(), failed.len());
+    Ok(BulkResult { changed, failed })
+}
+
+/// Comando para eliminar varias transacciones a la vez. Por defecto se mueven a la
+/// papelera; con `permanent` se borran definitivamente junto con sus adjuntos. Se
+/// deshace como un único paso y admite `all_or_nothing` como la edición masiva.
+#[tauri::command]
+pub async fn bulk_delete_transactions_command(
+    state: State<'_, AppState>,
+    app: AppHandle,
+    ids: Vec<String>,
+    permanent: Option<bool>,
+    all_or_nothing: Option<bool>,
+) -> Result<BulkResult, AppError> {
+    debug!("Received bulk_delete_transactions_command for {} IDs (permanent: {:?})", ids.len(), permanent);
+    let ids = normalize_ids(ids)?;
+    let permanent = permanent.unwrap_or(false);
+
+    let db = state.db().await?;
+    let tx = db.connection().unchecked_transaction().map_err(db_error)?;
+    let mut changes = Vec::new();
+    let mut failed = Vec::new();
+    for id in &ids {
+        let result = db.get_transaction(id)?.ok_or_else(|| not_found(id)).and_then(|existing| {
+            if permanent {
+                db.delete_transaction(id)?;
+                Ok(Change::Delete(existing))
+            } else {
+                trash::move_to_trash(&db, existing)
+            }
+        });
+        match result {
+            Ok(change) => changes.push(change),
+            Err(error) => failed.push(BulkFailure { id: id.clone(), error }),
+        }
+    }
+
+    if all_or_nothing.unwrap_or(false) && !failed.is_empty() {
+        warn!("Bulk delete rolled back: {} of {} transactions failed.", failed.len(), ids.len());
+        drop(tx);
+        return Ok(BulkResult { changed: Vec::new(), failed });
+    }
+    tx.commit().map_err(db_error)?;
+
+    let changed: Vec<String> = ids.iter().filter(|id| !failed.iter().any(|f| &f.id == *id)).cloned().collect();
+    // Los adjuntos solo se borran del disco una vez confirmado el borrado.
+    if permanent {
+        for id in &changed {
+            attachments::remove_transaction_attachments(id);
+        }
+    }
+    if !changes.is_empty() {
+        audit::record_changes(db.connection(), "bulk_delete_transactions_command", &changes);
+        events::emit_transaction_changes(&app, &changes);
+        state.history.lock().unwrap().record(HistoryEntry::new("Eliminar transacciones", changes));
+    }
+    info!("Bulk delete: {} transactions deleted, {} failed.", changed.len(), failed.len());
+    Ok(BulkResult { changed, failed })
+}
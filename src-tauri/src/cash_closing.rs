@@ -0,0 +1,438 @@
+// src-tauri/src/cash_closing.rs
+
+use chrono::NaiveDate;
+use rusqlite::{params, Connection, OptionalExtension, Row};
+use rust_decimal::Decimal;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::path::PathBuf;
+use tauri::{AppHandle, State};
+use log::{debug, error, info};
+
+use crate::audit;
+use crate::categories;
+use crate::currencies::{self, RateTable};
+use crate::error::AppError;
+use crate::events::{self, ChangeAction};
+use crate::history::Change;
+use crate::i18n;
+use crate::merge;
+use crate::money::round_money;
+use crate::payments::PaymentMethod;
+use crate::pdf::{PdfWriter, MARGIN_MM};
+use crate::periods;
+use crate::settings::{self, Settings};
+use crate::storage::{self, date_column, db_error, decimal_column, SqliteStorage, TransactionRepository, DATE_FORMAT};
+use crate::trash;
+use crate::{AppState, Transaction, TransactionType};
+
+// Cierre de caja diario: al terminar el día se cuenta el efectivo de la caja y se
+// compara con el que debería haber (lo que había al abrir más los cobros y menos los
+// pagos en efectivo del día). La diferencia se registra como un ingreso (sobrante) o
+// un gasto (faltante) y el día queda cerrado: sus transacciones ya no se pueden crear,
+// modificar ni borrar, igual que las de un mes cerrado (ver `closing`).
+
+/// Tienda y categoría de las transacciones que ajustan la diferencia de caja.
+pub const CASH_ADJUSTMENT_STORE: &str = "Caja";
+const CASH_ADJUSTMENT_CATEGORY: &str = "Ajustes de caja";
+
+/// Lo que debería haber en la caja al cerrar el día `date`, en la moneda base.
+#[derive(Debug, Clone, Serialize)]
+pub struct CashClosingPreview {
+    pub date: NaiveDate,
+    /// Efectivo al abrir: lo contado en el cierre anterior, salvo que se indique otro.
+    pub opening_cash: Decimal,
+    pub cash_income: Decimal,
+    pub cash_expenses: Decimal,
+    pub expected_cash: Decimal,
+    /// Transacciones en efectivo del día, por orden.
+    pub transactions: Vec<Transaction>,
+}
+
+/// Día cerrado, con lo esperado y lo contado en la moneda base.
+#[derive(Debug, Clone, Serialize)]
+pub struct CashClosing {
+    pub date: NaiveDate,
+    pub opening_cash: Decimal,
+    pub cash_income: Decimal,
+    pub cash_expenses: Decimal,
+    pub expected_cash: Decimal,
+    pub counted_cash: Decimal,
+    /// Contado menos esperado: positivo si sobra dinero y negativo si falta.
+    pub difference: Decimal,
+    /// Transacción que registra la diferencia; `None` si la caja cuadró.
+    pub adjustment_transaction_id: Option<String>,
+    pub notes: String,
+    pub closed_at: u64,
+    pub actor: String,
+}
+
+const CLOSING_COLUMNS: &str = "date, opening_cash, cash_income, cash_expenses, expected_cash, counted_cash, difference, \
+     adjustment_transaction_id, notes, closed_at, actor";
+
+fn row_to_cash_closing(row: &Row) -> rusqlite::Result<CashClosing> {
+    Ok(CashClosing {
+        date: date_column(row, 0)?,
+        opening_cash: decimal_column(row, 1)?,
+        cash_income: decimal_column(row, 2)?,
+        cash_expenses: decimal_column(row, 3)?,
+        expected_cash: decimal_column(row, 4)?,
+        counted_cash: decimal_column(row, 5)?,
+        difference: decimal_column(row, 6)?,
+        adjustment_transaction_id: row.get(7)?,
+        notes: row.get(8)?,
+        closed_at: row.get::<_, i64>(9)? as u64,
+        actor: row.get(10)?,
+    })
+}
+
+pub fn get_cash_closing(conn: &Connection, date: NaiveDate) -> Result<Option<CashClosing>, AppError> {
+    conn.query_row(
+        &format!("SELECT {} FROM cash_closings WHERE date = ?1", CLOSING_COLUMNS),
+        params![date.format(DATE_FORMAT).to_string()],
+        row_to_cash_closing,
+    )
+    .optional()
+    .map_err(db_error)
+}
+
+/// Cierres de `[from, to]` (sin límite si no se indican), del más antiguo al más reciente.
+pub fn list_cash_closings(
+    conn: &Connection,
+    from: Option<NaiveDate>,
+    to: Option<NaiveDate>,
+) -> Result<Vec<CashClosing>, AppError> {
+    let bound = |date: Option<NaiveDate>| date.map(|d| d.format(DATE_FORMAT).to_string());
+    let mut stmt = conn
+        .prepare(&format!(
+            "SELECT {} FROM cash_closings WHERE (?1 IS NULL OR date >= ?1) AND (?2 IS NULL OR date <= ?2) ORDER BY date",
+            CLOSING_COLUMNS
+        ))
+        .map_err(db_error)?;
+    let rows = stmt.query_map(params![bound(from), bound(to)], row_to_cash_closing).map_err(db_error)?;
+    rows.collect::<rusqlite::Result<Vec<_>>>().map_err(db_error)
+}
+
+/// Efectivo contado en el último cierre anterior a `date`.
+fn previous_counted_cash(conn: &Connection, date: NaiveDate) -> Result<Option<Decimal>, AppError> {
+    conn.query_row(
+        "SELECT counted_cash FROM cash_closings WHERE date < ?1 ORDER BY date DESC LIMIT 1",
+        params![date.format(DATE_FORMAT).to_string()],
+        |row| decimal_column(row, 0),
+    )
+    .optional()
+    .map_err(db_error)
+}
+
+/// Calcula el efectivo esperado al cerrar `date`. Cuentan los ingresos y gastos
+/// pagados en efectivo; las transferencias, no.
+pub fn preview_closing(db: &SqliteStorage, date: NaiveDate, opening_cash: Option<Decimal>) -> Result<CashClosingPreview, AppError> {
+    let opening_cash = match opening_cash {
+        Some(amount) => round_money(amount),
+        None => previous_counted_cash(db.connection(), date)?.unwrap_or_default(),
+    };
+    let rates = RateTable::load(db)?;
+    let transactions: Vec<Transaction> = db
+        .list_transactions()?
+        .into_iter()
+        .filter(|t| {
+            t.transaction_date == date
+                && t.payment_method == PaymentMethod::Efectivo
+                && t.transaction_type != TransactionType::Transferencia
+        })
+        .collect();
+    let (mut cash_income, mut cash_expenses) = (Decimal::ZERO, Decimal::ZERO);
+    for t in transactions.iter() {
+        let amount = rates.to_base(t.amount, &t.currency)?;
+        match t.transaction_type {
+            TransactionType::Ingreso => cash_income += amount,
+            _ => cash_expenses += amount,
+        }
+    }
+    let (cash_income, cash_expenses) = (round_money(cash_income), round_money(cash_expenses));
+    Ok(CashClosingPreview {
+        date,
+        opening_cash,
+        cash_income,
+        cash_expenses,
+        expected_cash: opening_cash + cash_income - cash_expenses,
+        transactions,
+    })
+}
+
+/// Transacción en efectivo que lleva el saldo de la caja de lo esperado a lo contado.
+fn adjustment_transaction(conn: &Connection, date: NaiveDate, difference: Decimal, base_currency: &str) -> Transaction {
+    let (category, subcategory) =
+        categories::resolve_transaction_category(conn, Some(CASH_ADJUSTMENT_CATEGORY.to_string()), None)
+            .unwrap_or((None, None));
+    let (transaction_type, description) = if difference.is_sign_positive() {
+        (TransactionType::Ingreso, "Sobrante de caja")
+    } else {
+        (TransactionType::Gasto, "Faltante de caja")
+    };
+    let now = periods::now_timestamp();
+    Transaction {
+        id: uuid::Uuid::new_v4().to_string(),
+        transaction_type,
+        amount: difference.abs(),
+        description: format!("{} del {}", description, date.format("%d/%m/%Y")),
+        store_name: CASH_ADJUSTMENT_STORE.to_string(),
+        timestamp: periods::timestamp_for_date(date),
+        category,
+        subcategory,
+        currency: base_currency.to_owned(),
+        receipt_paths: Vec::new(),
+        deleted_at: None,
+        tax_rate: None,
+        tax_amount: None,
+        tags: Vec::new(),
+        external_id: None,
+        transaction_date: date,
+        created_at: now,
+        updated_at: now,
+        notes: String::new(),
+        custom_fields: HashMap::new(),
+        payment_method: PaymentMethod::Efectivo,
+        contact_id: None,
+        transfer_store: None,
+        account_id: None,
+        transfer_account_id: None,
+        user_id: None,
+        revision: merge::initial_revision(),
+        device_id: String::new(),
+        sequence: 0,
+    }
+}
+
+// --- Informe ---
+
+/// Genera el PDF del cierre de caja: el cuadre y las transacciones en efectivo del día.
+pub fn render_closing_pdf(
+    closing: &CashClosing,
+    transactions: &[Transaction],
+    settings: &Settings,
+    base_currency: &str,
+) -> Result<Vec<u8>, AppError> {
+    let language = settings.language();
+    let date = settings.format_date(closing.date);
+    let title = i18n::format(language, "cash_closing.title", &[("date", &date)]);
+    let mut pdf = PdfWriter::new(&title, i18n::text(language, "report.layer"))?;
+    let money = |amount| settings.format_money(amount, base_currency, base_currency);
+
+    pdf.text(MARGIN_MM, &title, 18.0, true);
+    pdf.newline();
+    pdf.text(MARGIN_MM, &i18n::format(language, "cash_closing.closed_by", &[("actor", &closing.actor)]), 10.0, false);
+    pdf.newline();
+    pdf.newline();
+    let rows = [
+        ("cash_closing.opening", closing.opening_cash, false),
+        ("cash_closing.income", closing.cash_income, false),
+        ("cash_closing.expenses", closing.cash_expenses, false),
+        ("cash_closing.expected", closing.expected_cash, true),
+        ("cash_closing.counted", closing.counted_cash, true),
+        ("cash_closing.difference", closing.difference, true),
+    ];
+    for (key, amount, bold) in rows {
+        pdf.text(MARGIN_MM, i18n::text(language, key), 10.0, bold);
+        pdf.text(120.0, &money(amount), 10.0, bold);
+        pdf.newline();
+    }
+    if !closing.notes.is_empty() {
+        pdf.newline();
+        pdf.text(MARGIN_MM, &closing.notes, 10.0, false);
+        pdf.newline();
+    }
+
+    pdf.newline();
+    pdf.text(MARGIN_MM, i18n::text(language, "cash_closing.transactions"), 12.0, true);
+    pdf.newline();
+    for t in transactions {
+        let sign = if t.transaction_type == TransactionType::Ingreso { Decimal::ONE } else { -Decimal::ONE };
+        pdf.text(MARGIN_MM, &t.store_name, 10.0, false);
+        pdf.text(70.0, &t.description, 10.0, false);
+        pdf.text(160.0, &settings.format_money(sign * t.amount, &t.currency, base_currency), 10.0, false);
+        pdf.newline();
+    }
+    pdf.finish()
+}
+
+// --- Comandos Tauri ---
+
+/// Comando para empezar el cierre de caja del día `date` (hoy si no se indica):
+/// devuelve el efectivo que debería haber en la caja, para compararlo con el contado.
+/// `opening_cash` sustituye al efectivo contado en el cierre anterior.
+#[tauri::command]
+pub async fn start_closing_command(
+    state: State<'_, AppState>,
+    date: Option<NaiveDate>,
+    opening_cash: Option<Decimal>,
+) -> Result<CashClosingPreview, AppError> {
+    debug!("Received start_closing_command: {:?} (opening cash {:?})", date, opening_cash);
+    let date = date.unwrap_or_else(periods::today);
+    let db = state.db().await?;
+    if get_cash_closing(db.connection(), date)?.is_some() {
+        return Err(AppError::Conflict(format!("La caja del {} ya está cerrada.", date)));
+    }
+    preview_closing(&db, date, opening_cash)
+}
+
+/// Comando para terminar el cierre de caja con el efectivo contado. Si no cuadra con
+/// el esperado, registra la diferencia como un ingreso o un gasto en efectivo en la
+/// tienda "Caja". Después el día queda cerrado. No se puede cerrar un día futuro.
+#[tauri::command]
+pub async fn complete_closing_command(
+    state: State<'_, AppState>,
+    app: AppHandle,
+    date: Option<NaiveDate>,
+    counted_cash: Decimal,
+    opening_cash: Option<Decimal>,
+    notes: Option<String>,
+) -> Result<CashClosing, AppError> {
+    debug!("Received complete_closing_command: {:?}, counted {}", date, counted_cash);
+    let date = date.unwrap_or_else(periods::today);
+    if date > periods::today() {
+        return Err(AppError::invalid_field("date", "No se puede cerrar la caja de un día futuro."));
+    }
+    if counted_cash.is_sign_negative() {
+        return Err(AppError::invalid_field("counted_cash", "El efectivo contado no puede ser negativo."));
+    }
+
+    let db = state.db().await?;
+    if get_cash_closing(db.connection(), date)?.is_some() {
+        return Err(AppError::Conflict(format!("La caja del {} ya está cerrada.", date)));
+    }
+    let preview = preview_closing(&db, date, opening_cash)?;
+    let counted_cash = round_money(counted_cash);
+    let difference = counted_cash - preview.expected_cash;
+    let adjustment = if difference.is_zero() {
+        None
+    } else {
+        Some(adjustment_transaction(db.connection(), date, difference, &currencies::get_base_currency(&db)?))
+    };
+    let closing = CashClosing {
+        date,
+        opening_cash: preview.opening_cash,
+        cash_income: preview.cash_income,
+        cash_expenses: preview.cash_expenses,
+        expected_cash: preview.expected_cash,
+        counted_cash,
+        difference,
+        adjustment_transaction_id: adjustment.as_ref().map(|t| t.id.clone()),
+        notes: notes.map(|n| n.trim().to_owned()).unwrap_or_default(),
+        closed_at: periods::now_timestamp(),
+        actor: audit::current_actor(),
+    };
+
+    // El ajuste se guarda antes que el cierre: después, el día ya no admite cambios.
+    let tx = db.connection().unchecked_transaction().map_err(db_error)?;
+    if let Some(adjustment) = &adjustment {
+        db.insert_transaction(adjustment)?;
+    }
+    tx.execute(
+        &format!("INSERT INTO cash_closings ({}) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11)", CLOSING_COLUMNS),
+        params![
+            closing.date.format(DATE_FORMAT).to_string(),
+            closing.opening_cash.to_string(),
+            closing.cash_income.to_string(),
+            closing.cash_expenses.to_string(),
+            closing.expected_cash.to_string(),
+            closing.counted_cash.to_string(),
+            closing.difference.to_string(),
+            closing.adjustment_transaction_id,
+            closing.notes,
+            closing.closed_at as i64,
+            closing.actor,
+        ],
+    )
+    .map_err(db_error)?;
+    tx.commit().map_err(db_error)?;
+
+    let key = date.format(DATE_FORMAT).to_string();
+    if let Some(adjustment) = adjustment {
+        let changes = vec![Change::Insert(adjustment)];
+        audit::record_changes(db.connection(), "complete_closing_command", &changes);
+        events::emit_transaction_changes(&app, &changes);
+    }
+    audit::record(db.connection(), "complete_closing_command", Some(&key), None, audit::snapshot(&closing));
+    events::emit_entity(&app, events::CASH_CLOSING_CHANGED_EVENT, ChangeAction::Created, &key);
+    info!("Cash closed for {} (difference {}).", key, closing.difference);
+    Ok(closing)
+}
+
+/// Comando para listar los cierres de caja entre `from` y `to`, ambos incluidos.
+#[tauri::command]
+pub async fn list_cash_closings_command(
+    state: State<'_, AppState>,
+    from: Option<NaiveDate>,
+    to: Option<NaiveDate>,
+) -> Result<Vec<CashClosing>, AppError> {
+    debug!("Received list_cash_closings_command: {:?} - {:?}", from, to);
+    list_cash_closings(state.db().await?.connection(), from, to)
+}
+
+/// Comando para reabrir la caja de un día. Exige `confirm`, como reabrir un mes; la
+/// transacción de ajuste, si la hubo, pasa a la papelera para que el nuevo cierre
+/// calcule la diferencia desde cero.
+#[tauri::command]
+pub async fn reopen_cash_closing_command(
+    state: State<'_, AppState>,
+    app: AppHandle,
+    date: NaiveDate,
+    confirm: Option<bool>,
+) -> Result<(), AppError> {
+    debug!("Received reopen_cash_closing_command: {} (confirm: {:?})", date, confirm);
+    let db = state.db().await?;
+    let closing = get_cash_closing(db.connection(), date)?
+        .ok_or_else(|| AppError::NotFound(format!("La caja del {} no está cerrada.", date)))?;
+    if !confirm.unwrap_or(false) {
+        return Err(AppError::invalid_field(
+            "confirm",
+            format!("Al reabrir la caja del {} se podrán volver a modificar sus transacciones. Confirma para continuar.", date),
+        ));
+    }
+
+    let key = date.format(DATE_FORMAT).to_string();
+    let tx = db.connection().unchecked_transaction().map_err(db_error)?;
+    tx.execute("DELETE FROM cash_closings WHERE date = ?1", params![key]).map_err(db_error)?;
+    let adjustment = match &closing.adjustment_transaction_id {
+        Some(id) => db.get_transaction(id)?,
+        None => None,
+    };
+    let changes = match adjustment {
+        Some(adjustment) => vec![trash::move_to_trash(&db, adjustment)?],
+        None => Vec::new(),
+    };
+    tx.commit().map_err(db_error)?;
+
+    audit::record_changes(db.connection(), "reopen_cash_closing_command", &changes);
+    events::emit_transaction_changes(&app, &changes);
+    audit::record(db.connection(), "reopen_cash_closing_command", Some(&key), audit::snapshot(&closing), None);
+    events::emit_entity(&app, events::CASH_CLOSING_CHANGED_EVENT, ChangeAction::Deleted, &key);
+    info!("Cash closing for {} reopened.", key);
+    Ok(())
+}
+
+/// Comando para guardar en `path` el informe en PDF del cierre de caja de `date`.
+/// Devuelve la ruta escrita.
+#[tauri::command]
+pub async fn export_cash_closing_pdf_command(
+    state: State<'_, AppState>,
+    date: NaiveDate,
+    path: String,
+) -> Result<String, AppError> {
+    debug!("Received export_cash_closing_pdf_command: {} -> {}", date, path);
+    let (closing, preview, settings, base_currency) = {
+        let db = state.db().await?;
+        let Some(closing) = get_cash_closing(db.connection(), date)? else {
+            error!("No cash closing for {}.", date);
+            return Err(AppError::NotFound(format!("La caja del {} no está cerrada.", date)));
+        };
+        let preview = preview_closing(&db, date, Some(closing.opening_cash))?;
+        (closing, preview, settings::load_settings(&db), currencies::get_base_currency(&db)?)
+    };
+    let bytes = render_closing_pdf(&closing, &preview.transactions, &settings, &base_currency)?;
+    let path = PathBuf::from(path);
+    storage::write_atomic(&path, &bytes)?;
+    info!("Cash closing report for {} exported to {}", date, path.display());
+    Ok(path.display().to_string())
+}
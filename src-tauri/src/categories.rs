@@ -0,0 +1,305 @@
+// src-tauri/src/categories.rs
+
+use rusqlite::{params, Connection};
+use serde::Serialize;
+use serde_json::json;
+use tauri::{AppHandle, State};
+use log::{debug, error};
+
+use crate::aggregates;
+use crate::audit;
+use crate::closing;
+use crate::error::AppError;
+use crate::events::{self, ChangeAction};
+use crate::storage::db_error;
+use crate::validation;
+use crate::AppState;
+
+/// Categoría principal con sus subcategorías.
+#[derive(Debug, Clone, Serialize)]
+pub struct Category {
+    pub name: String,
+    pub subcategories: Vec<String>,
+}
+
+// --- Acceso a Datos ---
+
+/// Devuelve todas las categorías ordenadas alfabéticamente, con sus subcategorías.
+pub fn list_categories(conn: &Connection) -> Result<Vec<Category>, AppError> {
+    let mut stmt = conn
+        .prepare("SELECT name, parent FROM categories ORDER BY parent, name")
+        .map_err(db_error)?;
+    let rows = stmt
+        .query_map([], |row| Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?)))
+        .map_err(db_error)?;
+    let rows = rows.collect::<rusqlite::Result<Vec<_>>>().map_err(db_error)?;
+
+    // Las categorías principales (parent = '') aparecen primero por el ORDER BY.
+    let mut categories: Vec<Category> = Vec::new();
+    for (name, parent) in rows {
+        if parent.is_empty() {
+            categories.push(Category { name, subcategories: Vec::new() });
+        } else if let Some(category) = categories.iter_mut().find(|c| c.name == parent) {
+            category.subcategories.push(name);
+        }
+    }
+    Ok(categories)
+}
+
+fn category_exists(conn: &Connection, name: &str, parent: &str) -> Result<bool, AppError> {
+    conn.query_row(
+        "SELECT EXISTS(SELECT 1 FROM categories WHERE name = ?1 AND parent = ?2)",
+        params![name, parent],
+        |row| row.get::<_, bool>(0),
+    )
+    .map_err(db_error)
+}
+
+/// Convierte cadenas vacías en `None` y recorta espacios.
+fn normalize(value: Option<String>) -> Option<String> {
+    value
+        .map(|v| v.trim().to_owned())
+        .filter(|v| !v.is_empty())
+}
+
+/// Rechaza renombrar o borrar una categoría (o una subcategoría de `parent`) que
+/// tiene transacciones, o partes de una transacción repartida, en un mes cerrado.
+fn ensure_category_not_closed(conn: &Connection, name: &str, parent: &str) -> Result<(), AppError> {
+    if parent.is_empty() {
+        closing::ensure_not_closed(
+            conn,
+            "(category = ?1 OR t.id IN (SELECT transaction_id FROM transaction_splits WHERE category = ?1))",
+            params![name],
+            &format!("La categoría '{}'", name),
+        )
+    } else {
+        closing::ensure_not_closed(
+            conn,
+            "((category = ?1 AND subcategory = ?2) OR t.id IN (SELECT transaction_id FROM transaction_splits \
+             WHERE category = ?1 AND subcategory = ?2))",
+            params![parent, name],
+            &format!("La subcategoría '{}'", name),
+        )
+    }
+}
+
+/// Valida la categoría y subcategoría indicadas para una transacción.
+/// Ambas deben existir y la subcategoría solo se admite junto con su categoría.
+pub fn resolve_transaction_category(
+    conn: &Connection,
+    category: Option<String>,
+    subcategory: Option<String>,
+) -> Result<(Option<String>, Option<String>), AppError> {
+    let category = normalize(category);
+    let subcategory = normalize(subcategory);
+
+    match (&category, &subcategory) {
+        (None, None) => {}
+        (None, Some(_)) => {
+            error!("Subcategory provided without category.");
+            return Err(AppError::invalid_field("subcategory", "No se puede asignar una subcategoría sin categoría."));
+        }
+        (Some(cat), sub) => {
+            if !category_exists(conn, cat, "")? {
+                error!("Unknown category: {}", cat);
+                return Err(AppError::field_error("category", validation::NOT_FOUND, format!("La categoría '{}' no existe.", cat)));
+            }
+            if let Some(sub) = sub {
+                if !category_exists(conn, sub, cat)? {
+                    error!("Unknown subcategory '{}' for category '{}'", sub, cat);
+                    return Err(AppError::field_error(
+                        "subcategory",
+                        validation::NOT_FOUND,
+                        format!("La subcategoría '{}' no existe en '{}'.", sub, cat),
+                    ));
+                }
+            }
+        }
+    }
+    Ok((category, subcategory))
+}
+
+// --- Comandos Tauri ---
+
+/// Comando para obtener el árbol de categorías.
+#[tauri::command]
+pub async fn get_categories(state: State<'_, AppState>) -> Result<Vec<Category>, AppError> {
+    debug!("Received get_categories command.");
+    let db = state.db().await?;
+    list_categories(db.connection())
+}
+
+/// Comando para añadir una categoría, o una subcategoría si se indica `parent`.
+#[tauri::command]
+pub async fn add_category_command(
+    state: State<'_, AppState>,
+    app: AppHandle,
+    name: String,
+    parent: Option<String>,
+) -> Result<(), AppError> {
+    debug!("Received add_category_command: name='{}', parent={:?}", name, parent);
+    let name = name.trim();
+    let parent = normalize(parent).unwrap_or_default();
+
+    if name.is_empty() {
+        error!("Add category: Empty name.");
+        return Err(AppError::invalid_field("name", "El nombre de la categoría no puede estar vacío."));
+    }
+
+    let db = state.db().await?;
+    let conn = db.connection();
+
+    if !parent.is_empty() && !category_exists(conn, &parent, "")? {
+        error!("Add category: Parent '{}' not found.", parent);
+        return Err(AppError::invalid_field("parent", format!("La categoría '{}' no existe.", parent)));
+    }
+    if category_exists(conn, name, &parent)? {
+        error!("Add category: '{}' already exists.", name);
+        return Err(AppError::Conflict(format!("La categoría '{}' ya existe.", name)));
+    }
+
+    conn.execute(
+        "INSERT INTO categories (name, parent) VALUES (?1, ?2)",
+        params![name, parent],
+    )
+    .map_err(db_error)?;
+    audit::record(conn, "add_category_command", Some(name), None, Some(json!({"name": name, "parent": parent})));
+    events::emit_entity(&app, events::CATEGORY_CHANGED_EVENT, ChangeAction::Created, name);
+    debug!("Category '{}' added under '{}'.", name, parent);
+    Ok(())
+}
+
+/// Comando para renombrar una categoría (o subcategoría) y actualizar sus transacciones.
+#[tauri::command]
+pub async fn rename_category_command(
+    state: State<'_, AppState>,
+    app: AppHandle,
+    old_name: String,
+    new_name: String,
+    parent: Option<String>,
+) -> Result<(), AppError> {
+    debug!("Received rename_category_command: old='{}', new='{}', parent={:?}", old_name, new_name, parent);
+    let old_name = old_name.trim();
+    let new_name = new_name.trim();
+    let parent = normalize(parent).unwrap_or_default();
+
+    if old_name.is_empty() || new_name.is_empty() {
+        error!("Rename category: Empty old or new name.");
+        return Err(AppError::validation("Los nombres de categoría no pueden estar vacíos."));
+    }
+    if old_name == new_name {
+        return Err(AppError::invalid_field("new_name", "El nuevo nombre de la categoría es el mismo que el anterior."));
+    }
+
+    let db = state.db().await?;
+    let conn = db.connection();
+
+    if !category_exists(conn, old_name, &parent)? {
+        error!("Rename category: '{}' not found.", old_name);
+        return Err(AppError::NotFound(format!("Categoría '{}' no encontrada.", old_name)));
+    }
+    if category_exists(conn, new_name, &parent)? {
+        error!("Rename category: '{}' already exists.", new_name);
+        return Err(AppError::Conflict(format!("La categoría '{}' ya existe.", new_name)));
+    }
+    ensure_category_not_closed(conn, old_name, &parent)?;
+
+    let tx = conn.unchecked_transaction().map_err(db_error)?;
+    tx.execute(
+        "UPDATE categories SET name = ?1 WHERE name = ?2 AND parent = ?3",
+        params![new_name, old_name, parent],
+    )
+    .map_err(db_error)?;
+    let renamed_count = if parent.is_empty() {
+        tx.execute("UPDATE categories SET parent = ?1 WHERE parent = ?2", params![new_name, old_name])
+            .map_err(db_error)?;
+        tx.execute("UPDATE transaction_splits SET category = ?1 WHERE category = ?2", params![new_name, old_name])
+            .map_err(db_error)?;
+        tx.execute("UPDATE transactions SET category = ?1 WHERE category = ?2", params![new_name, old_name])
+            .map_err(db_error)?
+    } else {
+        tx.execute(
+            "UPDATE transaction_splits SET subcategory = ?1 WHERE category = ?2 AND subcategory = ?3",
+            params![new_name, parent, old_name],
+        )
+        .map_err(db_error)?;
+        tx.execute(
+            "UPDATE transactions SET subcategory = ?1 WHERE category = ?2 AND subcategory = ?3",
+            params![new_name, parent, old_name],
+        )
+        .map_err(db_error)?
+    };
+    tx.commit().map_err(db_error)?;
+    audit::record(
+        conn,
+        "rename_category_command",
+        Some(old_name),
+        Some(json!({"name": old_name, "parent": parent})),
+        Some(json!({"name": new_name, "parent": parent, "transactions_updated": renamed_count})),
+    );
+
+    // Se cambiaron transacciones directamente en SQL, sin pasar por `Change`.
+    aggregates::invalidate();
+    events::emit_entity(&app, events::CATEGORY_CHANGED_EVENT, ChangeAction::Updated, new_name);
+    debug!("Renamed category '{}' to '{}' ({} transactions updated).", old_name, new_name, renamed_count);
+    Ok(())
+}
+
+/// Comando para eliminar una categoría (o subcategoría).
+/// Las transacciones asociadas se conservan, pero quedan sin categoría.
+#[tauri::command]
+pub async fn delete_category_command(
+    state: State<'_, AppState>,
+    app: AppHandle,
+    name: String,
+    parent: Option<String>,
+) -> Result<(), AppError> {
+    debug!("Received delete_category_command: name='{}', parent={:?}", name, parent);
+    let name = name.trim();
+    let parent = normalize(parent).unwrap_or_default();
+
+    let db = state.db().await?;
+    let conn = db.connection();
+
+    if !category_exists(conn, name, &parent)? {
+        error!("Delete category: '{}' not found.", name);
+        return Err(AppError::NotFound(format!("Categoría '{}' no encontrada.", name)));
+    }
+    ensure_category_not_closed(conn, name, &parent)?;
+
+    let tx = conn.unchecked_transaction().map_err(db_error)?;
+    if parent.is_empty() {
+        tx.execute("DELETE FROM categories WHERE (name = ?1 AND parent = '') OR parent = ?1", params![name])
+            .map_err(db_error)?;
+        tx.execute(
+            "UPDATE transactions SET category = NULL, subcategory = NULL WHERE category = ?1",
+            params![name],
+        )
+        .map_err(db_error)?;
+        tx.execute(
+            "UPDATE transaction_splits SET category = NULL, subcategory = NULL WHERE category = ?1",
+            params![name],
+        )
+        .map_err(db_error)?;
+    } else {
+        tx.execute("DELETE FROM categories WHERE name = ?1 AND parent = ?2", params![name, parent])
+            .map_err(db_error)?;
+        tx.execute(
+            "UPDATE transactions SET subcategory = NULL WHERE category = ?1 AND subcategory = ?2",
+            params![parent, name],
+        )
+        .map_err(db_error)?;
+        tx.execute(
+            "UPDATE transaction_splits SET subcategory = NULL WHERE category = ?1 AND subcategory = ?2",
+            params![parent, name],
+        )
+        .map_err(db_error)?;
+    }
+    tx.commit().map_err(db_error)?;
+    audit::record(conn, "delete_category_command", Some(name), Some(json!({"name": name, "parent": parent})), None);
+
+    aggregates::invalidate();
+    events::emit_entity(&app, events::CATEGORY_CHANGED_EVENT, ChangeAction::Deleted, name);
+    debug!("Category '{}' deleted.", name);
+    Ok(())
+}
@@ -0,0 +1,251 @@
+// src-tauri/src/chat.rs
+
+use rusqlite::{params, Connection, OptionalExtension};
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, State};
+use log::{debug, error, info};
+
+use crate::ai::{self, AiFeature, AiMessage, GenerationParams};
+use crate::error::AppError;
+use crate::periods;
+use crate::settings;
+use crate::shutdown;
+use crate::storage::db_error;
+use crate::AppState;
+
+/// Número máximo de mensajes anteriores que se reenvían a la IA como contexto.
+const MAX_HISTORY_MESSAGES: usize = 40;
+
+/// Autor de un mensaje, con los mismos nombres de rol que usa la API de Gemini.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ChatRole {
+    User,
+    Model,
+}
+
+impl ChatRole {
+    fn as_str(self) -> &'static str {
+        match self {
+            ChatRole::User => "user",
+            ChatRole::Model => "model",
+        }
+    }
+}
+
+/// Conversación con la IA que conserva el contexto entre mensajes.
+#[derive(Debug, Clone, Serialize)]
+pub struct ChatSession {
+    pub id: String,
+    pub title: String,
+    pub created_at: u64,
+    pub updated_at: u64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ChatMessage {
+    pub role: ChatRole,
+    pub text: String,
+    pub timestamp: u64,
+}
+
+// --- Acceso a Datos ---
+
+fn get_session(conn: &Connection, session_id: &str) -> Result<Option<ChatSession>, AppError> {
+    conn.query_row(
+        "SELECT id, title, created_at, updated_at FROM chat_sessions WHERE id = ?1",
+        params![session_id],
+        |row| {
+            Ok(ChatSession {
+                id: row.get(0)?,
+                title: row.get(1)?,
+                created_at: row.get::<_, i64>(2)? as u64,
+                updated_at: row.get::<_, i64>(3)? as u64,
+            })
+        },
+    )
+    .optional()
+    .map_err(db_error)
+}
+
+pub fn list_sessions(conn: &Connection) -> Result<Vec<ChatSession>, AppError> {
+    let mut stmt = conn
+        .prepare("SELECT id, title, created_at, updated_at FROM chat_sessions ORDER BY updated_at DESC")
+        .map_err(db_error)?;
+    let rows = stmt
+        .query_map([], |row| {
+            Ok(ChatSession {
+                id: row.get(0)?,
+                title: row.get(1)?,
+                created_at: row.get::<_, i64>(2)? as u64,
+                updated_at: row.get::<_, i64>(3)? as u64,
+            })
+        })
+        .map_err(db_error)?;
+    rows.collect::<rusqlite::Result<Vec<_>>>().map_err(db_error)
+}
+
+pub fn load_history(conn: &Connection, session_id: &str) -> Result<Vec<ChatMessage>, AppError> {
+    let mut stmt = conn
+        .prepare("SELECT role, text, timestamp FROM chat_messages WHERE session_id = ?1 ORDER BY id")
+        .map_err(db_error)?;
+    let rows = stmt
+        .query_map(params![session_id], |row| {
+            let role: String = row.get(0)?;
+            Ok(ChatMessage {
+                role: if role == "model" { ChatRole::Model } else { ChatRole::User },
+                text: row.get(1)?,
+                timestamp: row.get::<_, i64>(2)? as u64,
+            })
+        })
+        .map_err(db_error)?;
+    rows.collect::<rusqlite::Result<Vec<_>>>().map_err(db_error)
+}
+
+fn append_messages(conn: &Connection, session_id: &str, messages: &[ChatMessage]) -> Result<(), AppError> {
+    let tx = conn.unchecked_transaction().map_err(db_error)?;
+    for message in messages {
+        tx.execute(
+            "INSERT INTO chat_messages (session_id, role, text, timestamp) VALUES (?1, ?2, ?3, ?4)",
+            params![session_id, message.role.as_str(), message.text, message.timestamp as i64],
+        )
+        .map_err(db_error)?;
+    }
+    tx.execute(
+        "UPDATE chat_sessions SET updated_at = ?1 WHERE id = ?2",
+        params![periods::now_timestamp() as i64, session_id],
+    )
+    .map_err(db_error)?;
+    tx.commit().map_err(db_error)
+}
+
+/// Convierte el historial en mensajes para la IA, limitado a los últimos mensajes.
+fn history_to_messages(history: &[ChatMessage]) -> Vec<AiMessage> {
+    let start = history.len().saturating_sub(MAX_HISTORY_MESSAGES);
+    history[start..]
+        .iter()
+        .map(|m| match m.role {
+            ChatRole::User => AiMessage::user(m.text.clone()),
+            ChatRole::Model => AiMessage::model(m.text.clone()),
+        })
+        .collect()
+}
+
+// --- Comandos Tauri ---
+
+/// Comando para iniciar una nueva sesión de chat.
+#[tauri::command]
+pub async fn start_chat_session_command(
+    state: State<'_, AppState>,
+    title: Option<String>,
+) -> Result<ChatSession, AppError> {
+    debug!("Received start_chat_session_command.");
+    let now = periods::now_timestamp();
+    let session = ChatSession {
+        id: uuid::Uuid::new_v4().to_string(),
+        title: title
+            .map(|t| t.trim().to_owned())
+            .filter(|t| !t.is_empty())
+            .unwrap_or_else(|| "Nueva conversación".to_string()),
+        created_at: now,
+        updated_at: now,
+    };
+    state.db().await?
+        .connection()
+        .execute(
+            "INSERT INTO chat_sessions (id, title, created_at, updated_at) VALUES (?1, ?2, ?3, ?4)",
+            params![session.id, session.title, now as i64, now as i64],
+        )
+        .map_err(db_error)?;
+    info!("Chat session started: {}", session.id);
+    Ok(session)
+}
+
+/// Comando para enviar un mensaje dentro de una sesión. Se reenvía el historial a
+/// la IA para mantener el contexto y la respuesta se emite en streaming con
+/// `request_id` = `session_id`. Ambos mensajes se guardan solo si la IA responde.
+#[tauri::command]
+pub async fn send_chat_message_command(
+    state: State<'_, AppState>,
+    app: AppHandle,
+    session_id: String,
+    message: String,
+) -> Result<ChatMessage, AppError> {
+    debug!("Received send_chat_message_command for session {}", session_id);
+    let message = message.trim().to_owned();
+    if message.is_empty() {
+        error!("Empty chat message.");
+        return Err(AppError::invalid_field("message", "El mensaje no puede estar vacío."));
+    }
+
+    // La respuesta y la pregunta se guardan juntas al final: que no se pierdan al salir.
+    let _in_flight = shutdown::track("chat_message");
+    let (history, client) = {
+        let db = state.db().await?;
+        if get_session(db.connection(), &session_id)?.is_none() {
+            error!("Chat session {} not found.", session_id);
+            return Err(AppError::NotFound(format!("Sesión de chat {} no encontrada.", session_id)));
+        }
+        let settings = settings::load_settings(&db);
+        ai::ensure_enabled(&settings, Some(AiFeature::Chat))?;
+        (load_history(db.connection(), &session_id)?, settings.ai_client())
+    };
+
+    let user_message = ChatMessage {
+        role: ChatRole::User,
+        text: message,
+        timestamp: periods::now_timestamp(),
+    };
+    let mut messages = history_to_messages(&history);
+    messages.push(AiMessage::user(user_message.text.clone()));
+
+    let reply_text = ai::stream_to_frontend(&app, &state, &session_id, &client, &messages, &GenerationParams::default()).await?;
+    let reply = ChatMessage {
+        role: ChatRole::Model,
+        text: reply_text,
+        timestamp: periods::now_timestamp(),
+    };
+
+    {
+        let db = state.db().await?;
+        append_messages(db.connection(), &session_id, &[user_message, reply.clone()])?;
+    }
+    debug!("Chat session {} now has {} messages.", session_id, history.len() + 2);
+    Ok(reply)
+}
+
+/// Comando para obtener el historial completo de una sesión.
+#[tauri::command]
+pub async fn get_chat_history_command(
+    state: State<'_, AppState>,
+    session_id: String,
+) -> Result<Vec<ChatMessage>, AppError> {
+    debug!("Received get_chat_history_command for session {}", session_id);
+    let db = state.db().await?;
+    if get_session(db.connection(), &session_id)?.is_none() {
+        return Err(AppError::NotFound(format!("Sesión de chat {} no encontrada.", session_id)));
+    }
+    load_history(db.connection(), &session_id)
+}
+
+/// Comando para listar las sesiones de chat, de la más reciente a la más antigua.
+#[tauri::command]
+pub async fn list_chat_sessions_command(state: State<'_, AppState>) -> Result<Vec<ChatSession>, AppError> {
+    debug!("Received list_chat_sessions_command.");
+    let db = state.db().await?;
+    list_sessions(db.connection())
+}
+
+/// Comando para eliminar una sesión de chat y sus mensajes.
+#[tauri::command]
+pub async fn delete_chat_session_command(state: State<'_, AppState>, session_id: String) -> Result<(), AppError> {
+    debug!("Received delete_chat_session_command for session {}", session_id);
+    let changed = state.db().await?
+        .connection()
+        .execute("DELETE FROM chat_sessions WHERE id = ?1", params![session_id])
+        .map_err(db_error)?;
+    if changed == 0 {
+        return Err(AppError::NotFound(format!("Sesión de chat {} no encontrada.", session_id)));
+    }
+    Ok(())
+}
@@ -0,0 +1,206 @@
+// src-tauri/src/cli.rs
+
+use chrono::{Duration, NaiveDate};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use log::info;
+
+use crate::backup;
+use crate::currencies::RateTable;
+use crate::email;
+use crate::encryption;
+use crate::error::AppError;
+use crate::i18n;
+use crate::import::{self, ColumnMapping};
+use crate::periods::{self, Period};
+use crate::settings;
+use crate::storage::{self, SqliteStorage, TransactionRepository};
+use crate::summaries;
+use crate::workspaces;
+
+// Modo sin ventana para scripts y tareas programadas del sistema (cron, Programador de
+// tareas): `contabilidad --export csv --from 2024-01`, `--import extracto.csv` o
+// `--backup`. Abre la misma base de datos que la aplicación y termina sin mostrar
+// ninguna ventana.
+
+/// Variable de entorno con la contraseña de los datos cifrados.
+const PASSPHRASE_ENV: &str = "CONTABILIDAD_PASSPHRASE";
+
+const USAGE: &str = "Uso:
+  contabilidad --export csv [--from AAAA-MM[-DD]] [--to AAAA-MM[-DD]] [--output ARCHIVO]
+  contabilidad --import ARCHIVO [--store TIENDA] [--sheet HOJA]
+  contabilidad --backup
+  contabilidad --help
+
+Sin --output, el CSV se escribe en la salida estándar. --import admite extractos
+OFX/QFX, QIF, Excel (.xlsx) y CSV. Si los datos están cifrados, la contraseña se lee
+de la variable de entorno CONTABILIDAD_PASSPHRASE.";
+
+/// Operación pedida en la línea de comandos.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CliCommand {
+    /// Transacciones de `[from, to)` en CSV, en `output` o en la salida estándar.
+    Export { from: Option<NaiveDate>, to: Option<NaiveDate>, output: Option<PathBuf> },
+    Import { path: PathBuf, store: String, sheet: Option<String> },
+    Backup,
+    Help,
+}
+
+/// Fecha `AAAA-MM-DD` o mes `AAAA-MM`. Un mes es su primer día o, con `end_of_month`,
+/// el primer día del mes siguiente, para que `--to 2024-03` incluya todo marzo.
+fn parse_date_arg(option: &str, value: &str, end_of_month: bool) -> Result<NaiveDate, AppError> {
+    if let Ok(date) = NaiveDate::parse_from_str(value.trim(), "%Y-%m-%d") {
+        return Ok(if end_of_month { date + Duration::days(1) } else { date });
+    }
+    let start = summaries::parse_month(value).map_err(|_| {
+        AppError::invalid_field(
+            option.trim_start_matches('-'),
+            format!("{} debe ser un mes AAAA-MM o una fecha AAAA-MM-DD.", option),
+        )
+    })?;
+    Ok(if end_of_month { Period::Mensual.next_start_date(start) } else { start })
+}
+
+/// Interpreta los argumentos (sin el nombre del programa). Devuelve `None` si no
+/// piden ninguna operación de la línea de comandos y hay que abrir la aplicación.
+pub fn parse_args(args: &[String]) -> Result<Option<CliCommand>, AppError> {
+    let Some(action) = args.first() else { return Ok(None) };
+    if !matches!(action.as_str(), "--export" | "--import" | "--backup" | "--help" | "-h") {
+        return Ok(None);
+    }
+
+    let mut options: Vec<(&str, &str)> = Vec::new();
+    let mut rest = args.iter().skip(1);
+    let mut positional = None;
+    while let Some(arg) = rest.next() {
+        if !arg.starts_with("--") {
+            if positional.is_some() {
+                return Err(AppError::validation(format!("Argumento inesperado: {}", arg)));
+            }
+            positional = Some(arg.as_str());
+            continue;
+        }
+        let value = rest.next().ok_or_else(|| AppError::validation(format!("Falta el valor de {}.", arg)))?;
+        options.push((arg.as_str(), value.as_str()));
+    }
+    let allowed: &[&str] = match action.as_str() {
+        "--export" => &["--from", "--to", "--output"],
+        "--import" => &["--store", "--sheet"],
+        _ => &[],
+    };
+    if let Some((unknown, _)) = options.iter().find(|(name, _)| !allowed.contains(name)) {
+        return Err(AppError::validation(format!("Opción no válida para {}: {}", action, unknown)));
+    }
+    let option = |name: &str| options.iter().rev().find(|(n, _)| *n == name).map(|(_, v)| *v);
+
+    let command = match action.as_str() {
+        "--export" => {
+            match positional {
+                Some(format) if format.eq_ignore_ascii_case("csv") => {}
+                Some(format) => {
+                    return Err(AppError::invalid_field("format", format!("Formato de exportación no admitido: {}.", format)))
+                }
+                None => return Err(AppError::invalid_field("format", "Indica el formato: --export csv.")),
+            }
+            CliCommand::Export {
+                from: option("--from").map(|v| parse_date_arg("--from", v, false)).transpose()?,
+                to: option("--to").map(|v| parse_date_arg("--to", v, true)).transpose()?,
+                output: option("--output").map(PathBuf::from),
+            }
+        }
+        "--import" => CliCommand::Import {
+            path: positional
+                .map(PathBuf::from)
+                .ok_or_else(|| AppError::invalid_field("path", "Indica el archivo que quieres importar."))?,
+            store: option("--store").unwrap_or(import::DEFAULT_IMPORT_STORE).to_owned(),
+            sheet: option("--sheet").map(str::to_owned),
+        },
+        "--backup" if positional.is_none() => CliCommand::Backup,
+        "--backup" => return Err(AppError::validation("--backup no admite argumentos.")),
+        _ => CliCommand::Help,
+    };
+    Ok(Some(command))
+}
+
+/// Abre la base de datos del espacio de trabajo activo, como al arrancar la
+/// aplicación. Los datos cifrados se abren con la contraseña de `PASSPHRASE_ENV`.
+fn open_database() -> Result<SqliteStorage, AppError> {
+    workspaces::activate_saved_workspace();
+    let path = storage::get_database_path();
+    let backup_path = storage::get_backup_path();
+    let (db, recovery) = if encryption::is_encrypted_file(&path) {
+        let passphrase = std::env::var(PASSPHRASE_ENV).map_err(|_| {
+            AppError::validation(format!("Los datos están cifrados: indica la contraseña en la variable {}.", PASSPHRASE_ENV))
+        })?;
+        storage::open_encrypted_with_recovery(&path, &backup_path, &passphrase)?
+    } else {
+        storage::open_with_recovery(&path, &backup_path)?
+    };
+    if let Some(report) = recovery {
+        eprintln!("La base de datos estaba dañada y se ha restaurado desde la copia de seguridad: {:?}", report);
+    }
+    i18n::apply_settings(&db);
+    Ok(db)
+}
+
+fn export_csv(db: &SqliteStorage, from: Option<NaiveDate>, to: Option<NaiveDate>, output: Option<&Path>) -> Result<(), AppError> {
+    let from = from.map(periods::local_midnight_timestamp).unwrap_or(0);
+    let to = to.map(periods::local_midnight_timestamp).unwrap_or(u64::MAX);
+    let mut transactions: Vec<_> =
+        db.list_transactions()?.into_iter().filter(|t| t.timestamp >= from && t.timestamp < to).collect();
+    transactions.sort_by_key(|t| t.timestamp);
+    let csv = email::render_csv(&transactions, &RateTable::load(db)?, settings::load_settings(db).language())?;
+    match output {
+        Some(path) => {
+            storage::write_atomic(path, &csv)?;
+            eprintln!("{} transacciones exportadas a {}.", transactions.len(), path.display());
+        }
+        None => std::io::stdout()
+            .write_all(&csv)
+            .map_err(|e| AppError::Io(format!("Error al escribir el CSV: {}", e)))?,
+    }
+    info!("CLI export: {} transactions.", transactions.len());
+    Ok(())
+}
+
+fn import_file(db: &SqliteStorage, path: &Path, store: &str, sheet: Option<&str>) -> Result<(), AppError> {
+    let (format, entries) = import::read_file(path, sheet, &ColumnMapping::default())?;
+    let (summary, _) = import::import_into(db, format, &entries, store, "cli_import")?;
+    println!(
+        "{} movimientos importados en '{}', {} ya estaban importados, {} posibles duplicados.",
+        summary.imported,
+        store.trim(),
+        summary.skipped_existing,
+        summary.possible_duplicates.len()
+    );
+    info!("CLI import of {}: {} new.", path.display(), summary.imported);
+    Ok(())
+}
+
+/// Ejecuta la operación y devuelve el código de salida del proceso.
+pub fn run(command: CliCommand) -> i32 {
+    if command == CliCommand::Help {
+        println!("{}", USAGE);
+        return 0;
+    }
+    let result = open_database().and_then(|db| {
+        match &command {
+            CliCommand::Export { from, to, output } => export_csv(&db, *from, *to, output.as_deref())?,
+            CliCommand::Import { path, store, sheet } => import_file(&db, path, store, sheet.as_deref())?,
+            CliCommand::Backup => {
+                let backup = backup::create_backup(&db, backup::REASON_MANUAL)?;
+                println!("Copia de seguridad creada: {}", backup.id);
+            }
+            CliCommand::Help => {}
+        }
+        // Los datos cifrados se escriben al terminar, como al cerrar la aplicación.
+        db.flush()
+    });
+    match result {
+        Ok(()) => 0,
+        Err(e) => {
+            eprintln!("Error: {}", e);
+            1
+        }
+    }
+}
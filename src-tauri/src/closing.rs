@@ -0,0 +1,275 @@
+// src-tauri/src/closing.rs
+
+use chrono::NaiveDate;
+use rusqlite::{params, Connection, OptionalExtension, Params, Row};
+use serde::Serialize;
+use tauri::{AppHandle, State};
+use log::{debug, info};
+
+use crate::audit;
+use crate::error::AppError;
+use crate::events::{self, ChangeAction};
+use crate::periods;
+use crate::storage::db_error;
+use crate::AppState;
+
+/// Mes cerrado: sus transacciones activas ya no se pueden crear, modificar ni borrar.
+/// El bloqueo lo aplican los triggers de la migración v24.
+#[derive(Debug, Clone, Serialize)]
+pub struct ClosedPeriod {
+    /// `AAAA-MM`.
+    pub month: String,
+    pub closed_at: u64,
+    pub actor: String,
+}
+
+/// Valida el año y el mes y devuelve la clave `AAAA-MM`.
+fn month_key(year: i32, month: u32) -> Result<String, AppError> {
+    let date = NaiveDate::from_ymd_opt(year, month, 1)
+        .ok_or_else(|| AppError::invalid_field("month", "El mes debe estar entre 1 y 12."))?;
+    Ok(date.format("%Y-%m").to_string())
+}
+
+fn row_to_closed_period(row: &Row) -> rusqlite::Result<ClosedPeriod> {
+    Ok(ClosedPeriod {
+        month: row.get(0)?,
+        closed_at: row.get::<_, i64>(1)? as u64,
+        actor: row.get(2)?,
+    })
+}
+
+fn get_closed_period(conn: &Connection, month: &str) -> Result<Option<ClosedPeriod>, AppError> {
+    conn.query_row(
+        "SELECT month, closed_at, actor FROM closed_periods WHERE month = ?1",
+        params![month],
+        row_to_closed_period,
+    )
+    .optional()
+    .map_err(db_error)
+}
+
+pub fn list_closed_periods(conn: &Connection) -> Result<Vec<ClosedPeriod>, AppError> {
+    let mut stmt = conn
+        .prepare("SELECT month, closed_at, actor FROM closed_periods ORDER BY month")
+        .map_err(db_error)?;
+    let rows = stmt.query_map([], row_to_closed_period).map_err(db_error)?;
+    rows.collect::<rusqlite::Result<Vec<_>>>().map_err(db_error)
+}
+
+/// Comprueba, antes de un cambio en bloque como renombrar una categoría o una tienda,
+/// que ninguna transacción activa que cumple `filter` (una condición SQL sobre
+/// `transactions`) cae en un mes cerrado o en un día con la caja cerrada (ver
+/// `cash_closing`). Los triggers rechazarían el cambio igualmente, pero así el error
+/// dice qué hay que reabrir. `subject` es lo que se quiere cambiar, p. ej. "La
+/// categoría 'Comida'".
+pub fn ensure_not_closed(conn: &Connection, filter: &str, params: impl Params, subject: &str) -> Result<(), AppError> {
+    let closed: Option<(String, bool)> = conn
+        .query_row(
+            &format!(
+                "SELECT p.month, 0 FROM transactions t JOIN closed_periods p ON p.month = substr(t.transaction_date, 1, 7)
+                 WHERE t.deleted_at IS NULL AND ({0})
+                 UNION ALL
+                 SELECT c.date, 1 FROM transactions t JOIN cash_closings c ON c.date = t.transaction_date
+                 WHERE t.deleted_at IS NULL AND ({0})
+                 ORDER BY 1 LIMIT 1",
+                filter
+            ),
+            params,
+            |row| Ok((row.get(0)?, row.get(1)?)),
+        )
+        .optional()
+        .map_err(db_error)?;
+    match closed {
+        Some((month, false)) => Err(AppError::PeriodClosed(format!(
+            "{} tiene transacciones en {}, que está cerrado. Reábrelo para poder cambiarlas.",
+            subject, month
+        ))),
+        Some((date, true)) => Err(AppError::PeriodClosed(format!(
+            "{} tiene transacciones del {}, que tiene la caja cerrada. Reabre el cierre para poder cambiarlas.",
+            subject, date
+        ))),
+        None => Ok(()),
+    }
+}
+
+// --- Comandos Tauri ---
+
+/// Comando para obtener los meses cerrados, del más antiguo al más reciente.
+#[tauri::command]
+pub async fn list_closed_periods_command(state: State<'_, AppState>) -> Result<Vec<ClosedPeriod>, AppError> {
+    debug!("Received list_closed_periods_command.");
+    let db = state.db().await?;
+    list_closed_periods(db.connection())
+}
+
+/// Comando para cerrar un mes, como en el cierre contable: a partir de ahora crear,
+/// modificar o eliminar transacciones con fecha en ese mes devuelve un error
+/// `period_closed`. No se puede cerrar el mes en curso ni uno futuro.
+#[tauri::command]
+pub async fn close_period_command(
+    state: State<'_, AppState>,
+    app: AppHandle,
+    year: i32,
+    month: u32,
+) -> Result<ClosedPeriod, AppError> {
+    debug!("Received close_period_command: {}-{}", year, month);
+    let key = month_key(year, month)?;
+    if key >= periods::today().format("%Y-%m").to_string() {
+        return Err(AppError::invalid_field("month", "Solo se pueden cerrar meses ya terminados."));
+    }
+
+    let db = state.db().await?;
+    if get_closed_period(db.connection(), &key)?.is_some() {
+        return Err(AppError::Conflict(format!("El mes {} ya está cerrado.", key)));
+    }
+    let closed = ClosedPeriod { month: key, closed_at: periods::now_timestamp(), actor: audit::current_actor() };
+    db.connection()
+        .execute(
+            "INSERT INTO closed_periods (month, closed_at, actor) VALUES (?1, ?2, ?3)",
+            params![closed.month, closed.closed_at as i64, closed.actor],
+        )
+        .map_err(db_error)?;
+    audit::record(db.connection(), "close_period_command", Some(&closed.month), None, audit::snapshot(&closed));
+    events::emit_entity(&app, events::PERIOD_CHANGED_EVENT, ChangeAction::Created, &closed.month);
+    info!("Period {} closed.", closed.month);
+    Ok(closed)
+}
+
+/// Comando para reabrir un mes cerrado. Como deshace el cierre, exige `confirm`; sin
+/// él devuelve un error de validación con el aviso que el frontend debe mostrar.
+#[tauri::command]
+pub async fn reopen_period_command(
+    state: State<'_, AppState>,
+    app: AppHandle,
+    year: i32,
+    month: u32,
+    confirm: Option<bool>,
+) -> Result<(), AppError> {
+    debug!("Received reopen_period_command: {}-{} (confirm: {:?})", year, month, confirm);
+    let key = month_key(year, month)?;
+    let db = state.db().await?;
+    let closed = get_closed_period(db.connection(), &key)?
+        .ok_or_else(|| AppError::NotFound(format!("El mes {} no está cerrado.", key)))?;
+    if !confirm.unwrap_or(false) {
+        return Err(AppError::invalid_field(
+            "confirm",
+            format!("Al reabrir {} se podrán volver a modificar sus transacciones. Confirma para continuar.", key),
+        ));
+    }
+
+    db.connection()
+        .execute("DELETE FROM closed_periods WHERE month = ?1", params![key])
+        .map_err(db_error)?;
+    audit::record(db.connection(), "reopen_period_command", Some(&key), audit::snapshot(&closed), None);
+    events::emit_entity(&app, events::PERIOD_CHANGED_EVENT, ChangeAction::Deleted, &key);
+    info!("Period {} reopened.", key);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    use crate::contacts;
+    use crate::storage::{SqliteStorage, TransactionRepository};
+    use crate::Transaction;
+
+    fn transaction(id: &str, date: &str, amount: &str) -> Transaction {
+        serde_json::from_value(json!({
+            "id": id,
+            "type": "Gasto",
+            "amount": amount,
+            "description": "Compra",
+            "store_name": "Tienda",
+            "timestamp": 1_705_320_000,
+            "category": "Comida",
+            "transaction_date": date,
+            "created_at": 1_705_320_000,
+            "updated_at": 1_705_320_000,
+        }))
+        .unwrap()
+    }
+
+    /// Base de datos con una transacción del contacto `c1` en enero de 2024, ya cerrado.
+    fn closed_january() -> SqliteStorage {
+        let db = SqliteStorage::open_in_memory().unwrap();
+        db.connection()
+            .execute(
+                "INSERT INTO contacts (id, name, kind, created_at, updated_at) VALUES ('c1', 'Cliente', 'Cliente', 0, 0)",
+                [],
+            )
+            .unwrap();
+        let mut t = transaction("t1", "2024-01-15", "12.50");
+        t.contact_id = Some("c1".to_string());
+        db.insert_transaction(&t).unwrap();
+        db.connection()
+            .execute("INSERT INTO closed_periods (month, closed_at, actor) VALUES ('2024-01', 0, 'test')", [])
+            .unwrap();
+        db
+    }
+
+    #[test]
+    fn closed_month_allows_deleting_a_contact_used_in_it() {
+        let db = closed_january();
+        contacts::delete_contact(db.connection(), "c1").unwrap();
+        assert_eq!(db.get_transaction("t1").unwrap().unwrap().contact_id, None);
+    }
+
+    #[test]
+    fn closed_month_rejects_changing_the_amount() {
+        let db = closed_january();
+        let mut t = db.get_transaction("t1").unwrap().unwrap();
+        t.amount = "20".parse().unwrap();
+        assert!(matches!(db.update_transaction(&t), Err(AppError::PeriodClosed(_))));
+    }
+
+    #[test]
+    fn closed_month_rejects_inserting_and_moving_in() {
+        let db = closed_january();
+        let inserted = db.insert_transaction(&transaction("t2", "2024-01-20", "5"));
+        assert!(matches!(inserted, Err(AppError::PeriodClosed(_))));
+
+        db.insert_transaction(&transaction("t3", "2024-02-01", "5")).unwrap();
+        let mut t = db.get_transaction("t3").unwrap().unwrap();
+        t.transaction_date = "2024-01-31".parse().unwrap();
+        assert!(matches!(db.update_transaction(&t), Err(AppError::PeriodClosed(_))));
+    }
+
+    #[test]
+    fn ensure_not_closed_names_the_closed_month() {
+        let db = closed_january();
+        let result = ensure_not_closed(db.connection(), "category = ?1", ["Comida"], "La categoría 'Comida'");
+        match result {
+            Err(AppError::PeriodClosed(message)) => assert!(message.contains("2024-01")),
+            other => panic!("expected PeriodClosed, got {:?}", other),
+        }
+        ensure_not_closed(db.connection(), "category = ?1", ["Ropa"], "La categoría 'Ropa'").unwrap();
+    }
+
+    #[test]
+    fn closed_cash_day_locks_the_payment_method_but_not_the_contact() {
+        let db = SqliteStorage::open_in_memory().unwrap();
+        db.insert_transaction(&transaction("t1", "2024-03-04", "8")).unwrap();
+        db.connection()
+            .execute(
+                "INSERT INTO cash_closings (date, opening_cash, cash_income, cash_expenses, expected_cash, counted_cash,
+                                            difference, closed_at, actor)
+                 VALUES ('2024-03-04', '0', '0', '0', '0', '0', '0', 0, 'test')",
+                [],
+            )
+            .unwrap();
+
+        let mut t = db.get_transaction("t1").unwrap().unwrap();
+        t.notes = "Revisada".to_string();
+        assert!(db.update_transaction(&t).unwrap());
+        t.payment_method = crate::payments::PaymentMethod::Efectivo;
+        assert!(matches!(db.update_transaction(&t), Err(AppError::PeriodClosed(_))));
+
+        let result = ensure_not_closed(db.connection(), "store_name = ?1", ["Tienda"], "La tienda 'Tienda'");
+        match result {
+            Err(AppError::PeriodClosed(message)) => assert!(message.contains("2024-03-04")),
+            other => panic!("expected PeriodClosed, got {:?}", other),
+        }
+    }
+}
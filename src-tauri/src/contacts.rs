@@ -0,0 +1,323 @@
+// src-tauri/src/contacts.rs
+
+use rusqlite::{params, Connection, OptionalExtension, Row};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use tauri::{AppHandle, State};
+use log::{debug, error, info};
+
+use crate::audit;
+use crate::currencies::RateTable;
+use crate::error::AppError;
+use crate::events::{self, ChangeAction};
+use crate::periods;
+use crate::reports::{self, GroupTotals};
+use crate::storage::{db_error, TransactionRepository};
+use crate::stores;
+use crate::validation;
+use crate::{AppState, Transaction};
+
+/// Nombre del grupo de transacciones sin cliente en el desglose por cliente.
+const NO_CLIENT: &str = "Sin cliente";
+
+/// Si el contacto es un cliente o un proveedor.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ContactKind {
+    Cliente,
+    Proveedor,
+}
+
+impl ToString for ContactKind {
+    fn to_string(&self) -> String {
+        match self {
+            ContactKind::Cliente => "Cliente".to_string(),
+            ContactKind::Proveedor => "Proveedor".to_string(),
+        }
+    }
+}
+
+/// Cliente o proveedor. Las transacciones y facturas lo referencian por `id`.
+#[derive(Debug, Clone, Serialize)]
+pub struct Contact {
+    pub id: String,
+    pub name: String,
+    /// NIF o CIF, en mayúsculas y sin espacios ni guiones.
+    pub nif: Option<String>,
+    pub email: Option<String>,
+    pub kind: ContactKind,
+    pub created_at: u64,
+    pub updated_at: u64,
+}
+
+/// Datos de `create_contact_command` y `update_contact_command`.
+#[derive(Debug, Clone, Deserialize)]
+pub struct ContactInput {
+    pub name: String,
+    #[serde(default)]
+    pub nif: Option<String>,
+    #[serde(default)]
+    pub email: Option<String>,
+    pub kind: ContactKind,
+}
+
+const CONTACT_COLUMNS: &str = "id, name, nif, email, kind, created_at, updated_at";
+
+fn row_to_contact(row: &Row) -> rusqlite::Result<Contact> {
+    let kind = match row.get::<_, String>(4)?.as_str() {
+        "Proveedor" => ContactKind::Proveedor,
+        _ => ContactKind::Cliente,
+    };
+    Ok(Contact {
+        id: row.get(0)?,
+        name: row.get(1)?,
+        nif: row.get(2)?,
+        email: row.get(3)?,
+        kind,
+        created_at: row.get::<_, i64>(5)? as u64,
+        updated_at: row.get::<_, i64>(6)? as u64,
+    })
+}
+
+pub fn get_contact(conn: &Connection, id: &str) -> Result<Option<Contact>, AppError> {
+    conn.query_row(
+        &format!("SELECT {} FROM contacts WHERE id = ?1", CONTACT_COLUMNS),
+        params![id],
+        row_to_contact,
+    )
+    .optional()
+    .map_err(db_error)
+}
+
+/// Contacto con ese ID; si no existe, error de validación en `field`.
+pub fn require_contact(conn: &Connection, field: &str, id: &str) -> Result<Contact, AppError> {
+    get_contact(conn, id)?.ok_or_else(|| {
+        error!("Contact {} not found.", id);
+        AppError::field_error(field, validation::NOT_FOUND, format!("El contacto con ID {} no existe.", id))
+    })
+}
+
+/// Contactos ordenados por nombre, opcionalmente solo los de un tipo.
+pub fn list_contacts(conn: &Connection, kind: Option<ContactKind>) -> Result<Vec<Contact>, AppError> {
+    let mut stmt = conn
+        .prepare(&format!(
+            "SELECT {} FROM contacts WHERE ?1 IS NULL OR kind = ?1 ORDER BY name COLLATE NOCASE",
+            CONTACT_COLUMNS
+        ))
+        .map_err(db_error)?;
+    let rows = stmt
+        .query_map(params![kind.map(|k| k.to_string())], row_to_contact)
+        .map_err(db_error)?;
+    rows.collect::<rusqlite::Result<Vec<_>>>().map_err(db_error)
+}
+
+/// Valida el `contact_id` de una transacción o factura. Un ID vacío equivale a ninguno.
+pub fn resolve_contact_id(conn: &Connection, contact_id: Option<String>) -> Result<Option<String>, AppError> {
+    match stores::non_empty(contact_id) {
+        Some(id) => Ok(Some(require_contact(conn, "contact_id", &id)?.id)),
+        None => Ok(None),
+    }
+}
+
+/// Comprobación mínima del email: algo antes de la arroba y un dominio con punto.
+/// Borra el contacto `id`. Sus transacciones y facturas se conservan, sin contacto.
+pub fn delete_contact(conn: &Connection, id: &str) -> Result<(), AppError> {
+    let tx = conn.unchecked_transaction().map_err(db_error)?;
+    tx.execute("DELETE FROM contacts WHERE id = ?1", params![id]).map_err(db_error)?;
+    tx.execute("UPDATE transactions SET contact_id = NULL WHERE contact_id = ?1", params![id])
+        .map_err(db_error)?;
+    tx.execute("UPDATE invoices SET contact_id = NULL WHERE contact_id = ?1", params![id])
+        .map_err(db_error)?;
+    tx.commit().map_err(db_error)
+}
+
+fn validate_email(email: &str) -> Result<String, AppError> {
+    let valid = email
+        .split_once('@')
+        .is_some_and(|(user, domain)| !user.is_empty() && domain.contains('.') && !domain.contains('@'));
+    if !valid || email.chars().any(char::is_whitespace) {
+        error!("Invalid email: '{}'", email);
+        return Err(AppError::invalid_field("email", "El email no es válido."));
+    }
+    Ok(email.to_lowercase())
+}
+
+/// Valida y normaliza los datos de un contacto.
+fn resolve_input(input: ContactInput) -> Result<ContactInput, AppError> {
+    let name = input.name.trim().to_owned();
+    if name.is_empty() {
+        return Err(AppError::invalid_field("name", "El nombre del contacto no puede estar vacío."));
+    }
+    Ok(ContactInput {
+        name,
+        nif: stores::non_empty(input.nif).map(|n| stores::validate_nif("nif", &n)).transpose()?,
+        email: stores::non_empty(input.email).map(|e| validate_email(&e)).transpose()?,
+        kind: input.kind,
+    })
+}
+
+// --- Ingresos por cliente ---
+
+/// Ingresos y gastos asociados a un cliente, en la moneda base.
+#[derive(Debug, Clone, Serialize)]
+pub struct ClientRevenue {
+    /// `None` para las transacciones sin cliente.
+    pub contact_id: Option<String>,
+    pub name: String,
+    #[serde(flatten)]
+    pub totals: GroupTotals,
+}
+
+/// Desglose por cliente de las transacciones del rango `[from, to]`, de mayor a
+/// menor ingreso. Las transacciones de proveedores no se incluyen.
+pub fn build_client_revenue(
+    transactions: &[Transaction],
+    contacts: &[Contact],
+    rates: &RateTable,
+    from: Option<u64>,
+    to: Option<u64>,
+) -> Result<Vec<ClientRevenue>, AppError> {
+    let clients: HashMap<&str, &Contact> = contacts
+        .iter()
+        .filter(|c| c.kind == ContactKind::Cliente)
+        .map(|c| (c.id.as_str(), c))
+        .collect();
+    let mut by_client: BTreeMap<Option<&str>, GroupTotals> = BTreeMap::new();
+    for transaction in transactions.iter().filter(|t| reports::in_range(t.timestamp, from, to)) {
+        let client = match transaction.contact_id.as_deref() {
+            Some(id) if clients.contains_key(id) => Some(id),
+            Some(_) => continue,
+            None => None,
+        };
+        let amount = rates.to_base(transaction.amount, &transaction.currency)?;
+        by_client.entry(client).or_default().add(&transaction.transaction_type, amount);
+    }
+
+    let mut revenue: Vec<ClientRevenue> = by_client
+        .into_iter()
+        .map(|(id, totals)| ClientRevenue {
+            contact_id: id.map(str::to_owned),
+            name: id.map_or_else(|| NO_CLIENT.to_string(), |id| clients[id].name.clone()),
+            totals,
+        })
+        .collect();
+    revenue.sort_by(|a, b| b.totals.income.cmp(&a.totals.income).then_with(|| a.name.cmp(&b.name)));
+    Ok(revenue)
+}
+
+// --- Comandos Tauri ---
+
+/// Comando para listar los contactos, opcionalmente solo clientes o proveedores.
+#[tauri::command]
+pub async fn list_contacts_command(
+    state: State<'_, AppState>,
+    kind: Option<ContactKind>,
+) -> Result<Vec<Contact>, AppError> {
+    debug!("Received list_contacts_command: {:?}", kind);
+    list_contacts(state.db().await?.connection(), kind)
+}
+
+/// Comando para añadir un cliente o proveedor.
+#[tauri::command]
+pub async fn create_contact_command(
+    state: State<'_, AppState>,
+    app: AppHandle,
+    contact: ContactInput,
+) -> Result<Contact, AppError> {
+    debug!("Received create_contact_command: {:?}", contact);
+    let contact = resolve_input(contact)?;
+    let db = state.db().await?;
+    let id = uuid::Uuid::new_v4().to_string();
+    let now = periods::now_timestamp() as i64;
+    db.connection()
+        .execute(
+            &format!("INSERT INTO contacts ({}) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?6)", CONTACT_COLUMNS),
+            params![id, contact.name, contact.nif, contact.email, contact.kind.to_string(), now],
+        )
+        .map_err(db_error)?;
+    let created = require_contact(db.connection(), "id", &id)?;
+    audit::record(db.connection(), "create_contact_command", Some(&id), None, audit::snapshot(&created));
+    events::emit_entity(&app, events::CONTACT_CHANGED_EVENT, ChangeAction::Created, &id);
+    info!("Contact '{}' created.", created.name);
+    Ok(created)
+}
+
+/// Comando para cambiar los datos de un contacto.
+#[tauri::command]
+pub async fn update_contact_command(
+    state: State<'_, AppState>,
+    app: AppHandle,
+    id: String,
+    contact: ContactInput,
+) -> Result<Contact, AppError> {
+    debug!("Received update_contact_command for ID {}: {:?}", id, contact);
+    let contact = resolve_input(contact)?;
+    let db = state.db().await?;
+    let Some(before) = get_contact(db.connection(), &id)? else {
+        error!("Contact {} not found for update.", id);
+        return Err(AppError::NotFound(format!("Contacto con ID {} no encontrado.", id)));
+    };
+    db.connection()
+        .execute(
+            "UPDATE contacts SET name = ?2, nif = ?3, email = ?4, kind = ?5, updated_at = ?6 WHERE id = ?1",
+            params![
+                id,
+                contact.name,
+                contact.nif,
+                contact.email,
+                contact.kind.to_string(),
+                periods::now_timestamp() as i64
+            ],
+        )
+        .map_err(db_error)?;
+    let updated = require_contact(db.connection(), "id", &id)?;
+    audit::record(
+        db.connection(),
+        "update_contact_command",
+        Some(&id),
+        audit::snapshot(&before),
+        audit::snapshot(&updated),
+    );
+    events::emit_entity(&app, events::CONTACT_CHANGED_EVENT, ChangeAction::Updated, &id);
+    Ok(updated)
+}
+
+/// Comando para eliminar un contacto. Sus transacciones y facturas se conservan,
+/// sin contacto asociado.
+#[tauri::command]
+pub async fn delete_contact_command(state: State<'_, AppState>, app: AppHandle, id: String) -> Result<(), AppError> {
+    debug!("Received delete_contact_command: {}", id);
+    let db = state.db().await?;
+    let conn = db.connection();
+    let Some(before) = get_contact(conn, &id)? else {
+        error!("Contact {} not found for deletion.", id);
+        return Err(AppError::NotFound(format!("Contacto con ID {} no encontrado.", id)));
+    };
+
+    delete_contact(conn, &id)?;
+    audit::record(conn, "delete_contact_command", Some(&id), audit::snapshot(&before), None);
+    events::emit_entity(&app, events::CONTACT_CHANGED_EVENT, ChangeAction::Deleted, &id);
+    info!("Contact '{}' deleted.", before.name);
+    Ok(())
+}
+
+/// Comando para obtener los ingresos y gastos por cliente entre `from` y `to`
+/// (segundos Unix, inclusivos y opcionales).
+#[tauri::command]
+pub async fn get_revenue_by_client_command(
+    state: State<'_, AppState>,
+    from: Option<u64>,
+    to: Option<u64>,
+) -> Result<Vec<ClientRevenue>, AppError> {
+    debug!("Received get_revenue_by_client_command: from={:?}, to={:?}", from, to);
+    if let (Some(from), Some(to)) = (from, to) {
+        if from > to {
+            error!("Invalid report range: from={} > to={}", from, to);
+            return Err(AppError::invalid_field("from", "La fecha inicial no puede ser posterior a la fecha final."));
+        }
+    }
+
+    let db = state.db().await?;
+    let rates = RateTable::load(&db)?;
+    let contacts = list_contacts(db.connection(), Some(ContactKind::Cliente))?;
+    build_client_revenue(&db.list_transactions()?, &contacts, &rates, from, to)
+}
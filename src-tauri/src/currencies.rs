@@ -0,0 +1,244 @@
+// src-tauri/src/currencies.rs
+
+use rusqlite::params;
+use rust_decimal::Decimal;
+use serde::Serialize;
+use std::collections::HashMap;
+use tauri::{AppHandle, State};
+use chrono::Utc;
+use log::{debug, error, info};
+
+use crate::audit;
+use crate::error::AppError;
+use crate::events::{self, ChangeAction};
+use crate::storage::{self, db_error, SqliteStorage};
+use crate::validation;
+use crate::AppState;
+
+/// Moneda usada por defecto y para los datos anteriores al soporte multimoneda.
+pub const DEFAULT_CURRENCY: &str = "EUR";
+
+const BASE_CURRENCY_KEY: &str = "base_currency";
+
+/// Valor por defecto de `Transaction.currency` al leer datos antiguos.
+pub fn default_currency() -> String {
+    DEFAULT_CURRENCY.to_string()
+}
+
+/// Valida un código ISO 4217 (tres letras) y lo devuelve en mayúsculas.
+pub fn normalize_currency_code(code: &str) -> Result<String, AppError> {
+    let code = code.trim().to_ascii_uppercase();
+    if code.len() != 3 || !code.chars().all(|c| c.is_ascii_alphabetic()) {
+        error!("Invalid currency code: '{}'", code);
+        return Err(AppError::field_error(
+            "currency",
+            validation::INVALID_FORMAT,
+            format!("Código de moneda inválido: '{}'. Usa un código ISO 4217 como EUR o USD.", code),
+        ));
+    }
+    Ok(code)
+}
+
+/// Tipo de cambio de una moneda respecto a la moneda base.
+#[derive(Debug, Clone, Serialize)]
+pub struct ExchangeRate {
+    pub currency: String,
+    /// Unidades de la moneda base equivalentes a una unidad de `currency`.
+    pub rate: Decimal,
+    pub updated_at: u64,
+}
+
+/// Tabla de tipos de cambio cargada en memoria para convertir importes.
+pub struct RateTable {
+    pub base_currency: String,
+    rates: HashMap<String, Decimal>,
+}
+
+impl RateTable {
+    /// Carga la moneda base y los tipos de cambio guardados.
+    pub fn load(db: &SqliteStorage) -> Result<Self, AppError> {
+        let base_currency = get_base_currency(db)?;
+        let rates = list_exchange_rates(db)?
+            .into_iter()
+            .map(|r| (r.currency, r.rate))
+            .collect();
+        Ok(RateTable { base_currency, rates })
+    }
+
+    /// Convierte `amount` expresado en `currency` a la moneda base.
+    pub fn to_base(&self, amount: Decimal, currency: &str) -> Result<Decimal, AppError> {
+        if currency == self.base_currency {
+            return Ok(amount);
+        }
+        match self.rates.get(currency) {
+            Some(rate) => Ok(amount * rate),
+            None => {
+                error!("Missing exchange rate for {} -> {}", currency, self.base_currency);
+                Err(AppError::invalid_field(
+                    "currency",
+                    format!("No hay tipo de cambio configurado de {} a {}.", currency, self.base_currency),
+                ))
+            }
+        }
+    }
+}
+
+// --- Acceso a Datos ---
+
+pub fn get_base_currency(db: &SqliteStorage) -> Result<String, AppError> {
+    Ok(db
+        .get_setting(BASE_CURRENCY_KEY)?
+        .unwrap_or_else(default_currency))
+}
+
+pub fn list_exchange_rates(db: &SqliteStorage) -> Result<Vec<ExchangeRate>, AppError> {
+    let mut stmt = db
+        .connection()
+        .prepare("SELECT currency, rate, updated_at FROM exchange_rates ORDER BY currency")
+        .map_err(db_error)?;
+    let rows = stmt
+        .query_map([], |row| {
+            Ok(ExchangeRate {
+                currency: row.get(0)?,
+                rate: storage::decimal_column(row, 1)?,
+                updated_at: row.get::<_, i64>(2)? as u64,
+            })
+        })
+        .map_err(db_error)?;
+    rows.collect::<rusqlite::Result<Vec<_>>>().map_err(db_error)
+}
+
+// --- Comandos Tauri ---
+
+/// Comando para obtener la moneda base y los tipos de cambio configurados.
+#[tauri::command]
+pub async fn get_exchange_rates_command(state: State<'_, AppState>) -> Result<Vec<ExchangeRate>, AppError> {
+    debug!("Received get_exchange_rates_command.");
+    let db = state.db().await?;
+    list_exchange_rates(&db)
+}
+
+/// Comando para fijar el tipo de cambio de una moneda respecto a la moneda base.
+#[tauri::command]
+pub async fn set_exchange_rate_command(
+    state: State<'_, AppState>,
+    app: AppHandle,
+    currency: String,
+    rate: Decimal,
+) -> Result<ExchangeRate, AppError> {
+    debug!("Received set_exchange_rate_command: currency={}, rate={}", currency, rate);
+    let currency = normalize_currency_code(&currency)?;
+
+    if rate <= Decimal::ZERO {
+        error!("Invalid exchange rate received: {}", rate);
+        return Err(AppError::invalid_field("rate", "El tipo de cambio debe ser un número positivo."));
+    }
+
+    let db = state.db().await?;
+    let base_currency = get_base_currency(&db)?;
+    if currency == base_currency {
+        error!("Attempted to set exchange rate for base currency {}", currency);
+        return Err(AppError::invalid_field(
+            "currency",
+            format!("{} es la moneda base; su tipo de cambio siempre es 1.", currency),
+        ));
+    }
+
+    let previous = list_exchange_rates(&db)?.into_iter().find(|r| r.currency == currency);
+    let exchange_rate = ExchangeRate {
+        currency,
+        rate,
+        updated_at: Utc::now().timestamp() as u64,
+    };
+    db.connection()
+        .execute(
+            "INSERT INTO exchange_rates (currency, rate, updated_at) VALUES (?1, ?2, ?3)
+             ON CONFLICT(currency) DO UPDATE SET rate = excluded.rate, updated_at = excluded.updated_at",
+            params![exchange_rate.currency, exchange_rate.rate.to_string(), exchange_rate.updated_at as i64],
+        )
+        .map_err(db_error)?;
+    audit::record(
+        db.connection(),
+        "set_exchange_rate_command",
+        Some(&exchange_rate.currency),
+        previous.as_ref().and_then(audit::snapshot),
+        audit::snapshot(&exchange_rate),
+    );
+    let action = if previous.is_some() { ChangeAction::Updated } else { ChangeAction::Created };
+    events::emit_entity(&app, events::CURRENCY_CHANGED_EVENT, action, &exchange_rate.currency);
+    debug!("Exchange rate saved: {:?}", exchange_rate);
+    Ok(exchange_rate)
+}
+
+/// Comando para cambiar la moneda base en la que se informan los totales.
+/// Los tipos de cambio existentes se recalculan respecto a la nueva base y la antigua
+/// pasa a tener el suyo, para que las transacciones en ella se sigan pudiendo sumar.
+/// Por eso hace falta el tipo de cambio de la nueva moneda a la base actual: el
+/// guardado o, si no lo hay, `rate` (unidades de la base actual por unidad de la nueva).
+#[tauri::command]
+pub async fn set_base_currency_command(
+    state: State<'_, AppState>,
+    app: AppHandle,
+    currency: String,
+    rate: Option<Decimal>,
+) -> Result<(), AppError> {
+    debug!("Received set_base_currency_command: {} (rate {:?})", currency, rate);
+    let new_base = normalize_currency_code(&currency)?;
+
+    let db = state.db().await?;
+    let table = RateTable::load(&db)?;
+    if new_base == table.base_currency {
+        return Ok(());
+    }
+
+    let pivot = match rate.or_else(|| table.rates.get(&new_base).copied()) {
+        Some(rate) if rate > Decimal::ZERO => rate,
+        Some(rate) => {
+            error!("Invalid exchange rate for new base currency {}: {}", new_base, rate);
+            return Err(AppError::invalid_field("rate", "El tipo de cambio debe ser mayor que cero."));
+        }
+        None => {
+            error!("Cannot switch base currency to {} without an exchange rate.", new_base);
+            return Err(AppError::invalid_field(
+                "rate",
+                format!(
+                    "Indica el tipo de cambio de {} a {} para poder usarla como moneda base.",
+                    new_base, table.base_currency
+                ),
+            ));
+        }
+    };
+
+    let now = Utc::now().timestamp();
+    let tx = db.connection().unchecked_transaction().map_err(db_error)?;
+    tx.execute("DELETE FROM exchange_rates", []).map_err(db_error)?;
+    for (currency, rate) in table.rates.iter().filter(|(c, _)| **c != new_base) {
+        tx.execute(
+            "INSERT INTO exchange_rates (currency, rate, updated_at) VALUES (?1, ?2, ?3)",
+            params![currency, (rate / pivot).normalize().to_string(), now],
+        )
+        .map_err(db_error)?;
+    }
+    tx.execute(
+        "INSERT INTO exchange_rates (currency, rate, updated_at) VALUES (?1, ?2, ?3)",
+        params![table.base_currency, (Decimal::ONE / pivot).normalize().to_string(), now],
+    )
+    .map_err(db_error)?;
+    tx.execute(
+        "UPDATE app_settings SET value = ?1 WHERE key = ?2",
+        params![new_base, BASE_CURRENCY_KEY],
+    )
+    .map_err(db_error)?;
+    tx.commit().map_err(db_error)?;
+    audit::record(
+        db.connection(),
+        "set_base_currency_command",
+        Some(BASE_CURRENCY_KEY),
+        audit::snapshot(&table.base_currency),
+        audit::snapshot(&new_base),
+    );
+    events::emit_entity(&app, events::CURRENCY_CHANGED_EVENT, ChangeAction::Updated, &new_base);
+
+    info!("Base currency changed from {} to {}.", table.base_currency, new_base);
+    Ok(())
+}
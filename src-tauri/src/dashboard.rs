@@ -0,0 +1,371 @@
+// src-tauri/src/dashboard.rs
+
+use rust_decimal::prelude::ToPrimitive;
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use tauri::State;
+use log::{debug, error};
+
+use crate::aggregates::{self, Aggregates};
+use crate::currencies::RateTable;
+use crate::error::AppError;
+use crate::periods::{self, Period};
+use crate::reports::{self, percent_change, GroupBy, GroupTotals};
+use crate::splits;
+use crate::storage::SqliteStorage;
+use crate::{AppState, Transaction, TransactionType};
+
+/// Número de tiendas y categorías que se muestran en los rankings del panel.
+const TOP_N: usize = 5;
+/// Máximo de entradas que se pueden pedir a `get_top_expenses_command` y
+/// `get_top_stores_command`.
+const MAX_TOP_N: usize = 100;
+
+/// Gasto acumulado de una tienda o categoría en el periodo, en la moneda base.
+#[derive(Debug, Clone, Serialize)]
+pub struct RankedSpend {
+    pub name: String,
+    pub amount: Decimal,
+    pub transaction_count: usize,
+}
+
+/// Variación respecto al periodo anterior. Los porcentajes son `None` cuando el
+/// periodo anterior no tuvo movimientos de ese tipo.
+#[derive(Debug, Clone, Serialize)]
+pub struct PeriodDelta {
+    pub income: Decimal,
+    pub expenses: Decimal,
+    pub net: Decimal,
+    pub income_percent: Option<f64>,
+    pub expenses_percent: Option<f64>,
+}
+
+/// Datos del panel principal, calculados en una sola llamada.
+#[derive(Debug, Clone, Serialize)]
+pub struct DashboardSummary {
+    pub period: Period,
+    pub period_start: u64,
+    pub period_end: u64,
+    pub base_currency: String,
+    pub totals: GroupTotals,
+    pub previous_totals: GroupTotals,
+    pub delta: PeriodDelta,
+    pub top_stores: Vec<RankedSpend>,
+    pub top_categories: Vec<RankedSpend>,
+}
+
+/// Las `TOP_N` entradas con mayor gasto, de mayor a menor.
+fn top_spend(spend: HashMap<String, (Decimal, usize)>) -> Vec<RankedSpend> {
+    let mut ranked: Vec<RankedSpend> = spend
+        .into_iter()
+        .map(|(name, (amount, transaction_count))| RankedSpend { name, amount, transaction_count })
+        .collect();
+    ranked.sort_by(|a, b| b.amount.cmp(&a.amount).then_with(|| a.name.cmp(&b.name)));
+    ranked.truncate(TOP_N);
+    ranked
+}
+
+/// Calcula el panel del periodo que contiene `now` con los totales por día de
+/// `aggregates` y lo compara con el anterior.
+fn summary_from_aggregates(
+    aggregates: &Aggregates,
+    rates: &RateTable,
+    split: &[(Transaction, Vec<Transaction>)],
+    period: Period,
+    now: u64,
+) -> Result<DashboardSummary, AppError> {
+    let start = period.start_date(periods::local_date(now));
+    let end = period.next_start_date(start);
+    let previous_start = period.start_date(start.pred_opt().unwrap_or(start));
+
+    let mut totals = GroupTotals::default();
+    let mut previous_totals = GroupTotals::default();
+    let mut store_spend: HashMap<String, (Decimal, usize)> = HashMap::new();
+    let mut category_spend: HashMap<String, (Decimal, usize)> = HashMap::new();
+
+    for (date, key, bucket) in aggregates.range(Some(previous_start), Some(end)) {
+        let amount = rates.to_base(bucket.amount, &key.currency)?;
+        if date < start {
+            previous_totals.add_many(&key.transaction_type, amount, bucket.count);
+            continue;
+        }
+        totals.add_many(&key.transaction_type, amount, bucket.count);
+        if key.transaction_type == TransactionType::Gasto {
+            add_spend(&mut store_spend, key.store_name.clone(), amount, bucket.count);
+            add_spend(&mut category_spend, key.category.clone(), amount, bucket.count);
+        }
+    }
+
+    apply_splits(&mut category_spend, split, rates, |t| *t == TransactionType::Gasto)?;
+
+    let bounds = (periods::local_midnight_timestamp(start), periods::local_midnight_timestamp(end));
+    Ok(finish_summary(rates, period, bounds, totals, previous_totals, store_spend, category_spend))
+}
+
+fn add_spend(spend: &mut HashMap<String, (Decimal, usize)>, key: String, amount: Decimal, count: usize) {
+    let entry = spend.entry(key).or_insert((Decimal::ZERO, 0));
+    entry.0 += amount;
+    entry.1 += count;
+}
+
+/// Pasa las transacciones repartidas de `split` (ver `splits::split_transactions`)
+/// del total de su categoría al de la categoría de cada parte, porque `aggregates`
+/// las cuenta enteras. Solo las de un tipo que cumple `included`.
+fn apply_splits(
+    spend: &mut HashMap<String, (Decimal, usize)>,
+    split: &[(Transaction, Vec<Transaction>)],
+    rates: &RateTable,
+    included: impl Fn(&TransactionType) -> bool,
+) -> Result<(), AppError> {
+    for (transaction, parts) in split {
+        if !included(&transaction.transaction_type) {
+            continue;
+        }
+        let key = reports::group_key(transaction, GroupBy::Category);
+        if let Some(entry) = spend.get_mut(&key) {
+            entry.0 -= rates.to_base(transaction.amount, &transaction.currency)?;
+            entry.1 = entry.1.saturating_sub(1);
+            if entry.1 == 0 {
+                spend.remove(&key);
+            }
+        }
+        for part in parts {
+            let amount = rates.to_base(part.amount, &part.currency)?;
+            add_spend(spend, reports::group_key(part, GroupBy::Category), amount, 1);
+        }
+    }
+    Ok(())
+}
+
+fn finish_summary(
+    rates: &RateTable,
+    period: Period,
+    (period_start, period_end): (u64, u64),
+    totals: GroupTotals,
+    previous_totals: GroupTotals,
+    store_spend: HashMap<String, (Decimal, usize)>,
+    category_spend: HashMap<String, (Decimal, usize)>,
+) -> DashboardSummary {
+    let delta = PeriodDelta {
+        income: totals.income - previous_totals.income,
+        expenses: totals.expenses - previous_totals.expenses,
+        net: totals.net - previous_totals.net,
+        income_percent: percent_change(totals.income, previous_totals.income),
+        expenses_percent: percent_change(totals.expenses, previous_totals.expenses),
+    };
+    DashboardSummary {
+        period,
+        period_start,
+        period_end,
+        base_currency: rates.base_currency.clone(),
+        totals,
+        previous_totals,
+        delta,
+        top_stores: top_spend(store_spend),
+        top_categories: top_spend(category_spend),
+    }
+}
+
+/// Panel del periodo que contiene `now`.
+pub fn dashboard_summary(db: &SqliteStorage, period: Period, now: u64) -> Result<DashboardSummary, AppError> {
+    let rates = RateTable::load(db)?;
+    let start = period.start_date(periods::local_date(now));
+    let split = splits::split_transactions(
+        db,
+        periods::local_midnight_timestamp(start),
+        periods::local_midnight_timestamp(period.next_start_date(start)),
+    )?;
+    aggregates::with_aggregates(db, |a| summary_from_aggregates(a, &rates, &split, period, now))?
+}
+
+/// Qué se mide al ordenar las tiendas.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RankMetric {
+    Expenses,
+    Income,
+    TransactionCount,
+}
+
+/// Una entrada de un ranking con su parte del total.
+#[derive(Debug, Clone, Serialize)]
+pub struct RankedShare {
+    pub name: String,
+    pub amount: Decimal,
+    pub transaction_count: usize,
+    /// Porcentaje del total del periodo según la métrica del ranking.
+    pub share_percent: f64,
+}
+
+/// Las entradas con más peso en el periodo que contiene el momento de la consulta.
+/// `others` reúne todo lo que queda fuera de `items`, para completar un gráfico de
+/// sectores.
+#[derive(Debug, Clone, Serialize)]
+pub struct TopRanking {
+    pub period: Period,
+    pub period_start: u64,
+    pub period_end: u64,
+    pub base_currency: String,
+    pub metric: RankMetric,
+    /// Importe y número de los movimientos que entran en el ranking.
+    pub total: Decimal,
+    pub total_transactions: usize,
+    pub items: Vec<RankedShare>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub others: Option<RankedShare>,
+}
+
+fn share_of(value: Decimal, total: Decimal) -> f64 {
+    if total.is_zero() {
+        return 0.0;
+    }
+    (value / total * Decimal::ONE_HUNDRED).round_dp(2).to_f64().unwrap_or(0.0)
+}
+
+/// Ordena `values` por `metric` (de mayor a menor, y por nombre si empatan) y se
+/// queda con los `n` primeros.
+fn rank(
+    values: HashMap<String, (Decimal, usize)>,
+    rates: &RateTable,
+    period: Period,
+    bounds: (u64, u64),
+    metric: RankMetric,
+    n: usize,
+) -> TopRanking {
+    let weight = |(amount, count): &(Decimal, usize)| match metric {
+        RankMetric::TransactionCount => Decimal::from(*count),
+        RankMetric::Expenses | RankMetric::Income => *amount,
+    };
+    let total_amount: Decimal = values.values().map(|(amount, _)| *amount).sum();
+    let total_transactions: usize = values.values().map(|(_, count)| *count).sum();
+    let total_weight: Decimal = values.values().map(weight).sum();
+
+    let mut ranked: Vec<(String, (Decimal, usize))> = values.into_iter().collect();
+    ranked.sort_by(|a, b| weight(&b.1).cmp(&weight(&a.1)).then_with(|| a.0.cmp(&b.0)));
+    let rest = ranked.split_off(n.min(ranked.len()));
+
+    let items = ranked
+        .into_iter()
+        .map(|(name, value)| RankedShare {
+            share_percent: share_of(weight(&value), total_weight),
+            name,
+            amount: value.0,
+            transaction_count: value.1,
+        })
+        .collect();
+    let others = (!rest.is_empty()).then(|| {
+        let value = rest.iter().fold((Decimal::ZERO, 0), |acc, (_, v)| (acc.0 + v.0, acc.1 + v.1));
+        RankedShare {
+            name: "Otros".to_string(),
+            share_percent: share_of(weight(&value), total_weight),
+            amount: value.0,
+            transaction_count: value.1,
+        }
+    });
+    TopRanking {
+        period,
+        period_start: bounds.0,
+        period_end: bounds.1,
+        base_currency: rates.base_currency.clone(),
+        metric,
+        total: total_amount,
+        total_transactions,
+        items,
+        others,
+    }
+}
+
+/// Ranking de las categorías de gasto (`by_store == false`) o de las tiendas en el
+/// periodo que contiene `now`. Las categorías solo cuentan gastos; las tiendas, los
+/// movimientos del tipo de `metric` (ingresos y gastos con `TransactionCount`).
+pub fn top_ranking(
+    db: &SqliteStorage,
+    period: Period,
+    now: u64,
+    by_store: bool,
+    metric: RankMetric,
+    n: usize,
+) -> Result<TopRanking, AppError> {
+    let rates = RateTable::load(db)?;
+    let start = period.start_date(periods::local_date(now));
+    let end = period.next_start_date(start);
+    let bounds = (periods::local_midnight_timestamp(start), periods::local_midnight_timestamp(end));
+    let included = |transaction_type: &TransactionType| match metric {
+        RankMetric::Expenses => *transaction_type == TransactionType::Gasto,
+        RankMetric::Income => *transaction_type == TransactionType::Ingreso,
+        RankMetric::TransactionCount => *transaction_type != TransactionType::Transferencia,
+    };
+    let split = if by_store { Vec::new() } else { splits::split_transactions(db, bounds.0, bounds.1)? };
+    aggregates::with_aggregates(db, |aggregates| {
+        let mut values: HashMap<String, (Decimal, usize)> = HashMap::new();
+        for (_, key, bucket) in aggregates.range(Some(start), Some(end)) {
+            if !included(&key.transaction_type) {
+                continue;
+            }
+            let name = if by_store { key.store_name.clone() } else { key.category.clone() };
+            add_spend(&mut values, name, rates.to_base(bucket.amount, &key.currency)?, bucket.count);
+        }
+        apply_splits(&mut values, &split, &rates, included)?;
+        Ok(rank(values, &rates, period, bounds, metric, n))
+    })?
+}
+
+fn validate_top_n(n: Option<usize>) -> Result<usize, AppError> {
+    let n = n.unwrap_or(TOP_N);
+    if n == 0 || n > MAX_TOP_N {
+        error!("Invalid top-N size: {}", n);
+        return Err(AppError::invalid_field("n", format!("Indica un número entre 1 y {}.", MAX_TOP_N)));
+    }
+    Ok(n)
+}
+
+// --- Comandos Tauri ---
+
+/// Comando para obtener los indicadores del panel principal en el periodo actual
+/// (`Mensual` por defecto): totales, variación respecto al periodo anterior y las
+/// tiendas y categorías con más gasto.
+#[tauri::command]
+pub async fn get_dashboard_summary_command(
+    state: State<'_, AppState>,
+    period: Option<Period>,
+) -> Result<DashboardSummary, AppError> {
+    let period = period.unwrap_or(Period::Mensual);
+    debug!("Received get_dashboard_summary_command: {:?}", period);
+    let db = state.db().await?;
+    dashboard_summary(&db, period, periods::now_timestamp())
+}
+
+/// Comando para obtener las `n` categorías con más gasto (5 por defecto) en el periodo
+/// actual (`Mensual` por defecto), con su parte del gasto total y el resto agrupado
+/// en "Otros".
+#[tauri::command]
+pub async fn get_top_expenses_command(
+    state: State<'_, AppState>,
+    period: Option<Period>,
+    n: Option<usize>,
+) -> Result<TopRanking, AppError> {
+    let period = period.unwrap_or(Period::Mensual);
+    debug!("Received get_top_expenses_command: period={:?}, n={:?}", period, n);
+    let n = validate_top_n(n)?;
+    let db = state.db().await?;
+    top_ranking(&db, period, periods::now_timestamp(), false, RankMetric::Expenses, n)
+}
+
+/// Comando para obtener las `n` tiendas (5 por defecto) con más gasto, más ingresos o
+/// más movimientos (`metric`, gasto por defecto) en el periodo actual (`Mensual` por
+/// defecto), con su parte del total y el resto agrupado en "Otros".
+#[tauri::command]
+pub async fn get_top_stores_command(
+    state: State<'_, AppState>,
+    period: Option<Period>,
+    n: Option<usize>,
+    metric: Option<RankMetric>,
+) -> Result<TopRanking, AppError> {
+    let period = period.unwrap_or(Period::Mensual);
+    let metric = metric.unwrap_or(RankMetric::Expenses);
+    debug!("Received get_top_stores_command: period={:?}, n={:?}, metric={:?}", period, n, metric);
+    let n = validate_top_n(n)?;
+    let db = state.db().await?;
+    top_ranking(&db, period, periods::now_timestamp(), true, metric, n)
+}
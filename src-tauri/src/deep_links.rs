@@ -0,0 +1,236 @@
+// src-tauri/src/deep_links.rs
+
+use chrono::NaiveDate;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::sync::Mutex;
+use tauri::{AppHandle, Manager, RunEvent, State, Url};
+use log::{debug, info, warn};
+
+use crate::categories;
+use crate::error::AppError;
+use crate::events;
+use crate::periods;
+use crate::startup;
+use crate::storage::SqliteStorage;
+use crate::stores;
+use crate::summaries;
+use crate::text_entry::{self, TransactionTextDraft};
+use crate::windows;
+use crate::{AppState, NewTransaction, TransactionType};
+
+// Enlaces `contabilidad://` para abrir la aplicación desde otras herramientas o desde
+// un correo:
+//   contabilidad://open
+//   contabilidad://report?month=2024-03
+//   contabilidad://add?amount=20&store=Taxi&description=Aeropuerto&type=gasto
+// En Windows y Linux el enlace llega como argumento (el esquema se registra al
+// arrancar, ver `register_scheme`); en macOS, con `RunEvent::Opened` (el esquema se
+// declara en `Info.plist`). Si la aplicación ya estaba abierta, `instance` le pasa el
+// enlace.
+
+pub const SCHEME: &str = "contabilidad";
+
+/// Evento con la acción de un enlace abierto. Carga: `DeepLinkAction`.
+pub const DEEP_LINK_EVENT: &str = "deep-link://opened";
+
+/// Enlaces abiertos antes de que la interfaz pudiera escuchar `DEEP_LINK_EVENT`.
+static PENDING: Mutex<Vec<Url>> = Mutex::new(Vec::new());
+
+/// Lo que la interfaz debe hacer con un enlace. La ventana principal ya se ha mostrado.
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum DeepLinkAction {
+    /// Solo mostrar la aplicación.
+    Open,
+    /// Mostrar el informe del mes `month` (`AAAA-MM`).
+    Report { month: String },
+    /// Abrir el formulario de nueva transacción con el borrador, sin guardar nada.
+    NewTransaction(TransactionTextDraft),
+}
+
+/// `true` si `arg` es un enlace de la aplicación.
+pub fn is_deep_link(arg: &str) -> bool {
+    arg.get(..SCHEME.len()).is_some_and(|scheme| scheme.eq_ignore_ascii_case(SCHEME))
+        && arg[SCHEME.len()..].starts_with(':')
+}
+
+/// Borrador de transacción con los parámetros del enlace. La tienda y la categoría se
+/// ajustan a las existentes; lo que falta o no es válido queda en `missing_fields`.
+fn build_draft(db: &SqliteStorage, params: &HashMap<String, String>) -> Result<TransactionTextDraft, AppError> {
+    let param = |name: &str| params.get(name).map(|v| v.trim()).filter(|v| !v.is_empty());
+    let store_names: Vec<String> = stores::list_stores(db.connection())?.into_iter().map(|s| s.name).collect();
+    let categories = categories::list_categories(db.connection())?;
+
+    let amount = param("amount").and_then(text_entry::parse_amount);
+    let store_name = param("store").map(|s| text_entry::match_name(s, &store_names).unwrap_or_else(|| s.to_owned()));
+    let transaction_type = match param("type").map(str::to_lowercase).as_deref() {
+        Some("ingreso") | Some("income") => TransactionType::Ingreso,
+        _ => TransactionType::Gasto,
+    };
+    let (category, subcategory) = param("category").map_or((None, None), |c| text_entry::match_category(c, &categories));
+
+    let mut missing_fields = Vec::new();
+    if amount.is_none() {
+        missing_fields.push("amount".to_string());
+    }
+    if store_name.is_none() {
+        missing_fields.push("store_name".to_string());
+    }
+    let draft = NewTransaction {
+        transaction_type,
+        amount: amount.unwrap_or_default(),
+        description: param("description").unwrap_or_default().to_owned(),
+        store_name: store_name.unwrap_or_default(),
+        category,
+        subcategory,
+        currency: param("currency").map(str::to_uppercase),
+        tax_rate: None,
+        tax_amount: None,
+        tags: param("tags").map(|t| t.split(',').map(|tag| tag.trim().to_owned()).filter(|tag| !tag.is_empty()).collect()),
+        transaction_date: Some(
+            param("date").and_then(|d| NaiveDate::parse_from_str(d, "%Y-%m-%d").ok()).unwrap_or_else(periods::today),
+        ),
+        notes: param("notes").map(str::to_owned),
+        custom_fields: None,
+        payment_method: None,
+        contact_id: None,
+        account_id: None,
+    };
+    Ok(TransactionTextDraft { draft, missing_fields })
+}
+
+/// Acción de un enlace. La acción es el host (`contabilidad://add`) o, si no lo hay,
+/// la ruta (`contabilidad:add`).
+async fn resolve_link(state: &AppState, url: &Url) -> Result<DeepLinkAction, AppError> {
+    let action = url.host_str().unwrap_or_else(|| url.path()).trim_matches('/').to_lowercase();
+    let params: HashMap<String, String> = url.query_pairs().map(|(k, v)| (k.to_lowercase(), v.into_owned())).collect();
+    match action.as_str() {
+        "" | "open" => Ok(DeepLinkAction::Open),
+        "report" => {
+            let month = match params.get("month") {
+                Some(month) => summaries::parse_month(month)?.format("%Y-%m").to_string(),
+                None => periods::today().format("%Y-%m").to_string(),
+            };
+            Ok(DeepLinkAction::Report { month })
+        }
+        "add" => Ok(DeepLinkAction::NewTransaction(build_draft(&state.db().await?, &params)?)),
+        other => Err(AppError::invalid_field("url", format!("Enlace no reconocido: {}.", other))),
+    }
+}
+
+/// Muestra la ventana principal y emite la acción de cada enlace. Si la carga inicial
+/// aún no terminó, se guardan para `take_pending_deep_links_command`.
+pub fn open_links(app: &AppHandle, urls: Vec<Url>) {
+    let urls: Vec<Url> = urls.into_iter().filter(|u| u.scheme().eq_ignore_ascii_case(SCHEME)).collect();
+    if urls.is_empty() {
+        return;
+    }
+    if let Err(e) = windows::focus_window(app, windows::MAIN_WINDOW_LABEL) {
+        warn!("Failed to show main window: {}", e);
+    }
+    if !startup::is_ready() {
+        if let Ok(mut pending) = PENDING.lock() {
+            pending.extend(urls);
+        }
+        return;
+    }
+    let app = app.clone();
+    tauri::async_runtime::spawn(async move {
+        for url in urls {
+            match resolve_link(&app.state::<AppState>(), &url).await {
+                Ok(action) => events::emit(&app, DEEP_LINK_EVENT, action),
+                Err(e) => warn!("Ignoring deep link {}: {}", url, e),
+            }
+        }
+    });
+}
+
+/// Enlaces pasados como argumento al abrir la aplicación. Se llama desde `setup`.
+pub fn open_launch_args(app: &AppHandle) {
+    let urls = std::env::args().skip(1).filter(|arg| is_deep_link(arg)).filter_map(|arg| Url::parse(&arg).ok()).collect();
+    open_links(app, urls);
+}
+
+/// Enlaces abiertos con la aplicación ya en marcha en macOS.
+pub fn handle_run_event(app: &AppHandle, event: &RunEvent) {
+    #[cfg(target_os = "macos")]
+    if let RunEvent::Opened { urls } = event {
+        open_links(app, urls.clone());
+    }
+    #[cfg(not(target_os = "macos"))]
+    let _ = (app, event);
+}
+
+/// Registra el esquema `contabilidad://` para el usuario actual apuntando a este
+/// ejecutable, en segundo plano. En macOS lo declara `Info.plist` al instalar.
+pub fn register_scheme() {
+    std::thread::spawn(|| {
+        let Ok(exe) = std::env::current_exe() else { return };
+        match register_scheme_for(&exe) {
+            Ok(()) => debug!("URL scheme {}:// registered for {}.", SCHEME, exe.display()),
+            Err(e) => warn!("Could not register the {}:// URL scheme: {}", SCHEME, e),
+        }
+    });
+}
+
+#[cfg(target_os = "windows")]
+fn register_scheme_for(exe: &std::path::Path) -> std::io::Result<()> {
+    let key = format!("HKCU\\Software\\Classes\\{}", SCHEME);
+    let command = format!("\"{}\" \"%1\"", exe.display());
+    for args in [
+        vec!["add", key.as_str(), "/ve", "/d", "URL:Contabilidad IA", "/f"],
+        vec!["add", key.as_str(), "/v", "URL Protocol", "/d", "", "/f"],
+        vec!["add", &format!("{}\\shell\\open\\command", key), "/ve", "/d", command.as_str(), "/f"],
+    ] {
+        let status = std::process::Command::new("reg").args(&args).output()?.status;
+        if !status.success() {
+            return Err(std::io::Error::other(format!("reg {:?} failed", args)));
+        }
+    }
+    Ok(())
+}
+
+#[cfg(target_os = "linux")]
+fn register_scheme_for(exe: &std::path::Path) -> std::io::Result<()> {
+    let file_name = format!("{}-url-handler.desktop", SCHEME);
+    let dir = dirs::data_dir()
+        .ok_or_else(|| std::io::Error::other("no data directory"))?
+        .join("applications");
+    std::fs::create_dir_all(&dir)?;
+    let entry = format!(
+        "[Desktop Entry]\nType=Application\nName=Contabilidad IA App\nExec=\"{}\" %u\nTerminal=false\nNoDisplay=true\nMimeType=x-scheme-handler/{};\n",
+        exe.display(),
+        SCHEME
+    );
+    std::fs::write(dir.join(&file_name), entry)?;
+    std::process::Command::new("xdg-mime")
+        .args(["default", &file_name, &format!("x-scheme-handler/{}", SCHEME)])
+        .output()?;
+    Ok(())
+}
+
+#[cfg(not(any(target_os = "windows", target_os = "linux")))]
+fn register_scheme_for(_exe: &std::path::Path) -> std::io::Result<()> {
+    Ok(())
+}
+
+// --- Comandos Tauri ---
+
+/// Comando para recoger los enlaces abiertos antes de que la interfaz estuviera lista
+/// (p. ej. el enlace con el que se abrió la aplicación). Cada enlace se devuelve una
+/// sola vez; los no válidos se omiten.
+#[tauri::command]
+pub async fn take_pending_deep_links_command(state: State<'_, AppState>) -> Result<Vec<DeepLinkAction>, AppError> {
+    debug!("Received take_pending_deep_links_command.");
+    let urls = std::mem::take(&mut *PENDING.lock().map_err(|_| AppError::Internal("Lista de enlaces no disponible.".to_string()))?);
+    let mut actions = Vec::with_capacity(urls.len());
+    for url in urls {
+        match resolve_link(&state, &url).await {
+            Ok(action) => actions.push(action),
+            Err(e) => warn!("Ignoring deep link {}: {}", url, e),
+        }
+    }
+    info!("Returning {} pending deep links.", actions.len());
+    Ok(actions)
+}
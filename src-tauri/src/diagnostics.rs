@@ -0,0 +1,109 @@
+// src-tauri/src/diagnostics.rs
+
+use serde::Serialize;
+use std::time::UNIX_EPOCH;
+use tauri::{AppHandle, State};
+use log::{debug, warn};
+
+use crate::ai::AiProvider;
+use crate::backup;
+use crate::encryption;
+use crate::error::AppError;
+use crate::gemini;
+use crate::logging;
+use crate::migrations;
+use crate::settings;
+use crate::startup;
+use crate::storage::{self, TransactionRepository};
+use crate::AppState;
+
+/// Estado de la aplicación y de los datos para adjuntarlo a una petición de soporte.
+/// No incluye datos contables ni secretos: de la clave de la IA solo se indica si
+/// está configurada. Los campos que dependen de la base de datos quedan vacíos
+/// mientras los datos cifrados están bloqueados.
+#[derive(Debug, Clone, Serialize)]
+pub struct Diagnostics {
+    pub app_name: String,
+    pub app_version: String,
+    pub tauri_version: String,
+    pub os: String,
+    pub arch: String,
+    pub workspace_id: String,
+    pub workspace_name: String,
+    pub data_dir: String,
+    pub database_path: String,
+    pub database_size_bytes: Option<u64>,
+    /// Última escritura del archivo de datos (segundos Unix).
+    pub last_saved_at: Option<u64>,
+    /// Cambios cifrados aún no escritos en el archivo (ver `autosave`).
+    pub pending_changes: bool,
+    pub encrypted: bool,
+    pub locked: bool,
+    pub schema_version: usize,
+    pub transaction_count: Option<usize>,
+    /// Resultado de `PRAGMA quick_check`.
+    pub integrity_ok: Option<bool>,
+    pub backup_count: usize,
+    pub last_backup_at: Option<u64>,
+    pub ai_provider: Option<AiProvider>,
+    pub ai_key_configured: bool,
+    pub log_dir: String,
+    /// Milisegundos desde el arranque hasta `data://ready` (ver `startup`).
+    pub startup_ms: Option<u64>,
+}
+
+// --- Comandos Tauri ---
+
+/// Comando para obtener el diagnóstico de la aplicación (versión, archivo de datos,
+/// número de transacciones, última copia, integridad de la base de datos, etc.).
+#[tauri::command]
+pub async fn get_diagnostics_command(state: State<'_, AppState>, app: AppHandle) -> Result<Diagnostics, AppError> {
+    debug!("Received get_diagnostics_command.");
+    let package = app.package_info();
+    let workspace = state.workspace.lock().unwrap().clone();
+    let database_path = storage::get_database_path();
+    let metadata = std::fs::metadata(&database_path).ok();
+    let last_saved_at = metadata
+        .as_ref()
+        .and_then(|m| m.modified().ok())
+        .and_then(|t| t.duration_since(UNIX_EPOCH).ok())
+        .map(|d| d.as_secs());
+    let backups = backup::list_backups().unwrap_or_else(|e| {
+        warn!("Could not list backups for diagnostics: {}", e);
+        Vec::new()
+    });
+
+    let mut diagnostics = Diagnostics {
+        app_name: package.name.clone(),
+        app_version: package.version.to_string(),
+        tauri_version: tauri::VERSION.to_string(),
+        os: std::env::consts::OS.to_string(),
+        arch: std::env::consts::ARCH.to_string(),
+        workspace_id: workspace.id.clone(),
+        workspace_name: workspace.name.clone(),
+        data_dir: storage::get_data_dir().display().to_string(),
+        database_path: database_path.display().to_string(),
+        database_size_bytes: metadata.as_ref().map(|m| m.len()),
+        last_saved_at,
+        pending_changes: false,
+        encrypted: encryption::is_encrypted_file(&database_path),
+        locked: state.is_locked(),
+        schema_version: migrations::schema_version(),
+        transaction_count: None,
+        integrity_ok: None,
+        backup_count: backups.len(),
+        last_backup_at: backups.first().map(|b| b.created_at),
+        ai_provider: None,
+        ai_key_configured: gemini::has_api_key(),
+        log_dir: logging::log_dir().display().to_string(),
+        startup_ms: startup::ready_ms(),
+    };
+    // Sin desbloquear solo se informa de lo que no necesita la base de datos.
+    if let Ok(db) = state.db().await {
+        diagnostics.pending_changes = db.pending_changes_age().is_some();
+        diagnostics.transaction_count = Some(db.count_transactions()?);
+        diagnostics.integrity_ok = Some(db.check_integrity()?);
+        diagnostics.ai_provider = Some(settings::load_settings(&db).ai_provider);
+    }
+    Ok(diagnostics)
+}
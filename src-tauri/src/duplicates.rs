@@ -0,0 +1,155 @@
+// src-tauri/src/duplicates.rs
+
+use rust_decimal::Decimal;
+use serde::Serialize;
+use std::collections::HashMap;
+use tauri::State;
+use log::{debug, error, warn};
+
+use crate::audit;
+use crate::error::AppError;
+use crate::storage::{SqliteStorage, TransactionRepository};
+use crate::{AppState, Transaction};
+
+const DUPLICATE_WINDOW_KEY: &str = "duplicate_window_hours";
+/// Margen por defecto entre dos transacciones iguales para considerarlas repetidas.
+const DEFAULT_DUPLICATE_WINDOW_HOURS: u64 = 24;
+const MAX_DUPLICATE_WINDOW_HOURS: u64 = 31 * 24;
+
+/// Grupo de transacciones que parecen la misma, de la más antigua a la más reciente.
+#[derive(Debug, Clone, Serialize)]
+pub struct DuplicateGroup {
+    pub transactions: Vec<Transaction>,
+}
+
+/// Lee el margen configurado, en horas.
+pub fn load_duplicate_window(db: &SqliteStorage) -> u64 {
+    db.get_setting(DUPLICATE_WINDOW_KEY)
+        .ok()
+        .flatten()
+        .and_then(|v| v.parse().ok())
+        .unwrap_or(DEFAULT_DUPLICATE_WINDOW_HOURS)
+}
+
+fn validate_window(hours: u64) -> Result<u64, AppError> {
+    if hours == 0 || hours > MAX_DUPLICATE_WINDOW_HOURS {
+        error!("Invalid duplicate window: {}", hours);
+        return Err(AppError::invalid_field(
+            "window_hours",
+            format!("El margen debe estar entre 1 y {} horas.", MAX_DUPLICATE_WINDOW_HOURS),
+        ));
+    }
+    Ok(hours)
+}
+
+/// Clave con la que se comparan dos transacciones, sin tener en cuenta la fecha.
+fn duplicate_key(transaction: &Transaction) -> (String, Decimal, String, String) {
+    (
+        transaction.transaction_type.to_string(),
+        transaction.amount.normalize(),
+        transaction.currency.clone(),
+        transaction.store_name.to_lowercase(),
+    )
+}
+
+/// `true` si ambas transacciones tienen el mismo tipo, importe, moneda y tienda y
+/// ocurrieron con menos de `window_hours` de diferencia.
+pub fn is_duplicate(a: &Transaction, b: &Transaction, window_hours: u64) -> bool {
+    a.id != b.id && duplicate_key(a) == duplicate_key(b) && a.timestamp.abs_diff(b.timestamp) <= window_hours * 3600
+}
+
+/// Transacciones activas que parecen repetir `candidate`.
+pub fn find_duplicates_of(
+    db: &SqliteStorage,
+    candidate: &Transaction,
+    window_hours: u64,
+) -> Result<Vec<Transaction>, AppError> {
+    Ok(db
+        .list_transactions_by_store(&candidate.store_name)?
+        .into_iter()
+        .filter(|t| is_duplicate(candidate, t, window_hours))
+        .collect())
+}
+
+/// Error con el que se avisa de que `candidate` repite transacciones existentes.
+pub fn duplicate_error(candidate: &Transaction, duplicates: &[Transaction]) -> AppError {
+    warn!("Possible duplicate of {} existing transactions: {:?}", duplicates.len(), candidate);
+    AppError::Duplicate {
+        message: format!(
+            "Ya hay {} transacción(es) de {} en '{}' con fecha cercana. ¿Quieres añadirla igualmente?",
+            duplicates.len(),
+            candidate.amount,
+            candidate.store_name
+        ),
+        duplicates: duplicates.iter().map(|t| t.id.clone()).collect(),
+    }
+}
+
+/// Agrupa las transacciones repetidas. Dentro de un grupo, cada transacción está a
+/// menos de `window_hours` de la anterior.
+pub fn group_duplicates(transactions: Vec<Transaction>, window_hours: u64) -> Vec<DuplicateGroup> {
+    let mut by_key: HashMap<(String, Decimal, String, String), Vec<Transaction>> = HashMap::new();
+    for transaction in transactions {
+        by_key.entry(duplicate_key(&transaction)).or_default().push(transaction);
+    }
+
+    let mut groups = Vec::new();
+    for mut candidates in by_key.into_values() {
+        candidates.sort_by_key(|t| t.timestamp);
+        let mut current: Vec<Transaction> = Vec::new();
+        for transaction in candidates {
+            let close = current
+                .last()
+                .is_some_and(|last| transaction.timestamp - last.timestamp <= window_hours * 3600);
+            if !close && current.len() > 1 {
+                groups.push(DuplicateGroup { transactions: std::mem::take(&mut current) });
+            } else if !close {
+                current.clear();
+            }
+            current.push(transaction);
+        }
+        if current.len() > 1 {
+            groups.push(DuplicateGroup { transactions: current });
+        }
+    }
+    groups.sort_by(|a, b| b.transactions[0].timestamp.cmp(&a.transactions[0].timestamp));
+    groups
+}
+
+// --- Comandos Tauri ---
+
+/// Comando para buscar transacciones repetidas. Sin `window_hours` se usa el margen
+/// configurado. Devuelve primero los grupos más recientes.
+#[tauri::command]
+pub async fn find_duplicates_command(
+    state: State<'_, AppState>,
+    window_hours: Option<u64>,
+) -> Result<Vec<DuplicateGroup>, AppError> {
+    debug!("Received find_duplicates_command: {:?}", window_hours);
+    let db = state.db().await?;
+    let window_hours = match window_hours {
+        Some(hours) => validate_window(hours)?,
+        None => load_duplicate_window(&db),
+    };
+    let groups = group_duplicates(db.list_transactions()?, window_hours);
+    debug!("Found {} groups of possible duplicates.", groups.len());
+    Ok(groups)
+}
+
+/// Comando para cambiar el margen, en horas, con el que se detectan transacciones repetidas.
+#[tauri::command]
+pub async fn set_duplicate_window_command(state: State<'_, AppState>, window_hours: u64) -> Result<u64, AppError> {
+    debug!("Received set_duplicate_window_command: {}", window_hours);
+    let window_hours = validate_window(window_hours)?;
+    let db = state.db().await?;
+    let previous = load_duplicate_window(&db);
+    db.set_setting(DUPLICATE_WINDOW_KEY, &window_hours.to_string())?;
+    audit::record(
+        db.connection(),
+        "set_duplicate_window_command",
+        Some(DUPLICATE_WINDOW_KEY),
+        audit::snapshot(&previous),
+        audit::snapshot(&window_hours),
+    );
+    Ok(window_hours)
+}
@@ -0,0 +1,310 @@
+// src-tauri/src/email.rs
+
+use chrono::{Datelike, Duration as ChronoDuration, NaiveDate};
+use lettre::message::header::ContentType;
+use lettre::message::{Attachment, Mailbox, MultiPart, SinglePart};
+use lettre::transport::smtp::authentication::Credentials;
+use lettre::{AsyncSmtpTransport, AsyncTransport, Message, Tokio1Executor};
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::time::Duration;
+use tauri::{AppHandle, Manager};
+use log::{debug, error, info};
+
+use crate::currencies::RateTable;
+use crate::error::AppError;
+use crate::i18n::{self, Language};
+use crate::keychain;
+use crate::pdf::{PdfWriter, MARGIN_MM};
+use crate::periods::{self, Period};
+use crate::reports::{csv_field, GroupTotals};
+use crate::settings::{self, Settings};
+use crate::storage::{SqliteStorage, TransactionRepository};
+use crate::summaries::{self, MonthContext};
+use crate::{AppState, Transaction, TransactionType};
+
+/// Nombre de la contraseña SMTP en el llavero.
+const PASSWORD_SECRET: &str = "smtp_password";
+/// Último mes (`AAAA-MM`) cuyo informe se envió de forma programada.
+const LAST_SCHEDULED_KEY: &str = "report_email_last_month";
+const SMTP_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Cifrado de la conexión con el servidor SMTP.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SmtpSecurity {
+    /// Conexión en claro que pasa a TLS con STARTTLS (normalmente el puerto 587).
+    StartTls,
+    /// TLS desde el principio (normalmente el puerto 465).
+    Tls,
+    /// Sin cifrar; solo para servidores de la red local.
+    None,
+}
+
+/// Informe enviado.
+#[derive(Debug, Clone, Serialize)]
+pub struct ReportEmailSent {
+    pub month: String,
+    pub recipient: String,
+    pub sent_at: u64,
+}
+
+fn parse_mailbox(field: &str, address: &str) -> Result<Mailbox, AppError> {
+    address
+        .trim()
+        .parse::<Mailbox>()
+        .map_err(|_| AppError::invalid_field(field, format!("La dirección de correo {} no es válida.", address.trim())))
+}
+
+/// Comprueba los ajustes del correo. Con el envío mensual activado hacen falta el
+/// servidor, el remitente y el destinatario.
+pub fn validate_settings(settings: &Settings) -> Result<(), AppError> {
+    if !settings.smtp_from.is_empty() {
+        parse_mailbox("smtp_from", &settings.smtp_from)?;
+    }
+    if !settings.report_email_recipient.is_empty() {
+        parse_mailbox("report_email_recipient", &settings.report_email_recipient)?;
+    }
+    if settings.smtp_port == 0 {
+        return Err(AppError::invalid_field("smtp_port", "El puerto SMTP no es válido."));
+    }
+    if settings.report_email_monthly {
+        if settings.smtp_host.is_empty() {
+            return Err(AppError::invalid_field("smtp_host", "Indica el servidor SMTP."));
+        }
+        if settings.smtp_from.is_empty() {
+            return Err(AppError::invalid_field("smtp_from", "Indica la dirección del remitente."));
+        }
+        if settings.report_email_recipient.is_empty() {
+            return Err(AppError::invalid_field("report_email_recipient", "Indica a quién enviar el informe mensual."));
+        }
+    }
+    Ok(())
+}
+
+// --- Informe ---
+
+/// Transacciones activas del mes que empieza en `start`, por fecha.
+fn month_transactions(transactions: Vec<Transaction>, start: NaiveDate) -> Vec<Transaction> {
+    let from = periods::local_midnight_timestamp(start);
+    let to = periods::local_midnight_timestamp(Period::Mensual.next_start_date(start));
+    let mut selected: Vec<Transaction> =
+        transactions.into_iter().filter(|t| t.timestamp >= from && t.timestamp < to).collect();
+    selected.sort_by_key(|t| t.timestamp);
+    selected
+}
+
+/// CSV con las transacciones: importes con punto decimal, sin separador de miles, en
+/// su moneda y en la moneda base. Las cabeceras no se traducen; el tipo, sí. También
+/// lo usa la exportación desde la línea de comandos (ver `cli`).
+pub fn render_csv(transactions: &[Transaction], rates: &RateTable, language: Language) -> Result<Vec<u8>, AppError> {
+    let mut csv = String::from("fecha,tipo,descripcion,tienda,categoria,subcategoria,importe,moneda,importe_base\n");
+    for t in transactions {
+        let kind = match t.transaction_type {
+            TransactionType::Ingreso => i18n::text(language, "type.income"),
+            TransactionType::Gasto => i18n::text(language, "type.expense"),
+            TransactionType::Transferencia => i18n::text(language, "type.transfer"),
+        };
+        let row = [
+            t.transaction_date.format("%Y-%m-%d").to_string(),
+            kind.to_string(),
+            t.description.clone(),
+            t.store_name.clone(),
+            t.category.clone().unwrap_or_default(),
+            t.subcategory.clone().unwrap_or_default(),
+            t.amount.to_string(),
+            t.currency.clone(),
+            rates.to_base(t.amount, &t.currency)?.round_dp(2).to_string(),
+        ];
+        csv.push_str(&row.iter().map(|v| csv_field(v)).collect::<Vec<_>>().join(","));
+        csv.push('\n');
+    }
+    Ok(csv.into_bytes())
+}
+
+/// PDF con los totales del mes, comparados con el anterior, y el desglose por
+/// categoría y por tienda.
+fn render_pdf(context: &MonthContext, settings: &Settings) -> Result<Vec<u8>, AppError> {
+    let language = settings.language();
+    let title = i18n::format(language, "report.monthly_title", &[("month", &context.month)]);
+    let mut pdf = PdfWriter::new(&title, i18n::text(language, "report.layer"))?;
+    let money = |amount| settings.format_money(amount, &context.base_currency, &context.base_currency);
+
+    pdf.text(MARGIN_MM, &title, 18.0, true);
+    pdf.newline();
+    pdf.newline();
+    let columns = [MARGIN_MM, 110.0, 150.0];
+    pdf.text(columns[1], &context.month, 10.0, true);
+    pdf.text(columns[2], &context.previous_month, 10.0, true);
+    pdf.newline();
+    let rows: [(&str, fn(&GroupTotals) -> Decimal); 3] =
+        [("report.income", |t| t.income), ("report.expenses", |t| t.expenses), ("report.net", |t| t.net)];
+    for (key, value) in rows {
+        pdf.text(columns[0], i18n::text(language, key), 10.0, key == "report.net");
+        pdf.text(columns[1], &money(value(&context.totals)), 10.0, false);
+        pdf.text(columns[2], &money(value(&context.previous_totals)), 10.0, false);
+        pdf.newline();
+    }
+
+    let sections: [(&str, &BTreeMap<String, [GroupTotals; 2]>); 2] =
+        [("report.by_category", &context.by_category), ("report.by_store", &context.by_store)];
+    for (heading, groups) in sections {
+        pdf.newline();
+        pdf.text(MARGIN_MM, i18n::text(language, heading), 12.0, true);
+        pdf.newline();
+        for (header, x) in ["report.income", "report.expenses"].iter().zip(&columns[1..]) {
+            pdf.text(*x, i18n::text(language, header), 10.0, true);
+        }
+        pdf.newline();
+        for (name, [current, _]) in groups.iter().filter(|(_, [current, _])| current.transaction_count > 0) {
+            pdf.text(columns[0], name, 10.0, false);
+            pdf.text(columns[1], &money(current.income), 10.0, false);
+            pdf.text(columns[2], &money(current.expenses), 10.0, false);
+            pdf.newline();
+        }
+    }
+    pdf.finish()
+}
+
+/// Construye el correo del informe de `start` con el PDF y el CSV adjuntos.
+fn build_message(db: &SqliteStorage, settings: &Settings, start: NaiveDate, recipient: &Mailbox) -> Result<Message, AppError> {
+    let rates = RateTable::load(db)?;
+    let transactions = db.list_transactions()?;
+    let context = summaries::build_month_context(&transactions, &rates, start)?;
+    let month_transactions = month_transactions(transactions, start);
+    let pdf = render_pdf(&context, settings)?;
+    let language = settings.language();
+    let csv = render_csv(&month_transactions, &rates, language)?;
+
+    let money = |amount| settings.format_money(amount, &context.base_currency, &context.base_currency);
+    let body = i18n::format(
+        language,
+        "report.email_body",
+        &[
+            ("month", &context.month),
+            ("income", &money(context.totals.income)),
+            ("expenses", &money(context.totals.expenses)),
+            ("net", &money(context.totals.net)),
+            ("count", &context.totals.transaction_count),
+        ],
+    );
+    let from = parse_mailbox("smtp_from", &settings.smtp_from)?;
+    let csv_type = ContentType::parse("text/csv; charset=utf-8").map_err(|e| AppError::Internal(e.to_string()))?;
+    let pdf_type = ContentType::parse("application/pdf").map_err(|e| AppError::Internal(e.to_string()))?;
+    Message::builder()
+        .from(from)
+        .to(recipient.clone())
+        .subject(i18n::format(language, "report.monthly_title", &[("month", &context.month)]))
+        .multipart(
+            MultiPart::mixed()
+                .singlepart(SinglePart::plain(body))
+                .singlepart(
+                    Attachment::new(i18n::format(language, "report.pdf_file", &[("month", &context.month)]))
+                        .body(pdf, pdf_type),
+                )
+                .singlepart(
+                    Attachment::new(i18n::format(language, "report.csv_file", &[("month", &context.month)]))
+                        .body(csv, csv_type),
+                ),
+        )
+        .map_err(|e| AppError::Internal(format!("No se pudo componer el correo: {}", e)))
+}
+
+fn transport(settings: &Settings) -> Result<AsyncSmtpTransport<Tokio1Executor>, AppError> {
+    if settings.smtp_host.is_empty() {
+        return Err(AppError::invalid_field("smtp_host", "Configura el servidor SMTP en los ajustes."));
+    }
+    let host = settings.smtp_host.as_str();
+    let builder = match settings.smtp_security {
+        SmtpSecurity::StartTls => AsyncSmtpTransport::<Tokio1Executor>::starttls_relay(host),
+        SmtpSecurity::Tls => AsyncSmtpTransport::<Tokio1Executor>::relay(host),
+        SmtpSecurity::None => Ok(AsyncSmtpTransport::<Tokio1Executor>::builder_dangerous(host)),
+    }
+    .map_err(|e| AppError::invalid_field("smtp_host", format!("Servidor SMTP no válido: {}", e)))?;
+    let builder = builder.port(settings.smtp_port).timeout(Some(SMTP_TIMEOUT));
+    let builder = match keychain::get_secret(PASSWORD_SECRET)? {
+        Some(password) if !settings.smtp_username.is_empty() => {
+            builder.credentials(Credentials::new(settings.smtp_username.clone(), password))
+        }
+        _ => builder,
+    };
+    Ok(builder.build())
+}
+
+/// Envía el informe del mes `month` (`AAAA-MM`) a `recipient`.
+async fn send_report(app: &AppHandle, month: &str, recipient: &str) -> Result<ReportEmailSent, AppError> {
+    let start = summaries::parse_month(month)?;
+    if start > periods::today() {
+        return Err(AppError::invalid_field("month", "No se puede enviar el informe de un mes futuro."));
+    }
+    let recipient = parse_mailbox("recipient", recipient)?;
+    let state = app.state::<AppState>();
+    let (message, transport) = {
+        let db = state.db().await?;
+        let settings = settings::load_settings(&db);
+        if settings.smtp_from.is_empty() {
+            return Err(AppError::invalid_field("smtp_from", "Configura la dirección del remitente en los ajustes."));
+        }
+        (build_message(&db, &settings, start, &recipient)?, transport(&settings)?)
+    };
+    transport.send(message).await.map_err(|e| {
+        error!("SMTP send failed: {}", e);
+        AppError::Network(format!("No se pudo enviar el correo: {}", e))
+    })?;
+    let month = start.format("%Y-%m").to_string();
+    info!("Report for {} emailed.", month);
+    Ok(ReportEmailSent { month, recipient: recipient.email.to_string(), sent_at: periods::now_timestamp() })
+}
+
+/// Mes anterior a hoy (`AAAA-MM`).
+fn previous_month() -> String {
+    let today = periods::today();
+    (today.with_day(1).unwrap_or(today) - ChronoDuration::days(1)).format("%Y-%m").to_string()
+}
+
+/// Envía el informe del mes anterior si el envío mensual está activado y aún no se
+/// ha hecho, o siempre con `force`. Si la aplicación no estaba abierta el día 1, se
+/// envía al abrirla. Es la tarea `report_email` del planificador (ver `scheduler`).
+pub async fn send_scheduled(app: &AppHandle, force: bool) -> Result<String, AppError> {
+    let state = app.state::<AppState>();
+    if state.is_locked() {
+        return Ok("Datos bloqueados: no se ha enviado el informe.".to_string());
+    }
+    let month = previous_month();
+    let recipient = {
+        let db = state.db().await?;
+        let settings = settings::load_settings(&db);
+        if !force && !settings.report_email_monthly {
+            return Ok("El envío mensual está desactivado.".to_string());
+        }
+        if !force && db.get_setting(LAST_SCHEDULED_KEY)?.as_deref() == Some(month.as_str()) {
+            return Ok(format!("El informe de {} ya se envió.", month));
+        }
+        settings.report_email_recipient
+    };
+    let sent = send_report(app, &month, &recipient).await?;
+    state.db().await?.set_setting(LAST_SCHEDULED_KEY, &month)?;
+    Ok(format!("Informe de {} enviado a {}.", sent.month, sent.recipient))
+}
+
+// --- Comandos Tauri ---
+
+/// Comando para guardar en el llavero la contraseña SMTP. Vacía, la borra.
+#[tauri::command]
+pub async fn set_smtp_password_command(password: String) -> Result<(), AppError> {
+    debug!("Received set_smtp_password_command.");
+    if password.is_empty() {
+        keychain::delete_secret(PASSWORD_SECRET)
+    } else {
+        keychain::set_secret(PASSWORD_SECRET, &password)
+    }
+}
+
+/// Comando para enviar por correo el informe del mes `month` (`AAAA-MM`) a
+/// `recipient`, con el resumen en PDF y las transacciones en CSV adjuntos.
+#[tauri::command]
+pub async fn send_report_email_command(app: AppHandle, month: String, recipient: String) -> Result<ReportEmailSent, AppError> {
+    debug!("Received send_report_email_command: {} -> {}", month, recipient);
+    send_report(&app, &month, &recipient).await
+}
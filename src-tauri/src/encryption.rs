@@ -0,0 +1,230 @@
+// src-tauri/src/encryption.rs
+
+use aes_gcm::aead::rand_core::RngCore;
+use aes_gcm::aead::{Aead, AeadCore, KeyInit, OsRng};
+use aes_gcm::{Aes256Gcm, Key, Nonce};
+use argon2::Argon2;
+use serde::Serialize;
+use std::io::Read;
+use std::path::Path;
+use tauri::{AppHandle, State};
+use log::{debug, error, info, warn};
+
+use crate::attachments;
+use crate::audit;
+use crate::error::AppError;
+use crate::events;
+use crate::history;
+use crate::i18n;
+use crate::storage::{self, RecoveryReport};
+use crate::AppState;
+
+/// Cabecera de los archivos cifrados: identificador y versión del formato.
+const MAGIC: &[u8; 8] = b"CIAENC01";
+const SALT_LEN: usize = 16;
+const NONCE_LEN: usize = 12;
+const KEY_LEN: usize = 32;
+const HEADER_LEN: usize = MAGIC.len() + SALT_LEN + NONCE_LEN;
+const MIN_PASSPHRASE_LEN: usize = 8;
+
+/// Clave AES-256 derivada con Argon2id de la contraseña del usuario y de la sal
+/// guardada en la cabecera del archivo cifrado.
+#[derive(Clone)]
+pub struct DataKey {
+    key: [u8; KEY_LEN],
+    salt: [u8; SALT_LEN],
+}
+
+impl std::fmt::Debug for DataKey {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("DataKey(..)")
+    }
+}
+
+impl DataKey {
+    /// Deriva la clave de `passphrase` con una sal nueva.
+    pub fn generate(passphrase: &str) -> Result<Self, AppError> {
+        let mut salt = [0u8; SALT_LEN];
+        OsRng.fill_bytes(&mut salt);
+        Self::derive(passphrase, salt)
+    }
+
+    fn derive(passphrase: &str, salt: [u8; SALT_LEN]) -> Result<Self, AppError> {
+        let mut key = [0u8; KEY_LEN];
+        Argon2::default()
+            .hash_password_into(passphrase.as_bytes(), &salt, &mut key)
+            .map_err(|e| {
+                error!("Argon2 key derivation failed: {}", e);
+                AppError::Internal(format!("No se pudo derivar la clave de cifrado: {}", e))
+            })?;
+        Ok(DataKey { key, salt })
+    }
+
+    /// Comprueba si `passphrase` es la contraseña con la que se derivó esta clave.
+    pub fn matches(&self, passphrase: &str) -> bool {
+        Self::derive(passphrase, self.salt).map(|k| k.key == self.key).unwrap_or(false)
+    }
+
+    /// Cifra `plaintext` y devuelve el archivo completo: cabecera, sal, nonce y texto cifrado.
+    pub fn encrypt(&self, plaintext: &[u8]) -> Result<Vec<u8>, AppError> {
+        let cipher = Aes256Gcm::new(Key::<Aes256Gcm>::from_slice(&self.key));
+        let nonce = Aes256Gcm::generate_nonce(&mut OsRng);
+        let ciphertext = cipher
+            .encrypt(&nonce, plaintext)
+            .map_err(|_| AppError::Internal("Error al cifrar los datos.".to_string()))?;
+
+        let mut output = Vec::with_capacity(HEADER_LEN + ciphertext.len());
+        output.extend_from_slice(MAGIC);
+        output.extend_from_slice(&self.salt);
+        output.extend_from_slice(&nonce);
+        output.extend_from_slice(&ciphertext);
+        Ok(output)
+    }
+
+    /// Descifra un archivo cifrado con esta clave. Falla si la sal no coincide, si la
+    /// contraseña es otra o si el archivo se ha alterado.
+    pub fn decrypt(&self, data: &[u8]) -> Result<Vec<u8>, AppError> {
+        let salt = read_salt(data).ok_or_else(|| AppError::Internal("El archivo no está cifrado.".to_string()))?;
+        if salt != self.salt {
+            return Err(AppError::invalid_field("passphrase", "El archivo se cifró con otra contraseña."));
+        }
+        let cipher = Aes256Gcm::new(Key::<Aes256Gcm>::from_slice(&self.key));
+        let nonce = Nonce::from_slice(&data[MAGIC.len() + SALT_LEN..HEADER_LEN]);
+        cipher
+            .decrypt(nonce, &data[HEADER_LEN..])
+            .map_err(|_| AppError::invalid_field("passphrase", "Contraseña incorrecta o datos dañados."))
+    }
+}
+
+fn read_salt(data: &[u8]) -> Option<[u8; SALT_LEN]> {
+    if data.len() < HEADER_LEN || !data.starts_with(MAGIC) {
+        return None;
+    }
+    data[MAGIC.len()..MAGIC.len() + SALT_LEN].try_into().ok()
+}
+
+/// Deriva la clave con la sal del archivo y lo descifra.
+pub fn unlock(passphrase: &str, data: &[u8]) -> Result<(DataKey, Vec<u8>), AppError> {
+    let salt = read_salt(data).ok_or_else(|| AppError::Internal("El archivo no está cifrado.".to_string()))?;
+    let key = DataKey::derive(passphrase, salt)?;
+    let plaintext = key.decrypt(data)?;
+    Ok((key, plaintext))
+}
+
+/// Indica si el archivo de `path` tiene la cabecera de un archivo cifrado.
+pub fn is_encrypted_file(path: &Path) -> bool {
+    let mut header = [0u8; 8];
+    std::fs::File::open(path)
+        .and_then(|mut f| f.read_exact(&mut header))
+        .map(|_| &header == MAGIC)
+        .unwrap_or(false)
+}
+
+/// Estado del cifrado para el frontend.
+#[derive(Debug, Clone, Serialize)]
+pub struct EncryptionStatus {
+    pub encrypted: bool,
+    pub locked: bool,
+}
+
+// --- Comandos Tauri ---
+
+/// Comando para activar, cambiar o quitar la contraseña de cifrado de los datos.
+/// Con `passphrase` vacía o ausente se descifran los datos. Si ya están cifrados,
+/// `current_passphrase` debe ser la contraseña actual. Los justificantes adjuntos
+/// no se cifran.
+#[tauri::command]
+pub async fn set_encryption_passphrase_command(
+    state: State<'_, AppState>,
+    passphrase: Option<String>,
+    current_passphrase: Option<String>,
+) -> Result<EncryptionStatus, AppError> {
+    debug!("Received set_encryption_passphrase_command.");
+    let passphrase = passphrase.filter(|p| !p.is_empty());
+    if let Some(p) = &passphrase {
+        if p.chars().count() < MIN_PASSPHRASE_LEN {
+            error!("Encryption passphrase too short.");
+            return Err(AppError::invalid_field(
+                "passphrase",
+                format!("La contraseña debe tener al menos {} caracteres.", MIN_PASSPHRASE_LEN),
+            ));
+        }
+    }
+
+    let mut db = state.db().await?;
+    let was_encrypted = db.encryption_key().is_some();
+    if let Some(key) = db.encryption_key() {
+        let current = current_passphrase.unwrap_or_default();
+        if !key.matches(&current) {
+            error!("Wrong current passphrase when changing encryption.");
+            return Err(AppError::invalid_field("current_passphrase", "La contraseña actual no es correcta."));
+        }
+    }
+    if !was_encrypted && passphrase.is_none() {
+        return Ok(EncryptionStatus { encrypted: false, locked: false });
+    }
+
+    let new_key = passphrase.as_deref().map(DataKey::generate).transpose()?;
+    db.set_encryption_key(new_key)?;
+    if let Err(e) = db.write_backup(&storage::get_backup_path()) {
+        warn!("No se pudo actualizar la copia de seguridad tras cambiar el cifrado: {}", e);
+    }
+
+    let encrypted = passphrase.is_some();
+    audit::record(
+        db.connection(),
+        "set_encryption_passphrase_command",
+        None,
+        Some(serde_json::json!({"encrypted": was_encrypted})),
+        Some(serde_json::json!({"encrypted": encrypted})),
+    );
+    info!("Data encryption {}.", if encrypted { "enabled" } else { "disabled" });
+    Ok(EncryptionStatus { encrypted, locked: false })
+}
+
+/// Comando para desbloquear los datos cifrados al arrancar. Si la base de datos
+/// está dañada se restaura desde la copia `.bak` y se devuelve el informe.
+#[tauri::command]
+pub async fn unlock_data_command(
+    state: State<'_, AppState>,
+    app: AppHandle,
+    passphrase: String,
+) -> Result<Option<RecoveryReport>, AppError> {
+    debug!("Received unlock_data_command.");
+    if !state.is_locked() {
+        return Ok(None);
+    }
+
+    let (unlocked, recovery) = storage::open_encrypted_with_recovery(
+        &storage::get_database_path(),
+        &storage::get_backup_path(),
+        &passphrase,
+    )?;
+    if let Err(e) = attachments::cleanup_orphan_attachments(&unlocked) {
+        warn!("No se pudieron limpiar los adjuntos huérfanos: {}", e);
+    }
+    let depth = history::load_history_depth(&unlocked);
+
+    let mut db = state.db.lock().await;
+    *db = unlocked;
+    i18n::apply_settings(&db);
+    {
+        let mut history = state.history.lock().unwrap();
+        history.clear();
+        history.set_max_depth(depth);
+    }
+    state.set_locked(false);
+    events::emit_data_reloaded(&app);
+    info!("Encrypted data unlocked.");
+    Ok(recovery)
+}
+
+/// Comando para saber si los datos están cifrados y si siguen bloqueados.
+#[tauri::command]
+pub async fn get_encryption_status_command(state: State<'_, AppState>) -> Result<EncryptionStatus, AppError> {
+    if state.is_locked() {
+        return Ok(EncryptionStatus { encrypted: true, locked: true });
+    }
+    let db = state.db().await?;
+    Ok(EncryptionStatus { encrypted: db.encryption_key().is_some(), locked: false })
+}
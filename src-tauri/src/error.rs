@@ -0,0 +1,208 @@
+// src-tauri/src/error.rs
+
+use serde::ser::SerializeStruct;
+use serde::{Serialize, Serializer};
+use log::error;
+
+use crate::ai::AiFeature;
+use crate::i18n::{self, Language};
+use crate::validation::{self, ValidationError};
+
+/// Mensaje con el que abortan los triggers de los meses cerrados (migración v24).
+pub const PERIOD_CLOSED_MARKER: &str = "PERIOD_CLOSED";
+
+/// Error devuelto por los comandos. Se serializa como
+/// `{ "code": "...", "message": "...", "field": "..." | null }` para que el frontend
+/// pueda distinguir el tipo de error y mostrar el mensaje traducido. El mensaje sale
+/// en el idioma de los ajustes (ver `i18n`); fuera del español, `detail` lleva el
+/// mensaje original. Los errores `duplicate` incluyen además `duplicate_ids`, los
+/// `rate_limited`, `retry_after_secs`, los `queued`, `request_id`, los
+/// `ai_disabled`, `feature`, y los `validation`, `errors` con cada campo erróneo.
+#[derive(Debug, thiserror::Error)]
+pub enum AppError {
+    /// Datos de entrada no válidos. `field` indica el argumento afectado, si lo hay, y
+    /// `errors` todos los campos con error (ver `validation`).
+    #[error("{message}")]
+    Validation { message: String, field: Option<String>, errors: Vec<ValidationError> },
+    #[error("{0}")]
+    NotFound(String),
+    /// La operación choca con datos existentes (p. ej. un nombre duplicado).
+    #[error("{0}")]
+    Conflict(String),
+    /// La transacción parece repetir otras ya registradas (`duplicates` son sus IDs).
+    /// El frontend puede pedir confirmación y repetir la operación sin comprobarlo.
+    #[error("{message}")]
+    Duplicate { message: String, duplicates: Vec<String> },
+    #[error("Los datos están cifrados. Introduce la contraseña para desbloquearlos.")]
+    Locked,
+    #[error("{0}")]
+    Database(String),
+    #[error("{0}")]
+    Io(String),
+    #[error("{0}")]
+    Network(String),
+    /// La IA respondió, pero con un error o con un contenido inutilizable.
+    #[error("{0}")]
+    Ai(String),
+    /// Se superó el límite de peticiones de un servicio externo. `retry_after_secs`
+    /// indica, si se sabe, cuánto esperar antes de volver a intentarlo.
+    #[error("{message}")]
+    RateLimited { message: String, retry_after_secs: Option<u64> },
+    /// Se alcanzó un límite configurado por el usuario (p. ej. el gasto mensual en IA).
+    #[error("{0}")]
+    LimitReached(String),
+    /// La transacción cae en un mes cerrado (ver `closing`): hay que reabrirlo para
+    /// modificarla.
+    #[error("{0}")]
+    PeriodClosed(String),
+    /// No hay conexión y la petición a la IA se ha guardado para enviarla más tarde.
+    /// El resultado llegará con el evento `ai-queue://completed` de `request_id`.
+    #[error("{message}")]
+    Queued { message: String, request_id: String },
+    /// El usuario ha desactivado la IA, o la función `feature`, en los ajustes: no se
+    /// ha enviado nada.
+    #[error("{message}")]
+    AiDisabled { message: String, feature: Option<AiFeature> },
+    #[error("{0}")]
+    Internal(String),
+}
+
+impl AppError {
+    pub fn validation(message: impl Into<String>) -> Self {
+        AppError::Validation { message: message.into(), field: None, errors: Vec::new() }
+    }
+
+    pub fn invalid_field(field: &str, message: impl Into<String>) -> Self {
+        AppError::field_error(field, validation::INVALID, message)
+    }
+
+    /// Error de un solo campo con un código de `validation` (p. ej. `REQUIRED`).
+    pub fn field_error(field: &str, code: &str, message: impl Into<String>) -> Self {
+        AppError::invalid_fields(vec![ValidationError::new(field, code, message)])
+    }
+
+    /// Error con varios campos erróneos. El mensaje y el campo principales son los del
+    /// primero, para los clientes que solo miran esos.
+    pub fn invalid_fields(errors: Vec<ValidationError>) -> Self {
+        let (message, field) = match errors.as_slice() {
+            [] => ("Los datos no son válidos.".to_string(), None),
+            [only] => (only.message.clone(), Some(only.field.clone())),
+            [first, ..] => (more_errors(Language::Es, &first.message, errors.len() - 1), Some(first.field.clone())),
+        };
+        AppError::Validation { message, field, errors }
+    }
+
+    /// Código estable del tipo de error.
+    pub fn code(&self) -> &'static str {
+        match self {
+            AppError::Validation { .. } => "validation",
+            AppError::NotFound(_) => "not_found",
+            AppError::Conflict(_) => "conflict",
+            AppError::Duplicate { .. } => "duplicate",
+            AppError::Locked => "locked",
+            AppError::Database(_) => "database",
+            AppError::Io(_) => "io",
+            AppError::Network(_) => "network",
+            AppError::Ai(_) => "ai",
+            AppError::RateLimited { .. } => "rate_limited",
+            AppError::LimitReached(_) => "limit_reached",
+            AppError::PeriodClosed(_) => "period_closed",
+            AppError::Queued { .. } => "queued",
+            AppError::AiDisabled { .. } => "ai_disabled",
+            AppError::Internal(_) => "internal",
+        }
+    }
+
+    pub fn field(&self) -> Option<&str> {
+        match self {
+            AppError::Validation { field, .. } => field.as_deref(),
+            _ => None,
+        }
+    }
+
+    /// Mensaje en `language`. En español es el mensaje detallado; en otros idiomas, el
+    /// del catálogo para el código del error (o de cada campo, en los de validación).
+    pub fn localized_message(&self, language: Language) -> String {
+        if language == Language::Es {
+            return self.to_string();
+        }
+        match self {
+            AppError::Validation { errors, .. } if !errors.is_empty() => {
+                let first = localize_field_error(language, &errors[0]).message;
+                if errors.len() == 1 {
+                    first
+                } else {
+                    more_errors(language, &first, errors.len() - 1)
+                }
+            }
+            _ => i18n::text(language, &format!("error.{}", self.code())).to_owned(),
+        }
+    }
+}
+
+fn more_errors(language: Language, message: &str, count: usize) -> String {
+    i18n::format(language, "validation.more_errors", &[("message", &message), ("count", &count)])
+}
+
+fn localize_field_error(language: Language, error: &ValidationError) -> ValidationError {
+    if language == Language::Es {
+        return error.clone();
+    }
+    let message = i18n::text(language, &format!("validation.{}", error.code));
+    ValidationError::new(&error.field, &error.code, message)
+}
+
+impl Serialize for AppError {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let language = Language::current();
+        let mut state = serializer.serialize_struct("AppError", 9)?;
+        state.serialize_field("code", self.code())?;
+        state.serialize_field("message", &self.localized_message(language))?;
+        state.serialize_field("field", &self.field())?;
+        // Fuera del español se conserva el mensaje detallado original para soporte.
+        if language == Language::Es {
+            state.skip_field("detail")?;
+        } else {
+            state.serialize_field("detail", &self.to_string())?;
+        }
+        match self {
+            AppError::Duplicate { duplicates, .. } => state.serialize_field("duplicate_ids", duplicates)?,
+            _ => state.skip_field("duplicate_ids")?,
+        }
+        match self {
+            AppError::RateLimited { retry_after_secs, .. } => state.serialize_field("retry_after_secs", retry_after_secs)?,
+            _ => state.skip_field("retry_after_secs")?,
+        }
+        match self {
+            AppError::Queued { request_id, .. } => state.serialize_field("request_id", request_id)?,
+            _ => state.skip_field("request_id")?,
+        }
+        match self {
+            AppError::AiDisabled { feature, .. } => state.serialize_field("feature", feature)?,
+            _ => state.skip_field("feature")?,
+        }
+        match self {
+            AppError::Validation { errors, .. } => {
+                let errors: Vec<ValidationError> = errors.iter().map(|e| localize_field_error(language, e)).collect();
+                state.serialize_field("errors", &errors)?
+            }
+            _ => state.skip_field("errors")?,
+        }
+        state.end()
+    }
+}
+
+impl From<rusqlite::Error> for AppError {
+    fn from(e: rusqlite::Error) -> Self {
+        if let rusqlite::Error::SqliteFailure(_, Some(message)) = &e {
+            if message == PERIOD_CLOSED_MARKER {
+                return AppError::PeriodClosed(
+                    "La transacción pertenece a un mes cerrado o a un día con la caja cerrada. Reábrelo para poder modificarla."
+                        .to_string(),
+                );
+            }
+        }
+        error!("Database error: {}", e);
+        AppError::Database(format!("Error de base de datos: {}", e))
+    }
+}
@@ -0,0 +1,132 @@
+// src-tauri/src/events.rs
+
+use serde::Serialize;
+use tauri::{AppHandle, Emitter};
+use log::warn;
+
+use crate::aggregates;
+use crate::history::Change;
+use crate::webhooks;
+
+// Eventos que emiten los comandos que modifican datos, para que todas las ventanas y
+// vistas se actualicen sin volver a pedirlo todo.
+
+/// Transacción nueva o sacada de la papelera. Carga: la `Transaction`.
+pub const TRANSACTION_CREATED_EVENT: &str = "transaction://created";
+/// Transacción modificada. Carga: la `Transaction` tal como queda.
+pub const TRANSACTION_UPDATED_EVENT: &str = "transaction://updated";
+/// Transacción movida a la papelera o borrada. Carga: `TransactionDeleted`.
+pub const TRANSACTION_DELETED_EVENT: &str = "transaction://deleted";
+/// Tienda creada o con datos cambiados. Carga: la `Store`.
+pub const STORE_UPDATED_EVENT: &str = "store://updated";
+/// Carga: `StoreRenamed`.
+pub const STORE_RENAMED_EVENT: &str = "store://renamed";
+/// Carga: `StoresMerged`.
+pub const STORE_MERGED_EVENT: &str = "store://merged";
+/// Carga: el nombre de la tienda.
+pub const STORE_DELETED_EVENT: &str = "store://deleted";
+/// Cambios en otras entidades. Carga: `EntityChanged`.
+pub const CATEGORY_CHANGED_EVENT: &str = "category://changed";
+pub const CONTACT_CHANGED_EVENT: &str = "contact://changed";
+pub const ACCOUNT_CHANGED_EVENT: &str = "account://changed";
+pub const BUDGET_CHANGED_EVENT: &str = "budget://changed";
+pub const GOAL_CHANGED_EVENT: &str = "goal://changed";
+pub const CURRENCY_CHANGED_EVENT: &str = "currency://changed";
+pub const INVOICE_CHANGED_EVENT: &str = "invoice://changed";
+pub const TAG_CHANGED_EVENT: &str = "tag://changed";
+/// Prompt personalizado o devuelto al de la aplicación; `id` es el nombre de la plantilla.
+pub const PROMPT_CHANGED_EVENT: &str = "prompt://changed";
+/// Tarea programada con su programación o su estado cambiados; `id` es la tarea.
+pub const JOB_CHANGED_EVENT: &str = "job://changed";
+/// Mes cerrado (`Created`) o reabierto (`Deleted`); `id` es el mes `AAAA-MM`.
+pub const PERIOD_CHANGED_EVENT: &str = "period://changed";
+/// Caja de un día cerrada (`Created`) o reabierta (`Deleted`); `id` es la fecha
+/// `AAAA-MM-DD`.
+pub const CASH_CLOSING_CHANGED_EVENT: &str = "cash-closing://changed";
+/// Usuario de la aplicación creado, modificado o eliminado; `id` es su ID.
+pub const USER_CHANGED_EVENT: &str = "user://changed";
+/// Se abrió o se cerró la sesión de un usuario. Carga: el `users::User` con la sesión
+/// abierta, o `null`.
+pub const SESSION_CHANGED_EVENT: &str = "session://changed";
+/// Se cargaron datos completamente distintos (copia restaurada, otro espacio de
+/// trabajo, datos desbloqueados): hay que volver a pedirlo todo. Sin carga.
+pub const DATA_RELOADED_EVENT: &str = "data://reloaded";
+/// Terminó la carga en segundo plano del arranque (ver `startup`). Carga: `DataReady`.
+pub const DATA_READY_EVENT: &str = "data://ready";
+
+/// Qué le pasó a una entidad.
+#[derive(Debug, Clone, Copy, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ChangeAction {
+    Created,
+    Updated,
+    Deleted,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct EntityChanged {
+    pub action: ChangeAction,
+    /// ID o nombre de la entidad.
+    pub id: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct TransactionDeleted {
+    pub id: String,
+    /// `false` si se movió a la papelera y aún se puede restaurar.
+    pub permanent: bool,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct StoreRenamed {
+    pub old_name: String,
+    pub new_name: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct StoresMerged {
+    pub source_names: Vec<String>,
+    pub target_name: String,
+}
+
+/// Emite `event` a todas las ventanas y, si es de los que se reenvían, a los webhooks.
+/// Un fallo solo se registra: el cambio ya se guardó.
+pub fn emit<S: Serialize + Clone>(app: &AppHandle, event: &str, payload: S) {
+    webhooks::notify(event, &payload);
+    if let Err(e) = app.emit(event, payload) {
+        warn!("Failed to emit {}: {}", event, e);
+    }
+}
+
+/// Avisa de que hay que volver a cargar todos los datos.
+pub fn emit_data_reloaded(app: &AppHandle) {
+    aggregates::invalidate();
+    emit(app, DATA_RELOADED_EVENT, ());
+}
+
+/// Emite un `EntityChanged` con `event`.
+pub fn emit_entity(app: &AppHandle, event: &str, action: ChangeAction, id: &str) {
+    emit(app, event, EntityChanged { action, id: id.to_owned() });
+}
+
+pub fn emit_transaction_deleted(app: &AppHandle, id: &str, permanent: bool) {
+    emit(app, TRANSACTION_DELETED_EVENT, TransactionDeleted { id: id.to_owned(), permanent });
+}
+
+/// Emite el evento de cada cambio sobre transacciones. Mover a la papelera cuenta
+/// como borrado y restaurar como alta. También actualiza los totales de `aggregates`.
+pub fn emit_transaction_changes(app: &AppHandle, changes: &[Change]) {
+    aggregates::apply_changes(changes);
+    for change in changes {
+        match change {
+            Change::Insert(t) => emit(app, TRANSACTION_CREATED_EVENT, t),
+            Change::Update { before, after } => match (before.deleted_at, after.deleted_at) {
+                (None, Some(_)) => emit_transaction_deleted(app, &after.id, false),
+                (Some(_), None) => emit(app, TRANSACTION_CREATED_EVENT, after),
+                _ => emit(app, TRANSACTION_UPDATED_EVENT, after),
+            },
+            Change::Delete(t) => emit_transaction_deleted(app, &t.id, true),
+            Change::Splits { transaction, .. } => emit(app, TRANSACTION_UPDATED_EVENT, transaction),
+        }
+    }
+}
@@ -0,0 +1,254 @@
+// src-tauri/src/forecast.rs
+
+use chrono::{Datelike, Months, NaiveDate};
+use rust_decimal::Decimal;
+use serde::Serialize;
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use tauri::State;
+use log::{debug, error};
+
+use crate::anomalies;
+use crate::currencies::RateTable;
+use crate::error::AppError;
+use crate::invoices::{self, Invoice, InvoiceStatus};
+use crate::money::round_money;
+use crate::periods::{self, Period};
+use crate::reports::{self, GroupBy};
+use crate::settings;
+use crate::storage::TransactionRepository;
+use crate::{AppState, Transaction, TransactionType};
+
+/// Meses completos anteriores al actual con los que se calculan las medias.
+const HISTORY_MONTHS: u32 = 6;
+/// Meses de esos en los que debe repetirse un movimiento para considerarlo recurrente.
+const MIN_RECURRING_MONTHS: usize = 3;
+const MAX_MONTHS_AHEAD: u32 = 24;
+
+/// Movimiento que se repite cada mes (misma tienda, descripción y tipo).
+#[derive(Debug, Clone, Serialize)]
+pub struct RecurringItem {
+    pub transaction_type: TransactionType,
+    pub store_name: String,
+    pub description: String,
+    /// Importe mensual previsto (mediana de los meses en que apareció).
+    pub amount: Decimal,
+    pub months_seen: usize,
+    /// Día del mes en que suele registrarse (mediana).
+    pub usual_day: u32,
+}
+
+impl RecurringItem {
+    /// `true` si `transaction` es una repetición de este movimiento.
+    pub fn matches(&self, transaction: &Transaction) -> bool {
+        let (transaction_type, store_name, description) = recurring_key(transaction);
+        transaction_type == self.transaction_type.to_string()
+            && store_name == self.store_name.trim().to_lowercase()
+            && description == self.description.trim().to_lowercase()
+    }
+}
+
+/// Media mensual de los movimientos no recurrentes de una categoría.
+#[derive(Debug, Clone, Serialize)]
+pub struct CategoryAverage {
+    pub transaction_type: TransactionType,
+    pub category: String,
+    pub monthly_amount: Decimal,
+}
+
+/// Previsión de un mes, en la moneda base.
+#[derive(Debug, Clone, Serialize)]
+pub struct ForecastMonth {
+    /// `AAAA-MM`.
+    pub month: String,
+    pub income: Decimal,
+    pub expenses: Decimal,
+    pub net: Decimal,
+    /// Saldo previsto al terminar el mes.
+    pub balance: Decimal,
+    /// Parte de los ingresos y gastos que viene de movimientos recurrentes.
+    pub recurring_income: Decimal,
+    pub recurring_expenses: Decimal,
+    /// Cobros previstos de facturas pendientes.
+    pub invoice_income: Decimal,
+}
+
+/// Previsión de tesorería a partir del mes siguiente al actual.
+#[derive(Debug, Clone, Serialize)]
+pub struct CashflowForecast {
+    pub base_currency: String,
+    /// Saldo actual: el saldo inicial más todos los ingresos menos todos los gastos registrados.
+    pub starting_balance: Decimal,
+    pub months: Vec<ForecastMonth>,
+    pub recurring: Vec<RecurringItem>,
+    pub category_averages: Vec<CategoryAverage>,
+}
+
+/// Clave con la que se reconoce un movimiento recurrente.
+fn recurring_key(transaction: &Transaction) -> (String, String, String) {
+    (
+        transaction.transaction_type.to_string(),
+        transaction.store_name.trim().to_lowercase(),
+        transaction.description.trim().to_lowercase(),
+    )
+}
+
+/// Proyecta el saldo `months_ahead` meses a partir de `today`: los movimientos
+/// recurrentes se repiten cada mes, el resto de ingresos y gastos se estima con la
+/// media mensual de cada categoría en los últimos `HISTORY_MONTHS` meses, y las
+/// facturas pendientes se cobran en el mes de su vencimiento (las vencidas, el primero).
+pub fn build_forecast(
+    transactions: &[Transaction],
+    pending_invoices: &[Invoice],
+    rates: &RateTable,
+    opening_balance: Decimal,
+    today: NaiveDate,
+    months_ahead: u32,
+) -> Result<CashflowForecast, AppError> {
+    let current_start = Period::Mensual.start_date(today);
+    let history_start = periods::local_midnight_timestamp(current_start - Months::new(HISTORY_MONTHS));
+    let history_end = periods::local_midnight_timestamp(current_start);
+
+    let mut starting_balance = opening_balance;
+    // Importe por mes y días del mes de cada posible movimiento recurrente, y
+    // movimientos del histórico.
+    let mut by_key: HashMap<(String, String, String), (BTreeMap<String, Decimal>, Vec<u32>, &Transaction)> =
+        HashMap::new();
+    let mut history: Vec<(&Transaction, Decimal)> = Vec::new();
+    for transaction in transactions {
+        let amount = rates.to_base(transaction.amount, &transaction.currency)?;
+        match transaction.transaction_type {
+            TransactionType::Ingreso => starting_balance += amount,
+            TransactionType::Gasto => starting_balance -= amount,
+            // No cambian el saldo total ni se prevén.
+            TransactionType::Transferencia => continue,
+        }
+        if transaction.timestamp < history_start || transaction.timestamp >= history_end {
+            continue;
+        }
+        let month = reports::group_key(transaction, GroupBy::Month);
+        let (amounts, days, _) =
+            by_key.entry(recurring_key(transaction)).or_insert_with(|| (BTreeMap::new(), Vec::new(), transaction));
+        *amounts.entry(month).or_default() += amount;
+        days.push(transaction.transaction_date.day());
+        history.push((transaction, amount));
+    }
+
+    let mut recurring: Vec<RecurringItem> = Vec::new();
+    let mut recurring_keys: BTreeSet<(String, String, String)> = BTreeSet::new();
+    for (key, (months, mut days, example)) in by_key {
+        if months.len() < MIN_RECURRING_MONTHS {
+            continue;
+        }
+        days.sort_unstable();
+        let mut amounts: Vec<Decimal> = months.into_values().collect();
+        amounts.sort();
+        recurring.push(RecurringItem {
+            transaction_type: example.transaction_type.clone(),
+            store_name: example.store_name.clone(),
+            description: example.description.clone(),
+            amount: round_money(anomalies::median(&amounts)),
+            months_seen: amounts.len(),
+            usual_day: days[days.len() / 2],
+        });
+        recurring_keys.insert(key);
+    }
+    recurring.sort_by(|a, b| b.amount.cmp(&a.amount));
+
+    // Media mensual del resto, por tipo y categoría.
+    let mut totals: BTreeMap<(String, String), (TransactionType, Decimal)> = BTreeMap::new();
+    for (transaction, amount) in history.iter().filter(|(t, _)| !recurring_keys.contains(&recurring_key(t))) {
+        totals
+            .entry((transaction.transaction_type.to_string(), reports::group_key(transaction, GroupBy::Category)))
+            .or_insert_with(|| (transaction.transaction_type.clone(), Decimal::ZERO))
+            .1 += *amount;
+    }
+    let category_averages: Vec<CategoryAverage> = totals
+        .into_iter()
+        .map(|((_, category), (transaction_type, total))| CategoryAverage {
+            transaction_type,
+            category,
+            monthly_amount: round_money(total / Decimal::from(HISTORY_MONTHS)),
+        })
+        .collect();
+    let average_of = |transaction_type: TransactionType| -> Decimal {
+        category_averages
+            .iter()
+            .filter(|a| a.transaction_type == transaction_type)
+            .map(|a| a.monthly_amount)
+            .sum()
+    };
+    let (average_income, average_expenses) = (average_of(TransactionType::Ingreso), average_of(TransactionType::Gasto));
+    let recurring_income: Decimal = recurring
+        .iter()
+        .filter(|r| r.transaction_type == TransactionType::Ingreso)
+        .map(|r| r.amount)
+        .sum();
+    let recurring_expenses: Decimal = recurring
+        .iter()
+        .filter(|r| r.transaction_type == TransactionType::Gasto)
+        .map(|r| r.amount)
+        .sum();
+
+    let mut months: Vec<ForecastMonth> = Vec::with_capacity(months_ahead as usize);
+    let mut balance = starting_balance;
+    let mut start = Period::Mensual.next_start_date(current_start);
+    for index in 0..months_ahead {
+        let end = Period::Mensual.next_start_date(start);
+        let mut invoice_income = Decimal::ZERO;
+        for invoice in pending_invoices {
+            // Las vencidas y las que vencen este mes se prevén en el primer mes.
+            let due_in_month = invoice.due_date < end && (index == 0 || invoice.due_date >= start);
+            if due_in_month {
+                invoice_income += rates.to_base(invoice.total, &invoice.currency)?;
+            }
+        }
+        let income = round_money(recurring_income + average_income + invoice_income);
+        let expenses = round_money(recurring_expenses + average_expenses);
+        balance += income - expenses;
+        months.push(ForecastMonth {
+            month: start.format("%Y-%m").to_string(),
+            income,
+            expenses,
+            net: income - expenses,
+            balance,
+            recurring_income,
+            recurring_expenses,
+            invoice_income: round_money(invoice_income),
+        });
+        start = end;
+    }
+
+    Ok(CashflowForecast {
+        base_currency: rates.base_currency.clone(),
+        starting_balance,
+        months,
+        recurring,
+        category_averages,
+    })
+}
+
+// --- Comandos Tauri ---
+
+/// Comando para prever ingresos, gastos y saldo de los próximos `months_ahead` meses
+/// (entre 1 y `MAX_MONTHS_AHEAD`), listo para dibujar en un gráfico.
+#[tauri::command]
+pub async fn forecast_cashflow_command(
+    state: State<'_, AppState>,
+    months_ahead: u32,
+) -> Result<CashflowForecast, AppError> {
+    debug!("Received forecast_cashflow_command: {} months", months_ahead);
+    if months_ahead == 0 || months_ahead > MAX_MONTHS_AHEAD {
+        error!("Invalid forecast horizon: {}", months_ahead);
+        return Err(AppError::invalid_field(
+            "months_ahead",
+            format!("La previsión debe ser de entre 1 y {} meses.", MAX_MONTHS_AHEAD),
+        ));
+    }
+
+    let db = state.db().await?;
+    let rates = RateTable::load(&db)?;
+    let mut pending = invoices::list_invoices(db.connection(), Some(InvoiceStatus::Pending))?;
+    pending.extend(invoices::list_invoices(db.connection(), Some(InvoiceStatus::Overdue))?);
+    let opening_balance = settings::load_settings(&db).opening_balance;
+    build_forecast(&db.list_transactions()?, &pending, &rates, opening_balance, periods::today(), months_ahead)
+}
@@ -0,0 +1,508 @@
+// src-tauri/src/gemini.rs
+
+use reqwest::{Client, RequestBuilder, Response, StatusCode};
+use serde::Serialize;
+use serde_json::{json, Value};
+use std::collections::VecDeque;
+use std::env;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use log::{debug, error, info, trace, warn};
+
+use crate::ai::{self, AiBackend, AiMessage, AiReply, AiRole, GenerationParams, ModelInfo, TokenUsage};
+use crate::error::AppError;
+use crate::keychain;
+
+const GEMINI_API_BASE: &str = "https://generativelanguage.googleapis.com/v1beta/models";
+/// Modelo por defecto si los ajustes no indican otro.
+pub const DEFAULT_MODEL: &str = "gemini-1.5-flash-latest";
+/// Espera antes del primer reintento; se duplica en cada uno.
+const INITIAL_BACKOFF: Duration = Duration::from_secs(1);
+/// Espera máxima entre reintentos. Si la API pide esperar más, no se reintenta.
+const MAX_BACKOFF: Duration = Duration::from_secs(60);
+const RATE_WINDOW: Duration = Duration::from_secs(60);
+
+/// Inicio de las peticiones a Gemini del último minuto, para el límite por minuto.
+static RECENT_REQUESTS: Mutex<VecDeque<Instant>> = Mutex::new(VecDeque::new());
+
+/// Evento con cada fragmento de texto de una respuesta en streaming. Conserva el
+/// nombre `gemini://` aunque la respuesta venga del modelo local.
+pub const GEMINI_CHUNK_EVENT: &str = "gemini://chunk";
+/// Evento emitido al terminar (con éxito o con error) una respuesta en streaming.
+pub const GEMINI_DONE_EVENT: &str = "gemini://done";
+
+/// Carga útil de `gemini://chunk`.
+#[derive(Debug, Clone, Serialize)]
+pub struct GeminiChunk {
+    pub request_id: String,
+    pub text: String,
+}
+
+/// Carga útil de `gemini://done`.
+#[derive(Debug, Clone, Serialize)]
+pub struct GeminiDone {
+    pub request_id: String,
+    pub full_text: Option<String>,
+    pub error: Option<String>,
+}
+
+/// Cuerpo de la petición con `contents` y los parámetros de generación.
+fn build_payload(contents: Value, params: &GenerationParams) -> Value {
+    let mut config = serde_json::Map::new();
+    if let Some(temperature) = params.temperature {
+        config.insert("temperature".to_string(), json!(temperature));
+    }
+    if let Some(max_tokens) = params.max_output_tokens {
+        config.insert("maxOutputTokens".to_string(), json!(max_tokens));
+    }
+    if let Some(schema) = &params.response_schema {
+        config.insert("responseMimeType".to_string(), json!("application/json"));
+        config.insert("responseSchema".to_string(), schema.clone());
+    }
+
+    let mut payload = json!({
+        "contents": contents
+    });
+    if !config.is_empty() {
+        payload["generationConfig"] = Value::Object(config);
+    }
+    if !params.safety_settings.is_empty() {
+        payload["safetySettings"] = json!(params.safety_settings);
+    }
+    payload
+}
+
+/// Modelo y límites de red con los que se llama a Gemini (ver `Settings::gemini_config`).
+#[derive(Debug, Clone)]
+pub struct GeminiConfig {
+    pub model: String,
+    /// Tiempo máximo de una petición. En streaming, tiempo máximo sin recibir datos.
+    pub timeout: Duration,
+    /// Reintentos ante errores de red, 429 y 5xx.
+    pub max_retries: u32,
+    /// Peticiones por minuto como máximo; 0 sin límite.
+    pub requests_per_minute: u32,
+}
+
+/// Nombre de la entrada del llavero del sistema con la clave de Gemini.
+const API_KEY_SECRET: &str = "gemini_api_key";
+
+/// Lee la clave de la API de Gemini del llavero del sistema o, si no está
+/// guardada allí, de la variable de entorno `GEMINI_API_KEY`.
+pub fn api_key() -> Result<String, AppError> {
+    match keychain::get_secret(API_KEY_SECRET) {
+        Ok(Some(key)) => return Ok(key),
+        Ok(None) => {}
+        Err(e) => warn!("Falling back to GEMINI_API_KEY: {}", e),
+    }
+    env::var("GEMINI_API_KEY").map_err(|_| {
+        error!("Gemini API key not configured in keychain or environment.");
+        AppError::invalid_field("api_key", "No hay clave de la API de Gemini. Configúrala en los ajustes.")
+    })
+}
+
+/// Si hay una clave de la API de Gemini configurada, sin leer su valor fuera de aquí.
+pub fn has_api_key() -> bool {
+    matches!(keychain::get_secret(API_KEY_SECRET), Ok(Some(_))) || env::var_os("GEMINI_API_KEY").is_some()
+}
+
+/// Comprueba que `model` se pueda usar en la URL de la API.
+pub fn validate_model(model: &str) -> Result<(), AppError> {
+    if model.is_empty() || model.contains(['/', '?', '#', '&', ' ']) {
+        error!("Invalid Gemini model name: '{}'", model);
+        return Err(AppError::invalid_field("ai_model", "El nombre del modelo de IA no es válido."));
+    }
+    Ok(())
+}
+
+/// Mensajes en el formato `contents` de Gemini, con las imágenes en línea.
+fn to_contents(messages: &[AiMessage]) -> Value {
+    let contents: Vec<Value> = messages
+        .iter()
+        .map(|message| {
+            let mut parts = vec![json!({"text": message.text})];
+            parts.extend(message.images.iter().map(|image| {
+                json!({"inlineData": {"mimeType": image.mime_type, "data": image.data}})
+            }));
+            let role = match message.role {
+                AiRole::User => "user",
+                AiRole::Model => "model",
+            };
+            json!({"role": role, "parts": parts})
+        })
+        .collect();
+    Value::Array(contents)
+}
+
+/// Espera hasta que haya hueco en el límite de peticiones por minuto.
+async fn wait_for_rate_limit(requests_per_minute: u32) {
+    if requests_per_minute == 0 {
+        return;
+    }
+    loop {
+        let wait = {
+            let mut recent = RECENT_REQUESTS.lock().unwrap();
+            let now = Instant::now();
+            while recent.front().is_some_and(|t| now.duration_since(*t) >= RATE_WINDOW) {
+                recent.pop_front();
+            }
+            if recent.len() < requests_per_minute as usize {
+                recent.push_back(now);
+                return;
+            }
+            RATE_WINDOW - now.duration_since(recent[0])
+        };
+        debug!("Gemini rate limit reached, waiting {:?}", wait);
+        tokio::time::sleep(wait).await;
+    }
+}
+
+/// Cliente HTTP con los tiempos máximos de `config`.
+fn http_client(config: &GeminiConfig, streaming: bool) -> Result<Client, AppError> {
+    let builder = Client::builder().connect_timeout(config.timeout);
+    // En streaming la respuesta puede tardar más que el límite; solo cortamos si deja de llegar.
+    let builder = if streaming { builder.read_timeout(config.timeout) } else { builder.timeout(config.timeout) };
+    builder
+        .build()
+        .map_err(|e| AppError::Internal(format!("No se pudo crear el cliente HTTP: {}", e)))
+}
+
+/// Segundos que pide esperar la cabecera `Retry-After`.
+fn retry_after_header(response: &Response) -> Option<u64> {
+    response
+        .headers()
+        .get(reqwest::header::RETRY_AFTER)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.trim().parse::<u64>().ok())
+}
+
+/// Segundos que pide esperar el cuerpo de un error de Gemini: el `retryDelay`
+/// (p. ej. `"32s"`) de sus detalles.
+fn retry_delay_from_body(body: &Value) -> Option<u64> {
+    body.pointer("/error/details")?
+        .as_array()?
+        .iter()
+        .filter_map(|d| d.get("retryDelay")?.as_str())
+        .find_map(|delay| delay.trim_end_matches('s').parse::<f64>().ok())
+        .map(|secs| secs.ceil() as u64)
+}
+
+/// Error que corresponde a una respuesta de Gemini que no es un éxito.
+fn status_error(status: StatusCode, body: &Value, retry_after: Option<u64>) -> AppError {
+    let message = body.pointer("/error/message").and_then(|m| m.as_str()).unwrap_or("desconocido");
+    error!("Gemini request failed with {}: {}", status, message);
+    if status == StatusCode::TOO_MANY_REQUESTS {
+        let message = match retry_after {
+            Some(secs) => format!("Se ha superado el límite de peticiones a Gemini. Vuelve a intentarlo en {} segundos.", secs),
+            None => "Se ha superado el límite de peticiones a Gemini. Vuelve a intentarlo en unos minutos.".to_string(),
+        };
+        AppError::RateLimited { message, retry_after_secs: retry_after }
+    } else if status.is_server_error() {
+        AppError::Network(format!("Gemini no está disponible ahora mismo (estado {}).", status))
+    } else {
+        AppError::Ai(format!("Error de Gemini: {}", message))
+    }
+}
+
+/// Envía la petición de `build` respetando el límite por minuto y la reintenta con
+/// espera exponencial ante errores de red, 429 y 5xx. Devuelve la respuesta solo si
+/// es un éxito.
+async fn send_with_retry<F>(config: &GeminiConfig, streaming: bool, build: F) -> Result<Response, AppError>
+where
+    F: Fn(&Client) -> RequestBuilder,
+{
+    let client = http_client(config, streaming)?;
+    let mut attempt = 0;
+    loop {
+        wait_for_rate_limit(config.requests_per_minute).await;
+        let backoff = (INITIAL_BACKOFF * 2u32.saturating_pow(attempt)).min(MAX_BACKOFF);
+        let (error, wait) = match build(&client).send().await {
+            Ok(response) if response.status().is_success() => return Ok(response),
+            Ok(response) => {
+                let status = response.status();
+                let header_retry = retry_after_header(&response);
+                let body: Value = response.json().await.unwrap_or(Value::Null);
+                let retry_after = header_retry.or_else(|| retry_delay_from_body(&body));
+                let error = status_error(status, &body, retry_after);
+                if status != StatusCode::TOO_MANY_REQUESTS && !status.is_server_error() {
+                    return Err(error);
+                }
+                (error, retry_after.map_or(backoff, |secs| Duration::from_secs(secs).max(backoff)))
+            }
+            Err(e) => {
+                error!("Network error connecting to Gemini: {}", e);
+                let error = if e.is_timeout() {
+                    AppError::Network(format!("Gemini no respondió a tiempo ({} s).", config.timeout.as_secs()))
+                } else {
+                    AppError::Network(format!("Error de red al conectar con Gemini: {}", e))
+                };
+                (error, backoff)
+            }
+        };
+        if attempt >= config.max_retries || wait > MAX_BACKOFF {
+            return Err(error);
+        }
+        attempt += 1;
+        warn!("Gemini request failed ({}), retry {}/{} in {:?}", error, attempt, config.max_retries, wait);
+        tokio::time::sleep(wait).await;
+    }
+}
+
+/// Envía `contents` al endpoint `generateContent` del modelo de `config` y devuelve
+/// la respuesta JSON completa.
+pub async fn generate_content(config: &GeminiConfig, contents: Value, params: &GenerationParams) -> Result<Value, AppError> {
+    let api_key = api_key()?;
+    let api_url = format!("{}/{}:generateContent?key={}", GEMINI_API_BASE, config.model, api_key);
+    let payload = build_payload(contents, params);
+
+    debug!("Enviando solicitud a Gemini API ({})", config.model);
+
+    let response = send_with_retry(config, false, |client| {
+        client.post(&api_url).header("Content-Type", "application/json").json(&payload)
+    })
+    .await?;
+
+    let response_json: Value = response.json().await
+        .map_err(|e| {
+            error!("Error reading Gemini JSON response: {}", e);
+            AppError::Network(format!("Error al leer respuesta JSON de Gemini: {}", e))
+        })?;
+
+    // La respuesta incluye los datos del usuario: solo en el nivel `trace`.
+    trace!("Respuesta de Gemini API: {:?}", response_json);
+    Ok(response_json)
+}
+
+/// Extrae el texto del primer candidato de una respuesta de Gemini.
+pub fn extract_text(response_json: &Value) -> Result<String, AppError> {
+    let text = response_json
+        .get("candidates")
+        .and_then(|c| c.as_array())
+        .and_then(|candidates| candidates.first())
+        .and_then(|candidate| candidate.get("content"))
+        .and_then(|content| content.get("parts"))
+        .and_then(|p| p.as_array())
+        .and_then(|parts| parts.first())
+        .and_then(|part| part.get("text"))
+        .and_then(|t| t.as_str());
+
+    match text {
+        Some(text) => {
+            info!("Gemini API call successful.");
+            Ok(text.to_string())
+        }
+        None => {
+            error!("Could not extract text from Gemini AI response. Full response: {:?}", response_json);
+            Err(AppError::Ai("No se pudo extraer el texto de la respuesta de la IA.".to_string()))
+        }
+    }
+}
+
+/// Tokens consumidos según el `usageMetadata` de una respuesta o fragmento.
+fn extract_usage(response_json: &Value) -> Option<TokenUsage> {
+    let usage = response_json.get("usageMetadata")?;
+    let count = |key: &str| usage.get(key).and_then(|v| v.as_u64()).unwrap_or(0);
+    Some(TokenUsage {
+        prompt_tokens: count("promptTokenCount"),
+        // Los tokens de razonamiento se facturan como de respuesta.
+        response_tokens: count("candidatesTokenCount") + count("thoughtsTokenCount"),
+    })
+}
+
+/// Extrae el texto de todas las partes del primer candidato de un fragmento de
+/// respuesta en streaming. Los fragmentos sin texto (p. ej. solo metadatos) devuelven `None`.
+fn extract_chunk_text(chunk_json: &Value) -> Option<String> {
+    let parts = chunk_json
+        .get("candidates")?
+        .as_array()?
+        .first()?
+        .get("content")?
+        .get("parts")?
+        .as_array()?;
+    let text: String = parts
+        .iter()
+        .filter_map(|part| part.get("text").and_then(|t| t.as_str()))
+        .collect();
+    if text.is_empty() { None } else { Some(text) }
+}
+
+/// Procesa un evento SSE completo y devuelve el fragmento JSON que contiene.
+fn parse_sse_event(event: &str) -> Result<Option<Value>, AppError> {
+    let data = ai::sse_event_data(event);
+    if data.is_empty() || data == "[DONE]" {
+        return Ok(None);
+    }
+    let chunk_json: Value = serde_json::from_str(&data).map_err(|e| {
+        error!("Invalid streaming chunk from Gemini: {}. Data: {}", e, data);
+        AppError::Ai(format!("Fragmento inválido en la respuesta de Gemini: {}", e))
+    })?;
+    if let Some(error) = chunk_json.get("error") {
+        error!("Gemini streaming error: {:?}", error);
+        let message = error.get("message").and_then(|m| m.as_str()).unwrap_or("desconocido");
+        return Err(AppError::Ai(format!("Error de Gemini: {}", message)));
+    }
+    Ok(Some(chunk_json))
+}
+
+/// Envía `contents` al endpoint `streamGenerateContent` (Server-Sent Events) del modelo
+/// de `config` y llama a `on_chunk` con cada fragmento de texto según llega. Devuelve el
+/// texto completo. Solo se reintenta hasta recibir la respuesta, no a mitad del stream.
+pub async fn stream_generate_content<F>(
+    config: &GeminiConfig,
+    contents: Value,
+    params: &GenerationParams,
+    mut on_chunk: F,
+) -> Result<AiReply, AppError>
+where
+    F: FnMut(&str),
+{
+    let api_key = api_key()?;
+    let api_url = format!("{}/{}:streamGenerateContent?alt=sse&key={}", GEMINI_API_BASE, config.model, api_key);
+    let payload = build_payload(contents, params);
+
+    debug!("Enviando solicitud en streaming a Gemini API ({})", config.model);
+
+    let mut response = send_with_retry(config, true, |client| {
+        client.post(&api_url).header("Content-Type", "application/json").json(&payload)
+    })
+    .await?;
+
+    let mut buffer: Vec<u8> = Vec::new();
+    let mut full_text = String::new();
+    // Cada fragmento trae el consumo acumulado; vale el del último.
+    let mut usage = TokenUsage::default();
+    let mut handle_chunk = |chunk: Value, full_text: &mut String| {
+        if let Some(text) = extract_chunk_text(&chunk) {
+            on_chunk(&text);
+            full_text.push_str(&text);
+        }
+        if let Some(chunk_usage) = extract_usage(&chunk) {
+            usage = chunk_usage;
+        }
+    };
+    loop {
+        let chunk = response.chunk().await.map_err(|e| {
+            error!("Error reading Gemini stream: {}", e);
+            if e.is_timeout() {
+                AppError::Network(format!("Gemini dejó de responder ({} s sin datos).", config.timeout.as_secs()))
+            } else {
+                AppError::Network(format!("Error al leer la respuesta de Gemini: {}", e))
+            }
+        })?;
+        let Some(bytes) = chunk else { break };
+        buffer.extend_from_slice(&bytes);
+
+        while let Some((position, separator_len)) = ai::find_event_boundary(&buffer) {
+            let event_bytes: Vec<u8> = buffer.drain(..position + separator_len).collect();
+            let event = String::from_utf8_lossy(&event_bytes[..position]);
+            if let Some(chunk) = parse_sse_event(&event)? {
+                handle_chunk(chunk, &mut full_text);
+            }
+        }
+    }
+    // Último evento si el servidor no terminó con una línea en blanco.
+    if !buffer.is_empty() {
+        if let Some(chunk) = parse_sse_event(&String::from_utf8_lossy(&buffer))? {
+            handle_chunk(chunk, &mut full_text);
+        }
+    }
+
+    if full_text.is_empty() {
+        error!("Gemini stream finished without text.");
+        return Err(AppError::Ai("No se pudo extraer el texto de la respuesta de la IA.".to_string()));
+    }
+    info!("Gemini streaming call successful ({} chars).", full_text.len());
+    Ok(AiReply { text: full_text, usage })
+}
+
+/// Consulta los modelos de la API que admiten `generateContent`.
+pub async fn list_models(config: &GeminiConfig) -> Result<Vec<ModelInfo>, AppError> {
+    let api_key = api_key()?;
+    let mut models = Vec::new();
+    let mut page_token: Option<String> = None;
+    loop {
+        let response = send_with_retry(config, false, |client| {
+            let request = client.get(GEMINI_API_BASE).query(&[("key", api_key.as_str()), ("pageSize", "1000")]);
+            match &page_token {
+                Some(token) => request.query(&[("pageToken", token.as_str())]),
+                None => request,
+            }
+        })
+        .await?;
+        let page: Value = response.json().await.map_err(|e| {
+            error!("Error reading Gemini models response: {}", e);
+            AppError::Network(format!("Error al leer la lista de modelos de Gemini: {}", e))
+        })?;
+
+        for model in page.get("models").and_then(|m| m.as_array()).into_iter().flatten() {
+            let supports_generate = model
+                .get("supportedGenerationMethods")
+                .and_then(|m| m.as_array())
+                .is_some_and(|methods| methods.iter().any(|m| m.as_str() == Some("generateContent")));
+            let Some(name) = model.get("name").and_then(|n| n.as_str()) else { continue };
+            if !supports_generate {
+                continue;
+            }
+            let text = |key: &str| model.get(key).and_then(|v| v.as_str()).unwrap_or_default().to_string();
+            models.push(ModelInfo {
+                name: name.trim_start_matches("models/").to_string(),
+                display_name: text("displayName"),
+                description: text("description"),
+                input_token_limit: model.get("inputTokenLimit").and_then(|v| v.as_u64()),
+                output_token_limit: model.get("outputTokenLimit").and_then(|v| v.as_u64()),
+            });
+        }
+
+        page_token = page.get("nextPageToken").and_then(|t| t.as_str()).filter(|t| !t.is_empty()).map(str::to_owned);
+        if page_token.is_none() {
+            break;
+        }
+    }
+    info!("Found {} Gemini models.", models.len());
+    Ok(models)
+}
+
+impl AiBackend for GeminiConfig {
+    fn model(&self) -> &str {
+        &self.model
+    }
+
+    async fn generate(&self, messages: &[AiMessage], params: &GenerationParams) -> Result<AiReply, AppError> {
+        let response = generate_content(self, to_contents(messages), params).await?;
+        Ok(AiReply {
+            text: extract_text(&response)?,
+            usage: extract_usage(&response).unwrap_or_default(),
+        })
+    }
+
+    async fn stream<F>(&self, messages: &[AiMessage], params: &GenerationParams, on_chunk: F) -> Result<AiReply, AppError>
+    where
+        F: FnMut(&str) + Send,
+    {
+        stream_generate_content(self, to_contents(messages), params, on_chunk).await
+    }
+
+    async fn list_models(&self) -> Result<Vec<ModelInfo>, AppError> {
+        list_models(self).await
+    }
+}
+
+// --- Comandos Tauri ---
+
+/// Comando para guardar la clave de la API de Gemini en el llavero del sistema.
+/// Con una clave vacía o ausente se borra la clave guardada.
+#[tauri::command]
+pub async fn set_api_key_command(api_key: Option<String>) -> Result<(), AppError> {
+    debug!("Received set_api_key_command.");
+    match api_key.map(|k| k.trim().to_owned()).filter(|k| !k.is_empty()) {
+        Some(key) => keychain::set_secret(API_KEY_SECRET, &key),
+        None => keychain::delete_secret(API_KEY_SECRET),
+    }
+}
+
+/// Comando para saber si hay una clave de Gemini disponible, ya sea en el llavero
+/// o en la variable de entorno. Nunca devuelve la clave.
+#[tauri::command]
+pub async fn has_api_key_command() -> Result<bool, AppError> {
+    Ok(api_key().is_ok())
+}
@@ -0,0 +1,271 @@
+// src-tauri/src/goals.rs
+
+use rusqlite::{params, Connection, OptionalExtension, Row};
+use rust_decimal::prelude::ToPrimitive;
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, State};
+use log::{debug, error};
+
+use crate::aggregates;
+use crate::audit;
+use crate::currencies::RateTable;
+use crate::error::AppError;
+use crate::events::{self, ChangeAction};
+use crate::money;
+use crate::periods::{self, Period};
+use crate::reports::GroupTotals;
+use crate::storage::{self, db_error, SqliteStorage};
+use crate::AppState;
+
+/// Qué mide un objetivo. A diferencia de un presupuesto, que es un máximo de gasto,
+/// un objetivo es una cifra a alcanzar.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum GoalMetric {
+    /// Ventas: la suma de los ingresos.
+    Ingresos,
+    /// Ingresos menos gastos.
+    Resultado,
+}
+
+impl ToString for GoalMetric {
+    fn to_string(&self) -> String {
+        match self {
+            GoalMetric::Ingresos => "Ingresos".to_string(),
+            GoalMetric::Resultado => "Resultado".to_string(),
+        }
+    }
+}
+
+/// Cifra de ingresos o de resultado a alcanzar en cada periodo, en la moneda base.
+#[derive(Debug, Clone, Serialize)]
+pub struct Goal {
+    pub id: String,
+    pub metric: GoalMetric,
+    pub period: Period,
+    /// Tienda a la que se aplica; `None` para el total de todas.
+    pub store_name: Option<String>,
+    pub target_amount: Decimal,
+    pub created_at: u64,
+    pub updated_at: u64,
+}
+
+/// Avance de un objetivo en un periodo, con la proyección al ritmo actual.
+#[derive(Debug, Clone, Serialize)]
+pub struct GoalProgress {
+    pub goal: Goal,
+    pub period_start: u64,
+    pub period_end: u64,
+    pub currency: String,
+    pub achieved: Decimal,
+    pub remaining: Decimal,
+    pub percent_achieved: f64,
+    /// Días del periodo transcurridos (incluido el de hoy) y totales.
+    pub elapsed_days: i64,
+    pub total_days: i64,
+    /// Media diaria hasta hoy.
+    pub daily_run_rate: Decimal,
+    /// Cifra al terminar el periodo si se mantiene `daily_run_rate`. En un periodo ya
+    /// terminado es la cifra final.
+    pub projected: Decimal,
+    pub projected_percent: f64,
+    /// Media diaria necesaria en los días que quedan para llegar al objetivo.
+    pub required_daily_rate: Option<Decimal>,
+    pub on_track: bool,
+}
+
+const GOAL_SELECT: &str =
+    "SELECT id, metric, period, store_name, target_amount, created_at, updated_at FROM goals";
+
+fn row_to_goal(row: &Row) -> rusqlite::Result<Goal> {
+    let metric = match row.get::<_, String>(1)?.as_str() {
+        "Resultado" => GoalMetric::Resultado,
+        _ => GoalMetric::Ingresos,
+    };
+    let period_str: String = row.get(2)?;
+    Ok(Goal {
+        id: row.get(0)?,
+        metric,
+        period: Period::parse(&period_str).unwrap_or(Period::Mensual),
+        store_name: Some(row.get::<_, String>(3)?).filter(|s| !s.is_empty()),
+        target_amount: storage::decimal_column(row, 4)?,
+        created_at: row.get::<_, i64>(5)? as u64,
+        updated_at: row.get::<_, i64>(6)? as u64,
+    })
+}
+
+pub fn list_goals(conn: &Connection) -> Result<Vec<Goal>, AppError> {
+    let mut stmt = conn
+        .prepare(&format!("{} ORDER BY metric, period, store_name", GOAL_SELECT))
+        .map_err(db_error)?;
+    let rows = stmt.query_map([], row_to_goal).map_err(db_error)?;
+    rows.collect::<rusqlite::Result<Vec<_>>>().map_err(db_error)
+}
+
+fn find_goal(conn: &Connection, metric: GoalMetric, period: Period, store_name: &str) -> Result<Option<Goal>, AppError> {
+    conn.query_row(
+        &format!("{} WHERE metric = ?1 AND period = ?2 AND store_name = ?3", GOAL_SELECT),
+        params![metric.to_string(), period.to_string(), store_name],
+        row_to_goal,
+    )
+    .optional()
+    .map_err(db_error)
+}
+
+fn percent_of(amount: Decimal, target: Decimal) -> f64 {
+    if target > Decimal::ZERO {
+        (amount / target * Decimal::ONE_HUNDRED).to_f64().unwrap_or(0.0)
+    } else {
+        0.0
+    }
+}
+
+/// Avance de cada objetivo en su periodo que contiene `at`.
+pub fn compute_progress(db: &SqliteStorage, goals: Vec<Goal>, at: u64) -> Result<Vec<GoalProgress>, AppError> {
+    if goals.is_empty() {
+        return Ok(Vec::new());
+    }
+    let rates = RateTable::load(db)?;
+    let date = periods::local_date(at);
+    let today = periods::local_date(periods::now_timestamp());
+    aggregates::with_aggregates(db, |aggregates| {
+        let mut progress = Vec::with_capacity(goals.len());
+        for goal in goals {
+            let start = goal.period.start_date(date);
+            let end = goal.period.next_start_date(start);
+            let mut totals = GroupTotals::default();
+            for (_, key, bucket) in aggregates.range(Some(start), Some(end)) {
+                if goal.store_name.as_ref().is_some_and(|store| *store != key.store_name) {
+                    continue;
+                }
+                totals.add_many(&key.transaction_type, rates.to_base(bucket.amount, &key.currency)?, bucket.count);
+            }
+            let achieved = match goal.metric {
+                GoalMetric::Ingresos => totals.income,
+                GoalMetric::Resultado => totals.income - totals.expenses,
+            };
+
+            let total_days = (end - start).num_days();
+            // Un periodo futuro aún no ha empezado; uno pasado ya terminó.
+            let elapsed_days = ((today - start).num_days() + 1).clamp(0, total_days);
+            let daily_run_rate = if elapsed_days > 0 {
+                (achieved / Decimal::from(elapsed_days)).round_dp(2)
+            } else {
+                Decimal::ZERO
+            };
+            let projected = if elapsed_days > 0 {
+                achieved * Decimal::from(total_days) / Decimal::from(elapsed_days)
+            } else {
+                achieved
+            }
+            .round_dp(2);
+            let remaining = goal.target_amount - achieved;
+            let days_left = total_days - elapsed_days;
+            let required_daily_rate = (days_left > 0 && remaining > Decimal::ZERO)
+                .then(|| (remaining / Decimal::from(days_left)).round_dp(2));
+            progress.push(GoalProgress {
+                period_start: periods::local_midnight_timestamp(start),
+                period_end: periods::local_midnight_timestamp(end),
+                currency: rates.base_currency.clone(),
+                achieved,
+                remaining,
+                percent_achieved: percent_of(achieved, goal.target_amount),
+                elapsed_days,
+                total_days,
+                daily_run_rate,
+                projected,
+                projected_percent: percent_of(projected, goal.target_amount),
+                required_daily_rate,
+                on_track: projected >= goal.target_amount,
+                goal,
+            });
+        }
+        Ok(progress)
+    })?
+}
+
+// --- Comandos Tauri ---
+
+/// Comando para crear o actualizar el objetivo de ingresos o de resultado de un
+/// periodo, de todas las tiendas o de `store_name`. Si ya existe uno para la misma
+/// cifra, periodo y tienda, se cambia su importe.
+#[tauri::command]
+pub async fn set_goal_command(
+    state: State<'_, AppState>,
+    app: AppHandle,
+    metric: GoalMetric,
+    period: Period,
+    target_amount: Decimal,
+    store_name: Option<String>,
+) -> Result<Goal, AppError> {
+    debug!("Received set_goal_command: metric={:?}, period={:?}, store={:?}", metric, period, store_name);
+    money::validate_amount("target_amount", target_amount, "El objetivo debe ser positivo.")?;
+    let store_name = store_name.map(|s| s.trim().to_owned()).filter(|s| !s.is_empty()).unwrap_or_default();
+
+    let db = state.db().await?;
+    let conn = db.connection();
+    let previous = find_goal(conn, metric, period, &store_name)?;
+    let now = periods::now_timestamp();
+    conn.execute(
+        "INSERT INTO goals (id, metric, period, store_name, target_amount, created_at, updated_at)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?6)
+         ON CONFLICT(metric, period, store_name) DO UPDATE SET
+             target_amount = excluded.target_amount, updated_at = excluded.updated_at",
+        params![
+            uuid::Uuid::new_v4().to_string(),
+            metric.to_string(),
+            period.to_string(),
+            store_name,
+            target_amount.to_string(),
+            now as i64
+        ],
+    )
+    .map_err(db_error)?;
+    let saved = find_goal(conn, metric, period, &store_name)?
+        .ok_or_else(|| AppError::Internal("No se encontró el objetivo recién guardado.".to_string()))?;
+
+    audit::record(
+        conn,
+        "set_goal_command",
+        Some(&saved.id),
+        previous.as_ref().and_then(audit::snapshot),
+        audit::snapshot(&saved),
+    );
+    let action = if previous.is_some() { ChangeAction::Updated } else { ChangeAction::Created };
+    events::emit_entity(&app, events::GOAL_CHANGED_EVENT, action, &saved.id);
+    debug!("Goal saved: {}", saved.id);
+    Ok(saved)
+}
+
+/// Comando para obtener el avance de los objetivos en el periodo que contiene `at`
+/// (segundos Unix; ahora por defecto): lo conseguido, lo que falta y la cifra que
+/// se alcanzará al final del periodo al ritmo actual.
+#[tauri::command]
+pub async fn get_goal_progress_command(
+    state: State<'_, AppState>,
+    at: Option<u64>,
+) -> Result<Vec<GoalProgress>, AppError> {
+    debug!("Received get_goal_progress_command: at={:?}", at);
+    let db = state.db().await?;
+    let goals = list_goals(db.connection())?;
+    compute_progress(&db, goals, at.unwrap_or_else(periods::now_timestamp))
+}
+
+/// Comando para eliminar un objetivo.
+#[tauri::command]
+pub async fn delete_goal_command(state: State<'_, AppState>, app: AppHandle, id: String) -> Result<(), AppError> {
+    debug!("Received delete_goal_command for ID: {}", id);
+    let db = state.db().await?;
+    let existing = list_goals(db.connection())?.into_iter().find(|g| g.id == id);
+    let changed = db
+        .connection()
+        .execute("DELETE FROM goals WHERE id = ?1", params![id])
+        .map_err(db_error)?;
+    if changed == 0 {
+        error!("Goal with ID {} not found for deletion.", id);
+        return Err(AppError::NotFound(format!("Objetivo con ID {} no encontrado.", id)));
+    }
+    audit::record(db.connection(), "delete_goal_command", Some(&id), existing.as_ref().and_then(audit::snapshot), None);
+    events::emit_entity(&app, events::GOAL_CHANGED_EVENT, ChangeAction::Deleted, &id);
+    Ok(())
+}
@@ -0,0 +1,241 @@
+// src-tauri/src/history.rs
+
+use serde::Serialize;
+use std::collections::VecDeque;
+use tauri::{AppHandle, State};
+use log::{debug, error, info};
+
+use crate::attachments;
+use crate::audit;
+use crate::error::AppError;
+use crate::events;
+use crate::splits::{self, TransactionSplit};
+use crate::storage::{db_error, SqliteStorage, TransactionRepository};
+use crate::{AppState, Transaction};
+
+/// Profundidad del historial por defecto.
+pub const DEFAULT_HISTORY_DEPTH: usize = 50;
+/// Profundidad máxima admitida, para acotar la memoria usada.
+const MAX_HISTORY_DEPTH: usize = 500;
+const HISTORY_DEPTH_KEY: &str = "history_depth";
+
+/// Cambio atómico sobre una transacción, con lo necesario para invertirlo.
+#[derive(Debug, Clone)]
+pub enum Change {
+    Insert(Transaction),
+    Update { before: Transaction, after: Transaction },
+    Delete(Transaction),
+    /// Reparto de `transaction` entre categorías (ver `splits`); la transacción no cambia.
+    Splits { transaction: Transaction, before: Vec<TransactionSplit>, after: Vec<TransactionSplit> },
+}
+
+impl Change {
+    /// Cambio que deshace a este.
+    fn inverse(&self) -> Change {
+        match self {
+            Change::Insert(t) => Change::Delete(t.clone()),
+            Change::Update { before, after } => Change::Update { before: after.clone(), after: before.clone() },
+            Change::Delete(t) => Change::Insert(t.clone()),
+            Change::Splits { transaction, before, after } => Change::Splits {
+                transaction: transaction.clone(),
+                before: after.clone(),
+                after: before.clone(),
+            },
+        }
+    }
+
+    fn apply(&self, db: &SqliteStorage) -> Result<(), AppError> {
+        match self {
+            Change::Insert(t) => {
+                let mut restored = t.clone();
+                // Los adjuntos se borran del disco al eliminar la transacción.
+                restored.receipt_paths.retain(|p| attachments::resolve_receipt_path(p).exists());
+                db.insert_transaction(&restored)
+            }
+            Change::Update { after, .. } => {
+                if db.update_transaction(after)? {
+                    Ok(())
+                } else {
+                    Err(AppError::NotFound(format!("Transacción con ID {} no encontrada.", after.id)))
+                }
+            }
+            Change::Delete(t) => {
+                if db.delete_transaction(&t.id)? {
+                    attachments::remove_transaction_attachments(&t.id);
+                    Ok(())
+                } else {
+                    Err(AppError::NotFound(format!("Transacción con ID {} no encontrada.", t.id)))
+                }
+            }
+            Change::Splits { transaction, after, .. } => splits::replace_splits(db.connection(), &transaction.id, after),
+        }
+    }
+}
+
+/// Una acción del usuario (p. ej. renombrar una tienda) con todos sus cambios.
+#[derive(Debug, Clone)]
+pub struct HistoryEntry {
+    pub label: String,
+    pub changes: Vec<Change>,
+}
+
+impl HistoryEntry {
+    pub fn new(label: &str, changes: Vec<Change>) -> Self {
+        HistoryEntry { label: label.to_owned(), changes }
+    }
+}
+
+/// Estado del historial para que el frontend habilite los botones de deshacer/rehacer.
+#[derive(Debug, Clone, Serialize)]
+pub struct HistoryStatus {
+    pub can_undo: bool,
+    pub can_redo: bool,
+    pub undo_label: Option<String>,
+    pub redo_label: Option<String>,
+    pub depth: usize,
+}
+
+/// Pilas de deshacer/rehacer de las operaciones sobre transacciones.
+pub struct CommandHistory {
+    undo_stack: VecDeque<HistoryEntry>,
+    redo_stack: Vec<HistoryEntry>,
+    max_depth: usize,
+}
+
+impl CommandHistory {
+    pub fn new(max_depth: usize) -> Self {
+        CommandHistory {
+            undo_stack: VecDeque::new(),
+            redo_stack: Vec::new(),
+            max_depth: max_depth.clamp(1, MAX_HISTORY_DEPTH),
+        }
+    }
+
+    /// Registra una acción nueva. Cualquier acción pendiente de rehacer se descarta.
+    pub fn record(&mut self, entry: HistoryEntry) {
+        if entry.changes.is_empty() {
+            return;
+        }
+        debug!("Recording history entry '{}' ({} changes)", entry.label, entry.changes.len());
+        self.redo_stack.clear();
+        self.undo_stack.push_back(entry);
+        while self.undo_stack.len() > self.max_depth {
+            self.undo_stack.pop_front();
+        }
+    }
+
+    /// Vacía ambas pilas, p. ej. cuando los datos se reemplazan por completo.
+    pub fn clear(&mut self) {
+        self.undo_stack.clear();
+        self.redo_stack.clear();
+    }
+
+    pub fn set_max_depth(&mut self, max_depth: usize) {
+        self.max_depth = max_depth.clamp(1, MAX_HISTORY_DEPTH);
+        while self.undo_stack.len() > self.max_depth {
+            self.undo_stack.pop_front();
+        }
+    }
+
+    pub fn status(&self) -> HistoryStatus {
+        HistoryStatus {
+            can_undo: !self.undo_stack.is_empty(),
+            can_redo: !self.redo_stack.is_empty(),
+            undo_label: self.undo_stack.back().map(|e| e.label.clone()),
+            redo_label: self.redo_stack.last().map(|e| e.label.clone()),
+            depth: self.max_depth,
+        }
+    }
+}
+
+/// Aplica una lista de cambios dentro de una única transacción SQL.
+fn apply_changes(db: &SqliteStorage, changes: &[Change]) -> Result<(), AppError> {
+    let tx = db.connection().unchecked_transaction().map_err(db_error)?;
+    for change in changes {
+        change.apply(db)?;
+    }
+    tx.commit().map_err(db_error)
+}
+
+/// Lee la profundidad del historial guardada en los ajustes.
+pub fn load_history_depth(db: &SqliteStorage) -> usize {
+    db.get_setting(HISTORY_DEPTH_KEY)
+        .ok()
+        .flatten()
+        .and_then(|v| v.parse().ok())
+        .unwrap_or(DEFAULT_HISTORY_DEPTH)
+}
+
+// --- Comandos Tauri ---
+
+/// Comando para deshacer la última acción registrada.
+#[tauri::command]
+pub async fn undo_command(state: State<'_, AppState>, app: AppHandle) -> Result<HistoryStatus, AppError> {
+    debug!("Received undo_command.");
+    let db = state.db().await?;
+    let mut history = state.history.lock().unwrap();
+
+    let entry = history.undo_stack.pop_back().ok_or_else(|| AppError::validation("No hay nada que deshacer."))?;
+    let inverse: Vec<Change> = entry.changes.iter().rev().map(Change::inverse).collect();
+    if let Err(e) = apply_changes(&db, &inverse) {
+        // Los datos cambiaron por otra vía y la acción ya no se puede deshacer.
+        error!("Undo of '{}' failed: {}", entry.label, e);
+        return Err(AppError::Conflict(format!("No se pudo deshacer '{}': {}", entry.label, e)));
+    }
+    audit::record_changes(db.connection(), "undo_command", &inverse);
+    events::emit_transaction_changes(&app, &inverse);
+    info!("Undone: {}", entry.label);
+    history.redo_stack.push(entry);
+    Ok(history.status())
+}
+
+/// Comando para rehacer la última acción deshecha.
+#[tauri::command]
+pub async fn redo_command(state: State<'_, AppState>, app: AppHandle) -> Result<HistoryStatus, AppError> {
+    debug!("Received redo_command.");
+    let db = state.db().await?;
+    let mut history = state.history.lock().unwrap();
+
+    let entry = history.redo_stack.pop().ok_or_else(|| AppError::validation("No hay nada que rehacer."))?;
+    if let Err(e) = apply_changes(&db, &entry.changes) {
+        error!("Redo of '{}' failed: {}", entry.label, e);
+        return Err(AppError::Conflict(format!("No se pudo rehacer '{}': {}", entry.label, e)));
+    }
+    audit::record_changes(db.connection(), "redo_command", &entry.changes);
+    events::emit_transaction_changes(&app, &entry.changes);
+    info!("Redone: {}", entry.label);
+    history.undo_stack.push_back(entry);
+    Ok(history.status())
+}
+
+/// Comando para consultar si hay acciones para deshacer o rehacer.
+#[tauri::command]
+pub async fn get_history_status_command(state: State<'_, AppState>) -> Result<HistoryStatus, AppError> {
+    Ok(state.history.lock().unwrap().status())
+}
+
+/// Comando para cambiar cuántas acciones se conservan en el historial.
+#[tauri::command]
+pub async fn set_history_depth_command(state: State<'_, AppState>, depth: usize) -> Result<HistoryStatus, AppError> {
+    debug!("Received set_history_depth_command: {}", depth);
+    if depth == 0 || depth > MAX_HISTORY_DEPTH {
+        error!("Invalid history depth: {}", depth);
+        return Err(AppError::invalid_field(
+            "depth",
+            format!("La profundidad del historial debe estar entre 1 y {}.", MAX_HISTORY_DEPTH),
+        ));
+    }
+    let db = state.db().await?;
+    let previous = load_history_depth(&db);
+    db.set_setting(HISTORY_DEPTH_KEY, &depth.to_string())?;
+    audit::record(
+        db.connection(),
+        "set_history_depth_command",
+        Some(HISTORY_DEPTH_KEY),
+        audit::snapshot(&previous),
+        audit::snapshot(&depth),
+    );
+    let mut history = state.history.lock().unwrap();
+    history.set_max_depth(depth);
+    Ok(history.status())
+}
@@ -0,0 +1,238 @@
+// src-tauri/src/i18n.rs
+
+use std::fmt::Display;
+use std::sync::atomic::{AtomicU8, Ordering};
+use log::{debug, warn};
+
+use crate::settings;
+use crate::storage::SqliteStorage;
+
+/// Idioma de los mensajes del backend (errores, informes, facturas). Se elige con el
+/// `locale` de los ajustes: las etiquetas `en*` usan inglés y el resto, español.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Language {
+    Es,
+    En,
+}
+
+/// Idioma vigente, para los errores, que se serializan sin acceso a los ajustes.
+static CURRENT: AtomicU8 = AtomicU8::new(0);
+
+impl Language {
+    pub fn from_locale(locale: &str) -> Self {
+        let primary = locale.trim().split(['-', '_']).next().unwrap_or_default();
+        if primary.eq_ignore_ascii_case("en") {
+            Language::En
+        } else {
+            Language::Es
+        }
+    }
+
+    pub fn current() -> Self {
+        match CURRENT.load(Ordering::Relaxed) {
+            1 => Language::En,
+            _ => Language::Es,
+        }
+    }
+
+    fn catalog(self) -> &'static [(&'static str, &'static str)] {
+        match self {
+            Language::Es => ES,
+            Language::En => EN,
+        }
+    }
+}
+
+pub fn set_current(language: Language) {
+    let value = match language {
+        Language::Es => 0,
+        Language::En => 1,
+    };
+    if CURRENT.swap(value, Ordering::Relaxed) != value {
+        debug!("Backend language set to {:?}.", language);
+    }
+}
+
+/// Toma el idioma de los ajustes de `db`. Se llama al arrancar, al guardar los ajustes
+/// y cada vez que se cambia la base de datos abierta (desbloqueo, restauración,
+/// cambio de espacio de trabajo).
+pub fn apply_settings(db: &SqliteStorage) {
+    set_current(Language::from_locale(&settings::load_settings(db).locale));
+}
+
+/// Texto de `key` en `language`. Si falta, el español y, si tampoco existe, la clave.
+pub fn text(language: Language, key: &str) -> &str {
+    let lookup = |language: Language| language.catalog().iter().find(|(k, _)| *k == key).map(|(_, v)| *v);
+    lookup(language).or_else(|| lookup(Language::Es)).unwrap_or_else(|| {
+        warn!("Missing message '{}'.", key);
+        key
+    })
+}
+
+/// Como `text`, sustituyendo cada `{nombre}` por el valor de `args`.
+pub fn format(language: Language, key: &str, args: &[(&str, &dyn Display)]) -> String {
+    args.iter()
+        .fold(text(language, key).to_owned(), |message, (name, value)| {
+            message.replace(&format!("{{{}}}", name), &value.to_string())
+        })
+}
+
+// --- Catálogos ---
+//
+// Las claves `error.<código>` y `validation.<código>` corresponden a `AppError::code`
+// y a los códigos de `validation`. En español los errores conservan su mensaje
+// detallado, así que esas entradas solo se usan como respaldo.
+
+const ES: &[(&str, &str)] = &[
+    ("error.validation", "Los datos no son válidos."),
+    ("error.not_found", "No se encontró el elemento solicitado."),
+    ("error.conflict", "La operación choca con datos existentes."),
+    ("error.duplicate", "La transacción parece duplicada."),
+    ("error.locked", "Los datos están cifrados. Desbloquéalos para continuar."),
+    ("error.database", "Error de la base de datos."),
+    ("error.io", "Error al leer o escribir un archivo."),
+    ("error.network", "Error de red."),
+    ("error.ai", "Error de la IA."),
+    ("error.rate_limited", "Demasiadas peticiones a la IA. Espera un momento."),
+    ("error.limit_reached", "Se alcanzó el límite configurado."),
+    ("error.period_closed", "El periodo está cerrado y no admite cambios."),
+    ("error.queued", "Sin conexión: la petición se ha puesto en cola."),
+    ("error.ai_disabled", "La IA está desactivada en los ajustes."),
+    ("error.internal", "Error interno."),
+    ("validation.invalid", "El valor no es válido."),
+    ("validation.required", "Este campo es obligatorio."),
+    ("validation.not_positive", "El importe debe ser positivo."),
+    ("validation.too_many_decimals", "El importe tiene demasiados decimales."),
+    ("validation.too_long", "El texto es demasiado largo."),
+    ("validation.too_many", "Hay demasiados elementos."),
+    ("validation.duplicated", "El valor está repetido."),
+    ("validation.future_date", "La fecha no puede ser futura."),
+    ("validation.out_of_range", "El valor está fuera del rango permitido."),
+    ("validation.invalid_format", "El formato no es válido."),
+    ("validation.not_found", "No existe."),
+    ("validation.more_errors", "{message} (y {count} errores más)"),
+    ("type.income", "Ingreso"),
+    ("type.expense", "Gasto"),
+    ("type.transfer", "Transferencia"),
+    ("report.monthly_title", "Informe mensual {month}"),
+    ("report.layer", "Informe"),
+    ("report.income", "Ingresos"),
+    ("report.expenses", "Gastos"),
+    ("report.net", "Resultado"),
+    ("report.by_category", "Por categoría"),
+    ("report.by_store", "Por tienda"),
+    (
+        "report.email_body",
+        "Informe del mes {month}:\n\nIngresos: {income}\nGastos: {expenses}\nResultado: {net}\nTransacciones: {count}\n\n\
+         Se adjuntan el informe en PDF y las transacciones del mes en CSV.\n",
+    ),
+    ("report.pdf_file", "informe-{month}.pdf"),
+    ("report.csv_file", "transacciones-{month}.csv"),
+    ("invoice.title", "Factura {number}"),
+    ("invoice.layer", "Factura"),
+    ("invoice.issue_date", "Fecha de emisión: {date}"),
+    ("invoice.due_date", "Vencimiento: {date}"),
+    ("invoice.client", "Cliente"),
+    ("invoice.tax_id", "NIF: {tax_id}"),
+    ("invoice.item", "Concepto"),
+    ("invoice.quantity", "Cantidad"),
+    ("invoice.price", "Precio"),
+    ("invoice.amount", "Importe"),
+    ("invoice.subtotal", "Base imponible"),
+    ("invoice.vat", "IVA ({rate} %)"),
+    ("invoice.total", "Total"),
+    ("ticket.number", "Ticket {number}"),
+    ("ticket.payment", "Forma de pago"),
+    ("ticket.daily_title", "Resumen del día {date}"),
+    ("payment.cash", "Efectivo"),
+    ("payment.card", "Tarjeta"),
+    ("payment.transfer", "Transferencia"),
+    ("payment.bizum", "Bizum"),
+    ("payment.other", "Otro"),
+    ("cash_closing.title", "Cierre de caja {date}"),
+    ("cash_closing.closed_by", "Cerrada por {actor}"),
+    ("cash_closing.opening", "Efectivo al abrir"),
+    ("cash_closing.income", "Cobros en efectivo"),
+    ("cash_closing.expenses", "Pagos en efectivo"),
+    ("cash_closing.expected", "Efectivo esperado"),
+    ("cash_closing.counted", "Efectivo contado"),
+    ("cash_closing.difference", "Diferencia"),
+    ("cash_closing.transactions", "Movimientos en efectivo"),
+];
+
+const EN: &[(&str, &str)] = &[
+    ("error.validation", "The data is not valid."),
+    ("error.not_found", "The requested item was not found."),
+    ("error.conflict", "The operation conflicts with existing data."),
+    ("error.duplicate", "The transaction looks like a duplicate."),
+    ("error.locked", "The data is encrypted. Unlock it to continue."),
+    ("error.database", "Database error."),
+    ("error.io", "Error reading or writing a file."),
+    ("error.network", "Network error."),
+    ("error.ai", "AI error."),
+    ("error.rate_limited", "Too many AI requests. Please wait a moment."),
+    ("error.limit_reached", "The configured limit has been reached."),
+    ("error.period_closed", "The period is closed and cannot be changed."),
+    ("error.queued", "Offline: the request has been queued."),
+    ("error.ai_disabled", "AI is disabled in the settings."),
+    ("error.internal", "Internal error."),
+    ("validation.invalid", "The value is not valid."),
+    ("validation.required", "This field is required."),
+    ("validation.not_positive", "The amount must be positive."),
+    ("validation.too_many_decimals", "The amount has too many decimal places."),
+    ("validation.too_long", "The text is too long."),
+    ("validation.too_many", "There are too many items."),
+    ("validation.duplicated", "The value is repeated."),
+    ("validation.future_date", "The date cannot be in the future."),
+    ("validation.out_of_range", "The value is out of the allowed range."),
+    ("validation.invalid_format", "The format is not valid."),
+    ("validation.not_found", "It does not exist."),
+    ("validation.more_errors", "{message} (and {count} more errors)"),
+    ("type.income", "Income"),
+    ("type.expense", "Expense"),
+    ("type.transfer", "Transfer"),
+    ("report.monthly_title", "Monthly report {month}"),
+    ("report.layer", "Report"),
+    ("report.income", "Income"),
+    ("report.expenses", "Expenses"),
+    ("report.net", "Net result"),
+    ("report.by_category", "By category"),
+    ("report.by_store", "By store"),
+    (
+        "report.email_body",
+        "Report for {month}:\n\nIncome: {income}\nExpenses: {expenses}\nNet result: {net}\nTransactions: {count}\n\n\
+         The PDF report and the month's transactions in CSV are attached.\n",
+    ),
+    ("report.pdf_file", "report-{month}.pdf"),
+    ("report.csv_file", "transactions-{month}.csv"),
+    ("invoice.title", "Invoice {number}"),
+    ("invoice.layer", "Invoice"),
+    ("invoice.issue_date", "Issue date: {date}"),
+    ("invoice.due_date", "Due date: {date}"),
+    ("invoice.client", "Client"),
+    ("invoice.tax_id", "Tax ID: {tax_id}"),
+    ("invoice.item", "Description"),
+    ("invoice.quantity", "Quantity"),
+    ("invoice.price", "Price"),
+    ("invoice.amount", "Amount"),
+    ("invoice.subtotal", "Subtotal"),
+    ("invoice.vat", "VAT ({rate} %)"),
+    ("invoice.total", "Total"),
+    ("ticket.number", "Receipt {number}"),
+    ("ticket.payment", "Payment"),
+    ("ticket.daily_title", "Daily summary {date}"),
+    ("payment.cash", "Cash"),
+    ("payment.card", "Card"),
+    ("payment.transfer", "Bank transfer"),
+    ("payment.bizum", "Bizum"),
+    ("payment.other", "Other"),
+    ("cash_closing.title", "Cash closing {date}"),
+    ("cash_closing.closed_by", "Closed by {actor}"),
+    ("cash_closing.opening", "Opening cash"),
+    ("cash_closing.income", "Cash received"),
+    ("cash_closing.expenses", "Cash paid out"),
+    ("cash_closing.expected", "Expected cash"),
+    ("cash_closing.counted", "Counted cash"),
+    ("cash_closing.difference", "Difference"),
+    ("cash_closing.transactions", "Cash transactions"),
+];
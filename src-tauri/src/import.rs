@@ -0,0 +1,1006 @@
+// src-tauri/src/import.rs
+
+use calamine::{open_workbook, Data, Reader, Xlsx};
+use chrono::{Duration, NaiveDate};
+use rust_decimal::prelude::FromPrimitive;
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::Path;
+use std::str::FromStr;
+use tauri::{AppHandle, State};
+use log::{debug, error, info};
+
+use crate::audit;
+use crate::backup;
+use crate::budgets;
+use crate::currencies;
+use crate::duplicates;
+use crate::error::AppError;
+use crate::events;
+use crate::history::{Change, HistoryEntry};
+use crate::merge;
+use crate::money;
+use crate::payments::PaymentMethod;
+use crate::periods;
+use crate::rules::{self, ApplyMode};
+use crate::storage::{SqliteStorage, TransactionRepository};
+use crate::validation::{self, ValidationPolicy, Validator};
+use crate::{AppState, Transaction, TransactionType};
+
+/// Descripción de los movimientos que no traen concepto.
+const DEFAULT_DESCRIPTION: &str = "Movimiento bancario";
+/// Filas del principio de la hoja en las que se busca la cabecera.
+const MAX_HEADER_SCAN_ROWS: usize = 30;
+/// Tienda en la que se importa un archivo si no se indica otra (línea de comandos,
+/// archivos abiertos con la aplicación o arrastrados a la ventana).
+pub const DEFAULT_IMPORT_STORE: &str = "Extractos";
+/// Extensiones de los archivos que se pueden importar con `read_file`.
+pub const SUPPORTED_EXTENSIONS: &[&str] = &["ofx", "qfx", "qif", "xlsx", "csv"];
+
+/// Formato de extracto bancario admitido.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum StatementFormat {
+    Ofx,
+    Qif,
+    Xlsx,
+    Csv,
+}
+
+/// Movimiento leído de un extracto. `amount` lleva signo: negativo para los cargos.
+#[derive(Debug, Clone)]
+pub struct StatementEntry {
+    pub date: NaiveDate,
+    pub amount: Decimal,
+    pub payee: Option<String>,
+    pub memo: Option<String>,
+    /// Identificador estable del movimiento, para no importarlo dos veces.
+    pub external_id: String,
+    pub currency: Option<String>,
+}
+
+/// Resultado de `import_bank_statement_command` e `import_transactions_xlsx_command`.
+#[derive(Debug, Clone, Serialize)]
+pub struct ImportSummary {
+    pub format: StatementFormat,
+    pub imported: usize,
+    /// Movimientos que ya se habían importado antes (mismo identificador).
+    pub skipped_existing: usize,
+    /// IDs de las transacciones importadas que parecen repetir otras ya registradas a mano.
+    pub possible_duplicates: Vec<String>,
+}
+
+/// Interpreta un importe con punto o coma decimal y separadores de miles opcionales.
+fn parse_amount(text: &str) -> Option<Decimal> {
+    let text: String = text.trim().chars().filter(|c| !c.is_whitespace()).collect();
+    let normalized = match (text.rfind('.'), text.rfind(',')) {
+        // El separador que aparece el último es el decimal.
+        (Some(dot), Some(comma)) if comma > dot => text.replace('.', "").replace(',', "."),
+        (Some(_), Some(_)) => text.replace(',', ""),
+        (None, Some(_)) => text.replace(',', "."),
+        _ => text,
+    };
+    Decimal::from_str(normalized.trim_start_matches('+')).ok()
+}
+
+/// Los extractos OFX 1.x suelen venir en Windows-1252; si el archivo no es UTF-8
+/// válido, se lee byte a byte como Latin-1, que coincide en las letras acentuadas.
+fn decode(bytes: Vec<u8>) -> String {
+    String::from_utf8(bytes).unwrap_or_else(|e| e.into_bytes().iter().map(|&b| b as char).collect())
+}
+
+/// Formato del extracto según la extensión del archivo o, si no es conocida, su contenido.
+pub fn detect_format(path: &Path, contents: &str) -> Option<StatementFormat> {
+    match path.extension().and_then(|e| e.to_str()).map(str::to_ascii_lowercase).as_deref() {
+        Some("ofx") | Some("qfx") => return Some(StatementFormat::Ofx),
+        Some("qif") => return Some(StatementFormat::Qif),
+        _ => {}
+    }
+    let head = contents.trim_start().to_ascii_uppercase();
+    if head.starts_with("OFXHEADER") || (head.starts_with("<?XML") && head.contains("<OFX")) {
+        Some(StatementFormat::Ofx)
+    } else if head.starts_with("!TYPE") || head.starts_with("!ACCOUNT") {
+        Some(StatementFormat::Qif)
+    } else {
+        None
+    }
+}
+
+// --- OFX ---
+
+/// Valores de las etiquetas de un fragmento OFX, en mayúsculas. Sirve tanto para
+/// OFX 1.x (SGML, sin etiquetas de cierre) como para OFX 2.x (XML). Si una
+/// etiqueta se repite, se conserva la primera.
+fn ofx_fields(fragment: &str) -> HashMap<String, String> {
+    let mut fields = HashMap::new();
+    let mut rest = fragment;
+    while let Some(open) = rest.find('<') {
+        let Some(close) = rest[open..].find('>') else { break };
+        let tag = &rest[open + 1..open + close];
+        rest = &rest[open + close + 1..];
+        if tag.starts_with('/') || tag.starts_with('?') {
+            continue;
+        }
+        let value = rest[..rest.find('<').unwrap_or(rest.len())].trim();
+        if !value.is_empty() {
+            fields.entry(tag.trim().to_ascii_uppercase()).or_insert_with(|| value.to_owned());
+        }
+    }
+    fields
+}
+
+/// Fecha OFX (`AAAAMMDD[HHMMSS[.XXX]][[zona]]`); solo se usa el día.
+fn parse_ofx_date(text: &str) -> Option<NaiveDate> {
+    NaiveDate::parse_from_str(text.get(..8)?, "%Y%m%d").ok()
+}
+
+/// Campo `field` del movimiento o fila `number` (desde 1) de un extracto, para los
+/// errores de validación.
+fn row_field(number: usize, field: &str) -> String {
+    format!("rows[{}].{}", number, field)
+}
+
+/// Movimientos (`<STMTTRN>`) de un extracto OFX. Si hay movimientos sin fecha o sin
+/// importe, el error los incluye todos.
+pub fn parse_ofx(contents: &str) -> Result<Vec<StatementEntry>, AppError> {
+    // Las etiquetas OFX son ASCII, así que las posiciones coinciden con las del original.
+    let upper = contents.to_ascii_uppercase();
+    let starts: Vec<usize> = upper.match_indices("<STMTTRN>").map(|(i, _)| i).collect();
+    let header = ofx_fields(&contents[..starts.first().copied().unwrap_or(contents.len())]);
+    let currency = header.get("CURDEF").cloned();
+    let account = header.get("ACCTID").cloned().unwrap_or_default();
+
+    let mut entries = Vec::with_capacity(starts.len());
+    let mut seen: HashMap<String, usize> = HashMap::new();
+    let mut validator = Validator::new();
+    for (index, &start) in starts.iter().enumerate() {
+        let end = upper[start..]
+            .find("</STMTTRN>")
+            .map(|e| start + e)
+            .unwrap_or_else(|| starts.get(index + 1).copied().unwrap_or(contents.len()));
+        let fields = ofx_fields(&contents[start + "<STMTTRN>".len()..end]);
+
+        let date = fields.get("DTPOSTED").and_then(|d| parse_ofx_date(d));
+        if date.is_none() {
+            validator.add(
+                &row_field(index + 1, "date"),
+                validation::INVALID_FORMAT,
+                format!("El movimiento {} del extracto OFX no tiene una fecha válida.", index + 1),
+            );
+        }
+        let amount = fields.get("TRNAMT").and_then(|a| parse_amount(a));
+        if amount.is_none() {
+            validator.add(
+                &row_field(index + 1, "amount"),
+                validation::INVALID_FORMAT,
+                format!("El movimiento {} del extracto OFX no tiene un importe válido.", index + 1),
+            );
+        }
+        let (Some(date), Some(amount)) = (date, amount) else { continue };
+        let payee = fields.get("NAME").or_else(|| fields.get("PAYEE")).cloned();
+        let memo = fields.get("MEMO").cloned();
+        let external_id = match fields.get("FITID") {
+            Some(fitid) => format!("ofx:{}:{}", account, fitid),
+            None => synthetic_id("ofx", &mut seen, date, amount, payee.as_deref()),
+        };
+        entries.push(StatementEntry { date, amount, payee, memo, external_id, currency: currency.clone() });
+    }
+    validator.finish()?;
+    Ok(entries)
+}
+
+// --- QIF ---
+
+/// Fecha escrita como texto (QIF, hojas de cálculo). Si el año va delante se lee
+/// año/mes/día; si no, se prueba primero el orden día/mes, el habitual en los bancos
+/// españoles, y después mes/día. Admite el apóstrofo de Quicken (`1/25'24`).
+fn parse_text_date(text: &str) -> Option<NaiveDate> {
+    let text = text.trim().replace('\'', "/").replace(['-', '.'], "/");
+    let parts: Vec<&str> = text.split('/').collect();
+    let [first, _, last] = parts.as_slice() else { return None };
+    // `%Y` también acepta dos cifras (año 24), así que el formato depende de la
+    // longitud de la parte que lleva el año.
+    let formats: &[&str] = match (first.len(), last.len()) {
+        (4, _) => &["%Y/%m/%d"],
+        (_, 2) => &["%d/%m/%y", "%m/%d/%y"],
+        (_, 4) => &["%d/%m/%Y", "%m/%d/%Y"],
+        _ => return None,
+    };
+    formats
+        .iter()
+        .find_map(|format| NaiveDate::parse_from_str(&text, format).ok())
+}
+
+/// Movimientos de un extracto QIF; cada uno termina en una línea `^`. Los errores
+/// se atribuyen a la línea del QIF (`rows[<línea>].date`, etc.).
+pub fn parse_qif(contents: &str) -> Result<Vec<StatementEntry>, AppError> {
+    let mut entries = Vec::new();
+    let mut seen: HashMap<String, usize> = HashMap::new();
+    let mut validator = Validator::new();
+    let (mut date, mut amount, mut payee, mut memo) = (None, None, None, None);
+    // Si el movimiento tenía una fecha o un importe no válidos ya se ha informado.
+    let mut entry_invalid = false;
+
+    for (line_number, line) in contents.lines().enumerate() {
+        let line = line.trim_end();
+        let Some(code) = line.chars().next() else { continue };
+        let value = line[code.len_utf8()..].trim();
+        match code {
+            '!' => {}
+            'D' => {
+                date = parse_text_date(value);
+                if date.is_none() {
+                    entry_invalid = true;
+                    validator.add(
+                        &row_field(line_number + 1, "date"),
+                        validation::INVALID_FORMAT,
+                        format!("Fecha no válida en la línea {} del QIF: '{}'.", line_number + 1, value),
+                    );
+                }
+            }
+            'T' | 'U' => {
+                amount = parse_amount(value);
+                if amount.is_none() {
+                    entry_invalid = true;
+                    validator.add(
+                        &row_field(line_number + 1, "amount"),
+                        validation::INVALID_FORMAT,
+                        format!("Importe no válido en la línea {} del QIF: '{}'.", line_number + 1, value),
+                    );
+                }
+            }
+            'P' => payee = Some(value.to_owned()).filter(|v| !v.is_empty()),
+            'M' => memo = Some(value.to_owned()).filter(|v| !v.is_empty()),
+            '^' => {
+                match (date.take(), amount.take()) {
+                    (Some(date), Some(amount)) => {
+                        let external_id = synthetic_id("qif", &mut seen, date, amount, payee.as_deref());
+                        entries.push(StatementEntry {
+                            date,
+                            amount,
+                            payee: payee.take(),
+                            memo: memo.take(),
+                            external_id,
+                            currency: None,
+                        });
+                    }
+                    _ if entry_invalid => {
+                        payee = None;
+                        memo = None;
+                    }
+                    _ => {
+                        validator.add(
+                            &row_field(line_number + 1, "amount"),
+                            validation::REQUIRED,
+                            format!(
+                                "El movimiento que termina en la línea {} del QIF no tiene fecha o importe.",
+                                line_number + 1
+                            ),
+                        );
+                        payee = None;
+                        memo = None;
+                    }
+                }
+                entry_invalid = false;
+            }
+            // Número de cheque, categoría, desgloses, etc.: no se usan.
+            _ => {}
+        }
+    }
+    validator.finish()?;
+    Ok(entries)
+}
+
+// --- XLSX ---
+
+/// Cabeceras que se reconocen sin indicarlas en `ColumnMapping`, ya normalizadas
+/// con `normalize_header`.
+const DATE_HEADERS: &[&str] = &["fecha", "fecha operacion", "fecha de operacion", "f. operacion", "fecha valor", "date"];
+const AMOUNT_HEADERS: &[&str] = &["importe", "importe eur", "cantidad", "monto", "amount"];
+const DEBIT_HEADERS: &[&str] = &["cargo", "cargos", "debe", "debito", "debit"];
+const CREDIT_HEADERS: &[&str] = &["abono", "abonos", "haber", "credito", "credit"];
+const DESCRIPTION_HEADERS: &[&str] = &["concepto", "descripcion", "detalle", "movimiento", "description"];
+const PAYEE_HEADERS: &[&str] = &["beneficiario", "ordenante", "comercio", "payee"];
+const CURRENCY_HEADERS: &[&str] = &["divisa", "moneda", "currency"];
+
+/// Cabeceras de las columnas de la hoja. Las que falten se buscan entre las
+/// habituales de los bancos. El importe puede venir en una columna con signo
+/// (`amount`) o en dos, cargos (`debit`) y abonos (`credit`).
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct ColumnMapping {
+    pub date: Option<String>,
+    pub amount: Option<String>,
+    pub debit: Option<String>,
+    pub credit: Option<String>,
+    pub description: Option<String>,
+    pub payee: Option<String>,
+    pub currency: Option<String>,
+    /// Formato chrono de las fechas escritas como texto (p. ej. `%d/%m/%Y`). Si
+    /// falta, se prueban los habituales.
+    pub date_format: Option<String>,
+}
+
+/// Posición de cada columna en la fila de cabecera.
+struct ColumnIndexes {
+    date: usize,
+    amount: Option<usize>,
+    debit: Option<usize>,
+    credit: Option<usize>,
+    description: Option<usize>,
+    payee: Option<usize>,
+    currency: Option<usize>,
+}
+
+/// Minúsculas, sin tildes y con los espacios simplificados, para comparar cabeceras.
+fn normalize_header(text: &str) -> String {
+    text.split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+        .to_lowercase()
+        .chars()
+        .map(|c| match c {
+            'á' | 'à' => 'a',
+            'é' | 'è' => 'e',
+            'í' => 'i',
+            'ó' | 'ò' => 'o',
+            'ú' | 'ü' => 'u',
+            other => other,
+        })
+        .collect()
+}
+
+/// Columna de `header` con el nombre indicado o, si no se indicó, con uno de los habituales.
+fn find_column(header: &[String], mapped: Option<&String>, defaults: &[&str]) -> Option<usize> {
+    match mapped {
+        Some(name) => {
+            let name = normalize_header(name);
+            header.iter().position(|h| *h == name)
+        }
+        None => header.iter().position(|h| defaults.contains(&h.as_str())),
+    }
+}
+
+fn column_indexes(header: &[String], mapping: &ColumnMapping) -> Option<ColumnIndexes> {
+    let indexes = ColumnIndexes {
+        date: find_column(header, mapping.date.as_ref(), DATE_HEADERS)?,
+        amount: find_column(header, mapping.amount.as_ref(), AMOUNT_HEADERS),
+        debit: find_column(header, mapping.debit.as_ref(), DEBIT_HEADERS),
+        credit: find_column(header, mapping.credit.as_ref(), CREDIT_HEADERS),
+        description: find_column(header, mapping.description.as_ref(), DESCRIPTION_HEADERS),
+        payee: find_column(header, mapping.payee.as_ref(), PAYEE_HEADERS),
+        currency: find_column(header, mapping.currency.as_ref(), CURRENCY_HEADERS),
+    };
+    if indexes.amount.is_none() && indexes.debit.is_none() && indexes.credit.is_none() {
+        return None;
+    }
+    Some(indexes)
+}
+
+fn cell_text(cell: Option<&Data>) -> Option<String> {
+    let text = match cell? {
+        Data::String(s) | Data::DateTimeIso(s) => s.trim().to_owned(),
+        Data::Float(f) => f.to_string(),
+        Data::Int(i) => i.to_string(),
+        Data::Bool(b) => b.to_string(),
+        _ => return None,
+    };
+    Some(text).filter(|t| !t.is_empty())
+}
+
+/// Fecha de una celda: una fecha de Excel, un número de serie de fecha o un texto.
+fn cell_date(cell: Option<&Data>, date_format: Option<&str>) -> Option<NaiveDate> {
+    let serial = |days: f64| {
+        // Excel cuenta los días desde el 30/12/1899 (por el falso 29/02/1900). Hasta
+        // el 31/12/9999.
+        if !(1.0..2_958_466.0).contains(&days) {
+            return None;
+        }
+        NaiveDate::from_ymd_opt(1899, 12, 30).map(|epoch| epoch + Duration::days(days.trunc() as i64))
+    };
+    match cell? {
+        Data::DateTime(dt) => dt.as_datetime().map(|d| d.date()),
+        Data::Float(f) => serial(*f),
+        Data::Int(i) => serial(*i as f64),
+        _ => {
+            let text = cell_text(cell)?;
+            // Solo la fecha, sin la hora si la trae.
+            let text = text.split_whitespace().next()?;
+            match date_format {
+                Some(format) => NaiveDate::parse_from_str(text, format).ok(),
+                None => NaiveDate::parse_from_str(text, "%Y-%m-%d").ok().or_else(|| parse_text_date(text)),
+            }
+        }
+    }
+}
+
+/// Importe de una celda numérica o de texto (`-1.234,56 €`).
+fn cell_amount(cell: Option<&Data>) -> Option<Decimal> {
+    match cell? {
+        Data::Float(f) => Decimal::from_f64(*f).map(money::round_money),
+        Data::Int(i) => Some(Decimal::from(*i)),
+        _ => {
+            let text: String = cell_text(cell)?
+                .chars()
+                .filter(|c| c.is_ascii_digit() || matches!(c, '.' | ',' | '-' | '+'))
+                .collect();
+            parse_amount(&text)
+        }
+    }
+}
+
+/// Movimientos de una hoja de cálculo exportada por el banco. La cabecera es la
+/// primera fila, entre las `MAX_HEADER_SCAN_ROWS` primeras, en la que aparecen la
+/// fecha y el importe; las filas sin fecha (totales, notas al pie) se omiten.
+pub fn parse_xlsx_rows(rows: &[&[Data]], mapping: &ColumnMapping) -> Result<Vec<StatementEntry>, AppError> {
+    parse_sheet_rows(rows, mapping, "xlsx")
+}
+
+/// Filas de un CSV: separadas por comas o, si la primera línea tiene más, por punto y
+/// coma (como exporta Excel en español). Los campos pueden ir entre comillas dobles,
+/// con `""` para una comilla y saltos de línea dentro.
+pub fn parse_csv_rows(contents: &str) -> Vec<Vec<String>> {
+    let first_line = contents.lines().next().unwrap_or_default();
+    let delimiter = if first_line.matches(';').count() > first_line.matches(',').count() { ';' } else { ',' };
+    let mut rows = Vec::new();
+    let mut row = Vec::new();
+    let mut field = String::new();
+    let mut quoted = false;
+    let mut chars = contents.trim_start_matches('\u{feff}').chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '"' if quoted && chars.peek() == Some(&'"') => {
+                field.push('"');
+                chars.next();
+            }
+            '"' => quoted = !quoted,
+            c if c == delimiter && !quoted => row.push(std::mem::take(&mut field)),
+            '\r' if !quoted => {}
+            '\n' if !quoted => {
+                row.push(std::mem::take(&mut field));
+                rows.push(std::mem::take(&mut row));
+            }
+            c => field.push(c),
+        }
+    }
+    if !field.is_empty() || !row.is_empty() {
+        row.push(field);
+        rows.push(row);
+    }
+    rows
+}
+
+/// Lee los movimientos de un archivo CSV exportado por el banco, con las mismas
+/// cabeceras y el mismo `mapping` que las hojas de Excel.
+pub fn read_csv(path: &Path, mapping: &ColumnMapping) -> Result<Vec<StatementEntry>, AppError> {
+    let bytes = std::fs::read(path).map_err(|e| {
+        error!("Could not read CSV {}: {}", path.display(), e);
+        AppError::Io(format!("Error al leer el archivo {}: {}", path.display(), e))
+    })?;
+    let rows: Vec<Vec<Data>> = parse_csv_rows(&decode(bytes))
+        .into_iter()
+        .map(|row| row.into_iter().map(Data::String).collect())
+        .collect();
+    let rows: Vec<&[Data]> = rows.iter().map(Vec::as_slice).collect();
+    parse_sheet_rows(&rows, mapping, "csv")
+}
+
+/// Movimientos de las filas de una hoja o un CSV. `id_prefix` distingue los
+/// identificadores de cada origen.
+fn parse_sheet_rows(rows: &[&[Data]], mapping: &ColumnMapping, id_prefix: &str) -> Result<Vec<StatementEntry>, AppError> {
+    let (header_row, columns) = rows
+        .iter()
+        .take(MAX_HEADER_SCAN_ROWS)
+        .enumerate()
+        .find_map(|(index, row)| {
+            let header: Vec<String> = row.iter().map(|c| normalize_header(&cell_text(Some(c)).unwrap_or_default())).collect();
+            column_indexes(&header, mapping).map(|columns| (index, columns))
+        })
+        .ok_or_else(|| {
+            AppError::invalid_field(
+                "mapping",
+                "No se encontró la fila de cabecera con la fecha y el importe. Indica los nombres de las columnas.",
+            )
+        })?;
+
+    let mut entries = Vec::new();
+    let mut seen: HashMap<String, usize> = HashMap::new();
+    let mut validator = Validator::new();
+    for (offset, row) in rows.iter().enumerate().skip(header_row + 1) {
+        let Some(date) = cell_date(row.get(columns.date), mapping.date_format.as_deref()) else { continue };
+        let cell = |index: Option<usize>| index.and_then(|i| row.get(i));
+        let amount = match columns.amount {
+            Some(_) => cell_amount(cell(columns.amount)),
+            None => {
+                let debit = cell_amount(cell(columns.debit)).map(|d| -d.abs());
+                let credit = cell_amount(cell(columns.credit)).map(|c| c.abs());
+                debit.filter(|d| !d.is_zero()).or(credit)
+            }
+        };
+        let Some(amount) = amount else {
+            validator.add(
+                &row_field(offset + 1, "amount"),
+                validation::INVALID_FORMAT,
+                format!("La fila {} de la hoja no tiene un importe válido.", offset + 1),
+            );
+            continue;
+        };
+        let description = cell_text(cell(columns.description));
+        let payee = cell_text(cell(columns.payee));
+        let external_id = synthetic_id(id_prefix, &mut seen, date, amount, payee.as_deref().or(description.as_deref()));
+        entries.push(StatementEntry {
+            date,
+            amount,
+            payee,
+            memo: description,
+            external_id,
+            currency: cell_text(cell(columns.currency)),
+        });
+    }
+    validator.finish()?;
+    Ok(entries)
+}
+
+/// Lee los movimientos de la hoja `sheet` (la primera si no se indica) de un archivo XLSX.
+pub fn read_xlsx(path: &Path, sheet: Option<&str>, mapping: &ColumnMapping) -> Result<Vec<StatementEntry>, AppError> {
+    let mut workbook: Xlsx<_> = open_workbook(path).map_err(|e| {
+        error!("Could not open workbook {}: {}", path.display(), e);
+        AppError::invalid_field("path", format!("No se pudo abrir el archivo Excel {}: {}", path.display(), e))
+    })?;
+    let sheet_names = workbook.sheet_names();
+    let sheet = match sheet.map(str::trim).filter(|s| !s.is_empty()) {
+        Some(name) => sheet_names.iter().find(|s| s.as_str() == name).cloned().ok_or_else(|| {
+            AppError::invalid_field(
+                "sheet",
+                format!("El archivo no tiene la hoja '{}'. Hojas disponibles: {}.", name, sheet_names.join(", ")),
+            )
+        })?,
+        None => sheet_names
+            .first()
+            .cloned()
+            .ok_or_else(|| AppError::invalid_field("path", "El archivo Excel no tiene hojas."))?,
+    };
+    let range = workbook.worksheet_range(&sheet).map_err(|e| {
+        error!("Could not read sheet '{}' of {}: {}", sheet, path.display(), e);
+        AppError::Io(format!("Error al leer la hoja '{}': {}", sheet, e))
+    })?;
+    let rows: Vec<&[Data]> = range.rows().collect();
+    parse_xlsx_rows(&rows, mapping)
+}
+
+/// Identificador para movimientos sin FITID (todos los de QIF): fecha, importe y
+/// beneficiario, más un contador para distinguir movimientos idénticos del mismo día.
+fn synthetic_id(
+    prefix: &str,
+    seen: &mut HashMap<String, usize>,
+    date: NaiveDate,
+    amount: Decimal,
+    payee: Option<&str>,
+) -> String {
+    let base = format!("{}:{}:{}:{}", prefix, date, amount.normalize(), payee.unwrap_or_default());
+    let count = seen.entry(base.clone()).or_insert(0);
+    *count += 1;
+    format!("{}#{}", base, count)
+}
+
+/// Lee y analiza un extracto OFX o QIF.
+pub fn read_statement(path: &Path) -> Result<(StatementFormat, Vec<StatementEntry>), AppError> {
+    let bytes = std::fs::read(path).map_err(|e| {
+        error!("Could not read statement {}: {}", path.display(), e);
+        AppError::Io(format!("Error al leer el extracto {}: {}", path.display(), e))
+    })?;
+    let contents = decode(bytes);
+    let format = detect_format(path, &contents).ok_or_else(|| {
+        AppError::invalid_field("path", "El archivo no parece un extracto OFX ni QIF.")
+    })?;
+    let entries = match format {
+        StatementFormat::Ofx => parse_ofx(&contents)?,
+        StatementFormat::Qif => parse_qif(&contents)?,
+    };
+    Ok((format, entries))
+}
+
+/// Lee un archivo de movimientos según su extensión: Excel y CSV con `mapping`
+/// (`sheet` solo cuenta en Excel) y el resto como extracto OFX o QIF.
+pub fn read_file(
+    path: &Path,
+    sheet: Option<&str>,
+    mapping: &ColumnMapping,
+) -> Result<(StatementFormat, Vec<StatementEntry>), AppError> {
+    match path.extension().and_then(|e| e.to_str()).map(str::to_ascii_lowercase).as_deref() {
+        Some("xlsx") => Ok((StatementFormat::Xlsx, read_xlsx(path, sheet, mapping)?)),
+        Some("csv") => Ok((StatementFormat::Csv, read_csv(path, mapping)?)),
+        _ => read_statement(path),
+    }
+}
+
+/// `true` si la extensión de `path` es una de `SUPPORTED_EXTENSIONS`.
+pub fn is_supported_file(path: &Path) -> bool {
+    path.extension()
+        .and_then(|e| e.to_str())
+        .is_some_and(|e| SUPPORTED_EXTENSIONS.contains(&e.to_ascii_lowercase().as_str()))
+}
+
+fn entry_description(entry: &StatementEntry) -> String {
+    match (entry.payee.as_deref(), entry.memo.as_deref()) {
+        (Some(payee), Some(memo)) if !payee.eq_ignore_ascii_case(memo) => format!("{} - {}", payee, memo),
+        (Some(text), _) | (None, Some(text)) => text.to_owned(),
+        (None, None) => DEFAULT_DESCRIPTION.to_owned(),
+    }
+}
+
+/// Convierte un movimiento en transacción de `store_name`. Los abonos son ingresos
+/// y los cargos, gastos.
+pub fn entry_to_transaction(entry: &StatementEntry, store_name: &str, currency: &str) -> Result<Transaction, AppError> {
+    let transaction_type = if entry.amount.is_sign_negative() { TransactionType::Gasto } else { TransactionType::Ingreso };
+    let amount = entry.amount.abs();
+    let now = periods::now_timestamp();
+    money::validate_amount("amount", amount, "El extracto contiene un movimiento sin importe.")?;
+    Ok(Transaction {
+        id: uuid::Uuid::new_v4().to_string(),
+        transaction_type,
+        amount,
+        description: entry_description(entry),
+        store_name: store_name.to_owned(),
+        timestamp: periods::timestamp_for_date(entry.date),
+        category: None,
+        subcategory: None,
+        currency: currency.to_owned(),
+        receipt_paths: Vec::new(),
+        deleted_at: None,
+        tax_rate: None,
+        tax_amount: None,
+        tags: Vec::new(),
+        external_id: Some(entry.external_id.clone()),
+        transaction_date: entry.date,
+        created_at: now,
+        updated_at: now,
+        notes: String::new(),
+        custom_fields: HashMap::new(),
+        payment_method: PaymentMethod::Otro,
+        contact_id: None,
+        transfer_store: None,
+        account_id: None,
+        transfer_account_id: None,
+        user_id: None,
+        revision: merge::initial_revision(),
+        device_id: String::new(),
+        sequence: 0,
+    })
+}
+
+/// Tienda de destino de una importación, sin espacios. No puede ser la vista de todas.
+fn validate_target_store(target_store: &str) -> Result<&str, AppError> {
+    let target_store = target_store.trim();
+    if target_store.is_empty() || target_store == "Todas las Tiendas" {
+        return Err(AppError::invalid_field("target_store", "Indica la tienda en la que importar el extracto."));
+    }
+    Ok(target_store)
+}
+
+/// Movimientos de un extracto convertidos en transacciones, antes de guardarlas.
+#[derive(Debug, Clone, Serialize)]
+pub struct StagedImport {
+    /// Transacciones nuevas, con las reglas ya aplicadas.
+    pub transactions: Vec<Transaction>,
+    /// Movimientos que ya se habían importado antes (mismo identificador).
+    pub skipped_existing: usize,
+    /// IDs de `transactions` que parecen repetir otras ya registradas a mano.
+    pub possible_duplicates: Vec<String>,
+}
+
+/// Convierte en transacciones de `target_store` los movimientos que no se habían
+/// importado antes y marca los que parecen repetir transacciones registradas a mano,
+/// sin guardar nada. Si algún movimiento no es válido, devuelve todos los errores.
+pub fn stage_entries(db: &SqliteStorage, entries: &[StatementEntry], target_store: &str) -> Result<StagedImport, AppError> {
+    let target_store = validate_target_store(target_store)?;
+    let base_currency = currencies::get_base_currency(db)?;
+    let existing_ids = db.all_external_ids()?;
+    let rules = rules::load_enabled(db.connection())?;
+    let policy = ValidationPolicy { require_category: false, ..validation::load_policy(db) };
+    let mut transactions = Vec::new();
+    let mut validator = Validator::new();
+    for (index, entry) in entries.iter().enumerate() {
+        if existing_ids.contains(&entry.external_id) || entry.amount.is_zero() {
+            continue;
+        }
+        let prefix = format!("rows[{}]", index + 1);
+        let currency = match &entry.currency {
+            Some(code) => validator.check_prefixed(&prefix, currencies::normalize_currency_code(code))?,
+            None => Some(base_currency.clone()),
+        };
+        let Some(currency) = currency else { continue };
+        let Some(mut transaction) =
+            validator.check_prefixed(&prefix, entry_to_transaction(entry, target_store, &currency))?
+        else {
+            continue;
+        };
+        rules::apply(db.connection(), &rules, &mut transaction, ApplyMode::Overwrite);
+        if validator.check_prefixed(&prefix, policy.check(&transaction))?.is_some() {
+            transactions.push(transaction);
+        }
+    }
+    // No se importa nada si algún movimiento no es válido.
+    validator.finish()?;
+    let skipped_existing = entries.iter().filter(|e| existing_ids.contains(&e.external_id)).count();
+
+    let window_hours = duplicates::load_duplicate_window(db);
+    let mut possible_duplicates = Vec::new();
+    for transaction in transactions.iter() {
+        if !duplicates::find_duplicates_of(db, transaction, window_hours)?.is_empty() {
+            possible_duplicates.push(transaction.id.clone());
+        }
+    }
+    Ok(StagedImport { transactions, skipped_existing, possible_duplicates })
+}
+
+/// Guarda los movimientos preparados con `stage_entries`. No avisa a la interfaz:
+/// sirve también sin ventana (ver `cli`). Devuelve además los cambios hechos, para el
+/// historial y los eventos.
+pub fn import_into(
+    db: &SqliteStorage,
+    format: StatementFormat,
+    entries: &[StatementEntry],
+    target_store: &str,
+    command: &str,
+) -> Result<(ImportSummary, Vec<Change>), AppError> {
+    let StagedImport { transactions, skipped_existing, possible_duplicates } = stage_entries(db, entries, target_store)?;
+    let changes: Vec<Change> = transactions.iter().cloned().map(Change::Insert).collect();
+    if !transactions.is_empty() {
+        backup::snapshot_before(db, backup::REASON_IMPORT)?;
+        db.insert_transactions(&transactions)?;
+        audit::record_changes(db.connection(), command, &changes);
+    }
+    Ok((ImportSummary { format, imported: transactions.len(), skipped_existing, possible_duplicates }, changes))
+}
+
+/// `import_into` desde la interfaz: avisa de los cambios y cada importación se deshace
+/// como un único paso.
+fn import_entries(
+    state: &AppState,
+    db: &SqliteStorage,
+    app: &AppHandle,
+    format: StatementFormat,
+    entries: &[StatementEntry],
+    target_store: &str,
+    command: &str,
+) -> Result<ImportSummary, AppError> {
+    let (summary, changes) = import_into(db, format, entries, target_store, command)?;
+    if !changes.is_empty() {
+        events::emit_transaction_changes(app, &changes);
+        state.history.lock().unwrap().record(HistoryEntry::new("Importar extracto", changes));
+        budgets::check_budget_alerts(db, app);
+    }
+    Ok(summary)
+}
+
+/// Vista previa de la importación de un archivo: lo que se importaría en
+/// `target_store`, lo que ya estaba importado y los posibles duplicados.
+#[derive(Debug, Clone, Serialize)]
+pub struct ImportPreview {
+    pub path: String,
+    pub format: StatementFormat,
+    pub target_store: String,
+    #[serde(flatten)]
+    pub staged: StagedImport,
+}
+
+/// Lee `path` y prepara su importación en `target_store` (por defecto,
+/// `DEFAULT_IMPORT_STORE`) sin guardar nada.
+pub fn preview_file(
+    db: &SqliteStorage,
+    path: &Path,
+    target_store: Option<&str>,
+    sheet: Option<&str>,
+) -> Result<ImportPreview, AppError> {
+    let target_store = target_store.unwrap_or(DEFAULT_IMPORT_STORE);
+    let (format, entries) = read_file(path, sheet, &ColumnMapping::default())?;
+    debug!("Parsed {} entries from {:?} file {}.", entries.len(), format, path.display());
+    Ok(ImportPreview {
+        path: path.display().to_string(),
+        format,
+        target_store: validate_target_store(target_store)?.to_owned(),
+        staged: stage_entries(db, &entries, target_store)?,
+    })
+}
+
+// --- Comandos Tauri ---
+
+/// Comando para importar un extracto bancario OFX/QFX o QIF en la tienda
+/// `target_store`. Los movimientos ya importados antes se omiten, así que el mismo
+/// extracto (o uno que se solape) se puede importar varias veces. Antes de importar
+/// se crea una copia de seguridad.
+#[tauri::command]
+pub async fn import_bank_statement_command(
+    state: State<'_, AppState>,
+    app: AppHandle,
+    path: String,
+    target_store: String,
+) -> Result<ImportSummary, AppError> {
+    info!("Received import_bank_statement_command: {} -> '{}'", path, target_store);
+    let target_store = validate_target_store(&target_store)?;
+    let (format, entries) = read_statement(Path::new(&path))?;
+    debug!("Parsed {} entries from {:?} statement.", entries.len(), format);
+
+    let db = state.db().await?;
+    let summary = import_entries(&state, &db, &app, format, &entries, target_store, "import_bank_statement_command")?;
+    info!(
+        "Statement {} imported: {} new, {} already present, {} possible duplicates.",
+        path,
+        summary.imported,
+        summary.skipped_existing,
+        summary.possible_duplicates.len()
+    );
+    Ok(summary)
+}
+
+/// Comando para importar los movimientos de una hoja de un archivo Excel (.xlsx)
+/// exportado por el banco en la tienda `target_store`. `mapping` indica las
+/// cabeceras de las columnas si no son las habituales; la fila de cabecera y el
+/// formato de fechas e importes se detectan. Como con los extractos, los movimientos
+/// ya importados se omiten y antes de importar se crea una copia de seguridad.
+#[tauri::command]
+pub async fn import_transactions_xlsx_command(
+    state: State<'_, AppState>,
+    app: AppHandle,
+    path: String,
+    sheet: Option<String>,
+    mapping: Option<ColumnMapping>,
+    target_store: String,
+) -> Result<ImportSummary, AppError> {
+    info!("Received import_transactions_xlsx_command: {} (sheet {:?}) -> '{}'", path, sheet, target_store);
+    let target_store = validate_target_store(&target_store)?;
+    let entries = read_xlsx(Path::new(&path), sheet.as_deref(), &mapping.unwrap_or_default())?;
+    debug!("Parsed {} entries from XLSX.", entries.len());
+
+    let db = state.db().await?;
+    let summary = import_entries(
+        &state,
+        &db,
+        &app,
+        StatementFormat::Xlsx,
+        &entries,
+        target_store,
+        "import_transactions_xlsx_command",
+    )?;
+    info!(
+        "Workbook {} imported: {} new, {} already present, {} possible duplicates.",
+        path,
+        summary.imported,
+        summary.skipped_existing,
+        summary.possible_duplicates.len()
+    );
+    Ok(summary)
+}
+
+/// Comando para ver qué se importaría de un archivo (extracto OFX/QIF, Excel o CSV)
+/// en `target_store` sin importar nada: las transacciones nuevas, las ya importadas y
+/// los posibles duplicados. Es la vista previa de los archivos abiertos con la
+/// aplicación o arrastrados a la ventana (ver `opened_files`).
+#[tauri::command]
+pub async fn preview_import_file_command(
+    state: State<'_, AppState>,
+    path: String,
+    target_store: Option<String>,
+    sheet: Option<String>,
+) -> Result<ImportPreview, AppError> {
+    debug!("Received preview_import_file_command: {} -> {:?}", path, target_store);
+    let db = state.db().await?;
+    preview_file(&db, Path::new(&path), target_store.as_deref(), sheet.as_deref())
+}
+
+/// Comando para importar un archivo de movimientos en `target_store`, con el formato
+/// según su extensión. Como con los extractos, los movimientos ya importados se
+/// omiten y antes de importar se crea una copia de seguridad.
+#[tauri::command]
+pub async fn import_file_command(
+    state: State<'_, AppState>,
+    app: AppHandle,
+    path: String,
+    target_store: String,
+    sheet: Option<String>,
+) -> Result<ImportSummary, AppError> {
+    info!("Received import_file_command: {} -> '{}'", path, target_store);
+    let target_store = validate_target_store(&target_store)?;
+    let (format, entries) = read_file(Path::new(&path), sheet.as_deref(), &ColumnMapping::default())?;
+    let db = state.db().await?;
+    let summary = import_entries(&state, &db, &app, format, &entries, target_store, "import_file_command")?;
+    info!(
+        "File {} imported: {} new, {} already present, {} possible duplicates.",
+        path,
+        summary.imported,
+        summary.skipped_existing,
+        summary.possible_duplicates.len()
+    );
+    Ok(summary)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn date(y: i32, m: u32, d: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(y, m, d).unwrap()
+    }
+
+    #[test]
+    fn parse_text_date_reads_two_digit_years() {
+        assert_eq!(parse_text_date("25/01/24"), Some(date(2024, 1, 25)));
+        assert_eq!(parse_text_date("05.03.24"), Some(date(2024, 3, 5)));
+        // Día/mes imposible: se lee como mes/día.
+        assert_eq!(parse_text_date("1/25'24"), Some(date(2024, 1, 25)));
+    }
+
+    #[test]
+    fn parse_text_date_reads_four_digit_years() {
+        assert_eq!(parse_text_date("25/01/2024"), Some(date(2024, 1, 25)));
+        assert_eq!(parse_text_date("2024-01-25"), Some(date(2024, 1, 25)));
+        assert_eq!(parse_text_date("2024/1/5"), Some(date(2024, 1, 5)));
+    }
+
+    #[test]
+    fn parse_text_date_rejects_invalid_dates() {
+        assert_eq!(parse_text_date(""), None);
+        assert_eq!(parse_text_date("25/01"), None);
+        assert_eq!(parse_text_date("32/13/2024"), None);
+        assert_eq!(parse_text_date("2024-13-01"), None);
+    }
+
+    #[test]
+    fn parse_qif_reads_entries() {
+        let qif = "!Type:Bank\nD25/01/2024\nT-12,50\nPMercadona\nMCompra semanal\n^\nD2024-01-26\nT1.200,00\nPNómina\n^\n";
+        let entries = parse_qif(qif).unwrap();
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[0].date, date(2024, 1, 25));
+        assert_eq!(entries[0].amount, Decimal::new(-1250, 2));
+        assert_eq!(entries[0].payee.as_deref(), Some("Mercadona"));
+        assert_eq!(entries[0].memo.as_deref(), Some("Compra semanal"));
+        assert_eq!(entries[1].date, date(2024, 1, 26));
+        assert_eq!(entries[1].amount, Decimal::new(120000, 2));
+        assert_ne!(entries[0].external_id, entries[1].external_id);
+    }
+
+    #[test]
+    fn parse_qif_reports_invalid_dates_by_line() {
+        let qif = "!Type:Bank\nDayer\nT-12,50\n^\n";
+        match parse_qif(qif) {
+            Err(AppError::Validation { errors, .. }) => {
+                assert_eq!(errors.len(), 1);
+                assert_eq!(errors[0].field, "rows[2].date");
+            }
+            other => panic!("unexpected result: {:?}", other),
+        }
+    }
+
+    #[test]
+    fn parse_ofx_reads_sgml_statements() {
+        let ofx = "OFXHEADER:100\n<OFX><BANKMSGSRSV1><STMTTRNRS><STMTRS><CURDEF>EUR\n\
+                   <BANKACCTFROM><ACCTID>ES001</BANKACCTFROM><BANKTRANLIST>\n\
+                   <STMTTRN><TRNTYPE>DEBIT<DTPOSTED>20240125120000[-5:EST]<TRNAMT>-12.50<FITID>A1<NAME>Mercadona<MEMO>Compra\n\
+                   <STMTTRN><TRNTYPE>CREDIT<DTPOSTED>20240126<TRNAMT>1200.00<NAME>Nómina\n\
+                   </BANKTRANLIST></STMTRS></STMTTRNRS></BANKMSGSRSV1></OFX>";
+        let entries = parse_ofx(ofx).unwrap();
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[0].date, date(2024, 1, 25));
+        assert_eq!(entries[0].amount, Decimal::new(-1250, 2));
+        assert_eq!(entries[0].payee.as_deref(), Some("Mercadona"));
+        assert_eq!(entries[0].memo.as_deref(), Some("Compra"));
+        assert_eq!(entries[0].external_id, "ofx:ES001:A1");
+        assert_eq!(entries[0].currency.as_deref(), Some("EUR"));
+        assert_eq!(entries[1].date, date(2024, 1, 26));
+        assert!(entries[1].external_id.starts_with("ofx:2024-01-26:1200"));
+    }
+
+    #[test]
+    fn parse_ofx_reports_entries_without_amount() {
+        let ofx = "<OFX><STMTTRN><DTPOSTED>20240125<FITID>A1</STMTTRN></OFX>";
+        match parse_ofx(ofx) {
+            Err(AppError::Validation { errors, .. }) => {
+                assert_eq!(errors.len(), 1);
+                assert_eq!(errors[0].field, "rows[1].amount");
+            }
+            other => panic!("unexpected result: {:?}", other),
+        }
+    }
+}
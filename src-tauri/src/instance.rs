@@ -0,0 +1,164 @@
+// src-tauri/src/instance.rs
+
+use serde::{Deserialize, Serialize};
+use std::io::{BufRead, BufReader, Write};
+use std::net::{SocketAddr, TcpStream};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use tauri::{AppHandle, Url};
+use tokio::io::{AsyncBufReadExt, AsyncReadExt, AsyncWriteExt};
+use log::{debug, info, warn};
+
+use crate::deep_links;
+use crate::import;
+use crate::opened_files;
+use crate::storage;
+
+// Si la aplicación ya está abierta, abrir un enlace `contabilidad://` o un extracto
+// lanza otro proceso. Ese proceso no abre otra ventana sobre los mismos datos: pasa
+// los enlaces y archivos a la instancia en marcha por una conexión local y termina.
+
+/// Archivo con el puerto y el token de la instancia en marcha.
+const INSTANCE_FILE: &str = "instance.json";
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(2);
+/// Tamaño máximo de un mensaje de otra instancia.
+const MAX_MESSAGE_BYTES: u64 = 64 * 1024;
+
+/// Contenido de `INSTANCE_FILE`.
+#[derive(Debug, Serialize, Deserialize)]
+struct InstanceInfo {
+    port: u16,
+    /// Secreto que debe enviar quien se conecta, para no aceptar mensajes de otros
+    /// programas que encuentren el puerto.
+    token: String,
+}
+
+/// Mensaje de una instancia nueva a la que está en marcha.
+#[derive(Debug, Serialize, Deserialize)]
+struct Forwarded {
+    token: String,
+    args: Vec<String>,
+}
+
+fn instance_path() -> PathBuf {
+    storage::get_app_data_dir().join(INSTANCE_FILE)
+}
+
+/// Argumentos que otra instancia puede atender: enlaces y archivos importables, estos
+/// con la ruta absoluta porque el directorio de trabajo de la otra puede ser distinto.
+fn forwardable(args: &[String]) -> Vec<String> {
+    args.iter()
+        .filter_map(|arg| {
+            if deep_links::is_deep_link(arg) {
+                return Some(arg.clone());
+            }
+            let path = Path::new(arg);
+            if !arg.starts_with('-') && import::is_supported_file(path) && path.is_file() {
+                return std::fs::canonicalize(path).ok().map(|p| p.display().to_string());
+            }
+            None
+        })
+        .collect()
+}
+
+fn send(info: &InstanceInfo, args: Vec<String>) -> std::io::Result<bool> {
+    let address = SocketAddr::from(([127, 0, 0, 1], info.port));
+    let mut stream = TcpStream::connect_timeout(&address, CONNECT_TIMEOUT)?;
+    stream.set_read_timeout(Some(CONNECT_TIMEOUT))?;
+    stream.set_write_timeout(Some(CONNECT_TIMEOUT))?;
+    let mut message = serde_json::to_vec(&Forwarded { token: info.token.clone(), args })?;
+    message.push(b'\n');
+    stream.write_all(&message)?;
+    let mut reply = String::new();
+    BufReader::new(stream).read_line(&mut reply)?;
+    Ok(reply.trim() == "ok")
+}
+
+/// Pasa los enlaces y archivos de `args` a la instancia en marcha, si la hay. Devuelve
+/// `true` si los aceptó y este proceso debe terminar. Se llama al principio de `main`.
+pub fn forward_to_running_instance(args: &[String]) -> bool {
+    let args = forwardable(args);
+    if args.is_empty() {
+        return false;
+    }
+    let Some(info) = std::fs::read(instance_path())
+        .ok()
+        .and_then(|contents| serde_json::from_slice::<InstanceInfo>(&contents).ok())
+    else {
+        return false;
+    };
+    match send(&info, args) {
+        Ok(accepted) => accepted,
+        // La instancia anterior ya no está: se arranca normalmente.
+        Err(e) => {
+            debug!("No running instance to forward to: {}", e);
+            false
+        }
+    }
+}
+
+/// Atiende los enlaces y archivos que llegan de otra instancia.
+fn dispatch(app: &AppHandle, args: Vec<String>) {
+    let (links, files): (Vec<String>, Vec<String>) = args.into_iter().partition(|arg| deep_links::is_deep_link(arg));
+    deep_links::open_links(app, links.iter().filter_map(|link| Url::parse(link).ok()).collect());
+    opened_files::open_files(app, files.into_iter().map(PathBuf::from).collect());
+}
+
+async fn handle_connection(app: &AppHandle, stream: tokio::net::TcpStream, token: &str) -> std::io::Result<()> {
+    let (reader, mut writer) = stream.into_split();
+    let mut line = String::new();
+    tokio::io::BufReader::new(reader.take(MAX_MESSAGE_BYTES)).read_line(&mut line).await?;
+    let accepted = match serde_json::from_str::<Forwarded>(&line) {
+        Ok(message) if message.token == token => {
+            info!("Received {} arguments from another instance.", message.args.len());
+            dispatch(app, message.args);
+            true
+        }
+        _ => {
+            warn!("Rejected a message from another process.");
+            false
+        }
+    };
+    writer.write_all(if accepted { b"ok\n" } else { b"error\n" }).await
+}
+
+/// Escucha en un puerto local los mensajes de otras instancias y deja el puerto en
+/// `INSTANCE_FILE`. Se llama desde `setup`.
+pub fn spawn_listener(app: AppHandle) {
+    tauri::async_runtime::spawn(async move {
+        let listener = match tokio::net::TcpListener::bind(("127.0.0.1", 0)).await {
+            Ok(listener) => listener,
+            Err(e) => {
+                warn!("Could not listen for other instances: {}", e);
+                return;
+            }
+        };
+        let info = match listener.local_addr() {
+            Ok(address) => InstanceInfo { port: address.port(), token: uuid::Uuid::new_v4().to_string() },
+            Err(e) => {
+                warn!("Could not listen for other instances: {}", e);
+                return;
+            }
+        };
+        let contents = serde_json::to_vec(&info).unwrap_or_default();
+        if let Err(e) = storage::write_atomic(&instance_path(), &contents) {
+            warn!("Could not write the instance file: {}", e);
+            return;
+        }
+        debug!("Listening for other instances on port {}.", info.port);
+        loop {
+            match listener.accept().await {
+                Ok((stream, _)) => {
+                    let app = app.clone();
+                    let token = info.token.clone();
+                    tauri::async_runtime::spawn(async move {
+                        if let Err(e) = handle_connection(&app, stream, &token).await {
+                            debug!("Instance connection failed: {}", e);
+                        }
+                    });
+                }
+                Err(e) => warn!("Failed to accept an instance connection: {}", e),
+            }
+        }
+    });
+}
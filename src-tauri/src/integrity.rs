@@ -0,0 +1,401 @@
+// src-tauri/src/integrity.rs
+
+use rusqlite::{params, Connection};
+use rusqlite::types::Value;
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashSet};
+use std::str::FromStr;
+use tauri::{AppHandle, State};
+use log::{debug, info, warn};
+
+use crate::accounts;
+use crate::attachments;
+use crate::audit;
+use crate::backup;
+use crate::categories;
+use crate::contacts;
+use crate::error::AppError;
+use crate::events;
+use crate::history::{Change, HistoryEntry};
+use crate::periods;
+use crate::storage::{db_error, SqliteStorage, TransactionRepository};
+use crate::stores::ALL_STORES;
+use crate::{AppState, Transaction};
+
+/// Tienda que se asigna a las transacciones con una tienda no válida.
+const FALLBACK_STORE: &str = "Sin tienda";
+
+/// Tipo de problema que detecta `verify_data_integrity_command`. Cada uno, salvo
+/// `DuplicateId` y `UnreadableTransaction`, tiene su reparación en `repair_data_command`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum IssueKind {
+    /// Varias filas con el mismo ID. La clave primaria lo impide; solo pasa si el
+    /// archivo se modificó fuera de la aplicación. No se repara automáticamente.
+    DuplicateId,
+    /// Filas que no se pueden leer por un motivo distinto del importe (tipo o medio
+    /// de pago desconocidos). No se reparan automáticamente.
+    UnreadableTransaction,
+    /// Importe que no es un número (p. ej. `NaN`) o, en una transacción activa, cero.
+    /// Se pasa a la papelera con importe cero y el valor original en las notas.
+    InvalidAmount,
+    /// Importe negativo: el signo lo da el tipo. Se guarda en positivo.
+    NegativeAmount,
+    /// Carpeta de adjuntos de una transacción que ya no existe. Se borra.
+    OrphanAttachment,
+    /// Justificante cuyo archivo ya no está. Se quita de la transacción.
+    MissingReceipt,
+    /// Categoría o subcategoría que ya no existe. Se quita de la transacción.
+    UnknownCategory,
+    /// Contacto que ya no existe. Se quita de la transacción.
+    UnknownContact,
+    /// Cuenta (de origen o de destino) que ya no existe. Se quita de la transacción.
+    UnknownAccount,
+    /// Tienda vacía o con el nombre reservado de la vista de todas las tiendas. Se
+    /// cambia por `FALLBACK_STORE`.
+    InvalidStore,
+}
+
+impl IssueKind {
+    fn is_fixable(self) -> bool {
+        !matches!(self, IssueKind::DuplicateId | IssueKind::UnreadableTransaction)
+    }
+}
+
+/// Problema encontrado. `transaction_id` falta en los que no son de una transacción
+/// (carpetas de adjuntos huérfanas).
+#[derive(Debug, Clone, Serialize)]
+pub struct IntegrityIssue {
+    pub kind: IssueKind,
+    pub transaction_id: Option<String>,
+    pub detail: String,
+    pub fixable: bool,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct IntegrityReport {
+    pub checked_transactions: usize,
+    pub issues: Vec<IntegrityIssue>,
+    /// Número de problemas de cada tipo.
+    pub counts: BTreeMap<IssueKind, usize>,
+}
+
+/// Resultado de `repair_data_command`: la copia previa, lo reparado por tipo y la
+/// comprobación después de reparar.
+#[derive(Debug, Clone, Serialize)]
+pub struct RepairSummary {
+    pub backup_created: bool,
+    pub fixed: BTreeMap<IssueKind, usize>,
+    pub report: IntegrityReport,
+}
+
+fn issue(kind: IssueKind, transaction_id: Option<&str>, detail: String) -> IntegrityIssue {
+    IntegrityIssue { kind, transaction_id: transaction_id.map(str::to_owned), detail, fixable: kind.is_fixable() }
+}
+
+/// Importe guardado en `value` si no es válido: no es un número o, en una transacción
+/// activa, es cero. Los negativos se tratan aparte.
+fn invalid_amount(value: &Value, active: bool) -> Option<String> {
+    let (text, parsed) = match value {
+        Value::Text(text) => (text.clone(), Decimal::from_str(text.trim()).ok()),
+        Value::Integer(i) => (i.to_string(), Some(Decimal::from(*i))),
+        Value::Real(r) => (r.to_string(), Decimal::from_str(&r.to_string()).ok()),
+        Value::Null => ("NULL".to_string(), None),
+        Value::Blob(_) => ("BLOB".to_string(), None),
+    };
+    match parsed {
+        None => Some(text),
+        Some(amount) if active && amount.is_zero() => Some(text),
+        Some(_) => None,
+    }
+}
+
+/// IDs e importes originales de las transacciones con un importe no válido.
+fn find_invalid_amounts(db: &SqliteStorage) -> Result<Vec<(String, String)>, AppError> {
+    let mut stmt = db
+        .connection()
+        .prepare("SELECT id, amount, deleted_at IS NULL FROM transactions ORDER BY rowid")
+        .map_err(db_error)?;
+    let rows = stmt
+        .query_map([], |row| Ok((row.get::<_, String>(0)?, row.get::<_, Value>(1)?, row.get::<_, bool>(2)?)))
+        .map_err(db_error)?;
+    let mut invalid = Vec::new();
+    for row in rows {
+        let (id, amount, active) = row.map_err(db_error)?;
+        if let Some(text) = invalid_amount(&amount, active) {
+            invalid.push((id, text));
+        }
+    }
+    Ok(invalid)
+}
+
+fn category_exists(known: &HashSet<(String, String)>, name: &str, parent: &str) -> bool {
+    known.contains(&(name.to_owned(), parent.to_owned()))
+}
+
+/// Categoría de `transaction` que no existe, si la hay.
+fn unknown_category(known: &HashSet<(String, String)>, transaction: &Transaction) -> Option<String> {
+    match (transaction.category.as_deref(), transaction.subcategory.as_deref()) {
+        (Some(category), _) if !category_exists(known, category, "") => Some(category.to_owned()),
+        (Some(category), Some(sub)) if !category_exists(known, sub, category) => Some(format!("{} > {}", category, sub)),
+        (None, Some(sub)) => Some(sub.to_owned()),
+        _ => None,
+    }
+}
+
+/// Categorías (como `(nombre, categoría padre)`, con padre vacío en las principales),
+/// contactos y cuentas que existen.
+struct Known {
+    categories: HashSet<(String, String)>,
+    contacts: HashSet<String>,
+    accounts: HashSet<String>,
+}
+
+impl Known {
+    fn load(conn: &Connection) -> Result<Self, AppError> {
+        let categories = categories::list_categories(conn)?
+            .into_iter()
+            .flat_map(|c| {
+                let subcategories: Vec<(String, String)> =
+                    c.subcategories.into_iter().map(|s| (s, c.name.clone())).collect();
+                std::iter::once((c.name, String::new())).chain(subcategories)
+            })
+            .collect();
+        let contacts = contacts::list_contacts(conn, None)?.into_iter().map(|c| c.id).collect();
+        let accounts = accounts::list_accounts(conn)?.into_iter().map(|a| a.id).collect();
+        Ok(Known { categories, contacts, accounts })
+    }
+}
+
+fn is_invalid_store(name: &str) -> bool {
+    name.trim().is_empty() || name == ALL_STORES
+}
+
+/// Comprueba las transacciones (también las de la papelera) y los adjuntos.
+pub fn verify(db: &SqliteStorage) -> Result<IntegrityReport, AppError> {
+    let conn = db.connection();
+    let mut issues = Vec::new();
+
+    let mut stmt = conn
+        .prepare("SELECT id, COUNT(*) FROM transactions GROUP BY id HAVING COUNT(*) > 1")
+        .map_err(db_error)?;
+    let duplicates = stmt
+        .query_map([], |row| Ok((row.get::<_, String>(0)?, row.get::<_, i64>(1)?)))
+        .map_err(db_error)?
+        .collect::<rusqlite::Result<Vec<_>>>()
+        .map_err(db_error)?;
+    for (id, count) in duplicates {
+        issues.push(issue(IssueKind::DuplicateId, Some(&id), format!("{} filas con el mismo ID.", count)));
+    }
+
+    let invalid_amounts = find_invalid_amounts(db)?;
+    let invalid_ids: HashSet<&str> = invalid_amounts.iter().map(|(id, _)| id.as_str()).collect();
+    for (id, amount) in invalid_amounts.iter() {
+        issues.push(issue(IssueKind::InvalidAmount, Some(id), format!("Importe no válido: '{}'.", amount)));
+    }
+
+    let (transactions, unreadable) = db.scan_transactions()?;
+    for (id, reason) in unreadable.iter().filter(|(id, _)| !invalid_ids.contains(id.as_str())) {
+        issues.push(issue(IssueKind::UnreadableTransaction, Some(id), format!("No se puede leer: {}", reason)));
+    }
+
+    let known = Known::load(conn)?;
+
+    for t in transactions.iter() {
+        let id = Some(t.id.as_str());
+        if t.amount.is_sign_negative() && !t.amount.is_zero() {
+            issues.push(issue(IssueKind::NegativeAmount, id, format!("Importe negativo: {}.", t.amount)));
+        }
+        for path in t.receipt_paths.iter().filter(|p| !attachments::resolve_receipt_path(p).exists()) {
+            issues.push(issue(IssueKind::MissingReceipt, id, format!("Falta el justificante {}.", path)));
+        }
+        if let Some(category) = unknown_category(&known.categories, t) {
+            issues.push(issue(IssueKind::UnknownCategory, id, format!("La categoría '{}' no existe.", category)));
+        }
+        if let Some(contact_id) = t.contact_id.as_ref().filter(|c| !known.contacts.contains(*c)) {
+            issues.push(issue(IssueKind::UnknownContact, id, format!("El contacto {} no existe.", contact_id)));
+        }
+        for account_id in [&t.account_id, &t.transfer_account_id].into_iter().flatten() {
+            if !known.accounts.contains(account_id) {
+                issues.push(issue(IssueKind::UnknownAccount, id, format!("La cuenta {} no existe.", account_id)));
+            }
+        }
+        for store in std::iter::once(&t.store_name).chain(t.transfer_store.as_ref()) {
+            if is_invalid_store(store) {
+                issues.push(issue(IssueKind::InvalidStore, id, format!("Tienda no válida: '{}'.", store)));
+            }
+        }
+    }
+
+    for folder in attachments::find_orphan_attachments(db)? {
+        issues.push(issue(IssueKind::OrphanAttachment, None, format!("Carpeta de adjuntos sin transacción: {}.", folder)));
+    }
+
+    let mut counts = BTreeMap::new();
+    for found in issues.iter() {
+        *counts.entry(found.kind).or_insert(0) += 1;
+    }
+    Ok(IntegrityReport { checked_transactions: transactions.len() + unreadable.len(), issues, counts })
+}
+
+/// Pasa a la papelera las transacciones con un importe no válido, con importe cero y
+/// el valor original en las notas. Se hace en SQL porque no se pueden leer como
+/// `Transaction`, así que no se puede deshacer desde el historial (sí con la copia).
+fn quarantine_invalid_amounts(db: &SqliteStorage) -> Result<usize, AppError> {
+    let invalid = find_invalid_amounts(db)?;
+    let now = periods::now_timestamp() as i64;
+    let tx = db.connection().unchecked_transaction().map_err(db_error)?;
+    for (id, amount) in invalid.iter() {
+        tx.execute(
+            "UPDATE transactions SET amount = '0', \
+                 notes = TRIM(notes || char(10) || ?2), \
+                 deleted_at = COALESCE(deleted_at, ?3), updated_at = ?3 \
+             WHERE id = ?1",
+            params![id, format!("Importe original no válido: '{}'.", amount), now],
+        )
+        .map_err(db_error)?;
+    }
+    tx.commit().map_err(db_error)?;
+    Ok(invalid.len())
+}
+
+/// Aplica a una transacción las reparaciones de `fixes` que le afecten. Devuelve la
+/// transacción reparada, si cambió, y cuántas reparaciones de cada tipo se hicieron.
+fn repair_transaction(
+    transaction: &Transaction,
+    fixes: &HashSet<IssueKind>,
+    known: &Known,
+) -> Option<(Transaction, BTreeMap<IssueKind, usize>)> {
+    let mut repaired = transaction.clone();
+    let mut fixed = BTreeMap::new();
+    let mut count = |kind: IssueKind| *fixed.entry(kind).or_insert(0) += 1;
+    if fixes.contains(&IssueKind::NegativeAmount) && repaired.amount.is_sign_negative() && !repaired.amount.is_zero() {
+        repaired.amount = repaired.amount.abs();
+        count(IssueKind::NegativeAmount);
+    }
+    if fixes.contains(&IssueKind::MissingReceipt) {
+        let before = repaired.receipt_paths.len();
+        repaired.receipt_paths.retain(|p| attachments::resolve_receipt_path(p).exists());
+        for _ in repaired.receipt_paths.len()..before {
+            count(IssueKind::MissingReceipt);
+        }
+    }
+    if fixes.contains(&IssueKind::UnknownCategory) && unknown_category(&known.categories, &repaired).is_some() {
+        repaired.category = None;
+        repaired.subcategory = None;
+        count(IssueKind::UnknownCategory);
+    }
+    if fixes.contains(&IssueKind::UnknownContact) && repaired.contact_id.as_ref().is_some_and(|c| !known.contacts.contains(c)) {
+        repaired.contact_id = None;
+        count(IssueKind::UnknownContact);
+    }
+    if fixes.contains(&IssueKind::UnknownAccount) {
+        for account_id in [&mut repaired.account_id, &mut repaired.transfer_account_id] {
+            if account_id.as_ref().is_some_and(|a| !known.accounts.contains(a)) {
+                *account_id = None;
+                count(IssueKind::UnknownAccount);
+            }
+        }
+    }
+    if fixes.contains(&IssueKind::InvalidStore) {
+        if is_invalid_store(&repaired.store_name) {
+            repaired.store_name = FALLBACK_STORE.to_string();
+            count(IssueKind::InvalidStore);
+        }
+        if repaired.transfer_store.as_deref().is_some_and(is_invalid_store) {
+            repaired.transfer_store = Some(FALLBACK_STORE.to_string());
+            count(IssueKind::InvalidStore);
+        }
+    }
+    if fixed.is_empty() {
+        return None;
+    }
+    repaired.updated_at = periods::now_timestamp();
+    Some((repaired, fixed))
+}
+
+// --- Comandos Tauri ---
+
+/// Comando para comprobar la integridad de los datos: IDs repetidos, importes no
+/// válidos o negativos, adjuntos huérfanos o que faltan, y transacciones con
+/// categorías, contactos, cuentas o tiendas que ya no existen. No cambia nada.
+#[tauri::command]
+pub async fn verify_data_integrity_command(state: State<'_, AppState>) -> Result<IntegrityReport, AppError> {
+    debug!("Received verify_data_integrity_command.");
+    let db = state.db().await?;
+    let report = verify(&db)?;
+    info!("Integrity check: {} issues in {} transactions.", report.issues.len(), report.checked_transactions);
+    Ok(report)
+}
+
+/// Comando para aplicar las reparaciones de los tipos de problema de `fixes`. Antes
+/// se crea una copia de seguridad; los cambios en transacciones legibles se pueden
+/// deshacer como un único paso. Devuelve lo reparado y una nueva comprobación.
+#[tauri::command]
+pub async fn repair_data_command(
+    state: State<'_, AppState>,
+    app: AppHandle,
+    fixes: Vec<IssueKind>,
+) -> Result<RepairSummary, AppError> {
+    info!("Received repair_data_command: {:?}", fixes);
+    if let Some(kind) = fixes.iter().find(|k| !k.is_fixable()) {
+        return Err(AppError::invalid_field("fixes", format!("El problema {:?} no se puede reparar automáticamente.", kind)));
+    }
+    let fixes: HashSet<IssueKind> = fixes.into_iter().collect();
+    let db = state.db().await?;
+    let before = verify(&db)?;
+    let pending = before.issues.iter().any(|i| fixes.contains(&i.kind));
+    if !pending {
+        return Ok(RepairSummary { backup_created: false, fixed: BTreeMap::new(), report: before });
+    }
+    backup::snapshot_before(&db, backup::REASON_REPAIR)?;
+
+    let mut fixed = BTreeMap::new();
+    if fixes.contains(&IssueKind::InvalidAmount) {
+        let count = quarantine_invalid_amounts(&db)?;
+        if count > 0 {
+            fixed.insert(IssueKind::InvalidAmount, count);
+        }
+    }
+
+    let conn = db.connection();
+    let known = Known::load(conn)?;
+    let (transactions, _) = db.scan_transactions()?;
+    let mut changes = Vec::new();
+    for transaction in transactions {
+        let Some((repaired, repairs)) = repair_transaction(&transaction, &fixes, &known) else {
+            continue;
+        };
+        match db.update_transaction(&repaired) {
+            Ok(_) => {
+                for (kind, count) in repairs {
+                    *fixed.entry(kind).or_insert(0) += count;
+                }
+                changes.push(Change::Update { before: transaction, after: repaired });
+            }
+            // Las de meses cerrados se quedan como están y siguen en el informe.
+            Err(AppError::PeriodClosed(message)) => warn!("Transaction {} not repaired: {}", transaction.id, message),
+            Err(e) => return Err(e),
+        }
+    }
+
+    if fixes.contains(&IssueKind::OrphanAttachment) {
+        let removed = attachments::cleanup_orphan_attachments(&db)?;
+        if removed > 0 {
+            fixed.insert(IssueKind::OrphanAttachment, removed);
+        }
+    }
+
+    if !changes.is_empty() {
+        audit::record_changes(db.connection(), "repair_data_command", &changes);
+        events::emit_transaction_changes(&app, &changes);
+        state.history.lock().unwrap().record(HistoryEntry::new("Reparar datos", changes));
+    }
+    if fixed.contains_key(&IssueKind::InvalidAmount) {
+        events::emit_data_reloaded(&app);
+    }
+    let report = verify(&db)?;
+    info!("Data repair applied: {:?}; {} issues remain.", fixed, report.issues.len());
+    Ok(RepairSummary { backup_created: true, fixed, report })
+}
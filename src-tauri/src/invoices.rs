@@ -0,0 +1,456 @@
+// src-tauri/src/invoices.rs
+
+use chrono::{Datelike, NaiveDate};
+use rusqlite::{params, Connection, OptionalExtension, Row};
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::PathBuf;
+use tauri::{AppHandle, State};
+use log::{debug, error, info};
+
+use crate::audit;
+use crate::categories;
+use crate::contacts;
+use crate::currencies;
+use crate::error::AppError;
+use crate::events::{self, ChangeAction};
+use crate::history::{Change, HistoryEntry};
+use crate::i18n;
+use crate::merge;
+use crate::money::{self, round_money};
+use crate::payments::PaymentMethod;
+use crate::pdf::{PdfWriter, MARGIN_MM};
+use crate::periods;
+use crate::settings::{self, Settings};
+use crate::storage::{self, db_error, SqliteStorage, TransactionRepository};
+use crate::{AppState, Transaction, TransactionType};
+
+/// Categoría con la que se registra el cobro de una factura, si existe.
+const INVOICE_INCOME_CATEGORY: &str = "Ventas";
+
+/// Estado de una factura. `Overdue` no se guarda: es una factura pendiente cuyo
+/// vencimiento ya pasó.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum InvoiceStatus {
+    Pending,
+    Paid,
+    Overdue,
+}
+
+/// Línea de una factura. Los importes no incluyen IVA.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct InvoiceLineItem {
+    pub description: String,
+    pub quantity: Decimal,
+    pub unit_price: Decimal,
+}
+
+impl InvoiceLineItem {
+    pub fn total(&self) -> Decimal {
+        round_money(self.quantity * self.unit_price)
+    }
+}
+
+/// Factura emitida a un cliente.
+#[derive(Debug, Clone, Serialize)]
+pub struct Invoice {
+    pub id: String,
+    /// Número correlativo por año: `2024-0007`.
+    pub number: String,
+    pub client_name: String,
+    pub client_tax_id: Option<String>,
+    pub issue_date: NaiveDate,
+    pub due_date: NaiveDate,
+    pub currency: String,
+    /// Tipo de IVA en porcentaje (p. ej. 21).
+    pub iva_rate: Decimal,
+    pub line_items: Vec<InvoiceLineItem>,
+    pub subtotal: Decimal,
+    pub iva_amount: Decimal,
+    pub total: Decimal,
+    pub status: InvoiceStatus,
+    pub paid_at: Option<u64>,
+    /// Transacción de ingreso creada al cobrarla.
+    pub transaction_id: Option<String>,
+    pub created_at: u64,
+    /// Cliente (`contacts::Contact`) al que se emitió.
+    pub contact_id: Option<String>,
+}
+
+/// Datos de `create_invoice_command`.
+#[derive(Debug, Clone, Deserialize)]
+pub struct NewInvoice {
+    /// Con `contact_id`, si el nombre o el NIF no se indican se toman del contacto.
+    #[serde(default)]
+    pub contact_id: Option<String>,
+    #[serde(default)]
+    pub client_name: String,
+    #[serde(default)]
+    pub client_tax_id: Option<String>,
+    /// Fecha de emisión; hoy si no se indica.
+    #[serde(default)]
+    pub issue_date: Option<NaiveDate>,
+    pub due_date: NaiveDate,
+    #[serde(default)]
+    pub currency: Option<String>,
+    pub iva_rate: Decimal,
+    pub line_items: Vec<InvoiceLineItem>,
+}
+
+const INVOICE_COLUMNS: &str = "id, number, client_name, client_tax_id, issue_date, due_date, currency, \
+     iva_rate, line_items, status, paid_at, transaction_id, created_at, contact_id";
+
+fn row_to_invoice(row: &Row) -> rusqlite::Result<Invoice> {
+    let line_items: Vec<InvoiceLineItem> = storage::json_column(row, 8)?;
+    let iva_rate = storage::decimal_column(row, 7)?;
+    let due_date = storage::date_column(row, 5)?;
+    let subtotal: Decimal = line_items.iter().map(InvoiceLineItem::total).sum();
+    let iva_amount = round_money(subtotal * iva_rate / Decimal::ONE_HUNDRED);
+    let status = match row.get::<_, String>(9)?.as_str() {
+        "paid" => InvoiceStatus::Paid,
+        _ if due_date < periods::today() => InvoiceStatus::Overdue,
+        _ => InvoiceStatus::Pending,
+    };
+    Ok(Invoice {
+        id: row.get(0)?,
+        number: row.get(1)?,
+        client_name: row.get(2)?,
+        client_tax_id: row.get(3)?,
+        issue_date: storage::date_column(row, 4)?,
+        due_date,
+        currency: row.get(6)?,
+        iva_rate,
+        line_items,
+        subtotal,
+        iva_amount,
+        total: subtotal + iva_amount,
+        status,
+        paid_at: row.get::<_, Option<i64>>(10)?.map(|t| t as u64),
+        transaction_id: row.get(11)?,
+        created_at: row.get::<_, i64>(12)? as u64,
+        contact_id: row.get(13)?,
+    })
+}
+
+fn get_invoice(conn: &Connection, id: &str) -> Result<Option<Invoice>, AppError> {
+    conn.query_row(
+        &format!("SELECT {} FROM invoices WHERE id = ?1", INVOICE_COLUMNS),
+        params![id],
+        row_to_invoice,
+    )
+    .optional()
+    .map_err(db_error)
+}
+
+fn require_invoice(conn: &Connection, id: &str) -> Result<Invoice, AppError> {
+    get_invoice(conn, id)?.ok_or_else(|| {
+        error!("Invoice {} not found.", id);
+        AppError::NotFound(format!("Factura con ID {} no encontrada.", id))
+    })
+}
+
+/// Siguiente número correlativo del año de `issue_date`.
+fn next_invoice_number(conn: &Connection, issue_date: NaiveDate) -> Result<String, AppError> {
+    let prefix = format!("{}-", issue_date.year());
+    let last: Option<String> = conn
+        .query_row(
+            "SELECT number FROM invoices WHERE number LIKE ?1 ORDER BY number DESC LIMIT 1",
+            params![format!("{}%", prefix)],
+            |row| row.get(0),
+        )
+        .optional()
+        .map_err(db_error)?;
+    let next = last
+        .and_then(|n| n.strip_prefix(&prefix).and_then(|seq| seq.parse::<u32>().ok()))
+        .unwrap_or(0)
+        + 1;
+    Ok(format!("{}{:04}", prefix, next))
+}
+
+fn validate_new_invoice(invoice: &NewInvoice) -> Result<(), AppError> {
+    if invoice.client_name.trim().is_empty() {
+        return Err(AppError::invalid_field("client_name", "El nombre del cliente no puede estar vacío."));
+    }
+    if invoice.iva_rate < Decimal::ZERO || invoice.iva_rate > Decimal::ONE_HUNDRED {
+        return Err(AppError::invalid_field("iva_rate", "El tipo de IVA debe estar entre 0 y 100."));
+    }
+    if invoice.line_items.is_empty() {
+        return Err(AppError::invalid_field("line_items", "La factura debe tener al menos una línea."));
+    }
+    for item in invoice.line_items.iter() {
+        if item.description.trim().is_empty() {
+            return Err(AppError::invalid_field("line_items", "Todas las líneas necesitan una descripción."));
+        }
+        money::validate_amount("line_items", item.quantity, "La cantidad de cada línea debe ser positiva.")?;
+        money::validate_amount("line_items", item.unit_price, "El precio de cada línea debe ser positivo.")?;
+    }
+    Ok(())
+}
+
+// --- PDF ---
+
+/// Genera el PDF de la factura (A4), con las fechas e importes en el formato de `settings`.
+pub fn render_invoice_pdf(invoice: &Invoice, settings: &Settings, base_currency: &str) -> Result<Vec<u8>, AppError> {
+    let language = settings.language();
+    let title = i18n::format(language, "invoice.title", &[("number", &invoice.number)]);
+    let mut pdf = PdfWriter::new(&title, i18n::text(language, "invoice.layer"))?;
+
+    pdf.text(MARGIN_MM, &title, 18.0, true);
+    pdf.newline();
+    pdf.newline();
+    let issue_date = settings.format_date(invoice.issue_date);
+    pdf.text(MARGIN_MM, &i18n::format(language, "invoice.issue_date", &[("date", &issue_date)]), 10.0, false);
+    pdf.newline();
+    let due_date = settings.format_date(invoice.due_date);
+    pdf.text(MARGIN_MM, &i18n::format(language, "invoice.due_date", &[("date", &due_date)]), 10.0, false);
+    pdf.newline();
+    pdf.newline();
+    pdf.text(MARGIN_MM, i18n::text(language, "invoice.client"), 11.0, true);
+    pdf.newline();
+    pdf.text(MARGIN_MM, &invoice.client_name, 10.0, false);
+    pdf.newline();
+    if let Some(tax_id) = &invoice.client_tax_id {
+        pdf.text(MARGIN_MM, &i18n::format(language, "invoice.tax_id", &[("tax_id", tax_id)]), 10.0, false);
+        pdf.newline();
+    }
+    pdf.newline();
+
+    let columns = [MARGIN_MM, 120.0, 145.0, 170.0];
+    let headers = ["invoice.item", "invoice.quantity", "invoice.price", "invoice.amount"];
+    for (x, header) in columns.iter().zip(headers) {
+        pdf.text(*x, i18n::text(language, header), 10.0, true);
+    }
+    pdf.newline();
+    for item in invoice.line_items.iter() {
+        pdf.text(columns[0], &item.description, 10.0, false);
+        pdf.text(columns[1], &item.quantity.normalize().to_string(), 10.0, false);
+        pdf.text(columns[2], &settings.format_number(item.unit_price), 10.0, false);
+        pdf.text(columns[3], &settings.format_number(item.total()), 10.0, false);
+        pdf.newline();
+    }
+    pdf.newline();
+
+    let totals = [
+        (i18n::text(language, "invoice.subtotal").to_owned(), invoice.subtotal, false),
+        (i18n::format(language, "invoice.vat", &[("rate", &invoice.iva_rate.normalize())]), invoice.iva_amount, false),
+        (i18n::text(language, "invoice.total").to_owned(), invoice.total, true),
+    ];
+    for (label, amount, bold) in totals {
+        pdf.text(columns[1], &label, 10.0, bold);
+        pdf.text(columns[3], &settings.format_money(amount, &invoice.currency, base_currency), 10.0, bold);
+        pdf.newline();
+    }
+
+    pdf.finish()
+}
+
+/// Registra el cobro: crea la transacción de ingreso y marca la factura como pagada.
+fn post_payment(
+    db: &SqliteStorage,
+    invoice: &Invoice,
+    store_name: &str,
+    payment_method: PaymentMethod,
+) -> Result<Transaction, AppError> {
+    let (category, subcategory) = categories::resolve_transaction_category(
+        db.connection(),
+        Some(INVOICE_INCOME_CATEGORY.to_string()),
+        None,
+    )
+    .unwrap_or((None, None));
+    let now = periods::now_timestamp();
+    let transaction = Transaction {
+        id: uuid::Uuid::new_v4().to_string(),
+        transaction_type: TransactionType::Ingreso,
+        amount: invoice.total,
+        description: format!("Factura {} - {}", invoice.number, invoice.client_name),
+        store_name: store_name.to_owned(),
+        timestamp: now,
+        category,
+        subcategory,
+        currency: invoice.currency.clone(),
+        receipt_paths: Vec::new(),
+        deleted_at: None,
+        tax_rate: Some(invoice.iva_rate),
+        tax_amount: Some(invoice.iva_amount),
+        tags: Vec::new(),
+        external_id: None,
+        transaction_date: periods::local_date(now),
+        created_at: now,
+        updated_at: now,
+        notes: String::new(),
+        custom_fields: HashMap::new(),
+        payment_method,
+        contact_id: invoice.contact_id.clone(),
+        transfer_store: None,
+        account_id: None,
+        transfer_account_id: None,
+        user_id: None,
+        revision: merge::initial_revision(),
+        device_id: String::new(),
+        sequence: 0,
+    };
+
+    let tx = db.connection().unchecked_transaction().map_err(db_error)?;
+    db.insert_transaction(&transaction)?;
+    tx.execute(
+        "UPDATE invoices SET status = 'paid', paid_at = ?2, transaction_id = ?3 WHERE id = ?1",
+        params![invoice.id, now as i64, transaction.id],
+    )
+    .map_err(db_error)?;
+    tx.commit().map_err(db_error)?;
+    Ok(transaction)
+}
+
+// --- Comandos Tauri ---
+
+/// Comando para crear una factura pendiente de cobro. El número se asigna
+/// automáticamente de forma correlativa dentro del año de emisión.
+#[tauri::command]
+pub async fn create_invoice_command(
+    state: State<'_, AppState>,
+    app: AppHandle,
+    mut invoice: NewInvoice,
+) -> Result<Invoice, AppError> {
+    debug!("Received create_invoice_command: {:?}", invoice);
+    let db = state.db().await?;
+    invoice.contact_id = contacts::resolve_contact_id(db.connection(), invoice.contact_id)?;
+    if let Some(contact_id) = &invoice.contact_id {
+        let contact = contacts::require_contact(db.connection(), "contact_id", contact_id)?;
+        if invoice.client_name.trim().is_empty() {
+            invoice.client_name = contact.name;
+        }
+        if invoice.client_tax_id.as_deref().map_or(true, |t| t.trim().is_empty()) {
+            invoice.client_tax_id = contact.nif;
+        }
+    }
+    validate_new_invoice(&invoice)?;
+    let issue_date = invoice.issue_date.unwrap_or_else(periods::today);
+    if invoice.due_date < issue_date {
+        return Err(AppError::invalid_field("due_date", "El vencimiento no puede ser anterior a la fecha de emisión."));
+    }
+
+    let currency = match invoice.currency {
+        Some(code) => currencies::normalize_currency_code(&code)?,
+        None => currencies::get_base_currency(&db)?,
+    };
+    let id = uuid::Uuid::new_v4().to_string();
+    let number = next_invoice_number(db.connection(), issue_date)?;
+    let line_items: Vec<InvoiceLineItem> = invoice
+        .line_items
+        .into_iter()
+        .map(|item| InvoiceLineItem { description: item.description.trim().to_owned(), ..item })
+        .collect();
+    db.connection()
+        .execute(
+            &format!(
+                "INSERT INTO invoices ({}) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, 'pending', NULL, NULL, ?10, ?11)",
+                INVOICE_COLUMNS
+            ),
+            params![
+                id,
+                number,
+                invoice.client_name.trim(),
+                invoice.client_tax_id.map(|t| t.trim().to_owned()).filter(|t| !t.is_empty()),
+                issue_date.format(storage::DATE_FORMAT).to_string(),
+                invoice.due_date.format(storage::DATE_FORMAT).to_string(),
+                currency,
+                invoice.iva_rate.to_string(),
+                storage::to_json_column(&line_items),
+                periods::now_timestamp() as i64,
+                invoice.contact_id,
+            ],
+        )
+        .map_err(db_error)?;
+
+    let created = require_invoice(db.connection(), &id)?;
+    audit::record(db.connection(), "create_invoice_command", Some(&id), None, audit::snapshot(&created));
+    events::emit_entity(&app, events::INVOICE_CHANGED_EVENT, ChangeAction::Created, &id);
+    info!("Invoice {} created for {}.", created.number, created.client_name);
+    Ok(created)
+}
+
+/// Facturas de la más reciente a la más antigua, opcionalmente solo las de un estado.
+pub fn list_invoices(conn: &Connection, status: Option<InvoiceStatus>) -> Result<Vec<Invoice>, AppError> {
+    let mut stmt = conn
+        .prepare(&format!("SELECT {} FROM invoices ORDER BY issue_date DESC, number DESC", INVOICE_COLUMNS))
+        .map_err(db_error)?;
+    let invoices = stmt
+        .query_map([], row_to_invoice)
+        .map_err(db_error)?
+        .collect::<rusqlite::Result<Vec<_>>>()
+        .map_err(db_error)?;
+    Ok(invoices.into_iter().filter(|i| status.map_or(true, |s| i.status == s)).collect())
+}
+
+/// Comando para listar las facturas, de la más reciente a la más antigua,
+/// opcionalmente solo las de un estado.
+#[tauri::command]
+pub async fn list_invoices_command(
+    state: State<'_, AppState>,
+    status: Option<InvoiceStatus>,
+) -> Result<Vec<Invoice>, AppError> {
+    debug!("Received list_invoices_command: {:?}", status);
+    list_invoices(state.db().await?.connection(), status)
+}
+
+/// Comando para marcar una factura como cobrada. Registra un ingreso por el total
+/// (IVA incluido) en `store_name`, con la categoría "Ventas" si existe. Sin
+/// `payment_method` se considera cobrada por transferencia.
+#[tauri::command]
+pub async fn mark_invoice_paid_command(
+    state: State<'_, AppState>,
+    app: AppHandle,
+    id: String,
+    store_name: String,
+    payment_method: Option<PaymentMethod>,
+) -> Result<Invoice, AppError> {
+    debug!("Received mark_invoice_paid_command: {} (store '{}')", id, store_name);
+    let store_name = store_name.trim();
+    if store_name.is_empty() {
+        return Err(AppError::invalid_field("store_name", "El nombre de la tienda no puede estar vacío."));
+    }
+
+    let db = state.db().await?;
+    let invoice = require_invoice(db.connection(), &id)?;
+    if invoice.status == InvoiceStatus::Paid {
+        return Err(AppError::Conflict(format!("La factura {} ya está cobrada.", invoice.number)));
+    }
+    let transaction = post_payment(&db, &invoice, store_name, payment_method.unwrap_or(PaymentMethod::Transferencia))?;
+    let paid = require_invoice(db.connection(), &id)?;
+
+    let changes = vec![Change::Insert(transaction)];
+    audit::record_changes(db.connection(), "mark_invoice_paid_command", &changes);
+    events::emit_transaction_changes(&app, &changes);
+    audit::record(
+        db.connection(),
+        "mark_invoice_paid_command",
+        Some(&id),
+        audit::snapshot(&invoice),
+        audit::snapshot(&paid),
+    );
+    state.history.lock().unwrap().record(HistoryEntry::new("Cobrar factura", changes));
+    events::emit_entity(&app, events::INVOICE_CHANGED_EVENT, ChangeAction::Updated, &id);
+    info!("Invoice {} marked as paid.", paid.number);
+    Ok(paid)
+}
+
+/// Comando para guardar el PDF de una factura en `path`. Devuelve la ruta escrita.
+#[tauri::command]
+pub async fn export_invoice_pdf_command(
+    state: State<'_, AppState>,
+    id: String,
+    path: String,
+) -> Result<String, AppError> {
+    debug!("Received export_invoice_pdf_command: {} -> {}", id, path);
+    let (invoice, settings, base_currency) = {
+        let db = state.db().await?;
+        (require_invoice(db.connection(), &id)?, settings::load_settings(&db), currencies::get_base_currency(&db)?)
+    };
+    let bytes = render_invoice_pdf(&invoice, &settings, &base_currency)?;
+    let path = PathBuf::from(path);
+    storage::write_atomic(&path, &bytes)?;
+    info!("Invoice {} exported to {}", invoice.number, path.display());
+    Ok(path.display().to_string())
+}
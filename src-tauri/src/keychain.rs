@@ -0,0 +1,53 @@
+// src-tauri/src/keychain.rs
+
+use keyring::Entry;
+use log::{debug, error};
+
+use crate::error::AppError;
+
+/// Servicio bajo el que se guardan los secretos en el llavero del sistema
+/// (Administrador de credenciales de Windows, Llavero de macOS o Secret Service).
+const KEYCHAIN_SERVICE: &str = "com.tuempresa.contabilidad";
+
+fn entry(name: &str) -> Result<Entry, AppError> {
+    Entry::new(KEYCHAIN_SERVICE, name).map_err(|e| {
+        error!("Could not access keychain entry '{}': {}", name, e);
+        AppError::Io(format!("No se pudo acceder al llavero del sistema: {}", e))
+    })
+}
+
+/// Lee un secreto del llavero. Devuelve `None` si no está guardado.
+pub fn get_secret(name: &str) -> Result<Option<String>, AppError> {
+    match entry(name)?.get_password() {
+        Ok(secret) => Ok(Some(secret)),
+        Err(keyring::Error::NoEntry) => Ok(None),
+        Err(e) => {
+            error!("Could not read keychain entry '{}': {}", name, e);
+            Err(AppError::Io(format!("No se pudo leer del llavero del sistema: {}", e)))
+        }
+    }
+}
+
+/// Guarda (o reemplaza) un secreto en el llavero.
+pub fn set_secret(name: &str, secret: &str) -> Result<(), AppError> {
+    entry(name)?.set_password(secret).map_err(|e| {
+        error!("Could not write keychain entry '{}': {}", name, e);
+        AppError::Io(format!("No se pudo guardar en el llavero del sistema: {}", e))
+    })?;
+    debug!("Secret '{}' saved to keychain.", name);
+    Ok(())
+}
+
+/// Borra un secreto del llavero. No falla si no existía.
+pub fn delete_secret(name: &str) -> Result<(), AppError> {
+    match entry(name)?.delete_credential() {
+        Ok(()) | Err(keyring::Error::NoEntry) => {
+            debug!("Secret '{}' removed from keychain.", name);
+            Ok(())
+        }
+        Err(e) => {
+            error!("Could not delete keychain entry '{}': {}", name, e);
+            Err(AppError::Io(format!("No se pudo borrar del llavero del sistema: {}", e)))
+        }
+    }
+}
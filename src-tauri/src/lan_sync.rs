@@ -0,0 +1,881 @@
+// src-tauri/src/lan_sync.rs
+
+use aes_gcm::aead::rand_core::RngCore;
+use aes_gcm::aead::OsRng;
+use base64::Engine;
+use mdns_sd::{ServiceDaemon, ServiceEvent, ServiceInfo};
+use rusqlite::{params, Connection, OptionalExtension, Row};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::net::SocketAddr;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use tauri::{AppHandle, Manager, State};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+use log::{debug, error, info, warn};
+
+use crate::audit;
+use crate::encryption::{self, DataKey};
+use crate::error::AppError;
+use crate::events;
+use crate::keychain;
+use crate::merge::{self, MergeSummary};
+use crate::periods;
+use crate::settings;
+use crate::storage::{db_error, SqliteStorage, TransactionRepository};
+use crate::sync;
+use crate::{AppState, Transaction};
+
+/// Tipo de servicio mDNS con el que se anuncian los equipos.
+const SERVICE_TYPE: &str = "_contabilidad-ia._tcp.local.";
+/// Versión del protocolo de intercambio.
+const PROTOCOL_VERSION: u32 = 1;
+/// Tamaño máximo de un mensaje, para no reservar memoria sin límite.
+const MAX_FRAME_LEN: usize = 64 * 1024 * 1024;
+const IO_TIMEOUT: Duration = Duration::from_secs(30);
+/// Tiempo que se escuchan los anuncios mDNS al buscar equipos.
+const DISCOVERY_TIMEOUT: Duration = Duration::from_secs(3);
+/// Validez del código de emparejamiento.
+const PAIRING_CODE_TTL: Duration = Duration::from_secs(5 * 60);
+const PAIRING_CODE_LEN: usize = 8;
+/// Sin caracteres que se confundan al dictarlos (0/O, 1/I/L).
+const PAIRING_CODE_ALPHABET: &[u8] = b"ABCDEFGHJKMNPQRSTUVWXYZ23456789";
+/// Diferencia máxima entre relojes aceptada en un mensaje, contra repeticiones.
+const MAX_CLOCK_SKEW_SECS: u64 = 10 * 60;
+/// Cada cuánto se revisa si el servicio debe estar activo.
+const SERVICE_TICK: Duration = Duration::from_secs(60);
+/// Cada cuánto se sincroniza automáticamente con los equipos emparejados visibles.
+const AUTO_SYNC_INTERVAL: Duration = Duration::from_secs(5 * 60);
+const DEFAULT_LOG_LIMIT: u32 = 100;
+
+/// Evento emitido al terminar un intercambio. Carga: `LanSyncLogEntry`.
+pub const LAN_SYNC_EVENT: &str = "lan-sync://completed";
+
+/// Servicio activo: anuncio mDNS y tarea que acepta conexiones.
+struct Service {
+    daemon: ServiceDaemon,
+    listener: tauri::async_runtime::JoinHandle<()>,
+    port: u16,
+}
+
+/// Código de emparejamiento a la espera de que otro equipo lo use.
+struct PendingPairing {
+    code: String,
+    expires_at: Instant,
+}
+
+static SERVICE: Mutex<Option<Service>> = Mutex::new(None);
+static PENDING_PAIRING: Mutex<Option<PendingPairing>> = Mutex::new(None);
+/// `true` mientras este equipo inicia intercambios, para no solaparlos.
+static SYNCING: AtomicBool = AtomicBool::new(false);
+
+/// Equipo de la red que anuncia el servicio.
+#[derive(Debug, Clone, Serialize)]
+pub struct DiscoveredDevice {
+    pub device_id: String,
+    pub name: String,
+    /// `ip:puerto` con el que conectar.
+    pub address: String,
+    pub paired: bool,
+}
+
+/// Equipo emparejado. La clave compartida no sale del llavero.
+#[derive(Debug, Clone, Serialize)]
+pub struct PairedDevice {
+    pub device_id: String,
+    pub name: String,
+    /// Última dirección conocida.
+    pub address: String,
+    pub paired_at: u64,
+    /// Último cambio recibido del equipo, en su reloj: el siguiente intercambio solo
+    /// pide lo modificado desde entonces.
+    #[serde(skip)]
+    received_until: u64,
+    pub last_sync_at: Option<u64>,
+}
+
+/// Código que hay que introducir en el otro equipo con `pair_device_command`.
+#[derive(Debug, Clone, Serialize)]
+pub struct PairingCode {
+    pub code: String,
+    pub expires_at: u64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SyncDirection {
+    /// Lo inició este equipo.
+    Outgoing,
+    /// Lo inició el otro equipo.
+    Incoming,
+    Pairing,
+}
+
+impl SyncDirection {
+    fn as_str(self) -> &'static str {
+        match self {
+            SyncDirection::Outgoing => "outgoing",
+            SyncDirection::Incoming => "incoming",
+            SyncDirection::Pairing => "pairing",
+        }
+    }
+
+    fn parse(s: &str) -> SyncDirection {
+        match s {
+            "incoming" => SyncDirection::Incoming,
+            "pairing" => SyncDirection::Pairing,
+            _ => SyncDirection::Outgoing,
+        }
+    }
+}
+
+/// Entrada del registro de intercambios.
+#[derive(Debug, Clone, Serialize)]
+pub struct LanSyncLogEntry {
+    pub id: i64,
+    pub timestamp: u64,
+    pub device_id: String,
+    pub device_name: String,
+    pub direction: SyncDirection,
+    /// Transacciones enviadas y aplicadas al recibirlas.
+    pub sent: usize,
+    pub received: usize,
+    /// Transacciones que cambiaron en ambos equipos (ver `merge::resolve_conflicts_command`).
+    pub conflicts: usize,
+    pub error: Option<String>,
+}
+
+/// Recuento de un intercambio.
+#[derive(Debug, Default, Clone, Copy)]
+struct ExchangeCounts {
+    sent: usize,
+    received: usize,
+    conflicts: usize,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+enum Purpose {
+    Pair,
+    Sync,
+}
+
+/// Primer mensaje de cada conexión, sin cifrar: dice quién conecta y para qué, y así
+/// el otro equipo sabe con qué clave descifrar el resto.
+#[derive(Debug, Serialize, Deserialize)]
+struct Hello {
+    protocol: u32,
+    device_id: String,
+    device_name: String,
+    purpose: Purpose,
+}
+
+/// Mensajes cifrados del protocolo.
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+enum Message {
+    PairRequest { sent_at: u64 },
+    PairAccepted { device_id: String, device_name: String, key: String },
+    /// Pide los cambios desde `since`.
+    SyncRequest { since: u64, sent_at: u64 },
+    /// Cambios pedidos y desde cuándo quiere recibir los del otro equipo.
+    Delta { changes: Vec<Transaction>, since: u64 },
+    Push { changes: Vec<Transaction> },
+    Ack { applied: usize },
+}
+
+/// Conexión cifrada con la clave compartida o con el código de emparejamiento. La
+/// clave se deriva una vez por conexión: la genera quien envía primero y el otro la
+/// obtiene al descifrar el primer mensaje.
+struct Channel {
+    stream: TcpStream,
+    secret: String,
+    key: Option<DataKey>,
+}
+
+impl Channel {
+    fn new(stream: TcpStream, secret: String) -> Self {
+        Channel { stream, secret, key: None }
+    }
+
+    async fn send(&mut self, message: &Message) -> Result<(), AppError> {
+        let json = serde_json::to_vec(message)
+            .map_err(|e| AppError::Internal(format!("Error al preparar el mensaje: {}", e)))?;
+        if self.key.is_none() {
+            self.key = Some(DataKey::generate(&self.secret)?);
+        }
+        let data = self.key.as_ref().expect("clave recién generada").encrypt(&json)?;
+        write_frame(&mut self.stream, &data).await
+    }
+
+    async fn recv(&mut self) -> Result<Message, AppError> {
+        let data = read_frame(&mut self.stream).await?;
+        let json = match &self.key {
+            Some(key) => key.decrypt(&data)?,
+            None => {
+                let (key, json) = encryption::unlock(&self.secret, &data)?;
+                self.key = Some(key);
+                json
+            }
+        };
+        serde_json::from_slice(&json).map_err(|e| AppError::Network(format!("Mensaje no válido del otro equipo: {}", e)))
+    }
+}
+
+fn io_error(e: impl std::fmt::Display) -> AppError {
+    error!("LAN sync connection error: {}", e);
+    AppError::Network(format!("Error en la conexión con el otro equipo: {}", e))
+}
+
+async fn write_frame(stream: &mut TcpStream, data: &[u8]) -> Result<(), AppError> {
+    let write = async {
+        stream.write_all(&(data.len() as u32).to_be_bytes()).await?;
+        stream.write_all(data).await?;
+        stream.flush().await
+    };
+    tokio::time::timeout(IO_TIMEOUT, write).await.map_err(io_error)?.map_err(io_error)
+}
+
+async fn read_frame(stream: &mut TcpStream) -> Result<Vec<u8>, AppError> {
+    let read = async {
+        let mut len = [0u8; 4];
+        stream.read_exact(&mut len).await?;
+        let len = u32::from_be_bytes(len) as usize;
+        if len > MAX_FRAME_LEN {
+            return Err(std::io::Error::new(std::io::ErrorKind::InvalidData, "mensaje demasiado grande"));
+        }
+        let mut data = vec![0u8; len];
+        stream.read_exact(&mut data).await?;
+        Ok(data)
+    };
+    tokio::time::timeout(IO_TIMEOUT, read).await.map_err(io_error)?.map_err(io_error)
+}
+
+async fn send_hello(stream: &mut TcpStream, hello: &Hello) -> Result<(), AppError> {
+    let json = serde_json::to_vec(hello).map_err(|e| AppError::Internal(format!("Error al preparar el saludo: {}", e)))?;
+    write_frame(stream, &json).await
+}
+
+async fn read_hello(stream: &mut TcpStream) -> Result<Hello, AppError> {
+    let hello: Hello = serde_json::from_slice(&read_frame(stream).await?)
+        .map_err(|e| AppError::Network(format!("Saludo no válido: {}", e)))?;
+    if hello.protocol != PROTOCOL_VERSION {
+        return Err(AppError::Network(format!("El otro equipo usa otra versión del protocolo ({}).", hello.protocol)));
+    }
+    Ok(hello)
+}
+
+async fn connect(address: &str) -> Result<TcpStream, AppError> {
+    tokio::time::timeout(IO_TIMEOUT, TcpStream::connect(address))
+        .await
+        .map_err(io_error)?
+        .map_err(|e| AppError::Network(format!("No se pudo conectar con {}: {}", address, e)))
+}
+
+fn check_clock(sent_at: u64) -> Result<(), AppError> {
+    if periods::now_timestamp().abs_diff(sent_at) > MAX_CLOCK_SKEW_SECS {
+        return Err(AppError::Network("Los relojes de los equipos no coinciden; revisa la hora de ambos.".to_string()));
+    }
+    Ok(())
+}
+
+/// Nombre con el que este equipo aparece en los demás.
+fn device_name() -> String {
+    std::env::var("COMPUTERNAME")
+        .or_else(|_| std::env::var("HOSTNAME"))
+        .ok()
+        .filter(|n| !n.trim().is_empty())
+        .unwrap_or_else(audit::current_actor)
+}
+
+fn key_secret_name(device_id: &str) -> String {
+    format!("lan_sync:{}", device_id)
+}
+
+fn generate_pairing_code() -> String {
+    (0..PAIRING_CODE_LEN)
+        .map(|_| PAIRING_CODE_ALPHABET[(OsRng.next_u32() as usize) % PAIRING_CODE_ALPHABET.len()] as char)
+        .collect()
+}
+
+fn row_to_paired_device(row: &Row) -> rusqlite::Result<PairedDevice> {
+    Ok(PairedDevice {
+        device_id: row.get(0)?,
+        name: row.get(1)?,
+        address: row.get(2)?,
+        paired_at: row.get::<_, i64>(3)? as u64,
+        received_until: row.get::<_, i64>(4)? as u64,
+        last_sync_at: row.get::<_, Option<i64>>(5)?.map(|t| t as u64),
+    })
+}
+
+const PAIRED_DEVICE_COLUMNS: &str = "device_id, name, address, paired_at, received_until, last_sync_at";
+
+pub fn list_paired_devices(conn: &Connection) -> Result<Vec<PairedDevice>, AppError> {
+    let mut stmt = conn
+        .prepare(&format!("SELECT {} FROM paired_devices ORDER BY name", PAIRED_DEVICE_COLUMNS))
+        .map_err(db_error)?;
+    let rows = stmt.query_map([], row_to_paired_device).map_err(db_error)?;
+    rows.collect::<rusqlite::Result<Vec<_>>>().map_err(db_error)
+}
+
+fn get_paired_device(conn: &Connection, device_id: &str) -> Result<Option<PairedDevice>, AppError> {
+    conn.query_row(
+        &format!("SELECT {} FROM paired_devices WHERE device_id = ?1", PAIRED_DEVICE_COLUMNS),
+        params![device_id],
+        row_to_paired_device,
+    )
+    .optional()
+    .map_err(db_error)
+}
+
+/// Guarda (o renueva) el emparejamiento y su clave compartida.
+fn save_paired_device(conn: &Connection, device: &PairedDevice, key: &str) -> Result<(), AppError> {
+    keychain::set_secret(&key_secret_name(&device.device_id), key)?;
+    conn.execute(
+        "INSERT INTO paired_devices (device_id, name, address, paired_at, received_until, last_sync_at)
+         VALUES (?1, ?2, ?3, ?4, 0, NULL)
+         ON CONFLICT(device_id) DO UPDATE SET name = excluded.name, address = excluded.address,
+             paired_at = excluded.paired_at",
+        params![device.device_id, device.name, device.address, device.paired_at as i64],
+    )
+    .map_err(db_error)?;
+    Ok(())
+}
+
+fn row_to_log_entry(row: &Row) -> rusqlite::Result<LanSyncLogEntry> {
+    Ok(LanSyncLogEntry {
+        id: row.get(0)?,
+        timestamp: row.get::<_, i64>(1)? as u64,
+        device_id: row.get(2)?,
+        device_name: row.get(3)?,
+        direction: SyncDirection::parse(&row.get::<_, String>(4)?),
+        sent: row.get::<_, i64>(5)? as usize,
+        received: row.get::<_, i64>(6)? as usize,
+        conflicts: row.get::<_, i64>(7)? as usize,
+        error: row.get(8)?,
+    })
+}
+
+/// Apunta un intercambio en el registro y avisa al frontend.
+fn log_exchange(
+    app: &AppHandle,
+    conn: &Connection,
+    device_id: &str,
+    device_name: &str,
+    direction: SyncDirection,
+    result: &Result<ExchangeCounts, AppError>,
+) -> Result<LanSyncLogEntry, AppError> {
+    let (counts, error) = match result {
+        Ok(counts) => (*counts, None),
+        Err(e) => (ExchangeCounts::default(), Some(e.to_string())),
+    };
+    let timestamp = periods::now_timestamp();
+    conn.execute(
+        "INSERT INTO lan_sync_log (timestamp, device_id, device_name, direction, sent, received, conflicts, error)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+        params![
+            timestamp as i64,
+            device_id,
+            device_name,
+            direction.as_str(),
+            counts.sent as i64,
+            counts.received as i64,
+            counts.conflicts as i64,
+            error
+        ],
+    )
+    .map_err(db_error)?;
+    let entry = LanSyncLogEntry {
+        id: conn.last_insert_rowid(),
+        timestamp,
+        device_id: device_id.to_string(),
+        device_name: device_name.to_string(),
+        direction,
+        sent: counts.sent,
+        received: counts.received,
+        conflicts: counts.conflicts,
+        error,
+    };
+    events::emit(app, LAN_SYNC_EVENT, entry.clone());
+    Ok(entry)
+}
+
+/// Transacciones (también las de la papelera) que cambiaron desde `since`.
+fn changes_since(db: &SqliteStorage, since: u64) -> Result<Vec<Transaction>, AppError> {
+    let mut transactions = db.list_transactions()?;
+    transactions.extend(db.list_deleted_transactions()?);
+    transactions.retain(|t| merge::last_change(t) >= since);
+    Ok(transactions)
+}
+
+/// Combina los cambios recibidos de `device` con `merge::apply_incoming`. Los que
+/// cambiaron aquí y allí desde el último intercambio quedan como conflictos.
+fn apply_changes(
+    state: &AppState,
+    db: &SqliteStorage,
+    app: &AppHandle,
+    device: &PairedDevice,
+    changes: &[Transaction],
+) -> Result<MergeSummary, AppError> {
+    let own_device = sync::device_id(db)?;
+    let source = format!("lan:{}", device.device_id);
+    let summary = merge::apply_incoming(db, &source, &own_device, device.last_sync_at.unwrap_or(0), changes)?;
+    if summary.applied > 0 || summary.conflicts > 0 {
+        state.history.lock().unwrap().clear();
+        audit::record(
+            db.connection(),
+            "lan_sync",
+            None,
+            None,
+            Some(serde_json::json!({
+                "device_id": device.device_id,
+                "applied": summary.applied,
+                "conflicts": summary.conflicts,
+            })),
+        );
+        events::emit_data_reloaded(app);
+    }
+    Ok(summary)
+}
+
+fn update_after_sync(conn: &Connection, device_id: &str, address: Option<&str>, changes: &[Transaction]) -> Result<(), AppError> {
+    let received_until = changes.iter().map(merge::last_change).max().unwrap_or(0) as i64;
+    conn.execute(
+        "UPDATE paired_devices SET received_until = MAX(received_until, ?2), last_sync_at = ?3,
+             address = COALESCE(?4, address)
+         WHERE device_id = ?1",
+        params![device_id, received_until, periods::now_timestamp() as i64, address],
+    )
+    .map_err(db_error)?;
+    Ok(())
+}
+
+// --- Servidor ---
+
+/// Atiende una conexión entrante: un emparejamiento con el código pendiente o un
+/// intercambio con un equipo ya emparejado.
+async fn handle_incoming(app: AppHandle, mut stream: TcpStream, peer: SocketAddr) -> Result<(), AppError> {
+    let hello = read_hello(&mut stream).await?;
+    debug!("LAN sync connection from {} ({:?}).", peer, hello.purpose);
+    let state = app.state::<AppState>();
+    match hello.purpose {
+        Purpose::Pair => {
+            // El código se gasta en el primer intento, acierte o no, para que no se
+            // pueda adivinar probando.
+            let code = match PENDING_PAIRING.lock().unwrap().take() {
+                Some(pending) if pending.expires_at > Instant::now() => pending.code,
+                _ => return Err(AppError::Conflict("No hay ningún emparejamiento pendiente.".to_string())),
+            };
+            let mut channel = Channel::new(stream, code);
+            let sent_at = match channel.recv().await? {
+                Message::PairRequest { sent_at } => sent_at,
+                other => return Err(AppError::Network(format!("Mensaje inesperado: {:?}", other))),
+            };
+            check_clock(sent_at)?;
+
+            let mut key = [0u8; 32];
+            OsRng.fill_bytes(&mut key);
+            let key = base64::engine::general_purpose::STANDARD.encode(key);
+            let db = state.db().await?;
+            let own_id = sync::device_id(&db)?;
+            let device = PairedDevice {
+                device_id: hello.device_id.clone(),
+                name: hello.device_name.clone(),
+                address: peer.ip().to_string(),
+                paired_at: periods::now_timestamp(),
+                received_until: 0,
+                last_sync_at: None,
+            };
+            save_paired_device(db.connection(), &device, &key)?;
+            log_exchange(&app, db.connection(), &device.device_id, &device.name, SyncDirection::Pairing, &Ok(ExchangeCounts::default()))?;
+            drop(db);
+            channel.send(&Message::PairAccepted { device_id: own_id, device_name: device_name(), key }).await?;
+            info!("Paired with device {} ({}).", device.name, device.device_id);
+            Ok(())
+        }
+        Purpose::Sync => {
+            let db = state.db().await?;
+            let device = get_paired_device(db.connection(), &hello.device_id)?
+                .ok_or_else(|| AppError::NotFound(format!("Equipo {} no emparejado.", hello.device_id)))?;
+            drop(db);
+            let key = keychain::get_secret(&key_secret_name(&device.device_id))?
+                .ok_or_else(|| AppError::NotFound(format!("Falta la clave del equipo {}.", device.name)))?;
+            let mut channel = Channel::new(stream, key);
+            let result = serve_sync(&app, &mut channel, &device).await;
+            let db = state.db().await?;
+            log_exchange(&app, db.connection(), &device.device_id, &device.name, SyncDirection::Incoming, &result)?;
+            result.map(|_| ())
+        }
+    }
+}
+
+/// Lado que recibe la conexión en un intercambio: envía lo que le piden, pide lo suyo
+/// y lo aplica.
+async fn serve_sync(app: &AppHandle, channel: &mut Channel, device: &PairedDevice) -> Result<ExchangeCounts, AppError> {
+    let state = app.state::<AppState>();
+    let since = match channel.recv().await? {
+        Message::SyncRequest { since, sent_at } => {
+            check_clock(sent_at)?;
+            since
+        }
+        other => return Err(AppError::Network(format!("Mensaje inesperado: {:?}", other))),
+    };
+    let changes = changes_since(&state.db().await?, since)?;
+    let sent = changes.len();
+    channel.send(&Message::Delta { changes, since: device.received_until }).await?;
+
+    let received = match channel.recv().await? {
+        Message::Push { changes } => changes,
+        other => return Err(AppError::Network(format!("Mensaje inesperado: {:?}", other))),
+    };
+    let db = state.db().await?;
+    let summary = apply_changes(&state, &db, app, device, &received)?;
+    update_after_sync(db.connection(), &device.device_id, None, &received)?;
+    drop(db);
+    channel.send(&Message::Ack { applied: summary.applied }).await?;
+    Ok(ExchangeCounts { sent, received: summary.applied, conflicts: summary.conflicts })
+}
+
+async fn accept_loop(app: AppHandle, listener: TcpListener) {
+    loop {
+        match listener.accept().await {
+            Ok((stream, peer)) => {
+                let app = app.clone();
+                tauri::async_runtime::spawn(async move {
+                    if let Err(e) = handle_incoming(app, stream, peer).await {
+                        warn!("LAN sync connection from {} failed: {}", peer, e);
+                    }
+                });
+            }
+            Err(e) => {
+                error!("LAN sync listener failed: {}", e);
+                tokio::time::sleep(Duration::from_secs(1)).await;
+            }
+        }
+    }
+}
+
+/// Empieza a escuchar en `port` y anuncia el servicio por mDNS.
+async fn start_service(app: &AppHandle, device_id: &str, port: u16) -> Result<Service, AppError> {
+    let listener = TcpListener::bind(("0.0.0.0", port))
+        .await
+        .map_err(|e| AppError::Io(format!("No se pudo escuchar en el puerto {}: {}", port, e)))?;
+    let daemon = ServiceDaemon::new().map_err(|e| AppError::Network(format!("No se pudo iniciar mDNS: {}", e)))?;
+    let name = device_name();
+    let properties = [("device_id", device_id), ("name", name.as_str())];
+    let info = ServiceInfo::new(SERVICE_TYPE, device_id, &format!("{}.local.", device_id), "", port, &properties[..])
+        .map_err(|e| AppError::Network(format!("No se pudo anunciar el servicio: {}", e)))?
+        .enable_addr_auto();
+    daemon.register(info).map_err(|e| AppError::Network(format!("No se pudo anunciar el servicio: {}", e)))?;
+    let listener = tauri::async_runtime::spawn(accept_loop(app.clone(), listener));
+    info!("LAN sync service listening on port {}.", port);
+    Ok(Service { daemon, listener, port })
+}
+
+fn stop_service() {
+    if let Some(service) = SERVICE.lock().unwrap().take() {
+        service.listener.abort();
+        if let Err(e) = service.daemon.shutdown() {
+            warn!("Failed to stop mDNS daemon: {}", e);
+        }
+        info!("LAN sync service stopped.");
+    }
+}
+
+/// Arranca, para o reinicia el servicio según los ajustes. Devuelve si está activo.
+async fn apply_settings(app: &AppHandle) -> Result<bool, AppError> {
+    let state = app.state::<AppState>();
+    if state.is_locked() {
+        stop_service();
+        return Ok(false);
+    }
+    let db = state.db().await?;
+    let settings = settings::load_settings(&db);
+    let device_id = sync::device_id(&db)?;
+    drop(db);
+
+    let running_port = SERVICE.lock().unwrap().as_ref().map(|s| s.port);
+    match (settings.lan_sync_enabled, running_port) {
+        (false, _) => {
+            stop_service();
+            Ok(false)
+        }
+        (true, Some(port)) if port == settings.lan_sync_port => Ok(true),
+        (true, _) => {
+            stop_service();
+            let service = start_service(app, &device_id, settings.lan_sync_port).await?;
+            *SERVICE.lock().unwrap() = Some(service);
+            Ok(true)
+        }
+    }
+}
+
+/// Busca equipos que anuncien el servicio en la red local, sin incluir este.
+async fn discover(own_id: String, paired: Vec<String>) -> Result<Vec<DiscoveredDevice>, AppError> {
+    let daemon = ServiceDaemon::new().map_err(|e| AppError::Network(format!("No se pudo iniciar mDNS: {}", e)))?;
+    let receiver = daemon.browse(SERVICE_TYPE).map_err(|e| AppError::Network(format!("No se pudo buscar equipos: {}", e)))?;
+    let devices = tauri::async_runtime::spawn_blocking(move || {
+        let deadline = Instant::now() + DISCOVERY_TIMEOUT;
+        let mut devices: HashMap<String, DiscoveredDevice> = HashMap::new();
+        while let Some(remaining) = deadline.checked_duration_since(Instant::now()) {
+            let Ok(event) = receiver.recv_timeout(remaining) else { break };
+            let ServiceEvent::ServiceResolved(info) = event else { continue };
+            let Some(device_id) = info.get_property_val_str("device_id").map(str::to_owned) else { continue };
+            let Some(ip) = info.get_addresses().iter().next().copied() else { continue };
+            if device_id == own_id {
+                continue;
+            }
+            devices.insert(
+                device_id.clone(),
+                DiscoveredDevice {
+                    name: info.get_property_val_str("name").unwrap_or(&device_id).to_string(),
+                    address: SocketAddr::new(ip.into(), info.get_port()).to_string(),
+                    paired: paired.contains(&device_id),
+                    device_id,
+                },
+            );
+        }
+        devices.into_values().collect::<Vec<_>>()
+    })
+    .await
+    .map_err(|e| AppError::Internal(format!("Error al buscar equipos: {}", e)))?;
+    if let Err(e) = daemon.shutdown() {
+        debug!("Failed to stop discovery daemon: {}", e);
+    }
+    Ok(devices)
+}
+
+async fn discover_from_app(app: &AppHandle) -> Result<Vec<DiscoveredDevice>, AppError> {
+    let state = app.state::<AppState>();
+    let db = state.db().await?;
+    let own_id = sync::device_id(&db)?;
+    let paired = list_paired_devices(db.connection())?.into_iter().map(|d| d.device_id).collect();
+    drop(db);
+    discover(own_id, paired).await
+}
+
+// --- Cliente ---
+
+/// Intercambia cambios con un equipo emparejado en `address`.
+async fn sync_with(app: &AppHandle, device: &PairedDevice, address: &str) -> Result<ExchangeCounts, AppError> {
+    let state = app.state::<AppState>();
+    let key = keychain::get_secret(&key_secret_name(&device.device_id))?
+        .ok_or_else(|| AppError::NotFound(format!("Falta la clave del equipo {}. Vuelve a emparejarlo.", device.name)))?;
+    let own_id = sync::device_id(&*state.db().await?)?;
+
+    let mut stream = connect(address).await?;
+    send_hello(&mut stream, &Hello { protocol: PROTOCOL_VERSION, device_id: own_id, device_name: device_name(), purpose: Purpose::Sync }).await?;
+    let mut channel = Channel::new(stream, key);
+    channel.send(&Message::SyncRequest { since: device.received_until, sent_at: periods::now_timestamp() }).await?;
+    let (received, their_since) = match channel.recv().await? {
+        Message::Delta { changes, since } => (changes, since),
+        other => return Err(AppError::Network(format!("Mensaje inesperado: {:?}", other))),
+    };
+
+    let db = state.db().await?;
+    let summary = apply_changes(&state, &db, app, device, &received)?;
+    update_after_sync(db.connection(), &device.device_id, Some(address), &received)?;
+    let changes = changes_since(&db, their_since)?;
+    drop(db);
+    let sent = changes.len();
+    channel.send(&Message::Push { changes }).await?;
+    match channel.recv().await? {
+        Message::Ack { applied: acknowledged } => debug!("{} accepted {} of {} changes.", device.name, acknowledged, sent),
+        other => return Err(AppError::Network(format!("Mensaje inesperado: {:?}", other))),
+    }
+    Ok(ExchangeCounts { sent, received: summary.applied, conflicts: summary.conflicts })
+}
+
+/// Sincroniza con los equipos emparejados indicados (o con todos), usando la
+/// dirección anunciada por mDNS si se encuentran y si no la última conocida.
+async fn sync_devices(app: &AppHandle, only: Option<&str>) -> Result<Vec<LanSyncLogEntry>, AppError> {
+    if SYNCING.swap(true, Ordering::SeqCst) {
+        return Err(AppError::Conflict("Ya hay una sincronización en red local en curso.".to_string()));
+    }
+    let result = sync_devices_inner(app, only).await;
+    SYNCING.store(false, Ordering::SeqCst);
+    result
+}
+
+async fn sync_devices_inner(app: &AppHandle, only: Option<&str>) -> Result<Vec<LanSyncLogEntry>, AppError> {
+    let state = app.state::<AppState>();
+    let devices: Vec<PairedDevice> = list_paired_devices(state.db().await?.connection())?
+        .into_iter()
+        .filter(|d| only.map_or(true, |id| d.device_id == id))
+        .collect();
+    if let Some(id) = only {
+        if devices.is_empty() {
+            return Err(AppError::NotFound(format!("Equipo {} no emparejado.", id)));
+        }
+    }
+    let discovered = match discover_from_app(app).await {
+        Ok(found) => found,
+        Err(e) => {
+            warn!("LAN discovery failed, using last known addresses: {}", e);
+            Vec::new()
+        }
+    };
+
+    let mut entries = Vec::new();
+    for device in devices {
+        let address = discovered
+            .iter()
+            .find(|d| d.device_id == device.device_id)
+            .map(|d| d.address.clone())
+            .unwrap_or_else(|| device.address.clone());
+        let result = sync_with(app, &device, &address).await;
+        match &result {
+            Ok(counts) => info!(
+                "LAN sync with {}: {} sent, {} received, {} conflicts.",
+                device.name, counts.sent, counts.received, counts.conflicts
+            ),
+            Err(e) => warn!("LAN sync with {} failed: {}", device.name, e),
+        }
+        let db = state.db().await?;
+        entries.push(log_exchange(app, db.connection(), &device.device_id, &device.name, SyncDirection::Outgoing, &result)?);
+    }
+    Ok(entries)
+}
+
+/// Mantiene el servicio de sincronización en red local de acuerdo con los ajustes:
+/// anuncio por mDNS y escucha en un puerto TCP. Cada cinco minutos intercambia con los
+/// equipos emparejados que estén en la red las transacciones modificadas desde el
+/// último intercambio, cifradas con la clave que se acordó al emparejarlos.
+pub fn spawn_service(app: AppHandle) {
+    tauri::async_runtime::spawn(async move {
+        let mut last_auto_sync = Instant::now();
+        loop {
+            match apply_settings(&app).await {
+                Ok(true) if last_auto_sync.elapsed() >= AUTO_SYNC_INTERVAL => {
+                    last_auto_sync = Instant::now();
+                    if let Err(e) = sync_devices(&app, None).await {
+                        debug!("Skipping automatic LAN sync: {}", e);
+                    }
+                }
+                Ok(_) => {}
+                Err(e) => warn!("No se pudo actualizar el servicio de sincronización local: {}", e),
+            }
+            tokio::time::sleep(SERVICE_TICK).await;
+        }
+    });
+}
+
+// --- Comandos Tauri ---
+
+/// Comando para buscar en la red local otros equipos con la sincronización activada.
+#[tauri::command]
+pub async fn discover_devices_command(app: AppHandle) -> Result<Vec<DiscoveredDevice>, AppError> {
+    debug!("Received discover_devices_command.");
+    discover_from_app(&app).await
+}
+
+/// Comando para generar en este equipo el código de un solo uso que hay que
+/// introducir en el otro con `pair_device_command`. Caduca a los cinco minutos y se
+/// gasta en el primer intento. Requiere tener activada la sincronización local.
+#[tauri::command]
+pub async fn start_pairing_command(app: AppHandle) -> Result<PairingCode, AppError> {
+    debug!("Received start_pairing_command.");
+    if !apply_settings(&app).await? {
+        return Err(AppError::validation("Activa la sincronización en red local en los ajustes."));
+    }
+    let code = generate_pairing_code();
+    *PENDING_PAIRING.lock().unwrap() =
+        Some(PendingPairing { code: code.clone(), expires_at: Instant::now() + PAIRING_CODE_TTL });
+    info!("LAN pairing code generated.");
+    Ok(PairingCode { code, expires_at: periods::now_timestamp() + PAIRING_CODE_TTL.as_secs() })
+}
+
+/// Comando para emparejar este equipo con el de `address` (ver
+/// `discover_devices_command`) usando el código que muestra el otro equipo.
+#[tauri::command]
+pub async fn pair_device_command(
+    state: State<'_, AppState>,
+    app: AppHandle,
+    address: String,
+    code: String,
+) -> Result<PairedDevice, AppError> {
+    info!("Received pair_device_command: {}", address);
+    let code = code.trim().to_uppercase();
+    if code.len() != PAIRING_CODE_LEN {
+        return Err(AppError::invalid_field("code", format!("El código tiene {} caracteres.", PAIRING_CODE_LEN)));
+    }
+    let own_id = sync::device_id(&*state.db().await?)?;
+
+    let mut stream = connect(&address).await?;
+    send_hello(&mut stream, &Hello { protocol: PROTOCOL_VERSION, device_id: own_id, device_name: device_name(), purpose: Purpose::Pair }).await?;
+    let mut channel = Channel::new(stream, code);
+    channel.send(&Message::PairRequest { sent_at: periods::now_timestamp() }).await?;
+    let (device_id, name, key) = match channel.recv().await {
+        Ok(Message::PairAccepted { device_id, device_name, key }) => (device_id, device_name, key),
+        Ok(other) => return Err(AppError::Network(format!("Mensaje inesperado: {:?}", other))),
+        Err(e) => {
+            warn!("Pairing with {} failed: {}", address, e);
+            return Err(AppError::invalid_field("code", "El otro equipo rechazó el código o ya había caducado."));
+        }
+    };
+
+    let device = PairedDevice {
+        device_id,
+        name,
+        address: address.clone(),
+        paired_at: periods::now_timestamp(),
+        received_until: 0,
+        last_sync_at: None,
+    };
+    let db = state.db().await?;
+    save_paired_device(db.connection(), &device, &key)?;
+    log_exchange(&app, db.connection(), &device.device_id, &device.name, SyncDirection::Pairing, &Ok(ExchangeCounts::default()))?;
+    info!("Paired with device {} ({}).", device.name, device.device_id);
+    Ok(device)
+}
+
+/// Comando para obtener los equipos emparejados.
+#[tauri::command]
+pub async fn list_paired_devices_command(state: State<'_, AppState>) -> Result<Vec<PairedDevice>, AppError> {
+    debug!("Received list_paired_devices_command.");
+    list_paired_devices(state.db().await?.connection())
+}
+
+/// Comando para olvidar un equipo emparejado y su clave.
+#[tauri::command]
+pub async fn unpair_device_command(state: State<'_, AppState>, device_id: String) -> Result<(), AppError> {
+    info!("Received unpair_device_command: {}", device_id);
+    let db = state.db().await?;
+    let removed = db
+        .connection()
+        .execute("DELETE FROM paired_devices WHERE device_id = ?1", params![device_id])
+        .map_err(db_error)?;
+    if removed == 0 {
+        return Err(AppError::NotFound(format!("Equipo {} no emparejado.", device_id)));
+    }
+    keychain::delete_secret(&key_secret_name(&device_id))
+}
+
+/// Comando para sincronizar ahora con un equipo emparejado o, sin `device_id`, con
+/// todos. Devuelve el resultado de cada intercambio.
+#[tauri::command]
+pub async fn lan_sync_now_command(app: AppHandle, device_id: Option<String>) -> Result<Vec<LanSyncLogEntry>, AppError> {
+    info!("Received lan_sync_now_command: {:?}", device_id);
+    sync_devices(&app, device_id.as_deref()).await
+}
+
+/// Comando para obtener el registro de intercambios, del más reciente al más antiguo.
+#[tauri::command]
+pub async fn get_lan_sync_log_command(state: State<'_, AppState>, limit: Option<u32>) -> Result<Vec<LanSyncLogEntry>, AppError> {
+    debug!("Received get_lan_sync_log_command.");
+    let db = state.db().await?;
+    let mut stmt = db
+        .connection()
+        .prepare(
+            "SELECT id, timestamp, device_id, device_name, direction, sent, received, conflicts, error
+             FROM lan_sync_log ORDER BY timestamp DESC, id DESC LIMIT ?1",
+        )
+        .map_err(db_error)?;
+    let rows = stmt
+        .query_map(params![limit.unwrap_or(DEFAULT_LOG_LIMIT) as i64], row_to_log_entry)
+        .map_err(db_error)?;
+    rows.collect::<rusqlite::Result<Vec<_>>>().map_err(db_error)
+}
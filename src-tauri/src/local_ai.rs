@@ -0,0 +1,235 @@
+// src-tauri/src/local_ai.rs
+
+use reqwest::{Client, Response};
+use serde_json::{json, Value};
+use std::time::Duration;
+use log::{debug, error, info};
+
+use crate::ai::{self, AiBackend, AiMessage, AiReply, AiRole, GenerationParams, ModelInfo, TokenUsage};
+use crate::error::AppError;
+
+/// Dirección por defecto del servidor local (la de Ollama).
+pub const DEFAULT_URL: &str = "http://localhost:11434";
+pub const DEFAULT_MODEL: &str = "llama3.1";
+
+/// Servidor de IA local con API compatible con OpenAI (`/v1/chat/completions`), como
+/// Ollama o el servidor de llama.cpp.
+#[derive(Debug, Clone)]
+pub struct LocalAiConfig {
+    /// Dirección base, sin `/v1` (p. ej. `http://localhost:11434`).
+    pub base_url: String,
+    pub model: String,
+    /// Tiempo máximo de una petición. En streaming, tiempo máximo sin recibir datos.
+    pub timeout: Duration,
+}
+
+/// Comprueba que `url` sea una dirección HTTP utilizable como base.
+pub fn validate_url(url: &str) -> Result<(), AppError> {
+    if !(url.starts_with("http://") || url.starts_with("https://")) || url.contains(char::is_whitespace) {
+        error!("Invalid local AI URL: '{}'", url);
+        return Err(AppError::invalid_field(
+            "local_ai_url",
+            "La dirección del servidor de IA local debe empezar por http:// o https://.",
+        ));
+    }
+    Ok(())
+}
+
+impl LocalAiConfig {
+    fn endpoint(&self, path: &str) -> String {
+        format!("{}/v1/{}", self.base_url.trim_end_matches('/'), path)
+    }
+
+    fn client(&self, streaming: bool) -> Result<Client, AppError> {
+        let builder = Client::builder().connect_timeout(self.timeout);
+        let builder = if streaming { builder.read_timeout(self.timeout) } else { builder.timeout(self.timeout) };
+        builder
+            .build()
+            .map_err(|e| AppError::Internal(format!("No se pudo crear el cliente HTTP: {}", e)))
+    }
+
+    fn network_error(&self, e: reqwest::Error) -> AppError {
+        error!("Error connecting to local AI at {}: {}", self.base_url, e);
+        if e.is_timeout() {
+            AppError::Network(format!("La IA local no respondió a tiempo ({} s).", self.timeout.as_secs()))
+        } else {
+            AppError::Network(format!(
+                "No se pudo conectar con la IA local en {}. Comprueba que el servidor está en marcha.",
+                self.base_url
+            ))
+        }
+    }
+
+    /// Cuerpo de `/v1/chat/completions`.
+    fn payload(&self, messages: &[AiMessage], params: &GenerationParams, stream: bool) -> Result<Value, AppError> {
+        let messages = messages.iter().map(to_message).collect::<Result<Vec<_>, _>>()?;
+        let mut payload = json!({
+            "model": self.model,
+            "messages": messages,
+            "stream": stream,
+        });
+        if stream {
+            payload["stream_options"] = json!({"include_usage": true});
+        }
+        if let Some(temperature) = params.temperature {
+            payload["temperature"] = json!(temperature);
+        }
+        if let Some(max_tokens) = params.max_output_tokens {
+            payload["max_tokens"] = json!(max_tokens);
+        }
+        // Los servidores locales no admiten el esquema de Gemini; el prompt lo describe.
+        if params.response_schema.is_some() {
+            payload["response_format"] = json!({"type": "json_object"});
+        }
+        Ok(payload)
+    }
+
+    async fn post(&self, messages: &[AiMessage], params: &GenerationParams, stream: bool) -> Result<Response, AppError> {
+        let payload = self.payload(messages, params, stream)?;
+        debug!("Enviando solicitud a la IA local ({})", self.model);
+        let response = self
+            .client(stream)?
+            .post(self.endpoint("chat/completions"))
+            .json(&payload)
+            .send()
+            .await
+            .map_err(|e| self.network_error(e))?;
+        if !response.status().is_success() {
+            let status = response.status();
+            let body: Value = response.json().await.unwrap_or(Value::Null);
+            let message = body.pointer("/error/message").and_then(|m| m.as_str()).unwrap_or("desconocido");
+            error!("Local AI request failed with {}: {}", status, message);
+            return Err(AppError::Ai(format!("Error de la IA local: {}", message)));
+        }
+        Ok(response)
+    }
+}
+
+/// Tokens consumidos según el campo `usage` de una respuesta o del último fragmento.
+fn extract_usage(response: &Value) -> Option<TokenUsage> {
+    let usage = response.get("usage").filter(|u| u.is_object())?;
+    let count = |key: &str| usage.get(key).and_then(|v| v.as_u64()).unwrap_or(0);
+    Some(TokenUsage { prompt_tokens: count("prompt_tokens"), response_tokens: count("completion_tokens") })
+}
+
+/// Mensaje en el formato de OpenAI. Las imágenes van como URL `data:`.
+fn to_message(message: &AiMessage) -> Result<Value, AppError> {
+    let role = match message.role {
+        AiRole::User => "user",
+        AiRole::Model => "assistant",
+    };
+    if message.images.is_empty() {
+        return Ok(json!({"role": role, "content": message.text}));
+    }
+    let mut content = vec![json!({"type": "text", "text": message.text})];
+    for image in &message.images {
+        if !image.mime_type.starts_with("image/") {
+            return Err(AppError::Ai("La IA local solo puede leer imágenes, no documentos PDF.".to_string()));
+        }
+        content.push(json!({
+            "type": "image_url",
+            "image_url": {"url": format!("data:{};base64,{}", image.mime_type, image.data)}
+        }));
+    }
+    Ok(json!({"role": role, "content": content}))
+}
+
+impl AiBackend for LocalAiConfig {
+    fn model(&self) -> &str {
+        &self.model
+    }
+
+    async fn generate(&self, messages: &[AiMessage], params: &GenerationParams) -> Result<AiReply, AppError> {
+        let response: Value = self
+            .post(messages, params, false)
+            .await?
+            .json()
+            .await
+            .map_err(|e| AppError::Network(format!("Error al leer la respuesta de la IA local: {}", e)))?;
+        let text = response.pointer("/choices/0/message/content").and_then(|t| t.as_str());
+        match text {
+            Some(text) => {
+                info!("Local AI call successful.");
+                Ok(AiReply { text: text.to_string(), usage: extract_usage(&response).unwrap_or_default() })
+            }
+            None => {
+                error!("Could not extract text from local AI response: {:?}", response);
+                Err(AppError::Ai("No se pudo extraer el texto de la respuesta de la IA.".to_string()))
+            }
+        }
+    }
+
+    async fn stream<F>(&self, messages: &[AiMessage], params: &GenerationParams, mut on_chunk: F) -> Result<AiReply, AppError>
+    where
+        F: FnMut(&str) + Send,
+    {
+        let mut response = self.post(messages, params, true).await?;
+        let mut buffer: Vec<u8> = Vec::new();
+        let mut full_text = String::new();
+        let mut usage = TokenUsage::default();
+        let mut handle_event = |event: &str, full_text: &mut String| -> Result<(), AppError> {
+            let data = ai::sse_event_data(event);
+            if data.is_empty() || data == "[DONE]" {
+                return Ok(());
+            }
+            let chunk: Value = serde_json::from_str(&data)
+                .map_err(|e| AppError::Ai(format!("Fragmento inválido en la respuesta de la IA local: {}", e)))?;
+            if let Some(text) = chunk.pointer("/choices/0/delta/content").and_then(|t| t.as_str()) {
+                if !text.is_empty() {
+                    on_chunk(text);
+                    full_text.push_str(text);
+                }
+            }
+            if let Some(chunk_usage) = extract_usage(&chunk) {
+                usage = chunk_usage;
+            }
+            Ok(())
+        };
+
+        while let Some(bytes) = response.chunk().await.map_err(|e| self.network_error(e))? {
+            buffer.extend_from_slice(&bytes);
+            while let Some((position, separator_len)) = ai::find_event_boundary(&buffer) {
+                let event_bytes: Vec<u8> = buffer.drain(..position + separator_len).collect();
+                handle_event(&String::from_utf8_lossy(&event_bytes[..position]), &mut full_text)?;
+            }
+        }
+        if !buffer.is_empty() {
+            handle_event(&String::from_utf8_lossy(&buffer), &mut full_text)?;
+        }
+
+        if full_text.is_empty() {
+            error!("Local AI stream finished without text.");
+            return Err(AppError::Ai("No se pudo extraer el texto de la respuesta de la IA.".to_string()));
+        }
+        info!("Local AI streaming call successful ({} chars).", full_text.len());
+        Ok(AiReply { text: full_text, usage })
+    }
+
+    async fn list_models(&self) -> Result<Vec<ModelInfo>, AppError> {
+        let response = self
+            .client(false)?
+            .get(self.endpoint("models"))
+            .send()
+            .await
+            .map_err(|e| self.network_error(e))?;
+        let body: Value = response
+            .json()
+            .await
+            .map_err(|e| AppError::Network(format!("Error al leer la lista de modelos de la IA local: {}", e)))?;
+        let models = body
+            .get("data")
+            .and_then(|d| d.as_array())
+            .into_iter()
+            .flatten()
+            .filter_map(|model| model.get("id").and_then(|id| id.as_str()))
+            .map(|id| ModelInfo {
+                name: id.to_string(),
+                display_name: id.to_string(),
+                description: String::new(),
+                input_token_limit: None,
+                output_token_limit: None,
+            })
+            .collect();
+        Ok(models)
+    }
+}
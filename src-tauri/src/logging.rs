@@ -0,0 +1,306 @@
+// src-tauri/src/logging.rs
+
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs;
+use std::io::{Cursor, Write};
+use std::path::{Path, PathBuf};
+use std::sync::RwLock;
+use std::time::{Duration, SystemTime};
+use tauri_plugin_log::{RotationStrategy, Target, TargetKind};
+use log::{debug, info, warn, LevelFilter, Metadata};
+
+use crate::error::AppError;
+use crate::settings::Settings;
+use crate::storage;
+
+/// Carpeta de los registros, dentro del directorio de datos de la aplicación (común
+/// a todos los espacios de trabajo).
+const LOG_DIR_NAME: &str = "logs";
+const LOG_FILE_NAME: &str = "contabilidad";
+/// Al llegar a este tamaño el archivo de registro se archiva y se empieza otro.
+const MAX_LOG_FILE_BYTES: u128 = 5 * 1024 * 1024;
+/// Archivos de registro que se conservan, contando el actual.
+const MAX_LOG_FILES: usize = 10;
+/// Días de registros que incluye `export_logs_command`.
+const EXPORT_DAYS: u64 = 7;
+/// Módulos de este programa en el `target` de los registros.
+const CRATE_PREFIX: &str = "contabilidad_desktop::";
+
+const REDACTED: &str = "[REDACTED]";
+const REDACTED_AMOUNT: &str = "[AMOUNT]";
+/// Texto tras el que viene un secreto (en minúsculas).
+const SECRET_MARKERS: &[&str] = &["key=", "token=", "password=", "secret=", "bearer "];
+/// Longitud mínima de una clave de API de Google (`AIza...`).
+const MIN_GOOGLE_KEY_LENGTH: usize = 30;
+
+/// Nivel de detalle de los registros.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LogLevel {
+    Off,
+    Error,
+    Warn,
+    Info,
+    Debug,
+    Trace,
+}
+
+impl From<LogLevel> for LevelFilter {
+    fn from(level: LogLevel) -> Self {
+        match level {
+            LogLevel::Off => LevelFilter::Off,
+            LogLevel::Error => LevelFilter::Error,
+            LogLevel::Warn => LevelFilter::Warn,
+            LogLevel::Info => LevelFilter::Info,
+            LogLevel::Debug => LevelFilter::Debug,
+            LogLevel::Trace => LevelFilter::Trace,
+        }
+    }
+}
+
+/// Niveles vigentes: el general y los de cada módulo, del más específico al más general.
+struct LogFilter {
+    default: LevelFilter,
+    modules: Vec<(String, LevelFilter)>,
+}
+
+static FILTER: RwLock<Option<LogFilter>> = RwLock::new(None);
+
+/// Aplica los niveles de `settings`. Se puede llamar en cualquier momento; los
+/// cambios valen para los siguientes registros.
+pub fn apply_settings(settings: &Settings) {
+    let mut modules: Vec<(String, LevelFilter)> =
+        settings.log_module_levels.iter().map(|(module, level)| (module.clone(), (*level).into())).collect();
+    modules.sort_by(|(a, _), (b, _)| b.len().cmp(&a.len()).then_with(|| a.cmp(b)));
+    *FILTER.write().unwrap() = Some(LogFilter { default: settings.log_level.into(), modules });
+}
+
+/// Si `module` (un módulo del programa como `lan_sync`, o una dependencia como
+/// `reqwest`) es `target` o lo contiene.
+fn module_matches(module: &str, target: &str) -> bool {
+    let matches = |target: &str| {
+        target == module || target.strip_prefix(module).is_some_and(|rest| rest.starts_with("::"))
+    };
+    matches(target) || target.strip_prefix(CRATE_PREFIX).is_some_and(matches)
+}
+
+/// Si se debe registrar un mensaje con `metadata`. Hasta aplicar los ajustes solo
+/// se registra desde `Info`.
+pub fn enabled(metadata: &Metadata) -> bool {
+    let filter = FILTER.read().unwrap();
+    let level = match filter.as_ref() {
+        Some(filter) => filter
+            .modules
+            .iter()
+            .find(|(module, _)| module_matches(module, metadata.target()))
+            .map(|(_, level)| *level)
+            .unwrap_or(filter.default),
+        None => LevelFilter::Info,
+    };
+    metadata.level() <= level
+}
+
+// --- Redacción ---
+
+fn is_token_char(c: char) -> bool {
+    c.is_ascii_alphanumeric() || c == '_' || c == '-'
+}
+
+fn starts_with_ignore_case(chars: &[char], prefix: &str) -> bool {
+    let mut prefix_chars = prefix.chars();
+    let matched = chars.iter().zip(prefix_chars.by_ref()).all(|(c, p)| c.to_ascii_lowercase() == p);
+    matched && prefix_chars.next().is_none()
+}
+
+/// Fin de la cifra que empieza en `start` (dígitos con `.` o `,` entre medias) y si
+/// parece un importe: tiene decimales de una o dos cifras, no va seguida de letras
+/// (`1.5s`) y no es una dirección IP.
+fn scan_number(chars: &[char], start: usize) -> (usize, bool) {
+    let mut end = start;
+    let mut separators = 0;
+    let mut dots = 0;
+    let mut last_group = 0;
+    while end < chars.len() {
+        let c = chars[end];
+        if c.is_ascii_digit() {
+            last_group += 1;
+            end += 1;
+        } else if (c == '.' || c == ',') && chars.get(end + 1).is_some_and(|n| n.is_ascii_digit()) {
+            separators += 1;
+            dots += usize::from(c == '.');
+            last_group = 0;
+            end += 1;
+        } else {
+            break;
+        }
+    }
+    let followed_by_letter = chars.get(end).is_some_and(|c| c.is_alphabetic());
+    let is_amount = separators > 0 && (1..=2).contains(&last_group) && !followed_by_letter && dots < 3;
+    (end, is_amount)
+}
+
+/// Quita de un mensaje de registro los importes (cifras con decimales), las claves
+/// de API de Google y los valores tras `key=`, `token=`, `password=` o `Bearer`.
+pub fn redact(message: &str) -> String {
+    let chars: Vec<char> = message.chars().collect();
+    let mut out = String::with_capacity(message.len());
+    let mut i = 0;
+    while i < chars.len() {
+        let at_boundary = i == 0 || !is_token_char(chars[i - 1]);
+        if let Some(marker) = SECRET_MARKERS.iter().find(|m| starts_with_ignore_case(&chars[i..], m)) {
+            let value_start = i + marker.chars().count();
+            let value_end = chars[value_start..]
+                .iter()
+                .position(|c| c.is_whitespace() || matches!(c, '&' | '"' | '\'' | ',' | ';'))
+                .map_or(chars.len(), |p| value_start + p);
+            out.extend(&chars[i..value_start]);
+            if value_end > value_start {
+                out.push_str(REDACTED);
+            }
+            i = value_end;
+            continue;
+        }
+        if at_boundary && starts_with_ignore_case(&chars[i..], "aiza") {
+            let end = chars[i..].iter().position(|c| !is_token_char(*c)).map_or(chars.len(), |p| i + p);
+            if end - i >= MIN_GOOGLE_KEY_LENGTH {
+                out.push_str(REDACTED);
+                i = end;
+                continue;
+            }
+        }
+        if chars[i].is_ascii_digit() && (i == 0 || !(chars[i - 1].is_alphanumeric() || matches!(chars[i - 1], '.' | ',' | '_'))) {
+            let (end, is_amount) = scan_number(&chars, i);
+            if is_amount {
+                out.push_str(REDACTED_AMOUNT);
+            } else {
+                out.extend(&chars[i..end]);
+            }
+            i = end;
+            continue;
+        }
+        out.push(chars[i]);
+        i += 1;
+    }
+    out
+}
+
+// --- Archivos ---
+
+pub fn log_dir() -> PathBuf {
+    storage::get_app_data_dir().join(LOG_DIR_NAME)
+}
+
+/// Plugin de registro: consola y archivos rotativos en `log_dir()`, con los niveles
+/// de `enabled` y los mensajes pasados por `redact`.
+pub fn plugin<R: tauri::Runtime>() -> tauri::plugin::TauriPlugin<R> {
+    tauri_plugin_log::Builder::default()
+        .clear_targets()
+        .targets([
+            Target::new(TargetKind::Stdout),
+            Target::new(TargetKind::Folder { path: log_dir(), file_name: Some(LOG_FILE_NAME.to_string()) }),
+        ])
+        .level(LevelFilter::Trace)
+        .filter(enabled)
+        .max_file_size(MAX_LOG_FILE_BYTES)
+        .rotation_strategy(RotationStrategy::KeepAll)
+        .format(|out, message, record| {
+            out.finish(format_args!(
+                "{} {:<5} [{}] {}",
+                chrono::Local::now().format("%Y-%m-%d %H:%M:%S%.3f"),
+                record.level(),
+                record.target(),
+                redact(&message.to_string())
+            ))
+        })
+        .build()
+}
+
+/// Archivos de registro, del más reciente al más antiguo.
+fn list_log_files(dir: &Path) -> Vec<(PathBuf, SystemTime)> {
+    let Ok(entries) = fs::read_dir(dir) else { return Vec::new() };
+    let mut files: Vec<(PathBuf, SystemTime)> = entries
+        .filter_map(Result::ok)
+        .map(|entry| entry.path())
+        .filter(|path| path.extension().is_some_and(|ext| ext == "log"))
+        .filter_map(|path| {
+            let modified = fs::metadata(&path).and_then(|m| m.modified()).ok()?;
+            Some((path, modified))
+        })
+        .collect();
+    files.sort_by(|(_, a), (_, b)| b.cmp(a));
+    files
+}
+
+/// Borra los archivos de registro que sobran de `MAX_LOG_FILES`.
+pub fn prune_old_logs() {
+    for (path, _) in list_log_files(&log_dir()).into_iter().skip(MAX_LOG_FILES) {
+        match fs::remove_file(&path) {
+            Ok(()) => debug!("Old log file removed: {}", path.display()),
+            Err(e) => warn!("Could not remove old log file {}: {}", path.display(), e),
+        }
+    }
+}
+
+/// Registros exportados.
+#[derive(Debug, Clone, Serialize)]
+pub struct LogExport {
+    pub path: String,
+    pub files: usize,
+    pub bytes: u64,
+}
+
+/// ZIP con los archivos de registro de los últimos `EXPORT_DAYS` días.
+fn zip_recent_logs(dir: &Path) -> Result<(Vec<u8>, usize), AppError> {
+    let io_error = |e: std::io::Error| AppError::Io(format!("Error al leer los registros: {}", e));
+    let zip_error = |e: zip::result::ZipError| AppError::Internal(format!("Error al comprimir los registros: {}", e));
+    let since = SystemTime::now() - Duration::from_secs(EXPORT_DAYS * 24 * 60 * 60);
+    let files: Vec<PathBuf> =
+        list_log_files(dir).into_iter().filter(|(_, modified)| *modified >= since).map(|(path, _)| path).collect();
+    if files.is_empty() {
+        return Err(AppError::NotFound("No hay registros recientes que exportar.".to_string()));
+    }
+
+    let mut zip = zip::ZipWriter::new(Cursor::new(Vec::new()));
+    let options = zip::write::SimpleFileOptions::default().compression_method(zip::CompressionMethod::Deflated);
+    for path in files.iter() {
+        let name = path.file_name().unwrap_or_default().to_string_lossy();
+        zip.start_file(name, options).map_err(zip_error)?;
+        zip.write_all(&fs::read(path).map_err(io_error)?).map_err(io_error)?;
+    }
+    let bytes = zip.finish().map_err(zip_error)?.into_inner();
+    Ok((bytes, files.len()))
+}
+
+/// Niveles de registro por módulo de los ajustes, sin espacios y sin módulos vacíos.
+pub fn validate_module_levels(levels: &HashMap<String, LogLevel>) -> Result<HashMap<String, LogLevel>, AppError> {
+    let mut resolved = HashMap::new();
+    for (module, level) in levels {
+        let module = module.trim();
+        if module.is_empty() || module.contains(char::is_whitespace) {
+            return Err(AppError::invalid_field(
+                "log_module_levels",
+                format!("El módulo '{}' no es válido.", module),
+            ));
+        }
+        resolved.insert(module.to_owned(), *level);
+    }
+    Ok(resolved)
+}
+
+// --- Comandos Tauri ---
+
+/// Comando para guardar en `path` un ZIP con los registros de los últimos días, para
+/// enviarlo a soporte. Los registros ya se escriben sin importes ni claves.
+#[tauri::command]
+pub async fn export_logs_command(path: String) -> Result<LogExport, AppError> {
+    info!("Received export_logs_command: {}", path);
+    let target = PathBuf::from(&path);
+    let (bytes, files) = tokio::task::spawn_blocking(|| zip_recent_logs(&log_dir()))
+        .await
+        .map_err(|e| AppError::Internal(format!("Error al exportar los registros: {}", e)))??;
+    storage::write_atomic(&target, &bytes)?;
+    info!("Exported {} log files ({} bytes).", files, bytes.len());
+    Ok(LogExport { path, files, bytes: bytes.len() as u64 })
+}
+
@@ -5,24 +5,116 @@
 #![cfg_attr(not(debug_assertions), windows_subsystem = "windows")]
 
 use serde::{Serialize, Deserialize};
-use serde_json::json;
-use std::collections::{HashSet, HashMap};
-use std::env;
-use std::path::PathBuf;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
 use tokio::fs;
-use reqwest::Client;
-use chrono::Utc;
+use chrono::{Datelike, NaiveDate};
+use rust_decimal::Decimal;
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::Mutex;
-use tauri::State;
-use log::{info, debug, error, warn}; // Import debug, error, and warn
+use tauri::{AppHandle, State};
+use log::{info, debug, error}; // Import debug and error
+
+mod accounts;
+mod aggregates;
+mod ai;
+mod ai_cache;
+mod ai_queue;
+mod ai_usage;
+mod anomalies;
+mod api_server;
+mod archive;
+mod assistant;
+mod attachments;
+mod audit;
+mod autosave;
+mod backup;
+mod banking;
+mod budgets;
+mod bulk;
+mod cash_closing;
+mod categories;
+mod chat;
+mod cli;
+mod closing;
+mod contacts;
+mod currencies;
+mod deep_links;
+mod dashboard;
+mod diagnostics;
+mod duplicates;
+mod email;
+mod encryption;
+mod error;
+mod events;
+mod forecast;
+mod gemini;
+mod goals;
+mod history;
+mod i18n;
+mod import;
+mod instance;
+mod integrity;
+mod invoices;
+mod keychain;
+mod lan_sync;
+mod local_ai;
+mod logging;
+mod merge;
+mod migrations;
+mod money;
+mod notifications;
+mod opened_files;
+mod payments;
+mod pdf;
+mod periods;
+mod petty_cash;
+mod privacy;
+mod prompts;
+mod receipts;
+mod reports;
+mod rules;
+mod scheduler;
+mod search;
+mod settings;
+mod shutdown;
+mod speech;
+mod splits;
+mod startup;
+mod storage;
+mod stores;
+mod summaries;
+mod sync;
+mod tags;
+mod taxes;
+mod templates;
+mod text_entry;
+mod tickets;
+mod transfers;
+mod trash;
+mod tray;
+mod users;
+mod validation;
+mod webhooks;
+mod windows;
+mod workspaces;
+
+use error::AppError;
+use history::{Change, CommandHistory, HistoryEntry};
+use payments::PaymentMethod;
+use validation::Validator;
+use storage::{RecoveryReport, SqliteStorage, StorageGuard, TransactionPage, TransactionQuery, TransactionRepository};
 
 // --- Estructuras de Datos de la Aplicación ---
 
-/// Tipo de transacción: Ingreso o Gasto.
-#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+/// Tipo de transacción: Ingreso, Gasto o Transferencia. Una transferencia mueve dinero
+/// de una tienda (`store_name`) a otra (`transfer_store`): no es ingreso ni gasto y
+/// solo cuenta en el balance de cada tienda.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
 enum TransactionType {
     Ingreso,
     Gasto,
+    Transferencia,
 }
 
 impl ToString for TransactionType {
@@ -30,6 +122,7 @@ impl ToString for TransactionType {
         match self {
             TransactionType::Ingreso => "Ingreso".to_string(),
             TransactionType::Gasto => "Gasto".to_string(),
+            TransactionType::Transferencia => "Transferencia".to_string(),
         }
     }
 }
@@ -40,486 +133,1050 @@ struct Transaction {
     id: String,
     #[serde(rename = "type")]
     transaction_type: TransactionType,
-    amount: f64,
+    /// Importe exacto. Se serializa como texto (`"12.50"`); al leer se aceptan también
+    /// números, como los del antiguo `transactions.json`.
+    amount: Decimal,
     description: String,
     store_name: String,
+    /// Momento de la transacción, con el que se ordena y se calculan los informes.
+    /// Siempre cae en `transaction_date` (hora local).
     timestamp: u64,
+    #[serde(default)]
+    category: Option<String>,
+    #[serde(default)]
+    subcategory: Option<String>,
+    /// Código ISO 4217 de la moneda del importe.
+    #[serde(default = "currencies::default_currency")]
+    currency: String,
+    /// Justificantes adjuntos, como rutas relativas al directorio de datos.
+    #[serde(default)]
+    receipt_paths: Vec<String>,
+    /// Momento en que se movió a la papelera; `None` si está activa.
+    #[serde(default)]
+    deleted_at: Option<u64>,
+    /// Tipo de IVA en porcentaje (p. ej. 21), si la transacción lleva IVA.
+    #[serde(default)]
+    tax_rate: Option<Decimal>,
+    /// Cuota de IVA incluida en `amount`.
+    #[serde(default)]
+    tax_amount: Option<Decimal>,
+    /// Etiquetas libres, normalizadas con `tags::normalize_tag`.
+    #[serde(default)]
+    tags: Vec<String>,
+    /// Identificador del movimiento en el extracto bancario del que se importó.
+    #[serde(default)]
+    external_id: Option<String>,
+    /// Fecha de la transacción indicada por el usuario (p. ej. la del ticket).
+    transaction_date: NaiveDate,
+    /// Momentos en que se registró y en que se modificó por última vez.
+    created_at: u64,
+    updated_at: u64,
+    /// Notas libres del usuario.
+    #[serde(default)]
+    notes: String,
+    /// Campos personalizados definidos por el usuario (p. ej. "proyecto" → "Reforma").
+    #[serde(default)]
+    custom_fields: HashMap<String, String>,
+    /// Medio con el que se pagó o cobró.
+    #[serde(default)]
+    payment_method: PaymentMethod,
+    /// Cliente o proveedor (`contacts::Contact`) de la transacción.
+    #[serde(default)]
+    contact_id: Option<String>,
+    /// Tienda de destino de una `Transferencia`; la de origen es `store_name`.
+    #[serde(default)]
+    transfer_store: Option<String>,
+    /// Cuenta (`accounts::Account`) en la que entra o de la que sale el dinero. En una
+    /// transferencia es la de origen, y `transfer_account_id` la de destino.
+    #[serde(default)]
+    account_id: Option<String>,
+    #[serde(default)]
+    transfer_account_id: Option<String>,
+    /// Usuario (`users::User`) que registró la transacción. Al insertar una transacción
+    /// nueva sin usuario se le pone el de la sesión abierta, si la hay.
+    #[serde(default)]
+    user_id: Option<String>,
+    /// Revisión, que sube con cada cambio, y equipo que hizo el último (ver `merge`).
+    /// Al insertar, un `device_id` vacío se sustituye por el de este equipo.
+    #[serde(default = "merge::initial_revision")]
+    revision: u64,
+    #[serde(default)]
+    device_id: String,
+    /// Número de secuencia de esta base de datos: lo asigna `storage` al insertar,
+    /// bajo el bloqueo de los datos, y siempre es mayor que el de cualquier inserción
+    /// anterior. Desempata el orden cuando coinciden los momentos. Al insertar se
+    /// ignora el valor que traiga.
+    #[serde(default)]
+    sequence: u64,
 }
 
 /// Estado compartido de la aplicación Rust.
 /// Usamos Mutex para permitir el acceso mutable y seguro desde múltiples threads/comandos.
 struct AppState {
-    transactions: Mutex<Vec<Transaction>>,
+    /// Base de datos. Los comandos acceden a ella con `AppState::db`. Es un mutex de
+    /// tokio para que un comando que espera la base de datos no bloquee el hilo del
+    /// runtime; no puede ser un `RwLock` porque la conexión de SQLite no es `Sync`.
+    /// `history` y `workspace` siguen siendo mutex síncronos porque nunca se
+    /// mantienen bloqueados a través de un `.await`.
+    db: tokio::sync::Mutex<SqliteStorage>,
+    /// Historial de deshacer/rehacer. Si se necesitan ambos, se bloquea siempre `db` primero.
+    history: Mutex<CommandHistory>,
+    /// `true` mientras los datos cifrados no se han desbloqueado con su contraseña.
+    locked: AtomicBool,
+    /// Espacio de trabajo (libro contable) activo. Se bloquea después de `db` y `history`.
+    workspace: Mutex<workspaces::Workspace>,
+}
+
+impl AppState {
+    /// Bloquea la base de datos para un comando, esperando sin bloquear el hilo si la
+    /// usa otro. Mientras los datos cifrados no se han desbloqueado devuelve
+    /// `AppError::Locked`.
+    async fn db(&self) -> Result<StorageGuard<'_>, AppError> {
+        let guard = self.db.lock().await;
+        // Se comprueba con la base de datos ya bloqueada: un cambio de espacio de
+        // trabajo o un bloqueo pudo ocurrir mientras se esperaba.
+        if self.is_locked() {
+            return Err(AppError::Locked);
+        }
+        Ok(StorageGuard::new(guard))
+    }
+
+    fn is_locked(&self) -> bool {
+        self.locked.load(Ordering::SeqCst)
+    }
+
+    fn set_locked(&self, locked: bool) {
+        self.locked.store(locked, Ordering::SeqCst);
+    }
 }
 
 // --- Lógica de Persistencia Local ---
 
-const DATA_FILE_NAME: &str = "transactions.json";
-
-/// Obtiene la ruta persistente para guardar el archivo usando dirs.
-/// Esta función ha sido restaurada para usar dirs::data_local_dir()
-/// para asegurar la persistencia de los datos entre ejecuciones.
-fn get_data_file_path() -> PathBuf {
-    let mut path = dirs::data_local_dir()
-        .expect("No se pudo obtener el directorio de datos local.");
-    path.push("com.tuempresa.contabilidad"); // Subdirectorio específico para tu app
-    path.push(DATA_FILE_NAME);
-    debug!("Ruta del archivo de datos: {}", path.display());
+const LEGACY_DATA_FILE_NAME: &str = "transactions.json";
+
+/// Ruta del antiguo archivo JSON de transacciones, usado antes de SQLite.
+/// Solo se lee una vez para importar su contenido a la base de datos.
+fn get_legacy_data_file_path() -> PathBuf {
+    let mut path = storage::get_app_data_dir();
+    path.push(LEGACY_DATA_FILE_NAME);
+    debug!("Ruta del archivo de datos heredado: {}", path.display());
     path
 }
 
-/// Carga las transacciones desde el archivo JSON local.
-async fn load_transactions_from_file() -> Result<Vec<Transaction>, String> {
-    let path = get_data_file_path();
-    if path.exists() {
-        match fs::read_to_string(&path).await {
-            Ok(data) => {
-                // Clonar 'data' para usarla en el log después de que 'serde_json::from_str' la tome por referencia.
-                // Esto resuelve el error "borrow of moved value: `data`".
-                let data_for_log = data.clone(); 
-                match serde_json::from_str(&data) {
-                    Ok(transactions) => {
-                        info!("Transacciones cargadas de: {}", path.display());
-                        debug!("Contenido cargado (para depuración): {}", data_for_log); // Usamos la copia
-                        Ok(transactions)
-                    },
-                    Err(e) => {
-                        error!("Error al parsear transacciones de {}: {}", path.display(), e);
-                        Err(format!("Error al parsear datos de transacciones: {}", e))
-                    }
-                }
-            },
-            Err(e) => {
-                error!("Error al leer archivo de transacciones {}: {}", path.display(), e);
-                Err(format!("Error al leer archivo de datos: {}", e))
-            }
+/// Carga las transacciones desde el archivo JSON heredado, migrándolo antes al
+/// formato actual si es de una versión anterior.
+async fn load_transactions_from_file(path: &Path) -> Result<Vec<Transaction>, AppError> {
+    match fs::read_to_string(path).await {
+        Ok(data) => {
+            let transactions = migrations::load_transactions_file(path, &data)?;
+            info!("Transacciones cargadas de: {}", path.display());
+            debug!("Cargadas {} transacciones del archivo heredado.", transactions.len());
+            Ok(transactions)
+        },
+        Err(e) => {
+            error!("Error al leer archivo de transacciones {}: {}", path.display(), e);
+            Err(AppError::Io(format!("Error al leer archivo de datos: {}", e)))
         }
-    } else {
-        warn!("Archivo de datos no encontrado en {}. Iniciando con transacciones vacías.", path.display());
-        Ok(Vec::new())
     }
 }
 
-/// Guarda las transacciones al archivo JSON local.
-async fn save_transactions_to_file(transactions: &[Transaction]) -> Result<(), String> {
-    let path = get_data_file_path();
-    if let Some(parent) = path.parent() {
-        if let Err(e) = fs::create_dir_all(parent).await {
-            return Err(format!("Falló la creación del directorio padre ({}): {}", parent.display(), e));
-        }
+/// Ruta del antiguo `transactions.json` si existe y la base de datos todavía está
+/// vacía, es decir, si hay que importarlo.
+fn legacy_import_pending(db: &SqliteStorage) -> Result<Option<PathBuf>, AppError> {
+    let path = get_legacy_data_file_path();
+    if !path.exists() || db.count_transactions()? > 0 {
+        return Ok(None);
     }
+    Ok(Some(path))
+}
 
-    match serde_json::to_string_pretty(transactions) {
-        Ok(data) => {
-            match fs::write(&path, data).await {
-                Ok(_) => {
-                    info!("Transacciones guardadas en: {}", path.display());
-                    debug!("Contenido guardado: {}", serde_json::to_string_pretty(transactions).unwrap_or_else(|_| "Error al serializar para depuración".to_string()));
-                    Ok(())
-                },
-                Err(e) => {
-                    error!("Error al guardar transacciones en {}: {}", path.display(), e);
-                    Err(format!("Error al guardar transacciones: {}", e))
-                }
-            }
-        },
-        Err(e) => Err(format!("Falló la serialización de transacciones para guardar: {}", e)),
+/// Importa a SQLite las transacciones ya leídas (con `load_transactions_from_file`)
+/// del antiguo `transactions.json` de `path`. Tras importarlo, el archivo se renombra
+/// a `transactions.json.migrated` para que no vuelva a importarse.
+fn import_legacy_transactions(db: &SqliteStorage, path: &Path, transactions: &[Transaction]) -> Result<(), AppError> {
+    // Mientras se leía el archivo se han podido registrar transacciones nuevas: solo
+    // se descarta la importación si ya se había hecho.
+    let existing = db.all_transaction_ids()?;
+    if transactions.iter().any(|t| existing.contains(&t.id)) {
+        info!("Legacy transactions from {} were already imported; skipping.", path.display());
+        return Ok(());
+    }
+    backup::snapshot_before(db, backup::REASON_IMPORT)?;
+    db.insert_transactions(transactions)?;
+
+    let migrated_path = path.with_extension("json.migrated");
+    std::fs::rename(path, &migrated_path)
+        .map_err(|e| AppError::Io(format!("Error al renombrar el archivo de datos heredado: {}", e)))?;
+    info!("Importadas {} transacciones desde {} a SQLite.", transactions.len(), migrated_path.display());
+    Ok(())
+}
+
+/// Añade una transacción de ejemplo si la base de datos está vacía, para que la
+/// primera vez la interfaz no aparezca en blanco.
+fn insert_sample_transaction(db: &SqliteStorage) -> Result<(), AppError> {
+    if db.count_transactions()? > 0 {
+        return Ok(());
     }
+    db.insert_transaction(&Transaction {
+        id: uuid::Uuid::new_v4().to_string(),
+        transaction_type: TransactionType::Ingreso,
+        amount: Decimal::new(1000, 2),
+        description: "Transacción inicial de prueba (Rust)".to_string(),
+        store_name: "Tienda de Prueba (Rust)".to_string(),
+        timestamp: periods::now_timestamp(),
+        category: None,
+        subcategory: None,
+        currency: currencies::get_base_currency(db)?,
+        receipt_paths: Vec::new(),
+        deleted_at: None,
+        tax_rate: None,
+        tax_amount: None,
+        tags: Vec::new(),
+        external_id: None,
+        transaction_date: periods::today(),
+        created_at: periods::now_timestamp(),
+        updated_at: periods::now_timestamp(),
+        notes: String::new(),
+        custom_fields: HashMap::new(),
+        payment_method: PaymentMethod::Otro,
+        contact_id: None,
+        transfer_store: None,
+        account_id: None,
+        transfer_account_id: None,
+        user_id: None,
+        revision: merge::initial_revision(),
+        device_id: String::new(),
+        sequence: 0,
+    })?;
+    info!("Añadida una transacción de prueba inicial.");
+    Ok(())
 }
 
 // --- Comandos Tauri (accesibles desde el frontend) ---
 
 /// Comando para obtener todas las transacciones.
 #[tauri::command]
-async fn get_all_transactions(state: State<'_, AppState>) -> Result<Vec<Transaction>, String> {
+async fn get_all_transactions(state: State<'_, AppState>) -> Result<Vec<Transaction>, AppError> {
     debug!("Received get_all_transactions command.");
-    let transactions = state.transactions.lock().unwrap().clone();
+    let transactions = state.db().await?.list_transactions()?;
     debug!("Returning {} transactions.", transactions.len());
     Ok(transactions)
 }
 
-/// Comando para añadir una nueva transacción.
+/// Comando para consultar transacciones con filtros, orden y paginación calculados en Rust.
+/// Devuelve solo la página pedida (`items`) y el número total de coincidencias (`total_count`).
+#[tauri::command]
+async fn query_transactions_command(
+    state: State<'_, AppState>,
+    query: TransactionQuery,
+) -> Result<TransactionPage, AppError> {
+    debug!("Received query_transactions_command: {:?}", query);
+    if let (Some(from), Some(to)) = (query.from, query.to) {
+        if from > to {
+            error!("Invalid date range: from={} > to={}", from, to);
+            return Err(AppError::invalid_field("from", "La fecha inicial no puede ser posterior a la fecha final."));
+        }
+    }
+    let page = state.db().await?.query_transactions(&query)?;
+    debug!("Returning {} of {} matching transactions.", page.items.len(), page.total_count);
+    Ok(page)
+}
+
+/// Datos de una transacción nueva, tal como llegan del formulario o de una entrada
+/// por lotes. Los campos opcionales se resuelven igual que en `add_transaction_command`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct NewTransaction {
+    transaction_type: TransactionType,
+    amount: Decimal,
+    description: String,
+    store_name: String,
+    #[serde(default)]
+    category: Option<String>,
+    #[serde(default)]
+    subcategory: Option<String>,
+    #[serde(default)]
+    currency: Option<String>,
+    #[serde(default)]
+    tax_rate: Option<Decimal>,
+    #[serde(default)]
+    tax_amount: Option<Decimal>,
+    #[serde(default)]
+    tags: Option<Vec<String>>,
+    #[serde(default)]
+    transaction_date: Option<NaiveDate>,
+    #[serde(default)]
+    notes: Option<String>,
+    #[serde(default)]
+    custom_fields: Option<HashMap<String, String>>,
+    #[serde(default)]
+    payment_method: Option<PaymentMethod>,
+    #[serde(default)]
+    contact_id: Option<String>,
+    #[serde(default)]
+    account_id: Option<String>,
+}
+
+/// Valida `input` y construye la transacción con sus valores resueltos (categoría de
+/// la tienda, moneda base, impuestos, fecha de hoy...). No la guarda. Si hay datos no
+/// válidos, el error `validation` incluye todos los campos erróneos, no solo el primero.
+fn build_new_transaction(db: &SqliteStorage, input: NewTransaction) -> Result<Transaction, AppError> {
+    let NewTransaction {
+        transaction_type,
+        amount,
+        description,
+        store_name,
+        category,
+        subcategory,
+        currency,
+        tax_rate,
+        tax_amount,
+        tags,
+        transaction_date,
+        notes,
+        custom_fields,
+        payment_method,
+        contact_id,
+        account_id,
+    } = input;
+
+    let mut validator = Validator::new();
+    if transaction_type == TransactionType::Transferencia {
+        validator.add(
+            "transaction_type",
+            validation::INVALID,
+            "Las transferencias se registran con su origen y su destino (add_transfer_command).",
+        );
+    }
+    let amount_valid = validator.check(money::validate_amount("amount", amount, "El monto debe ser positivo."))?.is_some();
+    let description = validator.check(validation::required_text("description", &description, "La descripción no puede estar vacía."))?;
+    let store_name = validator.check(validation::required_text("store_name", &store_name, "El nombre de la tienda no puede estar vacío."))?;
+
+    let category = category.filter(|c| !c.trim().is_empty());
+    let uncategorized = category.is_none() && subcategory.is_none();
+    let resolved_category = validator.check(categories::resolve_transaction_category(db.connection(), category, subcategory))?;
+    let currency = match currency {
+        Some(code) => validator.check(currencies::normalize_currency_code(&code))?,
+        None => Some(currencies::get_base_currency(db)?),
+    };
+    // El IVA solo se puede comprobar con un importe válido.
+    let tax = if amount_valid { validator.check(taxes::resolve_tax(amount, tax_rate, tax_amount))? } else { None };
+    let tags = validator.check(tags::resolve_tags(tags.unwrap_or_default()))?;
+    let transaction_date = validator.check(validation::validate_transaction_date(transaction_date.unwrap_or_else(periods::today)))?;
+    let notes = validator.check(validation::validate_notes(notes.unwrap_or_default()))?;
+    let custom_fields = validator.check(validation::validate_custom_fields(custom_fields.unwrap_or_default()))?;
+    let contact_id = validator.check(contacts::resolve_contact_id(db.connection(), contact_id))?;
+    let account_id = validator.check(accounts::resolve_account_id(db.connection(), "account_id", account_id))?;
+    if !validator.is_empty() {
+        error!("Invalid new transaction: {:?}", validator);
+    }
+    validator.finish()?;
+    // Tras `finish` todos los valores están resueltos.
+    let (
+        Some(description),
+        Some(store_name),
+        Some((category, subcategory)),
+        Some(currency),
+        Some((tax_rate, tax_amount)),
+        Some(tags),
+        Some(transaction_date),
+        Some(notes),
+        Some(custom_fields),
+        Some(contact_id),
+        Some(account_id),
+    ) = (description, store_name, resolved_category, currency, tax, tags, transaction_date, notes, custom_fields, contact_id, account_id)
+    else {
+        return Err(AppError::Internal("Validación incompleta de la transacción.".to_string()));
+    };
+    let now = periods::now_timestamp();
+
+    let mut transaction = Transaction {
+        id: uuid::Uuid::new_v4().to_string(),
+        transaction_type,
+        amount,
+        description,
+        store_name,
+        timestamp: periods::timestamp_for_date(transaction_date),
+        category,
+        subcategory,
+        currency,
+        receipt_paths: Vec::new(),
+        deleted_at: None,
+        tax_rate,
+        tax_amount,
+        tags,
+        external_id: None,
+        transaction_date,
+        created_at: now,
+        updated_at: now,
+        notes,
+        custom_fields,
+        payment_method: payment_method.unwrap_or_default(),
+        contact_id,
+        transfer_store: None,
+        account_id,
+        transfer_account_id: None,
+        user_id: None,
+        revision: merge::initial_revision(),
+        device_id: String::new(),
+        sequence: 0,
+    };
+    // Sin categoría se usa la de la primera regla que cumpla y, si no, la de la tienda.
+    if uncategorized {
+        let rules = rules::load_enabled(db.connection())?;
+        rules::apply(db.connection(), &rules, &mut transaction, rules::ApplyMode::FillEmpty);
+        if transaction.category.is_none() && transaction.subcategory.is_none() {
+            let default = stores::default_category(db.connection(), &transaction.store_name)?;
+            let (category, subcategory) = categories::resolve_transaction_category(db.connection(), default, None)?;
+            transaction.category = category;
+            transaction.subcategory = subcategory;
+        }
+    }
+    validation::enforce_policy(db, &transaction)?;
+    Ok(transaction)
+}
+
+/// Comando para añadir una nueva transacción. Sin `transaction_date` se usa la fecha
+/// de hoy. Con `check_duplicates`, si ya hay
+/// transacciones iguales en fecha cercana no se añade y se devuelve un error
+/// `duplicate` con sus IDs.
 #[tauri::command]
 async fn add_transaction_command(
     state: State<'_, AppState>,
+    app: AppHandle,
     transaction_type_str: String,
-    amount: f64,
+    amount: Decimal,
     description: String,
     store_name: String,
-) -> Result<Transaction, String> {
-    debug!("Received add_transaction_command: type={}, amount={}, desc='{}', store='{}'",
-           transaction_type_str, amount, description, store_name);
+    category: Option<String>,
+    subcategory: Option<String>,
+    currency: Option<String>,
+    tax_rate: Option<Decimal>,
+    tax_amount: Option<Decimal>,
+    tags: Option<Vec<String>>,
+    transaction_date: Option<NaiveDate>,
+    notes: Option<String>,
+    custom_fields: Option<HashMap<String, String>>,
+    payment_method: Option<PaymentMethod>,
+    contact_id: Option<String>,
+    account_id: Option<String>,
+    check_duplicates: Option<bool>,
+) -> Result<Transaction, AppError> {
+    debug!("Received add_transaction_command: type={}, store='{}'", transaction_type_str, store_name);
 
     let transaction_type = match transaction_type_str.as_str() {
         "Ingreso" => TransactionType::Ingreso,
         "Gasto" => TransactionType::Gasto,
         _ => {
             error!("Invalid transaction type received: {}", transaction_type_str);
-            return Err("Tipo de transacción inválido".to_string())
+            return Err(AppError::invalid_field("transaction_type_str", "Tipo de transacción inválido"))
         },
     };
 
-    if amount <= 0.0 {
-        error!("Invalid amount received: {}", amount);
-        return Err("El monto debe ser positivo.".to_string());
-    }
-    if description.trim().is_empty() || store_name.trim().is_empty() {
-        error!("Empty description or store name.");
-        return Err("La descripción y el nombre de la tienda no pueden estar vacíos.".to_string());
-    }
-
-    let new_transaction = Transaction {
-        id: uuid::Uuid::new_v4().to_string(),
+    let db = state.db().await?;
+    let new_transaction = build_new_transaction(&db, NewTransaction {
         transaction_type,
         amount,
-        description: description.trim().to_owned(),
-        store_name: store_name.trim().to_owned(),
-        timestamp: Utc::now().timestamp() as u64,
-    };
-
-    let transactions_to_save: Vec<Transaction>;
-
-    {
-        let mut transactions = state.transactions.lock().unwrap();
-        transactions.push(new_transaction.clone());
-        transactions_to_save = transactions.clone();
+        description,
+        store_name,
+        category,
+        subcategory,
+        currency,
+        tax_rate,
+        tax_amount,
+        tags,
+        transaction_date,
+        notes,
+        custom_fields,
+        payment_method,
+        contact_id,
+        account_id,
+    })?;
+    if check_duplicates.unwrap_or(false) {
+        let window_hours = duplicates::load_duplicate_window(&db);
+        let existing = duplicates::find_duplicates_of(&db, &new_transaction, window_hours)?;
+        if !existing.is_empty() {
+            return Err(duplicates::duplicate_error(&new_transaction, &existing));
+        }
     }
 
-    match save_transactions_to_file(&transactions_to_save).await {
+    match db.insert_transaction(&new_transaction) {
         Ok(_) => {
-            debug!("Transaction added and saved successfully: {:?}", new_transaction);
+            debug!("Transaction added and saved successfully: ID {}", new_transaction.id);
+            let changes = vec![Change::Insert(new_transaction.clone())];
+            audit::record_changes(db.connection(), "add_transaction_command", &changes);
+            events::emit_transaction_changes(&app, &changes);
+            state.history.lock().unwrap().record(HistoryEntry::new("Añadir transacción", changes));
+            budgets::check_budget_alerts(&db, &app);
             Ok(new_transaction)
         },
         Err(e) => {
-            error!("Failed to save transactions after adding: {}", e);
+            error!("Failed to save transaction: {}", e);
             Err(e)
         }
     }
 }
 
-/// Comando para actualizar una transacción existente.
+/// Resultado de una fila de `add_transactions_batch_command`: la transacción creada o
+/// el error que impidió crearla.
+#[derive(Debug, Serialize)]
+struct BatchRowResult {
+    index: usize,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    transaction: Option<Transaction>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<AppError>,
+}
+
+/// Número máximo de filas por lote.
+const MAX_BATCH_SIZE: usize = 5000;
+
+/// Comando para añadir varias transacciones con una sola llamada. Se validan todas
+/// las filas y, solo si ninguna tiene errores, se insertan todas a la vez en una
+/// única transacción de la base de datos (se deshace como un solo paso). Devuelve un
+/// resultado por fila, en el mismo orden; si alguna falla no se añade ninguna y las
+/// demás filas vuelven sin error ni transacción. Con `check_duplicates`, una fila
+/// parecida a una transacción ya guardada cuenta como error `duplicate`.
+#[tauri::command]
+async fn add_transactions_batch_command(
+    state: State<'_, AppState>,
+    app: AppHandle,
+    transactions: Vec<NewTransaction>,
+    check_duplicates: Option<bool>,
+) -> Result<Vec<BatchRowResult>, AppError> {
+    info!("Received add_transactions_batch_command with {} rows.", transactions.len());
+    if transactions.len() > MAX_BATCH_SIZE {
+        return Err(AppError::invalid_field(
+            "transactions",
+            format!("Un lote admite como máximo {} transacciones.", MAX_BATCH_SIZE),
+        ));
+    }
+
+    let db = state.db().await?;
+    let window_hours = check_duplicates.unwrap_or(false).then(|| duplicates::load_duplicate_window(&db));
+    let mut rows = Vec::with_capacity(transactions.len());
+    for input in transactions {
+        let row = build_new_transaction(&db, input).and_then(|transaction| {
+            if let Some(window_hours) = window_hours {
+                let existing = duplicates::find_duplicates_of(&db, &transaction, window_hours)?;
+                if !existing.is_empty() {
+                    return Err(duplicates::duplicate_error(&transaction, &existing));
+                }
+            }
+            Ok(transaction)
+        });
+        rows.push(row);
+    }
+
+    let failed = rows.iter().filter(|row| row.is_err()).count();
+    if failed > 0 {
+        info!("Batch rejected: {} of {} rows are invalid.", failed, rows.len());
+        return Ok(rows
+            .into_iter()
+            .enumerate()
+            .map(|(index, row)| BatchRowResult { index, transaction: None, error: row.err() })
+            .collect());
+    }
+
+    let new_transactions: Vec<Transaction> = rows.into_iter().filter_map(Result::ok).collect();
+    if !new_transactions.is_empty() {
+        db.insert_transactions(&new_transactions)?;
+        let changes: Vec<Change> = new_transactions.iter().cloned().map(Change::Insert).collect();
+        audit::record_changes(db.connection(), "add_transactions_batch_command", &changes);
+        events::emit_transaction_changes(&app, &changes);
+        state.history.lock().unwrap().record(HistoryEntry::new("Añadir transacciones", changes));
+        budgets::check_budget_alerts(&db, &app);
+    }
+    info!("Batch of {} transactions added.", new_transactions.len());
+    Ok(new_transactions
+        .into_iter()
+        .enumerate()
+        .map(|(index, transaction)| BatchRowResult { index, transaction: Some(transaction), error: None })
+        .collect())
+}
+
+/// Comando para actualizar una transacción existente. Los datos opcionales que no se
+/// indiquen conservan su valor; el IVA solo se quita con `clear_tax`.
 #[tauri::command]
 async fn update_transaction_command(
     state: State<'_, AppState>,
+    app: AppHandle,
     id: String,
     transaction_type_str: String,
-    amount: f64,
+    amount: Decimal,
     description: String,
     store_name: String,
-) -> Result<Transaction, String> {
+    category: Option<String>,
+    subcategory: Option<String>,
+    currency: Option<String>,
+    tax_rate: Option<Decimal>,
+    tax_amount: Option<Decimal>,
+    clear_tax: Option<bool>,
+    tags: Option<Vec<String>>,
+    transaction_date: Option<NaiveDate>,
+    notes: Option<String>,
+    custom_fields: Option<HashMap<String, String>>,
+    payment_method: Option<PaymentMethod>,
+    contact_id: Option<String>,
+    account_id: Option<String>,
+) -> Result<Transaction, AppError> {
     debug!("Received update_transaction_command for ID: {}", id);
-    let transaction_type = match transaction_type_str.as_str() {
-        "Ingreso" => TransactionType::Ingreso,
-        "Gasto" => TransactionType::Gasto,
+    let db = state.db().await?;
+    let mut transaction = match db.get_transaction(&id)? {
+        Some(t) => t,
+        None => {
+            error!("Transaction with ID {} not found for update.", id);
+            return Err(AppError::NotFound(format!("Transacción con ID {} no encontrada.", id)));
+        }
+    };
+    // Una transferencia mueve dinero entre dos tiendas o cuentas: cambiarla aquí a
+    // ingreso o gasto descuadraría los dos lados. Se registra con `add_transfer_command`.
+    if transaction.transaction_type == TransactionType::Transferencia {
+        error!("Transaction {} is a transfer; use add_transfer_command instead of updating it.", id);
+        return Err(AppError::invalid_field(
+            "transaction_type_str",
+            "Las transferencias no se pueden modificar como ingreso o gasto. Elimínala y regístrala de nuevo como transferencia.",
+        ));
+    }
+    let before = transaction.clone();
+
+    // Cada dato válido se aplica a `transaction`; si alguno falla se devuelven todos
+    // los errores y no se guarda nada.
+    let mut validator = Validator::new();
+    match transaction_type_str.as_str() {
+        "Ingreso" => transaction.transaction_type = TransactionType::Ingreso,
+        "Gasto" => transaction.transaction_type = TransactionType::Gasto,
         _ => {
             error!("Invalid transaction type received for update: {}", transaction_type_str);
-            return Err("Tipo de transacción inválido".to_string())
-        },
-    };
-
-    if amount <= 0.0 {
-        error!("Invalid amount received for update: {}", amount);
-        return Err("El monto debe ser positivo.".to_string());
+            validator.add("transaction_type_str", validation::INVALID, "Tipo de transacción inválido");
+        }
     }
-    if description.trim().is_empty() || store_name.trim().is_empty() {
-        error!("Empty description or store name for update.");
-        return Err("La descripción y el nombre de la tienda no pueden estar vacíos.".to_string());
+    let amount_valid = validator.check(money::validate_amount("amount", amount, "El monto debe ser positivo."))?.is_some()
+        && validator.check(splits::check_amount_change(db.connection(), &transaction, amount))?.is_some();
+    transaction.amount = amount;
+    if let Some(description) = validator.check(validation::required_text("description", &description, "La descripción no puede estar vacía."))? {
+        transaction.description = description;
     }
-
-    let updated_transaction_result: Result<Transaction, String>;
-    let transactions_to_save: Vec<Transaction>;
-
-    { // Inicia un nuevo scope para controlar la vida útil de `transactions_guard`
-        let mut transactions_guard = state.transactions.lock().unwrap();
-
-        if let Some(pos) = transactions_guard.iter().position(|t| t.id == id) {
-            let transaction = &mut transactions_guard[pos];
-            transaction.transaction_type = transaction_type;
-            transaction.amount = amount;
-            transaction.description = description.trim().to_owned();
-            transaction.store_name = store_name.trim().to_owned();
-            
-            updated_transaction_result = Ok(transaction.clone()); // Inicializar con Ok aquí
-            transactions_to_save = transactions_guard.clone(); // Clonar para guardar
-            debug!("Transaction found and updated in memory: ID {}", id);
+    if let Some(store_name) = validator.check(validation::required_text("store_name", &store_name, "El nombre de la tienda no puede estar vacío."))? {
+        transaction.store_name = store_name;
+    }
+    // Sin `category` ni `subcategory` se conserva la categoría; con una categoría vacía
+    // se quita. Con solo la subcategoría se busca en la categoría que ya tenía.
+    if category.is_some() || subcategory.is_some() {
+        let category = category.or_else(|| transaction.category.clone());
+        if let Some((category, subcategory)) =
+            validator.check(categories::resolve_transaction_category(db.connection(), category, subcategory))?
+        {
+            transaction.category = category;
+            transaction.subcategory = subcategory;
+        }
+    }
+    if let Some(code) = currency {
+        if let Some(currency) = validator.check(currencies::normalize_currency_code(&code))? {
+            transaction.currency = currency;
+        }
+    }
+    // Sin tipo ni cuota de IVA se conserva el tipo que tenía y la cuota se recalcula
+    // para el nuevo importe; para quitarlo hay que pedirlo con `clear_tax`.
+    if clear_tax.unwrap_or(false) {
+        if tax_rate.is_some() || tax_amount.is_some() {
+            validator.add("tax_rate", validation::INVALID, "No se puede indicar el IVA y quitarlo a la vez.");
         } else {
-            error!("Transaction with ID {} not found for update.", id);
-            updated_transaction_result = Err(format!("Transacción con ID {} no encontrada.", id));
-            transactions_to_save = transactions_guard.clone(); // Clonar el estado actual si no se encuentra
+            transaction.tax_rate = None;
+            transaction.tax_amount = None;
         }
-    } // `transactions_guard` se libera automáticamente aquí
-
-    // Si la transacción no se encontró, devuelve el error inmediatamente
-    if updated_transaction_result.is_err() {
-        return updated_transaction_result;
+    } else if amount_valid {
+        let tax_rate = match (tax_rate, tax_amount) {
+            (None, None) => transaction.tax_rate,
+            _ => tax_rate,
+        };
+        if let Some(tax) = validator.check(taxes::resolve_tax(amount, tax_rate, tax_amount))? {
+            (transaction.tax_rate, transaction.tax_amount) = tax;
+        }
+    }
+    // Sin `tags` se conservan las etiquetas que ya tenía.
+    if let Some(tags) = tags {
+        if let Some(tags) = validator.check(tags::resolve_tags(tags))? {
+            transaction.tags = tags;
+        }
+    }
+    // Igual con las notas, los campos personalizados y el medio de pago.
+    if let Some(notes) = notes {
+        if let Some(notes) = validator.check(validation::validate_notes(notes))? {
+            transaction.notes = notes;
+        }
+    }
+    if let Some(fields) = custom_fields {
+        if let Some(fields) = validator.check(validation::validate_custom_fields(fields))? {
+            transaction.custom_fields = fields;
+        }
+    }
+    if let Some(method) = payment_method {
+        transaction.payment_method = method;
+    }
+    // Sin `contact_id` se conserva el contacto; con un ID vacío se quita.
+    if let Some(contact_id) = contact_id {
+        if let Some(contact_id) = validator.check(contacts::resolve_contact_id(db.connection(), Some(contact_id)))? {
+            transaction.contact_id = contact_id;
+        }
+    }
+    // Igual con la cuenta.
+    if let Some(account_id) = account_id {
+        if let Some(account_id) =
+            validator.check(accounts::resolve_account_id(db.connection(), "account_id", Some(account_id)))?
+        {
+            transaction.account_id = account_id;
+        }
+    }
+    // Si la fecha no cambia se conserva la hora original.
+    if let Some(date) = transaction_date.filter(|d| *d != transaction.transaction_date) {
+        if let Some(date) = validator.check(validation::validate_transaction_date(date))? {
+            transaction.transaction_date = date;
+            transaction.timestamp = periods::timestamp_for_date(date);
+        }
+    }
+    validator.check(validation::enforce_policy(&db, &transaction))?;
+    if !validator.is_empty() {
+        error!("Invalid update for transaction {}: {:?}", id, validator);
     }
+    validator.finish()?;
+    transaction.updated_at = periods::now_timestamp();
 
-    // Si se encontró y actualizó, guarda los cambios y devuelve el resultado
-    match save_transactions_to_file(&transactions_to_save).await {
+    match db.update_transaction(&transaction) {
         Ok(_) => {
-            debug!("Transactions saved after update.");
-            updated_transaction_result
+            debug!("Transaction updated and saved: ID {}", id);
+            let changes = vec![Change::Update { before, after: transaction.clone() }];
+            audit::record_changes(db.connection(), "update_transaction_command", &changes);
+            events::emit_transaction_changes(&app, &changes);
+            state.history.lock().unwrap().record(HistoryEntry::new("Editar transacción", changes));
+            budgets::check_budget_alerts(&db, &app);
+            Ok(transaction)
         },
         Err(e) => {
-            error!("Failed to save transactions after update: {}", e);
+            error!("Failed to save transaction after update: {}", e);
             Err(e)
         }
     }
 }
 
-/// Comando para eliminar una transacción.
+/// Comando para eliminar una transacción. Por defecto se mueve a la papelera;
+/// con `permanent` se borra definitivamente junto con sus adjuntos.
 #[tauri::command]
-async fn delete_transaction_command(state: State<'_, AppState>, id: String) -> Result<(), String> {
-    debug!("Received delete_transaction_command for ID: {}", id);
-    let transactions_to_save: Vec<Transaction>;
-    let mut found = false;
-
-    {
-        let mut transactions = state.transactions.lock().unwrap();
-        let initial_len = transactions.len();
-        transactions.retain(|t| t.id != id);
-        if transactions.len() < initial_len {
-            found = true;
-        }
-        transactions_to_save = transactions.clone();
-    }
-
-    if found {
-        match save_transactions_to_file(&transactions_to_save).await {
-            Ok(_) => {
-                debug!("Transaction deleted and saved successfully: ID {}", id);
-                Ok(())
-            },
-            Err(e) => {
-                error!("Failed to save transactions after deletion: {}", e);
-                Err(e)
-            }
+async fn delete_transaction_command(
+    state: State<'_, AppState>,
+    app: AppHandle,
+    id: String,
+    permanent: Option<bool>,
+) -> Result<(), AppError> {
+    debug!("Received delete_transaction_command for ID: {} (permanent: {:?})", id, permanent);
+
+    let db = state.db().await?;
+    let existing = match db.get_transaction(&id)? {
+        Some(t) => t,
+        None => {
+            error!("Transaction with ID {} not found for deletion.", id);
+            return Err(AppError::NotFound(format!("Transacción con ID {} no encontrada.", id)));
         }
+    };
+
+    let change = if permanent.unwrap_or(false) {
+        db.delete_transaction(&id)?;
+        attachments::remove_transaction_attachments(&id);
+        Change::Delete(existing)
     } else {
-        error!("Transaction with ID {} not found for deletion.", id);
-        Err(format!("Transacción con ID {} no encontrada.", id))
-    }
+        trash::move_to_trash(&db, existing)?
+    };
+    let changes = vec![change];
+    audit::record_changes(db.connection(), "delete_transaction_command", &changes);
+    events::emit_transaction_changes(&app, &changes);
+    state.history.lock().unwrap().record(HistoryEntry::new("Eliminar transacción", changes));
+    debug!("Transaction deleted successfully: ID {}", id);
+    Ok(())
 }
 
-/// Comando para obtener la lista de tiendas únicas.
+/// Comando para obtener las tiendas, con sus datos y su número de transacciones,
+/// ordenadas por nombre. No incluye la entrada 'Todas las Tiendas' de la interfaz.
 #[tauri::command]
-async fn get_unique_stores(state: State<'_, AppState>) -> Result<Vec<String>, String> {
+async fn get_unique_stores(state: State<'_, AppState>) -> Result<Vec<stores::Store>, AppError> {
     debug!("Received get_unique_stores command.");
-    let transactions = state.transactions.lock().unwrap();
-    let mut unique_stores: HashSet<String> = transactions.iter()
-        .map(|t| t.store_name.clone())
-        .collect();
-    unique_stores.insert("Todas las Tiendas".to_string());
-    let mut sorted_stores: Vec<String> = unique_stores.into_iter().collect();
-    sorted_stores.sort_unstable();
-    debug!("Returning unique stores: {:?}", sorted_stores);
-    Ok(sorted_stores)
+    let stores = stores::list_stores(state.db().await?.connection())?;
+    debug!("Returning {} stores.", stores.len());
+    Ok(stores)
 }
 
 /// Comando para obtener un mapa de tiendas y el número de transacciones asociadas.
 #[tauri::command]
-async fn get_store_info_command(state: State<'_, AppState>) -> Result<HashMap<String, usize>, String> {
+async fn get_store_info_command(state: State<'_, AppState>) -> Result<HashMap<String, usize>, AppError> {
     debug!("Received get_store_info_command.");
-    let transactions = state.transactions.lock().unwrap();
-    let mut store_counts: HashMap<String, usize> = HashMap::new();
-
-    for transaction in transactions.iter() {
-        *store_counts.entry(transaction.store_name.clone()).or_insert(0) += 1;
-    }
+    let store_counts = state.db().await?.store_transaction_counts()?;
     debug!("Returning store info: {:?}", store_counts);
     Ok(store_counts)
 }
 
-/// Comando para renombrar una tienda.
+/// Totales de ingresos y gastos convertidos a la moneda base.
+#[derive(Debug, Serialize)]
+struct Summary {
+    base_currency: String,
+    total_income: Decimal,
+    total_expenses: Decimal,
+    balance: Decimal,
+    transaction_count: usize,
+}
+
+/// Comando para obtener el resumen de ingresos, gastos y balance en la moneda base,
+/// para todas las tiendas o solo para `store_name`.
+#[tauri::command]
+async fn get_summary_command(
+    state: State<'_, AppState>,
+    store_name: Option<String>,
+) -> Result<Summary, AppError> {
+    debug!("Received get_summary_command for store: {:?}", store_name);
+    let store_filter = store_name
+        .map(|s| s.trim().to_owned())
+        .filter(|s| !s.is_empty() && s != "Todas las Tiendas");
+
+    let db = state.db().await?;
+    let rates = currencies::RateTable::load(&db)?;
+    let transactions = db.list_transactions()?;
+
+    let mut summary = Summary {
+        base_currency: rates.base_currency.clone(),
+        total_income: Decimal::ZERO,
+        total_expenses: Decimal::ZERO,
+        balance: Decimal::ZERO,
+        transaction_count: 0,
+    };
+    for transaction in transactions.iter()
+        .filter(|t| store_filter.as_ref().map_or(true, |s| &t.store_name == s))
+    {
+        let amount = rates.to_base(transaction.amount, &transaction.currency)?;
+        match transaction.transaction_type {
+            TransactionType::Ingreso => summary.total_income += amount,
+            TransactionType::Gasto => summary.total_expenses += amount,
+            // Solo cambian el balance de una tienda; entre todas se compensan.
+            TransactionType::Transferencia => {
+                if store_filter.is_some() {
+                    summary.balance -= amount;
+                }
+            }
+        }
+        summary.transaction_count += 1;
+    }
+    // Transferencias recibidas por la tienda filtrada.
+    if let Some(store) = &store_filter {
+        for transaction in transactions.iter().filter(|t| t.transfer_store.as_ref() == Some(store)) {
+            summary.balance += rates.to_base(transaction.amount, &transaction.currency)?;
+        }
+    }
+    summary.balance += summary.total_income - summary.total_expenses;
+    debug!("Returning summary for {} transactions.", summary.transaction_count);
+    Ok(summary)
+}
+
+/// Comando para renombrar una tienda, con sus transacciones y sus datos. Si ya
+/// existía una tienda con el nuevo nombre, ambas se fusionan.
 #[tauri::command]
 async fn rename_store_command(
     state: State<'_, AppState>,
+    app: AppHandle,
     old_store_name: String,
     new_store_name: String,
-) -> Result<(), String> {
+) -> Result<(), AppError> {
     debug!("Received rename_store_command: old='{}', new='{}'", old_store_name, new_store_name);
     let trimmed_old_name = old_store_name.trim();
     let trimmed_new_name = new_store_name.trim();
 
     if trimmed_old_name.is_empty() || trimmed_new_name.is_empty() {
         error!("Rename store: Empty old or new store name.");
-        return Err("Los nombres de tienda no pueden estar vacíos.".to_string());
+        return Err(AppError::validation("Los nombres de tienda no pueden estar vacíos."));
     }
     if trimmed_old_name == "Todas las Tiendas" {
         error!("Rename store: Attempted to rename 'Todas las Tiendas'.");
-        return Err("No se puede renombrar 'Todas las Tiendas'.".to_string());
+        return Err(AppError::invalid_field("old_store_name", "No se puede renombrar 'Todas las Tiendas'."));
     }
     if trimmed_old_name == trimmed_new_name {
         debug!("Rename store: New name is same as old name. No operation needed.");
-        return Err("El nuevo nombre de la tienda es el mismo que el anterior.".to_string());
+        return Err(AppError::invalid_field("new_store_name", "El nuevo nombre de la tienda es el mismo que el anterior."));
     }
 
-    let transactions_to_save: Vec<Transaction>;
-    let mut renamed_count = 0;
-
-    {
-        let mut transactions = state.transactions.lock().unwrap();
-        for transaction in transactions.iter_mut() {
-            if transaction.store_name == trimmed_old_name {
-                transaction.store_name = trimmed_new_name.to_owned();
-                renamed_count += 1;
-            }
-        }
-        transactions_to_save = transactions.clone();
-    }
-
-    if renamed_count > 0 {
-        match save_transactions_to_file(&transactions_to_save).await {
-            Ok(_) => {
-                debug!("Renamed {} transactions from '{}' to '{}'. Saved successfully.", renamed_count, trimmed_old_name, trimmed_new_name);
-                Ok(())
-            },
-            Err(e) => {
-                error!("Failed to save transactions after renaming: {}", e);
-                Err(e)
-            }
-        }
+    let db = state.db().await?;
+    let subject = format!("La tienda '{}'", trimmed_old_name);
+    closing::ensure_not_closed(db.connection(), "store_name = ?1", [trimmed_old_name], &subject)?;
+    let affected = db.list_transactions_by_store(trimmed_old_name)?;
+    let tx = db.connection().unchecked_transaction().map_err(storage::db_error)?;
+    let renamed_count = db.rename_store(trimmed_old_name, trimmed_new_name)?;
+    let renamed_record = stores::rename_store_record(db.connection(), trimmed_old_name, trimmed_new_name)?;
+    tx.commit().map_err(storage::db_error)?;
+
+    if renamed_count > 0 || renamed_record {
+        let changes: Vec<Change> = affected
+            .into_iter()
+            .map(|before| {
+                let mut after = before.clone();
+                after.store_name = trimmed_new_name.to_owned();
+                Change::Update { before, after }
+            })
+            .collect();
+        audit::record_changes(db.connection(), "rename_store_command", &changes);
+        events::emit_transaction_changes(&app, &changes);
+        // `changes` no incluye las transferencias hacia la tienda, también renombradas.
+        aggregates::invalidate();
+        state.history.lock().unwrap().record(HistoryEntry::new("Renombrar tienda", changes));
+        events::emit(&app, events::STORE_RENAMED_EVENT, events::StoreRenamed {
+            old_name: trimmed_old_name.to_owned(),
+            new_name: trimmed_new_name.to_owned(),
+        });
+        debug!("Renamed {} transactions from '{}' to '{}'. Saved successfully.", renamed_count, trimmed_old_name, trimmed_new_name);
+        Ok(())
     } else {
         debug!("Rename store: Old store name '{}' not found or no transactions to rename.", trimmed_old_name);
-        Err(format!("Tienda '{}' no encontrada o sin transacciones para renombrar.", trimmed_old_name))
+        Err(AppError::NotFound(format!("Tienda '{}' no encontrada o sin transacciones para renombrar.", trimmed_old_name)))
     }
 }
 
-/// Comando para eliminar una tienda y todas sus transacciones.
+/// Comando para eliminar una tienda, sus datos y todas sus transacciones. Por defecto las
+/// transacciones se mueven a la papelera; con `permanent` se borran definitivamente,
+/// incluidas las que ya estaban en la papelera.
 #[tauri::command]
 async fn delete_store_command(
     state: State<'_, AppState>,
+    app: AppHandle,
     store_name: String,
-) -> Result<(), String> {
-    debug!("Received delete_store_command for store: '{}'", store_name);
+    permanent: Option<bool>,
+) -> Result<(), AppError> {
+    debug!("Received delete_store_command for store: '{}' (permanent: {:?})", store_name, permanent);
     let trimmed_store_name = store_name.trim();
 
     if trimmed_store_name.is_empty() {
         error!("Delete store: Empty store name provided.");
-        return Err("El nombre de tienda no puede estar vacío.".to_string());
+        return Err(AppError::invalid_field("store_name", "El nombre de tienda no puede estar vacío."));
     }
     if trimmed_store_name == "Todas las Tiendas" {
         error!("Delete store: Attempted to delete 'Todas las Tiendas'.");
-        return Err("No se puede eliminar 'Todas las Tiendas'.".to_string());
+        return Err(AppError::invalid_field("store_name", "No se puede eliminar 'Todas las Tiendas'."));
     }
 
-    let transactions_to_save: Vec<Transaction>;
-    let initial_len;
-
-    {
-        let mut transactions = state.transactions.lock().unwrap();
-        initial_len = transactions.len();
-        transactions.retain(|t| t.store_name != trimmed_store_name);
-        transactions_to_save = transactions.clone();
-    }
-
-    if transactions_to_save.len() < initial_len {
-        match save_transactions_to_file(&transactions_to_save).await {
-            Ok(_) => {
-                debug!("Deleted transactions for store '{}'. Saved successfully.", trimmed_store_name);
-                Ok(())
-            },
-            Err(e) => {
-                error!("Failed to save transactions after deleting store: {}", e);
-                Err(e)
-            }
+    let db = state.db().await?;
+    let subject = format!("La tienda '{}'", trimmed_store_name);
+    closing::ensure_not_closed(db.connection(), "store_name = ?1", [trimmed_store_name], &subject)?;
+    let affected = db.list_transactions_by_store(trimmed_store_name)?;
+    if !affected.is_empty() {
+        backup::snapshot_before(&db, backup::REASON_DELETE_STORE)?;
+    }
+    // Los datos de la tienda se borran junto con sus transacciones.
+    let permanent = permanent.unwrap_or(false);
+    let tx = db.connection().unchecked_transaction().map_err(storage::db_error)?;
+    let deleted_record = stores::delete_store_record(db.connection(), trimmed_store_name)?;
+    let (deleted_count, changes) = if permanent {
+        // El historial solo puede reinsertar las activas; las de la papelera se pierden.
+        let deleted_count = db.delete_store(trimmed_store_name)?;
+        (deleted_count, affected.into_iter().map(Change::Delete).collect::<Vec<_>>())
+    } else {
+        let changes = affected
+            .into_iter()
+            .map(|t| trash::move_to_trash(&db, t))
+            .collect::<Result<Vec<_>, _>>()?;
+        (changes.len(), changes)
+    };
+    tx.commit().map_err(storage::db_error)?;
+    if permanent && deleted_count > 0 {
+        if let Err(e) = attachments::cleanup_orphan_attachments(&db) {
+            log::warn!("No se pudieron limpiar los adjuntos huérfanos: {}", e);
         }
+    }
+    audit::record_changes(db.connection(), "delete_store_command", &changes);
+    events::emit_transaction_changes(&app, &changes);
+    if permanent {
+        // `delete_store` también borra las transferencias hacia la tienda, que no están en `changes`.
+        aggregates::invalidate();
+    }
+    state.history.lock().unwrap().record(HistoryEntry::new("Eliminar tienda", changes));
+
+    if deleted_count > 0 || deleted_record {
+        events::emit(&app, events::STORE_DELETED_EVENT, trimmed_store_name);
+        debug!("Deleted {} transactions for store '{}'. Saved successfully.", deleted_count, trimmed_store_name);
+        Ok(())
     } else {
         debug!("Delete store: Store '{}' not found or no transactions to delete.", trimmed_store_name);
-        Err(format!("Tienda '{}' no encontrada o sin transacciones para eliminar.", trimmed_store_name))
+        Err(AppError::NotFound(format!("Tienda '{}' no encontrada o sin transacciones para eliminar.", trimmed_store_name)))
     }
 }
 
-/// Comando para llamar a la API de Google Gemini.
+/// Comando para restaurar la base de datos desde la última copia `.bak` verificada.
+/// Devuelve qué copia se restauró, dónde quedó la base de datos anterior y cuántas
+/// transacciones contiene ahora.
 #[tauri::command]
-async fn call_gemini_api_command(prompt: String) -> Result<String, String> {
-    info!("Received call_gemini_api_command.");
-    let api_key = env::var("GEMINI_API_KEY")
-        .map_err(|_| {
-            error!("GEMINI_API_KEY environment variable not configured.");
-            "La variable de entorno GEMINI_API_KEY no está configurada.".to_string()
-        })?;
-    // Changed model to gemini-1.5-flash-latest
-    let api_url = format!("https://generativelanguage.googleapis.com/v1beta/models/gemini-1.5-flash-latest:generateContent?key={}", api_key);
-
-    let client = Client::new();
-    let chat_history = json!([
-        {
-            "role": "user",
-            "parts": [{"text": prompt}]
-        }
-    ]);
-
-    let payload = json!({
-        "contents": chat_history
-    });
-
-    debug!("Enviando solicitud a Gemini API");
-
-    let response = client.post(&api_url)
-        .header("Content-Type", "application/json")
-        .json(&payload)
-        .send()
-        .await
-        .map_err(|e| {
-            error!("Network error connecting to Gemini: {}", e);
-            format!("Error de red al conectar con Gemini: {}", e)
-        })?;
-
-    let response_json: serde_json::Value = response.json().await
-        .map_err(|e| {
-            error!("Error reading Gemini JSON response: {}", e);
-            format!("Error al leer respuesta JSON de Gemini: {}", e)
-        })?;
-
-    debug!("Respuesta de Gemini API: {:?}", response_json);
-
-    // More robust parsing for Gemini API response
-    if let Some(candidates) = response_json.get("candidates").and_then(|c| c.as_array()) {
-        if let Some(first_candidate) = candidates.get(0) {
-            if let Some(content) = first_candidate.get("content").and_then(|c| c.as_object()) {
-                if let Some(parts) = content.get("parts").and_then(|p| p.as_array()) {
-                    if let Some(first_part) = parts.get(0) {
-                        if let Some(text) = first_part.get("text").and_then(|t| t.as_str()) {
-                            info!("Gemini API call successful.");
-                            return Ok(text.to_string());
-                        }
-                    }
+async fn recover_data_command(state: State<'_, AppState>, app: AppHandle) -> Result<RecoveryReport, AppError> {
+    info!("Received recover_data_command.");
+    let mut db = state.db().await?;
+
+    // Cerramos la conexión actual antes de tocar el archivo.
+    let key = db.encryption_key().cloned();
+    let placeholder = SqliteStorage::open_in_memory()?;
+    drop(std::mem::replace(&mut *db, placeholder));
+
+    let db_path = storage::get_database_path();
+    match storage::restore_from_backup(&db_path, &storage::get_backup_path(), key.clone()) {
+        Ok((restored, report)) => {
+            *db = restored;
+            i18n::apply_settings(&db);
+            state.history.lock().unwrap().clear();
+            // La copia restaurada trae su propio log; dejamos constancia de la restauración en él.
+            audit::record(db.connection(), "recover_data_command", None, None, audit::snapshot(&report));
+            events::emit_data_reloaded(&app);
+            Ok(report)
+        },
+        Err(e) => {
+            error!("Manual recovery failed: {}", e);
+            // La base de datos original sigue en su sitio: se vuelve a abrir para no
+            // dejar la app sin datos. Si tampoco se puede, se bloquea el acceso para
+            // que ningún comando trabaje sobre la base de datos vacía provisional, y
+            // se informa del fallo de la recuperación, que es el que ha pedido el usuario.
+            match SqliteStorage::open_path(&db_path, key) {
+                Ok(original) => *db = original,
+                Err(reopen) => {
+                    error!("Could not reopen the original database after a failed recovery: {}", reopen);
+                    state.set_locked(true);
                 }
             }
+            Err(e)
         }
     }
-    error!("Could not extract text from Gemini AI response. Full response: {:?}", response_json);
-    Err("No se pudo extraer el texto de la respuesta de la IA.".to_string())
 }
 
-
-/// Formatea un número f64 al estilo de moneda español (es-EA).
+/// Comando para llamar a la IA (Google Gemini o el modelo local de los ajustes).
+/// La respuesta se emite progresivamente con los eventos `gemini://chunk` y
+/// `gemini://done` (identificados por `request_id`) y además se devuelve completa.
+/// Se usa el proveedor y el modelo de los ajustes salvo que se indique `model`; `params` permite
+/// fijar la temperatura, el máximo de tokens y los umbrales de seguridad.
 #[tauri::command]
-fn format_currency_es_ea_command(amount: f64) -> String {
-    debug!("Formatting currency: {}", amount);
-    let s = format!("{:.2}", amount.abs());
-    let parts: Vec<&str> = s.split('.').collect();
-
-    let integer_part_str = parts[0];
-    let decimal_part_str = if parts.len() > 1 { parts[1] } else { "00" };
-
-    let mut formatted_integer = String::new();
-    // Corrected logic for thousands separator: iterate in reverse and insert at front
-    for (i, c) in integer_part_str.chars().rev().enumerate() {
-        if i > 0 && i % 3 == 0 {
-            formatted_integer.insert(0, '.');
-        }
-        formatted_integer.insert(0, c);
+async fn call_gemini_api_command(
+    state: State<'_, AppState>,
+    app: AppHandle,
+    prompt: String,
+    request_id: Option<String>,
+    model: Option<String>,
+    params: Option<ai::GenerationParams>,
+) -> Result<String, AppError> {
+    info!("Received call_gemini_api_command.");
+    let params = params.unwrap_or_default();
+    params.validate()?;
+    let settings = settings::load_settings(&state.db().await?);
+    ai::ensure_enabled(&settings, Some(ai::AiFeature::Chat))?;
+    let mut client = settings.ai_client();
+    if let Some(model) = model.map(|m| m.trim().to_owned()).filter(|m| !m.is_empty()) {
+        client.set_model(model)?;
     }
+    let request_id = request_id.unwrap_or_else(|| uuid::Uuid::new_v4().to_string());
+    ai::stream_to_frontend(&app, &state, &request_id, &client, &[ai::AiMessage::user(prompt)], &params).await
+}
 
-    let final_string = format!("{},{}", formatted_integer, decimal_part_str);
 
-    if amount < 0.0 {
-        format!("-{}", final_string)
-    } else {
-        final_string
-    }
+/// Formatea un importe al estilo de moneda español (es-EA), sin tener en cuenta los
+/// ajustes. Para el formato configurado está `settings::format_currency_command`.
+#[tauri::command]
+fn format_currency_es_ea_command(amount: Decimal) -> String {
+    debug!("Formatting currency: {}", amount);
+    settings::Settings::default().format_number(amount)
 }
 
 
@@ -527,54 +1184,289 @@ fn format_currency_es_ea_command(amount: f64) -> String {
 
 #[tokio::main]
 async fn main() -> Result<(), Box<dyn std::error::Error>> {
-    dotenv::dotenv().ok();
-    log::info!("Tauri backend starting. Loading initial transactions...");
-
-    let initial_transactions = match load_transactions_from_file().await {
-        Ok(t) => t,
+    // Con `--export`, `--import` o `--backup` se trabaja sin abrir la ventana (ver `cli`).
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    match cli::parse_args(&args) {
+        Ok(Some(command)) => std::process::exit(cli::run(command)),
+        Ok(None) => {}
         Err(e) => {
-            log::error!("Error al cargar transacciones: {}. Se iniciará con datos vacías.", e);
-            Vec::new()
+            eprintln!("Error: {}\nUsa --help para ver las opciones.", e);
+            std::process::exit(2);
         }
+    }
+    // Enlaces y archivos abiertos con la aplicación ya en marcha: los atiende ella.
+    if instance::forward_to_running_instance(&args) {
+        return Ok(());
+    }
+    startup::mark_launch();
+    shutdown::mark_session_started();
+    dotenv::dotenv().ok();
+    log::info!("Tauri backend starting. Opening database...");
+
+    let workspace = workspaces::activate_saved_workspace();
+    let db_path = storage::get_database_path();
+    let locked = encryption::is_encrypted_file(&db_path);
+    let db = if locked {
+        // Los datos cifrados se abren con `unlock_data_command`; hasta entonces los
+        // comandos devuelven el error `locked`.
+        log::info!("Datos cifrados: la aplicación arranca bloqueada.");
+        SqliteStorage::open_in_memory()?
+    } else {
+        let (db, recovery) = storage::open_with_recovery(&db_path, &storage::get_backup_path())?;
+        if let Some(report) = recovery {
+            log::warn!("La base de datos se restauró automáticamente desde la copia de seguridad: {:?}", report);
+        }
+        db
     };
 
-    let app_state = if initial_transactions.is_empty() {
-        let mut transactions = Vec::new();
-        transactions.push(Transaction {
-            id: uuid::Uuid::new_v4().to_string(),
-            transaction_type: TransactionType::Ingreso,
-            amount: 10.00,
-            description: "Transacción inicial de prueba (Rust)".to_string(),
-            store_name: "Tienda de Prueba (Rust)".to_string(),
-            timestamp: chrono::Utc::now().timestamp() as u64,
-        });
-        log::info!("Añadida una transacción de prueba inicial.");
-        AppState { transactions: std::sync::Mutex::new(transactions) }
-    } else {
-        AppState { transactions: std::sync::Mutex::new(initial_transactions) }
+    i18n::apply_settings(&db);
+    logging::apply_settings(&settings::load_settings(&db));
+    logging::prune_old_logs();
+    let history = CommandHistory::new(history::load_history_depth(&db));
+    startup::mark_opened();
+    let app_state = AppState {
+        db: tokio::sync::Mutex::new(db),
+        history: std::sync::Mutex::new(history),
+        locked: AtomicBool::new(locked),
+        workspace: std::sync::Mutex::new(workspace),
     };
 
     tauri::Builder::default()
         .plugin(tauri_plugin_shell::init())
-        .plugin(
-            tauri_plugin_log::Builder::default()
-                .level(log::LevelFilter::Debug) // Configure log level to Debug
-                .build()
-        )
+        .plugin(tauri_plugin_notification::init())
+        .plugin(logging::plugin())
         .manage(app_state)
+        .setup(|app| {
+            startup::spawn_deferred_load(app.handle().clone());
+            autosave::spawn_autosave(app.handle().clone());
+            ai_queue::spawn_worker(app.handle().clone());
+            lan_sync::spawn_service(app.handle().clone());
+            api_server::spawn_service(app.handle().clone());
+            webhooks::spawn_worker(app.handle().clone());
+            scheduler::spawn_runner(app.handle().clone());
+            shutdown::setup(app.handle());
+            tray::setup_tray(app.handle())?;
+            opened_files::open_launch_args(app.handle());
+            deep_links::open_launch_args(app.handle());
+            deep_links::register_scheme();
+            instance::spawn_listener(app.handle().clone());
+            Ok(())
+        })
+        .on_window_event(|window, event| {
+            tray::handle_window_event(window, event);
+            opened_files::handle_window_event(window, event);
+        })
         .invoke_handler(tauri::generate_handler![
             get_all_transactions,
+            query_transactions_command,
             add_transaction_command,
+            add_transactions_batch_command,
+            bulk::bulk_update_transactions_command,
+            bulk::bulk_delete_transactions_command,
             update_transaction_command,
             delete_transaction_command,
             get_unique_stores,
             call_gemini_api_command,
             format_currency_es_ea_command,
+            settings::get_settings_command,
+            settings::update_settings_command,
+            settings::format_currency_command,
+            validation::get_validation_policy_command,
+            logging::export_logs_command,
+            startup::get_startup_status_command,
+            diagnostics::get_diagnostics_command,
+            integrity::verify_data_integrity_command,
+            integrity::repair_data_command,
             get_store_info_command,
+            stores::create_store_command,
+            stores::update_store_command,
+            contacts::list_contacts_command,
+            contacts::create_contact_command,
+            contacts::update_contact_command,
+            contacts::delete_contact_command,
+            contacts::get_revenue_by_client_command,
+            summaries::generate_ai_monthly_summary_command,
+            anomalies::detect_anomalies_command,
+            forecast::forecast_cashflow_command,
             rename_store_command,
-            delete_store_command
+            stores::merge_stores_command,
+            stores::get_store_balances_command,
+            delete_store_command,
+            categories::get_categories,
+            categories::add_category_command,
+            categories::rename_category_command,
+            categories::delete_category_command,
+            get_summary_command,
+            currencies::get_exchange_rates_command,
+            currencies::set_exchange_rate_command,
+            currencies::set_base_currency_command,
+            recover_data_command,
+            budgets::set_budget_command,
+            budgets::get_budget_status_command,
+            budgets::delete_budget_command,
+            goals::set_goal_command,
+            goals::get_goal_progress_command,
+            goals::delete_goal_command,
+            attachments::attach_receipt_command,
+            attachments::list_receipts_command,
+            attachments::delete_receipt_command,
+            receipts::extract_receipt_command,
+            text_entry::parse_transaction_text_command,
+            speech::transcribe_and_parse_command,
+            speech::set_whisper_api_key_command,
+            assistant::ask_accounting_question_command,
+            chat::start_chat_session_command,
+            chat::send_chat_message_command,
+            chat::get_chat_history_command,
+            chat::list_chat_sessions_command,
+            chat::delete_chat_session_command,
+            reports::get_profit_loss_report_command,
+            reports::get_time_series_command,
+            reports::get_running_balance_command,
+            reports::get_comparison_report_command,
+            reports::export_comparison_report_command,
+            reports::get_spending_heatmap_command,
+            history::undo_command,
+            history::redo_command,
+            history::get_history_status_command,
+            history::set_history_depth_command,
+            trash::list_trash_command,
+            trash::restore_transaction_command,
+            trash::purge_trash_command,
+            audit::get_audit_log_command,
+            encryption::set_encryption_passphrase_command,
+            encryption::unlock_data_command,
+            encryption::get_encryption_status_command,
+            gemini::set_api_key_command,
+            gemini::has_api_key_command,
+            ai::list_available_models_command,
+            prompts::list_prompt_templates_command,
+            prompts::set_prompt_template_command,
+            privacy::preview_ai_payload_command,
+            ai_usage::get_ai_usage_command,
+            ai_cache::get_ai_cache_stats_command,
+            ai_cache::clear_ai_cache_command,
+            ai_queue::list_ai_queue_command,
+            ai_queue::process_ai_queue_command,
+            ai_queue::delete_ai_request_command,
+            ai_queue::get_connectivity_command,
+            backup::list_backups_command,
+            backup::create_backup_command,
+            backup::restore_backup_command,
+            dashboard::get_dashboard_summary_command,
+            dashboard::get_top_expenses_command,
+            dashboard::get_top_stores_command,
+            workspaces::list_workspaces_command,
+            workspaces::create_workspace_command,
+            workspaces::switch_workspace_command,
+            invoices::create_invoice_command,
+            invoices::list_invoices_command,
+            invoices::mark_invoice_paid_command,
+            invoices::export_invoice_pdf_command,
+            tickets::print_receipt_command,
+            tickets::print_daily_summary_command,
+            taxes::get_tax_report_command,
+            taxes::get_ai_tax_tips_command,
+            payments::get_payment_method_totals_command,
+            tags::get_all_tags_command,
+            tags::rename_tag_command,
+            tags::delete_tag_command,
+            search::search_transactions_command,
+            duplicates::find_duplicates_command,
+            duplicates::set_duplicate_window_command,
+            import::import_bank_statement_command,
+            import::import_transactions_xlsx_command,
+            import::preview_import_file_command,
+            import::import_file_command,
+            opened_files::take_opened_files_command,
+            deep_links::take_pending_deep_links_command,
+            autosave::flush_command,
+            windows::open_quick_entry_window_command,
+            tray::open_month_report_command,
+            tray::quit_app_command,
+            notifications::get_pending_alerts_command,
+            closing::list_closed_periods_command,
+            closing::close_period_command,
+            closing::reopen_period_command,
+            cash_closing::start_closing_command,
+            cash_closing::complete_closing_command,
+            cash_closing::list_cash_closings_command,
+            cash_closing::reopen_cash_closing_command,
+            cash_closing::export_cash_closing_pdf_command,
+            archive::export_workspace_command,
+            archive::import_workspace_command,
+            sync::set_sync_credentials_command,
+            sync::get_sync_status_command,
+            sync::sync_now_command,
+            lan_sync::discover_devices_command,
+            lan_sync::start_pairing_command,
+            lan_sync::pair_device_command,
+            lan_sync::list_paired_devices_command,
+            lan_sync::unpair_device_command,
+            lan_sync::lan_sync_now_command,
+            lan_sync::get_lan_sync_log_command,
+            merge::resolve_conflicts_command,
+            api_server::get_api_server_status_command,
+            api_server::regenerate_api_token_command,
+            webhooks::test_webhook_command,
+            webhooks::get_webhook_secret_command,
+            webhooks::regenerate_webhook_secret_command,
+            webhooks::list_webhook_deliveries_command,
+            email::set_smtp_password_command,
+            email::send_report_email_command,
+            scheduler::list_jobs_command,
+            scheduler::run_job_now_command,
+            scheduler::update_job_command,
+            banking::set_banking_credentials_command,
+            banking::list_bank_institutions_command,
+            banking::link_bank_account_command,
+            banking::list_bank_accounts_command,
+            banking::set_bank_account_store_command,
+            banking::unlink_bank_command,
+            banking::fetch_bank_transactions_command,
+            banking::list_staged_bank_transactions_command,
+            banking::list_bank_mapping_rules_command,
+            banking::set_bank_mapping_rules_command,
+            banking::import_staged_bank_transactions_command,
+            rules::list_rules_command,
+            rules::create_rule_command,
+            rules::update_rule_command,
+            rules::delete_rule_command,
+            rules::reorder_rules_command,
+            rules::test_rule_command,
+            rules::apply_rules_retroactively_command,
+            templates::list_templates_command,
+            templates::create_template_command,
+            templates::update_template_command,
+            templates::delete_template_command,
+            templates::create_from_template_command,
+            splits::get_transaction_splits_command,
+            splits::set_transaction_splits_command,
+            transfers::add_transfer_command,
+            accounts::list_accounts_command,
+            accounts::create_account_command,
+            accounts::update_account_command,
+            accounts::delete_account_command,
+            accounts::get_account_balances_command,
+            petty_cash::get_petty_cash_command,
+            petty_cash::set_petty_cash_command,
+            petty_cash::replenish_petty_cash_command,
+            petty_cash::get_petty_cash_report_command,
+            users::list_users_command,
+            users::create_user_command,
+            users::update_user_command,
+            users::delete_user_command,
+            users::login_command,
+            users::logout_command,
+            users::get_current_user_command,
+            users::get_revenue_by_user_command
         ])
-        .run(tauri::generate_context!())
-        .expect("error while running tauri application");
+        .build(tauri::generate_context!())
+        .expect("error while building tauri application")
+        .run(|app, event| {
+            opened_files::handle_run_event(app, &event);
+            deep_links::handle_run_event(app, &event);
+            shutdown::handle_run_event(app, &event);
+        });
     Ok(())
 }
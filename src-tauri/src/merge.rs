@@ -0,0 +1,284 @@
+// src-tauri/src/merge.rs
+
+use rusqlite::{params, Connection, OptionalExtension, Row};
+use serde::{Deserialize, Serialize};
+use serde_json::Value as JsonValue;
+use std::collections::HashMap;
+use tauri::{AppHandle, State};
+use log::{debug, info, warn};
+
+use crate::audit;
+use crate::error::AppError;
+use crate::events;
+use crate::periods;
+use crate::storage::{db_error, json_column, to_json_column, SqliteStorage, TransactionRepository};
+use crate::sync;
+use crate::{AppState, Transaction};
+
+/// Campos de sincronización, que no cuentan al comparar dos versiones.
+const METADATA_FIELDS: &[&str] = &["revision", "device_id", "updated_at", "sequence"];
+
+/// Revisión de una transacción recién creada.
+pub fn initial_revision() -> u64 {
+    1
+}
+
+/// Qué hacer con una versión recibida de otro equipo.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MergeDecision {
+    /// No existe aquí: se añade.
+    Insert,
+    /// Es posterior a la local, que no ha cambiado: la sustituye.
+    Update,
+    /// La local es igual o posterior: se conserva.
+    Keep,
+    /// Ambas cambiaron por separado: se guarda el conflicto y se conserva la local
+    /// hasta que el usuario elija.
+    Conflict,
+}
+
+/// Último cambio de una transacción: su modificación o su paso a la papelera.
+pub fn last_change(transaction: &Transaction) -> u64 {
+    transaction.updated_at.max(transaction.deleted_at.unwrap_or(0))
+}
+
+/// Contenido de la transacción sin los campos de sincronización.
+fn content(transaction: &Transaction) -> JsonValue {
+    let mut value = serde_json::to_value(transaction).unwrap_or(JsonValue::Null);
+    if let Some(object) = value.as_object_mut() {
+        for field in METADATA_FIELDS {
+            object.remove(*field);
+        }
+    }
+    value
+}
+
+/// Decide, siempre igual para las mismas entradas, qué hacer con `incoming`.
+///
+/// Gana la revisión mayor, salvo que la local se haya modificado en este equipo
+/// después de `synced_at` (la última sincronización con el origen de `incoming`):
+/// entonces ambas cambiaron y es un conflicto. Dos versiones distintas con la misma
+/// revisión también lo son.
+pub fn decide(local: Option<&Transaction>, incoming: &Transaction, own_device: &str, synced_at: u64) -> MergeDecision {
+    let Some(local) = local else {
+        return MergeDecision::Insert;
+    };
+    if content(local) == content(incoming) {
+        return MergeDecision::Keep;
+    }
+    let changed_here = local.device_id == own_device && last_change(local) > synced_at;
+    match incoming.revision.cmp(&local.revision) {
+        std::cmp::Ordering::Greater if !changed_here => MergeDecision::Update,
+        std::cmp::Ordering::Less => MergeDecision::Keep,
+        _ => MergeDecision::Conflict,
+    }
+}
+
+/// Resultado de `apply_incoming`.
+#[derive(Debug, Default, Clone, Copy, Serialize)]
+pub struct MergeSummary {
+    /// Transacciones añadidas o sustituidas.
+    pub applied: usize,
+    /// Conflictos nuevos o actualizados.
+    pub conflicts: usize,
+}
+
+/// Conflicto pendiente entre la versión local de una transacción y la recibida.
+#[derive(Debug, Clone, Serialize)]
+pub struct SyncConflict {
+    pub id: String,
+    pub transaction_id: String,
+    /// Origen de la versión recibida: `cloud` o `lan:<equipo>`.
+    pub source: String,
+    pub detected_at: u64,
+    pub local: Transaction,
+    pub incoming: Transaction,
+}
+
+/// Versión con la que se queda el usuario al resolver un conflicto.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ConflictChoice {
+    Local,
+    Incoming,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct ConflictResolution {
+    pub conflict_id: String,
+    pub choice: ConflictChoice,
+}
+
+fn row_to_conflict(row: &Row) -> rusqlite::Result<SyncConflict> {
+    Ok(SyncConflict {
+        id: row.get(0)?,
+        transaction_id: row.get(1)?,
+        source: row.get(2)?,
+        local: json_column(row, 3)?,
+        incoming: json_column(row, 4)?,
+        detected_at: row.get::<_, i64>(5)? as u64,
+    })
+}
+
+const CONFLICT_COLUMNS: &str = "id, transaction_id, source, local_json, incoming_json, detected_at";
+
+pub fn list_conflicts(conn: &Connection) -> Result<Vec<SyncConflict>, AppError> {
+    let mut stmt = conn
+        .prepare(&format!("SELECT {} FROM sync_conflicts ORDER BY detected_at, id", CONFLICT_COLUMNS))
+        .map_err(db_error)?;
+    let rows = stmt.query_map([], row_to_conflict).map_err(db_error)?;
+    rows.collect::<rusqlite::Result<Vec<_>>>().map_err(db_error)
+}
+
+fn get_conflict(conn: &Connection, id: &str) -> Result<Option<SyncConflict>, AppError> {
+    conn.query_row(&format!("SELECT {} FROM sync_conflicts WHERE id = ?1", CONFLICT_COLUMNS), params![id], row_to_conflict)
+        .optional()
+        .map_err(db_error)
+}
+
+/// Guarda el conflicto de una transacción. Si ya había uno pendiente se actualiza la
+/// versión recibida y se conserva su fecha.
+fn save_conflict(conn: &Connection, source: &str, local: &Transaction, incoming: &Transaction) -> Result<(), AppError> {
+    conn.execute(
+        "INSERT INTO sync_conflicts (id, transaction_id, source, local_json, incoming_json, detected_at)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6)
+         ON CONFLICT(transaction_id) DO UPDATE SET source = excluded.source, local_json = excluded.local_json,
+             incoming_json = excluded.incoming_json",
+        params![
+            uuid::Uuid::new_v4().to_string(),
+            local.id,
+            source,
+            to_json_column(local),
+            to_json_column(incoming),
+            periods::now_timestamp() as i64
+        ],
+    )
+    .map_err(db_error)?;
+    Ok(())
+}
+
+/// Aplica las versiones recibidas de `source` según `decide`, en una transacción SQL.
+/// Mientras una transacción tiene un conflicto pendiente no se sustituye, salvo que
+/// llegue una revisión posterior a las dos en conflicto (alguien lo resolvió en otro
+/// equipo). Las de meses cerrados se omiten.
+pub fn apply_incoming(
+    db: &SqliteStorage,
+    source: &str,
+    own_device: &str,
+    synced_at: u64,
+    incoming: &[Transaction],
+) -> Result<MergeSummary, AppError> {
+    let mut local: HashMap<String, Transaction> = HashMap::new();
+    for transaction in db.list_transactions()?.into_iter().chain(db.list_deleted_transactions()?) {
+        local.insert(transaction.id.clone(), transaction);
+    }
+    let pending: HashMap<String, u64> = list_conflicts(db.connection())?
+        .into_iter()
+        .map(|c| (c.transaction_id, c.local.revision.max(c.incoming.revision)))
+        .collect();
+
+    let mut summary = MergeSummary::default();
+    let tx = db.connection().unchecked_transaction().map_err(db_error)?;
+    for transaction in incoming {
+        let current = local.get(&transaction.id);
+        let decision = match (pending.get(&transaction.id), current) {
+            (Some(&revision), Some(_)) if transaction.revision > revision => MergeDecision::Update,
+            (Some(_), Some(current)) if content(current) != content(transaction) => MergeDecision::Conflict,
+            (Some(_), _) => MergeDecision::Keep,
+            (None, _) => decide(current, transaction, own_device, synced_at),
+        };
+        let result = match (decision, current) {
+            (MergeDecision::Insert, _) => db.insert_transaction(transaction).map(|_| true),
+            (MergeDecision::Update, _) => {
+                if pending.contains_key(&transaction.id) {
+                    tx.execute("DELETE FROM sync_conflicts WHERE transaction_id = ?1", params![transaction.id])
+                        .map_err(db_error)?;
+                }
+                db.update_transaction(transaction)
+            }
+            (MergeDecision::Conflict, Some(current)) => {
+                save_conflict(&tx, source, current, transaction)?;
+                summary.conflicts += 1;
+                Ok(false)
+            }
+            _ => Ok(false),
+        };
+        match result {
+            Ok(true) => summary.applied += 1,
+            Ok(false) => {}
+            Err(AppError::PeriodClosed(_)) => {
+                warn!("Merge skipped transaction {} in a closed period.", transaction.id);
+            }
+            Err(e) => return Err(e),
+        }
+    }
+    tx.commit().map_err(db_error)?;
+    if summary.conflicts > 0 {
+        warn!("{} sync conflicts from {} need manual resolution.", summary.conflicts, source);
+    }
+    debug!("Merged {} transactions from {}: {:?}", incoming.len(), source, summary);
+    Ok(summary)
+}
+
+/// Deja la versión elegida con una revisión mayor que las dos en conflicto, para que
+/// prevalezca en la próxima sincronización con el otro equipo.
+fn resolve_conflict(db: &SqliteStorage, own_device: &str, conflict: &SyncConflict, choice: ConflictChoice) -> Result<Transaction, AppError> {
+    let current = db
+        .list_transactions()?
+        .into_iter()
+        .chain(db.list_deleted_transactions()?)
+        .find(|t| t.id == conflict.transaction_id);
+    let mut chosen = match choice {
+        ConflictChoice::Local => current.clone().unwrap_or_else(|| conflict.local.clone()),
+        ConflictChoice::Incoming => conflict.incoming.clone(),
+    };
+    let base = current.as_ref().map_or(0, |t| t.revision);
+    chosen.revision = base.max(conflict.local.revision).max(conflict.incoming.revision) + 1;
+    chosen.device_id = own_device.to_string();
+    chosen.updated_at = periods::now_timestamp();
+
+    let tx = db.connection().unchecked_transaction().map_err(db_error)?;
+    if current.is_some() {
+        db.update_transaction(&chosen)?;
+    } else {
+        db.insert_transaction(&chosen)?;
+    }
+    tx.execute("DELETE FROM sync_conflicts WHERE id = ?1", params![conflict.id]).map_err(db_error)?;
+    tx.commit().map_err(db_error)?;
+    Ok(chosen)
+}
+
+// --- Comandos Tauri ---
+
+/// Comando para resolver conflictos de sincronización: por cada elemento de
+/// `resolutions` se queda con la versión local o con la recibida. Devuelve los
+/// conflictos que siguen pendientes; sin `resolutions` solo los lista.
+#[tauri::command]
+pub async fn resolve_conflicts_command(
+    state: State<'_, AppState>,
+    app: AppHandle,
+    resolutions: Option<Vec<ConflictResolution>>,
+) -> Result<Vec<SyncConflict>, AppError> {
+    let resolutions = resolutions.unwrap_or_default();
+    debug!("Received resolve_conflicts_command: {} resolutions", resolutions.len());
+    let db = state.db().await?;
+    if !resolutions.is_empty() {
+        let own_device = sync::device_id(&db)?;
+        for resolution in &resolutions {
+            let conflict = get_conflict(db.connection(), &resolution.conflict_id)?
+                .ok_or_else(|| AppError::NotFound(format!("Conflicto {} no encontrado.", resolution.conflict_id)))?;
+            let chosen = resolve_conflict(&db, &own_device, &conflict, resolution.choice)?;
+            audit::record(
+                db.connection(),
+                "resolve_conflicts_command",
+                Some(&conflict.transaction_id),
+                audit::snapshot(&conflict.local),
+                audit::snapshot(&chosen),
+            );
+            info!("Conflict on transaction {} resolved with the {:?} version.", conflict.transaction_id, resolution.choice);
+        }
+        state.history.lock().unwrap().clear();
+        events::emit_data_reloaded(&app);
+    }
+    list_conflicts(db.connection())
+}
@@ -0,0 +1,755 @@
+// src-tauri/src/migrations.rs
+
+use rusqlite::Connection;
+use serde::Deserialize;
+use serde_json::{json, Value};
+use std::path::Path;
+use log::{debug, error, info, warn};
+
+use crate::error::AppError;
+use crate::periods;
+use crate::storage::{self, db_error};
+use crate::Transaction;
+
+// --- Migraciones de la Base de Datos ---
+
+/// Migraciones del esquema, en orden. La posición en la lista (empezando en 1)
+/// es la versión que queda registrada en `PRAGMA user_version` tras aplicarla.
+/// Nunca se modifica una migración ya publicada: solo se añaden nuevas al final.
+const MIGRATIONS: &[&str] = &[
+    // v1: tabla de transacciones e índices para las consultas habituales.
+    "CREATE TABLE transactions (
+        id TEXT PRIMARY KEY NOT NULL,
+        transaction_type TEXT NOT NULL,
+        amount REAL NOT NULL,
+        description TEXT NOT NULL,
+        store_name TEXT NOT NULL,
+        timestamp INTEGER NOT NULL
+    );
+    CREATE INDEX idx_transactions_store_name ON transactions(store_name);
+    CREATE INDEX idx_transactions_timestamp ON transactions(timestamp);
+    CREATE INDEX idx_transactions_type ON transactions(transaction_type);",
+    // v2: categorías y subcategorías. Las categorías principales usan parent = ''.
+    "CREATE TABLE categories (
+        name TEXT NOT NULL,
+        parent TEXT NOT NULL DEFAULT '',
+        PRIMARY KEY (name, parent)
+    );
+    INSERT INTO categories (name) VALUES
+        ('Alquiler'), ('Nómina'), ('Suministros'), ('Ventas'), ('Compras'), ('Impuestos'), ('Otros');
+    ALTER TABLE transactions ADD COLUMN category TEXT;
+    ALTER TABLE transactions ADD COLUMN subcategory TEXT;
+    CREATE INDEX idx_transactions_category ON transactions(category, subcategory);",
+    // v3: moneda por transacción, tabla de tipos de cambio y ajustes clave/valor.
+    "ALTER TABLE transactions ADD COLUMN currency TEXT NOT NULL DEFAULT 'EUR';
+    CREATE TABLE exchange_rates (
+        currency TEXT PRIMARY KEY NOT NULL,
+        rate REAL NOT NULL,
+        updated_at INTEGER NOT NULL
+    );
+    CREATE TABLE app_settings (
+        key TEXT PRIMARY KEY NOT NULL,
+        value TEXT NOT NULL
+    );
+    INSERT INTO app_settings (key, value) VALUES ('base_currency', 'EUR');",
+    // v4: presupuestos por categoría o tienda. alert_level guarda el último umbral
+    // notificado (0, 80 o 100) dentro del periodo que empieza en alert_period_start.
+    "CREATE TABLE budgets (
+        id TEXT PRIMARY KEY NOT NULL,
+        scope TEXT NOT NULL,
+        scope_name TEXT NOT NULL,
+        period TEXT NOT NULL,
+        limit_amount REAL NOT NULL,
+        alert_level INTEGER NOT NULL DEFAULT 0,
+        alert_period_start INTEGER NOT NULL DEFAULT 0,
+        UNIQUE (scope, scope_name, period)
+    );",
+    // v5: rutas de justificantes adjuntos, como array JSON de rutas relativas.
+    "ALTER TABLE transactions ADD COLUMN receipt_paths TEXT NOT NULL DEFAULT '[]';",
+    // v6: sesiones de chat con Gemini y sus mensajes.
+    "CREATE TABLE chat_sessions (
+        id TEXT PRIMARY KEY NOT NULL,
+        title TEXT NOT NULL,
+        created_at INTEGER NOT NULL,
+        updated_at INTEGER NOT NULL
+    );
+    CREATE TABLE chat_messages (
+        id INTEGER PRIMARY KEY AUTOINCREMENT,
+        session_id TEXT NOT NULL REFERENCES chat_sessions(id) ON DELETE CASCADE,
+        role TEXT NOT NULL,
+        text TEXT NOT NULL,
+        timestamp INTEGER NOT NULL
+    );
+    CREATE INDEX idx_chat_messages_session ON chat_messages(session_id, id);",
+    // v7: papelera. Las transacciones con deleted_at no nulo están eliminadas de forma reversible.
+    "ALTER TABLE transactions ADD COLUMN deleted_at INTEGER;
+    CREATE INDEX idx_transactions_deleted_at ON transactions(deleted_at);",
+    // v8: log de auditoría de solo anexado; los triggers impiden modificar o borrar registros.
+    "CREATE TABLE audit_log (
+        id INTEGER PRIMARY KEY AUTOINCREMENT,
+        timestamp INTEGER NOT NULL,
+        command TEXT NOT NULL,
+        entity_id TEXT,
+        before_json TEXT,
+        after_json TEXT,
+        actor TEXT NOT NULL
+    );
+    CREATE INDEX idx_audit_log_timestamp ON audit_log(timestamp);
+    CREATE INDEX idx_audit_log_entity ON audit_log(entity_id);
+    CREATE TRIGGER audit_log_no_update BEFORE UPDATE ON audit_log
+    BEGIN SELECT RAISE(ABORT, 'El log de auditoría no se puede modificar.'); END;
+    CREATE TRIGGER audit_log_no_delete BEFORE DELETE ON audit_log
+    BEGIN SELECT RAISE(ABORT, 'El log de auditoría no se puede modificar.'); END;",
+    // v9: importes, límites y tipos de cambio como texto decimal exacto en lugar de REAL.
+    // SQLite no permite cambiar el tipo de una columna, así que se reconstruyen las tablas.
+    "CREATE TABLE transactions_v9 (
+        id TEXT PRIMARY KEY NOT NULL,
+        transaction_type TEXT NOT NULL,
+        amount TEXT NOT NULL,
+        description TEXT NOT NULL,
+        store_name TEXT NOT NULL,
+        timestamp INTEGER NOT NULL,
+        category TEXT,
+        subcategory TEXT,
+        currency TEXT NOT NULL DEFAULT 'EUR',
+        receipt_paths TEXT NOT NULL DEFAULT '[]',
+        deleted_at INTEGER
+    );
+    INSERT INTO transactions_v9
+        SELECT id, transaction_type, CAST(amount AS TEXT), description, store_name, timestamp,
+               category, subcategory, currency, receipt_paths, deleted_at
+        FROM transactions;
+    DROP TABLE transactions;
+    ALTER TABLE transactions_v9 RENAME TO transactions;
+    CREATE INDEX idx_transactions_store_name ON transactions(store_name);
+    CREATE INDEX idx_transactions_timestamp ON transactions(timestamp);
+    CREATE INDEX idx_transactions_type ON transactions(transaction_type);
+    CREATE INDEX idx_transactions_category ON transactions(category, subcategory);
+    CREATE INDEX idx_transactions_deleted_at ON transactions(deleted_at);
+    CREATE TABLE exchange_rates_v9 (
+        currency TEXT PRIMARY KEY NOT NULL,
+        rate TEXT NOT NULL,
+        updated_at INTEGER NOT NULL
+    );
+    INSERT INTO exchange_rates_v9 SELECT currency, CAST(rate AS TEXT), updated_at FROM exchange_rates;
+    DROP TABLE exchange_rates;
+    ALTER TABLE exchange_rates_v9 RENAME TO exchange_rates;
+    CREATE TABLE budgets_v9 (
+        id TEXT PRIMARY KEY NOT NULL,
+        scope TEXT NOT NULL,
+        scope_name TEXT NOT NULL,
+        period TEXT NOT NULL,
+        limit_amount TEXT NOT NULL,
+        alert_level INTEGER NOT NULL DEFAULT 0,
+        alert_period_start INTEGER NOT NULL DEFAULT 0,
+        UNIQUE (scope, scope_name, period)
+    );
+    INSERT INTO budgets_v9
+        SELECT id, scope, scope_name, period, CAST(limit_amount AS TEXT), alert_level, alert_period_start
+        FROM budgets;
+    DROP TABLE budgets;
+    ALTER TABLE budgets_v9 RENAME TO budgets;",
+    // v10: facturas emitidas. status guarda 'pending' o 'paid'; 'overdue' se calcula al leer.
+    "CREATE TABLE invoices (
+        id TEXT PRIMARY KEY NOT NULL,
+        number TEXT NOT NULL UNIQUE,
+        client_name TEXT NOT NULL,
+        client_tax_id TEXT,
+        issue_date TEXT NOT NULL,
+        due_date TEXT NOT NULL,
+        currency TEXT NOT NULL,
+        iva_rate TEXT NOT NULL,
+        line_items TEXT NOT NULL DEFAULT '[]',
+        status TEXT NOT NULL DEFAULT 'pending',
+        paid_at INTEGER,
+        transaction_id TEXT,
+        created_at INTEGER NOT NULL
+    );
+    CREATE INDEX idx_invoices_status ON invoices(status, due_date);",
+    // v11: IVA de cada transacción (tipo en porcentaje y cuota), ambos opcionales.
+    "ALTER TABLE transactions ADD COLUMN tax_rate TEXT;
+    ALTER TABLE transactions ADD COLUMN tax_amount TEXT;",
+    // v12: etiquetas libres de cada transacción, como array JSON.
+    "ALTER TABLE transactions ADD COLUMN tags TEXT NOT NULL DEFAULT '[]';",
+    // v13: índice de texto completo sin distinguir acentos, mantenido por triggers.
+    "CREATE VIRTUAL TABLE transactions_fts USING fts5(
+        description, store_name, category, subcategory, tags,
+        content = 'transactions', content_rowid = 'rowid',
+        tokenize = 'unicode61 remove_diacritics 2'
+    );
+    CREATE TRIGGER transactions_fts_insert AFTER INSERT ON transactions BEGIN
+        INSERT INTO transactions_fts (rowid, description, store_name, category, subcategory, tags)
+            VALUES (new.rowid, new.description, new.store_name, new.category, new.subcategory, new.tags);
+    END;
+    CREATE TRIGGER transactions_fts_delete AFTER DELETE ON transactions BEGIN
+        INSERT INTO transactions_fts (transactions_fts, rowid, description, store_name, category, subcategory, tags)
+            VALUES ('delete', old.rowid, old.description, old.store_name, old.category, old.subcategory, old.tags);
+    END;
+    CREATE TRIGGER transactions_fts_update AFTER UPDATE ON transactions BEGIN
+        INSERT INTO transactions_fts (transactions_fts, rowid, description, store_name, category, subcategory, tags)
+            VALUES ('delete', old.rowid, old.description, old.store_name, old.category, old.subcategory, old.tags);
+        INSERT INTO transactions_fts (rowid, description, store_name, category, subcategory, tags)
+            VALUES (new.rowid, new.description, new.store_name, new.category, new.subcategory, new.tags);
+    END;
+    INSERT INTO transactions_fts (transactions_fts) VALUES ('rebuild');",
+    // v14: identificador del movimiento en el extracto bancario (FITID) del que se importó.
+    "ALTER TABLE transactions ADD COLUMN external_id TEXT;
+    CREATE INDEX idx_transactions_external_id ON transactions(external_id);",
+    // v15: fecha de la transacción indicada por el usuario y fechas de creación y
+    // última modificación. Hasta ahora `timestamp` era el momento de creación.
+    "ALTER TABLE transactions ADD COLUMN transaction_date TEXT NOT NULL DEFAULT '';
+    ALTER TABLE transactions ADD COLUMN created_at INTEGER NOT NULL DEFAULT 0;
+    ALTER TABLE transactions ADD COLUMN updated_at INTEGER NOT NULL DEFAULT 0;
+    UPDATE transactions SET
+        transaction_date = date(timestamp, 'unixepoch', 'localtime'),
+        created_at = timestamp,
+        updated_at = timestamp;
+    CREATE INDEX idx_transactions_date ON transactions(transaction_date);",
+    // v16: notas y campos personalizados. El índice de texto completo se rehace para
+    // incluirlos.
+    "ALTER TABLE transactions ADD COLUMN notes TEXT NOT NULL DEFAULT '';
+    ALTER TABLE transactions ADD COLUMN custom_fields TEXT NOT NULL DEFAULT '{}';
+    DROP TRIGGER transactions_fts_insert;
+    DROP TRIGGER transactions_fts_delete;
+    DROP TRIGGER transactions_fts_update;
+    DROP TABLE transactions_fts;
+    CREATE VIRTUAL TABLE transactions_fts USING fts5(
+        description, store_name, category, subcategory, tags, notes, custom_fields,
+        content = 'transactions', content_rowid = 'rowid',
+        tokenize = 'unicode61 remove_diacritics 2'
+    );
+    CREATE TRIGGER transactions_fts_insert AFTER INSERT ON transactions BEGIN
+        INSERT INTO transactions_fts (rowid, description, store_name, category, subcategory, tags, notes, custom_fields)
+            VALUES (new.rowid, new.description, new.store_name, new.category, new.subcategory, new.tags,
+                    new.notes, new.custom_fields);
+    END;
+    CREATE TRIGGER transactions_fts_delete AFTER DELETE ON transactions BEGIN
+        INSERT INTO transactions_fts (transactions_fts, rowid, description, store_name, category, subcategory, tags,
+                                      notes, custom_fields)
+            VALUES ('delete', old.rowid, old.description, old.store_name, old.category, old.subcategory, old.tags,
+                    old.notes, old.custom_fields);
+    END;
+    CREATE TRIGGER transactions_fts_update AFTER UPDATE ON transactions BEGIN
+        INSERT INTO transactions_fts (transactions_fts, rowid, description, store_name, category, subcategory, tags,
+                                      notes, custom_fields)
+            VALUES ('delete', old.rowid, old.description, old.store_name, old.category, old.subcategory, old.tags,
+                    old.notes, old.custom_fields);
+        INSERT INTO transactions_fts (rowid, description, store_name, category, subcategory, tags, notes, custom_fields)
+            VALUES (new.rowid, new.description, new.store_name, new.category, new.subcategory, new.tags,
+                    new.notes, new.custom_fields);
+    END;
+    INSERT INTO transactions_fts (transactions_fts) VALUES ('rebuild');",
+    // v17: medio de pago.
+    "ALTER TABLE transactions ADD COLUMN payment_method TEXT NOT NULL DEFAULT 'Otro';",
+    // v18: datos propios de cada tienda. Las transacciones la siguen referenciando por
+    // nombre; se registran las tiendas que ya existían.
+    "CREATE TABLE stores (
+        name TEXT PRIMARY KEY NOT NULL,
+        address TEXT,
+        nif TEXT,
+        default_category TEXT,
+        created_at INTEGER NOT NULL,
+        updated_at INTEGER NOT NULL
+    );
+    INSERT INTO stores (name, created_at, updated_at)
+        SELECT store_name, MIN(created_at), MIN(created_at) FROM transactions GROUP BY store_name;",
+    // v19: clientes y proveedores, y el contacto de cada transacción y factura.
+    "CREATE TABLE contacts (
+        id TEXT PRIMARY KEY NOT NULL,
+        name TEXT NOT NULL,
+        nif TEXT,
+        email TEXT,
+        kind TEXT NOT NULL,
+        created_at INTEGER NOT NULL,
+        updated_at INTEGER NOT NULL
+    );
+    ALTER TABLE transactions ADD COLUMN contact_id TEXT;
+    CREATE INDEX idx_transactions_contact ON transactions(contact_id);
+    ALTER TABLE invoices ADD COLUMN contact_id TEXT;",
+    // v20: resúmenes mensuales generados por la IA, con los datos con los que se
+    // generaron para saber si siguen valiendo.
+    "CREATE TABLE ai_summaries (
+        month TEXT PRIMARY KEY NOT NULL,
+        context TEXT NOT NULL,
+        summary TEXT NOT NULL,
+        generated_at INTEGER NOT NULL
+    );",
+    // v21: peticiones a la IA pendientes de enviar por falta de conexión.
+    "CREATE TABLE ai_outbox (
+        id TEXT PRIMARY KEY NOT NULL,
+        kind TEXT NOT NULL,
+        request TEXT NOT NULL,
+        status TEXT NOT NULL DEFAULT 'pending',
+        attempts INTEGER NOT NULL DEFAULT 0,
+        last_error TEXT,
+        created_at INTEGER NOT NULL,
+        updated_at INTEGER NOT NULL
+    );",
+    // v22: consumo de tokens y coste estimado de cada llamada a la IA.
+    "CREATE TABLE ai_usage (
+        id INTEGER PRIMARY KEY AUTOINCREMENT,
+        month TEXT NOT NULL,
+        timestamp INTEGER NOT NULL,
+        provider TEXT NOT NULL,
+        model TEXT NOT NULL,
+        prompt_tokens INTEGER NOT NULL,
+        response_tokens INTEGER NOT NULL,
+        cost TEXT NOT NULL
+    );
+    CREATE INDEX idx_ai_usage_month ON ai_usage(month);",
+    // v23: avisos pendientes ya notificados al sistema, para no repetirlos.
+    "CREATE TABLE notified_alerts (
+        key TEXT PRIMARY KEY NOT NULL,
+        notified_at INTEGER NOT NULL
+    );",
+    // v24: meses cerrados. Los triggers impiden crear o borrar transacciones activas
+    // con fecha en un mes cerrado, y cambiar en ellas los datos que cuentan en la
+    // contabilidad (importe, moneda, tipo, fecha, tienda, categoría, IVA y papelera);
+    // el resto, como el contacto o las notas, se puede seguir cambiando. Las de la
+    // papelera se pueden purgar. El mensaje lo reconoce `AppError::from` (ver
+    // `error::PERIOD_CLOSED_MARKER`).
+    "CREATE TABLE closed_periods (
+        month TEXT PRIMARY KEY NOT NULL,
+        closed_at INTEGER NOT NULL,
+        actor TEXT NOT NULL
+    );
+    CREATE TRIGGER transactions_closed_period_insert BEFORE INSERT ON transactions
+    WHEN NEW.deleted_at IS NULL AND EXISTS (SELECT 1 FROM closed_periods WHERE month = substr(NEW.transaction_date, 1, 7))
+    BEGIN SELECT RAISE(ABORT, 'PERIOD_CLOSED'); END;
+    CREATE TRIGGER transactions_closed_period_update BEFORE UPDATE ON transactions
+    WHEN (OLD.amount IS NOT NEW.amount OR OLD.currency IS NOT NEW.currency
+            OR OLD.transaction_type IS NOT NEW.transaction_type OR OLD.transaction_date IS NOT NEW.transaction_date
+            OR OLD.store_name IS NOT NEW.store_name OR OLD.category IS NOT NEW.category
+            OR OLD.subcategory IS NOT NEW.subcategory OR OLD.tax_rate IS NOT NEW.tax_rate
+            OR OLD.tax_amount IS NOT NEW.tax_amount OR OLD.deleted_at IS NOT NEW.deleted_at)
+        AND ((OLD.deleted_at IS NULL AND EXISTS (SELECT 1 FROM closed_periods WHERE month = substr(OLD.transaction_date, 1, 7)))
+            OR (NEW.deleted_at IS NULL AND EXISTS (SELECT 1 FROM closed_periods WHERE month = substr(NEW.transaction_date, 1, 7))))
+    BEGIN SELECT RAISE(ABORT, 'PERIOD_CLOSED'); END;
+    CREATE TRIGGER transactions_closed_period_delete BEFORE DELETE ON transactions
+    WHEN OLD.deleted_at IS NULL AND EXISTS (SELECT 1 FROM closed_periods WHERE month = substr(OLD.transaction_date, 1, 7))
+    BEGIN SELECT RAISE(ABORT, 'PERIOD_CLOSED'); END;",
+    // v25: equipos emparejados para la sincronización en red local y registro de sus
+    // intercambios. La clave compartida de cada equipo se guarda en el llavero.
+    "CREATE TABLE paired_devices (
+        device_id TEXT PRIMARY KEY NOT NULL,
+        name TEXT NOT NULL,
+        address TEXT NOT NULL,
+        paired_at INTEGER NOT NULL,
+        received_until INTEGER NOT NULL DEFAULT 0,
+        last_sync_at INTEGER
+    );
+    CREATE TABLE lan_sync_log (
+        id INTEGER PRIMARY KEY AUTOINCREMENT,
+        timestamp INTEGER NOT NULL,
+        device_id TEXT NOT NULL,
+        device_name TEXT NOT NULL,
+        direction TEXT NOT NULL,
+        sent INTEGER NOT NULL DEFAULT 0,
+        received INTEGER NOT NULL DEFAULT 0,
+        error TEXT
+    );
+    CREATE INDEX idx_lan_sync_log_timestamp ON lan_sync_log(timestamp);",
+    // v26: revisión de cada transacción y equipo que hizo el último cambio, para
+    // combinar cambios concurrentes al sincronizar. El trigger sube la revisión en cada
+    // cambio local; al aplicar una versión recibida se escribe ya con una revisión
+    // mayor y no se dispara. Las transacciones anteriores quedan sin equipo.
+    "ALTER TABLE transactions ADD COLUMN revision INTEGER NOT NULL DEFAULT 1;
+    ALTER TABLE transactions ADD COLUMN device_id TEXT NOT NULL DEFAULT '';
+    INSERT OR IGNORE INTO app_settings (key, value) VALUES ('device_id', lower(hex(randomblob(16))));
+    CREATE TRIGGER transactions_revision_update AFTER UPDATE ON transactions
+    WHEN NEW.revision <= OLD.revision
+    BEGIN
+        UPDATE transactions SET revision = OLD.revision + 1,
+            device_id = (SELECT value FROM app_settings WHERE key = 'device_id')
+        WHERE id = NEW.id;
+    END;
+    CREATE TABLE sync_conflicts (
+        id TEXT PRIMARY KEY NOT NULL,
+        transaction_id TEXT NOT NULL UNIQUE,
+        source TEXT NOT NULL,
+        local_json TEXT NOT NULL,
+        incoming_json TEXT NOT NULL,
+        detected_at INTEGER NOT NULL
+    );
+    ALTER TABLE lan_sync_log ADD COLUMN conflicts INTEGER NOT NULL DEFAULT 0;",
+    // v27: cola de envíos a webhooks, con sus reintentos. `payload_id` identifica el
+    // evento y se repite en cada URL.
+    "CREATE TABLE webhook_deliveries (
+        id TEXT PRIMARY KEY NOT NULL,
+        payload_id TEXT NOT NULL,
+        url TEXT NOT NULL,
+        event TEXT NOT NULL,
+        body TEXT NOT NULL,
+        status TEXT NOT NULL DEFAULT 'pending',
+        attempts INTEGER NOT NULL DEFAULT 0,
+        next_attempt_at INTEGER NOT NULL,
+        last_error TEXT,
+        created_at INTEGER NOT NULL
+    );
+    CREATE INDEX idx_webhook_deliveries_status ON webhook_deliveries(status, next_attempt_at);",
+    // v28: bancos vinculados con GoCardless, sus cuentas y los movimientos descargados
+    // a la espera de revisarlos. El ID de cada movimiento es el `external_id` que
+    // tendrá la transacción.
+    "CREATE TABLE bank_connections (
+        id TEXT PRIMARY KEY NOT NULL,
+        institution_id TEXT NOT NULL,
+        institution_name TEXT NOT NULL,
+        status TEXT NOT NULL,
+        link TEXT NOT NULL,
+        created_at INTEGER NOT NULL
+    );
+    CREATE TABLE bank_accounts (
+        id TEXT PRIMARY KEY NOT NULL,
+        connection_id TEXT NOT NULL,
+        name TEXT NOT NULL,
+        iban TEXT,
+        currency TEXT,
+        target_store TEXT NOT NULL,
+        last_fetched_at INTEGER
+    );
+    CREATE TABLE bank_staging (
+        id TEXT PRIMARY KEY NOT NULL,
+        account_id TEXT NOT NULL,
+        booking_date TEXT NOT NULL,
+        amount TEXT NOT NULL,
+        currency TEXT NOT NULL,
+        payee TEXT,
+        memo TEXT,
+        status TEXT NOT NULL DEFAULT 'pending',
+        transaction_id TEXT,
+        fetched_at INTEGER NOT NULL
+    );
+    CREATE INDEX idx_bank_staging_status ON bank_staging(status, booking_date);",
+    // v29: reglas de clasificación automática. `tags` es un array JSON.
+    "CREATE TABLE classification_rules (
+        id TEXT PRIMARY KEY NOT NULL,
+        name TEXT NOT NULL,
+        position INTEGER NOT NULL,
+        enabled INTEGER NOT NULL DEFAULT 1,
+        field TEXT NOT NULL,
+        operator TEXT NOT NULL,
+        pattern TEXT NOT NULL,
+        category TEXT,
+        subcategory TEXT,
+        store_name TEXT,
+        tags TEXT NOT NULL DEFAULT '[]',
+        created_at INTEGER NOT NULL
+    );",
+    // v30: plantillas de transacción. `fields` guarda los campos rellenados en JSON.
+    "CREATE TABLE transaction_templates (
+        id TEXT PRIMARY KEY NOT NULL,
+        name TEXT NOT NULL,
+        fields TEXT NOT NULL DEFAULT '{}',
+        use_count INTEGER NOT NULL DEFAULT 0,
+        created_at INTEGER NOT NULL,
+        updated_at INTEGER NOT NULL
+    );",
+    // v31: reparto de transacciones entre categorías. Como las transacciones, el
+    // reparto de una transacción de un mes cerrado no se puede cambiar.
+    "CREATE TABLE transaction_splits (
+        id TEXT PRIMARY KEY NOT NULL,
+        transaction_id TEXT NOT NULL REFERENCES transactions(id) ON DELETE CASCADE,
+        position INTEGER NOT NULL,
+        amount TEXT NOT NULL,
+        category TEXT,
+        subcategory TEXT,
+        notes TEXT NOT NULL DEFAULT ''
+    );
+    CREATE INDEX idx_transaction_splits_transaction ON transaction_splits(transaction_id, position);
+    CREATE TRIGGER transaction_splits_closed_period_insert BEFORE INSERT ON transaction_splits
+    WHEN EXISTS (SELECT 1 FROM transactions t JOIN closed_periods p ON p.month = substr(t.transaction_date, 1, 7)
+                 WHERE t.id = NEW.transaction_id AND t.deleted_at IS NULL)
+    BEGIN SELECT RAISE(ABORT, 'PERIOD_CLOSED'); END;
+    CREATE TRIGGER transaction_splits_closed_period_delete BEFORE DELETE ON transaction_splits
+    WHEN EXISTS (SELECT 1 FROM transactions t JOIN closed_periods p ON p.month = substr(t.transaction_date, 1, 7)
+                 WHERE t.id = OLD.transaction_id AND t.deleted_at IS NULL)
+    BEGIN SELECT RAISE(ABORT, 'PERIOD_CLOSED'); END;",
+    // v32: tienda de destino de las transferencias.
+    "ALTER TABLE transactions ADD COLUMN transfer_store TEXT;",
+    // v33: cuentas (caja, banco, tarjeta) y cuenta de cada transacción. En las
+    // transferencias, `account_id` es la de origen y `transfer_account_id` la de destino.
+    "CREATE TABLE accounts (
+        id TEXT PRIMARY KEY NOT NULL,
+        name TEXT NOT NULL,
+        kind TEXT NOT NULL,
+        opening_balance TEXT NOT NULL DEFAULT '0',
+        created_at INTEGER NOT NULL,
+        updated_at INTEGER NOT NULL
+    );
+    ALTER TABLE transactions ADD COLUMN account_id TEXT;
+    ALTER TABLE transactions ADD COLUMN transfer_account_id TEXT;
+    CREATE INDEX idx_transactions_account ON transactions(account_id);",
+    // v34: número de secuencia, que ordena las transacciones con el mismo momento.
+    // Las existentes lo toman del orden en que se insertaron. El trigger de revisión se
+    // quita mientras tanto: no es un cambio de los datos. El de los meses cerrados no
+    // vigila esta columna.
+    "ALTER TABLE transactions ADD COLUMN sequence INTEGER NOT NULL DEFAULT 0;
+    DROP TRIGGER transactions_revision_update;
+    UPDATE transactions SET sequence = rowid;
+    CREATE TRIGGER transactions_revision_update AFTER UPDATE ON transactions
+    WHEN NEW.revision <= OLD.revision
+    BEGIN
+        UPDATE transactions SET revision = OLD.revision + 1,
+            device_id = (SELECT value FROM app_settings WHERE key = 'device_id')
+        WHERE id = NEW.id;
+    END;
+    CREATE UNIQUE INDEX idx_transactions_sequence ON transactions(sequence);
+    CREATE INDEX idx_transactions_timestamp_sequence ON transactions(timestamp, sequence);",
+    // v35: objetivos de ingresos o de resultado por periodo, de todas las tiendas
+    // (`store_name` vacío) o de una.
+    "CREATE TABLE goals (
+        id TEXT PRIMARY KEY NOT NULL,
+        metric TEXT NOT NULL,
+        period TEXT NOT NULL,
+        store_name TEXT NOT NULL DEFAULT '',
+        target_amount TEXT NOT NULL,
+        created_at INTEGER NOT NULL,
+        updated_at INTEGER NOT NULL,
+        UNIQUE (metric, period, store_name)
+    );",
+    // v36: textos de los prompts cambiados por el usuario (ver `prompts`). `version` es
+    // la de la plantilla de la aplicación sobre la que se hizo el cambio.
+    "CREATE TABLE prompt_templates (
+        name TEXT PRIMARY KEY NOT NULL,
+        template TEXT NOT NULL,
+        version INTEGER NOT NULL,
+        updated_at INTEGER NOT NULL
+    );",
+    // v37: respuestas de la IA guardadas por el hash de la petición (ver `ai_cache`),
+    // con los tokens que costaron, y los aciertos y fallos acumulados de la caché.
+    "CREATE TABLE ai_cache (
+        key TEXT PRIMARY KEY NOT NULL,
+        provider TEXT NOT NULL,
+        model TEXT NOT NULL,
+        response TEXT NOT NULL,
+        prompt_tokens INTEGER NOT NULL,
+        response_tokens INTEGER NOT NULL,
+        hits INTEGER NOT NULL DEFAULT 0,
+        created_at INTEGER NOT NULL,
+        expires_at INTEGER NOT NULL
+    );
+    CREATE INDEX idx_ai_cache_expires ON ai_cache(expires_at);
+    CREATE TABLE ai_cache_stats (
+        id INTEGER PRIMARY KEY CHECK (id = 1),
+        hits INTEGER NOT NULL DEFAULT 0,
+        misses INTEGER NOT NULL DEFAULT 0,
+        saved_cost TEXT NOT NULL DEFAULT '0'
+    );
+    INSERT INTO ai_cache_stats (id) VALUES (1);",
+    // v38: tareas programadas (ver `scheduler`), con su expresión cron, y el historial
+    // de sus ejecuciones.
+    "CREATE TABLE scheduled_jobs (
+        id TEXT PRIMARY KEY NOT NULL,
+        schedule TEXT NOT NULL,
+        enabled INTEGER NOT NULL DEFAULT 1,
+        next_run_at INTEGER,
+        updated_at INTEGER NOT NULL
+    );
+    CREATE TABLE job_runs (
+        id INTEGER PRIMARY KEY AUTOINCREMENT,
+        job_id TEXT NOT NULL,
+        trigger TEXT NOT NULL,
+        status TEXT NOT NULL,
+        message TEXT NOT NULL,
+        started_at INTEGER NOT NULL,
+        finished_at INTEGER NOT NULL
+    );
+    CREATE INDEX idx_job_runs_job ON job_runs(job_id, id);",
+    // v39: cierres de caja diarios (ver `cash_closing`). Como en los meses cerrados,
+    // los triggers impiden cambiar las transacciones activas de un día cerrado, y
+    // además su medio de pago, del que sale el efectivo esperado.
+    "CREATE TABLE cash_closings (
+        date TEXT PRIMARY KEY NOT NULL,
+        opening_cash TEXT NOT NULL,
+        cash_income TEXT NOT NULL,
+        cash_expenses TEXT NOT NULL,
+        expected_cash TEXT NOT NULL,
+        counted_cash TEXT NOT NULL,
+        difference TEXT NOT NULL,
+        adjustment_transaction_id TEXT,
+        notes TEXT NOT NULL DEFAULT '',
+        closed_at INTEGER NOT NULL,
+        actor TEXT NOT NULL
+    );
+    CREATE TRIGGER transactions_cash_closing_insert BEFORE INSERT ON transactions
+    WHEN NEW.deleted_at IS NULL AND EXISTS (SELECT 1 FROM cash_closings WHERE date = NEW.transaction_date)
+    BEGIN SELECT RAISE(ABORT, 'PERIOD_CLOSED'); END;
+    CREATE TRIGGER transactions_cash_closing_update BEFORE UPDATE ON transactions
+    WHEN (OLD.amount IS NOT NEW.amount OR OLD.currency IS NOT NEW.currency
+            OR OLD.transaction_type IS NOT NEW.transaction_type OR OLD.transaction_date IS NOT NEW.transaction_date
+            OR OLD.store_name IS NOT NEW.store_name OR OLD.category IS NOT NEW.category
+            OR OLD.subcategory IS NOT NEW.subcategory OR OLD.tax_rate IS NOT NEW.tax_rate
+            OR OLD.tax_amount IS NOT NEW.tax_amount OR OLD.deleted_at IS NOT NEW.deleted_at
+            OR OLD.payment_method IS NOT NEW.payment_method)
+        AND ((OLD.deleted_at IS NULL AND EXISTS (SELECT 1 FROM cash_closings WHERE date = OLD.transaction_date))
+            OR (NEW.deleted_at IS NULL AND EXISTS (SELECT 1 FROM cash_closings WHERE date = NEW.transaction_date)))
+    BEGIN SELECT RAISE(ABORT, 'PERIOD_CLOSED'); END;
+    CREATE TRIGGER transactions_cash_closing_delete BEFORE DELETE ON transactions
+    WHEN OLD.deleted_at IS NULL AND EXISTS (SELECT 1 FROM cash_closings WHERE date = OLD.transaction_date)
+    BEGIN SELECT RAISE(ABORT, 'PERIOD_CLOSED'); END;
+    CREATE TRIGGER transaction_splits_cash_closing_insert BEFORE INSERT ON transaction_splits
+    WHEN EXISTS (SELECT 1 FROM transactions t JOIN cash_closings c ON c.date = t.transaction_date
+                 WHERE t.id = NEW.transaction_id AND t.deleted_at IS NULL)
+    BEGIN SELECT RAISE(ABORT, 'PERIOD_CLOSED'); END;
+    CREATE TRIGGER transaction_splits_cash_closing_delete BEFORE DELETE ON transaction_splits
+    WHEN EXISTS (SELECT 1 FROM transactions t JOIN cash_closings c ON c.date = t.transaction_date
+                 WHERE t.id = OLD.transaction_id AND t.deleted_at IS NULL)
+    BEGIN SELECT RAISE(ABORT, 'PERIOD_CLOSED'); END;",
+    // v40: usuarios con PIN (ver `users`) y usuario que registró cada transacción y
+    // cada cambio del log de auditoría.
+    "CREATE TABLE users (
+        id TEXT PRIMARY KEY NOT NULL,
+        name TEXT NOT NULL UNIQUE COLLATE NOCASE,
+        pin_hash TEXT NOT NULL,
+        pin_salt TEXT NOT NULL,
+        created_at INTEGER NOT NULL,
+        updated_at INTEGER NOT NULL
+    );
+    ALTER TABLE transactions ADD COLUMN user_id TEXT;
+    CREATE INDEX idx_transactions_user ON transactions(user_id);
+    ALTER TABLE audit_log ADD COLUMN user_id TEXT;
+    CREATE INDEX idx_audit_log_user ON audit_log(user_id);",
+];
+
+/// Versión del esquema que deja `run_migrations`.
+pub fn schema_version() -> usize {
+    MIGRATIONS.len()
+}
+
+/// Aplica las migraciones pendientes dentro de una única transacción SQL. Si la
+/// base de datos de `path` ya tenía datos, antes se guarda una copia del archivo
+/// tal como estaba (`<archivo>.pre-v<versión>`), cifrado o no.
+pub fn run_migrations(conn: &Connection, path: Option<&Path>) -> Result<(), AppError> {
+    let current_version: usize = conn
+        .pragma_query_value(None, "user_version", |row| row.get::<_, i64>(0))
+        .map_err(db_error)? as usize;
+
+    if current_version > MIGRATIONS.len() {
+        error!("Database version {} is newer than supported {}", current_version, MIGRATIONS.len());
+        return Err(AppError::Database(format!(
+            "La base de datos es de una versión más reciente de la aplicación (esquema v{}).",
+            current_version
+        )));
+    }
+    if current_version == MIGRATIONS.len() {
+        debug!("Esquema de base de datos al día (versión {}).", current_version);
+        return Ok(());
+    }
+    if let Some(path) = path.filter(|p| current_version > 0 && p.exists()) {
+        backup_before_migration(path, &format!(".pre-v{}", current_version))?;
+    }
+
+    let tx = conn.unchecked_transaction().map_err(db_error)?;
+    for (index, migration) in MIGRATIONS.iter().enumerate().skip(current_version) {
+        let version = index + 1;
+        info!("Aplicando migración de esquema v{}", version);
+        tx.execute_batch(migration).map_err(|e| {
+            error!("Migration v{} failed: {}", version, e);
+            AppError::Database(format!("Falló la migración de la base de datos a la versión {}: {}", version, e))
+        })?;
+    }
+    tx.pragma_update(None, "user_version", MIGRATIONS.len() as i64).map_err(db_error)?;
+    tx.commit().map_err(db_error)?;
+    info!("Esquema de base de datos actualizado a la versión {}.", MIGRATIONS.len());
+    Ok(())
+}
+
+/// Copia `path` junto a sí mismo con `suffix` antes de migrarlo. Si ya existe una
+/// copia con ese nombre se conserva: es la del primer intento de migración.
+fn backup_before_migration(path: &Path, suffix: &str) -> Result<(), AppError> {
+    let backup_path = storage::sibling_path(path, suffix);
+    if backup_path.exists() {
+        warn!("Pre-migration backup {} already exists; keeping it.", backup_path.display());
+        return Ok(());
+    }
+    std::fs::copy(path, &backup_path).map_err(|e| {
+        error!("Could not back up {} before migrating: {}", path.display(), e);
+        AppError::Io(format!("No se pudo copiar {} antes de migrarlo: {}", path.display(), e))
+    })?;
+    info!("Copia previa a la migración guardada en {}", backup_path.display());
+    Ok(())
+}
+
+// --- Migraciones del Archivo JSON de Transacciones ---
+
+/// Versión actual del formato `{ "version": N, "transactions": [...] }`. Los archivos
+/// sin envoltorio (un array de transacciones) se consideran versión 0.
+pub const TRANSACTIONS_FILE_VERSION: u64 = 2;
+
+/// Pasos de migración del archivo JSON. La posición `i` convierte la versión `i`
+/// en la `i + 1`; igual que en `MIGRATIONS`, solo se añaden pasos al final.
+const FILE_MIGRATIONS: &[fn(Value) -> Result<Value, AppError>] = &[
+    file_v0_to_v1,
+    file_v1_to_v2,
+];
+
+/// v0 -> v1: el array de transacciones pasa a ir dentro del envoltorio versionado.
+fn file_v0_to_v1(transactions: Value) -> Result<Value, AppError> {
+    Ok(json!({ "version": 1, "transactions": transactions }))
+}
+
+/// v1 -> v2: cada transacción lleva su fecha y sus fechas de creación y modificación,
+/// que se deducen de `timestamp`.
+fn file_v1_to_v2(mut file: Value) -> Result<Value, AppError> {
+    let transactions = file
+        .get_mut("transactions")
+        .and_then(Value::as_array_mut)
+        .ok_or_else(|| AppError::validation("El archivo de transacciones no contiene la lista de transacciones."))?;
+    for transaction in transactions.iter_mut().filter_map(Value::as_object_mut) {
+        let timestamp = transaction.get("timestamp").and_then(Value::as_u64).unwrap_or(0);
+        let date = periods::local_date(timestamp).format("%Y-%m-%d").to_string();
+        transaction.entry("transaction_date").or_insert_with(|| json!(date));
+        transaction.entry("created_at").or_insert_with(|| json!(timestamp));
+        transaction.entry("updated_at").or_insert_with(|| json!(timestamp));
+    }
+    file["version"] = json!(2);
+    Ok(file)
+}
+
+#[derive(Deserialize)]
+struct TransactionsFile {
+    transactions: Vec<Transaction>,
+}
+
+/// Versión de un archivo de transacciones ya parseado.
+fn file_version(value: &Value) -> Result<u64, AppError> {
+    match value {
+        Value::Array(_) => Ok(0),
+        Value::Object(map) => map.get("version").and_then(Value::as_u64).ok_or_else(|| {
+            AppError::validation("El archivo de transacciones no indica su versión.")
+        }),
+        _ => Err(AppError::validation("El archivo de transacciones no tiene un formato reconocible.")),
+    }
+}
+
+/// Lee un archivo de transacciones de cualquier versión conocida y lo actualiza paso
+/// a paso hasta `TRANSACTIONS_FILE_VERSION`. Si el archivo era de una versión
+/// anterior, antes se guarda una copia como `<archivo>.v<versión>.bak`.
+pub fn load_transactions_file(path: &Path, data: &str) -> Result<Vec<Transaction>, AppError> {
+    let mut value: Value = serde_json::from_str(data).map_err(|e| {
+        error!("Error al parsear transacciones de {}: {}", path.display(), e);
+        AppError::validation(format!("Error al parsear datos de transacciones: {}", e))
+    })?;
+
+    let version = file_version(&value)?;
+    if version > TRANSACTIONS_FILE_VERSION {
+        error!("Transactions file {} has unsupported version {}", path.display(), version);
+        return Err(AppError::validation(format!(
+            "El archivo de transacciones es de una versión más reciente de la aplicación (v{}).",
+            version
+        )));
+    }
+    if version < TRANSACTIONS_FILE_VERSION {
+        backup_before_migration(path, &format!(".v{}.bak", version))?;
+        for (index, migrate) in FILE_MIGRATIONS.iter().enumerate().skip(version as usize) {
+            info!("Migrando {} a la versión {}", path.display(), index + 1);
+            value = migrate(value)?;
+        }
+    }
+
+    let file: TransactionsFile = serde_json::from_value(value).map_err(|e| {
+        error!("Error al leer transacciones migradas de {}: {}", path.display(), e);
+        AppError::validation(format!("Error al parsear datos de transacciones: {}", e))
+    })?;
+    Ok(file.transactions)
+}
@@ -0,0 +1,33 @@
+// src-tauri/src/money.rs
+
+use rust_decimal::{Decimal, RoundingStrategy};
+use log::error;
+
+use crate::error::AppError;
+use crate::validation;
+
+/// Decimales máximos admitidos en un importe introducido por el usuario.
+pub const MAX_AMOUNT_DECIMALS: u32 = 4;
+
+/// Redondea a céntimos, con los medios hacia arriba como en facturas y tickets.
+pub fn round_money(value: Decimal) -> Decimal {
+    value.round_dp_with_strategy(2, RoundingStrategy::MidpointAwayFromZero)
+}
+
+/// Comprueba que un importe sea positivo y no tenga más de `MAX_AMOUNT_DECIMALS`
+/// decimales significativos. `field` es el argumento del comando que se valida.
+pub fn validate_amount(field: &str, amount: Decimal, message: &str) -> Result<(), AppError> {
+    if amount <= Decimal::ZERO {
+        error!("Invalid {} received: {}", field, amount);
+        return Err(AppError::field_error(field, validation::NOT_POSITIVE, message));
+    }
+    if amount.normalize().scale() > MAX_AMOUNT_DECIMALS {
+        error!("Too many decimals in {}: {}", field, amount);
+        return Err(AppError::field_error(
+            field,
+            validation::TOO_MANY_DECIMALS,
+            format!("El importe admite como máximo {} decimales.", MAX_AMOUNT_DECIMALS),
+        ));
+    }
+    Ok(())
+}
@@ -0,0 +1,200 @@
+// src-tauri/src/notifications.rs
+
+use chrono::Datelike;
+use rusqlite::params;
+use serde::Serialize;
+use std::collections::HashSet;
+use tauri::{AppHandle, Manager, State};
+use tauri_plugin_notification::NotificationExt;
+use log::{debug, info, warn};
+
+use crate::budgets::{self, BudgetScope};
+use crate::currencies::RateTable;
+use crate::error::AppError;
+use crate::events;
+use crate::forecast;
+use crate::invoices::{self, InvoiceStatus};
+use crate::periods::{self, Period};
+use crate::petty_cash;
+use crate::settings;
+use crate::storage::{db_error, SqliteStorage, TransactionRepository};
+use crate::{AppState, TransactionType};
+
+/// Evento con el número de avisos pendientes, para el contador del frontend. Carga: `usize`.
+pub const ALERTS_CHANGED_EVENT: &str = "alerts://changed";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AlertKind {
+    /// Un movimiento recurrente que este mes ya debería haberse registrado.
+    RecurringDue,
+    InvoiceOverdue,
+    BudgetExceeded,
+    /// La caja chica está por debajo del umbral y hay que reponerla.
+    PettyCashLow,
+}
+
+/// Algo que requiere atención del usuario.
+#[derive(Debug, Clone, Serialize)]
+pub struct PendingAlert {
+    /// Identifica el aviso concreto (movimiento y mes, factura, presupuesto y periodo)
+    /// para notificarlo una sola vez.
+    pub key: String,
+    pub kind: AlertKind,
+    pub title: String,
+    pub message: String,
+}
+
+/// Reúne los avisos pendientes a día de hoy.
+pub fn collect_pending_alerts(db: &SqliteStorage) -> Result<Vec<PendingAlert>, AppError> {
+    let settings = settings::load_settings(db);
+    let rates = RateTable::load(db)?;
+    let today = periods::today();
+    let mut alerts = Vec::new();
+
+    // Recurrentes: el día habitual ya pasó y este mes no hay ninguna repetición.
+    let transactions = db.list_transactions()?;
+    let month_start = periods::local_midnight_timestamp(Period::Mensual.start_date(today));
+    let month = today.format("%Y-%m").to_string();
+    let forecast = forecast::build_forecast(&transactions, &[], &rates, settings.opening_balance, today, 1)?;
+    for item in forecast.recurring {
+        if today.day() < item.usual_day {
+            continue;
+        }
+        if transactions.iter().any(|t| t.timestamp >= month_start && item.matches(t)) {
+            continue;
+        }
+        let kind_label = match item.transaction_type {
+            TransactionType::Ingreso => "Ingreso",
+            TransactionType::Gasto => "Gasto",
+            TransactionType::Transferencia => "Transferencia",
+        };
+        alerts.push(PendingAlert {
+            key: format!(
+                "recurring:{}:{}:{}:{}",
+                item.transaction_type.to_string(),
+                item.store_name.trim().to_lowercase(),
+                item.description.trim().to_lowercase(),
+                month
+            ),
+            kind: AlertKind::RecurringDue,
+            title: format!("{} recurrente pendiente", kind_label),
+            message: format!(
+                "{} ({}) suele registrarse el día {} y este mes aún no aparece.",
+                item.store_name,
+                settings.format_money(item.amount, &rates.base_currency, &rates.base_currency),
+                item.usual_day
+            ),
+        });
+    }
+
+    for invoice in invoices::list_invoices(db.connection(), Some(InvoiceStatus::Overdue))? {
+        alerts.push(PendingAlert {
+            key: format!("invoice:{}", invoice.id),
+            kind: AlertKind::InvoiceOverdue,
+            title: format!("Factura {} vencida", invoice.number),
+            message: format!(
+                "{} debe {} desde el {}.",
+                invoice.client_name,
+                settings.format_money(invoice.total, &invoice.currency, &rates.base_currency),
+                settings.format_date(invoice.due_date)
+            ),
+        });
+    }
+
+    for status in budgets::current_statuses(db)? {
+        if status.percent_used < 100.0 {
+            continue;
+        }
+        let scope = match status.budget.scope {
+            BudgetScope::Categoria => "la categoría",
+            BudgetScope::Tienda => "la tienda",
+        };
+        alerts.push(PendingAlert {
+            key: format!("budget:{}:{}", status.budget.id, status.period_start),
+            kind: AlertKind::BudgetExceeded,
+            title: "Presupuesto superado".to_string(),
+            message: format!(
+                "Llevas {} de {} en {} {}.",
+                settings.format_money(status.spent, &status.currency, &status.currency),
+                settings.format_money(status.budget.limit_amount, &status.currency, &status.currency),
+                scope,
+                status.budget.scope_name
+            ),
+        });
+    }
+
+    if let Some(petty_cash) = petty_cash::current_status(db)?.filter(|s| s.low_balance) {
+        alerts.push(PendingAlert {
+            key: format!("petty_cash:{}", petty_cash.config.account_id),
+            kind: AlertKind::PettyCashLow,
+            title: "Caja chica baja".to_string(),
+            message: format!(
+                "Quedan {} en {}. Repón {} para volver al fondo fijo.",
+                settings.format_money(petty_cash.balance, &rates.base_currency, &rates.base_currency),
+                petty_cash.account_name,
+                settings.format_money(petty_cash.replenish_amount, &rates.base_currency, &rates.base_currency)
+            ),
+        });
+    }
+    Ok(alerts)
+}
+
+/// Devuelve los avisos que aún no se notificaron y los marca como notificados. Olvida
+/// los que ya no están pendientes, por si vuelven a estarlo más adelante.
+fn take_new_alerts<'a>(db: &SqliteStorage, alerts: &'a [PendingAlert]) -> Result<Vec<&'a PendingAlert>, AppError> {
+    let tx = db.connection().unchecked_transaction().map_err(db_error)?;
+    let mut stmt = tx.prepare("SELECT key FROM notified_alerts").map_err(db_error)?;
+    let notified: HashSet<String> = stmt
+        .query_map([], |row| row.get(0))
+        .map_err(db_error)?
+        .collect::<rusqlite::Result<_>>()
+        .map_err(db_error)?;
+    drop(stmt);
+    let pending: HashSet<&str> = alerts.iter().map(|a| a.key.as_str()).collect();
+    for key in notified.iter().filter(|k| !pending.contains(k.as_str())) {
+        tx.execute("DELETE FROM notified_alerts WHERE key = ?1", params![key]).map_err(db_error)?;
+    }
+    let now = periods::now_timestamp() as i64;
+    let mut new_alerts = Vec::new();
+    for alert in alerts.iter().filter(|a| !notified.contains(&a.key)) {
+        tx.execute("INSERT INTO notified_alerts (key, notified_at) VALUES (?1, ?2)", params![alert.key, now])
+            .map_err(db_error)?;
+        new_alerts.push(alert);
+    }
+    tx.commit().map_err(db_error)?;
+    Ok(new_alerts)
+}
+
+/// Revisa los avisos, envía una notificación del sistema por cada uno nuevo y avisa al
+/// frontend del total. Con los datos bloqueados no hace nada. Es la tarea `alerts`
+/// del planificador (ver `scheduler`).
+pub async fn check_alerts(app: &AppHandle) -> Result<String, AppError> {
+    let state = app.state::<AppState>();
+    if state.is_locked() {
+        return Ok("Datos bloqueados: no se han revisado los avisos.".to_string());
+    }
+    let db = state.db().await?;
+    let alerts = collect_pending_alerts(&db)?;
+    let new_alerts = take_new_alerts(&db, &alerts)?;
+    for alert in &new_alerts {
+        info!("Notifying pending alert: {}", alert.key);
+        if let Err(e) = app.notification().builder().title(&alert.title).body(&alert.message).show() {
+            warn!("Failed to show notification: {}", e);
+        }
+    }
+    events::emit(app, ALERTS_CHANGED_EVENT, alerts.len());
+    Ok(format!("{} avisos pendientes, {} nuevos.", alerts.len(), new_alerts.len()))
+}
+
+// --- Comandos Tauri ---
+
+/// Comando para obtener los avisos pendientes: movimientos recurrentes que aún no se
+/// registraron este mes, facturas vencidas, presupuestos superados y la caja chica
+/// por debajo del umbral.
+#[tauri::command]
+pub async fn get_pending_alerts_command(state: State<'_, AppState>) -> Result<Vec<PendingAlert>, AppError> {
+    debug!("Received get_pending_alerts_command.");
+    let db = state.db().await?;
+    collect_pending_alerts(&db)
+}
@@ -0,0 +1,122 @@
+// src-tauri/src/opened_files.rs
+
+use serde::Serialize;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use tauri::{AppHandle, DragDropEvent, Manager, RunEvent, State, Window, WindowEvent};
+use log::{debug, info, warn};
+
+use crate::error::AppError;
+use crate::events;
+use crate::import::{self, ImportPreview};
+use crate::startup;
+use crate::windows;
+use crate::AppState;
+
+// Archivos de movimientos que llegan a la aplicación sin pasar por el diálogo de
+// importar: abiertos con ella desde el explorador (la ruta llega como argumento o, en
+// macOS, con `RunEvent::Opened`; si ya estaba abierta, la pasa `instance`) o
+// arrastrados a la ventana. De cada uno se prepara la
+// vista previa de la importación; la interfaz la muestra y el usuario elige la tienda
+// y confirma con `import_file_command`.
+
+/// Evento con la vista previa de un archivo abierto o arrastrado. Carga: `OpenedFile`.
+pub const FILE_OPENED_EVENT: &str = "import://file-opened";
+
+/// Archivos abiertos antes de que la interfaz pudiera escuchar `FILE_OPENED_EVENT`.
+static PENDING: Mutex<Vec<PathBuf>> = Mutex::new(Vec::new());
+
+/// Archivo abierto con la aplicación o arrastrado a la ventana.
+#[derive(Debug, Clone, Serialize)]
+pub struct OpenedFile {
+    pub path: String,
+    /// Vista previa de la importación en `import::DEFAULT_IMPORT_STORE`.
+    pub preview: Option<ImportPreview>,
+    /// Por qué no se pudo leer el archivo, si falló.
+    pub error: Option<String>,
+}
+
+async fn open_file(state: &AppState, path: &Path) -> OpenedFile {
+    let preview = match state.db().await {
+        Ok(db) => import::preview_file(&db, path, None, None),
+        Err(e) => Err(e),
+    };
+    if let Err(e) = &preview {
+        warn!("Could not preview opened file {}: {}", path.display(), e);
+    }
+    OpenedFile { path: path.display().to_string(), error: preview.as_ref().err().map(|e| e.to_string()), preview: preview.ok() }
+}
+
+/// Prepara la vista previa de los archivos admitidos de `paths` y la emite con
+/// `FILE_OPENED_EVENT`. Si la carga inicial aún no terminó, la interfaz quizá no esté
+/// escuchando: se guardan para `take_opened_files_command`.
+pub fn open_files(app: &AppHandle, paths: Vec<PathBuf>) {
+    let paths: Vec<PathBuf> = paths.into_iter().filter(|p| import::is_supported_file(p)).collect();
+    if paths.is_empty() {
+        return;
+    }
+    info!("Opening {} statement files.", paths.len());
+    if let Err(e) = windows::focus_window(app, windows::MAIN_WINDOW_LABEL) {
+        warn!("Failed to show main window: {}", e);
+    }
+    if !startup::is_ready() {
+        if let Ok(mut pending) = PENDING.lock() {
+            pending.extend(paths);
+        }
+        return;
+    }
+    let app = app.clone();
+    tauri::async_runtime::spawn(async move {
+        for path in paths {
+            let opened = open_file(&app.state::<AppState>(), &path).await;
+            events::emit(&app, FILE_OPENED_EVENT, opened);
+        }
+    });
+}
+
+/// Archivos pasados como argumento al abrir la aplicación (p. ej. con "Abrir con").
+/// Se llama desde `setup`.
+pub fn open_launch_args(app: &AppHandle) {
+    let paths: Vec<PathBuf> = std::env::args()
+        .skip(1)
+        .filter(|arg| !arg.starts_with('-'))
+        .map(PathBuf::from)
+        .filter(|path| path.is_file())
+        .collect();
+    open_files(app, paths);
+}
+
+/// Archivos soltados sobre una ventana.
+pub fn handle_window_event(window: &Window, event: &WindowEvent) {
+    if let WindowEvent::DragDrop(DragDropEvent::Drop { paths, .. }) = event {
+        debug!("{} files dropped on window {}.", paths.len(), window.label());
+        open_files(window.app_handle(), paths.clone());
+    }
+}
+
+/// Archivos abiertos con la aplicación ya en marcha. Solo macOS los entrega así; en
+/// Windows y Linux llegan como argumentos (ver `open_launch_args`).
+pub fn handle_run_event(app: &AppHandle, event: &RunEvent) {
+    #[cfg(target_os = "macos")]
+    if let RunEvent::Opened { urls } = event {
+        open_files(app, urls.iter().filter_map(|url| url.to_file_path().ok()).collect());
+    }
+    #[cfg(not(target_os = "macos"))]
+    let _ = (app, event);
+}
+
+// --- Comandos Tauri ---
+
+/// Comando para recoger los archivos abiertos antes de que la interfaz estuviera
+/// lista (p. ej. el archivo con el que se abrió la aplicación), con su vista previa.
+/// Cada archivo se devuelve una sola vez.
+#[tauri::command]
+pub async fn take_opened_files_command(state: State<'_, AppState>) -> Result<Vec<OpenedFile>, AppError> {
+    debug!("Received take_opened_files_command.");
+    let paths = std::mem::take(&mut *PENDING.lock().map_err(|_| AppError::Internal("Lista de archivos no disponible.".to_string()))?);
+    let mut opened = Vec::with_capacity(paths.len());
+    for path in paths {
+        opened.push(open_file(&state, &path).await);
+    }
+    Ok(opened)
+}
@@ -0,0 +1,125 @@
+// src-tauri/src/payments.rs
+
+use chrono::NaiveDate;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use tauri::State;
+use log::debug;
+
+use crate::currencies::RateTable;
+use crate::error::AppError;
+use crate::periods::{self, Period};
+use crate::reports::GroupTotals;
+use crate::storage::TransactionRepository;
+use crate::{AppState, Transaction};
+
+/// Forma en que se pagó o cobró una transacción.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+pub enum PaymentMethod {
+    Efectivo,
+    Tarjeta,
+    Transferencia,
+    Bizum,
+    #[default]
+    Otro,
+}
+
+impl ToString for PaymentMethod {
+    fn to_string(&self) -> String {
+        match self {
+            PaymentMethod::Efectivo => "Efectivo".to_string(),
+            PaymentMethod::Tarjeta => "Tarjeta".to_string(),
+            PaymentMethod::Transferencia => "Transferencia".to_string(),
+            PaymentMethod::Bizum => "Bizum".to_string(),
+            PaymentMethod::Otro => "Otro".to_string(),
+        }
+    }
+}
+
+impl PaymentMethod {
+    pub const ALL: [PaymentMethod; 5] = [
+        PaymentMethod::Efectivo,
+        PaymentMethod::Tarjeta,
+        PaymentMethod::Transferencia,
+        PaymentMethod::Bizum,
+        PaymentMethod::Otro,
+    ];
+
+    pub fn parse(value: &str) -> Option<PaymentMethod> {
+        PaymentMethod::ALL.into_iter().find(|m| m.to_string() == value)
+    }
+}
+
+/// Totales de un medio de pago en el periodo, en la moneda base.
+#[derive(Debug, Clone, Serialize)]
+pub struct PaymentMethodTotals {
+    pub payment_method: PaymentMethod,
+    #[serde(flatten)]
+    pub totals: GroupTotals,
+}
+
+/// Ingresos y gastos del periodo desglosados por medio de pago. Incluye todos los
+/// medios, también los que no tuvieron movimientos, para cuadrar la caja.
+#[derive(Debug, Clone, Serialize)]
+pub struct PaymentMethodReport {
+    pub period: Period,
+    pub period_start: u64,
+    pub period_end: u64,
+    pub base_currency: String,
+    pub methods: Vec<PaymentMethodTotals>,
+    pub totals: GroupTotals,
+}
+
+/// Calcula los totales por medio de pago del periodo `[start, end)`.
+pub fn build_payment_method_report(
+    transactions: &[Transaction],
+    rates: &RateTable,
+    period: Period,
+    (start, end): (u64, u64),
+) -> Result<PaymentMethodReport, AppError> {
+    let mut by_method: BTreeMap<PaymentMethod, GroupTotals> =
+        PaymentMethod::ALL.into_iter().map(|m| (m, GroupTotals::default())).collect();
+    let mut totals = GroupTotals::default();
+
+    for transaction in transactions.iter().filter(|t| t.timestamp >= start && t.timestamp < end) {
+        let amount = rates.to_base(transaction.amount, &transaction.currency)?;
+        by_method
+            .entry(transaction.payment_method)
+            .or_default()
+            .add(&transaction.transaction_type, amount);
+        totals.add(&transaction.transaction_type, amount);
+    }
+
+    Ok(PaymentMethodReport {
+        period,
+        period_start: start,
+        period_end: end,
+        base_currency: rates.base_currency.clone(),
+        methods: by_method
+            .into_iter()
+            .map(|(payment_method, totals)| PaymentMethodTotals { payment_method, totals })
+            .collect(),
+        totals,
+    })
+}
+
+// --- Comandos Tauri ---
+
+/// Comando para obtener los totales por medio de pago del periodo (por defecto,
+/// `Mensual`) que contiene `date` (por defecto, hoy).
+#[tauri::command]
+pub async fn get_payment_method_totals_command(
+    state: State<'_, AppState>,
+    period: Option<Period>,
+    date: Option<NaiveDate>,
+) -> Result<PaymentMethodReport, AppError> {
+    let period = period.unwrap_or(Period::Mensual);
+    let date = date.unwrap_or_else(periods::today);
+    debug!("Received get_payment_method_totals_command: {:?} containing {}", period, date);
+    let bounds = period.bounds_containing(periods::local_midnight_timestamp(date));
+
+    let db = state.db().await?;
+    let rates = RateTable::load(&db)?;
+    let transactions = db.list_transactions()?;
+    build_payment_method_report(&transactions, &rates, period, bounds)
+}
@@ -0,0 +1,65 @@
+// src-tauri/src/pdf.rs
+
+use printpdf::{BuiltinFont, IndirectFontRef, Mm, PdfDocument, PdfDocumentReference, PdfLayerReference};
+use std::io::BufWriter;
+use log::error;
+
+use crate::error::AppError;
+
+/// Página A4.
+const PAGE_WIDTH_MM: f32 = 210.0;
+const PAGE_HEIGHT_MM: f32 = 297.0;
+pub const MARGIN_MM: f32 = 20.0;
+const LINE_HEIGHT_MM: f32 = 6.0;
+
+fn pdf_error(e: printpdf::Error) -> AppError {
+    error!("PDF rendering failed: {}", e);
+    AppError::Internal(format!("Error al generar el PDF: {}", e))
+}
+
+/// Escribe texto en la página en curso y pasa a otra cuando se llena.
+pub struct PdfWriter {
+    doc: PdfDocumentReference,
+    layer: PdfLayerReference,
+    layer_name: String,
+    regular: IndirectFontRef,
+    bold: IndirectFontRef,
+    y: f32,
+}
+
+impl PdfWriter {
+    /// Documento A4 con una primera página vacía. `layer_name` nombra la capa de
+    /// cada página.
+    pub fn new(title: &str, layer_name: &str) -> Result<Self, AppError> {
+        let (doc, page, layer) = PdfDocument::new(title, Mm(PAGE_WIDTH_MM), Mm(PAGE_HEIGHT_MM), layer_name);
+        let regular = doc.add_builtin_font(BuiltinFont::Helvetica).map_err(pdf_error)?;
+        let bold = doc.add_builtin_font(BuiltinFont::HelveticaBold).map_err(pdf_error)?;
+        let layer = doc.get_page(page).get_layer(layer);
+        Ok(PdfWriter { doc, layer, layer_name: layer_name.to_owned(), regular, bold, y: PAGE_HEIGHT_MM - MARGIN_MM })
+    }
+
+    fn ensure_space(&mut self) {
+        if self.y < MARGIN_MM {
+            let (page, layer) = self.doc.add_page(Mm(PAGE_WIDTH_MM), Mm(PAGE_HEIGHT_MM), &self.layer_name);
+            self.layer = self.doc.get_page(page).get_layer(layer);
+            self.y = PAGE_HEIGHT_MM - MARGIN_MM;
+        }
+    }
+
+    pub fn text(&mut self, x: f32, text: &str, size: f32, bold: bool) {
+        self.ensure_space();
+        let font = if bold { &self.bold } else { &self.regular };
+        self.layer.use_text(text, size, Mm(x), Mm(self.y), font);
+    }
+
+    pub fn newline(&mut self) {
+        self.y -= LINE_HEIGHT_MM;
+    }
+
+    /// Devuelve el PDF terminado.
+    pub fn finish(self) -> Result<Vec<u8>, AppError> {
+        let mut bytes = Vec::new();
+        self.doc.save(&mut BufWriter::new(&mut bytes)).map_err(pdf_error)?;
+        Ok(bytes)
+    }
+}
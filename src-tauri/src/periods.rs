@@ -0,0 +1,113 @@
+// src-tauri/src/periods.rs
+
+use chrono::{Datelike, Duration, Local, NaiveDate, TimeZone};
+use serde::{Deserialize, Serialize};
+
+/// Periodo de calendario usado por presupuestos e informes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Period {
+    Semanal,
+    Mensual,
+    Trimestral,
+    Anual,
+}
+
+impl ToString for Period {
+    fn to_string(&self) -> String {
+        match self {
+            Period::Semanal => "Semanal".to_string(),
+            Period::Mensual => "Mensual".to_string(),
+            Period::Trimestral => "Trimestral".to_string(),
+            Period::Anual => "Anual".to_string(),
+        }
+    }
+}
+
+impl Period {
+    pub fn parse(value: &str) -> Option<Period> {
+        match value {
+            "Semanal" => Some(Period::Semanal),
+            "Mensual" => Some(Period::Mensual),
+            "Trimestral" => Some(Period::Trimestral),
+            "Anual" => Some(Period::Anual),
+            _ => None,
+        }
+    }
+
+    /// Primer día del periodo que contiene `date`.
+    pub fn start_date(self, date: NaiveDate) -> NaiveDate {
+        match self {
+            Period::Semanal => date - Duration::days(date.weekday().num_days_from_monday() as i64),
+            Period::Mensual => first_day_of_month(date.year(), date.month()),
+            Period::Trimestral => first_day_of_month(date.year(), (date.month0() / 3) * 3 + 1),
+            Period::Anual => first_day_of_month(date.year(), 1),
+        }
+    }
+
+    /// Primer día del periodo siguiente al que empieza en `start`.
+    pub fn next_start_date(self, start: NaiveDate) -> NaiveDate {
+        match self {
+            Period::Semanal => start + Duration::days(7),
+            Period::Mensual => add_months(start, 1),
+            Period::Trimestral => add_months(start, 3),
+            Period::Anual => add_months(start, 12),
+        }
+    }
+
+    /// Límites `[inicio, fin)` en segundos Unix del periodo que contiene `timestamp`.
+    pub fn bounds_containing(self, timestamp: u64) -> (u64, u64) {
+        let start = self.start_date(local_date(timestamp));
+        let end = self.next_start_date(start);
+        (local_midnight_timestamp(start), local_midnight_timestamp(end))
+    }
+}
+
+fn first_day_of_month(year: i32, month: u32) -> NaiveDate {
+    NaiveDate::from_ymd_opt(year, month, 1).expect("mes válido")
+}
+
+/// Suma `months` meses a una fecha que cae en día 1.
+pub fn add_months(date: NaiveDate, months: u32) -> NaiveDate {
+    let total = date.year() * 12 + date.month0() as i32 + months as i32;
+    first_day_of_month(total.div_euclid(12), total.rem_euclid(12) as u32 + 1)
+}
+
+/// Fecha local (zona horaria del equipo) de un timestamp en segundos Unix.
+pub fn local_date(timestamp: u64) -> NaiveDate {
+    Local
+        .timestamp_opt(timestamp as i64, 0)
+        .single()
+        .map(|dt| dt.date_naive())
+        .unwrap_or_default()
+}
+
+/// Timestamp Unix de la medianoche local del día `date`.
+pub fn local_midnight_timestamp(date: NaiveDate) -> u64 {
+    let midnight = date.and_hms_opt(0, 0, 0).expect("hora válida");
+    Local
+        .from_local_datetime(&midnight)
+        .earliest()
+        .map(|dt| dt.timestamp().max(0) as u64)
+        // Si la medianoche no existe por un cambio de hora, usamos UTC como aproximación.
+        .unwrap_or_else(|| midnight.and_utc().timestamp().max(0) as u64)
+}
+
+/// Fecha local de hoy.
+pub fn today() -> NaiveDate {
+    Local::now().date_naive()
+}
+
+/// Momento con el que se guarda una transacción del día `date`: ahora si es hoy y,
+/// si no, el mediodía local de ese día, lejos de los cambios de hora.
+pub fn timestamp_for_date(date: NaiveDate) -> u64 {
+    if date == today() {
+        now_timestamp()
+    } else {
+        local_midnight_timestamp(date) + 12 * 60 * 60
+    }
+}
+
+/// Timestamp Unix actual.
+pub fn now_timestamp() -> u64 {
+    chrono::Utc::now().timestamp() as u64
+}
@@ -0,0 +1,335 @@
+// src-tauri/src/petty_cash.rs
+
+use chrono::{Duration, NaiveDate};
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use tauri::{AppHandle, State};
+use log::{debug, info, warn};
+
+use crate::accounts::{self, AccountKind};
+use crate::audit;
+use crate::currencies::{self, RateTable};
+use crate::error::AppError;
+use crate::events;
+use crate::history::{Change, HistoryEntry};
+use crate::merge;
+use crate::money::{self, round_money};
+use crate::payments::PaymentMethod;
+use crate::periods::{self, Period};
+use crate::storage::{SqliteStorage, TransactionRepository};
+use crate::stores;
+use crate::validation::{self, validate_transaction_date};
+use crate::{AppState, Transaction, TransactionType};
+
+// Caja chica con fondo fijo: una cuenta de efectivo con un importe que se repone
+// periódicamente. Los gastos menudos se registran con esa cuenta; cuando el saldo baja
+// del umbral se avisa (ver `notifications`) y la reposición es una transferencia desde
+// la cuenta de origen (normalmente el banco) por lo gastado, para volver al fondo.
+
+const PETTY_CASH_KEY: &str = "petty_cash";
+/// Tienda de la caja chica si no se indica otra.
+pub const DEFAULT_PETTY_CASH_STORE: &str = "Caja chica";
+const UNCATEGORIZED: &str = "Sin categoría";
+
+/// Configuración de la caja chica, guardada en `app_settings`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PettyCashConfig {
+    /// Cuenta de efectivo de la caja chica.
+    pub account_id: String,
+    /// Tienda de destino de las reposiciones.
+    pub store_name: String,
+    /// Fondo fijo: saldo al que se repone la caja.
+    pub float_amount: Decimal,
+    /// Saldo por debajo del cual se avisa de que hay que reponer.
+    pub low_balance_threshold: Decimal,
+    /// De dónde sale el dinero de las reposiciones.
+    pub source_account_id: Option<String>,
+    pub source_store: String,
+}
+
+/// Estado actual de la caja chica, en la moneda base.
+#[derive(Debug, Clone, Serialize)]
+pub struct PettyCashStatus {
+    #[serde(flatten)]
+    pub config: PettyCashConfig,
+    pub account_name: String,
+    pub balance: Decimal,
+    pub low_balance: bool,
+    /// Lo que hay que reponer para volver al fondo fijo (0 si ya está completo).
+    pub replenish_amount: Decimal,
+}
+
+/// Gastos de la caja chica por categoría.
+#[derive(Debug, Clone, Serialize)]
+pub struct PettyCashCategoryTotal {
+    pub category: String,
+    pub amount: Decimal,
+    pub transaction_count: usize,
+}
+
+/// Informe de la caja chica entre dos fechas, en la moneda base.
+#[derive(Debug, Clone, Serialize)]
+pub struct PettyCashReport {
+    pub from: NaiveDate,
+    pub to: NaiveDate,
+    pub float_amount: Decimal,
+    pub opening_balance: Decimal,
+    pub expenses: Decimal,
+    pub expenses_by_category: Vec<PettyCashCategoryTotal>,
+    /// Reposiciones recibidas y su total.
+    pub replenishments: Vec<Transaction>,
+    pub replenished: Decimal,
+    pub closing_balance: Decimal,
+    /// Gastos y demás movimientos de la cuenta en el periodo, por orden.
+    pub transactions: Vec<Transaction>,
+}
+
+pub fn load_config(db: &SqliteStorage) -> Option<PettyCashConfig> {
+    match db.get_setting(PETTY_CASH_KEY) {
+        Ok(Some(json)) => serde_json::from_str(&json)
+            .map_err(|e| warn!("Invalid petty cash settings, ignoring them: {}", e))
+            .ok(),
+        Ok(None) => None,
+        Err(e) => {
+            warn!("Could not load petty cash settings: {}", e);
+            None
+        }
+    }
+}
+
+fn require_config(db: &SqliteStorage) -> Result<PettyCashConfig, AppError> {
+    load_config(db).ok_or_else(|| AppError::NotFound("La caja chica no está configurada.".to_string()))
+}
+
+/// Saldo de la cuenta `account_id` en la moneda base con las transacciones hasta `to`
+/// (segundos Unix, inclusivo).
+fn account_balance(db: &SqliteStorage, account_id: &str, to: Option<u64>) -> Result<Decimal, AppError> {
+    let accounts = accounts::list_accounts(db.connection())?;
+    let balances = accounts::build_account_balances(&db.list_transactions()?, &accounts, &RateTable::load(db)?, to)?;
+    Ok(balances
+        .into_iter()
+        .find(|b| b.account_id.as_deref() == Some(account_id))
+        .map(|b| round_money(b.balance))
+        .unwrap_or_default())
+}
+
+/// Estado de la caja chica, o `None` si no está configurada.
+pub fn current_status(db: &SqliteStorage) -> Result<Option<PettyCashStatus>, AppError> {
+    let Some(config) = load_config(db) else { return Ok(None) };
+    let Some(account) = accounts::get_account(db.connection(), &config.account_id)? else {
+        warn!("Petty cash account {} no longer exists.", config.account_id);
+        return Ok(None);
+    };
+    let balance = account_balance(db, &config.account_id, None)?;
+    Ok(Some(PettyCashStatus {
+        account_name: account.name,
+        low_balance: balance < config.low_balance_threshold,
+        replenish_amount: (config.float_amount - balance).max(Decimal::ZERO),
+        balance,
+        config,
+    }))
+}
+
+fn validate_config(db: &SqliteStorage, config: PettyCashConfig) -> Result<PettyCashConfig, AppError> {
+    let account = accounts::get_account(db.connection(), &config.account_id)?
+        .ok_or_else(|| AppError::invalid_field("account_id", "La cuenta de la caja chica no existe."))?;
+    if account.kind != AccountKind::Efectivo {
+        return Err(AppError::invalid_field("account_id", "La caja chica debe ser una cuenta de efectivo."));
+    }
+    money::validate_amount("float_amount", config.float_amount, "El fondo fijo debe ser positivo.")?;
+    let float_amount = round_money(config.float_amount);
+    let low_balance_threshold = round_money(config.low_balance_threshold);
+    if low_balance_threshold.is_sign_negative() || low_balance_threshold > float_amount {
+        return Err(AppError::invalid_field(
+            "low_balance_threshold",
+            "El umbral de aviso debe estar entre 0 y el fondo fijo.",
+        ));
+    }
+    let source_account_id = accounts::resolve_account_id(db.connection(), "source_account_id", config.source_account_id)?;
+    if source_account_id.as_deref() == Some(account.id.as_str()) {
+        return Err(AppError::invalid_field("source_account_id", "La reposición no puede salir de la propia caja chica."));
+    }
+    let store_name = stores::non_empty(Some(config.store_name)).unwrap_or_else(|| DEFAULT_PETTY_CASH_STORE.to_string());
+    let source_store = stores::non_empty(Some(config.source_store))
+        .ok_or_else(|| AppError::invalid_field("source_store", "Indica la tienda de la que sale la reposición."))?;
+    if source_store == store_name {
+        return Err(AppError::invalid_field("source_store", "El origen y la caja chica deben ser tiendas distintas."));
+    }
+    Ok(PettyCashConfig {
+        account_id: account.id,
+        store_name,
+        float_amount,
+        low_balance_threshold,
+        source_account_id,
+        source_store,
+    })
+}
+
+/// Informe de la caja chica de `[from, to]`: saldo al empezar, gastos por categoría,
+/// reposiciones y saldo al terminar.
+pub fn build_report(db: &SqliteStorage, config: &PettyCashConfig, from: NaiveDate, to: NaiveDate) -> Result<PettyCashReport, AppError> {
+    let start = periods::local_midnight_timestamp(from);
+    let end = periods::local_midnight_timestamp(to + Duration::days(1));
+    let rates = RateTable::load(db)?;
+    let account_id = Some(config.account_id.clone());
+    let transactions: Vec<Transaction> = db
+        .list_transactions()?
+        .into_iter()
+        .filter(|t| t.timestamp >= start && t.timestamp < end)
+        .filter(|t| t.account_id == account_id || t.transfer_account_id == account_id)
+        .collect();
+
+    let mut by_category: BTreeMap<String, (Decimal, usize)> = BTreeMap::new();
+    let mut replenishments = Vec::new();
+    let (mut expenses, mut replenished) = (Decimal::ZERO, Decimal::ZERO);
+    for t in transactions.iter() {
+        let amount = rates.to_base(t.amount, &t.currency)?;
+        match t.transaction_type {
+            TransactionType::Gasto if t.account_id == account_id => {
+                let entry = by_category.entry(t.category.clone().unwrap_or_else(|| UNCATEGORIZED.to_string())).or_default();
+                entry.0 += amount;
+                entry.1 += 1;
+                expenses += amount;
+            }
+            TransactionType::Transferencia if t.transfer_account_id == account_id && t.account_id != account_id => {
+                replenished += amount;
+                replenishments.push(t.clone());
+            }
+            _ => {}
+        }
+    }
+    let mut expenses_by_category: Vec<PettyCashCategoryTotal> = by_category
+        .into_iter()
+        .map(|(category, (amount, transaction_count))| PettyCashCategoryTotal {
+            category,
+            amount: round_money(amount),
+            transaction_count,
+        })
+        .collect();
+    expenses_by_category.sort_by(|a, b| b.amount.cmp(&a.amount));
+
+    Ok(PettyCashReport {
+        from,
+        to,
+        float_amount: config.float_amount,
+        opening_balance: account_balance(db, &config.account_id, start.checked_sub(1))?,
+        expenses: round_money(expenses),
+        expenses_by_category,
+        replenishments,
+        replenished: round_money(replenished),
+        closing_balance: account_balance(db, &config.account_id, Some(end - 1))?,
+        transactions,
+    })
+}
+
+// --- Comandos Tauri ---
+
+/// Comando para obtener el estado de la caja chica: saldo, si está por debajo del
+/// umbral y cuánto hay que reponer. `None` si no está configurada.
+#[tauri::command]
+pub async fn get_petty_cash_command(state: State<'_, AppState>) -> Result<Option<PettyCashStatus>, AppError> {
+    debug!("Received get_petty_cash_command.");
+    current_status(&state.db().await?)
+}
+
+/// Comando para configurar la caja chica: su cuenta de efectivo, el fondo fijo, el
+/// umbral de aviso y de dónde sale el dinero de las reposiciones.
+#[tauri::command]
+pub async fn set_petty_cash_command(
+    state: State<'_, AppState>,
+    config: PettyCashConfig,
+) -> Result<Option<PettyCashStatus>, AppError> {
+    debug!("Received set_petty_cash_command: {:?}", config);
+    let db = state.db().await?;
+    let config = validate_config(&db, config)?;
+    let before = load_config(&db);
+    let json = serde_json::to_string(&config).map_err(|e| AppError::Internal(e.to_string()))?;
+    db.set_setting(PETTY_CASH_KEY, &json)?;
+    audit::record(db.connection(), "set_petty_cash_command", None, audit::snapshot(&before), audit::snapshot(&config));
+    current_status(&db)
+}
+
+/// Comando para reponer la caja chica con una transferencia desde la cuenta y la
+/// tienda de origen. Sin `amount` se repone lo necesario para volver al fondo fijo.
+#[tauri::command]
+pub async fn replenish_petty_cash_command(
+    state: State<'_, AppState>,
+    app: AppHandle,
+    amount: Option<Decimal>,
+    transaction_date: Option<NaiveDate>,
+) -> Result<Transaction, AppError> {
+    debug!("Received replenish_petty_cash_command: {:?}", amount);
+    let db = state.db().await?;
+    let config = require_config(&db)?;
+    let amount = match amount {
+        Some(amount) => amount,
+        None => config.float_amount - account_balance(&db, &config.account_id, None)?,
+    };
+    if amount <= Decimal::ZERO {
+        return Err(AppError::invalid_field("amount", "La caja chica ya tiene el fondo completo."));
+    }
+    money::validate_amount("amount", amount, "El monto debe ser positivo.")?;
+    let transaction_date = validate_transaction_date(transaction_date.unwrap_or_else(periods::today))?;
+    let now = periods::now_timestamp();
+    let transaction = Transaction {
+        id: uuid::Uuid::new_v4().to_string(),
+        transaction_type: TransactionType::Transferencia,
+        amount: round_money(amount),
+        description: format!("Reposición de {}", config.store_name),
+        store_name: config.source_store.clone(),
+        timestamp: periods::timestamp_for_date(transaction_date),
+        category: None,
+        subcategory: None,
+        currency: currencies::get_base_currency(&db)?,
+        receipt_paths: Vec::new(),
+        deleted_at: None,
+        tax_rate: None,
+        tax_amount: None,
+        tags: Vec::new(),
+        external_id: None,
+        transaction_date,
+        created_at: now,
+        updated_at: now,
+        notes: String::new(),
+        custom_fields: HashMap::new(),
+        payment_method: PaymentMethod::Efectivo,
+        contact_id: None,
+        transfer_store: Some(config.store_name.clone()),
+        account_id: config.source_account_id.clone(),
+        transfer_account_id: Some(config.account_id.clone()),
+        user_id: None,
+        revision: merge::initial_revision(),
+        device_id: String::new(),
+        sequence: 0,
+    };
+    validation::enforce_policy(&db, &transaction)?;
+    db.insert_transaction(&transaction)?;
+
+    let changes = vec![Change::Insert(transaction.clone())];
+    audit::record_changes(db.connection(), "replenish_petty_cash_command", &changes);
+    events::emit_transaction_changes(&app, &changes);
+    state.history.lock().unwrap().record(HistoryEntry::new("Reponer caja chica", changes));
+    info!("Petty cash replenished with {} {}.", transaction.amount, transaction.currency);
+    Ok(transaction)
+}
+
+/// Comando para obtener el informe de la caja chica entre `from` y `to`, ambos
+/// incluidos (por defecto, el mes en curso).
+#[tauri::command]
+pub async fn get_petty_cash_report_command(
+    state: State<'_, AppState>,
+    from: Option<NaiveDate>,
+    to: Option<NaiveDate>,
+) -> Result<PettyCashReport, AppError> {
+    debug!("Received get_petty_cash_report_command: {:?} - {:?}", from, to);
+    let today = periods::today();
+    let from = from.unwrap_or_else(|| Period::Mensual.start_date(today));
+    let to = to.unwrap_or(today);
+    if to < from {
+        return Err(AppError::invalid_field("to", "La fecha final no puede ser anterior a la inicial."));
+    }
+    let db = state.db().await?;
+    let config = require_config(&db)?;
+    build_report(&db, &config, from, to)
+}
@@ -0,0 +1,219 @@
+// src-tauri/src/privacy.rs
+
+use rusqlite::Connection;
+use serde::{Deserialize, Serialize};
+use tauri::State;
+use log::{debug, info};
+
+use crate::ai::{AiBackend, AiProvider};
+use crate::anomalies;
+use crate::assistant;
+use crate::error::AppError;
+use crate::periods::Period;
+use crate::settings::{self, Settings};
+use crate::stores;
+use crate::summaries;
+use crate::taxes::{self, Quarter};
+use crate::text_entry;
+use crate::AppState;
+
+/// Dato que se ha sustituido por un marcador.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RedactionKind {
+    Store,
+    Description,
+}
+
+/// Marcador enviado a la IA en lugar de `original`.
+#[derive(Debug, Clone, Serialize)]
+pub struct Redaction {
+    pub kind: RedactionKind,
+    pub placeholder: String,
+    pub original: String,
+}
+
+/// Oculta los nombres de las tiendas y las descripciones antes de enviarlos a la IA,
+/// según el modo privacidad de los ajustes. Cada valor distinto recibe un marcador
+/// (`[TIENDA_1]`, `[DESCRIPCION_1]`) que es el mismo en todo el prompt, y `restore`
+/// devuelve los originales en la respuesta.
+#[derive(Debug, Default)]
+pub struct Redactor {
+    stores: bool,
+    descriptions: bool,
+    /// Tiendas que se buscan en los textos libres, de la más larga a la más corta.
+    known_stores: Vec<String>,
+    redactions: Vec<Redaction>,
+}
+
+impl Redactor {
+    /// Redactor con la configuración de `settings`. Carga las tiendas conocidas para
+    /// ocultarlas también cuando aparecen dentro de un texto.
+    pub fn new(conn: &Connection, settings: &Settings) -> Result<Self, AppError> {
+        let mut redactor = Redactor {
+            stores: settings.ai_redact_store_names,
+            descriptions: settings.ai_redact_descriptions,
+            ..Redactor::default()
+        };
+        if redactor.stores {
+            for store in stores::list_stores(conn)? {
+                redactor.add_known_store(&store.name);
+            }
+        }
+        Ok(redactor)
+    }
+
+    fn add_known_store(&mut self, name: &str) {
+        let name = name.trim();
+        if name.is_empty() || self.known_stores.iter().any(|s| s.to_lowercase() == name.to_lowercase()) {
+            return;
+        }
+        self.known_stores.push(name.to_owned());
+        self.known_stores.sort_by_key(|s| std::cmp::Reverse(s.len()));
+    }
+
+    /// Marcador de `original`, el mismo si ya se había ocultado.
+    fn placeholder(&mut self, kind: RedactionKind, original: &str) -> String {
+        if let Some(existing) = self.redactions.iter().find(|r| r.kind == kind && r.original == original) {
+            return existing.placeholder.clone();
+        }
+        let prefix = match kind {
+            RedactionKind::Store => "TIENDA",
+            RedactionKind::Description => "DESCRIPCION",
+        };
+        let number = self.redactions.iter().filter(|r| r.kind == kind).count() + 1;
+        let placeholder = format!("[{}_{}]", prefix, number);
+        self.redactions.push(Redaction { kind, placeholder: placeholder.clone(), original: original.to_owned() });
+        placeholder
+    }
+
+    /// Nombre de tienda tal como se envía a la IA.
+    pub fn store(&mut self, name: &str) -> String {
+        if !self.stores || name.trim().is_empty() {
+            return name.to_owned();
+        }
+        let name = name.trim();
+        self.add_known_store(name);
+        // Misma tienda aunque cambien las mayúsculas: un único marcador.
+        let canonical = self.known_stores.iter().find(|s| s.to_lowercase() == name.to_lowercase()).cloned();
+        self.placeholder(RedactionKind::Store, &canonical.unwrap_or_else(|| name.to_owned()))
+    }
+
+    /// Descripción de una transacción tal como se envía a la IA.
+    pub fn description(&mut self, description: &str) -> String {
+        if !self.descriptions || description.trim().is_empty() {
+            return description.to_owned();
+        }
+        self.placeholder(RedactionKind::Description, description)
+    }
+
+    /// Texto libre (una pregunta o una frase del usuario) con los nombres de las
+    /// tiendas conocidas sustituidos por sus marcadores. El resto se envía tal cual.
+    pub fn text(&mut self, text: &str) -> String {
+        if !self.stores {
+            return text.to_owned();
+        }
+        let mut output = text.to_owned();
+        for store in self.known_stores.clone() {
+            let lower = store.to_lowercase();
+            let mut result = String::with_capacity(output.len());
+            let mut last = 0;
+            for (start, _) in output.char_indices() {
+                if start < last {
+                    continue;
+                }
+                let end = start + store.len();
+                let Some(candidate) = output.get(start..end) else { continue };
+                let boundary_before = output[..start].chars().next_back().map_or(true, |c| !c.is_alphanumeric());
+                let boundary_after = output[end..].chars().next().map_or(true, |c| !c.is_alphanumeric());
+                if boundary_before && boundary_after && candidate.to_lowercase() == lower {
+                    result.push_str(&output[last..start]);
+                    result.push_str(&self.placeholder(RedactionKind::Store, &store));
+                    last = end;
+                }
+            }
+            result.push_str(&output[last..]);
+            output = result;
+        }
+        output
+    }
+
+    /// Respuesta de la IA con los marcadores sustituidos por los datos originales.
+    pub fn restore(&self, text: &str) -> String {
+        self.redactions
+            .iter()
+            .fold(text.to_owned(), |text, r| text.replace(&r.placeholder, &r.original))
+    }
+
+    /// Datos ocultados hasta ahora.
+    pub fn redactions(&self) -> &[Redaction] {
+        &self.redactions
+    }
+}
+
+/// Petición a la IA de la que se quiere ver lo que se enviaría.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum AiPayloadRequest {
+    MonthlySummary { month: String },
+    AccountingQuestion { question: String },
+    AnomalyExplanation { period: Option<Period> },
+    TaxTips { quarter: Quarter },
+    TransactionText { text: String },
+}
+
+/// Lo que saldría del equipo con una petición a la IA.
+#[derive(Debug, Clone, Serialize)]
+pub struct AiPayloadPreview {
+    pub provider: AiProvider,
+    pub model: String,
+    /// Prompt exacto, con los datos ya ocultados.
+    pub prompt: String,
+    /// Marcadores usados y el dato que sustituyen. Se quedan en el equipo.
+    pub redactions: Vec<Redaction>,
+}
+
+// --- Comandos Tauri ---
+
+/// Comando para ver el prompt exacto que se enviaría a la IA con una petición, con los
+/// datos ocultados por el modo privacidad, sin enviar nada.
+#[tauri::command]
+pub async fn preview_ai_payload_command(
+    state: State<'_, AppState>,
+    request: AiPayloadRequest,
+) -> Result<AiPayloadPreview, AppError> {
+    debug!("Received preview_ai_payload_command: {:?}", request);
+    let db = state.db().await?;
+    let settings = settings::load_settings(&db);
+    let mut redactor = Redactor::new(db.connection(), &settings)?;
+    let prompt = match request {
+        AiPayloadRequest::MonthlySummary { month } => {
+            let start = summaries::parse_month(&month)?;
+            let context = summaries::load_month_context(&db, start)?;
+            summaries::build_summary_prompt(db.connection(), &context, &mut redactor)?
+        }
+        AiPayloadRequest::AccountingQuestion { question } => {
+            let context = assistant::load_books_context(&db)?;
+            assistant::build_question_prompt(&question, &context, &mut redactor)?
+        }
+        AiPayloadRequest::AnomalyExplanation { period } => {
+            let anomalies = anomalies::load_anomalies(&db, &settings, period.unwrap_or(Period::Mensual))?;
+            if anomalies.is_empty() {
+                return Err(AppError::validation("No hay gastos inusuales en el periodo: no se enviaría nada."));
+            }
+            anomalies::build_explanation_prompt(db.connection(), &anomalies, &mut redactor)?
+        }
+        AiPayloadRequest::TaxTips { quarter } => taxes::build_tax_tips_prompt(&db, quarter)?,
+        AiPayloadRequest::TransactionText { text } => {
+            text_entry::build_text_prompt(db.connection(), &text, &mut redactor)?.prompt
+        }
+    };
+    let client = settings.ai_client();
+    info!("AI payload preview built ({} redactions).", redactor.redactions().len());
+    Ok(AiPayloadPreview {
+        provider: settings.ai_provider,
+        model: client.model().to_owned(),
+        prompt,
+        redactions: redactor.redactions().to_vec(),
+    })
+}
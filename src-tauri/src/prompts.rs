@@ -0,0 +1,271 @@
+// src-tauri/src/prompts.rs
+
+use rusqlite::{params, Connection, OptionalExtension};
+use serde::Serialize;
+use std::collections::HashMap;
+use tauri::{AppHandle, State};
+use log::{debug, error, info};
+
+use crate::audit;
+use crate::error::AppError;
+use crate::events::{self, ChangeAction};
+use crate::periods;
+use crate::storage::db_error;
+use crate::AppState;
+
+/// Longitud máxima de un prompt personalizado.
+const MAX_TEMPLATE_CHARS: usize = 20_000;
+
+pub const MONTHLY_SUMMARY: &str = "monthly_summary";
+pub const ANOMALY_EXPLANATION: &str = "anomaly_explanation";
+pub const TAX_TIPS: &str = "tax_tips";
+pub const TRANSACTION_TEXT: &str = "transaction_text";
+
+/// Plantilla de prompt de la aplicación. Los `{marcadores}` se sustituyen por datos
+/// reales al usarla; el resto del texto, llaves incluidas, se envía tal cual.
+struct DefaultTemplate {
+    name: &'static str,
+    title: &'static str,
+    description: &'static str,
+    /// Sube cada vez que cambia `template`, para avisar a quien la personalizó.
+    version: u32,
+    placeholders: &'static [&'static str],
+    template: &'static str,
+}
+
+const DEFAULT_TEMPLATES: &[DefaultTemplate] = &[
+    DefaultTemplate {
+        name: MONTHLY_SUMMARY,
+        title: "Resumen mensual",
+        description: "Resumen narrativo de un mes comparado con el anterior.",
+        version: 1,
+        placeholders: &["month", "previous_month", "currency", "context_json"],
+        template: "Eres un asistente contable para un pequeño negocio. A continuación tienes los \
+datos agregados reales del mes {month} y del mes anterior ({previous_month}) en formato \
+JSON (importes en {currency}; en cada categoría y tienda, el primer elemento es el \
+mes {month} y el segundo el anterior; \"net\" = ingresos - gastos):\n\
+```json\n{context_json}\n```\n\
+Escribe en español un resumen de 1 a 3 párrafos cortos del mes {month} para el \
+dueño del negocio: ingresos, gastos y resultado, las variaciones más importantes \
+respecto al mes anterior en porcentaje (por ejemplo, \"Tus gastos subieron un 12% \
+por Suministros\") y las categorías o tiendas que las explican. Usa solo estos \
+datos y cita las cifras; no inventes causas. Si el mes no tiene movimientos, dilo.",
+    },
+    DefaultTemplate {
+        name: ANOMALY_EXPLANATION,
+        title: "Explicación de gastos inusuales",
+        description: "Posibles causas de los gastos que se salen de lo normal en su tienda y categoría.",
+        version: 1,
+        placeholders: &["items_json"],
+        template: "Eres un asistente contable para un pequeño negocio. Estos gastos son inusualmente \
+altos o bajos comparados con el resto de gastos de la misma tienda y categoría:\n\
+```json\n{items_json}\n```\n\
+Para cada uno, escribe en español una frase breve con posibles explicaciones y qué \
+debería revisar el usuario (por ejemplo, un error al teclear el importe o un pago \
+anual). Responde SOLO con un array JSON de objetos {\"id\": ..., \"explanation\": ...}.",
+    },
+    DefaultTemplate {
+        name: TAX_TIPS,
+        title: "Consejos de IVA",
+        description: "Observaciones sobre la liquidación de IVA de un trimestre.",
+        version: 1,
+        placeholders: &["quarter", "currency", "report_json"],
+        template: "Eres un asesor fiscal para un pequeño negocio en España. Esta es la liquidación \
+de IVA del trimestre {quarter} calculada a partir de su contabilidad (importes en \
+{currency}; \"repercutido\" es el IVA de las ventas, \"soportado\" el de las compras, \
+\"result\" lo que sale a ingresar si es positivo o a compensar si es negativo, y \
+\"untaxed_count\" las transacciones sin IVA registrado):\n\
+```json\n{report_json}\n```\n\
+Escribe en español de 3 a 5 consejos breves y concretos para este trimestre: datos \
+que conviene revisar (por ejemplo, transacciones sin IVA o tipos poco habituales), \
+gastos cuyo IVA suele ser deducible y plazos a tener en cuenta. Usa solo estos datos \
+y cita las cifras. Recuerda al final que no sustituye el consejo de un gestor.",
+    },
+    DefaultTemplate {
+        name: TRANSACTION_TEXT,
+        title: "Transacción escrita",
+        description: "Lectura de una transacción descrita con palabras, como \"ayer pagué 85,40 de luz en Iberdrola\".",
+        version: 1,
+        placeholders: &["text", "today", "weekday", "stores", "categories"],
+        template: "Eres un asistente contable para un pequeño negocio. Hoy es {weekday} {today}. \
+El usuario ha descrito con sus palabras una transacción:\n\
+\"{text}\"\n\
+Extrae sus datos. \"transaction_type\" es \"Gasto\" si pagó o compró algo e \"Ingreso\" si \
+cobró o vendió. \"amount\" es el importe como texto con punto decimal (\"85.40\"). \
+\"description\" es un concepto breve en español. Para \"store_name\" usa, si encaja, una \
+de estas tiendas: {stores}. Para \"category\" usa solo una de estas categorías o null: \
+{categories}. \"currency\" es el código ISO 4217 solo si el texto lo indica. \
+No calcules la fecha: indica \"days_ago\" (0 hoy, 1 ayer, 2 anteayer), o \"weekday\" \
+(0 lunes ... 6 domingo) si nombra un día de la semana, o \"day\", \"month\" y \"year\" \
+si da una fecha; lo que no se diga, null. Si un dato no aparece, usa null.",
+    },
+];
+
+fn default_template(name: &str) -> Result<&'static DefaultTemplate, AppError> {
+    DEFAULT_TEMPLATES.iter().find(|t| t.name == name).ok_or_else(|| {
+        error!("Unknown prompt template: {}", name);
+        AppError::NotFound(format!("No existe la plantilla de prompt '{}'.", name))
+    })
+}
+
+/// Plantilla tal como la ve el usuario en los ajustes.
+#[derive(Debug, Clone, Serialize)]
+pub struct PromptTemplate {
+    pub name: String,
+    pub title: String,
+    pub description: String,
+    pub version: u32,
+    /// Marcadores que se pueden usar, sin llaves.
+    pub placeholders: Vec<String>,
+    pub default_template: String,
+    /// Texto personalizado; `None` si se usa el de la aplicación.
+    pub custom_template: Option<String>,
+    /// La plantilla de la aplicación cambió después de personalizarla.
+    pub outdated: bool,
+    pub updated_at: Option<u64>,
+}
+
+fn custom_template(conn: &Connection, name: &str) -> Result<Option<(String, u32, u64)>, AppError> {
+    conn.query_row(
+        "SELECT template, version, updated_at FROM prompt_templates WHERE name = ?1",
+        params![name],
+        |row| Ok((row.get(0)?, row.get::<_, i64>(1)? as u32, row.get::<_, i64>(2)? as u64)),
+    )
+    .optional()
+    .map_err(db_error)
+}
+
+fn to_prompt_template(conn: &Connection, default: &DefaultTemplate) -> Result<PromptTemplate, AppError> {
+    let custom = custom_template(conn, default.name)?;
+    Ok(PromptTemplate {
+        name: default.name.to_owned(),
+        title: default.title.to_owned(),
+        description: default.description.to_owned(),
+        version: default.version,
+        placeholders: default.placeholders.iter().map(|p| p.to_string()).collect(),
+        default_template: default.template.to_owned(),
+        outdated: custom.as_ref().is_some_and(|(_, version, _)| *version < default.version),
+        updated_at: custom.as_ref().map(|(_, _, updated_at)| *updated_at),
+        custom_template: custom.map(|(template, _, _)| template),
+    })
+}
+
+/// Nombres de los `{marcadores}` de `template`: texto entre llaves formado solo por
+/// minúsculas, dígitos y `_`.
+fn placeholders_in(template: &str) -> Vec<&str> {
+    let mut found = Vec::new();
+    let mut rest = template;
+    while let Some(open) = rest.find('{') {
+        rest = &rest[open + 1..];
+        let Some(close) = rest.find(['{', '}']) else { break };
+        let candidate = &rest[..close];
+        if rest[close..].starts_with('}')
+            && !candidate.is_empty()
+            && candidate.chars().all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '_')
+        {
+            found.push(candidate);
+        }
+        rest = &rest[close..];
+    }
+    found
+}
+
+/// Sustituye los `{marcadores}` de `template` por `values`. Los que no están en
+/// `values` se dejan tal cual.
+pub fn render(template: &str, values: &[(&str, &str)]) -> String {
+    let values: HashMap<&str, &str> = values.iter().copied().collect();
+    let mut output = String::with_capacity(template.len());
+    let mut rest = template;
+    while let Some(open) = rest.find('{') {
+        output.push_str(&rest[..open]);
+        rest = &rest[open..];
+        match rest[1..].find('}').map(|close| &rest[1..close + 1]).and_then(|name| values.get(name).map(|v| (name, v))) {
+            Some((name, value)) => {
+                output.push_str(value);
+                rest = &rest[name.len() + 2..];
+            }
+            None => {
+                output.push('{');
+                rest = &rest[1..];
+            }
+        }
+    }
+    output.push_str(rest);
+    output
+}
+
+/// Prompt `name` (el personalizado o, si no hay, el de la aplicación) con los
+/// marcadores sustituidos por `values`.
+pub fn render_prompt(conn: &Connection, name: &str, values: &[(&str, &str)]) -> Result<String, AppError> {
+    let default = default_template(name)?;
+    let template = match custom_template(conn, name)? {
+        Some((template, _, _)) => template,
+        None => default.template.to_owned(),
+    };
+    Ok(render(&template, values))
+}
+
+// --- Comandos Tauri ---
+
+/// Comando para listar las plantillas de los prompts que usa la aplicación, con el
+/// texto por defecto, el personalizado si lo hay y los marcadores disponibles.
+#[tauri::command]
+pub async fn list_prompt_templates_command(state: State<'_, AppState>) -> Result<Vec<PromptTemplate>, AppError> {
+    debug!("Received list_prompt_templates_command.");
+    let db = state.db().await?;
+    DEFAULT_TEMPLATES.iter().map(|t| to_prompt_template(db.connection(), t)).collect()
+}
+
+/// Comando para personalizar el texto del prompt `name`. Solo se admiten los
+/// marcadores de la plantilla. Con un texto vacío o ausente se vuelve al de la
+/// aplicación.
+#[tauri::command]
+pub async fn set_prompt_template_command(
+    state: State<'_, AppState>,
+    app: AppHandle,
+    name: String,
+    template: Option<String>,
+) -> Result<PromptTemplate, AppError> {
+    debug!("Received set_prompt_template_command: {}", name);
+    let default = default_template(&name)?;
+    let template = template.filter(|t| !t.trim().is_empty());
+    if let Some(template) = &template {
+        if template.chars().count() > MAX_TEMPLATE_CHARS {
+            return Err(AppError::invalid_field(
+                "template",
+                format!("El prompt no puede superar los {} caracteres.", MAX_TEMPLATE_CHARS),
+            ));
+        }
+        let unknown: Vec<&str> =
+            placeholders_in(template).into_iter().filter(|p| !default.placeholders.contains(p)).collect();
+        if !unknown.is_empty() {
+            error!("Unknown placeholders in prompt {}: {:?}", name, unknown);
+            return Err(AppError::invalid_field(
+                "template",
+                format!(
+                    "Marcadores desconocidos: {}. Se pueden usar: {}.",
+                    unknown.iter().map(|p| format!("{{{}}}", p)).collect::<Vec<_>>().join(", "),
+                    default.placeholders.iter().map(|p| format!("{{{}}}", p)).collect::<Vec<_>>().join(", ")
+                ),
+            ));
+        }
+    }
+
+    let db = state.db().await?;
+    let conn = db.connection();
+    let before = to_prompt_template(conn, default)?;
+    match &template {
+        Some(template) => conn.execute(
+            "INSERT OR REPLACE INTO prompt_templates (name, template, version, updated_at) VALUES (?1, ?2, ?3, ?4)",
+            params![name, template, default.version as i64, periods::now_timestamp() as i64],
+        ),
+        None => conn.execute("DELETE FROM prompt_templates WHERE name = ?1", params![name]),
+    }
+    .map_err(db_error)?;
+    let after = to_prompt_template(conn, default)?;
+
+    audit::record(conn, "set_prompt_template_command", Some(&name), audit::snapshot(&before), audit::snapshot(&after));
+    events::emit_entity(&app, events::PROMPT_CHANGED_EVENT, ChangeAction::Updated, &name);
+    info!("Prompt template {} {}.", name, if template.is_some() { "customized" } else { "reset" });
+    Ok(after)
+}
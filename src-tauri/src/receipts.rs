@@ -0,0 +1,159 @@
+// src-tauri/src/receipts.rs
+
+use base64::Engine;
+use chrono::NaiveDate;
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::path::Path;
+use tauri::State;
+use tokio::fs;
+use log::{debug, error, info};
+
+use crate::ai::{self, AiClient, AiFeature, AiMessage};
+use crate::ai_queue::{self, AiRequest};
+use crate::currencies;
+use crate::error::AppError;
+use crate::settings;
+use crate::AppState;
+
+/// Tamaño máximo de imagen que enviamos en línea a la IA.
+const MAX_RECEIPT_BYTES: u64 = 15 * 1024 * 1024;
+
+const RECEIPT_PROMPT: &str = "Eres un asistente contable. Analiza este ticket o factura y \
+devuelve SOLO un objeto JSON, sin texto adicional, con esta forma exacta:
+{\"merchant\": string|null, \"date\": \"YYYY-MM-DD\"|null, \"total_amount\": number|null, \
+\"currency\": \"código ISO 4217\"|null, \"line_items\": [{\"description\": string, \
+\"quantity\": number|null, \"amount\": number|null}]}
+Usa punto como separador decimal. Si un dato no aparece, usa null.";
+
+/// Esquema de `ReceiptDraft` para la salida estructurada de la IA.
+fn receipt_schema() -> Value {
+    let nullable = |kind: &str| json!({"type": kind, "nullable": true});
+    json!({
+        "type": "OBJECT",
+        "properties": {
+            "merchant": nullable("STRING"),
+            "date": {"type": "STRING", "nullable": true, "description": "Fecha en formato YYYY-MM-DD"},
+            "total_amount": nullable("NUMBER"),
+            "currency": {"type": "STRING", "nullable": true, "description": "Código ISO 4217"},
+            "line_items": {
+                "type": "ARRAY",
+                "items": {
+                    "type": "OBJECT",
+                    "properties": {
+                        "description": {"type": "STRING"},
+                        "quantity": nullable("NUMBER"),
+                        "amount": nullable("NUMBER"),
+                    },
+                    "required": ["description"],
+                },
+            },
+        },
+        "required": ["merchant", "date", "total_amount", "currency", "line_items"],
+    })
+}
+
+/// Línea de detalle leída del ticket.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ReceiptLineItem {
+    pub description: String,
+    #[serde(default)]
+    pub quantity: Option<f64>,
+    #[serde(default)]
+    pub amount: Option<Decimal>,
+}
+
+/// Borrador de transacción extraído de un ticket. No se guarda nada hasta que
+/// el usuario lo confirma desde el frontend con `add_transaction_command`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ReceiptDraft {
+    #[serde(default)]
+    pub merchant: Option<String>,
+    /// Fecha del ticket en formato `YYYY-MM-DD`.
+    #[serde(default)]
+    pub date: Option<String>,
+    #[serde(default)]
+    pub total_amount: Option<Decimal>,
+    #[serde(default)]
+    pub currency: Option<String>,
+    #[serde(default)]
+    pub line_items: Vec<ReceiptLineItem>,
+}
+
+impl ReceiptDraft {
+    /// Descarta los valores que la IA devolvió con un formato no válido.
+    fn sanitize(mut self) -> Self {
+        self.merchant = self.merchant.map(|m| m.trim().to_owned()).filter(|m| !m.is_empty());
+        self.date = self.date.filter(|d| NaiveDate::parse_from_str(d, "%Y-%m-%d").is_ok());
+        self.total_amount = self.total_amount.filter(|a| *a > Decimal::ZERO);
+        self.currency = self.currency.and_then(|c| currencies::normalize_currency_code(&c).ok());
+        self.line_items.retain(|item| !item.description.trim().is_empty());
+        self
+    }
+}
+
+/// Tipo MIME según la extensión del archivo.
+fn mime_type_for(path: &Path) -> Option<&'static str> {
+    let extension = path.extension()?.to_string_lossy().to_lowercase();
+    match extension.as_str() {
+        "jpg" | "jpeg" => Some("image/jpeg"),
+        "png" => Some("image/png"),
+        "webp" => Some("image/webp"),
+        "heic" => Some("image/heic"),
+        "pdf" => Some("application/pdf"),
+        _ => None,
+    }
+}
+
+/// Lee un ticket escaneado con la IA y devuelve un borrador de transacción.
+pub async fn extract_receipt(state: &AppState, client: &AiClient, image_path: &str) -> Result<ReceiptDraft, AppError> {
+    let path = Path::new(image_path);
+
+    let mime_type = mime_type_for(path).ok_or_else(|| {
+        error!("Unsupported receipt file type: {}", path.display());
+        AppError::invalid_field(
+            "image_path",
+            "Tipo de archivo no admitido. Usa una imagen (JPG, PNG, WEBP, HEIC) o un PDF.",
+        )
+    })?;
+    let metadata = fs::metadata(path).await
+        .map_err(|e| AppError::Io(format!("No se pudo leer el archivo {}: {}", path.display(), e)))?;
+    if metadata.len() > MAX_RECEIPT_BYTES {
+        error!("Receipt too large: {} bytes", metadata.len());
+        return Err(AppError::invalid_field("image_path", "El archivo es demasiado grande (máximo 15 MB)."));
+    }
+
+    let bytes = fs::read(path).await
+        .map_err(|e| AppError::Io(format!("No se pudo leer el archivo {}: {}", path.display(), e)))?;
+    let encoded = base64::engine::general_purpose::STANDARD.encode(&bytes);
+
+    let message = AiMessage::user(RECEIPT_PROMPT).with_image(mime_type, encoded);
+    let draft: ReceiptDraft = ai::generate_json(
+        state,
+        client,
+        &[message],
+        receipt_schema(),
+        "La IA no devolvió un ticket legible. Prueba con una imagen más nítida.",
+    )
+    .await?;
+    let draft = draft.sanitize();
+    debug!("Receipt draft extracted: {:?}", draft);
+    Ok(draft)
+}
+
+// --- Comandos Tauri ---
+
+/// Comando para leer un ticket escaneado con la IA y devolver un borrador de transacción.
+/// Sin conexión, la petición se encola y el comando devuelve `AppError::Queued`.
+#[tauri::command]
+pub async fn extract_receipt_command(state: State<'_, AppState>, image_path: String) -> Result<ReceiptDraft, AppError> {
+    info!("Received extract_receipt_command for '{}'", image_path);
+    let settings = settings::load_settings(&state.db().await?);
+    ai::ensure_enabled(&settings, Some(AiFeature::Ocr))?;
+    let request = AiRequest::Receipt { image_path: image_path.clone() };
+    ai_queue::ensure_online(&state, &request).await?;
+    let client = settings.ai_client();
+    let result = extract_receipt(&state, &client, &image_path).await;
+    ai_queue::queue_if_offline(&state, request, result).await
+}
@@ -0,0 +1,895 @@
+// src-tauri/src/reports.rs
+
+use rust_decimal::prelude::ToPrimitive;
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::Path;
+use tauri::State;
+use chrono::{Datelike, Duration, Local, NaiveDate, TimeZone, Timelike};
+use log::{debug, error, info};
+
+use crate::aggregates::{self, Aggregates};
+use crate::currencies::RateTable;
+use crate::error::AppError;
+use crate::periods::{self, Period};
+use crate::settings;
+use crate::splits;
+use crate::storage::{self, SqliteStorage, TransactionRepository};
+use crate::users;
+use crate::{AppState, Transaction, TransactionType};
+
+/// Ingresos y gastos acumulados de un grupo, en la moneda base.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct GroupTotals {
+    pub income: Decimal,
+    pub expenses: Decimal,
+    pub net: Decimal,
+    pub transaction_count: usize,
+    /// Transferencias recibidas y enviadas. Solo aparecen en los balances por tienda.
+    #[serde(skip_serializing_if = "Decimal::is_zero")]
+    pub transfers_in: Decimal,
+    #[serde(skip_serializing_if = "Decimal::is_zero")]
+    pub transfers_out: Decimal,
+}
+
+impl GroupTotals {
+    /// Suma un ingreso o un gasto. Las transferencias no son ni una cosa ni otra y se
+    /// ignoran; para los balances por tienda se usa `add_transfer`.
+    pub fn add(&mut self, transaction_type: &TransactionType, amount: Decimal) {
+        self.add_many(transaction_type, amount, 1);
+    }
+
+    /// Como `add`, para `count` transacciones que suman `amount` (ver `aggregates`).
+    pub fn add_many(&mut self, transaction_type: &TransactionType, amount: Decimal, count: usize) {
+        match transaction_type {
+            TransactionType::Ingreso => self.income += amount,
+            TransactionType::Gasto => self.expenses += amount,
+            TransactionType::Transferencia => return,
+        }
+        self.update_net();
+        self.transaction_count += count;
+    }
+
+    /// Suma una transferencia recibida (`incoming`) o enviada.
+    pub fn add_transfer(&mut self, amount: Decimal, incoming: bool) {
+        self.add_transfers(amount, 1, incoming);
+    }
+
+    pub fn add_transfers(&mut self, amount: Decimal, count: usize, incoming: bool) {
+        if incoming {
+            self.transfers_in += amount;
+        } else {
+            self.transfers_out += amount;
+        }
+        self.update_net();
+        self.transaction_count += count;
+    }
+
+    fn update_net(&mut self) {
+        self.net = self.income - self.expenses + self.transfers_in - self.transfers_out;
+    }
+}
+
+/// Criterio de agrupación de los informes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum GroupBy {
+    Month,
+    Quarter,
+    Store,
+    Category,
+}
+
+/// Clave del grupo al que pertenece una transacción. Las claves de mes (`2024-03`)
+/// y trimestre (`2024-T1`) se ordenan cronológicamente como texto.
+pub fn group_key(transaction: &Transaction, group_by: GroupBy) -> String {
+    match group_by {
+        GroupBy::Month | GroupBy::Quarter => date_key(periods::local_date(transaction.timestamp), group_by),
+        GroupBy::Store => transaction.store_name.clone(),
+        GroupBy::Category => transaction
+            .category
+            .clone()
+            .unwrap_or_else(|| "Sin categoría".to_string()),
+    }
+}
+
+/// Clave del trimestre de `date` con `GroupBy::Quarter` y de su mes en otro caso.
+fn date_key(date: NaiveDate, group_by: GroupBy) -> String {
+    match group_by {
+        GroupBy::Quarter => format!("{}-T{}", date.year(), date.month0() / 3 + 1),
+        _ => date.format("%Y-%m").to_string(),
+    }
+}
+
+/// Devuelve `true` si `timestamp` está dentro del rango inclusivo `[from, to]`.
+pub fn in_range(timestamp: u64, from: Option<u64>, to: Option<u64>) -> bool {
+    from.map_or(true, |f| timestamp >= f) && to.map_or(true, |t| timestamp <= t)
+}
+
+/// Variación porcentual de `previous` a `current`, o `None` si `previous` es cero. Se
+/// divide por el valor absoluto para que mejorar un resultado negativo sea una subida.
+pub fn percent_change(current: Decimal, previous: Decimal) -> Option<f64> {
+    if previous.is_zero() {
+        return None;
+    }
+    ((current - previous) / previous.abs() * Decimal::ONE_HUNDRED).to_f64()
+}
+
+/// Escapa un campo de CSV si contiene comas, comillas o saltos de línea.
+pub fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_owned()
+    }
+}
+
+/// Una fila de la cuenta de resultados.
+#[derive(Debug, Clone, Serialize)]
+pub struct ProfitLossGroup {
+    pub key: String,
+    #[serde(flatten)]
+    pub totals: GroupTotals,
+}
+
+/// Cuenta de resultados (ingresos, gastos y resultado neto) agrupada.
+#[derive(Debug, Clone, Serialize)]
+pub struct ProfitLossReport {
+    pub from: Option<u64>,
+    pub to: Option<u64>,
+    pub group_by: GroupBy,
+    pub base_currency: String,
+    pub groups: Vec<ProfitLossGroup>,
+    pub totals: GroupTotals,
+}
+
+/// Calcula la cuenta de resultados de las transacciones dentro del rango.
+pub fn build_profit_loss_report(
+    transactions: &[Transaction],
+    rates: &RateTable,
+    from: Option<u64>,
+    to: Option<u64>,
+    group_by: GroupBy,
+) -> Result<ProfitLossReport, AppError> {
+    let mut groups: BTreeMap<String, GroupTotals> = BTreeMap::new();
+    let mut totals = GroupTotals::default();
+
+    for transaction in transactions.iter().filter(|t| in_range(t.timestamp, from, to)) {
+        let amount = rates.to_base(transaction.amount, &transaction.currency)?;
+        groups
+            .entry(group_key(transaction, group_by))
+            .or_default()
+            .add(&transaction.transaction_type, amount);
+        totals.add(&transaction.transaction_type, amount);
+    }
+
+    Ok(ProfitLossReport {
+        from,
+        to,
+        group_by,
+        base_currency: rates.base_currency.clone(),
+        groups: groups
+            .into_iter()
+            .map(|(key, totals)| ProfitLossGroup { key, totals })
+            .collect(),
+        totals,
+    })
+}
+
+/// Como `build_profit_loss_report`, con los totales de `aggregates` de los días
+/// `[days.0, days.1)`, que deben corresponder exactamente a `[from, to]`.
+fn profit_loss_from_aggregates(
+    aggregates: &Aggregates,
+    rates: &RateTable,
+    from: Option<u64>,
+    to: Option<u64>,
+    days: (Option<NaiveDate>, Option<NaiveDate>),
+    group_by: GroupBy,
+) -> Result<ProfitLossReport, AppError> {
+    let mut groups: BTreeMap<String, GroupTotals> = BTreeMap::new();
+    let mut totals = GroupTotals::default();
+
+    for (date, key, bucket) in aggregates.range(days.0, days.1) {
+        let amount = rates.to_base(bucket.amount, &key.currency)?;
+        let group = match group_by {
+            GroupBy::Month | GroupBy::Quarter => date_key(date, group_by),
+            GroupBy::Store => key.store_name.clone(),
+            GroupBy::Category => key.category.clone(),
+        };
+        groups.entry(group).or_default().add_many(&key.transaction_type, amount, bucket.count);
+        totals.add_many(&key.transaction_type, amount, bucket.count);
+    }
+
+    Ok(ProfitLossReport {
+        from,
+        to,
+        group_by,
+        base_currency: rates.base_currency.clone(),
+        groups: groups
+            .into_iter()
+            .map(|(key, totals)| ProfitLossGroup { key, totals })
+            .collect(),
+        totals,
+    })
+}
+
+// --- Series Temporales ---
+
+/// Máximo de intervalos que devuelve una serie temporal.
+const MAX_TIME_SERIES_BUCKETS: usize = 3660;
+
+/// Tamaño de los intervalos de una serie temporal.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Granularity {
+    Day,
+    Week,
+    Month,
+}
+
+impl Granularity {
+    /// Primer día del intervalo que contiene `date`.
+    fn bucket_start(self, date: NaiveDate) -> NaiveDate {
+        match self {
+            Granularity::Day => date,
+            Granularity::Week => Period::Semanal.start_date(date),
+            Granularity::Month => Period::Mensual.start_date(date),
+        }
+    }
+
+    fn next_bucket(self, start: NaiveDate) -> NaiveDate {
+        match self {
+            Granularity::Day => start + Duration::days(1),
+            Granularity::Week => Period::Semanal.next_start_date(start),
+            Granularity::Month => Period::Mensual.next_start_date(start),
+        }
+    }
+
+    /// Etiqueta del intervalo: `2024-03-15`, `2024-W11` (semana ISO) o `2024-03`.
+    fn label(self, start: NaiveDate) -> String {
+        match self {
+            Granularity::Day => start.format("%Y-%m-%d").to_string(),
+            Granularity::Week => start.format("%G-W%V").to_string(),
+            Granularity::Month => start.format("%Y-%m").to_string(),
+        }
+    }
+}
+
+/// Criterio para separar una serie temporal en varias.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SplitBy {
+    Type,
+    Store,
+    Category,
+}
+
+/// Una serie: un valor por intervalo, en el mismo orden que `TimeSeries::labels`.
+#[derive(Debug, Clone, Serialize)]
+pub struct Series {
+    pub key: String,
+    pub income: Vec<Decimal>,
+    pub expenses: Vec<Decimal>,
+    pub net: Vec<Decimal>,
+}
+
+impl Series {
+    fn new(key: String, len: usize) -> Self {
+        Series {
+            key,
+            income: vec![Decimal::ZERO; len],
+            expenses: vec![Decimal::ZERO; len],
+            net: vec![Decimal::ZERO; len],
+        }
+    }
+
+    fn add(&mut self, index: usize, transaction_type: &TransactionType, amount: Decimal) {
+        match transaction_type {
+            TransactionType::Ingreso => {
+                self.income[index] += amount;
+                self.net[index] += amount;
+            }
+            TransactionType::Gasto => {
+                self.expenses[index] += amount;
+                self.net[index] -= amount;
+            }
+            TransactionType::Transferencia => {}
+        }
+    }
+}
+
+/// Datos listos para un gráfico: los intervalos (incluidos los vacíos) y una serie
+/// por grupo, o una única serie `Total` si no se separa.
+#[derive(Debug, Clone, Serialize)]
+pub struct TimeSeries {
+    pub granularity: Granularity,
+    pub split_by: Option<SplitBy>,
+    pub base_currency: String,
+    pub labels: Vec<String>,
+    /// Inicio de cada intervalo (segundos Unix, medianoche local).
+    pub bucket_starts: Vec<u64>,
+    pub series: Vec<Series>,
+}
+
+/// Inicio de cada intervalo entre `first` y `last`, ambos incluidos.
+fn bucket_starts(granularity: Granularity, first: Option<u64>, last: Option<u64>) -> Result<Vec<NaiveDate>, AppError> {
+    let mut starts: Vec<NaiveDate> = Vec::new();
+    if let (Some(first), Some(last)) = (first, last) {
+        let last_date = periods::local_date(last);
+        let mut start = granularity.bucket_start(periods::local_date(first));
+        while start <= last_date {
+            if starts.len() == MAX_TIME_SERIES_BUCKETS {
+                error!("Time series too long: more than {} buckets.", MAX_TIME_SERIES_BUCKETS);
+                return Err(AppError::invalid_field(
+                    "granularity",
+                    format!("El rango es demasiado largo: más de {} intervalos. Usa una granularidad mayor.", MAX_TIME_SERIES_BUCKETS),
+                ));
+            }
+            starts.push(start);
+            start = granularity.next_bucket(start);
+        }
+    }
+    Ok(starts)
+}
+
+/// Agrupa las transacciones del rango `[from, to]` en intervalos consecutivos.
+pub fn build_time_series(
+    transactions: &[Transaction],
+    rates: &RateTable,
+    granularity: Granularity,
+    from: Option<u64>,
+    to: Option<u64>,
+    split_by: Option<SplitBy>,
+) -> Result<TimeSeries, AppError> {
+    let selected: Vec<&Transaction> = transactions.iter().filter(|t| in_range(t.timestamp, from, to)).collect();
+    let first = from.or_else(|| selected.iter().map(|t| t.timestamp).min());
+    let last = to.or_else(|| selected.iter().map(|t| t.timestamp).max());
+
+    let starts = bucket_starts(granularity, first, last)?;
+
+    let mut series: BTreeMap<String, Series> = BTreeMap::new();
+    if split_by.is_none() {
+        series.insert("Total".to_string(), Series::new("Total".to_string(), starts.len()));
+    }
+    for transaction in selected {
+        let date = periods::local_date(transaction.timestamp);
+        let index = starts.partition_point(|s| *s <= date).saturating_sub(1);
+        let key = match split_by {
+            None => "Total".to_string(),
+            Some(SplitBy::Type) => transaction.transaction_type.to_string(),
+            Some(SplitBy::Store) => group_key(transaction, GroupBy::Store),
+            Some(SplitBy::Category) => group_key(transaction, GroupBy::Category),
+        };
+        let amount = rates.to_base(transaction.amount, &transaction.currency)?;
+        series
+            .entry(key.clone())
+            .or_insert_with(|| Series::new(key, starts.len()))
+            .add(index, &transaction.transaction_type, amount);
+    }
+
+    Ok(TimeSeries {
+        granularity,
+        split_by,
+        base_currency: rates.base_currency.clone(),
+        labels: starts.iter().map(|s| granularity.label(*s)).collect(),
+        bucket_starts: starts.iter().map(|s| periods::local_midnight_timestamp(*s)).collect(),
+        series: series.into_values().collect(),
+    })
+}
+
+// --- Saldo Acumulado ---
+
+/// Saldo al terminar un intervalo.
+#[derive(Debug, Clone, Serialize)]
+pub struct BalancePoint {
+    pub label: String,
+    /// Inicio del intervalo (segundos Unix, medianoche local).
+    pub bucket_start: u64,
+    /// Variación del saldo dentro del intervalo.
+    pub net: Decimal,
+    pub balance: Decimal,
+}
+
+/// Evolución del saldo en la moneda base, partiendo del saldo inicial configurado.
+#[derive(Debug, Clone, Serialize)]
+pub struct RunningBalance {
+    pub granularity: Granularity,
+    pub base_currency: String,
+    pub opening_balance: Decimal,
+    /// Saldo al empezar el rango: el inicial más todo lo anterior a `from`.
+    pub starting_balance: Decimal,
+    pub ending_balance: Decimal,
+    pub points: Vec<BalancePoint>,
+}
+
+/// Calcula el saldo acumulado al final de cada intervalo del rango `[from, to]`.
+pub fn build_running_balance(
+    transactions: &[Transaction],
+    rates: &RateTable,
+    opening_balance: Decimal,
+    granularity: Granularity,
+    from: Option<u64>,
+    to: Option<u64>,
+) -> Result<RunningBalance, AppError> {
+    let first = from.or_else(|| transactions.iter().map(|t| t.timestamp).min());
+    let last = to.or_else(|| transactions.iter().map(|t| t.timestamp).max());
+    let starts = bucket_starts(granularity, first, last)?;
+
+    let mut starting_balance = opening_balance;
+    let mut net = vec![Decimal::ZERO; starts.len()];
+    for transaction in transactions {
+        if to.is_some_and(|to| transaction.timestamp > to) {
+            continue;
+        }
+        let amount = rates.to_base(transaction.amount, &transaction.currency)?;
+        let amount = match transaction.transaction_type {
+            TransactionType::Ingreso => amount,
+            TransactionType::Gasto => -amount,
+            // Mueve dinero entre tiendas sin cambiar el saldo total.
+            TransactionType::Transferencia => continue,
+        };
+        if from.is_some_and(|from| transaction.timestamp < from) {
+            starting_balance += amount;
+            continue;
+        }
+        let date = periods::local_date(transaction.timestamp);
+        let index = starts.partition_point(|s| *s <= date).saturating_sub(1);
+        net[index] += amount;
+    }
+
+    let mut balance = starting_balance;
+    let points = starts
+        .iter()
+        .zip(net)
+        .map(|(start, net)| {
+            balance += net;
+            BalancePoint {
+                label: granularity.label(*start),
+                bucket_start: periods::local_midnight_timestamp(*start),
+                net,
+                balance,
+            }
+        })
+        .collect();
+
+    Ok(RunningBalance {
+        granularity,
+        base_currency: rates.base_currency.clone(),
+        opening_balance,
+        starting_balance,
+        ending_balance: balance,
+        points,
+    })
+}
+
+/// Valida el rango y calcula la cuenta de resultados con las transacciones activas,
+/// o solo con las que registró `user_id`.
+pub fn profit_loss_report(
+    db: &SqliteStorage,
+    from: Option<u64>,
+    to: Option<u64>,
+    group_by: GroupBy,
+    user_id: Option<&str>,
+) -> Result<ProfitLossReport, AppError> {
+    if let (Some(from), Some(to)) = (from, to) {
+        if from > to {
+            error!("Invalid report range: from={} > to={}", from, to);
+            return Err(AppError::invalid_field("from", "La fecha inicial no puede ser posterior a la fecha final."));
+        }
+    }
+    let rates = RateTable::load(db)?;
+    // Por categoría cuenta cada parte de las transacciones repartidas, y por usuario
+    // solo las suyas, que los totales de `aggregates` no separan.
+    let report = match aggregates::day_range(from, to).filter(|_| group_by != GroupBy::Category && user_id.is_none()) {
+        Some(days) => aggregates::with_aggregates(db, |a| {
+            profit_loss_from_aggregates(a, &rates, from, to, days, group_by)
+        })??,
+        None => {
+            let mut transactions = db.list_transactions()?;
+            users::retain_user(&mut transactions, user_id);
+            if group_by == GroupBy::Category {
+                transactions = splits::expand(db.connection(), transactions)?;
+            }
+            build_profit_loss_report(&transactions, &rates, from, to, group_by)?
+        }
+    };
+    debug!("Profit & loss report with {} groups.", report.groups.len());
+    Ok(report)
+}
+
+// --- Comparación de periodos ---
+
+/// Rango de fechas inclusivo en segundos Unix.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct DateRange {
+    pub from: u64,
+    pub to: u64,
+}
+
+/// Diferencia de `b` respecto a `a`. Los porcentajes son `None` si en `a` el valor
+/// era cero.
+#[derive(Debug, Clone, Serialize)]
+pub struct TotalsDelta {
+    pub income: Decimal,
+    pub expenses: Decimal,
+    pub net: Decimal,
+    pub income_percent: Option<f64>,
+    pub expenses_percent: Option<f64>,
+    pub net_percent: Option<f64>,
+}
+
+impl TotalsDelta {
+    fn between(a: &GroupTotals, b: &GroupTotals) -> Self {
+        TotalsDelta {
+            income: b.income - a.income,
+            expenses: b.expenses - a.expenses,
+            net: b.net - a.net,
+            income_percent: percent_change(b.income, a.income),
+            expenses_percent: percent_change(b.expenses, a.expenses),
+            net_percent: percent_change(b.net, a.net),
+        }
+    }
+}
+
+/// Una fila de la comparación: el mismo grupo en los dos periodos.
+#[derive(Debug, Clone, Serialize)]
+pub struct ComparisonRow {
+    /// Tienda o categoría; con `Month` o `Quarter`, el mes o trimestre del periodo A.
+    pub key: String,
+    /// Con `Month` o `Quarter`, el mes o trimestre del periodo B que ocupa la misma
+    /// posición (marzo de 2024 frente a marzo de 2025).
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub key_b: Option<String>,
+    pub a: GroupTotals,
+    pub b: GroupTotals,
+    pub delta: TotalsDelta,
+}
+
+/// Comparación de dos periodos agrupados de la misma manera.
+#[derive(Debug, Clone, Serialize)]
+pub struct ComparisonReport {
+    pub period_a: DateRange,
+    pub period_b: DateRange,
+    pub group_by: GroupBy,
+    pub base_currency: String,
+    pub rows: Vec<ComparisonRow>,
+    pub totals_a: GroupTotals,
+    pub totals_b: GroupTotals,
+    pub delta: TotalsDelta,
+}
+
+/// Empareja los grupos de las dos cuentas de resultados. Las tiendas y categorías se
+/// emparejan por nombre (con ceros en el periodo en que no aparecen) y los meses y
+/// trimestres por posición dentro de su periodo.
+fn build_comparison(a: ProfitLossReport, b: ProfitLossReport, period_a: DateRange, period_b: DateRange) -> ComparisonReport {
+    let group_by = a.group_by;
+    let rows = match group_by {
+        GroupBy::Month | GroupBy::Quarter => {
+            let positions_a = range_keys(period_a, group_by);
+            let positions_b = range_keys(period_b, group_by);
+            let mut groups_a: BTreeMap<String, GroupTotals> = a.groups.into_iter().map(|g| (g.key, g.totals)).collect();
+            let mut groups_b: BTreeMap<String, GroupTotals> = b.groups.into_iter().map(|g| (g.key, g.totals)).collect();
+            (0..positions_a.len().max(positions_b.len()))
+                .map(|i| {
+                    let key_a = positions_a.get(i).cloned();
+                    let key_b = positions_b.get(i).cloned();
+                    let totals_a = key_a.as_ref().and_then(|k| groups_a.remove(k)).unwrap_or_default();
+                    let totals_b = key_b.as_ref().and_then(|k| groups_b.remove(k)).unwrap_or_default();
+                    ComparisonRow {
+                        delta: TotalsDelta::between(&totals_a, &totals_b),
+                        key: key_a.unwrap_or_default(),
+                        key_b,
+                        a: totals_a,
+                        b: totals_b,
+                    }
+                })
+                .collect()
+        }
+        GroupBy::Store | GroupBy::Category => {
+            let mut pairs: BTreeMap<String, (GroupTotals, GroupTotals)> = BTreeMap::new();
+            for group in a.groups {
+                pairs.entry(group.key).or_default().0 = group.totals;
+            }
+            for group in b.groups {
+                pairs.entry(group.key).or_default().1 = group.totals;
+            }
+            pairs
+                .into_iter()
+                .map(|(key, (a, b))| ComparisonRow { delta: TotalsDelta::between(&a, &b), key, key_b: None, a, b })
+                .collect()
+        }
+    };
+    ComparisonReport {
+        period_a,
+        period_b,
+        group_by,
+        base_currency: a.base_currency,
+        rows,
+        delta: TotalsDelta::between(&a.totals, &b.totals),
+        totals_a: a.totals,
+        totals_b: b.totals,
+    }
+}
+
+/// Claves de mes o trimestre de `range`, en orden.
+fn range_keys(range: DateRange, group_by: GroupBy) -> Vec<String> {
+    let period = if group_by == GroupBy::Quarter { Period::Trimestral } else { Period::Mensual };
+    let last = periods::local_date(range.to);
+    let mut keys = Vec::new();
+    let mut start = period.start_date(periods::local_date(range.from));
+    while start <= last {
+        keys.push(date_key(start, group_by));
+        start = period.next_start_date(start);
+    }
+    keys
+}
+
+/// Compara los periodos `period_a` y `period_b` agrupados por `group_by`.
+pub fn comparison_report(
+    db: &SqliteStorage,
+    period_a: DateRange,
+    period_b: DateRange,
+    group_by: GroupBy,
+) -> Result<ComparisonReport, AppError> {
+    for (field, range) in [("period_a", period_a), ("period_b", period_b)] {
+        if range.from > range.to {
+            error!("Invalid comparison range {}: from={} > to={}", field, range.from, range.to);
+            return Err(AppError::invalid_field(field, "La fecha inicial no puede ser posterior a la fecha final."));
+        }
+    }
+    let a = profit_loss_report(db, Some(period_a.from), Some(period_a.to), group_by, None)?;
+    let b = profit_loss_report(db, Some(period_b.from), Some(period_b.to), group_by, None)?;
+    Ok(build_comparison(a, b, period_a, period_b))
+}
+
+/// CSV de la comparación: una fila por grupo y una última con el total. Importes con
+/// punto decimal y porcentajes con dos decimales; vacíos si no se pueden calcular.
+fn render_comparison_csv(report: &ComparisonReport) -> String {
+    let mut csv = String::from(
+        "grupo,grupo_b,ingresos_a,ingresos_b,dif_ingresos,dif_ingresos_pct,gastos_a,gastos_b,dif_gastos,dif_gastos_pct,neto_a,neto_b,dif_neto,dif_neto_pct\n",
+    );
+    let total = ("Total".to_string(), None, &report.totals_a, &report.totals_b, &report.delta);
+    let rows = report.rows.iter().map(|r| (r.key.clone(), r.key_b.clone(), &r.a, &r.b, &r.delta));
+    for (key, key_b, a, b, delta) in rows.chain(std::iter::once(total)) {
+        let percent = |value: Option<f64>| value.map(|p| format!("{:.2}", p)).unwrap_or_default();
+        let fields = [
+            key,
+            key_b.unwrap_or_default(),
+            a.income.round_dp(2).to_string(),
+            b.income.round_dp(2).to_string(),
+            delta.income.round_dp(2).to_string(),
+            percent(delta.income_percent),
+            a.expenses.round_dp(2).to_string(),
+            b.expenses.round_dp(2).to_string(),
+            delta.expenses.round_dp(2).to_string(),
+            percent(delta.expenses_percent),
+            a.net.round_dp(2).to_string(),
+            b.net.round_dp(2).to_string(),
+            delta.net.round_dp(2).to_string(),
+            percent(delta.net_percent),
+        ];
+        csv.push_str(&fields.iter().map(|v| csv_field(v)).collect::<Vec<_>>().join(","));
+        csv.push('\n');
+    }
+    csv
+}
+
+// --- Mapa de calor ---
+
+/// Importes por día de la semana (filas, de lunes a domingo) y hora local (columnas,
+/// de 0 a 23), en la moneda base.
+#[derive(Debug, Clone, Serialize)]
+pub struct HeatmapMatrix {
+    pub amounts: Vec<Vec<Decimal>>,
+    pub counts: Vec<Vec<usize>>,
+    /// Mayor importe de una celda, para escalar los colores.
+    pub max_amount: Decimal,
+    /// Transacciones sin hora real, que no entran en la matriz (ver `SpendingHeatmap`).
+    pub without_time: usize,
+}
+
+impl HeatmapMatrix {
+    fn new() -> Self {
+        HeatmapMatrix {
+            amounts: vec![vec![Decimal::ZERO; 24]; 7],
+            counts: vec![vec![0; 24]; 7],
+            max_amount: Decimal::ZERO,
+            without_time: 0,
+        }
+    }
+
+    fn add(&mut self, weekday: usize, hour: usize, amount: Decimal) {
+        let cell = &mut self.amounts[weekday][hour];
+        *cell += amount;
+        self.max_amount = self.max_amount.max(*cell);
+        self.counts[weekday][hour] += 1;
+    }
+}
+
+/// Gastos e ingresos del periodo repartidos por día de la semana y hora.
+///
+/// Las transacciones registradas con una fecha pasada se guardan a mediodía (ver
+/// `periods::timestamp_for_date`) y no se sabe a qué hora ocurrieron: se cuentan en
+/// `without_time` para que no inflen la columna de las 12.
+#[derive(Debug, Clone, Serialize)]
+pub struct SpendingHeatmap {
+    pub period: Period,
+    pub period_start: u64,
+    pub period_end: u64,
+    pub base_currency: String,
+    pub expenses: HeatmapMatrix,
+    pub income: HeatmapMatrix,
+}
+
+/// Calcula el mapa de calor de las transacciones del periodo que contiene `now`.
+pub fn build_spending_heatmap(
+    transactions: &[Transaction],
+    rates: &RateTable,
+    period: Period,
+    now: u64,
+) -> Result<SpendingHeatmap, AppError> {
+    let (period_start, period_end) = period.bounds_containing(now);
+    let mut expenses = HeatmapMatrix::new();
+    let mut income = HeatmapMatrix::new();
+
+    for transaction in transactions.iter().filter(|t| t.timestamp >= period_start && t.timestamp < period_end) {
+        let matrix = match transaction.transaction_type {
+            TransactionType::Gasto => &mut expenses,
+            TransactionType::Ingreso => &mut income,
+            TransactionType::Transferencia => continue,
+        };
+        let noon = periods::local_midnight_timestamp(transaction.transaction_date) + 12 * 60 * 60;
+        let Some(local) = Local.timestamp_opt(transaction.timestamp as i64, 0).single() else {
+            matrix.without_time += 1;
+            continue;
+        };
+        if transaction.timestamp == noon && local.date_naive() != periods::local_date(transaction.created_at) {
+            matrix.without_time += 1;
+            continue;
+        }
+        let amount = rates.to_base(transaction.amount, &transaction.currency)?;
+        matrix.add(local.weekday().num_days_from_monday() as usize, local.hour() as usize, amount);
+    }
+
+    Ok(SpendingHeatmap {
+        period,
+        period_start,
+        period_end,
+        base_currency: rates.base_currency.clone(),
+        expenses,
+        income,
+    })
+}
+
+// --- Comandos Tauri ---
+
+/// Comando para obtener la cuenta de resultados entre `from` y `to` (segundos Unix,
+/// inclusivos y opcionales) agrupada por mes, trimestre, tienda o categoría. Con
+/// `user_id` solo cuenta las transacciones que registró ese usuario.
+#[tauri::command]
+pub async fn get_profit_loss_report_command(
+    state: State<'_, AppState>,
+    from: Option<u64>,
+    to: Option<u64>,
+    group_by: GroupBy,
+    user_id: Option<String>,
+) -> Result<ProfitLossReport, AppError> {
+    debug!(
+        "Received get_profit_loss_report_command: from={:?}, to={:?}, group_by={:?}, user_id={:?}",
+        from, to, group_by, user_id
+    );
+    let db = state.db().await?;
+    profit_loss_report(&db, from, to, group_by, user_id.as_deref())
+}
+
+/// Comando para obtener series temporales para gráficos: ingresos, gastos y neto por
+/// día, semana o mes entre `from` y `to` (segundos Unix, inclusivos y opcionales),
+/// opcionalmente separados por tipo, tienda o categoría y limitados a las transacciones
+/// de un usuario.
+#[tauri::command]
+pub async fn get_time_series_command(
+    state: State<'_, AppState>,
+    granularity: Granularity,
+    from: Option<u64>,
+    to: Option<u64>,
+    split_by: Option<SplitBy>,
+    user_id: Option<String>,
+) -> Result<TimeSeries, AppError> {
+    debug!(
+        "Received get_time_series_command: granularity={:?}, from={:?}, to={:?}, split_by={:?}, user_id={:?}",
+        granularity, from, to, split_by, user_id
+    );
+    if let (Some(from), Some(to)) = (from, to) {
+        if from > to {
+            error!("Invalid time series range: from={} > to={}", from, to);
+            return Err(AppError::invalid_field("from", "La fecha inicial no puede ser posterior a la fecha final."));
+        }
+    }
+
+    let db = state.db().await?;
+    let rates = RateTable::load(&db)?;
+    let mut transactions = db.list_transactions()?;
+    users::retain_user(&mut transactions, user_id.as_deref());
+    if split_by == Some(SplitBy::Category) {
+        transactions = splits::expand(db.connection(), transactions)?;
+    }
+    let series = build_time_series(&transactions, &rates, granularity, from, to, split_by)?;
+    debug!("Time series with {} buckets and {} series.", series.labels.len(), series.series.len());
+    Ok(series)
+}
+
+/// Comando para obtener la evolución del saldo entre `from` y `to` (segundos Unix,
+/// inclusivos y opcionales): el saldo al final de cada día, semana o mes (`Day` por
+/// defecto), partiendo del saldo inicial de los ajustes.
+#[tauri::command]
+pub async fn get_running_balance_command(
+    state: State<'_, AppState>,
+    from: Option<u64>,
+    to: Option<u64>,
+    granularity: Option<Granularity>,
+) -> Result<RunningBalance, AppError> {
+    let granularity = granularity.unwrap_or(Granularity::Day);
+    debug!("Received get_running_balance_command: from={:?}, to={:?}, granularity={:?}", from, to, granularity);
+    if let (Some(from), Some(to)) = (from, to) {
+        if from > to {
+            error!("Invalid running balance range: from={} > to={}", from, to);
+            return Err(AppError::invalid_field("from", "La fecha inicial no puede ser posterior a la fecha final."));
+        }
+    }
+
+    let db = state.db().await?;
+    let rates = RateTable::load(&db)?;
+    let opening_balance = settings::load_settings(&db).opening_balance;
+    build_running_balance(&db.list_transactions()?, &rates, opening_balance, granularity, from, to)
+}
+
+/// Comando para comparar dos periodos (por ejemplo, marzo de 2024 y marzo de 2025)
+/// agrupados por mes, trimestre, tienda o categoría: los totales de cada grupo en los
+/// dos periodos y la diferencia del segundo respecto al primero.
+#[tauri::command]
+pub async fn get_comparison_report_command(
+    state: State<'_, AppState>,
+    period_a: DateRange,
+    period_b: DateRange,
+    group_by: GroupBy,
+) -> Result<ComparisonReport, AppError> {
+    debug!("Received get_comparison_report_command: a={:?}, b={:?}, group_by={:?}", period_a, period_b, group_by);
+    let db = state.db().await?;
+    comparison_report(&db, period_a, period_b, group_by)
+}
+
+/// Comando para guardar en `path` la comparación de `get_comparison_report_command`
+/// como CSV. Devuelve la ruta escrita.
+#[tauri::command]
+pub async fn export_comparison_report_command(
+    state: State<'_, AppState>,
+    period_a: DateRange,
+    period_b: DateRange,
+    group_by: GroupBy,
+    path: String,
+) -> Result<String, AppError> {
+    debug!("Received export_comparison_report_command: a={:?}, b={:?}, group_by={:?} -> {}", period_a, period_b, group_by, path);
+    let report = {
+        let db = state.db().await?;
+        comparison_report(&db, period_a, period_b, group_by)?
+    };
+    storage::write_atomic(Path::new(&path), render_comparison_csv(&report).as_bytes())?;
+    info!("Comparison report with {} rows exported to {}", report.rows.len(), path);
+    Ok(path)
+}
+
+/// Comando para obtener el mapa de calor de gastos e ingresos del periodo actual
+/// (`Mensual` por defecto): importes y número de movimientos por día de la semana y
+/// hora.
+#[tauri::command]
+pub async fn get_spending_heatmap_command(
+    state: State<'_, AppState>,
+    period: Option<Period>,
+) -> Result<SpendingHeatmap, AppError> {
+    let period = period.unwrap_or(Period::Mensual);
+    debug!("Received get_spending_heatmap_command: {:?}", period);
+    let db = state.db().await?;
+    let rates = RateTable::load(&db)?;
+    build_spending_heatmap(&db.list_transactions()?, &rates, period, periods::now_timestamp())
+}
@@ -0,0 +1,457 @@
+// src-tauri/src/rules.rs
+
+use rusqlite::{params, Connection, OptionalExtension, Row};
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, State};
+use log::{debug, info, warn};
+
+use crate::audit;
+use crate::budgets;
+use crate::categories;
+use crate::error::AppError;
+use crate::events;
+use crate::history::{Change, HistoryEntry};
+use crate::periods;
+use crate::storage::{db_error, SqliteStorage, TransactionRepository};
+use crate::tags;
+use crate::{AppState, Transaction};
+
+/// Transacciones de ejemplo que devuelve `test_rule_command`.
+const TEST_SAMPLE_SIZE: usize = 20;
+
+/// Campo de la transacción que mira la condición de una regla.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RuleField {
+    Description,
+    StoreName,
+}
+
+impl RuleField {
+    fn as_str(self) -> &'static str {
+        match self {
+            RuleField::Description => "description",
+            RuleField::StoreName => "store_name",
+        }
+    }
+
+    fn parse(s: &str) -> RuleField {
+        match s {
+            "store_name" => RuleField::StoreName,
+            _ => RuleField::Description,
+        }
+    }
+}
+
+/// Comparación de la condición. Ninguna distingue mayúsculas ni tildes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RuleOperator {
+    Contains,
+    Equals,
+    StartsWith,
+}
+
+impl RuleOperator {
+    fn as_str(self) -> &'static str {
+        match self {
+            RuleOperator::Contains => "contains",
+            RuleOperator::Equals => "equals",
+            RuleOperator::StartsWith => "starts_with",
+        }
+    }
+
+    fn parse(s: &str) -> RuleOperator {
+        match s {
+            "equals" => RuleOperator::Equals,
+            "starts_with" => RuleOperator::StartsWith,
+            _ => RuleOperator::Contains,
+        }
+    }
+}
+
+/// Regla de clasificación: «si la descripción contiene IBERDROLA, categoría
+/// Suministros y tienda Iberdrola». Las reglas se prueban por `position` y se aplica
+/// la primera que coincide.
+#[derive(Debug, Clone, Serialize)]
+pub struct Rule {
+    pub id: String,
+    pub name: String,
+    pub position: u32,
+    pub enabled: bool,
+    pub field: RuleField,
+    pub operator: RuleOperator,
+    pub pattern: String,
+    pub category: Option<String>,
+    pub subcategory: Option<String>,
+    pub store_name: Option<String>,
+    /// Etiquetas que se añaden a las que ya tenga.
+    pub tags: Vec<String>,
+    pub created_at: u64,
+}
+
+/// Datos de una regla al crearla o modificarla.
+#[derive(Debug, Clone, Deserialize)]
+pub struct RuleInput {
+    #[serde(default)]
+    pub name: Option<String>,
+    #[serde(default)]
+    pub enabled: Option<bool>,
+    pub field: RuleField,
+    pub operator: RuleOperator,
+    pub pattern: String,
+    #[serde(default)]
+    pub category: Option<String>,
+    #[serde(default)]
+    pub subcategory: Option<String>,
+    #[serde(default)]
+    pub store_name: Option<String>,
+    #[serde(default)]
+    pub tags: Option<Vec<String>>,
+}
+
+/// Cómo se aplican las acciones de una regla.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ApplyMode {
+    /// Solo rellena la categoría si no tiene y nunca cambia la tienda: para lo que
+    /// escribe el usuario.
+    FillEmpty,
+    /// Sustituye la categoría y la tienda: para lo importado, que llega con la tienda
+    /// de la cuenta y sin categoría.
+    Overwrite,
+}
+
+/// Resultado de `test_rule_command`.
+#[derive(Debug, Clone, Serialize)]
+pub struct RuleTestResult {
+    /// Transacciones activas que cumplen la condición.
+    pub matched: usize,
+    pub sample: Vec<Transaction>,
+}
+
+/// Resultado de `apply_rules_retroactively_command`.
+#[derive(Debug, Clone, Serialize)]
+pub struct RetroactiveResult {
+    pub updated: Vec<String>,
+    /// Transacciones de meses cerrados que cumplían alguna regla y no se tocaron.
+    pub skipped_closed: usize,
+}
+
+/// Minúsculas y sin tildes, para comparar sin distinguirlas.
+fn fold(text: &str) -> String {
+    text.trim()
+        .to_lowercase()
+        .chars()
+        .map(|c| match c {
+            'á' | 'à' => 'a',
+            'é' | 'è' => 'e',
+            'í' => 'i',
+            'ó' | 'ò' => 'o',
+            'ú' | 'ü' => 'u',
+            other => other,
+        })
+        .collect()
+}
+
+impl Rule {
+    pub fn matches(&self, transaction: &Transaction) -> bool {
+        let value = fold(match self.field {
+            RuleField::Description => &transaction.description,
+            RuleField::StoreName => &transaction.store_name,
+        });
+        let pattern = fold(&self.pattern);
+        match self.operator {
+            RuleOperator::Contains => value.contains(&pattern),
+            RuleOperator::Equals => value == pattern,
+            RuleOperator::StartsWith => value.starts_with(&pattern),
+        }
+    }
+}
+
+fn row_to_rule(row: &Row) -> rusqlite::Result<Rule> {
+    Ok(Rule {
+        id: row.get(0)?,
+        name: row.get(1)?,
+        position: row.get(2)?,
+        enabled: row.get(3)?,
+        field: RuleField::parse(&row.get::<_, String>(4)?),
+        operator: RuleOperator::parse(&row.get::<_, String>(5)?),
+        pattern: row.get(6)?,
+        category: row.get(7)?,
+        subcategory: row.get(8)?,
+        store_name: row.get(9)?,
+        tags: serde_json::from_str(&row.get::<_, String>(10)?).unwrap_or_default(),
+        created_at: row.get::<_, i64>(11)? as u64,
+    })
+}
+
+const RULE_COLUMNS: &str =
+    "id, name, position, enabled, field, operator, pattern, category, subcategory, store_name, tags, created_at";
+
+/// Reglas por orden de aplicación.
+pub fn list_rules(conn: &Connection) -> Result<Vec<Rule>, AppError> {
+    let mut stmt = conn
+        .prepare(&format!("SELECT {} FROM classification_rules ORDER BY position, created_at", RULE_COLUMNS))
+        .map_err(db_error)?;
+    let rows = stmt.query_map([], row_to_rule).map_err(db_error)?;
+    rows.collect::<rusqlite::Result<Vec<_>>>().map_err(db_error)
+}
+
+/// Reglas activas por orden de aplicación.
+pub fn load_enabled(conn: &Connection) -> Result<Vec<Rule>, AppError> {
+    Ok(list_rules(conn)?.into_iter().filter(|r| r.enabled).collect())
+}
+
+fn get_rule(conn: &Connection, id: &str) -> Result<Option<Rule>, AppError> {
+    conn.query_row(&format!("SELECT {} FROM classification_rules WHERE id = ?1", RULE_COLUMNS), params![id], row_to_rule)
+        .optional()
+        .map_err(db_error)
+}
+
+fn require_rule(conn: &Connection, id: &str) -> Result<Rule, AppError> {
+    get_rule(conn, id)?.ok_or_else(|| AppError::NotFound(format!("Regla {} no encontrada.", id)))
+}
+
+/// Primera regla de `rules` que cumple `transaction`.
+pub fn find_match<'a>(rules: &'a [Rule], transaction: &Transaction) -> Option<&'a Rule> {
+    rules.iter().find(|rule| rule.matches(transaction))
+}
+
+/// Aplica a `transaction` la primera regla que cumple. Devuelve si la ha cambiado.
+/// Una categoría borrada después de crear la regla se ignora.
+pub fn apply(conn: &Connection, rules: &[Rule], transaction: &mut Transaction, mode: ApplyMode) -> bool {
+    let Some(rule) = find_match(rules, transaction) else {
+        return false;
+    };
+    let before = (transaction.category.clone(), transaction.subcategory.clone(), transaction.store_name.clone(), transaction.tags.clone());
+    let fill_category = mode == ApplyMode::Overwrite || (transaction.category.is_none() && transaction.subcategory.is_none());
+    if fill_category && (rule.category.is_some() || rule.subcategory.is_some()) {
+        match categories::resolve_transaction_category(conn, rule.category.clone(), rule.subcategory.clone()) {
+            Ok((category, subcategory)) => {
+                transaction.category = category;
+                transaction.subcategory = subcategory;
+            }
+            Err(e) => warn!("Rule '{}' has an invalid category: {}", rule.name, e),
+        }
+    }
+    if mode == ApplyMode::Overwrite {
+        if let Some(store) = &rule.store_name {
+            transaction.store_name = store.clone();
+        }
+    }
+    for tag in &rule.tags {
+        if !transaction.tags.contains(tag) {
+            transaction.tags.push(tag.clone());
+        }
+    }
+    let changed = before != (transaction.category.clone(), transaction.subcategory.clone(), transaction.store_name.clone(), transaction.tags.clone());
+    if changed {
+        debug!("Rule '{}' applied to transaction {}.", rule.name, transaction.id);
+    }
+    changed
+}
+
+/// Valida los datos de una regla y devuelve la regla con `id`, `position` y
+/// `created_at` sin rellenar.
+fn validate_input(conn: &Connection, input: RuleInput) -> Result<Rule, AppError> {
+    let pattern = input.pattern.trim().to_owned();
+    if pattern.is_empty() {
+        return Err(AppError::invalid_field("pattern", "Indica el texto que debe cumplir la regla."));
+    }
+    let (category, subcategory) = categories::resolve_transaction_category(conn, input.category, input.subcategory)?;
+    let store_name = input.store_name.map(|s| s.trim().to_owned()).filter(|s| !s.is_empty());
+    let tags = tags::resolve_tags(input.tags.unwrap_or_default())?;
+    if category.is_none() && subcategory.is_none() && store_name.is_none() && tags.is_empty() {
+        return Err(AppError::validation("La regla debe asignar una categoría, una tienda o alguna etiqueta."));
+    }
+    let name = input
+        .name
+        .map(|n| n.trim().to_owned())
+        .filter(|n| !n.is_empty())
+        .unwrap_or_else(|| pattern.clone());
+    Ok(Rule {
+        id: String::new(),
+        name,
+        position: 0,
+        enabled: input.enabled.unwrap_or(true),
+        field: input.field,
+        operator: input.operator,
+        pattern,
+        category,
+        subcategory,
+        store_name,
+        tags,
+        created_at: 0,
+    })
+}
+
+fn save_rule(conn: &Connection, rule: &Rule) -> Result<(), AppError> {
+    conn.execute(
+        "INSERT OR REPLACE INTO classification_rules
+         (id, name, position, enabled, field, operator, pattern, category, subcategory, store_name, tags, created_at)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12)",
+        params![
+            rule.id,
+            rule.name,
+            rule.position,
+            rule.enabled,
+            rule.field.as_str(),
+            rule.operator.as_str(),
+            rule.pattern,
+            rule.category,
+            rule.subcategory,
+            rule.store_name,
+            serde_json::to_string(&rule.tags).unwrap_or_else(|_| "[]".to_string()),
+            rule.created_at as i64
+        ],
+    )
+    .map_err(db_error)?;
+    Ok(())
+}
+
+/// Aplica `rules` a todas las transacciones activas en una transacción SQL. Devuelve
+/// los cambios y cuántas se omitieron por estar en un mes cerrado.
+fn apply_to_all(db: &SqliteStorage, rules: &[Rule], mode: ApplyMode) -> Result<(Vec<Change>, usize), AppError> {
+    let tx = db.connection().unchecked_transaction().map_err(db_error)?;
+    let mut changes = Vec::new();
+    let mut skipped_closed = 0;
+    for before in db.list_transactions()? {
+        let mut after = before.clone();
+        if !apply(db.connection(), rules, &mut after, mode) {
+            continue;
+        }
+        after.updated_at = periods::now_timestamp();
+        match db.update_transaction(&after) {
+            Ok(_) => changes.push(Change::Update { before, after }),
+            Err(AppError::PeriodClosed(_)) => skipped_closed += 1,
+            Err(e) => return Err(e),
+        }
+    }
+    tx.commit().map_err(db_error)?;
+    info!("Rules applied retroactively: {} updated, {} in closed periods.", changes.len(), skipped_closed);
+    Ok((changes, skipped_closed))
+}
+
+// --- Comandos Tauri ---
+
+/// Comando para obtener las reglas de clasificación en el orden en que se aplican.
+#[tauri::command]
+pub async fn list_rules_command(state: State<'_, AppState>) -> Result<Vec<Rule>, AppError> {
+    debug!("Received list_rules_command.");
+    let db = state.db().await?;
+    list_rules(db.connection())
+}
+
+/// Comando para crear una regla de clasificación. Se añade al final de la lista.
+#[tauri::command]
+pub async fn create_rule_command(state: State<'_, AppState>, rule: RuleInput) -> Result<Rule, AppError> {
+    debug!("Received create_rule_command: {:?}", rule);
+    let db = state.db().await?;
+    let mut created = validate_input(db.connection(), rule)?;
+    created.id = uuid::Uuid::new_v4().to_string();
+    created.position = list_rules(db.connection())?.iter().map(|r| r.position + 1).max().unwrap_or(0);
+    created.created_at = periods::now_timestamp();
+    save_rule(db.connection(), &created)?;
+    audit::record(db.connection(), "create_rule_command", Some(&created.id), None, audit::snapshot(&created));
+    info!("Rule '{}' created.", created.name);
+    Ok(created)
+}
+
+/// Comando para modificar una regla. Conserva su posición.
+#[tauri::command]
+pub async fn update_rule_command(state: State<'_, AppState>, id: String, rule: RuleInput) -> Result<Rule, AppError> {
+    debug!("Received update_rule_command: {} {:?}", id, rule);
+    let db = state.db().await?;
+    let before = require_rule(db.connection(), &id)?;
+    let mut updated = validate_input(db.connection(), rule)?;
+    updated.id = before.id.clone();
+    updated.position = before.position;
+    updated.created_at = before.created_at;
+    save_rule(db.connection(), &updated)?;
+    audit::record(db.connection(), "update_rule_command", Some(&id), audit::snapshot(&before), audit::snapshot(&updated));
+    Ok(updated)
+}
+
+/// Comando para eliminar una regla.
+#[tauri::command]
+pub async fn delete_rule_command(state: State<'_, AppState>, id: String) -> Result<(), AppError> {
+    debug!("Received delete_rule_command: {}", id);
+    let db = state.db().await?;
+    let before = require_rule(db.connection(), &id)?;
+    db.connection()
+        .execute("DELETE FROM classification_rules WHERE id = ?1", params![id])
+        .map_err(db_error)?;
+    audit::record(db.connection(), "delete_rule_command", Some(&id), audit::snapshot(&before), None);
+    Ok(())
+}
+
+/// Comando para cambiar el orden de las reglas. `ids` debe contener todas las reglas,
+/// en el nuevo orden.
+#[tauri::command]
+pub async fn reorder_rules_command(state: State<'_, AppState>, ids: Vec<String>) -> Result<Vec<Rule>, AppError> {
+    debug!("Received reorder_rules_command: {:?}", ids);
+    let db = state.db().await?;
+    let current = list_rules(db.connection())?;
+    let mut sorted_ids = ids.clone();
+    sorted_ids.sort();
+    sorted_ids.dedup();
+    let mut current_ids: Vec<String> = current.iter().map(|r| r.id.clone()).collect();
+    current_ids.sort();
+    if sorted_ids.len() != ids.len() || sorted_ids != current_ids {
+        return Err(AppError::invalid_field("ids", "Indica todas las reglas, cada una una vez."));
+    }
+    let tx = db.connection().unchecked_transaction().map_err(db_error)?;
+    for (position, id) in ids.iter().enumerate() {
+        tx.execute("UPDATE classification_rules SET position = ?2 WHERE id = ?1", params![id, position as u32])
+            .map_err(db_error)?;
+    }
+    tx.commit().map_err(db_error)?;
+    audit::record(db.connection(), "reorder_rules_command", None, audit::snapshot(&current_ids), audit::snapshot(&ids));
+    list_rules(db.connection())
+}
+
+/// Comando para probar una regla sin guardarla: cuántas transacciones activas cumplen
+/// su condición y algunas de ejemplo, de las más recientes.
+#[tauri::command]
+pub async fn test_rule_command(state: State<'_, AppState>, rule: RuleInput) -> Result<RuleTestResult, AppError> {
+    debug!("Received test_rule_command: {:?}", rule);
+    let db = state.db().await?;
+    let rule = validate_input(db.connection(), rule)?;
+    let mut matching: Vec<Transaction> = db.list_transactions()?.into_iter().filter(|t| rule.matches(t)).collect();
+    matching.sort_by(|a, b| b.timestamp.cmp(&a.timestamp));
+    let matched = matching.len();
+    matching.truncate(TEST_SAMPLE_SIZE);
+    Ok(RuleTestResult { matched, sample: matching })
+}
+
+/// Comando para aplicar las reglas activas a las transacciones ya registradas. Por
+/// defecto solo clasifica las que no tienen categoría; con `overwrite` también cambia
+/// la categoría y la tienda de las demás. Las de meses cerrados se omiten. Se deshace
+/// como un único paso.
+#[tauri::command]
+pub async fn apply_rules_retroactively_command(
+    state: State<'_, AppState>,
+    app: AppHandle,
+    overwrite: Option<bool>,
+) -> Result<RetroactiveResult, AppError> {
+    info!("Received apply_rules_retroactively_command: overwrite={:?}", overwrite);
+    let mode = if overwrite.unwrap_or(false) { ApplyMode::Overwrite } else { ApplyMode::FillEmpty };
+    let db = state.db().await?;
+    let rules = load_enabled(db.connection())?;
+    let (changes, skipped_closed) = apply_to_all(&db, &rules, mode)?;
+
+    let updated = changes
+        .iter()
+        .filter_map(|c| match c {
+            Change::Update { after, .. } => Some(after.id.clone()),
+            _ => None,
+        })
+        .collect();
+    if !changes.is_empty() {
+        audit::record_changes(db.connection(), "apply_rules_retroactively_command", &changes);
+        events::emit_transaction_changes(&app, &changes);
+        state.history.lock().unwrap().record(HistoryEntry::new("Aplicar reglas", changes));
+        budgets::check_budget_alerts(&db, &app);
+    }
+    Ok(RetroactiveResult { updated, skipped_closed })
+}
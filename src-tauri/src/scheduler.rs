@@ -0,0 +1,511 @@
+// src-tauri/src/scheduler.rs
+
+use chrono::{Datelike, Duration as ChronoDuration, Local, NaiveDate, TimeZone, Timelike};
+use rusqlite::{params, Connection, OptionalExtension, Row};
+use serde::{Deserialize, Serialize};
+use std::sync::Mutex;
+use std::time::Duration;
+use tauri::{AppHandle, Manager, State};
+use log::{debug, error, info, warn};
+
+use crate::audit;
+use crate::backup;
+use crate::email;
+use crate::error::AppError;
+use crate::events::{self, ChangeAction};
+use crate::notifications;
+use crate::periods;
+use crate::shutdown;
+use crate::storage::db_error;
+use crate::sync;
+use crate::AppState;
+
+/// Cada cuánto se buscan tareas a las que les toca ejecutarse.
+const RUNNER_TICK: Duration = Duration::from_secs(30);
+/// Ejecuciones que se guardan en el historial de cada tarea.
+const HISTORY_KEPT: usize = 50;
+/// Ejecuciones de cada tarea que devuelve `list_jobs_command`.
+const RECENT_RUNS: usize = 10;
+/// Años hacia delante en los que se busca la próxima ejecución (p. ej. un 29 de febrero).
+const MAX_LOOKAHEAD_DAYS: i64 = 366 * 4;
+
+/// Evento emitido al terminar cada ejecución. Carga: `JobRun`.
+pub const JOB_FINISHED_EVENT: &str = "scheduler://job-finished";
+
+/// Tareas que se están ejecutando, para no lanzar la misma dos veces a la vez.
+static RUNNING: Mutex<Vec<JobKind>> = Mutex::new(Vec::new());
+
+/// Tareas en segundo plano de la aplicación. Cada una decide, además, si le toca
+/// hacer algo según los ajustes (p. ej. el intervalo de las copias automáticas).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum JobKind {
+    /// Copia de seguridad automática y política de retención (ver `backup`).
+    Backup,
+    /// Sincronización con la nube (ver `sync`).
+    Sync,
+    /// Envío del informe mensual por correo (ver `email`).
+    ReportEmail,
+    /// Revisión de los avisos pendientes (ver `notifications`).
+    Alerts,
+}
+
+impl JobKind {
+    const ALL: [JobKind; 4] = [JobKind::Backup, JobKind::Sync, JobKind::ReportEmail, JobKind::Alerts];
+
+    fn id(self) -> &'static str {
+        match self {
+            JobKind::Backup => "backup",
+            JobKind::Sync => "sync",
+            JobKind::ReportEmail => "report_email",
+            JobKind::Alerts => "alerts",
+        }
+    }
+
+    fn from_id(id: &str) -> Option<JobKind> {
+        JobKind::ALL.into_iter().find(|kind| kind.id() == id)
+    }
+
+    fn title(self) -> &'static str {
+        match self {
+            JobKind::Backup => "Copia de seguridad automática",
+            JobKind::Sync => "Sincronización",
+            JobKind::ReportEmail => "Informe mensual por correo",
+            JobKind::Alerts => "Avisos pendientes",
+        }
+    }
+
+    /// Programación con la que se crea la tarea. Las tareas comprueban a menudo si
+    /// les toca: el intervalo real es el de los ajustes.
+    fn default_schedule(self) -> &'static str {
+        match self {
+            JobKind::Backup => "0 * * * *",
+            JobKind::Sync => "* * * * *",
+            JobKind::ReportEmail => "30 * * * *",
+            JobKind::Alerts => "*/15 * * * *",
+        }
+    }
+
+    /// Ejecuta la tarea y devuelve un resumen para el historial. Con `force` (al
+    /// lanzarla a mano) se hace el trabajo aunque según los ajustes aún no tocara.
+    async fn run(self, app: &AppHandle, force: bool) -> Result<String, AppError> {
+        match self {
+            JobKind::Backup => backup::run_scheduled_backup(&app.state::<AppState>(), force).await,
+            JobKind::Sync => sync::run_scheduled_sync(app, force).await,
+            JobKind::ReportEmail => email::send_scheduled(app, force).await,
+            JobKind::Alerts => notifications::check_alerts(app).await,
+        }
+    }
+}
+
+/// Programación al estilo de cron: minuto, hora, día del mes, mes y día de la semana
+/// (0 o 7 es domingo). Cada campo admite `*`, valores, listas (`1,15`), rangos
+/// (`1-5`) y pasos (`*/15`, `8-18/2`). Las horas son locales.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CronSchedule {
+    minutes: u64,
+    hours: u64,
+    days: u64,
+    months: u64,
+    weekdays: u64,
+    /// El día del mes y el de la semana no son `*`: basta con que coincida uno.
+    either_day: bool,
+}
+
+/// Bits de los valores de un campo entre `min` y `max`.
+fn parse_cron_field(field: &str, min: u32, max: u32, name: &str) -> Result<u64, AppError> {
+    let invalid = || {
+        AppError::invalid_field(
+            "schedule",
+            format!(
+                "El campo {} de la programación ('{}') no es válido: usa valores entre {} y {}.",
+                name, field, min, max
+            ),
+        )
+    };
+    let mut bits = 0u64;
+    for part in field.split(',') {
+        let (range, step) = match part.split_once('/') {
+            Some((range, step)) => (range, step.parse::<u32>().map_err(|_| invalid())?),
+            None => (part, 1),
+        };
+        if step == 0 {
+            return Err(invalid());
+        }
+        let (start, end) = match range {
+            "*" => (min, max),
+            _ => match range.split_once('-') {
+                Some((start, end)) => {
+                    (start.parse::<u32>().map_err(|_| invalid())?, end.parse::<u32>().map_err(|_| invalid())?)
+                }
+                // `5/10` equivale a `5-max/10`, como en cron.
+                None => {
+                    let start = range.parse::<u32>().map_err(|_| invalid())?;
+                    (start, if part.contains('/') { max } else { start })
+                }
+            },
+        };
+        if start < min || end > max || start > end {
+            return Err(invalid());
+        }
+        for value in (start..=end).step_by(step as usize) {
+            bits |= 1 << value;
+        }
+    }
+    Ok(bits)
+}
+
+impl CronSchedule {
+    pub fn parse(expression: &str) -> Result<CronSchedule, AppError> {
+        let fields: Vec<&str> = expression.split_whitespace().collect();
+        let [minute, hour, day, month, weekday] = fields.as_slice() else {
+            error!("Invalid cron expression: '{}'", expression);
+            return Err(AppError::invalid_field(
+                "schedule",
+                "La programación debe tener cinco campos: minuto, hora, día del mes, mes y día de la semana.",
+            ));
+        };
+        let mut weekdays = parse_cron_field(weekday, 0, 7, "día de la semana")?;
+        if weekdays & (1 << 7) != 0 {
+            weekdays = (weekdays | 1) & !(1 << 7);
+        }
+        Ok(CronSchedule {
+            minutes: parse_cron_field(minute, 0, 59, "minuto")?,
+            hours: parse_cron_field(hour, 0, 23, "hora")?,
+            days: parse_cron_field(day, 1, 31, "día del mes")?,
+            months: parse_cron_field(month, 1, 12, "mes")?,
+            weekdays,
+            either_day: *day != "*" && *weekday != "*",
+        })
+    }
+
+    fn matches_date(&self, date: NaiveDate) -> bool {
+        if self.months & (1 << date.month()) == 0 {
+            return false;
+        }
+        let day = self.days & (1 << date.day()) != 0;
+        let weekday = self.weekdays & (1 << date.weekday().num_days_from_sunday()) != 0;
+        if self.either_day {
+            day || weekday
+        } else {
+            day && weekday
+        }
+    }
+
+    /// Primer momento (timestamp Unix) posterior a `after` que cumple la programación.
+    /// Las horas que no existen por un cambio de hora se saltan.
+    pub fn next_after(&self, after: u64) -> Option<u64> {
+        let start = Local.timestamp_opt(after as i64, 0).single()?.naive_local();
+        let start = start.with_second(0)?.with_nanosecond(0)? + ChronoDuration::minutes(1);
+        for offset in 0..MAX_LOOKAHEAD_DAYS {
+            let date = start.date() + ChronoDuration::days(offset);
+            if !self.matches_date(date) {
+                continue;
+            }
+            for hour in (0..24).filter(|h| self.hours & (1 << h) != 0) {
+                for minute in (0..60).filter(|m| self.minutes & (1 << m) != 0) {
+                    let candidate = date.and_hms_opt(hour, minute, 0)?;
+                    if candidate < start {
+                        continue;
+                    }
+                    if let Some(local) = Local.from_local_datetime(&candidate).earliest() {
+                        return Some(local.timestamp().max(0) as u64);
+                    }
+                }
+            }
+        }
+        None
+    }
+}
+
+/// Cómo se lanzó una ejecución.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum JobTrigger {
+    Schedule,
+    Manual,
+}
+
+/// Una ejecución de una tarea.
+#[derive(Debug, Clone, Serialize)]
+pub struct JobRun {
+    pub job: JobKind,
+    pub trigger: JobTrigger,
+    /// `true` si terminó bien; si no, `message` es el error.
+    pub success: bool,
+    pub message: String,
+    pub started_at: u64,
+    pub finished_at: u64,
+}
+
+/// Tarea programada tal como se muestra en los ajustes.
+#[derive(Debug, Clone, Serialize)]
+pub struct Job {
+    pub id: JobKind,
+    pub title: String,
+    /// Expresión cron (ver `CronSchedule`).
+    pub schedule: String,
+    pub default_schedule: String,
+    pub enabled: bool,
+    pub running: bool,
+    pub next_run_at: Option<u64>,
+    /// Últimas ejecuciones, de la más reciente a la más antigua.
+    pub recent_runs: Vec<JobRun>,
+}
+
+/// Crea las tareas que falten con su programación por defecto. Las nuevas se ejecutan
+/// en la siguiente vuelta, como hacían las comprobaciones al abrir la aplicación.
+fn ensure_jobs(conn: &Connection) -> Result<(), AppError> {
+    let now = periods::now_timestamp() as i64;
+    for kind in JobKind::ALL {
+        conn.execute(
+            "INSERT OR IGNORE INTO scheduled_jobs (id, schedule, enabled, next_run_at, updated_at) VALUES (?1, ?2, 1, ?3, ?3)",
+            params![kind.id(), kind.default_schedule(), now],
+        )
+        .map_err(db_error)?;
+    }
+    Ok(())
+}
+
+fn row_to_run(row: &Row) -> rusqlite::Result<Option<JobRun>> {
+    let job: String = row.get(0)?;
+    let trigger: String = row.get(1)?;
+    let status: String = row.get(2)?;
+    let message: String = row.get(3)?;
+    let started_at: i64 = row.get(4)?;
+    let finished_at: i64 = row.get(5)?;
+    Ok(JobKind::from_id(&job).map(|job| JobRun {
+        job,
+        trigger: if trigger == "manual" { JobTrigger::Manual } else { JobTrigger::Schedule },
+        success: status == "ok",
+        message,
+        started_at: started_at as u64,
+        finished_at: finished_at as u64,
+    }))
+}
+
+fn recent_runs(conn: &Connection, kind: JobKind, limit: usize) -> Result<Vec<JobRun>, AppError> {
+    let mut stmt = conn
+        .prepare(
+            "SELECT job_id, trigger, status, message, started_at, finished_at FROM job_runs
+             WHERE job_id = ?1 ORDER BY id DESC LIMIT ?2",
+        )
+        .map_err(db_error)?;
+    let runs = stmt
+        .query_map(params![kind.id(), limit as i64], row_to_run)
+        .map_err(db_error)?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(db_error)?;
+    Ok(runs.into_iter().flatten().collect())
+}
+
+fn is_running(kind: JobKind) -> bool {
+    RUNNING.lock().map(|running| running.contains(&kind)).unwrap_or(false)
+}
+
+fn load_job(conn: &Connection, kind: JobKind) -> Result<Job, AppError> {
+    ensure_jobs(conn)?;
+    let (schedule, enabled, next_run_at) = conn
+        .query_row(
+            "SELECT schedule, enabled, next_run_at FROM scheduled_jobs WHERE id = ?1",
+            params![kind.id()],
+            |row| Ok((row.get::<_, String>(0)?, row.get::<_, bool>(1)?, row.get::<_, Option<i64>>(2)?)),
+        )
+        .map_err(db_error)?;
+    Ok(Job {
+        id: kind,
+        title: kind.title().to_owned(),
+        schedule,
+        default_schedule: kind.default_schedule().to_owned(),
+        enabled,
+        running: is_running(kind),
+        next_run_at: next_run_at.filter(|_| enabled).map(|t| t as u64),
+        recent_runs: recent_runs(conn, kind, RECENT_RUNS)?,
+    })
+}
+
+/// Tareas activas a las que ya les toca ejecutarse.
+fn due_jobs(conn: &Connection, now: u64) -> Result<Vec<JobKind>, AppError> {
+    ensure_jobs(conn)?;
+    let mut stmt = conn
+        .prepare("SELECT id FROM scheduled_jobs WHERE enabled = 1 AND next_run_at IS NOT NULL AND next_run_at <= ?1")
+        .map_err(db_error)?;
+    let ids = stmt
+        .query_map(params![now as i64], |row| row.get::<_, String>(0))
+        .map_err(db_error)?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(db_error)?;
+    Ok(ids.iter().filter_map(|id| JobKind::from_id(id)).collect())
+}
+
+/// Guarda la ejecución en el historial, recorta el historial y, si la lanzó la
+/// programación, calcula la siguiente.
+fn record_run(conn: &Connection, run: &JobRun) -> Result<(), AppError> {
+    conn.execute(
+        "INSERT INTO job_runs (job_id, trigger, status, message, started_at, finished_at) VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+        params![
+            run.job.id(),
+            if run.trigger == JobTrigger::Manual { "manual" } else { "schedule" },
+            if run.success { "ok" } else { "failed" },
+            run.message,
+            run.started_at as i64,
+            run.finished_at as i64
+        ],
+    )
+    .map_err(db_error)?;
+    conn.execute(
+        "DELETE FROM job_runs WHERE job_id = ?1 AND id NOT IN
+            (SELECT id FROM job_runs WHERE job_id = ?1 ORDER BY id DESC LIMIT ?2)",
+        params![run.job.id(), HISTORY_KEPT as i64],
+    )
+    .map_err(db_error)?;
+    if run.trigger == JobTrigger::Schedule {
+        let schedule: Option<String> = conn
+            .query_row("SELECT schedule FROM scheduled_jobs WHERE id = ?1", params![run.job.id()], |row| row.get(0))
+            .optional()
+            .map_err(db_error)?;
+        let next = schedule
+            .and_then(|s| CronSchedule::parse(&s).ok())
+            .and_then(|s| s.next_after(run.finished_at));
+        conn.execute(
+            "UPDATE scheduled_jobs SET next_run_at = ?1 WHERE id = ?2",
+            params![next.map(|t| t as i64), run.job.id()],
+        )
+        .map_err(db_error)?;
+    }
+    Ok(())
+}
+
+/// Quita la tarea de `RUNNING` al terminar, también si la ejecución falla por el camino.
+struct RunningGuard(JobKind);
+
+impl Drop for RunningGuard {
+    fn drop(&mut self) {
+        if let Ok(mut running) = RUNNING.lock() {
+            running.retain(|kind| *kind != self.0);
+        }
+    }
+}
+
+/// Ejecuta la tarea, guarda el resultado en el historial y lo emite con
+/// `JOB_FINISHED_EVENT`. Si ya se está ejecutando devuelve `AppError::Conflict`.
+async fn run_job(app: &AppHandle, kind: JobKind, trigger: JobTrigger) -> Result<JobRun, AppError> {
+    {
+        let mut running = RUNNING.lock().map_err(|_| AppError::Internal("Planificador no disponible.".to_string()))?;
+        if running.contains(&kind) {
+            return Err(AppError::Conflict(format!("La tarea '{}' ya se está ejecutando.", kind.title())));
+        }
+        running.push(kind);
+    }
+    let _guard = RunningGuard(kind);
+    let _in_flight = shutdown::track("scheduled_job");
+
+    debug!("Running job {} ({:?}).", kind.id(), trigger);
+    let started_at = periods::now_timestamp();
+    let result = kind.run(app, trigger == JobTrigger::Manual).await;
+    let run = JobRun {
+        job: kind,
+        trigger,
+        success: result.is_ok(),
+        message: match result {
+            Ok(message) => message,
+            Err(e) => {
+                warn!("Job {} failed: {}", kind.id(), e);
+                e.to_string()
+            }
+        },
+        started_at,
+        finished_at: periods::now_timestamp(),
+    };
+    record_run(app.state::<AppState>().db().await?.connection(), &run)?;
+    events::emit(app, JOB_FINISHED_EVENT, run.clone());
+    Ok(run)
+}
+
+/// Lanza las tareas a las que les toca. Cada una corre por separado para que una
+/// sincronización lenta no retrase las demás.
+async fn tick(app: &AppHandle) -> Result<(), AppError> {
+    let state = app.state::<AppState>();
+    if state.is_locked() || shutdown::is_shutting_down() {
+        return Ok(());
+    }
+    let due = due_jobs(state.db().await?.connection(), periods::now_timestamp())?;
+    for kind in due.into_iter().filter(|kind| !is_running(*kind)) {
+        let app = app.clone();
+        tauri::async_runtime::spawn(async move {
+            if let Err(e) = run_job(&app, kind, JobTrigger::Schedule).await {
+                warn!("No se pudo ejecutar la tarea programada {}: {}", kind.id(), e);
+            }
+        });
+    }
+    Ok(())
+}
+
+/// Lanza el planificador de tareas en segundo plano.
+pub fn spawn_runner(app: AppHandle) {
+    tauri::async_runtime::spawn(async move {
+        loop {
+            if let Err(e) = tick(&app).await {
+                debug!("Skipping scheduler tick: {}", e);
+            }
+            tokio::time::sleep(RUNNER_TICK).await;
+        }
+    });
+}
+
+// --- Comandos Tauri ---
+
+/// Comando para listar las tareas programadas con su programación, la próxima
+/// ejecución y las últimas ejecuciones.
+#[tauri::command]
+pub async fn list_jobs_command(state: State<'_, AppState>) -> Result<Vec<Job>, AppError> {
+    debug!("Received list_jobs_command.");
+    let db = state.db().await?;
+    JobKind::ALL.into_iter().map(|kind| load_job(db.connection(), kind)).collect()
+}
+
+/// Comando para ejecutar ahora una tarea, haga falta o no según los ajustes (p. ej.
+/// crear una copia aunque la última sea reciente). No cambia la próxima ejecución
+/// programada. Devuelve la ejecución, también si falló.
+#[tauri::command]
+pub async fn run_job_now_command(app: AppHandle, job: JobKind) -> Result<JobRun, AppError> {
+    info!("Received run_job_now_command: {}", job.id());
+    run_job(&app, job, JobTrigger::Manual).await
+}
+
+/// Comando para cambiar la programación (expresión cron) de una tarea o activarla y
+/// desactivarla. Un campo ausente no cambia; una programación vacía vuelve a la de
+/// por defecto.
+#[tauri::command]
+pub async fn update_job_command(
+    state: State<'_, AppState>,
+    app: AppHandle,
+    job: JobKind,
+    schedule: Option<String>,
+    enabled: Option<bool>,
+) -> Result<Job, AppError> {
+    debug!("Received update_job_command: {} {:?} {:?}", job.id(), schedule, enabled);
+    let db = state.db().await?;
+    let conn = db.connection();
+    let before = load_job(conn, job)?;
+    let schedule = match schedule.map(|s| s.split_whitespace().collect::<Vec<_>>().join(" ")) {
+        Some(s) if s.is_empty() => job.default_schedule().to_owned(),
+        Some(s) => s,
+        None => before.schedule.clone(),
+    };
+    let next_run_at = CronSchedule::parse(&schedule)?.next_after(periods::now_timestamp()).ok_or_else(|| {
+        AppError::invalid_field("schedule", "La programación no se cumple nunca (p. ej. un 31 de febrero).")
+    })?;
+    let enabled = enabled.unwrap_or(before.enabled);
+    conn.execute(
+        "UPDATE scheduled_jobs SET schedule = ?1, enabled = ?2, next_run_at = ?3, updated_at = ?4 WHERE id = ?5",
+        params![schedule, enabled, next_run_at as i64, periods::now_timestamp() as i64, job.id()],
+    )
+    .map_err(db_error)?;
+    let after = load_job(conn, job)?;
+
+    audit::record(conn, "update_job_command", Some(job.id()), audit::snapshot(&before), audit::snapshot(&after));
+    events::emit_entity(&app, events::JOB_CHANGED_EVENT, ChangeAction::Updated, job.id());
+    info!("Job {} now '{}' ({}).", job.id(), schedule, if enabled { "enabled" } else { "disabled" });
+    Ok(after)
+}
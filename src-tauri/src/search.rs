@@ -0,0 +1,48 @@
+// src-tauri/src/search.rs
+
+use tauri::State;
+use log::debug;
+
+use crate::error::AppError;
+use crate::storage::{TransactionRepository, MAX_PAGE_SIZE};
+use crate::{AppState, Transaction};
+
+/// Resultados devueltos si no se indica `limit`.
+const DEFAULT_SEARCH_LIMIT: usize = 50;
+
+/// Convierte el texto buscado en una expresión FTS5: cada palabra debe aparecer
+/// como prefijo de alguna palabra de la transacción (`elec` encuentra "Electricidad").
+/// Las comillas y demás signos se descartan para que el usuario no pueda escribir
+/// una expresión FTS5 inválida. Devuelve `None` si no queda ninguna palabra.
+pub fn match_expression(query: &str) -> Option<String> {
+    let terms: Vec<String> = query
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .map(|word| format!("\"{}\"*", word))
+        .collect();
+    if terms.is_empty() {
+        None
+    } else {
+        Some(terms.join(" AND "))
+    }
+}
+
+// --- Comandos Tauri ---
+
+/// Comando para buscar transacciones por texto en descripción, tienda, categoría,
+/// etiquetas, notas y campos personalizados, sin distinguir mayúsculas ni acentos. Devuelve primero las más relevantes.
+#[tauri::command]
+pub async fn search_transactions_command(
+    state: State<'_, AppState>,
+    query: String,
+    limit: Option<usize>,
+) -> Result<Vec<Transaction>, AppError> {
+    debug!("Received search_transactions_command: '{}'", query);
+    let Some(match_expr) = match_expression(&query) else {
+        return Ok(Vec::new());
+    };
+    let limit = limit.unwrap_or(DEFAULT_SEARCH_LIMIT).clamp(1, MAX_PAGE_SIZE);
+    let results = state.db().await?.search_transactions(&match_expr, limit)?;
+    debug!("Search '{}' returned {} transactions.", query, results.len());
+    Ok(results)
+}
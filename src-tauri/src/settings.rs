@@ -0,0 +1,483 @@
+// src-tauri/src/settings.rs
+
+use chrono::format::{Item, StrftimeItems};
+use chrono::NaiveDate;
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::time::Duration;
+use tauri::{AppHandle, Emitter, State};
+use log::{debug, error, warn};
+
+use crate::api_server;
+use crate::audit;
+use crate::currencies;
+use crate::email::{self, SmtpSecurity};
+use crate::error::AppError;
+use crate::ai::{AiClient, AiProvider};
+use crate::gemini::{self, GeminiConfig};
+use crate::i18n::{self, Language};
+use crate::local_ai::{self, LocalAiConfig};
+use crate::logging::{self, LogLevel};
+use crate::money::round_money;
+use crate::speech::{self, SpeechProvider};
+use crate::storage::SqliteStorage;
+use crate::sync::{self, SyncProvider};
+use crate::tickets::{self, ReceiptPrinter};
+use crate::validation::ValidationPolicy;
+use crate::webhooks;
+use crate::{AppState, TransactionType};
+
+const SETTINGS_KEY: &str = "settings";
+/// Evento emitido con los nuevos ajustes cada vez que se guardan.
+pub const SETTINGS_CHANGED_EVENT: &str = "settings://changed";
+/// Máximo de horas entre copias de seguridad automáticas (30 días).
+const MAX_BACKUP_INTERVAL_HOURS: u64 = 30 * 24;
+const MAX_AI_TIMEOUT_SECS: u64 = 600;
+const MAX_AI_RETRIES: u32 = 10;
+/// Máximo de horas que se guarda una respuesta de la IA (90 días).
+const MAX_AI_CACHE_TTL_HOURS: u64 = 90 * 24;
+const MAX_SHUTDOWN_TIMEOUT_SECS: u64 = 120;
+
+/// Posición del símbolo de la moneda respecto al importe.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SymbolPosition {
+    Before,
+    After,
+}
+
+/// Tema de la interfaz que prefiere el usuario.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Theme {
+    System,
+    Light,
+    Dark,
+}
+
+/// Preferencias de la aplicación. Los campos que falten en los ajustes guardados
+/// toman su valor por defecto (formato español, copia diaria, tema del sistema).
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Settings {
+    /// Etiqueta BCP 47 (p. ej. `es-ES`), para que el frontend formatee igual.
+    pub locale: String,
+    /// Símbolo de la moneda base.
+    pub currency_symbol: String,
+    pub symbol_position: SymbolPosition,
+    pub decimal_separator: String,
+    /// Puede estar vacío para no separar los miles.
+    pub thousands_separator: String,
+    /// Formato de fecha de chrono (p. ej. `%d/%m/%Y`).
+    pub date_format: String,
+    /// Tienda y tipo que el formulario de nueva transacción propone de entrada.
+    pub default_store: Option<String>,
+    pub default_transaction_type: TransactionType,
+    /// Horas entre copias de seguridad automáticas; 0 las desactiva.
+    pub backup_interval_hours: u64,
+    /// Interruptor general de la IA y consentimiento para cada función (ver
+    /// `ai::ensure_enabled`). Con la IA desactivada no se envía nada a ningún proveedor.
+    pub ai_enabled: bool,
+    pub ai_chat_enabled: bool,
+    pub ai_categorization_enabled: bool,
+    pub ai_ocr_enabled: bool,
+    pub ai_insights_enabled: bool,
+    /// Proveedor de IA: Gemini o un servidor local (Ollama, llama.cpp).
+    pub ai_provider: AiProvider,
+    /// Modelo de Gemini con el que se hacen las llamadas a la IA (ver
+    /// `ai::list_available_models_command`).
+    pub ai_model: String,
+    /// Dirección y modelo del servidor de IA local.
+    pub local_ai_url: String,
+    pub local_ai_model: String,
+    /// Segundos que se espera a la IA antes de darla por caída.
+    pub ai_timeout_secs: u64,
+    /// Reintentos ante fallos de red o de límite de peticiones.
+    pub ai_max_retries: u32,
+    /// Peticiones a Gemini por minuto como máximo (0 sin límite).
+    pub ai_requests_per_minute: u32,
+    /// Gasto mensual estimado máximo en Gemini, en USD (ver `ai_usage`). Sin límite si
+    /// no se indica.
+    pub ai_monthly_spend_cap: Option<Decimal>,
+    /// Horas que se guardan las respuestas de la IA para reutilizarlas en peticiones
+    /// idénticas (ver `ai_cache`); 0 desactiva la caché.
+    pub ai_cache_ttl_hours: u64,
+    /// Modo privacidad (ver `privacy`): enviar a la IA marcadores en lugar de los
+    /// nombres de las tiendas y de las descripciones de las transacciones.
+    pub ai_redact_store_names: bool,
+    pub ai_redact_descriptions: bool,
+    /// Servicio que transcribe las notas de voz (ver `speech`): Gemini o un servidor
+    /// compatible con la API de Whisper de OpenAI, con su dirección y modelo. La clave,
+    /// si hace falta, va al llavero.
+    pub speech_provider: SpeechProvider,
+    pub whisper_url: String,
+    pub whisper_model: String,
+    pub theme: Theme,
+    /// Al cerrar la ventana principal, ocultarla en la bandeja del sistema en lugar
+    /// de salir.
+    pub minimize_to_tray: bool,
+    /// Segundos que se espera al salir a que terminen las tareas en curso y se
+    /// escriban los cambios pendientes (ver `shutdown`).
+    pub shutdown_timeout_secs: u64,
+    /// Saldo en la moneda base antes de la primera transacción registrada. Cada
+    /// espacio de trabajo tiene el suyo.
+    pub opening_balance: Decimal,
+    /// Servicio de sincronización en la nube (ver `sync`), su dirección (carpeta WebDAV
+    /// o endpoint S3) y, para S3, el bucket y la región. Las credenciales van al llavero.
+    pub sync_provider: SyncProvider,
+    pub sync_url: String,
+    pub sync_bucket: String,
+    pub sync_region: String,
+    /// Minutos entre sincronizaciones automáticas; 0 para sincronizar solo a mano.
+    pub sync_interval_minutes: u64,
+    /// Sincronización directa con otros equipos de la red local (ver `lan_sync`) y
+    /// puerto TCP en el que se escucha.
+    pub lan_sync_enabled: bool,
+    pub lan_sync_port: u16,
+    /// Servidor HTTP local para otros programas del equipo (ver `api_server`) y
+    /// puerto en el que escucha.
+    pub api_server_enabled: bool,
+    pub api_server_port: u16,
+    /// Direcciones que reciben los cambios de transacciones y los avisos de
+    /// presupuesto (ver `webhooks`).
+    pub webhook_urls: Vec<String>,
+    /// Servidor SMTP con el que se envían los informes por correo (ver `email`). La
+    /// contraseña va al llavero.
+    pub smtp_host: String,
+    pub smtp_port: u16,
+    pub smtp_security: SmtpSecurity,
+    pub smtp_username: String,
+    /// Remitente, p. ej. `Mi Tienda <tienda@example.com>`.
+    pub smtp_from: String,
+    /// Enviar cada día 1 el informe del mes anterior a `report_email_recipient`.
+    pub report_email_monthly: bool,
+    pub report_email_recipient: String,
+    /// Impresora térmica de los tickets del mostrador (ver `tickets`), su dirección
+    /// (`host[:puerto]` o ruta del dispositivo) y caracteres por línea del papel.
+    pub receipt_printer: ReceiptPrinter,
+    pub receipt_printer_address: String,
+    pub receipt_width_chars: u32,
+    /// Líneas que se imprimen al principio (nombre y datos del negocio) y al final de
+    /// cada ticket.
+    pub receipt_header: String,
+    pub receipt_footer: String,
+    /// Límites y comprobaciones de las transacciones (ver `validation`).
+    pub validation_policy: ValidationPolicy,
+    /// Nivel general de los registros y el de algunos módulos en concreto (p. ej.
+    /// `lan_sync` en `debug` para investigar la sincronización). Ver `logging`.
+    pub log_level: LogLevel,
+    pub log_module_levels: HashMap<String, LogLevel>,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Settings {
+            locale: "es-ES".to_string(),
+            currency_symbol: "€".to_string(),
+            symbol_position: SymbolPosition::After,
+            decimal_separator: ",".to_string(),
+            thousands_separator: ".".to_string(),
+            date_format: "%d/%m/%Y".to_string(),
+            default_store: None,
+            default_transaction_type: TransactionType::Gasto,
+            backup_interval_hours: 24,
+            ai_enabled: true,
+            ai_chat_enabled: true,
+            ai_categorization_enabled: true,
+            ai_ocr_enabled: true,
+            ai_insights_enabled: true,
+            ai_provider: AiProvider::Gemini,
+            ai_model: gemini::DEFAULT_MODEL.to_string(),
+            local_ai_url: local_ai::DEFAULT_URL.to_string(),
+            local_ai_model: local_ai::DEFAULT_MODEL.to_string(),
+            ai_timeout_secs: 60,
+            ai_max_retries: 3,
+            ai_requests_per_minute: 15,
+            ai_monthly_spend_cap: None,
+            ai_cache_ttl_hours: 24,
+            ai_redact_store_names: false,
+            ai_redact_descriptions: false,
+            speech_provider: SpeechProvider::Gemini,
+            whisper_url: speech::DEFAULT_WHISPER_URL.to_string(),
+            whisper_model: speech::DEFAULT_WHISPER_MODEL.to_string(),
+            theme: Theme::System,
+            minimize_to_tray: false,
+            shutdown_timeout_secs: 10,
+            opening_balance: Decimal::ZERO,
+            sync_provider: SyncProvider::Disabled,
+            sync_url: String::new(),
+            sync_bucket: String::new(),
+            sync_region: "us-east-1".to_string(),
+            sync_interval_minutes: 0,
+            lan_sync_enabled: false,
+            lan_sync_port: 47821,
+            api_server_enabled: false,
+            api_server_port: 47822,
+            webhook_urls: Vec::new(),
+            smtp_host: String::new(),
+            smtp_port: 587,
+            smtp_security: SmtpSecurity::StartTls,
+            smtp_username: String::new(),
+            smtp_from: String::new(),
+            report_email_monthly: false,
+            report_email_recipient: String::new(),
+            receipt_printer: ReceiptPrinter::Disabled,
+            receipt_printer_address: String::new(),
+            receipt_width_chars: 42,
+            receipt_header: String::new(),
+            receipt_footer: String::new(),
+            validation_policy: ValidationPolicy::default(),
+            log_level: LogLevel::Info,
+            log_module_levels: HashMap::new(),
+        }
+    }
+}
+
+impl Settings {
+    /// Idioma de los informes y mensajes generados, según `locale`.
+    pub fn language(&self) -> Language {
+        Language::from_locale(&self.locale)
+    }
+
+    /// Importe con dos decimales y los separadores configurados: `-1.234,50`.
+    pub fn format_number(&self, amount: Decimal) -> String {
+        let text = format!("{:.2}", round_money(amount).abs());
+        let (integer, decimals) = text.split_once('.').unwrap_or((&text, "00"));
+
+        let mut grouped = String::new();
+        for (i, c) in integer.chars().enumerate() {
+            if i > 0 && (integer.len() - i) % 3 == 0 {
+                grouped.push_str(&self.thousands_separator);
+            }
+            grouped.push(c);
+        }
+        let sign = if amount.is_sign_negative() && !round_money(amount).is_zero() { "-" } else { "" };
+        format!("{}{}{}{}", sign, grouped, self.decimal_separator, decimals)
+    }
+
+    /// Importe con su moneda: el símbolo configurado si es la moneda base y, si no,
+    /// el código ISO (`1.234,50 €`, `99,00 USD`).
+    pub fn format_money(&self, amount: Decimal, currency: &str, base_currency: &str) -> String {
+        let number = self.format_number(amount);
+        let symbol = if currency == base_currency { self.currency_symbol.as_str() } else { currency };
+        match self.symbol_position {
+            SymbolPosition::Before => format!("{}{}", symbol, number),
+            SymbolPosition::After => format!("{} {}", number, symbol),
+        }
+    }
+
+    pub fn format_date(&self, date: NaiveDate) -> String {
+        date.format(&self.date_format).to_string()
+    }
+
+    /// Proveedor, modelo y límites de red para las llamadas a la IA.
+    pub fn ai_client(&self) -> AiClient {
+        match self.ai_provider {
+            AiProvider::Gemini => AiClient::Gemini(self.gemini_config()),
+            AiProvider::Local => AiClient::Local(LocalAiConfig {
+                base_url: self.local_ai_url.clone(),
+                model: self.local_ai_model.clone(),
+                timeout: Duration::from_secs(self.ai_timeout_secs),
+            }),
+        }
+    }
+
+    /// Modelo y límites de red de Gemini, aunque el proveedor elegido sea otro.
+    pub fn gemini_config(&self) -> GeminiConfig {
+        GeminiConfig {
+            model: self.ai_model.clone(),
+            timeout: Duration::from_secs(self.ai_timeout_secs),
+            max_retries: self.ai_max_retries,
+            requests_per_minute: self.ai_requests_per_minute,
+        }
+    }
+}
+
+/// Lee los ajustes guardados; si no hay o no se pueden leer, los de por defecto.
+pub fn load_settings(db: &SqliteStorage) -> Settings {
+    match db.get_setting(SETTINGS_KEY) {
+        Ok(Some(json)) => serde_json::from_str(&json).unwrap_or_else(|e| {
+            warn!("Invalid saved settings, using defaults: {}", e);
+            Settings::default()
+        }),
+        Ok(None) => Settings::default(),
+        Err(e) => {
+            warn!("Could not read settings, using defaults: {}", e);
+            Settings::default()
+        }
+    }
+}
+
+/// Valida y guarda los ajustes. Devuelve los ajustes tal como quedan guardados.
+pub fn save_settings(db: &SqliteStorage, settings: Settings) -> Result<Settings, AppError> {
+    let settings = validate_settings(settings)?;
+    let json = serde_json::to_string(&settings)
+        .map_err(|e| AppError::Internal(format!("Error al guardar los ajustes: {}", e)))?;
+    db.set_setting(SETTINGS_KEY, &json)?;
+    i18n::set_current(settings.language());
+    logging::apply_settings(&settings);
+    Ok(settings)
+}
+
+fn validate_settings(mut settings: Settings) -> Result<Settings, AppError> {
+    settings.locale = settings.locale.trim().to_owned();
+    settings.currency_symbol = settings.currency_symbol.trim().to_owned();
+    if settings.locale.is_empty() {
+        return Err(AppError::invalid_field("locale", "El idioma no puede estar vacío."));
+    }
+    if settings.default_transaction_type == TransactionType::Transferencia {
+        return Err(AppError::invalid_field(
+            "default_transaction_type",
+            "El tipo por defecto debe ser ingreso o gasto.",
+        ));
+    }
+    if settings.currency_symbol.is_empty() {
+        return Err(AppError::invalid_field("currency_symbol", "El símbolo de la moneda no puede estar vacío."));
+    }
+    if settings.decimal_separator.chars().count() != 1 {
+        return Err(AppError::invalid_field("decimal_separator", "El separador decimal debe ser un único carácter."));
+    }
+    if settings.thousands_separator.chars().count() > 1 {
+        return Err(AppError::invalid_field(
+            "thousands_separator",
+            "El separador de miles debe ser un único carácter o estar vacío.",
+        ));
+    }
+    if settings.thousands_separator == settings.decimal_separator {
+        return Err(AppError::invalid_field(
+            "thousands_separator",
+            "El separador de miles no puede ser igual que el decimal.",
+        ));
+    }
+    let date_format = settings.date_format.trim();
+    if date_format.is_empty() || StrftimeItems::new(date_format).any(|item| matches!(item, Item::Error)) {
+        error!("Invalid date format: '{}'", settings.date_format);
+        return Err(AppError::invalid_field("date_format", "El formato de fecha no es válido."));
+    }
+    settings.date_format = date_format.to_owned();
+    settings.default_store = settings.default_store.map(|s| s.trim().to_owned()).filter(|s| !s.is_empty());
+    if settings.backup_interval_hours > MAX_BACKUP_INTERVAL_HOURS {
+        return Err(AppError::invalid_field(
+            "backup_interval_hours",
+            format!("Las copias automáticas deben hacerse al menos cada {} horas.", MAX_BACKUP_INTERVAL_HOURS),
+        ));
+    }
+    settings.ai_model = settings.ai_model.trim().to_owned();
+    gemini::validate_model(&settings.ai_model)?;
+    settings.local_ai_url = settings.local_ai_url.trim().trim_end_matches('/').to_owned();
+    local_ai::validate_url(&settings.local_ai_url)?;
+    settings.local_ai_model = settings.local_ai_model.trim().to_owned();
+    if settings.local_ai_model.is_empty() {
+        return Err(AppError::invalid_field("local_ai_model", "Indica el modelo de la IA local."));
+    }
+    if settings.ai_timeout_secs == 0 || settings.ai_timeout_secs > MAX_AI_TIMEOUT_SECS {
+        return Err(AppError::invalid_field(
+            "ai_timeout_secs",
+            format!("El tiempo de espera de la IA debe estar entre 1 y {} segundos.", MAX_AI_TIMEOUT_SECS),
+        ));
+    }
+    if settings.ai_cache_ttl_hours > MAX_AI_CACHE_TTL_HOURS {
+        return Err(AppError::invalid_field(
+            "ai_cache_ttl_hours",
+            format!("Las respuestas de la IA no se pueden guardar más de {} horas.", MAX_AI_CACHE_TTL_HOURS),
+        ));
+    }
+    if settings.shutdown_timeout_secs == 0 || settings.shutdown_timeout_secs > MAX_SHUTDOWN_TIMEOUT_SECS {
+        return Err(AppError::invalid_field(
+            "shutdown_timeout_secs",
+            format!("El tiempo de espera al salir debe estar entre 1 y {} segundos.", MAX_SHUTDOWN_TIMEOUT_SECS),
+        ));
+    }
+    settings.whisper_url = settings.whisper_url.trim().trim_end_matches('/').to_owned();
+    settings.whisper_model = settings.whisper_model.trim().to_owned();
+    speech::validate_settings(&settings)?;
+    settings.opening_balance = round_money(settings.opening_balance);
+    settings.sync_url = settings.sync_url.trim().trim_end_matches('/').to_owned();
+    settings.sync_bucket = settings.sync_bucket.trim().to_owned();
+    settings.sync_region = settings.sync_region.trim().to_owned();
+    sync::validate_settings(&settings)?;
+    if settings.lan_sync_port < 1024 {
+        return Err(AppError::invalid_field("lan_sync_port", "El puerto debe estar entre 1024 y 65535."));
+    }
+    settings.webhook_urls = webhooks::validate_urls(&settings.webhook_urls)?;
+    settings.smtp_host = settings.smtp_host.trim().to_owned();
+    settings.smtp_username = settings.smtp_username.trim().to_owned();
+    settings.smtp_from = settings.smtp_from.trim().to_owned();
+    settings.report_email_recipient = settings.report_email_recipient.trim().to_owned();
+    email::validate_settings(&settings)?;
+    settings.receipt_printer_address = settings.receipt_printer_address.trim().to_owned();
+    settings.receipt_header = settings.receipt_header.trim().to_owned();
+    settings.receipt_footer = settings.receipt_footer.trim().to_owned();
+    tickets::validate_settings(&settings)?;
+    if settings.api_server_port < 1024 {
+        return Err(AppError::invalid_field("api_server_port", "El puerto debe estar entre 1024 y 65535."));
+    }
+    if settings.api_server_enabled && settings.lan_sync_enabled && settings.api_server_port == settings.lan_sync_port {
+        return Err(AppError::invalid_field(
+            "api_server_port",
+            "El puerto de la API no puede ser el de la sincronización en red local.",
+        ));
+    }
+    if settings.ai_monthly_spend_cap.is_some_and(|cap| cap.is_sign_negative()) {
+        return Err(AppError::invalid_field("ai_monthly_spend_cap", "El límite de gasto no puede ser negativo."));
+    }
+    if settings.ai_max_retries > MAX_AI_RETRIES {
+        return Err(AppError::invalid_field(
+            "ai_max_retries",
+            format!("Los reintentos de la IA no pueden ser más de {}.", MAX_AI_RETRIES),
+        ));
+    }
+    settings.validation_policy.validate()?;
+    settings.log_module_levels = logging::validate_module_levels(&settings.log_module_levels)?;
+    Ok(settings)
+}
+
+// --- Comandos Tauri ---
+
+/// Comando para obtener los ajustes de la aplicación.
+#[tauri::command]
+pub async fn get_settings_command(state: State<'_, AppState>) -> Result<Settings, AppError> {
+    debug!("Received get_settings_command.");
+    Ok(load_settings(&state.db().await?))
+}
+
+/// Comando para guardar los ajustes de la aplicación. Devuelve los ajustes guardados
+/// y los emite como `settings://changed` para que todas las ventanas se actualicen.
+#[tauri::command]
+pub async fn update_settings_command(
+    state: State<'_, AppState>,
+    app: AppHandle,
+    settings: Settings,
+) -> Result<Settings, AppError> {
+    debug!("Received update_settings_command: {:?}", settings);
+    let db = state.db().await?;
+    let previous = load_settings(&db);
+    let settings = save_settings(&db, settings)?;
+    audit::record(
+        db.connection(),
+        "update_settings_command",
+        Some(SETTINGS_KEY),
+        audit::snapshot(&previous),
+        audit::snapshot(&settings),
+    );
+    if let Err(e) = app.emit(SETTINGS_CHANGED_EVENT, &settings) {
+        warn!("Failed to emit settings change: {}", e);
+    }
+    api_server::settings_changed();
+    Ok(settings)
+}
+
+/// Comando para formatear un importe con los ajustes guardados. Sin `currency` se
+/// entiende que está en la moneda base.
+#[tauri::command]
+pub async fn format_currency_command(
+    state: State<'_, AppState>,
+    amount: Decimal,
+    currency: Option<String>,
+) -> Result<String, AppError> {
+    let db = state.db().await?;
+    let base_currency = currencies::get_base_currency(&db)?;
+    let currency = currency.unwrap_or_else(|| base_currency.clone());
+    Ok(load_settings(&db).format_money(amount, &currency, &base_currency))
+}
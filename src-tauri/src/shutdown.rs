@@ -0,0 +1,193 @@
+// src-tauri/src/shutdown.rs
+
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use tauri::{AppHandle, Listener, Manager, RunEvent};
+use log::{debug, info, warn};
+
+use crate::autosave;
+use crate::events;
+use crate::periods;
+use crate::settings::{self, Settings, SETTINGS_CHANGED_EVENT};
+use crate::storage;
+use crate::AppState;
+
+// Al pedir la salida (cerrar la última ventana, "Salir" en la bandeja o
+// `quit_app_command`) la aplicación no termina enseguida: se espera a las tareas en
+// curso (tareas programadas, respuestas del chat), se escriben los cambios cifrados
+// pendientes y se deja una marca de cierre limpio. Si la marca falta al arrancar, la
+// sesión anterior terminó de forma inesperada.
+
+/// Archivo con la marca de sesión, en el directorio de datos de la aplicación.
+const SESSION_MARKER_FILE: &str = "session.json";
+/// Cada cuánto se comprueba si terminaron las tareas en curso.
+const WAIT_TICK: Duration = Duration::from_millis(100);
+
+/// Evento emitido al empezar el cierre, para que la interfaz guarde lo que tenga a
+/// medias. Carga: `()`.
+pub const SHUTTING_DOWN_EVENT: &str = "app://shutting-down";
+
+static SHUTTING_DOWN: AtomicBool = AtomicBool::new(false);
+/// `true` cuando el cierre ordenado terminó y ya se puede salir.
+static FINISHED: AtomicBool = AtomicBool::new(false);
+static PREVIOUS_UNCLEAN: AtomicBool = AtomicBool::new(false);
+/// Copia del ajuste `shutdown_timeout_secs`, para consultarlo al salir sin esperar a
+/// la base de datos.
+static TIMEOUT_SECS: AtomicU64 = AtomicU64::new(10);
+/// Tareas que deben terminar antes de salir.
+static IN_FLIGHT: Mutex<Vec<&'static str>> = Mutex::new(Vec::new());
+
+/// Contenido de `SESSION_MARKER_FILE`.
+#[derive(Debug, Serialize, Deserialize)]
+struct SessionMarker {
+    /// `false` mientras la aplicación está abierta; `true` tras un cierre ordenado.
+    clean: bool,
+    updated_at: u64,
+}
+
+fn marker_path() -> PathBuf {
+    storage::get_app_data_dir().join(SESSION_MARKER_FILE)
+}
+
+fn write_marker(clean: bool) {
+    let marker = SessionMarker { clean, updated_at: periods::now_timestamp() };
+    let contents = serde_json::to_vec(&marker).unwrap_or_default();
+    if let Err(e) = storage::write_atomic(&marker_path(), &contents) {
+        warn!("Could not write the session marker: {}", e);
+    }
+}
+
+/// Se llama al principio de `main`: comprueba si la sesión anterior se cerró de forma
+/// ordenada y marca la actual como abierta.
+pub fn mark_session_started() {
+    let previous = std::fs::read(marker_path())
+        .ok()
+        .and_then(|contents| serde_json::from_slice::<SessionMarker>(&contents).ok());
+    // Sin marca es la primera vez que se abre (o una versión anterior): no se avisa.
+    if let Some(marker) = previous.filter(|m| !m.clean) {
+        warn!("The previous session did not shut down cleanly (last seen at {}).", marker.updated_at);
+        PREVIOUS_UNCLEAN.store(true, Ordering::SeqCst);
+    }
+    write_marker(false);
+}
+
+/// `true` si la sesión anterior terminó sin pasar por el cierre ordenado (un fallo,
+/// un corte de luz o un cierre forzado).
+pub fn previous_shutdown_unclean() -> bool {
+    PREVIOUS_UNCLEAN.load(Ordering::SeqCst)
+}
+
+/// `true` desde que se pidió salir. Las tareas en segundo plano no deben empezar otras.
+pub fn is_shutting_down() -> bool {
+    SHUTTING_DOWN.load(Ordering::SeqCst)
+}
+
+/// Tarea en curso que retrasa la salida hasta que termina o vence el tiempo máximo.
+pub struct InFlightGuard(&'static str);
+
+impl Drop for InFlightGuard {
+    fn drop(&mut self) {
+        if let Ok(mut tasks) = IN_FLIGHT.lock() {
+            if let Some(index) = tasks.iter().position(|task| *task == self.0) {
+                tasks.remove(index);
+            }
+        }
+    }
+}
+
+/// Registra una tarea que debe terminar antes de salir. Se quita al soltar el guard.
+pub fn track(task: &'static str) -> InFlightGuard {
+    if let Ok(mut tasks) = IN_FLIGHT.lock() {
+        tasks.push(task);
+    }
+    InFlightGuard(task)
+}
+
+fn in_flight() -> Vec<&'static str> {
+    IN_FLIGHT.lock().map(|tasks| tasks.clone()).unwrap_or_default()
+}
+
+/// Mantiene al día la copia del tiempo máximo de cierre. Se llama desde `setup`.
+pub fn setup(app: &AppHandle) {
+    app.listen_any(SETTINGS_CHANGED_EVENT, |event| {
+        match serde_json::from_str::<Settings>(event.payload()) {
+            Ok(settings) => TIMEOUT_SECS.store(settings.shutdown_timeout_secs, Ordering::SeqCst),
+            Err(e) => warn!("Invalid settings event payload: {}", e),
+        }
+    });
+    let handle = app.clone();
+    tauri::async_runtime::spawn(async move {
+        if let Ok(db) = handle.state::<AppState>().db().await {
+            TIMEOUT_SECS.store(settings::load_settings(&db).shutdown_timeout_secs, Ordering::SeqCst);
+        }
+    });
+}
+
+/// Cierre ordenado: espera a las tareas en curso y a la base de datos como mucho
+/// `shutdown_timeout_secs`, escribe los cambios pendientes y, si todo terminó a
+/// tiempo, deja la marca de cierre limpio. Después sale con `code`.
+async fn shut_down(app: AppHandle, code: i32) {
+    let timeout = Duration::from_secs(TIMEOUT_SECS.load(Ordering::SeqCst));
+    let deadline = Instant::now() + timeout;
+    info!("Shutting down (waiting up to {:?}).", timeout);
+    events::emit(&app, SHUTTING_DOWN_EVENT, ());
+
+    let mut pending = in_flight();
+    while !pending.is_empty() && Instant::now() < deadline {
+        tokio::time::sleep(WAIT_TICK).await;
+        pending = in_flight();
+    }
+    let mut clean = pending.is_empty();
+    if !clean {
+        warn!("Exiting with unfinished tasks: {:?}", pending);
+    }
+
+    let state = app.state::<AppState>();
+    if !state.is_locked() {
+        let remaining = deadline.saturating_duration_since(Instant::now());
+        match tokio::time::timeout(remaining, state.db.lock()).await {
+            Ok(db) => {
+                if let Err(e) = db.flush() {
+                    warn!("No se pudieron guardar los datos cifrados al salir: {}", e);
+                    clean = false;
+                }
+            }
+            Err(_) => {
+                warn!("Database still busy after {:?}; exiting without flushing.", timeout);
+                clean = false;
+            }
+        }
+    }
+    if clean {
+        write_marker(true);
+        debug!("Clean shutdown recorded.");
+    }
+    FINISHED.store(true, Ordering::SeqCst);
+    app.exit(code);
+}
+
+/// Atiende los eventos de salida del bucle de Tauri. La primera petición de salida se
+/// retiene para hacer el cierre ordenado; al terminar, `shut_down` vuelve a pedirla.
+pub fn handle_run_event(app: &AppHandle, event: &RunEvent) {
+    match event {
+        RunEvent::ExitRequested { api, code, .. } => {
+            if FINISHED.load(Ordering::SeqCst) {
+                return;
+            }
+            api.prevent_exit();
+            if !SHUTTING_DOWN.swap(true, Ordering::SeqCst) {
+                tauri::async_runtime::spawn(shut_down(app.clone(), code.unwrap_or(0)));
+            }
+        }
+        // Salida sin pasar por `ExitRequested`: se guarda lo que se pueda sin esperar.
+        RunEvent::Exit if !FINISHED.load(Ordering::SeqCst) => {
+            if autosave::flush_on_exit(&app.state::<AppState>()) && in_flight().is_empty() {
+                write_marker(true);
+            }
+        }
+        _ => {}
+    }
+}
@@ -0,0 +1,234 @@
+// src-tauri/src/speech.rs
+
+use base64::Engine;
+use reqwest::Client;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::path::Path;
+use std::time::Duration;
+use tauri::State;
+use tokio::fs;
+use log::{debug, error, info};
+
+use crate::ai::{self, AiClient, AiFeature, AiMessage, GenerationParams};
+use crate::error::AppError;
+use crate::keychain;
+use crate::settings::{self, Settings};
+use crate::text_entry::{self, TransactionTextDraft};
+use crate::AppState;
+
+/// Dirección por defecto del servicio de Whisper (la API de OpenAI).
+pub const DEFAULT_WHISPER_URL: &str = "https://api.openai.com";
+pub const DEFAULT_WHISPER_MODEL: &str = "whisper-1";
+/// Tamaño máximo de una nota de voz. Gemini no admite más de 20 MB en línea y la API
+/// de Whisper de OpenAI tampoco acepta más de 25 MB.
+const MAX_AUDIO_BYTES: u64 = 20 * 1024 * 1024;
+/// Nombre de la entrada del llavero con la clave del servicio de Whisper.
+const WHISPER_API_KEY_SECRET: &str = "whisper_api_key";
+
+const TRANSCRIPTION_PROMPT: &str = "Transcribe literalmente esta nota de voz en español. \
+Devuelve solo el texto dicho, sin comentarios ni marcas de tiempo. Escribe los importes \
+con cifras (\"85,40\").";
+
+/// Servicio que convierte las notas de voz en texto.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SpeechProvider {
+    /// Gemini, con el modelo y la clave de los ajustes de IA.
+    Gemini,
+    /// Servidor con la API `/v1/audio/transcriptions` de OpenAI: la propia OpenAI o un
+    /// servidor local como whisper.cpp, para que el audio no salga del equipo.
+    Whisper,
+}
+
+/// Comprueba la configuración de la transcripción de los ajustes.
+pub fn validate_settings(settings: &Settings) -> Result<(), AppError> {
+    if settings.speech_provider != SpeechProvider::Whisper {
+        return Ok(());
+    }
+    let url = &settings.whisper_url;
+    if !(url.starts_with("http://") || url.starts_with("https://")) || url.contains(char::is_whitespace) {
+        error!("Invalid Whisper URL: '{}'", url);
+        return Err(AppError::invalid_field(
+            "whisper_url",
+            "La dirección del servicio de transcripción debe empezar por http:// o https://.",
+        ));
+    }
+    if settings.whisper_model.is_empty() {
+        return Err(AppError::invalid_field("whisper_model", "Indica el modelo de transcripción."));
+    }
+    Ok(())
+}
+
+/// Tipo MIME según la extensión del archivo de audio.
+fn audio_mime_type(path: &Path) -> Option<&'static str> {
+    let extension = path.extension()?.to_string_lossy().to_lowercase();
+    match extension.as_str() {
+        "mp3" => Some("audio/mpeg"),
+        "wav" => Some("audio/wav"),
+        "ogg" | "oga" | "opus" => Some("audio/ogg"),
+        "flac" => Some("audio/flac"),
+        "m4a" | "mp4" => Some("audio/mp4"),
+        "aac" => Some("audio/aac"),
+        "webm" => Some("audio/webm"),
+        _ => None,
+    }
+}
+
+/// Transcribe el audio con Gemini. Cuenta para el límite de gasto como cualquier otra
+/// llamada a la IA.
+async fn transcribe_with_gemini(
+    state: &AppState,
+    settings: &Settings,
+    bytes: &[u8],
+    mime_type: &str,
+) -> Result<String, AppError> {
+    let client = AiClient::Gemini(settings.gemini_config());
+    let encoded = base64::engine::general_purpose::STANDARD.encode(bytes);
+    let message = AiMessage::user(TRANSCRIPTION_PROMPT).with_image(mime_type, encoded);
+    ai::generate(state, &client, &[message], &GenerationParams::default()).await
+}
+
+/// Cuerpo `multipart/form-data` con los campos de texto `fields` y el audio en `file`.
+fn multipart_body(boundary: &str, fields: &[(&str, &str)], file_name: &str, mime_type: &str, bytes: &[u8]) -> Vec<u8> {
+    let mut body = Vec::with_capacity(bytes.len() + 1024);
+    for (name, value) in fields {
+        body.extend_from_slice(
+            format!("--{}\r\nContent-Disposition: form-data; name=\"{}\"\r\n\r\n{}\r\n", boundary, name, value).as_bytes(),
+        );
+    }
+    let file_name = file_name.replace(['"', '\r', '\n'], "_");
+    body.extend_from_slice(
+        format!(
+            "--{}\r\nContent-Disposition: form-data; name=\"file\"; filename=\"{}\"\r\nContent-Type: {}\r\n\r\n",
+            boundary, file_name, mime_type
+        )
+        .as_bytes(),
+    );
+    body.extend_from_slice(bytes);
+    body.extend_from_slice(format!("\r\n--{}--\r\n", boundary).as_bytes());
+    body
+}
+
+/// Transcribe el audio con un servidor compatible con la API de Whisper de OpenAI.
+async fn transcribe_with_whisper(
+    settings: &Settings,
+    bytes: &[u8],
+    file_name: &str,
+    mime_type: &str,
+) -> Result<String, AppError> {
+    let url = format!("{}/v1/audio/transcriptions", settings.whisper_url);
+    let boundary = format!("----contabilidad-{}", uuid::Uuid::new_v4().simple());
+    let body = multipart_body(
+        &boundary,
+        &[("model", settings.whisper_model.as_str()), ("language", "es"), ("response_format", "json")],
+        file_name,
+        mime_type,
+        bytes,
+    );
+
+    let client = Client::builder()
+        .timeout(Duration::from_secs(settings.ai_timeout_secs))
+        .build()
+        .map_err(|e| AppError::Internal(format!("No se pudo crear el cliente HTTP: {}", e)))?;
+    let mut request = client
+        .post(&url)
+        .header(reqwest::header::CONTENT_TYPE, format!("multipart/form-data; boundary={}", boundary))
+        .body(body);
+    if let Some(key) = keychain::get_secret(WHISPER_API_KEY_SECRET)? {
+        request = request.bearer_auth(key);
+    }
+    debug!("Sending audio to Whisper at {}", settings.whisper_url);
+    let response = request.send().await.map_err(|e| {
+        error!("Error connecting to Whisper at {}: {}", settings.whisper_url, e);
+        if e.is_timeout() {
+            AppError::Network(format!("El servicio de transcripción no respondió a tiempo ({} s).", settings.ai_timeout_secs))
+        } else {
+            AppError::Network(format!("No se pudo conectar con el servicio de transcripción en {}.", settings.whisper_url))
+        }
+    })?;
+    let status = response.status();
+    let body: Value = response.json().await.unwrap_or(Value::Null);
+    if !status.is_success() {
+        let message = body.pointer("/error/message").and_then(|m| m.as_str()).unwrap_or("desconocido");
+        error!("Whisper request failed with {}: {}", status, message);
+        return Err(AppError::Ai(format!("Error del servicio de transcripción: {}", message)));
+    }
+    body.get("text").and_then(|t| t.as_str()).map(str::to_owned).ok_or_else(|| {
+        error!("Whisper response without text.");
+        AppError::Ai("El servicio de transcripción no devolvió texto.".to_string())
+    })
+}
+
+/// Lee la nota de voz de `audio_path` y la transcribe con el servicio de los ajustes.
+pub async fn transcribe(state: &AppState, audio_path: &str) -> Result<String, AppError> {
+    let path = Path::new(audio_path);
+    let mime_type = audio_mime_type(path).ok_or_else(|| {
+        error!("Unsupported audio file type: {}", path.display());
+        AppError::invalid_field(
+            "audio_path",
+            "Tipo de archivo no admitido. Usa una grabación MP3, WAV, OGG, FLAC, M4A, AAC o WEBM.",
+        )
+    })?;
+    let metadata = fs::metadata(path)
+        .await
+        .map_err(|e| AppError::Io(format!("No se pudo leer el archivo {}: {}", path.display(), e)))?;
+    if metadata.len() > MAX_AUDIO_BYTES {
+        error!("Audio too large: {} bytes", metadata.len());
+        return Err(AppError::invalid_field("audio_path", "La grabación es demasiado grande (máximo 20 MB)."));
+    }
+    let bytes = fs::read(path)
+        .await
+        .map_err(|e| AppError::Io(format!("No se pudo leer el archivo {}: {}", path.display(), e)))?;
+
+    let settings = settings::load_settings(&state.db().await?);
+    let text = match settings.speech_provider {
+        SpeechProvider::Gemini => transcribe_with_gemini(state, &settings, &bytes, mime_type).await?,
+        SpeechProvider::Whisper => {
+            let file_name = path.file_name().map(|n| n.to_string_lossy().into_owned()).unwrap_or_default();
+            transcribe_with_whisper(&settings, &bytes, &file_name, mime_type).await?
+        }
+    };
+    let text = text.trim().to_owned();
+    if text.is_empty() {
+        return Err(AppError::Ai("No se entendió nada en la grabación.".to_string()));
+    }
+    Ok(text)
+}
+
+/// Transcripción de una nota de voz y el borrador de transacción leído de ella.
+#[derive(Debug, Clone, Serialize)]
+pub struct VoiceNoteDraft {
+    pub transcript: String,
+    #[serde(flatten)]
+    pub parsed: TransactionTextDraft,
+}
+
+// --- Comandos Tauri ---
+
+/// Comando para convertir una nota de voz en un borrador de transacción: la
+/// transcribe con el servicio de los ajustes y la interpreta como
+/// `parse_transaction_text_command`. Nunca se guarda nada sin que el usuario confirme.
+#[tauri::command]
+pub async fn transcribe_and_parse_command(
+    state: State<'_, AppState>,
+    audio_path: String,
+) -> Result<VoiceNoteDraft, AppError> {
+    info!("Received transcribe_and_parse_command for '{}'", audio_path);
+    ai::ensure_enabled(&settings::load_settings(&state.db().await?), Some(AiFeature::Categorization))?;
+    let transcript = transcribe(&state, &audio_path).await?;
+    debug!("Voice note transcribed ({} chars).", transcript.len());
+    let parsed = text_entry::parse_transaction_text(&state, &transcript).await?;
+    Ok(VoiceNoteDraft { transcript, parsed })
+}
+
+/// Comando para guardar la clave del servicio de Whisper en el llavero del sistema.
+/// Con una clave vacía o ausente se borra la guardada (p. ej. para un servidor local).
+#[tauri::command]
+pub async fn set_whisper_api_key_command(api_key: Option<String>) -> Result<(), AppError> {
+    debug!("Received set_whisper_api_key_command.");
+    match api_key.map(|k| k.trim().to_owned()).filter(|k| !k.is_empty()) {
+        Some(key) => keychain::set_secret(WHISPER_API_KEY_SECRET, &key),
+        None => keychain::delete_secret(WHISPER_API_KEY_SECRET),
+    }
+}
@@ -0,0 +1,311 @@
+// src-tauri/src/splits.rs
+
+use rusqlite::{params, Connection, Row};
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use tauri::{AppHandle, State};
+use log::{debug, info, warn};
+
+use crate::audit;
+use crate::budgets;
+use crate::categories;
+use crate::error::AppError;
+use crate::events;
+use crate::history::{Change, HistoryEntry};
+use crate::money;
+use crate::storage::{self, db_error, SqliteStorage, TransactionRepository};
+use crate::stores;
+use crate::{AppState, Transaction};
+
+/// Número máximo de partes de una transacción.
+const MAX_SPLITS: usize = 50;
+
+/// Parte de una transacción repartida entre categorías (p. ej. el ticket del
+/// supermercado: 30 € de Alimentación y 12 € de Limpieza). El importe está en la
+/// moneda de la transacción y las partes suman su importe.
+#[derive(Debug, Clone, Serialize)]
+pub struct TransactionSplit {
+    pub id: String,
+    pub transaction_id: String,
+    pub amount: Decimal,
+    pub category: Option<String>,
+    pub subcategory: Option<String>,
+    pub notes: String,
+}
+
+/// Datos de una parte en `set_transaction_splits_command`.
+#[derive(Debug, Clone, Deserialize)]
+pub struct SplitInput {
+    pub amount: Decimal,
+    #[serde(default)]
+    pub category: Option<String>,
+    #[serde(default)]
+    pub subcategory: Option<String>,
+    #[serde(default)]
+    pub notes: Option<String>,
+}
+
+const SPLIT_COLUMNS: &str = "id, transaction_id, amount, category, subcategory, notes";
+
+fn row_to_split(row: &Row) -> rusqlite::Result<TransactionSplit> {
+    Ok(TransactionSplit {
+        id: row.get(0)?,
+        transaction_id: row.get(1)?,
+        amount: storage::decimal_column(row, 2)?,
+        category: row.get(3)?,
+        subcategory: row.get(4)?,
+        notes: row.get(5)?,
+    })
+}
+
+/// Partes de una transacción, en el orden en que se indicaron.
+pub fn list_splits(conn: &Connection, transaction_id: &str) -> Result<Vec<TransactionSplit>, AppError> {
+    let mut stmt = conn
+        .prepare(&format!(
+            "SELECT {} FROM transaction_splits WHERE transaction_id = ?1 ORDER BY position",
+            SPLIT_COLUMNS
+        ))
+        .map_err(db_error)?;
+    let rows = stmt.query_map(params![transaction_id], row_to_split).map_err(db_error)?;
+    rows.collect::<rusqlite::Result<Vec<_>>>().map_err(db_error)
+}
+
+/// Partes de todas las transacciones repartidas, por ID de transacción.
+fn load_all(conn: &Connection) -> Result<HashMap<String, Vec<TransactionSplit>>, AppError> {
+    let mut stmt = conn
+        .prepare(&format!("SELECT {} FROM transaction_splits ORDER BY transaction_id, position", SPLIT_COLUMNS))
+        .map_err(db_error)?;
+    let rows = stmt.query_map([], row_to_split).map_err(db_error)?;
+    let mut by_transaction: HashMap<String, Vec<TransactionSplit>> = HashMap::new();
+    for split in rows {
+        let split = split.map_err(db_error)?;
+        by_transaction.entry(split.transaction_id.clone()).or_default().push(split);
+    }
+    Ok(by_transaction)
+}
+
+/// Copias de `transaction` por parte, con el importe y la categoría de cada una. Si
+/// las partes ya no suman el importe (no debería ocurrir) se usa la transacción entera.
+fn expand_one(transaction: Transaction, parts: Vec<TransactionSplit>) -> Vec<Transaction> {
+    if parts.iter().map(|s| s.amount).sum::<Decimal>() != transaction.amount {
+        warn!("Splits of transaction {} do not add up to its amount; using the whole transaction.", transaction.id);
+        return vec![transaction];
+    }
+    parts
+        .into_iter()
+        .map(|part| Transaction {
+            amount: part.amount,
+            category: part.category,
+            subcategory: part.subcategory,
+            ..transaction.clone()
+        })
+        .collect()
+}
+
+/// Sustituye cada transacción repartida por una copia por parte (ver `expand_one`),
+/// para que los informes por categoría sumen a ese nivel. Las copias conservan el ID
+/// de la transacción.
+pub fn expand(conn: &Connection, transactions: Vec<Transaction>) -> Result<Vec<Transaction>, AppError> {
+    let mut splits = load_all(conn)?;
+    if splits.is_empty() {
+        return Ok(transactions);
+    }
+    let mut expanded = Vec::with_capacity(transactions.len());
+    for transaction in transactions {
+        match splits.remove(&transaction.id) {
+            Some(parts) => expanded.extend(expand_one(transaction, parts)),
+            None => expanded.push(transaction),
+        }
+    }
+    Ok(expanded)
+}
+
+/// Transacciones activas repartidas con `timestamp` en `[from, to)`, cada una con sus
+/// copias por parte. Sirve para corregir los totales por categoría de `aggregates`,
+/// que cuentan la transacción entera (ver `dashboard`).
+pub fn split_transactions(db: &SqliteStorage, from: u64, to: u64) -> Result<Vec<(Transaction, Vec<Transaction>)>, AppError> {
+    let mut result = Vec::new();
+    for (id, parts) in load_all(db.connection())? {
+        let Some(transaction) = db.get_transaction(&id)? else { continue };
+        if transaction.deleted_at.is_some() || transaction.timestamp < from || transaction.timestamp >= to {
+            continue;
+        }
+        let expanded = expand_one(transaction.clone(), parts);
+        result.push((transaction, expanded));
+    }
+    Ok(result)
+}
+
+/// Sustituye el reparto guardado de `transaction_id` por `splits`. Quien llama abre la
+/// transacción SQL.
+pub fn replace_splits(conn: &Connection, transaction_id: &str, splits: &[TransactionSplit]) -> Result<(), AppError> {
+    conn.execute("DELETE FROM transaction_splits WHERE transaction_id = ?1", params![transaction_id])
+        .map_err(db_error)?;
+    for (position, split) in splits.iter().enumerate() {
+        conn.execute(
+            "INSERT INTO transaction_splits (id, transaction_id, position, amount, category, subcategory, notes)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+            params![
+                split.id,
+                transaction_id,
+                position as u32,
+                split.amount.to_string(),
+                split.category,
+                split.subcategory,
+                split.notes
+            ],
+        )
+        .map_err(db_error)?;
+    }
+    Ok(())
+}
+
+/// Error si se cambia el importe de una transacción repartida: antes hay que ajustar
+/// o quitar el reparto.
+pub fn check_amount_change(conn: &Connection, transaction: &Transaction, amount: Decimal) -> Result<(), AppError> {
+    if amount != transaction.amount && !list_splits(conn, &transaction.id)?.is_empty() {
+        return Err(AppError::invalid_field(
+            "amount",
+            "La transacción está repartida entre categorías. Ajusta o quita antes el reparto.",
+        ));
+    }
+    Ok(())
+}
+
+/// Valida las partes de `transaction`: al menos dos, importes positivos y que sumen
+/// exactamente el importe de la transacción.
+fn resolve_splits(conn: &Connection, transaction: &Transaction, inputs: Vec<SplitInput>) -> Result<Vec<TransactionSplit>, AppError> {
+    if inputs.len() == 1 {
+        return Err(AppError::invalid_field("splits", "Reparte la transacción en al menos dos partes."));
+    }
+    if inputs.len() > MAX_SPLITS {
+        return Err(AppError::invalid_field("splits", format!("Como máximo {} partes.", MAX_SPLITS)));
+    }
+    let mut splits = Vec::with_capacity(inputs.len());
+    for input in inputs {
+        money::validate_amount("splits", input.amount, "El importe de cada parte debe ser positivo.")?;
+        let (category, subcategory) = categories::resolve_transaction_category(conn, input.category, input.subcategory)?;
+        splits.push(TransactionSplit {
+            id: uuid::Uuid::new_v4().to_string(),
+            transaction_id: transaction.id.clone(),
+            amount: input.amount,
+            category,
+            subcategory,
+            notes: stores::non_empty(input.notes).unwrap_or_default(),
+        });
+    }
+    let total: Decimal = splits.iter().map(|s| s.amount).sum();
+    if !splits.is_empty() && total != transaction.amount {
+        return Err(AppError::invalid_field(
+            "splits",
+            format!("Las partes suman {} y la transacción es de {} {}.", total, transaction.amount, transaction.currency),
+        ));
+    }
+    Ok(splits)
+}
+
+// --- Comandos Tauri ---
+
+/// Comando para obtener el reparto de una transacción. Vacío si no está repartida.
+#[tauri::command]
+pub async fn get_transaction_splits_command(
+    state: State<'_, AppState>,
+    transaction_id: String,
+) -> Result<Vec<TransactionSplit>, AppError> {
+    debug!("Received get_transaction_splits_command: {}", transaction_id);
+    let db = state.db().await?;
+    list_splits(db.connection(), &transaction_id)
+}
+
+/// Comando para repartir una transacción entre varias categorías. Sustituye el reparto
+/// anterior; con una lista vacía se quita. Las partes deben sumar exactamente el
+/// importe de la transacción. Los informes por categoría y los presupuestos usan las
+/// partes en lugar de la categoría de la transacción. Se puede deshacer.
+#[tauri::command]
+pub async fn set_transaction_splits_command(
+    state: State<'_, AppState>,
+    app: AppHandle,
+    transaction_id: String,
+    splits: Vec<SplitInput>,
+) -> Result<Vec<TransactionSplit>, AppError> {
+    debug!("Received set_transaction_splits_command: {} ({} parts)", transaction_id, splits.len());
+    let db = state.db().await?;
+    let transaction = db
+        .get_transaction(&transaction_id)?
+        .filter(|t| t.deleted_at.is_none())
+        .ok_or_else(|| AppError::NotFound(format!("Transacción con ID {} no encontrada.", transaction_id)))?;
+    let splits = resolve_splits(db.connection(), &transaction, splits)?;
+    let before = list_splits(db.connection(), &transaction_id)?;
+
+    let tx = db.connection().unchecked_transaction().map_err(db_error)?;
+    replace_splits(&tx, &transaction_id, &splits)?;
+    tx.commit().map_err(db_error)?;
+
+    let changes = vec![Change::Splits { transaction, before, after: splits.clone() }];
+    audit::record_changes(db.connection(), "set_transaction_splits_command", &changes);
+    events::emit_transaction_changes(&app, &changes);
+    state.history.lock().unwrap().record(HistoryEntry::new("Repartir transacción", changes));
+    budgets::check_budget_alerts(&db, &app);
+    info!("Transaction {} split into {} parts.", transaction_id, splits.len());
+    Ok(splits)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn transaction(id: &str, amount: &str) -> Transaction {
+        serde_json::from_value(json!({
+            "id": id,
+            "type": "Gasto",
+            "amount": amount,
+            "description": "Supermercado",
+            "store_name": "Tienda",
+            "timestamp": 1_705_320_000,
+            "category": "Comida",
+            "transaction_date": "2024-01-15",
+            "created_at": 1_705_320_000,
+            "updated_at": 1_705_320_000,
+        }))
+        .unwrap()
+    }
+
+    fn part(amount: &str) -> SplitInput {
+        SplitInput { amount: amount.parse().unwrap(), category: None, subcategory: None, notes: None }
+    }
+
+    #[test]
+    fn resolve_splits_requires_the_exact_amount() {
+        let db = SqliteStorage::open_in_memory().unwrap();
+        let t = transaction("t1", "42");
+
+        let short = resolve_splits(db.connection(), &t, vec![part("30"), part("11.99")]);
+        assert!(matches!(short, Err(AppError::Validation { .. })));
+        let single = resolve_splits(db.connection(), &t, vec![part("42")]);
+        assert!(matches!(single, Err(AppError::Validation { .. })));
+
+        let splits = resolve_splits(db.connection(), &t, vec![part("30"), part("12")]).unwrap();
+        assert_eq!(splits.len(), 2);
+        assert!(resolve_splits(db.connection(), &t, Vec::new()).unwrap().is_empty());
+    }
+
+    #[test]
+    fn expand_replaces_a_split_transaction_by_its_parts() {
+        let db = SqliteStorage::open_in_memory().unwrap();
+        let t = transaction("t1", "42");
+        db.insert_transaction(&t).unwrap();
+        db.insert_transaction(&transaction("t2", "5")).unwrap();
+        let splits = resolve_splits(db.connection(), &t, vec![part("30"), part("12")]).unwrap();
+        replace_splits(db.connection(), "t1", &splits).unwrap();
+
+        let expanded = expand(db.connection(), db.list_transactions().unwrap()).unwrap();
+        let amounts: Vec<(String, Decimal)> = expanded.iter().map(|t| (t.id.clone(), t.amount)).collect();
+        assert_eq!(amounts.len(), 3);
+        assert!(amounts.contains(&("t1".to_string(), "30".parse().unwrap())));
+        assert!(amounts.contains(&("t1".to_string(), "12".parse().unwrap())));
+        assert!(expanded.iter().filter(|t| t.id == "t1").all(|t| t.category.is_none()));
+        assert_eq!(list_splits(db.connection(), "t1").unwrap().len(), 2);
+    }
+}
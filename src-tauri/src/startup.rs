@@ -0,0 +1,310 @@
+// src-tauri/src/startup.rs
+
+use serde::Serialize;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::OnceLock;
+use std::time::Instant;
+use tauri::{AppHandle, Manager, State};
+use log::{debug, error, info, warn};
+
+use crate::aggregates;
+use crate::attachments;
+use crate::error::AppError;
+use crate::events;
+use crate::shutdown;
+use crate::storage::{self, SqliteStorage, StorageGuard, TransactionRepository};
+use crate::workspaces;
+use crate::{AppState, Transaction};
+
+// Al arrancar solo se abre y verifica la base de datos, para que la ventana aparezca
+// cuanto antes. El resto (copia `.bak`, limpieza de adjuntos, importación del antiguo
+// transactions.json y precarga de los datos) se hace en segundo plano y, al terminar,
+// se emite `data://ready`. La carga no mantiene la base de datos bloqueada: cada paso
+// la bloquea por separado, y la precarga lo hace por bloques, así que los comandos de
+// la interfaz se atienden entre medias.
+
+/// Transacciones que lee la precarga cada vez que bloquea la base de datos.
+const PRELOAD_PAGE_SIZE: usize = 2_000;
+
+/// Momento en que arrancó el proceso, para medir el tiempo hasta `data://ready`.
+static LAUNCHED_AT: OnceLock<Instant> = OnceLock::new();
+static READY: AtomicBool = AtomicBool::new(false);
+/// Milisegundos desde el arranque hasta que se abrió la base de datos y hasta que
+/// terminó la carga en segundo plano (0 mientras no se sabe).
+static OPENED_MS: AtomicU64 = AtomicU64::new(0);
+static READY_MS: AtomicU64 = AtomicU64::new(0);
+
+/// Carga de `data://ready`.
+#[derive(Debug, Clone, Serialize)]
+pub struct DataReady {
+    pub transaction_count: usize,
+    pub elapsed_ms: u64,
+}
+
+/// Estado del arranque, para la interfaz que se carga antes de `data://ready` y para
+/// el diagnóstico.
+#[derive(Debug, Clone, Serialize)]
+pub struct StartupStatus {
+    /// `true` cuando terminó la carga en segundo plano.
+    pub ready: bool,
+    pub locked: bool,
+    pub workspace_id: String,
+    /// Transacciones activas; `None` mientras los datos están bloqueados o la carga en
+    /// segundo plano está usando la base de datos.
+    pub transaction_count: Option<usize>,
+    /// Milisegundos desde el arranque hasta que se abrió la base de datos.
+    pub opened_ms: Option<u64>,
+    /// Milisegundos desde el arranque hasta `data://ready`.
+    pub ready_ms: Option<u64>,
+    /// La sesión anterior terminó sin el cierre ordenado (ver `shutdown`): conviene
+    /// verificar los datos.
+    pub unclean_shutdown: bool,
+}
+
+fn elapsed_ms() -> u64 {
+    LAUNCHED_AT.get().map(|t| t.elapsed().as_millis() as u64).unwrap_or_default()
+}
+
+fn recorded(value: &AtomicU64) -> Option<u64> {
+    Some(value.load(Ordering::Relaxed)).filter(|ms| *ms > 0)
+}
+
+/// Se llama al principio de `main`.
+pub fn mark_launch() {
+    LAUNCHED_AT.get_or_init(Instant::now);
+}
+
+/// Se llama cuando la base de datos ya está abierta, antes de crear la ventana.
+pub fn mark_opened() {
+    let ms = elapsed_ms();
+    OPENED_MS.store(ms.max(1), Ordering::Relaxed);
+    info!("Database opened {} ms after launch.", ms);
+}
+
+/// `true` cuando terminó la carga en segundo plano y se emitió `data://ready`.
+pub fn is_ready() -> bool {
+    READY.load(Ordering::SeqCst)
+}
+
+pub fn ready_ms() -> Option<u64> {
+    recorded(&READY_MS)
+}
+
+/// Bloquea la base de datos para un paso de la carga, siempre que siga abierto el
+/// espacio de trabajo `workspace_id`: entre un paso y otro se ha podido cambiar.
+async fn db_of<'a>(state: &'a AppState, workspace_id: &str) -> Result<StorageGuard<'a>, AppError> {
+    let db = state.db().await?;
+    if state.workspace.lock().unwrap().id != workspace_id {
+        return Err(AppError::Conflict("Se cambió de espacio de trabajo durante la carga inicial.".to_string()));
+    }
+    Ok(db)
+}
+
+/// Actualiza la copia `.bak`. La base de datos solo se bloquea para tomar el origen
+/// de la copia; la copia en sí se escribe en otro hilo.
+async fn refresh_backup(state: &AppState, workspace_id: &str) -> Result<(), AppError> {
+    let source = db_of(state, workspace_id).await?.backup_source()?;
+    let backup_path = storage::get_backup_path();
+    match tokio::task::spawn_blocking(move || storage::write_backup_from(source, &backup_path)).await {
+        Ok(Ok(())) => {}
+        Ok(Err(e)) => warn!("No se pudo actualizar la copia de seguridad: {}", e),
+        Err(e) => warn!("Backup task failed: {}", e),
+    }
+    Ok(())
+}
+
+/// Importa el antiguo transactions.json. Leerlo y convertirlo es lo que más tarda,
+/// así que se hace sin tener la base de datos bloqueada.
+async fn import_legacy(state: &AppState, workspace_id: &str) -> Result<(), AppError> {
+    let Some(path) = crate::legacy_import_pending(&db_of(state, workspace_id).await?)? else {
+        return Ok(());
+    };
+    let transactions = crate::load_transactions_from_file(&path).await?;
+    crate::import_legacy_transactions(&db_of(state, workspace_id).await?, &path, &transactions)
+}
+
+/// Lo que lee la precarga cada vez que bloquea la base de datos: las siguientes
+/// `PRELOAD_PAGE_SIZE` transacciones activas con `sequence` mayor que `after`.
+fn preload_page(db: &SqliteStorage, after: u64) -> Result<Vec<Transaction>, AppError> {
+    db.transactions_after(after, PRELOAD_PAGE_SIZE)
+}
+
+/// Lee las transacciones activas por bloques de `PRELOAD_PAGE_SIZE`, soltando la base
+/// de datos entre uno y otro, y deja calculados los totales de `aggregates` para el
+/// panel y los informes. Devuelve cuántas filas leyó.
+async fn preload(state: &AppState, workspace_id: &str) -> Result<usize, AppError> {
+    let mut builder = aggregates::AggregatesBuilder::start();
+    let (mut after, mut rows) = (0, 0);
+    loop {
+        let page = preload_page(&db_of(state, workspace_id).await?, after)?;
+        let Some(last) = page.last() else { break };
+        after = last.sequence;
+        rows += page.len();
+        builder.add(&page);
+        if page.len() < PRELOAD_PAGE_SIZE {
+            break;
+        }
+        tokio::task::yield_now().await;
+    }
+    if !builder.finish() {
+        debug!("Data changed during preload; aggregates will be rebuilt on demand.");
+    }
+    Ok(rows)
+}
+
+/// Tareas que no hacen falta para mostrar la ventana. Cada paso bloquea la base de
+/// datos solo lo imprescindible, para que los primeros comandos de la interfaz no
+/// esperen a toda la carga.
+async fn load_deferred(state: &AppState, workspace_id: &str) -> Result<usize, AppError> {
+    refresh_backup(state, workspace_id).await?;
+    if let Err(e) = attachments::cleanup_orphan_attachments(&db_of(state, workspace_id).await?) {
+        warn!("No se pudieron limpiar los adjuntos huérfanos: {}", e);
+    }
+    // El antiguo transactions.json pertenece al espacio de trabajo principal.
+    if workspace_id == workspaces::DEFAULT_WORKSPACE_ID {
+        if let Err(e) = import_legacy(state, workspace_id).await {
+            error!("Error al importar transacciones heredadas: {}. Se conserva el archivo original.", e);
+        }
+        crate::insert_sample_transaction(&db_of(state, workspace_id).await?)?;
+    }
+    // La importación y la transacción de ejemplo no pasan por `events`.
+    aggregates::invalidate();
+    let rows = preload(state, workspace_id).await?;
+    debug!("Preloaded {} transaction rows.", rows);
+    db_of(state, workspace_id).await?.count_transactions()
+}
+
+/// Lanza la carga en segundo plano. Se llama desde `setup`.
+pub fn spawn_deferred_load(app: AppHandle) {
+    tauri::async_runtime::spawn(async move {
+        let state = app.state::<AppState>();
+        let transaction_count = if state.is_locked() {
+            0
+        } else {
+            let workspace_id = state.workspace.lock().unwrap().id.clone();
+            load_deferred(&state, &workspace_id).await.unwrap_or_else(|e| {
+                error!("Deferred startup load failed: {}", e);
+                0
+            })
+        };
+        let ms = elapsed_ms();
+        READY_MS.store(ms.max(1), Ordering::Relaxed);
+        READY.store(true, Ordering::SeqCst);
+        info!("Data ready {} ms after launch ({} transactions).", ms, transaction_count);
+        events::emit(&app, events::DATA_READY_EVENT, DataReady { transaction_count, elapsed_ms: ms });
+    });
+}
+
+// --- Comandos Tauri ---
+
+/// Comando para saber si terminó la carga inicial, por si la interfaz se montó
+/// después de `data://ready`. Solo hace consultas de metadatos.
+#[tauri::command]
+pub async fn get_startup_status_command(state: State<'_, AppState>) -> Result<StartupStatus, AppError> {
+    debug!("Received get_startup_status_command.");
+    let workspace_id = state.workspace.lock().unwrap().id.clone();
+    // Sin esperar si un comando o un paso de la carga tiene la base de datos bloqueada.
+    let transaction_count = match state.db.try_lock() {
+        Ok(db) if !state.is_locked() => Some(db.count_transactions()?),
+        _ => None,
+    };
+    Ok(StartupStatus {
+        ready: is_ready(),
+        locked: state.is_locked(),
+        workspace_id,
+        transaction_count,
+        opened_ms: recorded(&OPENED_MS),
+        ready_ms: ready_ms(),
+        unclean_shutdown: shutdown::previous_shutdown_unclean(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rust_decimal::Decimal;
+    use std::collections::HashMap;
+
+    use crate::payments::PaymentMethod;
+    use crate::{merge, periods, TransactionType};
+
+    /// Algo más de dos bloques de la precarga.
+    const TEST_TRANSACTIONS: usize = 2 * PRELOAD_PAGE_SIZE + 1;
+
+    fn transaction(i: usize) -> Transaction {
+        let timestamp = periods::now_timestamp() - (i as u64 % 1_000) * 86_400;
+        Transaction {
+            id: format!("preload-{}", i),
+            transaction_type: if i % 3 == 0 { TransactionType::Ingreso } else { TransactionType::Gasto },
+            amount: Decimal::new((i % 10_000) as i64 + 1, 2),
+            description: format!("Compra de prueba número {}", i),
+            store_name: format!("Tienda {}", i % 5),
+            timestamp,
+            category: Some(format!("Categoría {}", i % 12)),
+            subcategory: None,
+            currency: "EUR".to_string(),
+            receipt_paths: Vec::new(),
+            deleted_at: None,
+            tax_rate: None,
+            tax_amount: None,
+            tags: Vec::new(),
+            external_id: None,
+            transaction_date: periods::local_date(timestamp),
+            created_at: timestamp,
+            updated_at: timestamp,
+            notes: String::new(),
+            custom_fields: HashMap::new(),
+            payment_method: PaymentMethod::Otro,
+            contact_id: None,
+            transfer_store: None,
+            account_id: None,
+            transfer_account_id: None,
+            user_id: None,
+            revision: merge::initial_revision(),
+            device_id: String::new(),
+            sequence: 0,
+        }
+    }
+
+    /// Cada bloqueo de la precarga lee como mucho un bloque, y los bloques cubren
+    /// todas las transacciones activas una sola vez.
+    #[test]
+    fn preload_reads_at_most_one_page_per_lock() {
+        let db = SqliteStorage::open_in_memory().unwrap();
+        let transactions: Vec<_> = (0..TEST_TRANSACTIONS).map(transaction).collect();
+        db.insert_transactions(&transactions).unwrap();
+
+        let (mut after, mut pages, mut rows, mut ids) = (0, 0, 0, std::collections::HashSet::new());
+        loop {
+            let page = preload_page(&db, after).unwrap();
+            assert!(page.len() <= PRELOAD_PAGE_SIZE);
+            let Some(last) = page.last() else { break };
+            assert!(last.sequence > after);
+            after = last.sequence;
+            pages += 1;
+            rows += page.len();
+            ids.extend(page.into_iter().map(|t| t.id));
+        }
+        assert_eq!(pages, 3);
+        assert_eq!(rows, TEST_TRANSACTIONS);
+        assert_eq!(ids.len(), TEST_TRANSACTIONS);
+    }
+
+    /// Con la base de datos sin cifrar, `refresh_backup` solo toma la ruta del archivo
+    /// mientras la tiene bloqueada: la copia se escribe después, desde otra conexión.
+    #[test]
+    fn backup_source_of_a_plain_database_is_only_its_path() {
+        let dir = std::env::temp_dir().join(format!("contabilidad-test-{}", uuid::Uuid::new_v4()));
+        let path = dir.join("contabilidad.db");
+        let db = SqliteStorage::open(&path).unwrap();
+        db.insert_transaction(&transaction(0)).unwrap();
+
+        let source = db.backup_source().unwrap();
+        assert!(matches!(&source, storage::BackupSource::File(p) if *p == path));
+        let backup_path = dir.join("contabilidad.db.bak");
+        storage::write_backup_from(source, &backup_path).unwrap();
+        drop(db);
+        assert!(storage::backup_is_healthy(&backup_path, None).unwrap());
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,1400 @@
+// src-tauri/src/storage.rs
+
+use rusqlite::serialize::OwnedData;
+use rusqlite::types::{Type, Value};
+use rusqlite::{ffi, params, params_from_iter, Connection, DatabaseName, OpenFlags, OptionalExtension, Row};
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::ffi::OsString;
+use std::io::Write;
+use std::ops::{Deref, DerefMut};
+use std::path::{Path, PathBuf};
+use std::ptr::NonNull;
+use std::str::FromStr;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, RwLock};
+use std::time::Duration;
+use chrono::{NaiveDate, Utc};
+use log::{info, debug, error, warn};
+
+use crate::encryption::{self, DataKey};
+use crate::error::AppError;
+use crate::migrations;
+use crate::payments::PaymentMethod;
+use crate::tags;
+use crate::users;
+use crate::{Transaction, TransactionType};
+
+// --- Ubicación de la Base de Datos ---
+
+const DATABASE_FILE_NAME: &str = "contabilidad.db";
+
+/// Directorio de datos de la aplicación dentro del directorio local del usuario.
+pub fn get_app_data_dir() -> PathBuf {
+    let mut path = dirs::data_local_dir()
+        .expect("No se pudo obtener el directorio de datos local.");
+    path.push("com.tuempresa.contabilidad"); // Subdirectorio específico para tu app
+    path
+}
+
+/// Directorio de datos del espacio de trabajo activo. Mientras no se fije otro
+/// con `set_data_dir`, es el directorio de datos de la aplicación.
+static DATA_DIR: RwLock<Option<PathBuf>> = RwLock::new(None);
+
+/// Directorio con la base de datos, copias y justificantes del espacio de trabajo activo.
+pub fn get_data_dir() -> PathBuf {
+    DATA_DIR.read().unwrap().clone().unwrap_or_else(get_app_data_dir)
+}
+
+/// Cambia el directorio de datos activo. Solo lo llama `workspaces` al cambiar de espacio.
+pub fn set_data_dir(path: PathBuf) {
+    *DATA_DIR.write().unwrap() = Some(path);
+}
+
+/// Ruta del archivo SQLite de un directorio de datos.
+pub fn database_path_in(dir: &Path) -> PathBuf {
+    dir.join(DATABASE_FILE_NAME)
+}
+
+/// Ruta del archivo SQLite donde se guardan las transacciones.
+pub fn get_database_path() -> PathBuf {
+    let path = database_path_in(&get_data_dir());
+    debug!("Ruta de la base de datos: {}", path.display());
+    path
+}
+
+/// Ruta de la copia de seguridad de la última base de datos verificada.
+pub fn get_backup_path() -> PathBuf {
+    sibling_path(&get_database_path(), ".bak")
+}
+
+/// Devuelve `path` con `suffix` añadido al nombre del archivo (p. ej. `datos.db` -> `datos.db.bak`).
+pub fn sibling_path(path: &Path, suffix: &str) -> PathBuf {
+    let mut name: OsString = path.as_os_str().to_owned();
+    name.push(suffix);
+    PathBuf::from(name)
+}
+
+/// Milisegundos Unix actuales.
+fn now_millis() -> u64 {
+    Utc::now().timestamp_millis().max(0) as u64
+}
+
+// --- Escritura Atómica ---
+
+/// Escribe `contents` en un archivo temporal junto a `path`, lo sincroniza con el
+/// disco y lo renombra sobre `path`. Un fallo a mitad de escritura nunca deja el
+/// archivo de destino truncado: o queda la versión anterior o la nueva completa.
+pub fn write_atomic(path: &Path, contents: &[u8]) -> Result<(), AppError> {
+    let tmp_path = sibling_path(path, ".tmp");
+    let result = (|| -> std::io::Result<()> {
+        let mut file = std::fs::File::create(&tmp_path)?;
+        file.write_all(contents)?;
+        file.sync_all()?;
+        std::fs::rename(&tmp_path, path)
+    })();
+    result.map_err(|e| {
+        error!("Atomic write to {} failed: {}", path.display(), e);
+        let _ = std::fs::remove_file(&tmp_path);
+        AppError::Io(format!("Error al guardar {}: {}", path.display(), e))
+    })
+}
+
+/// Convierte un error de SQLite en el error que reciben los comandos.
+pub fn db_error(e: rusqlite::Error) -> AppError {
+    AppError::from(e)
+}
+
+// --- Consultas Paginadas ---
+
+/// Campo por el que se ordena el resultado de `query_transactions`.
+#[derive(Debug, Clone, Copy, Default, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SortField {
+    #[default]
+    Timestamp,
+    /// Momento en que se registró, no la fecha de la transacción.
+    CreatedAt,
+    Amount,
+    Description,
+    StoreName,
+    Type,
+    /// Orden de registro en esta base de datos (ver `Transaction::sequence`).
+    Sequence,
+}
+
+impl SortField {
+    fn column(self) -> &'static str {
+        match self {
+            SortField::Timestamp => "timestamp",
+            SortField::CreatedAt => "created_at",
+            SortField::Amount => "CAST(amount AS REAL)",
+            SortField::Description => "description",
+            SortField::StoreName => "store_name",
+            SortField::Type => "transaction_type",
+            SortField::Sequence => "sequence",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SortDirection {
+    Asc,
+    #[default]
+    Desc,
+}
+
+pub const DEFAULT_PAGE_SIZE: usize = 50;
+pub const MAX_PAGE_SIZE: usize = 500;
+
+/// Filtros, orden y paginación para `query_transactions`. Todos los campos son opcionales.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct TransactionQuery {
+    /// Página a devolver, empezando en 1.
+    pub page: Option<usize>,
+    pub page_size: Option<usize>,
+    pub sort_by: SortField,
+    pub sort_direction: SortDirection,
+    /// Límite inferior inclusivo (segundos Unix).
+    pub from: Option<u64>,
+    /// Límite superior inclusivo (segundos Unix).
+    pub to: Option<u64>,
+    pub transaction_type: Option<TransactionType>,
+    pub payment_method: Option<PaymentMethod>,
+    pub store_name: Option<String>,
+    /// Texto libre buscado en descripción, tienda, categoría, etiquetas, notas y campos personalizados.
+    pub search: Option<String>,
+    /// Etiquetas que deben tener todas las transacciones devueltas.
+    pub tags_all: Vec<String>,
+    /// Etiquetas de las que deben tener al menos una.
+    pub tags_any: Vec<String>,
+    /// Etiquetas que no deben tener.
+    pub tags_exclude: Vec<String>,
+    /// Solo las registradas por este usuario (`users::User`).
+    pub user_id: Option<String>,
+}
+
+/// Una página de resultados junto con el total de transacciones que cumplen los filtros.
+#[derive(Debug, Serialize)]
+pub struct TransactionPage {
+    pub items: Vec<Transaction>,
+    pub total_count: usize,
+    pub page: usize,
+    pub page_size: usize,
+}
+
+/// Escapa los comodines de LIKE para buscar el texto literalmente.
+fn like_pattern(text: &str) -> String {
+    let escaped = text
+        .replace('\\', "\\\\")
+        .replace('%', "\\%")
+        .replace('_', "\\_");
+    format!("%{}%", escaped)
+}
+
+/// Etiquetas de un filtro con el mismo formato con el que se guardan; las vacías se ignoran.
+fn normalized_tags(tags: &[String]) -> Vec<String> {
+    tags.iter().map(|t| tags::normalize_tag(t)).filter(|t| !t.is_empty()).collect()
+}
+
+/// Construye la cláusula WHERE y sus parámetros a partir de los filtros de la consulta.
+fn build_filter(query: &TransactionQuery) -> (String, Vec<Value>) {
+    let mut clauses: Vec<String> = vec![ACTIVE.to_owned()];
+    let mut values: Vec<Value> = Vec::new();
+
+    if let Some(from) = query.from {
+        clauses.push("timestamp >= ?".to_owned());
+        values.push(Value::Integer(from as i64));
+    }
+    if let Some(to) = query.to {
+        clauses.push("timestamp <= ?".to_owned());
+        values.push(Value::Integer(to as i64));
+    }
+    if let Some(transaction_type) = &query.transaction_type {
+        clauses.push("transaction_type = ?".to_owned());
+        values.push(Value::Text(transaction_type.to_string()));
+    }
+    if let Some(payment_method) = &query.payment_method {
+        clauses.push("payment_method = ?".to_owned());
+        values.push(Value::Text(payment_method.to_string()));
+    }
+    if let Some(user_id) = query.user_id.as_deref().map(str::trim).filter(|u| !u.is_empty()) {
+        clauses.push("user_id = ?".to_owned());
+        values.push(Value::Text(user_id.to_owned()));
+    }
+    if let Some(store_name) = query.store_name.as_deref().map(str::trim) {
+        if !store_name.is_empty() && store_name != "Todas las Tiendas" {
+            clauses.push("store_name = ?".to_owned());
+            values.push(Value::Text(store_name.to_owned()));
+        }
+    }
+    if let Some(search) = query.search.as_deref().map(str::trim) {
+        if !search.is_empty() {
+            clauses.push(
+                "(description LIKE ? ESCAPE '\\' OR store_name LIKE ? ESCAPE '\\' \
+                 OR IFNULL(category, '') LIKE ? ESCAPE '\\' OR IFNULL(subcategory, '') LIKE ? ESCAPE '\\' \
+                 OR tags LIKE ? ESCAPE '\\' OR notes LIKE ? ESCAPE '\\' OR custom_fields LIKE ? ESCAPE '\\')"
+                    .to_owned(),
+            );
+            let pattern = like_pattern(search);
+            for _ in 0..7 {
+                values.push(Value::Text(pattern.clone()));
+            }
+        }
+    }
+
+    for tag in normalized_tags(&query.tags_all) {
+        clauses.push("EXISTS (SELECT 1 FROM json_each(tags) WHERE value = ?)".to_owned());
+        values.push(Value::Text(tag));
+    }
+    for (tags, negate) in [(&query.tags_any, ""), (&query.tags_exclude, "NOT ")] {
+        let tags = normalized_tags(tags);
+        if tags.is_empty() {
+            continue;
+        }
+        let placeholders = vec!["?"; tags.len()].join(", ");
+        clauses.push(format!("{}EXISTS (SELECT 1 FROM json_each(tags) WHERE value IN ({}))", negate, placeholders));
+        values.extend(tags.into_iter().map(Value::Text));
+    }
+
+    (format!("WHERE {}", clauses.join(" AND ")), values)
+}
+
+// --- Repositorio de Transacciones ---
+
+/// Operaciones de persistencia sobre las transacciones.
+/// Los comandos Tauri solo validan la entrada y delegan en este trait.
+pub trait TransactionRepository {
+    /// Transacciones activas (fuera de la papelera). Lo mismo aplica al resto de
+    /// consultas salvo que se indique lo contrario.
+    fn list_transactions(&self) -> Result<Vec<Transaction>, AppError>;
+    fn get_transaction(&self, id: &str) -> Result<Option<Transaction>, AppError>;
+    fn list_transactions_by_store(&self, store_name: &str) -> Result<Vec<Transaction>, AppError>;
+    fn list_transactions_with_tag(&self, tag: &str) -> Result<Vec<Transaction>, AppError>;
+    /// Transacciones activas que cumplen la expresión FTS5 `match_expr`, de más a menos relevante.
+    fn search_transactions(&self, match_expr: &str, limit: usize) -> Result<Vec<Transaction>, AppError>;
+    fn insert_transaction(&self, transaction: &Transaction) -> Result<(), AppError>;
+    fn insert_transactions(&self, transactions: &[Transaction]) -> Result<(), AppError>;
+    /// Devuelve `false` si no existe ninguna transacción con ese ID.
+    fn update_transaction(&self, transaction: &Transaction) -> Result<bool, AppError>;
+    /// Devuelve `false` si no existe ninguna transacción con ese ID.
+    fn delete_transaction(&self, id: &str) -> Result<bool, AppError>;
+    fn count_transactions(&self) -> Result<usize, AppError>;
+    /// Filtra, ordena y pagina las transacciones directamente en SQLite.
+    fn query_transactions(&self, query: &TransactionQuery) -> Result<TransactionPage, AppError>;
+    fn store_transaction_counts(&self) -> Result<HashMap<String, usize>, AppError>;
+    /// Devuelve el número de transacciones reasignadas a la nueva tienda.
+    fn rename_store(&self, old_name: &str, new_name: &str) -> Result<usize, AppError>;
+    /// Elimina definitivamente todas las transacciones de la tienda, incluidas las
+    /// de la papelera y las transferencias hacia ella. Devuelve el número de filas borradas.
+    fn delete_store(&self, store_name: &str) -> Result<usize, AppError>;
+    /// Mueve la transacción a la papelera. Devuelve `false` si no existe o ya estaba en ella.
+    fn soft_delete_transaction(&self, id: &str, deleted_at: u64) -> Result<bool, AppError>;
+    fn list_deleted_transactions(&self) -> Result<Vec<Transaction>, AppError>;
+    /// Saca la transacción de la papelera. Devuelve `false` si no estaba en ella.
+    fn restore_transaction(&self, id: &str) -> Result<bool, AppError>;
+    /// Borra definitivamente las transacciones eliminadas antes de `cutoff` y las devuelve.
+    fn purge_deleted_before(&self, cutoff: u64) -> Result<Vec<Transaction>, AppError>;
+    /// IDs de todas las transacciones, incluidas las de la papelera.
+    fn all_transaction_ids(&self) -> Result<HashSet<String>, AppError>;
+    /// Identificadores externos (de extractos importados) de todas las transacciones,
+    /// incluidas las de la papelera.
+    fn all_external_ids(&self) -> Result<HashSet<String>, AppError>;
+}
+
+/// Clave y estado de una base de datos cifrada. Mientras está desbloqueada, la base
+/// de datos vive en memoria y su imagen cifrada se escribe en disco con `flush`.
+struct Vault {
+    key: DataKey,
+    /// Se activa en cada commit desde el `commit_hook` de SQLite.
+    dirty: Arc<AtomicBool>,
+    /// Momentos (ms Unix) del primer commit sin guardar y del último, para agrupar
+    /// las escrituras en `autosave`.
+    dirty_since_ms: Arc<AtomicU64>,
+    last_commit_ms: Arc<AtomicU64>,
+}
+
+/// Implementación de `TransactionRepository` sobre una base de datos SQLite.
+pub struct SqliteStorage {
+    conn: Connection,
+    /// Archivo de la base de datos; `None` para las bases de datos en memoria.
+    path: Option<PathBuf>,
+    vault: Option<Vault>,
+}
+
+/// Copia en memoria gestionada por SQLite con el contenido de `bytes`, para `deserialize`.
+fn owned_data(bytes: &[u8]) -> Result<OwnedData, AppError> {
+    // SAFETY: el búfer se reserva con el asignador de SQLite, que es quien lo libera
+    // al cerrar la conexión, y se rellena por completo antes de entregarlo.
+    unsafe {
+        let ptr = NonNull::new(ffi::sqlite3_malloc64(bytes.len() as u64) as *mut u8)
+            .ok_or_else(|| AppError::Internal("Memoria insuficiente para abrir la base de datos.".to_string()))?;
+        std::ptr::copy_nonoverlapping(bytes.as_ptr(), ptr.as_ptr(), bytes.len());
+        Ok(OwnedData::from_raw_nonnull(ptr, bytes.len()))
+    }
+}
+
+/// Bytes del archivo de la base de datos que se leen proyectados en memoria.
+const MMAP_SIZE: i64 = 256 * 1024 * 1024;
+
+impl SqliteStorage {
+    /// Abre (o crea) la base de datos en `path` y aplica las migraciones pendientes.
+    pub fn open(path: &Path) -> Result<Self, AppError> {
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent).map_err(|e| {
+                AppError::Io(format!("Falló la creación del directorio padre ({}): {}", parent.display(), e))
+            })?;
+        }
+        let conn = Connection::open(path).map_err(|e| {
+            error!("Could not open database {}: {}", path.display(), e);
+            AppError::Database(format!("No se pudo abrir la base de datos {}: {}", path.display(), e))
+        })?;
+        conn.pragma_update(None, "foreign_keys", "ON").map_err(db_error)?;
+        conn.pragma_update(None, "synchronous", "FULL").map_err(db_error)?;
+        // Las lecturas se hacen sobre el archivo proyectado en memoria, sin copiar
+        // cada página al caché de SQLite.
+        conn.pragma_update(None, "mmap_size", MMAP_SIZE).map_err(db_error)?;
+        migrations::run_migrations(&conn, Some(path))?;
+        info!("Base de datos abierta en: {}", path.display());
+        Ok(SqliteStorage { conn, path: Some(path.to_owned()), vault: None })
+    }
+
+    /// Base de datos vacía en memoria. Se usa como sustituto temporal mientras
+    /// se reemplaza el archivo de la base de datos real.
+    pub fn open_in_memory() -> Result<Self, AppError> {
+        let conn = Connection::open_in_memory().map_err(db_error)?;
+        migrations::run_migrations(&conn, None)?;
+        Ok(SqliteStorage { conn, path: None, vault: None })
+    }
+
+    /// Abre la base de datos cifrada de `path` con `key`. El contenido se descifra en
+    /// memoria; si el archivo no existe se crea una base de datos nueva.
+    pub fn open_encrypted(path: &Path, key: DataKey) -> Result<Self, AppError> {
+        let image = if path.exists() {
+            let data = std::fs::read(path)
+                .map_err(|e| AppError::Io(format!("No se pudo leer la base de datos {}: {}", path.display(), e)))?;
+            Some(key.decrypt(&data)?)
+        } else {
+            None
+        };
+        let storage = Self::from_image(path, image.as_deref(), key)?;
+        storage.flush()?;
+        info!("Base de datos cifrada abierta en: {}", path.display());
+        Ok(storage)
+    }
+
+    fn from_image(path: &Path, image: Option<&[u8]>, key: DataKey) -> Result<Self, AppError> {
+        let mut conn = Connection::open_in_memory().map_err(db_error)?;
+        if let Some(image) = image {
+            conn.deserialize(DatabaseName::Main, owned_data(image)?, false).map_err(db_error)?;
+        }
+        conn.pragma_update(None, "foreign_keys", "ON").map_err(db_error)?;
+
+        let dirty = Arc::new(AtomicBool::new(image.is_none()));
+        let dirty_since_ms = Arc::new(AtomicU64::new(now_millis()));
+        let last_commit_ms = Arc::new(AtomicU64::new(now_millis()));
+        let (hook_flag, hook_since, hook_last) =
+            (Arc::clone(&dirty), Arc::clone(&dirty_since_ms), Arc::clone(&last_commit_ms));
+        conn.commit_hook(Some(move || {
+            let now = now_millis();
+            if !hook_flag.swap(true, Ordering::SeqCst) {
+                hook_since.store(now, Ordering::SeqCst);
+            }
+            hook_last.store(now, Ordering::SeqCst);
+            false
+        }));
+        migrations::run_migrations(&conn, Some(path))?;
+        Ok(SqliteStorage {
+            conn,
+            path: Some(path.to_owned()),
+            vault: Some(Vault { key, dirty, dirty_since_ms, last_commit_ms }),
+        })
+    }
+
+    /// Abre la base de datos de `path`, cifrada si se indica `key`.
+    pub fn open_path(path: &Path, key: Option<DataKey>) -> Result<Self, AppError> {
+        match key {
+            Some(key) => Self::open_encrypted(path, key),
+            None => Self::open(path),
+        }
+    }
+
+    /// Clave de cifrado, si la base de datos está cifrada.
+    pub fn encryption_key(&self) -> Option<&DataKey> {
+        self.vault.as_ref().map(|v| &v.key)
+    }
+
+    /// Imagen completa de la base de datos en el formato de archivo de SQLite.
+    fn export_image(&self) -> Result<Vec<u8>, AppError> {
+        let data = self.conn.serialize(DatabaseName::Main).map_err(db_error)?;
+        Ok(data.to_vec())
+    }
+
+    /// Si hay cambios cifrados sin escribir, cuánto hace del primero y del último commit.
+    pub fn pending_changes_age(&self) -> Option<(Duration, Duration)> {
+        let vault = self.vault.as_ref().filter(|v| v.dirty.load(Ordering::SeqCst))?;
+        let now = now_millis();
+        let age = |ms: &AtomicU64| Duration::from_millis(now.saturating_sub(ms.load(Ordering::SeqCst)));
+        Some((age(&vault.dirty_since_ms), age(&vault.last_commit_ms)))
+    }
+
+    /// Escribe en disco la imagen cifrada si hubo cambios desde la última escritura.
+    /// No hace nada en las bases de datos sin cifrar, que SQLite ya guarda en cada commit.
+    pub fn flush(&self) -> Result<(), AppError> {
+        let (Some(vault), Some(path)) = (&self.vault, &self.path) else {
+            return Ok(());
+        };
+        if !vault.dirty.swap(false, Ordering::SeqCst) {
+            return Ok(());
+        }
+        let result = self.export_image()
+            .and_then(|image| vault.key.encrypt(&image))
+            .and_then(|contents| write_atomic(path, &contents));
+        if result.is_err() {
+            vault.dirty.store(true, Ordering::SeqCst);
+        }
+        result
+    }
+
+    /// Cifra la base de datos con `key`, cambia su clave o, con `None`, la descifra.
+    /// El archivo se reescribe de forma atómica.
+    pub fn set_encryption_key(&mut self, key: Option<DataKey>) -> Result<(), AppError> {
+        let path = self.path.clone()
+            .ok_or_else(|| AppError::Internal("La base de datos en memoria no se puede cifrar.".to_string()))?;
+
+        if let (Some(vault), Some(new_key)) = (self.vault.as_mut(), key.clone()) {
+            vault.key = new_key;
+            vault.dirty.store(true, Ordering::SeqCst);
+            return self.flush();
+        }
+
+        let previous_key = self.encryption_key().cloned();
+        let image = self.export_image()?;
+        let contents = match &key {
+            Some(new_key) => new_key.encrypt(&image)?,
+            None => image,
+        };
+        // Cerramos la conexión actual antes de sustituir el archivo.
+        *self = Self::open_in_memory()?;
+        if let Err(e) = write_atomic(&path, &contents) {
+            error!("Failed to rewrite database while changing encryption: {}", e);
+            *self = Self::open_path(&path, previous_key)?;
+            return Err(e);
+        }
+        *self = Self::open_path(&path, key)?;
+        Ok(())
+    }
+
+    /// Ejecuta `PRAGMA quick_check` y devuelve `true` si la base de datos está sana.
+    pub fn check_integrity(&self) -> Result<bool, AppError> {
+        let result: String = self.conn
+            .query_row("PRAGMA quick_check", [], |row| row.get(0))
+            .map_err(db_error)?;
+        if result != "ok" {
+            warn!("Integrity check failed: {}", result);
+        }
+        Ok(result == "ok")
+    }
+
+    /// Hasta `limit` transacciones activas con número de secuencia mayor que
+    /// `after_sequence`, por orden de secuencia. Permite recorrerlas por bloques sin
+    /// tener la base de datos bloqueada todo el rato (ver `startup`).
+    pub fn transactions_after(&self, after_sequence: u64, limit: usize) -> Result<Vec<Transaction>, AppError> {
+        let mut stmt = self.conn
+            .prepare(&format!(
+                "SELECT {} FROM transactions WHERE sequence > ?1 AND {} ORDER BY sequence LIMIT ?2",
+                TRANSACTION_COLUMNS, ACTIVE
+            ))
+            .map_err(db_error)?;
+        let rows = stmt
+            .query_map(params![after_sequence as i64, limit as i64], row_to_transaction)
+            .map_err(db_error)?;
+        rows.collect::<rusqlite::Result<Vec<_>>>().map_err(db_error)
+    }
+
+    /// Todas las transacciones, también las de la papelera, y el ID y el motivo de las
+    /// filas que no se pueden leer (ver `integrity`).
+    pub fn scan_transactions(&self) -> Result<(Vec<Transaction>, Vec<(String, String)>), AppError> {
+        let mut stmt = self.conn
+            .prepare(&format!("SELECT {} FROM transactions ORDER BY timestamp, sequence", TRANSACTION_COLUMNS))
+            .map_err(db_error)?;
+        let mut rows = stmt.query([]).map_err(db_error)?;
+        let (mut transactions, mut unreadable) = (Vec::new(), Vec::new());
+        while let Some(row) = rows.next().map_err(db_error)? {
+            match row_to_transaction(row) {
+                Ok(transaction) => transactions.push(transaction),
+                Err(e) => unreadable.push((row.get::<_, String>(0).map_err(db_error)?, e.to_string())),
+            }
+        }
+        Ok((transactions, unreadable))
+    }
+
+    /// Genera una copia consistente de la base de datos en `path` con `VACUUM INTO`
+    /// sobre un archivo temporal, que después se renombra de forma atómica. Si la base
+    /// de datos está cifrada, la copia se cifra con la misma clave.
+    pub fn write_backup(&self, path: &Path) -> Result<(), AppError> {
+        match &self.vault {
+            Some(vault) => write_encrypted_backup(&self.export_image()?, &vault.key, path),
+            None => vacuum_into(&self.conn, path),
+        }
+    }
+
+    /// Lo necesario para escribir la copia `.bak` con `write_backup_from`, que ya no
+    /// necesita esta conexión: solo la imagen en memoria si la base de datos está
+    /// cifrada, o la ruta del archivo si no lo está.
+    pub fn backup_source(&self) -> Result<BackupSource, AppError> {
+        match (&self.vault, &self.path) {
+            (Some(vault), _) => Ok(BackupSource::Encrypted { image: self.export_image()?, key: vault.key.clone() }),
+            (None, Some(path)) => Ok(BackupSource::File(path.clone())),
+            (None, None) => Err(AppError::Internal("La base de datos en memoria no tiene copia de seguridad.".to_string())),
+        }
+    }
+
+    /// Lee un valor de la tabla de ajustes `app_settings`.
+    pub fn get_setting(&self, key: &str) -> Result<Option<String>, AppError> {
+        self.conn
+            .query_row("SELECT value FROM app_settings WHERE key = ?1", params![key], |row| row.get(0))
+            .optional()
+            .map_err(db_error)
+    }
+
+    /// Guarda (o reemplaza) un valor en la tabla de ajustes `app_settings`.
+    pub fn set_setting(&self, key: &str, value: &str) -> Result<(), AppError> {
+        self.conn
+            .execute(
+                "INSERT INTO app_settings (key, value) VALUES (?1, ?2)
+                 ON CONFLICT(key) DO UPDATE SET value = excluded.value",
+                params![key, value],
+            )
+            .map_err(db_error)?;
+        Ok(())
+    }
+
+    /// Acceso directo a la conexión para los módulos con tablas propias.
+    pub fn connection(&self) -> &Connection {
+        &self.conn
+    }
+}
+
+/// Al cerrar una base de datos cifrada se escriben los cambios que `autosave` aún
+/// no había guardado.
+impl Drop for SqliteStorage {
+    fn drop(&mut self) {
+        if let Err(e) = self.flush() {
+            error!("No se pudieron guardar los datos cifrados al cerrar: {}", e);
+        }
+    }
+}
+
+/// Acceso exclusivo a la base de datos durante un comando. Los cambios de una base
+/// de datos cifrada no se escriben al soltarlo: los agrupa `autosave`.
+pub struct StorageGuard<'a>(tokio::sync::MutexGuard<'a, SqliteStorage>);
+
+impl<'a> StorageGuard<'a> {
+    pub fn new(guard: tokio::sync::MutexGuard<'a, SqliteStorage>) -> Self {
+        StorageGuard(guard)
+    }
+}
+
+impl Deref for StorageGuard<'_> {
+    type Target = SqliteStorage;
+
+    fn deref(&self) -> &SqliteStorage {
+        &self.0
+    }
+}
+
+impl DerefMut for StorageGuard<'_> {
+    fn deref_mut(&mut self) -> &mut SqliteStorage {
+        &mut self.0
+    }
+}
+
+// --- Recuperación ante Fallos ---
+
+/// Resultado de restaurar la base de datos desde su copia `.bak`.
+#[derive(Debug, Clone, Serialize)]
+pub struct RecoveryReport {
+    pub restored_from: String,
+    /// Fecha de modificación de la copia restaurada (segundos Unix).
+    pub backup_modified_at: Option<u64>,
+    /// Dónde se apartó la base de datos dañada, si existía.
+    pub damaged_copy: Option<String>,
+    pub transaction_count: usize,
+}
+
+/// Origen de la copia `.bak` que se escribe sin tener la base de datos bloqueada.
+pub enum BackupSource {
+    /// Archivo de una base de datos sin cifrar, que se copia desde otra conexión.
+    File(PathBuf),
+    /// Imagen de una base de datos cifrada, tomada en memoria, y su clave.
+    Encrypted { image: Vec<u8>, key: DataKey },
+}
+
+/// Escribe la copia `.bak` de `source` en `path`. A diferencia de
+/// `SqliteStorage::write_backup`, no usa la conexión de la aplicación, así que se
+/// puede llamar desde `spawn_blocking` mientras los comandos siguen usando la base
+/// de datos.
+pub fn write_backup_from(source: BackupSource, path: &Path) -> Result<(), AppError> {
+    match source {
+        BackupSource::Encrypted { image, key } => write_encrypted_backup(&image, &key, path),
+        BackupSource::File(db_path) => {
+            // Conexión de solo lectura: mientras dura la copia, las escrituras de la
+            // aplicación esperan (busy_timeout) en vez de fallar.
+            let conn = Connection::open_with_flags(&db_path, OpenFlags::SQLITE_OPEN_READ_ONLY | OpenFlags::SQLITE_OPEN_NO_MUTEX)
+                .map_err(db_error)?;
+            vacuum_into(&conn, path)
+        }
+    }
+}
+
+fn write_encrypted_backup(image: &[u8], key: &DataKey, path: &Path) -> Result<(), AppError> {
+    // Sin cifrar, VACUUM INTO dejaría una copia legible en disco.
+    write_atomic(path, &key.encrypt(image)?)?;
+    debug!("Copia de seguridad cifrada escrita en {}", path.display());
+    Ok(())
+}
+
+fn vacuum_into(conn: &Connection, path: &Path) -> Result<(), AppError> {
+    let tmp_path = sibling_path(path, ".tmp");
+    if tmp_path.exists() {
+        std::fs::remove_file(&tmp_path)
+            .map_err(|e| AppError::Io(format!("Error al limpiar el temporal {}: {}", tmp_path.display(), e)))?;
+    }
+    conn.execute("VACUUM INTO ?1", params![tmp_path.to_string_lossy()])
+        .map_err(db_error)?;
+    std::fs::rename(&tmp_path, path).map_err(|e| {
+        error!("Failed to move backup into place at {}: {}", path.display(), e);
+        AppError::Io(format!("Error al guardar la copia de seguridad {}: {}", path.display(), e))
+    })?;
+    debug!("Copia de seguridad escrita en {}", path.display());
+    Ok(())
+}
+
+/// Actualiza la copia `.bak` con el estado de `db`, ya verificado. Un fallo solo se
+/// registra: la copia anterior sigue sirviendo.
+pub fn refresh_backup(db: &SqliteStorage, backup_path: &Path) {
+    if let Err(e) = db.write_backup(backup_path) {
+        warn!("No se pudo actualizar la copia de seguridad: {}", e);
+    }
+}
+
+/// Abre la base de datos y comprueba su integridad. Si no se puede abrir o está
+/// dañada, la restaura automáticamente desde la copia `.bak`. Si el arranque es
+/// correcto, quien llama debe actualizar la copia con `refresh_backup`; al iniciar
+/// la aplicación se hace en segundo plano (ver `startup`).
+pub fn open_with_recovery(path: &Path, backup_path: &Path) -> Result<(SqliteStorage, Option<RecoveryReport>), AppError> {
+    let healthy = match SqliteStorage::open(path) {
+        Ok(db) => match db.check_integrity() {
+            Ok(true) => Some(db),
+            _ => None,
+        },
+        Err(e) => {
+            error!("Database could not be opened: {}", e);
+            None
+        }
+    };
+
+    match healthy {
+        Some(db) => Ok((db, None)),
+        None => {
+            warn!("Base de datos dañada en {}. Intentando restaurar desde {}", path.display(), backup_path.display());
+            let (db, report) = restore_from_backup(path, backup_path, None)?;
+            Ok((db, Some(report)))
+        }
+    }
+}
+
+/// Equivalente a `open_with_recovery` para la base de datos cifrada. Si el archivo
+/// principal no se puede descifrar pero la copia `.bak` sí, se considera dañado y se
+/// restaura; si ninguno se puede descifrar, la contraseña es incorrecta.
+pub fn open_encrypted_with_recovery(
+    path: &Path,
+    backup_path: &Path,
+    passphrase: &str,
+) -> Result<(SqliteStorage, Option<RecoveryReport>), AppError> {
+    let main = std::fs::read(path).ok().and_then(|data| encryption::unlock(passphrase, &data).ok());
+    if let Some((key, image)) = main {
+        let db = SqliteStorage::from_image(path, Some(&image), key)?;
+        if db.check_integrity()? {
+            db.flush()?;
+            if let Err(e) = db.write_backup(backup_path) {
+                warn!("No se pudo actualizar la copia de seguridad: {}", e);
+            }
+            return Ok((db, None));
+        }
+    }
+
+    let backup_key = std::fs::read(backup_path)
+        .ok()
+        .and_then(|data| encryption::unlock(passphrase, &data).ok())
+        .map(|(key, _)| key);
+    match backup_key {
+        Some(key) => {
+            warn!("Base de datos cifrada dañada en {}. Restaurando desde {}", path.display(), backup_path.display());
+            let (db, report) = restore_from_backup(path, backup_path, Some(key))?;
+            Ok((db, Some(report)))
+        }
+        None => {
+            error!("Could not decrypt database or backup with the given passphrase.");
+            Err(AppError::invalid_field("passphrase", "Contraseña incorrecta."))
+        }
+    }
+}
+
+/// Comprueba la integridad de una copia de seguridad sin modificarla. Con `key`, la
+/// copia debe estar cifrada con esa clave.
+pub fn backup_is_healthy(backup_path: &Path, key: Option<&DataKey>) -> Result<bool, AppError> {
+    match key {
+        Some(key) => {
+            let data = std::fs::read(backup_path)
+                .map_err(|e| AppError::Io(format!("Error al leer la copia de seguridad: {}", e)))?;
+            let image = key.decrypt(&data)?;
+            SqliteStorage::from_image(backup_path, Some(&image), key.clone())?.check_integrity()
+        }
+        None => {
+            let backup = Connection::open(backup_path).map_err(db_error)?;
+            let check: String = backup
+                .query_row("PRAGMA quick_check", [], |row| row.get(0))
+                .map_err(db_error)?;
+            Ok(check == "ok")
+        }
+    }
+}
+
+/// Sustituye la base de datos de `path` por la copia `backup_path`. La copia se
+/// escribe primero junto a `path` (`.restoring`) y solo reemplaza a la base de datos
+/// actual si pasa `verify_restored_copy`; si no, se devuelve el error y
+/// la actual queda como estaba. Al reemplazarla, la actual se aparta como
+/// `.damaged-<timestamp>` en lugar de borrarse.
+/// Cualquier conexión abierta sobre `path` debe cerrarse antes de llamar a esta función.
+/// Con `key`, la copia debe estar cifrada con esa clave.
+pub fn restore_from_backup(
+    path: &Path,
+    backup_path: &Path,
+    key: Option<DataKey>,
+) -> Result<(SqliteStorage, RecoveryReport), AppError> {
+    if !backup_path.exists() {
+        error!("No backup found at {}", backup_path.display());
+        return Err(AppError::NotFound(format!("No existe copia de seguridad en {}.", backup_path.display())));
+    }
+
+    if !backup_is_healthy(backup_path, key.as_ref())? {
+        error!("Backup {} is also damaged.", backup_path.display());
+        return Err(AppError::Database("La copia de seguridad también está dañada; no se puede restaurar.".to_string()));
+    }
+
+    let staging_path = sibling_path(path, ".restoring");
+    let contents = std::fs::read(backup_path)
+        .map_err(|e| AppError::Io(format!("Error al leer la copia de seguridad: {}", e)))?;
+    write_atomic(&staging_path, &contents)?;
+    if let Err(e) = verify_restored_copy(&staging_path, key.clone()) {
+        error!("Restored copy {} is not usable: {}", staging_path.display(), e);
+        let _ = std::fs::remove_file(&staging_path);
+        return Err(e);
+    }
+
+    let damaged_copy = if path.exists() {
+        let damaged_path = sibling_path(path, &format!(".damaged-{}", Utc::now().timestamp()));
+        if let Err(e) = std::fs::rename(path, &damaged_path) {
+            let _ = std::fs::remove_file(&staging_path);
+            return Err(AppError::Io(format!("Error al apartar la base de datos dañada: {}", e)));
+        }
+        let journal_path = sibling_path(path, "-journal");
+        if journal_path.exists() {
+            let _ = std::fs::rename(&journal_path, sibling_path(&damaged_path, "-journal"));
+        }
+        Some(damaged_path)
+    } else {
+        None
+    };
+
+    if let Err(e) = std::fs::rename(&staging_path, path) {
+        error!("Could not move restored copy into {}: {}", path.display(), e);
+        // Se devuelve la base de datos original a su sitio.
+        if let Some(damaged_path) = &damaged_copy {
+            let _ = std::fs::rename(damaged_path, path);
+        }
+        let _ = std::fs::remove_file(&staging_path);
+        return Err(AppError::Io(format!("Error al sustituir la base de datos por la copia: {}", e)));
+    }
+
+    let backup_modified_at = std::fs::metadata(backup_path)
+        .and_then(|m| m.modified())
+        .ok()
+        .and_then(|t| t.duration_since(std::time::UNIX_EPOCH).ok())
+        .map(|d| d.as_secs());
+
+    let db = SqliteStorage::open_path(path, key)?;
+    let report = RecoveryReport {
+        restored_from: backup_path.display().to_string(),
+        backup_modified_at,
+        damaged_copy: damaged_copy.map(|p| p.display().to_string()),
+        transaction_count: db.count_transactions()?,
+    };
+    info!("Base de datos restaurada: {:?}", report);
+    Ok((db, report))
+}
+
+/// Comprueba la copia que se va a restaurar sin modificarla: se abre en solo lectura
+/// (descifrada en memoria si hay `key`), sin aplicar migraciones, y debe pasar la
+/// comprobación de integridad completa y no ser de un esquema más reciente que el
+/// de esta versión. La conexión se cierra al terminar.
+fn verify_restored_copy(path: &Path, key: Option<DataKey>) -> Result<(), AppError> {
+    let conn = match key {
+        Some(key) => {
+            let data = std::fs::read(path)
+                .map_err(|e| AppError::Io(format!("Error al leer la copia restaurada: {}", e)))?;
+            let image = key.decrypt(&data)?;
+            let mut conn = Connection::open_in_memory().map_err(db_error)?;
+            conn.deserialize(DatabaseName::Main, owned_data(&image)?, true).map_err(db_error)?;
+            conn
+        }
+        None => Connection::open_with_flags(path, OpenFlags::SQLITE_OPEN_READ_ONLY | OpenFlags::SQLITE_OPEN_NO_MUTEX)
+            .map_err(db_error)?,
+    };
+
+    let result: String = conn
+        .query_row("PRAGMA integrity_check", [], |row| row.get(0))
+        .map_err(db_error)?;
+    if result != "ok" {
+        warn!("Integrity check of restored copy failed: {}", result);
+        return Err(AppError::Database(
+            "La copia de seguridad no supera la comprobación de integridad; no se ha cambiado nada.".to_string(),
+        ));
+    }
+    let version = conn
+        .pragma_query_value(None, "user_version", |row| row.get::<_, i64>(0))
+        .map_err(db_error)? as usize;
+    if version > migrations::schema_version() {
+        warn!("Restored copy has schema v{}, newer than v{}.", version, migrations::schema_version());
+        return Err(AppError::Database(format!(
+            "La copia de seguridad es de una versión más reciente de la aplicación (esquema v{}); no se ha cambiado nada.",
+            version
+        )));
+    }
+    Ok(())
+}
+
+/// Columnas de la tabla `transactions`, en el mismo orden que `transaction_values`.
+const TRANSACTION_COLUMNS: &str =
+    "id, transaction_type, amount, description, store_name, timestamp, category, subcategory, currency, receipt_paths, deleted_at, \
+     tax_rate, tax_amount, tags, external_id, transaction_date, created_at, updated_at, \
+     notes, custom_fields, payment_method, contact_id, transfer_store, account_id, transfer_account_id, user_id, revision, device_id, sequence";
+
+/// Posiciones en `TRANSACTION_COLUMNS` de `user_id`, `device_id` y `sequence`, que se
+/// tratan aparte al insertar y al modificar.
+const USER_ID_COLUMN: usize = 25;
+const DEVICE_ID_COLUMN: usize = 27;
+const SEQUENCE_COLUMN: usize = 28;
+
+/// Filtro que excluye las transacciones que están en la papelera.
+const ACTIVE: &str = "deleted_at IS NULL";
+
+fn text_or_null(value: &Option<String>) -> Value {
+    value.clone().map(Value::Text).unwrap_or(Value::Null)
+}
+
+fn integer_or_null(value: Option<u64>) -> Value {
+    value.map(|v| Value::Integer(v as i64)).unwrap_or(Value::Null)
+}
+
+fn decimal_or_null(value: Option<Decimal>) -> Value {
+    value.map(|v| Value::Text(v.to_string())).unwrap_or(Value::Null)
+}
+
+/// Valores de una transacción en el orden de `TRANSACTION_COLUMNS`.
+fn transaction_values(transaction: &Transaction) -> Vec<Value> {
+    vec![
+        Value::Text(transaction.id.clone()),
+        Value::Text(transaction.transaction_type.to_string()),
+        Value::Text(transaction.amount.to_string()),
+        Value::Text(transaction.description.clone()),
+        Value::Text(transaction.store_name.clone()),
+        Value::Integer(transaction.timestamp as i64),
+        text_or_null(&transaction.category),
+        text_or_null(&transaction.subcategory),
+        Value::Text(transaction.currency.clone()),
+        Value::Text(to_json_column(&transaction.receipt_paths)),
+        integer_or_null(transaction.deleted_at),
+        decimal_or_null(transaction.tax_rate),
+        decimal_or_null(transaction.tax_amount),
+        Value::Text(to_json_column(&transaction.tags)),
+        text_or_null(&transaction.external_id),
+        Value::Text(transaction.transaction_date.format(DATE_FORMAT).to_string()),
+        Value::Integer(transaction.created_at as i64),
+        Value::Integer(transaction.updated_at as i64),
+        Value::Text(transaction.notes.clone()),
+        Value::Text(to_json_column(&transaction.custom_fields)),
+        Value::Text(transaction.payment_method.to_string()),
+        transaction.contact_id.clone().map_or(Value::Null, Value::Text),
+        text_or_null(&transaction.transfer_store),
+        text_or_null(&transaction.account_id),
+        text_or_null(&transaction.transfer_account_id),
+        text_or_null(&transaction.user_id),
+        Value::Integer(transaction.revision as i64),
+        Value::Text(transaction.device_id.clone()),
+        Value::Integer(transaction.sequence as i64),
+    ]
+}
+
+/// Lee una columna TEXT que contiene un valor serializado en JSON.
+pub fn json_column<T: serde::de::DeserializeOwned>(row: &Row, index: usize) -> rusqlite::Result<T> {
+    let text: String = row.get(index)?;
+    serde_json::from_str(&text).map_err(|e| {
+        rusqlite::Error::FromSqlConversionFailure(index, rusqlite::types::Type::Text, Box::new(e))
+    })
+}
+
+/// Lee un importe guardado como texto decimal. Acepta también los valores numéricos
+/// que SQLite pudiera devolver para columnas anteriores a la migración v9.
+pub fn decimal_column(row: &Row, index: usize) -> rusqlite::Result<Decimal> {
+    let parsed = match row.get::<_, Value>(index)? {
+        Value::Text(text) => Decimal::from_str(text.trim()),
+        Value::Integer(i) => Ok(Decimal::from(i)),
+        Value::Real(r) => Decimal::from_str(&r.to_string()),
+        _ => return Err(rusqlite::Error::InvalidColumnType(index, "importe".to_owned(), Type::Null)),
+    };
+    parsed.map_err(|e| rusqlite::Error::FromSqlConversionFailure(index, Type::Text, Box::new(e)))
+}
+
+/// Formato con el que se guardan las fechas sin hora.
+pub const DATE_FORMAT: &str = "%Y-%m-%d";
+
+/// Lee una fecha guardada como texto `AAAA-MM-DD`.
+pub fn date_column(row: &Row, index: usize) -> rusqlite::Result<NaiveDate> {
+    let text: String = row.get(index)?;
+    NaiveDate::parse_from_str(&text, DATE_FORMAT)
+        .map_err(|e| rusqlite::Error::FromSqlConversionFailure(index, Type::Text, Box::new(e)))
+}
+
+/// Como `decimal_column`, pero admite `NULL`.
+pub fn optional_decimal_column(row: &Row, index: usize) -> rusqlite::Result<Option<Decimal>> {
+    match row.get_ref(index)? {
+        rusqlite::types::ValueRef::Null => Ok(None),
+        _ => decimal_column(row, index).map(Some),
+    }
+}
+
+/// Serializa un valor a JSON para guardarlo en una columna TEXT.
+pub fn to_json_column<T: Serialize>(value: &T) -> String {
+    serde_json::to_string(value).unwrap_or_else(|_| "null".to_string())
+}
+
+fn row_to_transaction(row: &Row) -> rusqlite::Result<Transaction> {
+    let type_str: String = row.get(1)?;
+    let transaction_type = match type_str.as_str() {
+        "Ingreso" => TransactionType::Ingreso,
+        "Gasto" => TransactionType::Gasto,
+        "Transferencia" => TransactionType::Transferencia,
+        other => {
+            return Err(rusqlite::Error::FromSqlConversionFailure(
+                1,
+                rusqlite::types::Type::Text,
+                format!("Tipo de transacción desconocido: {}", other).into(),
+            ))
+        }
+    };
+    let method_str: String = row.get(20)?;
+    let payment_method = PaymentMethod::parse(&method_str).ok_or_else(|| {
+        rusqlite::Error::FromSqlConversionFailure(
+            20,
+            rusqlite::types::Type::Text,
+            format!("Medio de pago desconocido: {}", method_str).into(),
+        )
+    })?;
+    Ok(Transaction {
+        id: row.get(0)?,
+        transaction_type,
+        amount: decimal_column(row, 2)?,
+        description: row.get(3)?,
+        store_name: row.get(4)?,
+        timestamp: row.get::<_, i64>(5)? as u64,
+        category: row.get(6)?,
+        subcategory: row.get(7)?,
+        currency: row.get(8)?,
+        receipt_paths: json_column(row, 9)?,
+        deleted_at: row.get::<_, Option<i64>>(10)?.map(|t| t as u64),
+        tax_rate: optional_decimal_column(row, 11)?,
+        tax_amount: optional_decimal_column(row, 12)?,
+        tags: json_column(row, 13)?,
+        external_id: row.get(14)?,
+        transaction_date: date_column(row, 15)?,
+        created_at: row.get::<_, i64>(16)? as u64,
+        updated_at: row.get::<_, i64>(17)? as u64,
+        notes: row.get(18)?,
+        custom_fields: json_column(row, 19)?,
+        payment_method,
+        contact_id: row.get(21)?,
+        transfer_store: row.get(22)?,
+        account_id: row.get(23)?,
+        transfer_account_id: row.get(24)?,
+        user_id: row.get(25)?,
+        revision: row.get::<_, i64>(26)? as u64,
+        device_id: row.get(27)?,
+        sequence: row.get::<_, i64>(28)? as u64,
+    })
+}
+
+/// Siguiente número de secuencia. El último asignado se guarda en `app_settings`
+/// para no repetir el de una transacción borrada.
+fn next_sequence(conn: &Connection) -> rusqlite::Result<i64> {
+    conn.query_row(
+        "INSERT INTO app_settings (key, value) \
+             VALUES ('transaction_sequence', (SELECT COALESCE(MAX(sequence), 0) + 1 FROM transactions)) \
+         ON CONFLICT(key) DO UPDATE SET \
+             value = MAX(CAST(value AS INTEGER), (SELECT COALESCE(MAX(sequence), 0) FROM transactions)) + 1 \
+         RETURNING CAST(value AS INTEGER)",
+        [],
+        |row| row.get(0),
+    )
+}
+
+/// Inserta una transacción con el siguiente número de secuencia. Si no trae
+/// `device_id` (es nueva) se le pone el de este equipo, guardado en `app_settings`,
+/// y, si tampoco trae usuario, el de la sesión abierta.
+fn insert_transaction_row(conn: &Connection, transaction: &Transaction) -> rusqlite::Result<usize> {
+    let mut values = transaction_values(transaction);
+    if transaction.device_id.is_empty() && transaction.user_id.is_none() {
+        if let Some(user_id) = users::current_user_id() {
+            values[USER_ID_COLUMN] = Value::Text(user_id);
+        }
+    }
+    if let Some(sequence) = values.last_mut() {
+        *sequence = Value::Integer(next_sequence(conn)?);
+    }
+    let mut placeholders: Vec<String> = (1..=values.len()).map(|i| format!("?{}", i)).collect();
+    let device_id = &mut placeholders[DEVICE_ID_COLUMN];
+    *device_id = format!(
+        "COALESCE(NULLIF({}, ''), (SELECT value FROM app_settings WHERE key = 'device_id'), '')",
+        device_id
+    );
+    conn.execute(
+        &format!("INSERT INTO transactions ({}) VALUES ({})", TRANSACTION_COLUMNS, placeholders.join(", ")),
+        params_from_iter(values),
+    )
+}
+
+impl TransactionRepository for SqliteStorage {
+    fn list_transactions(&self) -> Result<Vec<Transaction>, AppError> {
+        let mut stmt = self.conn
+            .prepare(&format!("SELECT {} FROM transactions WHERE {} ORDER BY timestamp, sequence", TRANSACTION_COLUMNS, ACTIVE))
+            .map_err(db_error)?;
+        let rows = stmt.query_map([], row_to_transaction).map_err(db_error)?;
+        rows.collect::<rusqlite::Result<Vec<_>>>().map_err(db_error)
+    }
+
+    fn get_transaction(&self, id: &str) -> Result<Option<Transaction>, AppError> {
+        self.conn
+            .query_row(
+                &format!("SELECT {} FROM transactions WHERE id = ?1 AND {}", TRANSACTION_COLUMNS, ACTIVE),
+                params![id],
+                row_to_transaction,
+            )
+            .optional()
+            .map_err(db_error)
+    }
+
+    fn list_transactions_by_store(&self, store_name: &str) -> Result<Vec<Transaction>, AppError> {
+        let mut stmt = self.conn
+            .prepare(&format!(
+                "SELECT {} FROM transactions WHERE store_name = ?1 AND {} ORDER BY timestamp, sequence",
+                TRANSACTION_COLUMNS, ACTIVE
+            ))
+            .map_err(db_error)?;
+        let rows = stmt.query_map(params![store_name], row_to_transaction).map_err(db_error)?;
+        rows.collect::<rusqlite::Result<Vec<_>>>().map_err(db_error)
+    }
+
+    fn list_transactions_with_tag(&self, tag: &str) -> Result<Vec<Transaction>, AppError> {
+        let mut stmt = self.conn
+            .prepare(&format!(
+                "SELECT {} FROM transactions \
+                 WHERE EXISTS (SELECT 1 FROM json_each(tags) WHERE value = ?1) AND {} ORDER BY timestamp, sequence",
+                TRANSACTION_COLUMNS, ACTIVE
+            ))
+            .map_err(db_error)?;
+        let rows = stmt.query_map(params![tag], row_to_transaction).map_err(db_error)?;
+        rows.collect::<rusqlite::Result<Vec<_>>>().map_err(db_error)
+    }
+
+    fn search_transactions(&self, match_expr: &str, limit: usize) -> Result<Vec<Transaction>, AppError> {
+        let mut stmt = self.conn
+            .prepare(&format!(
+                "SELECT {} FROM transactions \
+                 JOIN (SELECT rowid AS hit, rank FROM transactions_fts WHERE transactions_fts MATCH ?1) \
+                 ON transactions.rowid = hit \
+                 WHERE {} ORDER BY rank, timestamp DESC, sequence DESC LIMIT ?2",
+                TRANSACTION_COLUMNS, ACTIVE
+            ))
+            .map_err(db_error)?;
+        let rows = stmt
+            .query_map(params![match_expr, limit as i64], row_to_transaction)
+            .map_err(db_error)?;
+        rows.collect::<rusqlite::Result<Vec<_>>>().map_err(db_error)
+    }
+
+    fn insert_transaction(&self, transaction: &Transaction) -> Result<(), AppError> {
+        insert_transaction_row(&self.conn, transaction).map_err(db_error)?;
+        Ok(())
+    }
+
+    fn insert_transactions(&self, transactions: &[Transaction]) -> Result<(), AppError> {
+        let tx = self.conn.unchecked_transaction().map_err(db_error)?;
+        for transaction in transactions {
+            insert_transaction_row(&tx, transaction).map_err(db_error)?;
+        }
+        tx.commit().map_err(db_error)
+    }
+
+    fn update_transaction(&self, transaction: &Transaction) -> Result<bool, AppError> {
+        // La columna 1 es el ID; el resto se asigna en el mismo orden que en el INSERT,
+        // salvo el número de secuencia, que no cambia.
+        let assignments: Vec<String> = TRANSACTION_COLUMNS
+            .split(", ")
+            .enumerate()
+            .take(SEQUENCE_COLUMN)
+            .skip(1)
+            .map(|(i, column)| format!("{} = ?{}", column, i + 1))
+            .collect();
+        let mut values = transaction_values(transaction);
+        values.truncate(SEQUENCE_COLUMN);
+        let changed = self.conn
+            .execute(
+                &format!("UPDATE transactions SET {} WHERE id = ?1", assignments.join(", ")),
+                params_from_iter(values),
+            )
+            .map_err(db_error)?;
+        Ok(changed > 0)
+    }
+
+    fn delete_transaction(&self, id: &str) -> Result<bool, AppError> {
+        let changed = self.conn
+            .execute("DELETE FROM transactions WHERE id = ?1", params![id])
+            .map_err(db_error)?;
+        Ok(changed > 0)
+    }
+
+    fn count_transactions(&self) -> Result<usize, AppError> {
+        self.conn
+            .query_row(&format!("SELECT COUNT(*) FROM transactions WHERE {}", ACTIVE), [], |row| row.get::<_, i64>(0))
+            .map(|count| count as usize)
+            .map_err(db_error)
+    }
+
+    fn query_transactions(&self, query: &TransactionQuery) -> Result<TransactionPage, AppError> {
+        let page = query.page.unwrap_or(1).max(1);
+        let page_size = query.page_size.unwrap_or(DEFAULT_PAGE_SIZE).clamp(1, MAX_PAGE_SIZE);
+        let (where_clause, values) = build_filter(query);
+
+        let total_count = self.conn
+            .query_row(
+                &format!("SELECT COUNT(*) FROM transactions {}", where_clause),
+                params_from_iter(values.iter()),
+                |row| row.get::<_, i64>(0),
+            )
+            .map_err(db_error)? as usize;
+
+        let direction = match query.sort_direction {
+            SortDirection::Asc => "ASC",
+            SortDirection::Desc => "DESC",
+        };
+        let sql = format!(
+            "SELECT {} FROM transactions {} ORDER BY {} {}, sequence {} LIMIT {} OFFSET {}",
+            TRANSACTION_COLUMNS,
+            where_clause,
+            query.sort_by.column(),
+            direction,
+            direction,
+            page_size,
+            (page - 1) * page_size,
+        );
+        let mut stmt = self.conn.prepare(&sql).map_err(db_error)?;
+        let rows = stmt
+            .query_map(params_from_iter(values.iter()), row_to_transaction)
+            .map_err(db_error)?;
+        let items = rows.collect::<rusqlite::Result<Vec<_>>>().map_err(db_error)?;
+
+        Ok(TransactionPage { items, total_count, page, page_size })
+    }
+
+    fn store_transaction_counts(&self) -> Result<HashMap<String, usize>, AppError> {
+        let mut stmt = self.conn
+            .prepare(&format!("SELECT store_name, COUNT(*) FROM transactions WHERE {} GROUP BY store_name", ACTIVE))
+            .map_err(db_error)?;
+        let rows = stmt
+            .query_map([], |row| Ok((row.get::<_, String>(0)?, row.get::<_, i64>(1)? as usize)))
+            .map_err(db_error)?;
+        rows.collect::<rusqlite::Result<HashMap<_, _>>>().map_err(db_error)
+    }
+
+    fn rename_store(&self, old_name: &str, new_name: &str) -> Result<usize, AppError> {
+        let renamed = self.conn
+            .execute(
+                &format!("UPDATE transactions SET store_name = ?2 WHERE store_name = ?1 AND {}", ACTIVE),
+                params![old_name, new_name],
+            )
+            .map_err(db_error)?;
+        let transfers = self.conn
+            .execute(
+                &format!("UPDATE transactions SET transfer_store = ?2 WHERE transfer_store = ?1 AND {}", ACTIVE),
+                params![old_name, new_name],
+            )
+            .map_err(db_error)?;
+        Ok(renamed + transfers)
+    }
+
+    fn delete_store(&self, store_name: &str) -> Result<usize, AppError> {
+        self.conn
+            .execute("DELETE FROM transactions WHERE store_name = ?1 OR transfer_store = ?1", params![store_name])
+            .map_err(db_error)
+    }
+
+    fn soft_delete_transaction(&self, id: &str, deleted_at: u64) -> Result<bool, AppError> {
+        let changed = self.conn
+            .execute(
+                &format!("UPDATE transactions SET deleted_at = ?2 WHERE id = ?1 AND {}", ACTIVE),
+                params![id, deleted_at as i64],
+            )
+            .map_err(db_error)?;
+        Ok(changed > 0)
+    }
+
+    fn list_deleted_transactions(&self) -> Result<Vec<Transaction>, AppError> {
+        let mut stmt = self.conn
+            .prepare(&format!(
+                "SELECT {} FROM transactions WHERE deleted_at IS NOT NULL ORDER BY deleted_at DESC, sequence",
+                TRANSACTION_COLUMNS
+            ))
+            .map_err(db_error)?;
+        let rows = stmt.query_map([], row_to_transaction).map_err(db_error)?;
+        rows.collect::<rusqlite::Result<Vec<_>>>().map_err(db_error)
+    }
+
+    fn restore_transaction(&self, id: &str) -> Result<bool, AppError> {
+        let changed = self.conn
+            .execute(
+                "UPDATE transactions SET deleted_at = NULL WHERE id = ?1 AND deleted_at IS NOT NULL",
+                params![id],
+            )
+            .map_err(db_error)?;
+        Ok(changed > 0)
+    }
+
+    fn purge_deleted_before(&self, cutoff: u64) -> Result<Vec<Transaction>, AppError> {
+        let tx = self.conn.unchecked_transaction().map_err(db_error)?;
+        let purged = {
+            let mut stmt = tx
+                .prepare(&format!(
+                    "SELECT {} FROM transactions WHERE deleted_at IS NOT NULL AND deleted_at <= ?1",
+                    TRANSACTION_COLUMNS
+                ))
+                .map_err(db_error)?;
+            let rows = stmt.query_map(params![cutoff as i64], row_to_transaction).map_err(db_error)?;
+            rows.collect::<rusqlite::Result<Vec<_>>>().map_err(db_error)?
+        };
+        tx.execute(
+            "DELETE FROM transactions WHERE deleted_at IS NOT NULL AND deleted_at <= ?1",
+            params![cutoff as i64],
+        )
+        .map_err(db_error)?;
+        tx.commit().map_err(db_error)?;
+        Ok(purged)
+    }
+
+    fn all_transaction_ids(&self) -> Result<HashSet<String>, AppError> {
+        let mut stmt = self.conn.prepare("SELECT id FROM transactions").map_err(db_error)?;
+        let rows = stmt.query_map([], |row| row.get::<_, String>(0)).map_err(db_error)?;
+        rows.collect::<rusqlite::Result<HashSet<_>>>().map_err(db_error)
+    }
+
+    fn all_external_ids(&self) -> Result<HashSet<String>, AppError> {
+        let mut stmt = self.conn
+            .prepare("SELECT external_id FROM transactions WHERE external_id IS NOT NULL")
+            .map_err(db_error)?;
+        let rows = stmt.query_map([], |row| row.get::<_, String>(0)).map_err(db_error)?;
+        rows.collect::<rusqlite::Result<HashSet<_>>>().map_err(db_error)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    /// Directorio temporal propio de cada prueba.
+    fn temp_dir() -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("contabilidad-test-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    fn transaction(id: &str) -> Transaction {
+        serde_json::from_value(json!({
+            "id": id,
+            "type": "Gasto",
+            "amount": 10,
+            "description": "Compra",
+            "store_name": "Tienda",
+            "timestamp": 1_705_320_000,
+            "transaction_date": "2024-01-15",
+            "created_at": 1_705_320_000,
+            "updated_at": 1_705_320_000,
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn restore_replaces_the_database_with_a_good_backup() {
+        let dir = temp_dir();
+        let (path, backup_path) = (dir.join("data.db"), dir.join("data.db.bak"));
+        let db = SqliteStorage::open(&path).unwrap();
+        db.insert_transaction(&transaction("t1")).unwrap();
+        db.write_backup(&backup_path).unwrap();
+        db.insert_transaction(&transaction("t2")).unwrap();
+        drop(db);
+
+        let (restored, report) = restore_from_backup(&path, &backup_path, None).unwrap();
+        assert_eq!(restored.count_transactions().unwrap(), 1);
+        assert_eq!(report.transaction_count, 1);
+        assert!(report.damaged_copy.is_some());
+        assert!(!sibling_path(&path, ".restoring").exists());
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn restore_from_a_corrupt_backup_leaves_the_database_untouched() {
+        let dir = temp_dir();
+        let (path, backup_path) = (dir.join("data.db"), dir.join("data.db.bak"));
+        let db = SqliteStorage::open(&path).unwrap();
+        db.insert_transaction(&transaction("t1")).unwrap();
+        drop(db);
+        std::fs::write(&backup_path, b"esto no es una base de datos").unwrap();
+
+        assert!(restore_from_backup(&path, &backup_path, None).is_err());
+        assert!(!sibling_path(&path, ".restoring").exists());
+        assert_eq!(SqliteStorage::open(&path).unwrap().count_transactions().unwrap(), 1);
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn verify_restored_copy_rejects_a_newer_schema_without_migrating_it() {
+        let dir = temp_dir();
+        let path = dir.join("copy.db");
+        drop(SqliteStorage::open(&path).unwrap());
+        verify_restored_copy(&path, None).unwrap();
+
+        let newer = migrations::schema_version() as i64 + 1;
+        Connection::open(&path).unwrap().pragma_update(None, "user_version", newer).unwrap();
+        assert!(matches!(verify_restored_copy(&path, None), Err(AppError::Database(_))));
+        let version: i64 = Connection::open(&path)
+            .unwrap()
+            .pragma_query_value(None, "user_version", |row| row.get(0))
+            .unwrap();
+        assert_eq!(version, newer);
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
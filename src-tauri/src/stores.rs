@@ -0,0 +1,449 @@
+// src-tauri/src/stores.rs
+
+use rusqlite::{params, Connection, OptionalExtension, Row};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use tauri::{AppHandle, State};
+use log::{debug, error, info};
+
+use crate::aggregates::{self, Aggregates};
+use crate::audit;
+use crate::categories;
+use crate::currencies::RateTable;
+use crate::error::AppError;
+use crate::events;
+use crate::history::{Change, HistoryEntry};
+use crate::periods::{self, Period};
+use crate::reports::GroupTotals;
+use crate::storage::{db_error, TransactionRepository};
+use crate::{AppState, TransactionType};
+
+/// Nombre reservado con el que la interfaz muestra todas las tiendas a la vez.
+pub const ALL_STORES: &str = "Todas las Tiendas";
+
+/// Tienda con sus datos. Las transacciones la referencian por `name`; las tiendas
+/// que solo aparecen en transacciones (sin datos guardados) se devuelven con los
+/// campos opcionales vacíos.
+#[derive(Debug, Clone, Serialize)]
+pub struct Store {
+    pub name: String,
+    pub address: Option<String>,
+    /// NIF o CIF, en mayúsculas y sin espacios ni guiones.
+    pub nif: Option<String>,
+    /// Categoría que se asigna a las transacciones nuevas de la tienda si no se indica otra.
+    pub default_category: Option<String>,
+    /// Transacciones activas de la tienda.
+    pub transaction_count: usize,
+}
+
+/// Datos de `create_store_command` y `update_store_command`.
+#[derive(Debug, Clone, Deserialize)]
+pub struct StoreInput {
+    pub name: String,
+    #[serde(default)]
+    pub address: Option<String>,
+    #[serde(default)]
+    pub nif: Option<String>,
+    #[serde(default)]
+    pub default_category: Option<String>,
+}
+
+const STORE_SELECT: &str = "SELECT name, address, nif, default_category, \
+         (SELECT COUNT(*) FROM transactions WHERE store_name = stores.name AND deleted_at IS NULL) \
+     FROM stores";
+
+fn row_to_store(row: &Row) -> rusqlite::Result<Store> {
+    Ok(Store {
+        name: row.get(0)?,
+        address: row.get(1)?,
+        nif: row.get(2)?,
+        default_category: row.get(3)?,
+        transaction_count: row.get::<_, i64>(4)? as usize,
+    })
+}
+
+/// Todas las tiendas, registradas o usadas en alguna transacción activa, por nombre.
+pub fn list_stores(conn: &Connection) -> Result<Vec<Store>, AppError> {
+    let mut stmt = conn
+        .prepare(&format!(
+            "{} UNION ALL \
+             SELECT store_name, NULL, NULL, NULL, COUNT(*) FROM transactions \
+             WHERE deleted_at IS NULL AND store_name NOT IN (SELECT name FROM stores) GROUP BY store_name \
+             ORDER BY 1",
+            STORE_SELECT
+        ))
+        .map_err(db_error)?;
+    let rows = stmt.query_map([], row_to_store).map_err(db_error)?;
+    rows.collect::<rusqlite::Result<Vec<_>>>().map_err(db_error)
+}
+
+/// Tienda registrada con ese nombre, si la hay.
+pub fn get_store(conn: &Connection, name: &str) -> Result<Option<Store>, AppError> {
+    conn.query_row(&format!("{} WHERE name = ?1", STORE_SELECT), params![name], row_to_store)
+        .optional()
+        .map_err(db_error)
+}
+
+/// `true` si la tienda está registrada o tiene transacciones, incluidas las de la papelera.
+fn store_exists(conn: &Connection, name: &str) -> Result<bool, AppError> {
+    conn.query_row(
+        "SELECT EXISTS (SELECT 1 FROM stores WHERE name = ?1) \
+             OR EXISTS (SELECT 1 FROM transactions WHERE store_name = ?1)",
+        params![name],
+        |row| row.get(0),
+    )
+    .map_err(db_error)
+}
+
+/// Categoría por defecto de la tienda, si tiene.
+pub fn default_category(conn: &Connection, store_name: &str) -> Result<Option<String>, AppError> {
+    Ok(get_store(conn, store_name)?.and_then(|s| s.default_category))
+}
+
+/// Aplica a los datos de la tienda el cambio de nombre de sus transacciones. Si ya
+/// había una tienda registrada con el nuevo nombre, se conservan sus datos. Devuelve
+/// `false` si la tienda no estaba registrada.
+pub fn rename_store_record(conn: &Connection, old_name: &str, new_name: &str) -> Result<bool, AppError> {
+    let changed = if get_store(conn, new_name)?.is_some() {
+        conn.execute("DELETE FROM stores WHERE name = ?1", params![old_name])
+    } else {
+        conn.execute(
+            "UPDATE stores SET name = ?2, updated_at = ?3 WHERE name = ?1",
+            params![old_name, new_name, periods::now_timestamp() as i64],
+        )
+    }
+    .map_err(db_error)?;
+    Ok(changed > 0)
+}
+
+/// Borra los datos de la tienda. Devuelve `false` si no estaba registrada.
+pub fn delete_store_record(conn: &Connection, name: &str) -> Result<bool, AppError> {
+    let changed = conn.execute("DELETE FROM stores WHERE name = ?1", params![name]).map_err(db_error)?;
+    Ok(changed > 0)
+}
+
+/// Fusiona los datos de las tiendas `sources` en `target`: los campos que le falten a
+/// `target` se toman de la primera tienda de origen que los tenga, y los registros de
+/// origen se borran. Devuelve la tienda resultante, o `None` si ninguna estaba registrada.
+pub fn merge_store_records(conn: &Connection, sources: &[String], target: &str) -> Result<Option<Store>, AppError> {
+    let mut merged = get_store(conn, target)?;
+    for source in sources {
+        let Some(store) = get_store(conn, source)? else {
+            continue;
+        };
+        merged = Some(match merged {
+            Some(current) => Store {
+                address: current.address.or(store.address),
+                nif: current.nif.or(store.nif),
+                default_category: current.default_category.or(store.default_category),
+                ..current
+            },
+            None => Store { name: target.to_owned(), ..store },
+        });
+        delete_store_record(conn, source)?;
+    }
+    let Some(merged) = merged else {
+        return Ok(None);
+    };
+    let now = periods::now_timestamp() as i64;
+    conn.execute(
+        "INSERT INTO stores (name, address, nif, default_category, created_at, updated_at) \
+         VALUES (?1, ?2, ?3, ?4, ?5, ?5) \
+         ON CONFLICT(name) DO UPDATE SET address = excluded.address, nif = excluded.nif, \
+             default_category = excluded.default_category, updated_at = excluded.updated_at",
+        params![merged.name, merged.address, merged.nif, merged.default_category, now],
+    )
+    .map_err(db_error)?;
+    get_store(conn, target)
+}
+
+/// Quita espacios y guiones y pasa a mayúsculas: `12345678-z` → `12345678Z`.
+fn normalize_nif(nif: &str) -> String {
+    nif.chars().filter(|c| !c.is_whitespace() && *c != '-').collect::<String>().to_uppercase()
+}
+
+/// Normaliza un NIF o CIF y comprueba que tenga 9 letras o números.
+pub fn validate_nif(field: &str, nif: &str) -> Result<String, AppError> {
+    let nif = normalize_nif(nif);
+    if nif.len() != 9 || !nif.chars().all(|c| c.is_ascii_alphanumeric()) {
+        error!("Invalid NIF: '{}'", nif);
+        return Err(AppError::invalid_field(field, "El NIF debe tener 9 letras o números."));
+    }
+    Ok(nif)
+}
+
+/// Texto sin espacios alrededor, o `None` si queda vacío.
+pub fn non_empty(value: Option<String>) -> Option<String> {
+    value.map(|v| v.trim().to_owned()).filter(|v| !v.is_empty())
+}
+
+/// Valida y normaliza los datos de una tienda.
+fn resolve_input(conn: &Connection, input: StoreInput) -> Result<StoreInput, AppError> {
+    let name = input.name.trim().to_owned();
+    if name.is_empty() {
+        return Err(AppError::invalid_field("name", "El nombre de tienda no puede estar vacío."));
+    }
+    if name == ALL_STORES {
+        return Err(AppError::invalid_field("name", format!("'{}' es un nombre reservado.", ALL_STORES)));
+    }
+    let nif = non_empty(input.nif).map(|n| validate_nif("nif", &n)).transpose()?;
+    let (default_category, _) =
+        categories::resolve_transaction_category(conn, non_empty(input.default_category), None).map_err(|e| match e {
+            AppError::Validation { message, .. } => AppError::invalid_field("default_category", message),
+            other => other,
+        })?;
+    Ok(StoreInput { name, address: non_empty(input.address), nif, default_category })
+}
+
+/// Ingresos, gastos, resultado y número de transacciones de una tienda, en la moneda base.
+#[derive(Debug, Clone, Serialize)]
+pub struct StoreBalance {
+    pub store_name: String,
+    #[serde(flatten)]
+    pub totals: GroupTotals,
+}
+
+/// Balance por tienda del periodo actual o de todo el historial.
+#[derive(Debug, Clone, Serialize)]
+pub struct StoreBalances {
+    pub base_currency: String,
+    pub period: Option<Period>,
+    /// Límites `[inicio, fin)` del periodo; `None` sin periodo.
+    pub period_start: Option<u64>,
+    pub period_end: Option<u64>,
+    pub stores: Vec<StoreBalance>,
+}
+
+/// Agrupa por tienda los totales por día de `aggregates` del periodo que contiene
+/// `now`, o todos si `period` es `None`. Las tiendas se devuelven por nombre.
+pub fn build_store_balances(
+    aggregates: &Aggregates,
+    rates: &RateTable,
+    period: Option<Period>,
+    now: u64,
+) -> Result<StoreBalances, AppError> {
+    let days = period.map(|p| {
+        let start = p.start_date(periods::local_date(now));
+        (start, p.next_start_date(start))
+    });
+    let bounds = days.map(|(start, end)| (periods::local_midnight_timestamp(start), periods::local_midnight_timestamp(end)));
+    let mut by_store: BTreeMap<String, GroupTotals> = BTreeMap::new();
+    for (_, key, bucket) in aggregates.range(days.map(|(start, _)| start), days.map(|(_, end)| end)) {
+        let amount = rates.to_base(bucket.amount, &key.currency)?;
+        let totals = by_store.entry(key.store_name.clone()).or_default();
+        match &key.transfer_store {
+            Some(destination) if key.transaction_type == TransactionType::Transferencia => {
+                totals.add_transfers(amount, bucket.count, false);
+                by_store.entry(destination.clone()).or_default().add_transfers(amount, bucket.count, true);
+            }
+            _ => totals.add_many(&key.transaction_type, amount, bucket.count),
+        }
+    }
+    Ok(StoreBalances {
+        base_currency: rates.base_currency.clone(),
+        period,
+        period_start: bounds.map(|(start, _)| start),
+        period_end: bounds.map(|(_, end)| end),
+        stores: by_store
+            .into_iter()
+            .map(|(store_name, totals)| StoreBalance { store_name, totals })
+            .collect(),
+    })
+}
+
+// --- Comandos Tauri ---
+
+/// Comando para registrar una tienda nueva, aunque aún no tenga transacciones.
+#[tauri::command]
+pub async fn create_store_command(
+    state: State<'_, AppState>,
+    app: AppHandle,
+    store: StoreInput,
+) -> Result<Store, AppError> {
+    debug!("Received create_store_command: {:?}", store);
+    let db = state.db().await?;
+    let store = resolve_input(db.connection(), store)?;
+    if store_exists(db.connection(), &store.name)? {
+        error!("Store '{}' already exists.", store.name);
+        return Err(AppError::Conflict(format!("Ya existe una tienda llamada '{}'.", store.name)));
+    }
+
+    let now = periods::now_timestamp() as i64;
+    db.connection()
+        .execute(
+            "INSERT INTO stores (name, address, nif, default_category, created_at, updated_at) \
+             VALUES (?1, ?2, ?3, ?4, ?5, ?5)",
+            params![store.name, store.address, store.nif, store.default_category, now],
+        )
+        .map_err(db_error)?;
+    let created = get_store(db.connection(), &store.name)?
+        .ok_or_else(|| AppError::Internal("La tienda creada no se encuentra.".to_string()))?;
+    audit::record(db.connection(), "create_store_command", Some(&created.name), None, audit::snapshot(&created));
+    events::emit(&app, events::STORE_UPDATED_EVENT, &created);
+    info!("Store '{}' created.", created.name);
+    Ok(created)
+}
+
+/// Comando para cambiar los datos de una tienda. Para cambiarle el nombre se usa
+/// `rename_store_command`. Las tiendas que solo existían en transacciones quedan
+/// registradas con los datos indicados.
+#[tauri::command]
+pub async fn update_store_command(
+    state: State<'_, AppState>,
+    app: AppHandle,
+    store: StoreInput,
+) -> Result<Store, AppError> {
+    debug!("Received update_store_command: {:?}", store);
+    let db = state.db().await?;
+    let store = resolve_input(db.connection(), store)?;
+    if !store_exists(db.connection(), &store.name)? {
+        error!("Store '{}' not found for update.", store.name);
+        return Err(AppError::NotFound(format!("Tienda '{}' no encontrada.", store.name)));
+    }
+
+    let before = get_store(db.connection(), &store.name)?;
+    let now = periods::now_timestamp() as i64;
+    db.connection()
+        .execute(
+            "INSERT INTO stores (name, address, nif, default_category, created_at, updated_at) \
+             VALUES (?1, ?2, ?3, ?4, ?5, ?5) \
+             ON CONFLICT(name) DO UPDATE SET address = excluded.address, nif = excluded.nif, \
+                 default_category = excluded.default_category, updated_at = excluded.updated_at",
+            params![store.name, store.address, store.nif, store.default_category, now],
+        )
+        .map_err(db_error)?;
+    let updated = get_store(db.connection(), &store.name)?
+        .ok_or_else(|| AppError::Internal("La tienda actualizada no se encuentra.".to_string()))?;
+    audit::record(
+        db.connection(),
+        "update_store_command",
+        Some(&updated.name),
+        before.as_ref().and_then(audit::snapshot),
+        audit::snapshot(&updated),
+    );
+    events::emit(&app, events::STORE_UPDATED_EVENT, &updated);
+    Ok(updated)
+}
+
+/// Resultado de `merge_stores_command`.
+#[derive(Debug, Serialize)]
+pub struct MergeStoresResult {
+    pub store: Store,
+    /// Transacciones reasignadas, incluidas las de la papelera.
+    pub reassigned: usize,
+}
+
+/// Comando para fusionar varias tiendas en `target_name` (por ejemplo, tras escribir
+/// mal un nombre). Todas sus transacciones, también las de la papelera, pasan a
+/// `target_name`, que conserva sus datos y completa los que le falten con los de las
+/// tiendas fusionadas. `target_name` puede ser una tienda nueva. Se deshace como un
+/// único paso.
+#[tauri::command]
+pub async fn merge_stores_command(
+    state: State<'_, AppState>,
+    app: AppHandle,
+    source_names: Vec<String>,
+    target_name: String,
+) -> Result<MergeStoresResult, AppError> {
+    debug!("Received merge_stores_command: {:?} -> '{}'", source_names, target_name);
+    let target_name = target_name.trim().to_owned();
+    if target_name.is_empty() {
+        return Err(AppError::invalid_field("target_name", "El nombre de tienda no puede estar vacío."));
+    }
+    if target_name == ALL_STORES {
+        return Err(AppError::invalid_field("target_name", format!("'{}' es un nombre reservado.", ALL_STORES)));
+    }
+    let mut sources: Vec<String> = Vec::new();
+    for name in source_names.iter().map(|n| n.trim()).filter(|n| !n.is_empty() && *n != target_name) {
+        if name == ALL_STORES {
+            return Err(AppError::invalid_field("source_names", format!("No se puede fusionar '{}'.", ALL_STORES)));
+        }
+        if !sources.iter().any(|s| s == name) {
+            sources.push(name.to_owned());
+        }
+    }
+    if sources.is_empty() {
+        return Err(AppError::invalid_field("source_names", "Indica al menos una tienda distinta de la de destino."));
+    }
+
+    let db = state.db().await?;
+    for source in &sources {
+        if !store_exists(db.connection(), source)? {
+            error!("Store '{}' not found for merge.", source);
+            return Err(AppError::NotFound(format!("Tienda '{}' no encontrada.", source)));
+        }
+    }
+    let before: Vec<Store> = sources
+        .iter()
+        .chain(std::iter::once(&target_name))
+        .map(|name| get_store(db.connection(), name))
+        .collect::<Result<Vec<_>, _>>()?
+        .into_iter()
+        .flatten()
+        .collect();
+    let trashed = db.list_deleted_transactions()?;
+    let now = periods::now_timestamp();
+
+    let tx = db.connection().unchecked_transaction().map_err(db_error)?;
+    let mut changes = Vec::new();
+    for source in &sources {
+        let affected = db
+            .list_transactions_by_store(source)?
+            .into_iter()
+            .chain(trashed.iter().filter(|t| &t.store_name == source).cloned());
+        for before in affected {
+            let mut after = before.clone();
+            after.store_name = target_name.clone();
+            after.updated_at = now;
+            db.update_transaction(&after)?;
+            changes.push(Change::Update { before, after });
+        }
+    }
+    let merged = merge_store_records(db.connection(), &sources, &target_name)?;
+    tx.commit().map_err(db_error)?;
+
+    let store = match merged {
+        Some(store) => store,
+        None => list_stores(db.connection())?
+            .into_iter()
+            .find(|s| s.name == target_name)
+            .unwrap_or(Store {
+                name: target_name.clone(),
+                address: None,
+                nif: None,
+                default_category: None,
+                transaction_count: 0,
+            }),
+    };
+    audit::record(
+        db.connection(),
+        "merge_stores_command",
+        Some(&target_name),
+        audit::snapshot(&before),
+        audit::snapshot(&store),
+    );
+    audit::record_changes(db.connection(), "merge_stores_command", &changes);
+    events::emit_transaction_changes(&app, &changes);
+    events::emit(&app, events::STORE_MERGED_EVENT, events::StoresMerged {
+        source_names: sources.clone(),
+        target_name: target_name.clone(),
+    });
+    let reassigned = changes.len();
+    state.history.lock().unwrap().record(HistoryEntry::new("Fusionar tiendas", changes));
+    info!("Merged {} stores into '{}': {} transactions reassigned.", sources.len(), target_name, reassigned);
+    Ok(MergeStoresResult { store, reassigned })
+}
+
+/// Comando para obtener el balance de cada tienda (ingresos, gastos, resultado y
+/// número de transacciones) en el periodo actual, o en todo el historial si no se
+/// indica `period`.
+#[tauri::command]
+pub async fn get_store_balances_command(
+    state: State<'_, AppState>,
+    period: Option<Period>,
+) -> Result<StoreBalances, AppError> {
+    debug!("Received get_store_balances_command: {:?}", period);
+    let db = state.db().await?;
+    let rates = RateTable::load(&db)?;
+    aggregates::with_aggregates(&db, |a| build_store_balances(a, &rates, period, periods::now_timestamp()))?
+}
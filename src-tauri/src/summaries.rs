@@ -0,0 +1,213 @@
+// src-tauri/src/summaries.rs
+
+use chrono::{Duration, NaiveDate};
+use rusqlite::{params, Connection, OptionalExtension};
+use serde::Serialize;
+use std::collections::BTreeMap;
+use tauri::State;
+use log::{debug, error, info};
+
+use crate::ai::{self, AiFeature};
+use crate::ai_queue::{self, AiRequest};
+use crate::currencies::RateTable;
+use crate::error::AppError;
+use crate::periods::{self, Period};
+use crate::privacy::Redactor;
+use crate::prompts;
+use crate::reports::{self, GroupBy, GroupTotals};
+use crate::settings;
+use crate::storage::{db_error, SqliteStorage, TransactionRepository};
+use crate::{AppState, Transaction};
+
+/// Agregados de un mes y del anterior que se envían a la IA.
+#[derive(Debug, Clone, Serialize)]
+pub struct MonthContext {
+    pub month: String,
+    pub previous_month: String,
+    pub base_currency: String,
+    pub totals: GroupTotals,
+    pub previous_totals: GroupTotals,
+    /// Por categoría, con los totales del mes y del anterior.
+    pub by_category: BTreeMap<String, [GroupTotals; 2]>,
+    pub by_store: BTreeMap<String, [GroupTotals; 2]>,
+}
+
+/// Resumen narrativo de un mes generado por la IA.
+#[derive(Debug, Clone, Serialize)]
+pub struct MonthlySummary {
+    pub month: String,
+    pub summary: String,
+    pub generated_at: u64,
+    /// `true` si se devuelve el resumen guardado sin llamar a la API.
+    pub cached: bool,
+}
+
+/// Interpreta un mes `AAAA-MM` y devuelve su primer día.
+pub fn parse_month(month: &str) -> Result<NaiveDate, AppError> {
+    NaiveDate::parse_from_str(&format!("{}-01", month.trim()), "%Y-%m-%d").map_err(|_| {
+        error!("Invalid month: '{}'", month);
+        AppError::invalid_field("month", "El mes debe tener el formato AAAA-MM.")
+    })
+}
+
+/// Calcula los agregados del mes que empieza en `start` y del anterior.
+pub fn build_month_context(
+    transactions: &[Transaction],
+    rates: &RateTable,
+    start: NaiveDate,
+) -> Result<MonthContext, AppError> {
+    let previous_start = Period::Mensual.start_date(start - Duration::days(1));
+    let bounds = [
+        (
+            periods::local_midnight_timestamp(start),
+            periods::local_midnight_timestamp(Period::Mensual.next_start_date(start)),
+        ),
+        (periods::local_midnight_timestamp(previous_start), periods::local_midnight_timestamp(start)),
+    ];
+
+    let mut totals = [GroupTotals::default(), GroupTotals::default()];
+    let mut by_category: BTreeMap<String, [GroupTotals; 2]> = BTreeMap::new();
+    let mut by_store: BTreeMap<String, [GroupTotals; 2]> = BTreeMap::new();
+    for transaction in transactions {
+        let Some(index) = bounds.iter().position(|(from, to)| transaction.timestamp >= *from && transaction.timestamp < *to)
+        else {
+            continue;
+        };
+        let amount = rates.to_base(transaction.amount, &transaction.currency)?;
+        totals[index].add(&transaction.transaction_type, amount);
+        by_category
+            .entry(reports::group_key(transaction, GroupBy::Category))
+            .or_default()[index]
+            .add(&transaction.transaction_type, amount);
+        by_store
+            .entry(transaction.store_name.clone())
+            .or_default()[index]
+            .add(&transaction.transaction_type, amount);
+    }
+
+    let [totals, previous_totals] = totals;
+    Ok(MonthContext {
+        month: start.format("%Y-%m").to_string(),
+        previous_month: previous_start.format("%Y-%m").to_string(),
+        base_currency: rates.base_currency.clone(),
+        totals,
+        previous_totals,
+        by_category,
+        by_store,
+    })
+}
+
+/// Carga las transacciones y los tipos de cambio y calcula los agregados del mes que
+/// empieza en `start`.
+pub fn load_month_context(db: &SqliteStorage, start: NaiveDate) -> Result<MonthContext, AppError> {
+    let rates = RateTable::load(db)?;
+    build_month_context(&db.list_transactions()?, &rates, start)
+}
+
+fn context_to_json(context: &MonthContext) -> Result<String, AppError> {
+    serde_json::to_string_pretty(context)
+        .map_err(|e| AppError::Internal(format!("Error al preparar el contexto para la IA: {}", e)))
+}
+
+/// Prompt del resumen de `context`, con las tiendas ocultadas por `redactor`.
+pub fn build_summary_prompt(conn: &Connection, context: &MonthContext, redactor: &mut Redactor) -> Result<String, AppError> {
+    let mut redacted = context.clone();
+    redacted.by_store = context.by_store.iter().map(|(store, totals)| (redactor.store(store), totals.clone())).collect();
+    prompts::render_prompt(
+        conn,
+        prompts::MONTHLY_SUMMARY,
+        &[
+            ("month", &context.month),
+            ("previous_month", &context.previous_month),
+            ("currency", &context.base_currency),
+            ("context_json", &context_to_json(&redacted)?),
+        ],
+    )
+}
+
+/// Resumen guardado del mes si se generó con los mismos datos.
+fn cached_summary(conn: &Connection, month: &str, context_json: &str) -> Result<Option<MonthlySummary>, AppError> {
+    conn.query_row(
+        "SELECT summary, generated_at FROM ai_summaries WHERE month = ?1 AND context = ?2",
+        params![month, context_json],
+        |row| {
+            Ok(MonthlySummary {
+                month: month.to_owned(),
+                summary: row.get(0)?,
+                generated_at: row.get::<_, i64>(1)? as u64,
+                cached: true,
+            })
+        },
+    )
+    .optional()
+    .map_err(db_error)
+}
+
+fn save_summary(conn: &Connection, summary: &MonthlySummary, context_json: &str) -> Result<(), AppError> {
+    conn.execute(
+        "INSERT OR REPLACE INTO ai_summaries (month, context, summary, generated_at) VALUES (?1, ?2, ?3, ?4)",
+        params![summary.month, context_json, summary.summary, summary.generated_at as i64],
+    )
+    .map_err(db_error)?;
+    Ok(())
+}
+
+/// Resumen narrativo del mes (`AAAA-MM`) generado por la IA. Los agregados se
+/// calculan en Rust y el resumen se guarda en la base de datos: mientras los datos
+/// del mes no cambien, se devuelve el guardado sin llamar a la API. Con `refresh` se
+/// genera de nuevo igualmente, aunque mientras no caduque la respuesta puede salir de
+/// la caché de la IA (ver `ai_cache`).
+pub async fn generate_monthly_summary(state: &AppState, month: &str, refresh: bool) -> Result<MonthlySummary, AppError> {
+    let start = parse_month(month)?;
+    if start > periods::today() {
+        return Err(AppError::invalid_field("month", "El mes no puede ser futuro."));
+    }
+
+    // No retenemos la base de datos mientras esperamos a la IA.
+    // El resumen guardado se identifica por los datos reales, no por los ocultados.
+    let (context, context_json, prompt, redactor, client) = {
+        let db = state.db().await?;
+        let context = load_month_context(&db, start)?;
+        let context_json = context_to_json(&context)?;
+        if !refresh {
+            if let Some(summary) = cached_summary(db.connection(), &context.month, &context_json)? {
+                debug!("Returning cached summary for {}.", context.month);
+                return Ok(summary);
+            }
+        }
+        let settings = settings::load_settings(&db);
+        let mut redactor = Redactor::new(db.connection(), &settings)?;
+        let prompt = build_summary_prompt(db.connection(), &context, &mut redactor)?;
+        (context, context_json, prompt, redactor, settings.ai_client())
+    };
+
+    let text = ai::generate_text(state, &client, &prompt).await?;
+    let summary = MonthlySummary {
+        month: context.month,
+        summary: redactor.restore(text.trim()),
+        generated_at: periods::now_timestamp(),
+        cached: false,
+    };
+    save_summary(state.db().await?.connection(), &summary, &context_json)?;
+    info!("Monthly summary for {} generated.", summary.month);
+    Ok(summary)
+}
+
+// --- Comandos Tauri ---
+
+/// Comando para obtener el resumen narrativo de un mes (`AAAA-MM`); ver
+/// `generate_monthly_summary`. Sin conexión, la petición se encola y el comando
+/// devuelve `AppError::Queued`.
+#[tauri::command]
+pub async fn generate_ai_monthly_summary_command(
+    state: State<'_, AppState>,
+    month: String,
+    refresh: Option<bool>,
+) -> Result<MonthlySummary, AppError> {
+    info!("Received generate_ai_monthly_summary_command: {}", month);
+    ai::ensure_enabled(&settings::load_settings(&state.db().await?), Some(AiFeature::Insights))?;
+    let request = AiRequest::MonthlySummary { month: month.trim().to_owned() };
+    ai_queue::ensure_online(&state, &request).await?;
+    let result = generate_monthly_summary(&state, &month, refresh.unwrap_or(false)).await;
+    ai_queue::queue_if_offline(&state, request, result).await
+}
@@ -0,0 +1,593 @@
+// src-tauri/src/sync.rs
+
+use chrono::{DateTime, Utc};
+use hmac::{Hmac, Mac};
+use reqwest::header::{AUTHORIZATION, ETAG, IF_MATCH, IF_NONE_MATCH};
+use reqwest::{Client, Method, RequestBuilder, StatusCode, Url};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Duration;
+use tauri::{AppHandle, Manager, State};
+use log::{debug, error, info, warn};
+
+use crate::archive::{self, MergeStrategy, WorkspaceArchive};
+use crate::audit;
+use crate::encryption::{self, DataKey};
+use crate::error::AppError;
+use crate::events;
+use crate::keychain;
+use crate::merge;
+use crate::periods;
+use crate::settings::{self, Settings};
+use crate::storage::SqliteStorage;
+use crate::AppState;
+
+/// Evento con el estado de la sincronización. Carga: `SyncStatus`.
+pub const SYNC_STATUS_EVENT: &str = "sync://status";
+
+/// Clave de `app_settings` con el estado local de la sincronización.
+const SYNC_STATE_KEY: &str = "sync_state";
+/// Clave de `app_settings` con el identificador de este equipo.
+const DEVICE_ID_KEY: &str = "device_id";
+/// Usuario de WebDAV o Access Key ID de S3.
+const USERNAME_SECRET: &str = "sync_username";
+/// Contraseña de WebDAV o Secret Access Key de S3.
+const PASSWORD_SECRET: &str = "sync_password";
+/// Contraseña con la que se cifra la copia remota. Debe ser la misma en todos los equipos.
+const PASSPHRASE_SECRET: &str = "sync_passphrase";
+/// Extensión del objeto remoto, que se llama como el espacio de trabajo.
+const REMOTE_EXTENSION: &str = "ciasync";
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(120);
+
+/// `true` mientras hay una sincronización en curso, para no lanzar dos a la vez.
+static SYNCING: AtomicBool = AtomicBool::new(false);
+
+/// Servicio en el que se guarda la copia sincronizada.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SyncProvider {
+    Disabled,
+    #[serde(rename = "webdav")]
+    WebDav,
+    /// Amazon S3 o un servicio compatible (MinIO, Backblaze B2, Cloudflare R2...).
+    S3,
+}
+
+/// Qué hizo una sincronización.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SyncOutcome {
+    /// No había cambios en ningún lado.
+    UpToDate,
+    /// Se subieron los datos locales.
+    Pushed,
+    /// Se descargaron los datos remotos y sustituyeron a los locales.
+    Pulled,
+    /// Ambos lados cambiaron: se combinaron y se subió el resultado.
+    Merged,
+}
+
+/// Resultado de `sync_now_command`.
+#[derive(Debug, Clone, Serialize)]
+pub struct SyncReport {
+    pub outcome: SyncOutcome,
+    /// Transacciones que cambiaron en ambos lados y quedaron pendientes de resolver.
+    pub conflicts: usize,
+    /// Revisión remota tras sincronizar.
+    pub revision: u64,
+    pub synced_at: u64,
+}
+
+/// Estado que se emite en `SYNC_STATUS_EVENT` y devuelve `get_sync_status_command`.
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "state", rename_all = "snake_case")]
+pub enum SyncStatus {
+    Disabled,
+    Idle { revision: u64, last_sync_at: Option<u64> },
+    Syncing,
+    Synced(SyncReport),
+    Failed { message: String },
+}
+
+/// Lo que este equipo sabe de la copia remota desde la última sincronización.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+struct SyncState {
+    /// Revisión remota de la última sincronización.
+    revision: u64,
+    /// Huella de los datos locales tras la última sincronización.
+    content_hash: String,
+    etag: Option<String>,
+    last_sync_at: Option<u64>,
+}
+
+/// Contenido de la copia remota antes de cifrarlo. La revisión aumenta en cada
+/// subida, de modo que un equipo sabe que el remoto cambió si es mayor que la suya.
+#[derive(Debug, Serialize, Deserialize)]
+struct RemoteEnvelope {
+    revision: u64,
+    /// Equipo que subió la copia.
+    device_id: String,
+    content_hash: String,
+    archive: WorkspaceArchive,
+}
+
+/// Objeto remoto descargado, aún cifrado.
+struct RemoteObject {
+    data: Vec<u8>,
+    etag: Option<String>,
+}
+
+/// Condición de la subida, para no pisar una copia que otro equipo subió mientras.
+enum Precondition {
+    /// El objeto no debe existir.
+    Absent,
+    /// El objeto debe seguir teniendo esta ETag.
+    Matches(String),
+    /// El servidor no dio ETag: se sube sin condición.
+    Unconditional,
+}
+
+/// Destino configurado, con sus credenciales del llavero.
+enum Remote {
+    WebDav { url: String, username: Option<String>, password: Option<String> },
+    S3 { endpoint: Url, bucket: String, region: String, object: String, access_key: String, secret_key: String },
+}
+
+/// Comprueba los ajustes de sincronización (ya sin espacios sobrantes).
+pub fn validate_settings(settings: &Settings) -> Result<(), AppError> {
+    if settings.sync_provider == SyncProvider::Disabled {
+        return Ok(());
+    }
+    let url = &settings.sync_url;
+    if !(url.starts_with("http://") || url.starts_with("https://")) || Url::parse(url).is_err() {
+        error!("Invalid sync URL: '{}'", url);
+        return Err(AppError::invalid_field("sync_url", "La dirección de sincronización debe empezar por http:// o https://."));
+    }
+    if settings.sync_provider == SyncProvider::S3 {
+        let bucket = &settings.sync_bucket;
+        if bucket.len() < 3
+            || bucket.len() > 63
+            || !bucket.chars().all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '-' || c == '.')
+        {
+            return Err(AppError::invalid_field(
+                "sync_bucket",
+                "El bucket debe tener entre 3 y 63 letras minúsculas, números, puntos o guiones.",
+            ));
+        }
+        if settings.sync_region.is_empty() {
+            return Err(AppError::invalid_field("sync_region", "Indica la región del bucket."));
+        }
+    }
+    Ok(())
+}
+
+pub fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+fn sha256_hex(data: &[u8]) -> String {
+    hex(&Sha256::digest(data))
+}
+
+fn hmac_sha256(key: &[u8], data: &str) -> Vec<u8> {
+    let mut mac = Hmac::<Sha256>::new_from_slice(key).expect("HMAC admite claves de cualquier longitud");
+    mac.update(data.as_bytes());
+    mac.finalize().into_bytes().to_vec()
+}
+
+/// Huella del contenido del archivo, sin la fecha de exportación, para saber si los
+/// datos locales cambiaron desde la última sincronización.
+fn content_hash(archive: &mut WorkspaceArchive) -> Result<String, AppError> {
+    let exported_at = std::mem::replace(&mut archive.exported_at, 0);
+    let json = serde_json::to_vec(archive);
+    archive.exported_at = exported_at;
+    let json = json.map_err(|e| AppError::Internal(format!("Error al preparar la sincronización: {}", e)))?;
+    Ok(sha256_hex(&json))
+}
+
+fn load_state(db: &SqliteStorage) -> SyncState {
+    match db.get_setting(SYNC_STATE_KEY) {
+        Ok(Some(json)) => serde_json::from_str(&json).unwrap_or_else(|e| {
+            warn!("Invalid sync state, starting over: {}", e);
+            SyncState::default()
+        }),
+        Ok(None) => SyncState::default(),
+        Err(e) => {
+            warn!("Could not read sync state: {}", e);
+            SyncState::default()
+        }
+    }
+}
+
+fn save_state(db: &SqliteStorage, state: &SyncState) -> Result<(), AppError> {
+    let json = serde_json::to_string(state)
+        .map_err(|e| AppError::Internal(format!("Error al guardar el estado de la sincronización: {}", e)))?;
+    db.set_setting(SYNC_STATE_KEY, &json)
+}
+
+/// Identificador de este equipo en el espacio de trabajo activo. Lo crea la migración
+/// v26 y lo usa el trigger que anota quién cambió cada transacción.
+pub fn device_id(db: &SqliteStorage) -> Result<String, AppError> {
+    if let Some(id) = db.get_setting(DEVICE_ID_KEY)?.filter(|id| !id.is_empty()) {
+        return Ok(id);
+    }
+    let id = uuid::Uuid::new_v4().simple().to_string();
+    db.set_setting(DEVICE_ID_KEY, &id)?;
+    Ok(id)
+}
+
+fn required_secret(name: &str, message: &str) -> Result<String, AppError> {
+    keychain::get_secret(name)?.ok_or_else(|| AppError::validation(message))
+}
+
+impl Remote {
+    fn from_settings(settings: &Settings, workspace_id: &str) -> Result<Self, AppError> {
+        let object = format!("{}.{}", workspace_id, REMOTE_EXTENSION);
+        match settings.sync_provider {
+            SyncProvider::Disabled => Err(AppError::validation("La sincronización no está configurada.")),
+            SyncProvider::WebDav => Ok(Remote::WebDav {
+                url: format!("{}/{}", settings.sync_url, object),
+                username: keychain::get_secret(USERNAME_SECRET)?,
+                password: keychain::get_secret(PASSWORD_SECRET)?,
+            }),
+            SyncProvider::S3 => Ok(Remote::S3 {
+                endpoint: Url::parse(&settings.sync_url)
+                    .map_err(|e| AppError::invalid_field("sync_url", format!("Dirección no válida: {}", e)))?,
+                bucket: settings.sync_bucket.clone(),
+                region: settings.sync_region.clone(),
+                object,
+                access_key: required_secret(USERNAME_SECRET, "Falta la Access Key ID de S3.")?,
+                secret_key: required_secret(PASSWORD_SECRET, "Falta la Secret Access Key de S3.")?,
+            }),
+        }
+    }
+
+    /// Prepara la petición. Las de S3 van firmadas con AWS Signature V4 y estilo de
+    /// ruta (`endpoint/bucket/objeto`), que aceptan todos los servicios compatibles.
+    fn request(&self, client: &Client, method: Method, body: Vec<u8>) -> Result<RequestBuilder, AppError> {
+        match self {
+            Remote::WebDav { url, username, password } => {
+                let mut request = client.request(method, url).body(body);
+                if let Some(username) = username {
+                    request = request.basic_auth(username, password.as_deref());
+                }
+                Ok(request)
+            }
+            Remote::S3 { endpoint, bucket, region, object, access_key, secret_key } => {
+                let host = match (endpoint.host_str(), endpoint.port()) {
+                    (Some(host), Some(port)) => format!("{}:{}", host, port),
+                    (Some(host), None) => host.to_string(),
+                    (None, _) => return Err(AppError::invalid_field("sync_url", "La dirección de S3 no tiene servidor.")),
+                };
+                let path = format!("/{}/{}", bucket, object);
+                let now: DateTime<Utc> = Utc::now();
+                let amz_date = now.format("%Y%m%dT%H%M%SZ").to_string();
+                let scope = format!("{}/{}/s3/aws4_request", now.format("%Y%m%d"), region);
+                let payload_hash = sha256_hex(&body);
+
+                let signed_headers = "host;x-amz-content-sha256;x-amz-date";
+                let canonical_request = format!(
+                    "{}\n{}\n\nhost:{}\nx-amz-content-sha256:{}\nx-amz-date:{}\n\n{}\n{}",
+                    method, path, host, payload_hash, amz_date, signed_headers, payload_hash
+                );
+                let string_to_sign =
+                    format!("AWS4-HMAC-SHA256\n{}\n{}\n{}", amz_date, scope, sha256_hex(canonical_request.as_bytes()));
+                let mut key = hmac_sha256(format!("AWS4{}", secret_key).as_bytes(), &now.format("%Y%m%d").to_string());
+                for part in [region.as_str(), "s3", "aws4_request"] {
+                    key = hmac_sha256(&key, part);
+                }
+                let signature = hex(&hmac_sha256(&key, &string_to_sign));
+
+                let url = endpoint
+                    .join(&path)
+                    .map_err(|e| AppError::invalid_field("sync_url", format!("Dirección no válida: {}", e)))?;
+                Ok(client
+                    .request(method, url)
+                    .header("x-amz-date", amz_date)
+                    .header("x-amz-content-sha256", payload_hash)
+                    .header(
+                        AUTHORIZATION,
+                        format!(
+                            "AWS4-HMAC-SHA256 Credential={}/{}, SignedHeaders={}, Signature={}",
+                            access_key, scope, signed_headers, signature
+                        ),
+                    )
+                    .body(body))
+            }
+        }
+    }
+
+    async fn get(&self, client: &Client) -> Result<Option<RemoteObject>, AppError> {
+        let response = self.request(client, Method::GET, Vec::new())?.send().await.map_err(network_error)?;
+        match response.status() {
+            StatusCode::NOT_FOUND => Ok(None),
+            status if status.is_success() => {
+                let etag = header_etag(&response);
+                let data = response.bytes().await.map_err(network_error)?.to_vec();
+                Ok(Some(RemoteObject { data, etag }))
+            }
+            status => Err(status_error(status)),
+        }
+    }
+
+    /// Sube la copia y devuelve su nueva ETag si el servidor la indica.
+    async fn put(&self, client: &Client, data: Vec<u8>, precondition: Precondition) -> Result<Option<String>, AppError> {
+        let mut request = self.request(client, Method::PUT, data)?;
+        request = match precondition {
+            Precondition::Absent => request.header(IF_NONE_MATCH, "*"),
+            Precondition::Matches(etag) => request.header(IF_MATCH, etag),
+            Precondition::Unconditional => request,
+        };
+        let response = request.send().await.map_err(network_error)?;
+        match response.status() {
+            StatusCode::PRECONDITION_FAILED => Err(AppError::Conflict(
+                "Otro equipo sincronizó a la vez. Vuelve a sincronizar para combinar sus cambios.".to_string(),
+            )),
+            status if status.is_success() => Ok(header_etag(&response)),
+            status => Err(status_error(status)),
+        }
+    }
+}
+
+fn header_etag(response: &reqwest::Response) -> Option<String> {
+    response.headers().get(ETAG).and_then(|v| v.to_str().ok()).map(str::to_owned)
+}
+
+fn network_error(e: reqwest::Error) -> AppError {
+    error!("Sync request failed: {}", e);
+    AppError::Network(format!("No se pudo conectar con el servicio de sincronización: {}", e))
+}
+
+fn status_error(status: StatusCode) -> AppError {
+    error!("Sync server answered {}", status);
+    match status {
+        StatusCode::UNAUTHORIZED | StatusCode::FORBIDDEN => {
+            AppError::validation("El servicio de sincronización rechazó las credenciales.")
+        }
+        _ => AppError::Network(format!("El servicio de sincronización respondió con el error {}.", status)),
+    }
+}
+
+fn encode_envelope(envelope: &RemoteEnvelope, passphrase: &str) -> Result<Vec<u8>, AppError> {
+    let json = serde_json::to_vec(envelope)
+        .map_err(|e| AppError::Internal(format!("Error al preparar la sincronización: {}", e)))?;
+    DataKey::generate(passphrase)?.encrypt(&json)
+}
+
+fn decode_envelope(data: &[u8], passphrase: &str) -> Result<RemoteEnvelope, AppError> {
+    let (_, json) = encryption::unlock(passphrase, data)?;
+    let envelope: RemoteEnvelope = serde_json::from_slice(&json)
+        .map_err(|e| AppError::validation(format!("La copia remota no es válida: {}", e)))?;
+    archive::validate_archive(&envelope.archive)?;
+    Ok(envelope)
+}
+
+/// Sincroniza el espacio de trabajo activo con la copia remota.
+///
+/// Si solo cambiaron los datos locales se suben, y si solo cambió el remoto se
+/// descargan y sustituyen a los locales. Si cambiaron ambos se combinan: se añade lo
+/// que falta en cada lado, cada transacción se resuelve con `merge::apply_incoming`
+/// y el resultado se sube. Las transacciones que cambiaron en los dos lados quedan
+/// pendientes en `merge::resolve_conflicts_command`. La base de datos no se bloquea
+/// durante la red.
+async fn sync_workspace(app: &AppHandle) -> Result<SyncReport, AppError> {
+    let state = app.state::<AppState>();
+    let workspace_id = state.workspace.lock().unwrap().id.clone();
+    let passphrase = required_secret(PASSPHRASE_SECRET, "Falta la contraseña de cifrado de la sincronización.")?;
+
+    let db = state.db().await?;
+    let remote = Remote::from_settings(&settings::load_settings(&db), &workspace_id)?;
+    let own_device = device_id(&db)?;
+    let mut sync_state = load_state(&db);
+    let mut local = archive::build_archive(&db)?;
+    let local_hash = content_hash(&mut local)?;
+    drop(db);
+
+    let client = Client::builder()
+        .timeout(REQUEST_TIMEOUT)
+        .build()
+        .map_err(|e| AppError::Internal(format!("No se pudo crear el cliente HTTP: {}", e)))?;
+    let remote_object = remote.get(&client).await?;
+    let envelope = match &remote_object {
+        Some(object) => Some(decode_envelope(&object.data, &passphrase)?),
+        None => None,
+    };
+
+    let local_changed = local_hash != sync_state.content_hash;
+    let outcome = match &envelope {
+        None => SyncOutcome::Pushed,
+        Some(theirs) if theirs.content_hash == local_hash => SyncOutcome::UpToDate,
+        Some(theirs) => match (local_changed, theirs.revision > sync_state.revision) {
+            (false, false) => SyncOutcome::UpToDate,
+            (true, false) => SyncOutcome::Pushed,
+            (false, true) => SyncOutcome::Pulled,
+            (true, true) => SyncOutcome::Merged,
+        },
+    };
+    let remote_etag = remote_object.and_then(|o| o.etag);
+    let now = periods::now_timestamp();
+    let mut conflicts = 0;
+
+    let (to_push, push_hash) = match outcome {
+        SyncOutcome::UpToDate => {
+            let db = state.db().await?;
+            if let Some(theirs) = &envelope {
+                sync_state.revision = theirs.revision;
+            }
+            sync_state.content_hash = local_hash;
+            sync_state.etag = remote_etag.clone();
+            sync_state.last_sync_at = Some(now);
+            save_state(&db, &sync_state)?;
+            debug!("Workspace already in sync at revision {}.", sync_state.revision);
+            (None, String::new())
+        }
+        SyncOutcome::Pulled => {
+            let envelope = envelope.as_ref().expect("solo se descarga si hay copia remota");
+            let db = state.db().await?;
+            let summary = archive::apply_archive(&state, &db, app, &envelope.archive, MergeStrategy::Replace)?;
+            audit::record(
+                db.connection(),
+                "sync_now_command",
+                None,
+                None,
+                Some(serde_json::json!({ "pulled_revision": envelope.revision, "from_device": envelope.device_id })),
+            );
+            let mut pulled = archive::build_archive(&db)?;
+            sync_state.content_hash = content_hash(&mut pulled)?;
+            sync_state.revision = envelope.revision;
+            sync_state.etag = remote_etag.clone();
+            sync_state.last_sync_at = Some(now);
+            save_state(&db, &sync_state)?;
+            info!("Pulled sync revision {}: {:?}", envelope.revision, summary);
+            (None, String::new())
+        }
+        SyncOutcome::Pushed => (Some(local), local_hash),
+        SyncOutcome::Merged => {
+            let envelope = envelope.as_ref().expect("solo se combina si hay copia remota");
+            info!("Both sides changed since revision {}; merging revision {} from {}.", sync_state.revision, envelope.revision, envelope.device_id);
+            let db = state.db().await?;
+            // `Skip` añade lo que solo existe en el remoto sin tocar lo local; las
+            // transacciones que existen en ambos lados las decide `merge`.
+            archive::apply_archive(&state, &db, app, &envelope.archive, MergeStrategy::Skip)?;
+            let summary = merge::apply_incoming(
+                &db,
+                "cloud",
+                &own_device,
+                sync_state.last_sync_at.unwrap_or(0),
+                &envelope.archive.transactions,
+            )?;
+            conflicts = summary.conflicts;
+            audit::record(
+                db.connection(),
+                "sync_now_command",
+                None,
+                None,
+                Some(serde_json::json!({
+                    "merged_revision": envelope.revision,
+                    "from_device": envelope.device_id,
+                    "applied": summary.applied,
+                    "conflicts": summary.conflicts,
+                })),
+            );
+            events::emit_data_reloaded(app);
+            let mut merged = archive::build_archive(&db)?;
+            let merged_hash = content_hash(&mut merged)?;
+            (Some(merged), merged_hash)
+        }
+    };
+
+    if let Some(archive) = to_push {
+        let revision = envelope.as_ref().map_or(sync_state.revision, |theirs| theirs.revision.max(sync_state.revision)) + 1;
+        let precondition = match (&envelope, remote_etag) {
+            (None, _) => Precondition::Absent,
+            (Some(_), Some(etag)) => Precondition::Matches(etag),
+            (Some(_), None) => Precondition::Unconditional,
+        };
+        let data = encode_envelope(
+            &RemoteEnvelope { revision, device_id: own_device, content_hash: push_hash.clone(), archive },
+            &passphrase,
+        )?;
+        let etag = remote.put(&client, data, precondition).await?;
+        let db = state.db().await?;
+        sync_state.revision = revision;
+        sync_state.content_hash = push_hash;
+        sync_state.etag = etag;
+        sync_state.last_sync_at = Some(now);
+        save_state(&db, &sync_state)?;
+        info!("Pushed sync revision {}.", revision);
+    }
+
+    Ok(SyncReport { outcome, conflicts, revision: sync_state.revision, synced_at: now })
+}
+
+/// Sincroniza avisando al frontend del progreso. Si ya hay una sincronización en
+/// curso devuelve `AppError::Conflict`.
+async fn run_sync(app: &AppHandle) -> Result<SyncReport, AppError> {
+    if SYNCING.swap(true, Ordering::SeqCst) {
+        return Err(AppError::Conflict("Ya hay una sincronización en curso.".to_string()));
+    }
+    events::emit(app, SYNC_STATUS_EVENT, SyncStatus::Syncing);
+    let result = sync_workspace(app).await;
+    SYNCING.store(false, Ordering::SeqCst);
+    match &result {
+        Ok(report) => events::emit(app, SYNC_STATUS_EVENT, SyncStatus::Synced(report.clone())),
+        Err(e) => {
+            warn!("Sync failed: {}", e);
+            events::emit(app, SYNC_STATUS_EVENT, SyncStatus::Failed { message: e.to_string() });
+        }
+    }
+    result
+}
+
+/// Indica si toca una sincronización automática según el intervalo configurado.
+async fn sync_due(app: &AppHandle) -> Result<bool, AppError> {
+    let state = app.state::<AppState>();
+    if state.is_locked() || SYNCING.load(Ordering::SeqCst) {
+        return Ok(false);
+    }
+    let db = state.db().await?;
+    let settings = settings::load_settings(&db);
+    if settings.sync_provider == SyncProvider::Disabled || settings.sync_interval_minutes == 0 {
+        return Ok(false);
+    }
+    let last_sync_at = load_state(&db).last_sync_at.unwrap_or(0);
+    Ok(periods::now_timestamp().saturating_sub(last_sync_at) >= settings.sync_interval_minutes * 60)
+}
+
+/// Sincroniza si toca según el intervalo de los ajustes, o siempre con `force`. Es la
+/// tarea `sync` del planificador (ver `scheduler`).
+pub async fn run_scheduled_sync(app: &AppHandle, force: bool) -> Result<String, AppError> {
+    if !force && !sync_due(app).await? {
+        return Ok("Aún no tocaba sincronizar.".to_string());
+    }
+    let report = run_sync(app).await?;
+    Ok(format!("Sincronizado: revisión {}, {} conflictos.", report.revision, report.conflicts))
+}
+
+// --- Comandos Tauri ---
+
+/// Comando para guardar en el llavero las credenciales de la sincronización: usuario
+/// y contraseña de WebDAV (o Access Key ID y Secret Access Key de S3) y la contraseña
+/// con la que se cifra la copia remota, que debe ser la misma en todos los equipos.
+/// Un campo ausente no cambia; uno vacío borra el secreto guardado.
+#[tauri::command]
+pub async fn set_sync_credentials_command(
+    username: Option<String>,
+    password: Option<String>,
+    passphrase: Option<String>,
+) -> Result<(), AppError> {
+    debug!("Received set_sync_credentials_command.");
+    for (name, value) in [(USERNAME_SECRET, username), (PASSWORD_SECRET, password), (PASSPHRASE_SECRET, passphrase)] {
+        match value.map(|v| v.trim().to_owned()) {
+            Some(v) if v.is_empty() => keychain::delete_secret(name)?,
+            Some(v) => keychain::set_secret(name, &v)?,
+            None => {}
+        }
+    }
+    Ok(())
+}
+
+/// Comando para obtener el estado de la sincronización del espacio de trabajo activo.
+#[tauri::command]
+pub async fn get_sync_status_command(state: State<'_, AppState>) -> Result<SyncStatus, AppError> {
+    debug!("Received get_sync_status_command.");
+    if SYNCING.load(Ordering::SeqCst) {
+        return Ok(SyncStatus::Syncing);
+    }
+    let db = state.db().await?;
+    if settings::load_settings(&db).sync_provider == SyncProvider::Disabled {
+        return Ok(SyncStatus::Disabled);
+    }
+    let sync_state = load_state(&db);
+    Ok(SyncStatus::Idle { revision: sync_state.revision, last_sync_at: sync_state.last_sync_at })
+}
+
+/// Comando para sincronizar ahora el espacio de trabajo activo con la copia cifrada
+/// en WebDAV o S3. El progreso también se emite en `SYNC_STATUS_EVENT`.
+#[tauri::command]
+pub async fn sync_now_command(app: AppHandle) -> Result<SyncReport, AppError> {
+    info!("Received sync_now_command.");
+    run_sync(&app).await
+}
@@ -0,0 +1,156 @@
+// src-tauri/src/tags.rs
+
+use serde::Serialize;
+use tauri::{AppHandle, State};
+use log::{debug, error, info};
+
+use crate::audit;
+use crate::error::AppError;
+use crate::events::{self, ChangeAction};
+use crate::history::{Change, HistoryEntry};
+use crate::periods;
+use crate::storage::{db_error, SqliteStorage, TransactionRepository};
+use crate::AppState;
+
+/// Longitud máxima de una etiqueta, en caracteres.
+const MAX_TAG_LENGTH: usize = 40;
+
+/// Etiqueta en uso y cuántas transacciones activas la llevan.
+#[derive(Debug, Clone, Serialize)]
+pub struct TagInfo {
+    pub name: String,
+    pub transaction_count: usize,
+}
+
+/// Forma con la que se guardan las etiquetas: sin espacios alrededor y en minúsculas,
+/// para que `Deducible` y `deducible` sean la misma.
+pub fn normalize_tag(tag: &str) -> String {
+    tag.trim().to_lowercase()
+}
+
+fn validate_tag(field: &str, tag: &str) -> Result<String, AppError> {
+    let tag = normalize_tag(tag);
+    if tag.is_empty() {
+        return Err(AppError::invalid_field(field, "La etiqueta no puede estar vacía."));
+    }
+    if tag.chars().count() > MAX_TAG_LENGTH {
+        error!("Tag too long: '{}'", tag);
+        return Err(AppError::invalid_field(
+            field,
+            format!("Las etiquetas admiten como máximo {} caracteres.", MAX_TAG_LENGTH),
+        ));
+    }
+    Ok(tag)
+}
+
+/// Normaliza las etiquetas de una transacción: descarta las vacías y las repetidas
+/// y conserva el orden en que se indicaron.
+pub fn resolve_tags(tags: Vec<String>) -> Result<Vec<String>, AppError> {
+    let mut resolved: Vec<String> = Vec::new();
+    for tag in tags.iter().filter(|t| !t.trim().is_empty()) {
+        let tag = validate_tag("tags", tag)?;
+        if !resolved.contains(&tag) {
+            resolved.push(tag);
+        }
+    }
+    Ok(resolved)
+}
+
+/// Etiquetas usadas por las transacciones activas, en orden alfabético.
+pub fn list_tags(db: &SqliteStorage) -> Result<Vec<TagInfo>, AppError> {
+    let mut stmt = db
+        .connection()
+        .prepare(
+            "SELECT tag.value, COUNT(*) FROM transactions, json_each(transactions.tags) AS tag \
+             WHERE transactions.deleted_at IS NULL GROUP BY tag.value ORDER BY tag.value",
+        )
+        .map_err(db_error)?;
+    let rows = stmt
+        .query_map([], |row| {
+            Ok(TagInfo { name: row.get(0)?, transaction_count: row.get::<_, i64>(1)? as usize })
+        })
+        .map_err(db_error)?;
+    rows.collect::<rusqlite::Result<Vec<_>>>().map_err(db_error)
+}
+
+/// Cambia `tag` por `replacement` (o lo quita si es `None`) en todas las transacciones
+/// activas que lo llevan. Devuelve los cambios aplicados, para el historial.
+fn replace_tag(db: &SqliteStorage, tag: &str, replacement: Option<&str>) -> Result<Vec<Change>, AppError> {
+    let affected = db.list_transactions_with_tag(tag)?;
+    let tx = db.connection().unchecked_transaction().map_err(db_error)?;
+    let mut changes = Vec::with_capacity(affected.len());
+    for before in affected {
+        let mut after = before.clone();
+        after.tags = before
+            .tags
+            .iter()
+            .filter_map(|t| if t == tag { replacement.map(str::to_owned) } else { Some(t.clone()) })
+            .collect();
+        after.tags = resolve_tags(after.tags)?;
+        after.updated_at = periods::now_timestamp();
+        db.update_transaction(&after)?;
+        changes.push(Change::Update { before, after });
+    }
+    tx.commit().map_err(db_error)?;
+    Ok(changes)
+}
+
+// --- Comandos Tauri ---
+
+/// Comando para obtener todas las etiquetas en uso con su número de transacciones.
+#[tauri::command]
+pub async fn get_all_tags_command(state: State<'_, AppState>) -> Result<Vec<TagInfo>, AppError> {
+    debug!("Received get_all_tags_command.");
+    list_tags(&state.db().await?)
+}
+
+/// Comando para renombrar una etiqueta en todas las transacciones. Si ya existía una
+/// etiqueta con el nuevo nombre, ambas se fusionan. Devuelve las transacciones afectadas.
+#[tauri::command]
+pub async fn rename_tag_command(
+    state: State<'_, AppState>,
+    app: AppHandle,
+    old_tag: String,
+    new_tag: String,
+) -> Result<usize, AppError> {
+    debug!("Received rename_tag_command: old='{}', new='{}'", old_tag, new_tag);
+    let old_tag = validate_tag("old_tag", &old_tag)?;
+    let new_tag = validate_tag("new_tag", &new_tag)?;
+    if old_tag == new_tag {
+        return Err(AppError::invalid_field("new_tag", "La nueva etiqueta es igual que la anterior."));
+    }
+
+    let db = state.db().await?;
+    let changes = replace_tag(&db, &old_tag, Some(&new_tag))?;
+    if changes.is_empty() {
+        return Err(AppError::NotFound(format!("Etiqueta '{}' no encontrada.", old_tag)));
+    }
+    let count = changes.len();
+    audit::record_changes(db.connection(), "rename_tag_command", &changes);
+    events::emit_transaction_changes(&app, &changes);
+    state.history.lock().unwrap().record(HistoryEntry::new("Renombrar etiqueta", changes));
+    events::emit_entity(&app, events::TAG_CHANGED_EVENT, ChangeAction::Updated, &new_tag);
+    info!("Tag '{}' renamed to '{}' in {} transactions.", old_tag, new_tag, count);
+    Ok(count)
+}
+
+/// Comando para quitar una etiqueta de todas las transacciones. Las transacciones se
+/// conservan. Devuelve cuántas se han modificado.
+#[tauri::command]
+pub async fn delete_tag_command(state: State<'_, AppState>, app: AppHandle, tag: String) -> Result<usize, AppError> {
+    debug!("Received delete_tag_command: '{}'", tag);
+    let tag = validate_tag("tag", &tag)?;
+
+    let db = state.db().await?;
+    let changes = replace_tag(&db, &tag, None)?;
+    if changes.is_empty() {
+        return Err(AppError::NotFound(format!("Etiqueta '{}' no encontrada.", tag)));
+    }
+    let count = changes.len();
+    audit::record_changes(db.connection(), "delete_tag_command", &changes);
+    events::emit_transaction_changes(&app, &changes);
+    state.history.lock().unwrap().record(HistoryEntry::new("Eliminar etiqueta", changes));
+    events::emit_entity(&app, events::TAG_CHANGED_EVENT, ChangeAction::Deleted, &tag);
+    info!("Tag '{}' removed from {} transactions.", tag, count);
+    Ok(count)
+}
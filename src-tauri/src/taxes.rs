@@ -0,0 +1,214 @@
+// src-tauri/src/taxes.rs
+
+use chrono::NaiveDate;
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use tauri::State;
+use log::{debug, error, info};
+
+use crate::ai::{self, AiFeature};
+use crate::currencies::RateTable;
+use crate::error::AppError;
+use crate::money::round_money;
+use crate::periods::{self, Period};
+use crate::prompts;
+use crate::settings;
+use crate::storage::{SqliteStorage, TransactionRepository};
+use crate::{AppState, Transaction, TransactionType};
+
+/// Diferencia máxima admitida entre la cuota indicada y la calculada a partir del tipo,
+/// para tolerar el redondeo de los tickets.
+const TAX_AMOUNT_TOLERANCE: Decimal = Decimal::from_parts(1, 0, 0, false, 2);
+
+/// Cuota de IVA incluida en un importe con IVA (`amount`) al tipo `rate`.
+pub fn tax_included(amount: Decimal, rate: Decimal) -> Decimal {
+    round_money(amount * rate / (Decimal::ONE_HUNDRED + rate))
+}
+
+/// Valida el IVA de una transacción de importe `amount` (IVA incluido). Si solo se
+/// indica el tipo, la cuota se calcula; si se indican ambos, deben cuadrar.
+pub fn resolve_tax(
+    amount: Decimal,
+    tax_rate: Option<Decimal>,
+    tax_amount: Option<Decimal>,
+) -> Result<(Option<Decimal>, Option<Decimal>), AppError> {
+    let rate = match (tax_rate, tax_amount) {
+        (None, None) => return Ok((None, None)),
+        (None, Some(_)) => {
+            error!("Tax amount provided without tax rate.");
+            return Err(AppError::invalid_field("tax_rate", "Indica el tipo de IVA de la cuota."));
+        }
+        (Some(rate), _) => rate,
+    };
+    if rate < Decimal::ZERO || rate > Decimal::ONE_HUNDRED {
+        error!("Invalid tax rate: {}", rate);
+        return Err(AppError::invalid_field("tax_rate", "El tipo de IVA debe estar entre 0 y 100."));
+    }
+
+    let expected = tax_included(amount, rate);
+    let tax_amount = match tax_amount {
+        Some(given) if given < Decimal::ZERO => {
+            return Err(AppError::invalid_field("tax_amount", "La cuota de IVA no puede ser negativa."));
+        }
+        Some(given) if (given - expected).abs() > TAX_AMOUNT_TOLERANCE => {
+            error!("Tax amount {} does not match rate {} on {} (expected {}).", given, rate, amount, expected);
+            return Err(AppError::invalid_field(
+                "tax_amount",
+                format!("La cuota de IVA no corresponde al {} %: debería ser {}.", rate.normalize(), expected),
+            ));
+        }
+        Some(given) => given,
+        None => expected,
+    };
+    Ok((Some(rate), Some(tax_amount)))
+}
+
+/// Trimestre natural (`quarter` de 1 a 4) de un año.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct Quarter {
+    pub year: i32,
+    pub quarter: u32,
+}
+
+impl Quarter {
+    /// Límites `[inicio, fin)` en segundos Unix, en hora local.
+    fn bounds(self) -> Result<(u64, u64), AppError> {
+        if !(1..=4).contains(&self.quarter) {
+            return Err(AppError::invalid_field("quarter", "El trimestre debe estar entre 1 y 4."));
+        }
+        let start = NaiveDate::from_ymd_opt(self.year, (self.quarter - 1) * 3 + 1, 1)
+            .ok_or_else(|| AppError::invalid_field("quarter", format!("Año no válido: {}.", self.year)))?;
+        let end = Period::Trimestral.next_start_date(start);
+        Ok((periods::local_midnight_timestamp(start), periods::local_midnight_timestamp(end)))
+    }
+}
+
+/// Base imponible y cuota acumuladas a un tipo de IVA, en la moneda base.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct TaxLine {
+    pub rate: Decimal,
+    pub base: Decimal,
+    pub tax: Decimal,
+    pub transaction_count: usize,
+}
+
+/// IVA repercutido (ingresos) o soportado (gastos), desglosado por tipo.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct TaxSide {
+    pub lines: Vec<TaxLine>,
+    pub base: Decimal,
+    pub tax: Decimal,
+}
+
+impl TaxSide {
+    fn from_lines(lines: BTreeMap<Decimal, TaxLine>) -> Self {
+        let lines: Vec<TaxLine> = lines.into_values().collect();
+        TaxSide {
+            base: lines.iter().map(|l| l.base).sum(),
+            tax: lines.iter().map(|l| l.tax).sum(),
+            lines,
+        }
+    }
+}
+
+/// Liquidación trimestral del IVA al estilo del modelo 303.
+#[derive(Debug, Clone, Serialize)]
+pub struct TaxReport {
+    pub quarter: Quarter,
+    pub period_start: u64,
+    pub period_end: u64,
+    pub base_currency: String,
+    /// IVA devengado en las ventas.
+    pub repercutido: TaxSide,
+    /// IVA deducible de las compras.
+    pub soportado: TaxSide,
+    /// Repercutido menos soportado: positivo a ingresar, negativo a compensar.
+    pub result: Decimal,
+    /// Transacciones del trimestre sin IVA registrado, que no cuentan en el informe.
+    pub untaxed_count: usize,
+}
+
+/// Calcula la liquidación de IVA del trimestre a partir de las transacciones.
+pub fn build_tax_report(transactions: &[Transaction], rates: &RateTable, quarter: Quarter) -> Result<TaxReport, AppError> {
+    let (period_start, period_end) = quarter.bounds()?;
+    let mut repercutido: BTreeMap<Decimal, TaxLine> = BTreeMap::new();
+    let mut soportado: BTreeMap<Decimal, TaxLine> = BTreeMap::new();
+    let mut untaxed_count = 0;
+
+    for transaction in transactions
+        .iter()
+        .filter(|t| t.timestamp >= period_start && t.timestamp < period_end)
+        .filter(|t| t.transaction_type != TransactionType::Transferencia)
+    {
+        let (Some(rate), Some(tax_amount)) = (transaction.tax_rate, transaction.tax_amount) else {
+            untaxed_count += 1;
+            continue;
+        };
+        let side = match transaction.transaction_type {
+            TransactionType::Ingreso => &mut repercutido,
+            TransactionType::Gasto => &mut soportado,
+            TransactionType::Transferencia => continue,
+        };
+        let rate = rate.normalize();
+        let line = side.entry(rate).or_insert_with(|| TaxLine { rate, ..TaxLine::default() });
+        line.base += round_money(rates.to_base(transaction.amount - tax_amount, &transaction.currency)?);
+        line.tax += round_money(rates.to_base(tax_amount, &transaction.currency)?);
+        line.transaction_count += 1;
+    }
+
+    let repercutido = TaxSide::from_lines(repercutido);
+    let soportado = TaxSide::from_lines(soportado);
+    Ok(TaxReport {
+        quarter,
+        period_start,
+        period_end,
+        base_currency: rates.base_currency.clone(),
+        result: repercutido.tax - soportado.tax,
+        repercutido,
+        soportado,
+        untaxed_count,
+    })
+}
+
+/// Prompt de consejos de IVA con el informe del trimestre. Solo lleva importes y
+/// tipos, sin tiendas ni descripciones: no hay nada que ocultar en el modo privacidad.
+pub fn build_tax_tips_prompt(db: &SqliteStorage, quarter: Quarter) -> Result<String, AppError> {
+    let rates = RateTable::load(db)?;
+    let report = build_tax_report(&db.list_transactions()?, &rates, quarter)?;
+    let report_json = serde_json::to_string_pretty(&report)
+        .map_err(|e| AppError::Internal(format!("Error al preparar el contexto para la IA: {}", e)))?;
+    let quarter_label = format!("{}-T{}", quarter.year, quarter.quarter);
+    prompts::render_prompt(
+        db.connection(),
+        prompts::TAX_TIPS,
+        &[("quarter", &quarter_label), ("currency", &report.base_currency), ("report_json", &report_json)],
+    )
+}
+
+// --- Comandos Tauri ---
+
+/// Comando para obtener la liquidación de IVA de un trimestre: IVA repercutido y
+/// soportado por tipo y el resultado a ingresar o compensar.
+#[tauri::command]
+pub async fn get_tax_report_command(state: State<'_, AppState>, quarter: Quarter) -> Result<TaxReport, AppError> {
+    debug!("Received get_tax_report_command: {:?}", quarter);
+    let db = state.db().await?;
+    let rates = RateTable::load(&db)?;
+    let transactions = db.list_transactions()?;
+    build_tax_report(&transactions, &rates, quarter)
+}
+
+/// Comando para pedir a la IA consejos sobre la liquidación de IVA de un trimestre. El
+/// informe se calcula en Rust y se envía como contexto con el prompt `tax_tips`.
+#[tauri::command]
+pub async fn get_ai_tax_tips_command(state: State<'_, AppState>, quarter: Quarter) -> Result<String, AppError> {
+    info!("Received get_ai_tax_tips_command: {:?}", quarter);
+    let (prompt, client) = {
+        let db = state.db().await?;
+        let settings = settings::load_settings(&db);
+        ai::ensure_enabled(&settings, Some(AiFeature::Insights))?;
+        (build_tax_tips_prompt(&db, quarter)?, settings.ai_client())
+    };
+    ai::generate_text(&state, &client, &prompt).await
+}
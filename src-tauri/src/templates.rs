@@ -0,0 +1,325 @@
+// src-tauri/src/templates.rs
+
+use rusqlite::{params, Connection, OptionalExtension, Row};
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, State};
+use log::{debug, error, info};
+
+use crate::accounts;
+use crate::audit;
+use crate::budgets;
+use crate::contacts;
+use crate::currencies;
+use crate::error::AppError;
+use crate::events;
+use crate::history::{Change, HistoryEntry};
+use crate::money;
+use crate::payments::PaymentMethod;
+use crate::periods;
+use crate::storage::{db_error, TransactionRepository};
+use crate::stores;
+use crate::tags;
+use crate::{build_new_transaction, AppState, NewTransaction, Transaction, TransactionType};
+
+/// Campos de una transacción que puede rellenar una plantilla. Todos son opcionales:
+/// lo que falte se pide al usar la plantilla. Es también el tipo de `overrides` en
+/// `create_from_template_command`.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct TemplateFields {
+    #[serde(default)]
+    pub transaction_type: Option<TransactionType>,
+    #[serde(default)]
+    pub amount: Option<Decimal>,
+    #[serde(default)]
+    pub description: Option<String>,
+    #[serde(default)]
+    pub store_name: Option<String>,
+    #[serde(default)]
+    pub category: Option<String>,
+    #[serde(default)]
+    pub subcategory: Option<String>,
+    #[serde(default)]
+    pub currency: Option<String>,
+    #[serde(default)]
+    pub tax_rate: Option<Decimal>,
+    #[serde(default)]
+    pub tags: Option<Vec<String>>,
+    #[serde(default)]
+    pub notes: Option<String>,
+    #[serde(default)]
+    pub payment_method: Option<PaymentMethod>,
+    #[serde(default)]
+    pub contact_id: Option<String>,
+    #[serde(default)]
+    pub account_id: Option<String>,
+}
+
+impl TemplateFields {
+    /// Los campos de `overrides` que vienen informados sustituyen a los de la plantilla.
+    fn merged(self, overrides: TemplateFields) -> TemplateFields {
+        TemplateFields {
+            transaction_type: overrides.transaction_type.or(self.transaction_type),
+            amount: overrides.amount.or(self.amount),
+            description: overrides.description.or(self.description),
+            store_name: overrides.store_name.or(self.store_name),
+            category: overrides.category.or(self.category),
+            subcategory: overrides.subcategory.or(self.subcategory),
+            currency: overrides.currency.or(self.currency),
+            tax_rate: overrides.tax_rate.or(self.tax_rate),
+            tags: overrides.tags.or(self.tags),
+            notes: overrides.notes.or(self.notes),
+            payment_method: overrides.payment_method.or(self.payment_method),
+            contact_id: overrides.contact_id.or(self.contact_id),
+            account_id: overrides.account_id.or(self.account_id),
+        }
+    }
+}
+
+/// Plantilla de transacción para entradas frecuentes («Café proveedor — 35 €»).
+#[derive(Debug, Clone, Serialize)]
+pub struct TransactionTemplate {
+    pub id: String,
+    pub name: String,
+    #[serde(flatten)]
+    pub fields: TemplateFields,
+    /// Veces que se ha usado; la lista se ordena por este valor.
+    pub use_count: u32,
+    pub created_at: u64,
+    pub updated_at: u64,
+}
+
+/// Datos de `create_template_command` y `update_template_command`.
+#[derive(Debug, Clone, Deserialize)]
+pub struct TemplateInput {
+    pub name: String,
+    #[serde(flatten)]
+    pub fields: TemplateFields,
+}
+
+const TEMPLATE_COLUMNS: &str = "id, name, fields, use_count, created_at, updated_at";
+
+fn row_to_template(row: &Row) -> rusqlite::Result<TransactionTemplate> {
+    Ok(TransactionTemplate {
+        id: row.get(0)?,
+        name: row.get(1)?,
+        fields: serde_json::from_str(&row.get::<_, String>(2)?).unwrap_or_default(),
+        use_count: row.get(3)?,
+        created_at: row.get::<_, i64>(4)? as u64,
+        updated_at: row.get::<_, i64>(5)? as u64,
+    })
+}
+
+/// Plantillas, primero las más usadas.
+pub fn list_templates(conn: &Connection) -> Result<Vec<TransactionTemplate>, AppError> {
+    let mut stmt = conn
+        .prepare(&format!(
+            "SELECT {} FROM transaction_templates ORDER BY use_count DESC, name COLLATE NOCASE",
+            TEMPLATE_COLUMNS
+        ))
+        .map_err(db_error)?;
+    let rows = stmt.query_map([], row_to_template).map_err(db_error)?;
+    rows.collect::<rusqlite::Result<Vec<_>>>().map_err(db_error)
+}
+
+fn get_template(conn: &Connection, id: &str) -> Result<Option<TransactionTemplate>, AppError> {
+    conn.query_row(
+        &format!("SELECT {} FROM transaction_templates WHERE id = ?1", TEMPLATE_COLUMNS),
+        params![id],
+        row_to_template,
+    )
+    .optional()
+    .map_err(db_error)
+}
+
+fn require_template(conn: &Connection, id: &str) -> Result<TransactionTemplate, AppError> {
+    get_template(conn, id)?.ok_or_else(|| AppError::NotFound(format!("Plantilla {} no encontrada.", id)))
+}
+
+/// Valida y normaliza una plantilla. La categoría no se comprueba aquí: puede
+/// borrarse después y se resuelve al usar la plantilla, como en el formulario.
+fn resolve_input(conn: &Connection, input: TemplateInput) -> Result<TemplateInput, AppError> {
+    let name = input.name.trim().to_owned();
+    if name.is_empty() {
+        return Err(AppError::invalid_field("name", "El nombre de la plantilla no puede estar vacío."));
+    }
+    let fields = input.fields;
+    if let Some(amount) = fields.amount {
+        money::validate_amount("amount", amount, "El monto debe ser positivo.")?;
+    }
+    Ok(TemplateInput {
+        name,
+        fields: TemplateFields {
+            description: stores::non_empty(fields.description),
+            store_name: stores::non_empty(fields.store_name),
+            category: stores::non_empty(fields.category),
+            subcategory: stores::non_empty(fields.subcategory),
+            currency: stores::non_empty(fields.currency).map(|c| currencies::normalize_currency_code(&c)).transpose()?,
+            tags: fields.tags.map(tags::resolve_tags).transpose()?.filter(|t| !t.is_empty()),
+            notes: stores::non_empty(fields.notes),
+            contact_id: contacts::resolve_contact_id(conn, fields.contact_id)?,
+            account_id: accounts::resolve_account_id(conn, "account_id", fields.account_id)?,
+            ..fields
+        },
+    })
+}
+
+/// Error si ya hay otra plantilla con ese nombre, sin distinguir mayúsculas.
+fn check_unique_name(conn: &Connection, name: &str, except_id: Option<&str>) -> Result<(), AppError> {
+    let exists: bool = conn
+        .query_row(
+            "SELECT EXISTS(SELECT 1 FROM transaction_templates WHERE name = ?1 COLLATE NOCASE AND id IS NOT ?2)",
+            params![name, except_id],
+            |row| row.get(0),
+        )
+        .map_err(db_error)?;
+    if exists {
+        return Err(AppError::Conflict(format!("Ya existe una plantilla llamada '{}'.", name)));
+    }
+    Ok(())
+}
+
+fn save_template(conn: &Connection, template: &TransactionTemplate) -> Result<(), AppError> {
+    let fields = serde_json::to_string(&template.fields).map_err(|e| AppError::Internal(e.to_string()))?;
+    conn.execute(
+        "INSERT OR REPLACE INTO transaction_templates (id, name, fields, use_count, created_at, updated_at)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+        params![
+            template.id,
+            template.name,
+            fields,
+            template.use_count,
+            template.created_at as i64,
+            template.updated_at as i64
+        ],
+    )
+    .map_err(db_error)?;
+    Ok(())
+}
+
+/// Combina la plantilla con `overrides` y comprueba que estén los campos obligatorios.
+fn to_new_transaction(template: &TransactionTemplate, overrides: TemplateFields) -> Result<NewTransaction, AppError> {
+    let fields = template.fields.clone().merged(overrides);
+    let missing = |field: &str, message: &str| {
+        error!("Template {} used without {}.", template.id, field);
+        AppError::invalid_field(field, message)
+    };
+    Ok(NewTransaction {
+        transaction_type: fields.transaction_type.ok_or_else(|| missing("transaction_type", "Indica si es un ingreso o un gasto."))?,
+        amount: fields.amount.ok_or_else(|| missing("amount", "Indica el importe."))?,
+        description: fields.description.ok_or_else(|| missing("description", "Indica la descripción."))?,
+        store_name: fields.store_name.ok_or_else(|| missing("store_name", "Indica la tienda."))?,
+        category: fields.category,
+        subcategory: fields.subcategory,
+        currency: fields.currency,
+        tax_rate: fields.tax_rate,
+        tax_amount: None,
+        tags: fields.tags,
+        transaction_date: None,
+        notes: fields.notes,
+        custom_fields: None,
+        payment_method: fields.payment_method,
+        contact_id: fields.contact_id,
+        account_id: fields.account_id,
+    })
+}
+
+// --- Comandos Tauri ---
+
+/// Comando para listar las plantillas de transacción, primero las más usadas.
+#[tauri::command]
+pub async fn list_templates_command(state: State<'_, AppState>) -> Result<Vec<TransactionTemplate>, AppError> {
+    debug!("Received list_templates_command.");
+    let db = state.db().await?;
+    list_templates(db.connection())
+}
+
+/// Comando para crear una plantilla de transacción.
+#[tauri::command]
+pub async fn create_template_command(
+    state: State<'_, AppState>,
+    template: TemplateInput,
+) -> Result<TransactionTemplate, AppError> {
+    debug!("Received create_template_command: {:?}", template);
+    let db = state.db().await?;
+    let input = resolve_input(db.connection(), template)?;
+    check_unique_name(db.connection(), &input.name, None)?;
+    let now = periods::now_timestamp();
+    let created = TransactionTemplate {
+        id: uuid::Uuid::new_v4().to_string(),
+        name: input.name,
+        fields: input.fields,
+        use_count: 0,
+        created_at: now,
+        updated_at: now,
+    };
+    save_template(db.connection(), &created)?;
+    audit::record(db.connection(), "create_template_command", Some(&created.id), None, audit::snapshot(&created));
+    info!("Template '{}' created.", created.name);
+    Ok(created)
+}
+
+/// Comando para modificar una plantilla. Conserva su contador de usos.
+#[tauri::command]
+pub async fn update_template_command(
+    state: State<'_, AppState>,
+    id: String,
+    template: TemplateInput,
+) -> Result<TransactionTemplate, AppError> {
+    debug!("Received update_template_command: {} {:?}", id, template);
+    let db = state.db().await?;
+    let before = require_template(db.connection(), &id)?;
+    let input = resolve_input(db.connection(), template)?;
+    check_unique_name(db.connection(), &input.name, Some(&id))?;
+    let updated = TransactionTemplate {
+        name: input.name,
+        fields: input.fields,
+        updated_at: periods::now_timestamp(),
+        ..before.clone()
+    };
+    save_template(db.connection(), &updated)?;
+    audit::record(db.connection(), "update_template_command", Some(&id), audit::snapshot(&before), audit::snapshot(&updated));
+    Ok(updated)
+}
+
+/// Comando para eliminar una plantilla. Las transacciones creadas con ella no cambian.
+#[tauri::command]
+pub async fn delete_template_command(state: State<'_, AppState>, id: String) -> Result<(), AppError> {
+    debug!("Received delete_template_command: {}", id);
+    let db = state.db().await?;
+    let before = require_template(db.connection(), &id)?;
+    db.connection()
+        .execute("DELETE FROM transaction_templates WHERE id = ?1", params![id])
+        .map_err(db_error)?;
+    audit::record(db.connection(), "delete_template_command", Some(&id), audit::snapshot(&before), None);
+    Ok(())
+}
+
+/// Comando para registrar una transacción a partir de una plantilla, con la fecha de
+/// hoy. Los campos de `overrides` sustituyen a los de la plantilla (p. ej. otro
+/// importe); si a la plantilla le falta el tipo, el importe, la descripción o la
+/// tienda, hay que indicarlos ahí.
+#[tauri::command]
+pub async fn create_from_template_command(
+    state: State<'_, AppState>,
+    app: AppHandle,
+    template_id: String,
+    overrides: Option<TemplateFields>,
+) -> Result<Transaction, AppError> {
+    debug!("Received create_from_template_command: {} {:?}", template_id, overrides);
+    let db = state.db().await?;
+    let template = require_template(db.connection(), &template_id)?;
+    let new_transaction = build_new_transaction(&db, to_new_transaction(&template, overrides.unwrap_or_default())?)?;
+    db.insert_transaction(&new_transaction)?;
+    db.connection()
+        .execute("UPDATE transaction_templates SET use_count = use_count + 1 WHERE id = ?1", params![template_id])
+        .map_err(db_error)?;
+
+    let changes = vec![Change::Insert(new_transaction.clone())];
+    audit::record_changes(db.connection(), "create_from_template_command", &changes);
+    events::emit_transaction_changes(&app, &changes);
+    state.history.lock().unwrap().record(HistoryEntry::new(&format!("Añadir transacción ({})", template.name), changes));
+    budgets::check_budget_alerts(&db, &app);
+    info!("Transaction {} created from template '{}'.", new_transaction.id, template.name);
+    Ok(new_transaction)
+}
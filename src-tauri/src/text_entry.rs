@@ -0,0 +1,275 @@
+// src-tauri/src/text_entry.rs
+
+use chrono::{Datelike, Duration, Months, NaiveDate};
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+use rusqlite::Connection;
+use serde_json::{json, Value};
+use std::str::FromStr;
+use tauri::State;
+use log::{debug, error, info};
+
+use crate::ai::{self, AiFeature, AiMessage};
+use crate::categories::{self, Category};
+use crate::currencies;
+use crate::error::AppError;
+use crate::periods;
+use crate::privacy::Redactor;
+use crate::prompts;
+use crate::settings;
+use crate::stores;
+use crate::{AppState, NewTransaction, TransactionType};
+
+/// Longitud máxima del texto que se envía a la IA.
+const MAX_TEXT_CHARS: usize = 500;
+/// Días hacia atrás que se aceptan en `days_ago`.
+const MAX_DAYS_AGO: i64 = 366;
+const WEEKDAY_NAMES: [&str; 7] = ["lunes", "martes", "miércoles", "jueves", "viernes", "sábado", "domingo"];
+
+/// Datos tal como los devuelve la IA. La fecha llega sin calcular y se resuelve en
+/// `resolve_date`, porque los modelos se equivocan a menudo con las fechas relativas.
+#[derive(Debug, Deserialize)]
+struct ParsedText {
+    transaction_type: Option<TransactionType>,
+    amount: Option<String>,
+    description: Option<String>,
+    store_name: Option<String>,
+    category: Option<String>,
+    currency: Option<String>,
+    days_ago: Option<i64>,
+    weekday: Option<u32>,
+    day: Option<u32>,
+    month: Option<u32>,
+    year: Option<i32>,
+}
+
+fn parsed_text_schema() -> Value {
+    let nullable = |kind: &str| json!({"type": kind, "nullable": true});
+    json!({
+        "type": "OBJECT",
+        "properties": {
+            "transaction_type": {"type": "STRING", "enum": ["Ingreso", "Gasto"], "nullable": true},
+            "amount": {"type": "STRING", "nullable": true, "description": "Importe con punto decimal"},
+            "description": nullable("STRING"),
+            "store_name": nullable("STRING"),
+            "category": nullable("STRING"),
+            "currency": nullable("STRING"),
+            "days_ago": nullable("INTEGER"),
+            "weekday": {"type": "INTEGER", "nullable": true, "description": "0 lunes ... 6 domingo"},
+            "day": nullable("INTEGER"),
+            "month": nullable("INTEGER"),
+            "year": nullable("INTEGER"),
+        },
+        "required": [
+            "transaction_type", "amount", "description", "store_name", "category", "currency",
+            "days_ago", "weekday", "day", "month", "year",
+        ],
+    })
+}
+
+/// Borrador de transacción leído de un texto. No se guarda nada: el frontend lo
+/// muestra en el formulario y, si el usuario lo confirma, lo envía a
+/// `add_transaction_command`.
+#[derive(Debug, Clone, Serialize)]
+pub struct TransactionTextDraft {
+    pub draft: NewTransaction,
+    /// Campos que no se pudieron deducir del texto y que el usuario debe completar
+    /// (`amount`, `store_name`, `transaction_type`).
+    pub missing_fields: Vec<String>,
+}
+
+/// Fecha de la transacción a partir de lo que la IA leyó del texto, relativa a `today`.
+/// Una fecha explícita sin año es la última que no cae en el futuro; un día de la
+/// semana, el último que no es posterior a hoy. Sin ninguna indicación, hoy.
+fn resolve_date(parsed: &ParsedText, today: NaiveDate) -> NaiveDate {
+    if let Some(day) = parsed.day {
+        let month = parsed.month.unwrap_or(today.month());
+        let candidate = match parsed.year {
+            Some(year) => NaiveDate::from_ymd_opt(year, month, day),
+            None => NaiveDate::from_ymd_opt(today.year(), month, day).and_then(|date| {
+                if date <= today {
+                    Some(date)
+                } else if parsed.month.is_some() {
+                    NaiveDate::from_ymd_opt(today.year() - 1, month, day)
+                } else {
+                    let previous = today.checked_sub_months(Months::new(1))?;
+                    NaiveDate::from_ymd_opt(previous.year(), previous.month(), day)
+                }
+            }),
+        };
+        if let Some(date) = candidate.filter(|date| *date <= today) {
+            return date;
+        }
+    }
+    if let Some(weekday) = parsed.weekday.filter(|w| *w < 7) {
+        let back = (today.weekday().num_days_from_monday() + 7 - weekday) % 7;
+        return today - Duration::days(back as i64);
+    }
+    match parsed.days_ago.filter(|days| (0..=MAX_DAYS_AGO).contains(days)) {
+        Some(days) => today - Duration::days(days),
+        None => today,
+    }
+}
+
+/// Importe en texto (con punto o coma decimal) como número positivo.
+pub fn parse_amount(amount: &str) -> Option<Decimal> {
+    let cleaned: String = amount.chars().filter(|c| !c.is_whitespace() && *c != '€').collect();
+    // Con los dos separadores, el último es el decimal ("1.234,50" o "1,234.50").
+    let normalized = match (cleaned.rfind(','), cleaned.rfind('.')) {
+        (Some(comma), Some(dot)) if comma > dot => cleaned.replace('.', "").replace(',', "."),
+        (Some(_), Some(_)) => cleaned.replace(',', ""),
+        (Some(_), None) => cleaned.replace(',', "."),
+        _ => cleaned,
+    };
+    Decimal::from_str(&normalized).ok().map(|a| a.abs()).filter(|a| !a.is_zero())
+}
+
+/// Nombre que coincide, sin distinguir mayúsculas, con uno de `names`.
+pub fn match_name(value: &str, names: &[String]) -> Option<String> {
+    let value = value.trim().to_lowercase();
+    names.iter().find(|name| name.to_lowercase() == value).cloned()
+}
+
+/// Categoría y subcategoría existentes que corresponden a `value`. Si es una
+/// subcategoría, la categoría es su principal.
+pub fn match_category(value: &str, categories: &[Category]) -> (Option<String>, Option<String>) {
+    for category in categories {
+        if let Some(name) = match_name(value, std::slice::from_ref(&category.name)) {
+            return (Some(name), None);
+        }
+        if let Some(subcategory) = match_name(value, &category.subcategories) {
+            return (Some(category.name.clone()), Some(subcategory));
+        }
+    }
+    (None, None)
+}
+
+/// Prompt para leer una frase, con los datos con los que se ajusta la respuesta.
+pub struct TextPrompt {
+    pub prompt: String,
+    today: NaiveDate,
+    store_names: Vec<String>,
+    categories: Vec<Category>,
+}
+
+/// Prompt para leer `text`, con las tiendas ocultadas por `redactor` tanto en la lista
+/// de tiendas como en el propio texto.
+pub fn build_text_prompt(conn: &Connection, text: &str, redactor: &mut Redactor) -> Result<TextPrompt, AppError> {
+    let text = text.trim();
+    if text.is_empty() {
+        error!("Empty transaction text.");
+        return Err(AppError::invalid_field("text", "Escribe la transacción que quieres registrar."));
+    }
+    if text.chars().count() > MAX_TEXT_CHARS {
+        return Err(AppError::invalid_field(
+            "text",
+            format!("El texto no puede superar los {} caracteres.", MAX_TEXT_CHARS),
+        ));
+    }
+
+    let today = periods::today();
+    let store_names: Vec<String> = stores::list_stores(conn)?.into_iter().map(|s| s.name).collect();
+    let categories = categories::list_categories(conn)?;
+    let category_names: Vec<&str> = categories
+        .iter()
+        .flat_map(|c| std::iter::once(&c.name).chain(&c.subcategories))
+        .map(String::as_str)
+        .collect();
+    let redacted_stores: Vec<String> = store_names.iter().map(|s| redactor.store(s)).collect();
+    let today_text = today.format("%Y-%m-%d").to_string();
+    let prompt = prompts::render_prompt(
+        conn,
+        prompts::TRANSACTION_TEXT,
+        &[
+            ("text", &redactor.text(text)),
+            ("today", &today_text),
+            ("weekday", WEEKDAY_NAMES[today.weekday().num_days_from_monday() as usize]),
+            ("stores", &redacted_stores.join(", ")),
+            ("categories", &category_names.join(", ")),
+        ],
+    )?;
+    Ok(TextPrompt { prompt, today, store_names, categories })
+}
+
+/// Convierte una frase como "ayer pagué 85,40 de luz en Iberdrola" en un borrador de
+/// transacción con ayuda de la IA. La fecha se calcula en Rust a partir de hoy, y la
+/// tienda y la categoría se ajustan a las existentes. No guarda nada.
+pub async fn parse_transaction_text(state: &AppState, text: &str) -> Result<TransactionTextDraft, AppError> {
+    let text = text.trim();
+    let (TextPrompt { prompt, today, store_names, categories }, redactor, client) = {
+        let db = state.db().await?;
+        let settings = settings::load_settings(&db);
+        let mut redactor = Redactor::new(db.connection(), &settings)?;
+        (build_text_prompt(db.connection(), text, &mut redactor)?, redactor, settings.ai_client())
+    };
+
+    let parsed: ParsedText = ai::generate_json(
+        state,
+        &client,
+        &[AiMessage::user(prompt)],
+        parsed_text_schema(),
+        "La IA no pudo interpretar el texto. Prueba a escribirlo de otra forma.",
+    )
+    .await?;
+    debug!("Parsed transaction text: {:?}", parsed);
+
+    let transaction_date = resolve_date(&parsed, today);
+    let amount = parsed.amount.as_deref().and_then(parse_amount);
+    let store_name = parsed
+        .store_name
+        .map(|s| redactor.restore(s.trim()))
+        .filter(|s| !s.is_empty())
+        .map(|s| match_name(&s, &store_names).unwrap_or(s));
+    // Una transferencia necesita la tienda de destino: no se propone desde el texto.
+    let transaction_type = parsed.transaction_type.filter(|t| *t != TransactionType::Transferencia);
+    let (category, subcategory) =
+        parsed.category.as_deref().map_or((None, None), |c| match_category(c, &categories));
+    let description = parsed.description.map(|d| redactor.restore(d.trim())).filter(|d| !d.is_empty());
+
+    let mut missing_fields = Vec::new();
+    if amount.is_none() {
+        missing_fields.push("amount".to_string());
+    }
+    if store_name.is_none() {
+        missing_fields.push("store_name".to_string());
+    }
+    if transaction_type.is_none() {
+        missing_fields.push("transaction_type".to_string());
+    }
+
+    let draft = NewTransaction {
+        transaction_type: transaction_type.unwrap_or(TransactionType::Gasto),
+        amount: amount.unwrap_or_default(),
+        description: description.unwrap_or_else(|| text.to_owned()),
+        store_name: store_name.unwrap_or_default(),
+        category,
+        subcategory,
+        currency: parsed.currency.and_then(|c| currencies::normalize_currency_code(&c).ok()),
+        tax_rate: None,
+        tax_amount: None,
+        tags: None,
+        transaction_date: Some(transaction_date),
+        notes: None,
+        custom_fields: None,
+        payment_method: None,
+        contact_id: None,
+        account_id: None,
+    };
+    info!("Transaction draft from text ({} missing fields).", missing_fields.len());
+    Ok(TransactionTextDraft { draft, missing_fields })
+}
+
+// --- Comandos Tauri ---
+
+/// Comando para convertir una frase en un borrador de transacción (ver
+/// `parse_transaction_text`). Nunca se guarda nada: el usuario debe confirmar el
+/// borrador.
+#[tauri::command]
+pub async fn parse_transaction_text_command(
+    state: State<'_, AppState>,
+    text: String,
+) -> Result<TransactionTextDraft, AppError> {
+    info!("Received parse_transaction_text_command.");
+    ai::ensure_enabled(&settings::load_settings(&state.db().await?), Some(AiFeature::Categorization))?;
+    parse_transaction_text(&state, &text).await
+}
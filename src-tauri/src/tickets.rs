@@ -0,0 +1,396 @@
+// src-tauri/src/tickets.rs
+
+use chrono::NaiveDate;
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+use std::fs::OpenOptions;
+use std::io::Write;
+use std::net::{TcpStream, ToSocketAddrs};
+use std::time::Duration;
+use tauri::State;
+use log::{debug, error, info};
+
+use crate::cash_closing::{self, CashClosing};
+use crate::currencies::{self, RateTable};
+use crate::error::AppError;
+use crate::i18n::{self, Language};
+use crate::payments::PaymentMethod;
+use crate::periods;
+use crate::settings::{self, Settings};
+use crate::storage::TransactionRepository;
+use crate::{AppState, Transaction, TransactionType};
+
+// Tickets para la impresora térmica del mostrador: el de una transacción y el resumen
+// de un día. Se generan en ESC/POS, el juego de órdenes que entienden casi todas las
+// impresoras de tickets, y se envían a la impresora configurada: por la red (puerto
+// 9100) o a su dispositivo (`/dev/usb/lp0`, `COM3`, `\\equipo\ticket`). El texto va en
+// la página de códigos PC858, que tiene las letras acentuadas y el símbolo del euro.
+
+/// Puerto de las impresoras de red cuando la dirección no indica otro.
+pub const DEFAULT_PRINTER_PORT: u16 = 9100;
+/// Caracteres por línea admitidos: 32 en papel de 58 mm, 42 o 48 en papel de 80 mm.
+const MIN_RECEIPT_WIDTH: u32 = 24;
+const MAX_RECEIPT_WIDTH: u32 = 64;
+const MAX_RECEIPT_TEXT_LINES: usize = 6;
+const PRINTER_TIMEOUT: Duration = Duration::from_secs(5);
+/// Caracteres del identificador de la transacción que se imprimen como número de ticket.
+const TICKET_NUMBER_LEN: usize = 8;
+
+/// Cómo se llega a la impresora de tickets.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ReceiptPrinter {
+    Disabled,
+    /// Impresora de red: `receipt_printer_address` es `host` o `host:puerto`.
+    Network,
+    /// Impresora local: `receipt_printer_address` es la ruta del dispositivo o de la
+    /// impresora compartida.
+    Device,
+}
+
+pub fn validate_settings(settings: &Settings) -> Result<(), AppError> {
+    if settings.receipt_printer != ReceiptPrinter::Disabled && settings.receipt_printer_address.is_empty() {
+        return Err(AppError::invalid_field("receipt_printer_address", "Indica la dirección de la impresora de tickets."));
+    }
+    if !(MIN_RECEIPT_WIDTH..=MAX_RECEIPT_WIDTH).contains(&settings.receipt_width_chars) {
+        return Err(AppError::invalid_field(
+            "receipt_width_chars",
+            format!("El ancho del ticket debe estar entre {} y {} caracteres.", MIN_RECEIPT_WIDTH, MAX_RECEIPT_WIDTH),
+        ));
+    }
+    for (field, text) in [("receipt_header", &settings.receipt_header), ("receipt_footer", &settings.receipt_footer)] {
+        if text.lines().count() > MAX_RECEIPT_TEXT_LINES {
+            return Err(AppError::invalid_field(
+                field,
+                format!("El texto del ticket no puede tener más de {} líneas.", MAX_RECEIPT_TEXT_LINES),
+            ));
+        }
+    }
+    Ok(())
+}
+
+// --- ESC/POS ---
+
+const ESC: u8 = 0x1B;
+const GS: u8 = 0x1D;
+/// Número de la página de códigos PC858 en `ESC t`.
+const CODE_PAGE_PC858: u8 = 19;
+
+/// Carácter en PC858; los que no tiene se imprimen como `?`.
+fn pc858(c: char) -> u8 {
+    match c {
+        ' '..='~' => c as u8,
+        'Ç' => 0x80,
+        'ü' => 0x81,
+        'é' => 0x82,
+        'â' => 0x83,
+        'ä' => 0x84,
+        'à' => 0x85,
+        'ç' => 0x87,
+        'ê' => 0x88,
+        'è' => 0x8A,
+        'ï' => 0x8B,
+        'É' => 0x90,
+        'ô' => 0x93,
+        'ö' => 0x94,
+        'ò' => 0x95,
+        'û' => 0x96,
+        'ù' => 0x97,
+        'Ö' => 0x99,
+        'Ü' => 0x9A,
+        '£' => 0x9C,
+        'á' => 0xA0,
+        'í' => 0xA1,
+        'ó' => 0xA2,
+        'ú' => 0xA3,
+        'ñ' => 0xA4,
+        'Ñ' => 0xA5,
+        'ª' => 0xA6,
+        'º' => 0xA7,
+        '¿' => 0xA8,
+        '¡' => 0xAD,
+        'Á' => 0xB5,
+        'À' => 0xB7,
+        'ã' => 0xC6,
+        'Ã' => 0xC7,
+        '€' => 0xD5,
+        'Í' => 0xD6,
+        'Ó' => 0xE0,
+        'õ' => 0xE4,
+        'Õ' => 0xE5,
+        'Ú' => 0xE9,
+        _ => b'?',
+    }
+}
+
+/// Ticket en construcción: texto de `width` caracteres por línea con las órdenes
+/// ESC/POS de formato.
+struct ReceiptWriter {
+    width: usize,
+    bytes: Vec<u8>,
+}
+
+impl ReceiptWriter {
+    fn new(width: u32) -> Self {
+        // Inicializa la impresora y elige la página de códigos.
+        ReceiptWriter { width: width as usize, bytes: vec![ESC, b'@', ESC, b't', CODE_PAGE_PC858] }
+    }
+
+    fn center(&mut self, on: bool) {
+        self.bytes.extend([ESC, b'a', u8::from(on)]);
+    }
+
+    fn bold(&mut self, on: bool) {
+        self.bytes.extend([ESC, b'E', u8::from(on)]);
+    }
+
+    /// Escribe `text` en una o varias líneas, cortando por palabras.
+    fn text(&mut self, text: &str) {
+        let mut line = String::new();
+        for word in text.split_whitespace() {
+            let needed = line.chars().count() + usize::from(!line.is_empty()) + word.chars().count();
+            if needed > self.width && !line.is_empty() {
+                self.raw_line(&std::mem::take(&mut line));
+            }
+            if !line.is_empty() {
+                line.push(' ');
+            }
+            line.push_str(word);
+        }
+        self.raw_line(&line);
+    }
+
+    /// `label` a la izquierda y `value` a la derecha de la misma línea.
+    fn row(&mut self, label: &str, value: &str) {
+        let value_len = value.chars().count();
+        let label: String = label.chars().take(self.width.saturating_sub(value_len + 1)).collect();
+        let padding = self.width.saturating_sub(label.chars().count() + value_len);
+        self.raw_line(&format!("{}{}{}", label, " ".repeat(padding), value));
+    }
+
+    fn separator(&mut self) {
+        self.raw_line(&"-".repeat(self.width));
+    }
+
+    fn blank(&mut self) {
+        self.bytes.push(b'\n');
+    }
+
+    fn raw_line(&mut self, line: &str) {
+        self.bytes.extend(line.chars().take(self.width).map(pc858));
+        self.bytes.push(b'\n');
+    }
+
+    /// Líneas libres de los ajustes (cabecera o pie), centradas.
+    fn centered_block(&mut self, text: &str, bold_first: bool) {
+        let lines: Vec<&str> = text.lines().map(str::trim).filter(|l| !l.is_empty()).collect();
+        if lines.is_empty() {
+            return;
+        }
+        self.center(true);
+        for (i, line) in lines.into_iter().enumerate() {
+            self.bold(bold_first && i == 0);
+            self.text(line);
+        }
+        self.bold(false);
+        self.center(false);
+        self.blank();
+    }
+
+    /// Avanza el papel para que el corte no se lleve la última línea y corta.
+    fn finish(mut self) -> Vec<u8> {
+        self.bytes.extend([ESC, b'd', 4, GS, b'V', 1]);
+        self.bytes
+    }
+}
+
+fn payment_label(method: PaymentMethod, language: Language) -> &'static str {
+    let key = match method {
+        PaymentMethod::Efectivo => "payment.cash",
+        PaymentMethod::Tarjeta => "payment.card",
+        PaymentMethod::Transferencia => "payment.transfer",
+        PaymentMethod::Bizum => "payment.bizum",
+        PaymentMethod::Otro => "payment.other",
+    };
+    i18n::text(language, key)
+}
+
+/// Ticket de una transacción: cabecera de la tienda, fecha, concepto, IVA, total y
+/// forma de pago.
+pub fn render_transaction_receipt(transaction: &Transaction, settings: &Settings, base_currency: &str) -> Vec<u8> {
+    let language = settings.language();
+    let money = |amount| settings.format_money(amount, &transaction.currency, base_currency);
+    let mut receipt = ReceiptWriter::new(settings.receipt_width_chars);
+    receipt.centered_block(&settings.receipt_header, true);
+
+    let number: String = transaction.id.chars().take(TICKET_NUMBER_LEN).collect();
+    receipt.row(&i18n::format(language, "ticket.number", &[("number", &number)]), &settings.format_date(transaction.transaction_date));
+    receipt.separator();
+    let description = if transaction.description.trim().is_empty() { &transaction.store_name } else { &transaction.description };
+    receipt.text(description);
+    if let Some(category) = &transaction.category {
+        receipt.text(category);
+    }
+    receipt.separator();
+    if let (Some(rate), Some(tax)) = (transaction.tax_rate, transaction.tax_amount) {
+        receipt.row(i18n::text(language, "invoice.subtotal"), &money(transaction.amount - tax));
+        receipt.row(&i18n::format(language, "invoice.vat", &[("rate", &rate.normalize())]), &money(tax));
+    }
+    receipt.bold(true);
+    receipt.row(i18n::text(language, "invoice.total"), &money(transaction.amount));
+    receipt.bold(false);
+    receipt.row(i18n::text(language, "ticket.payment"), payment_label(transaction.payment_method, language));
+    receipt.blank();
+    receipt.centered_block(&settings.receipt_footer, false);
+    receipt.finish()
+}
+
+/// Resumen de las transacciones del día `date`, en la moneda base: ingresos y gastos
+/// por forma de pago y resultado. Las transferencias no cuentan. Si la caja del día
+/// está cerrada, añade el cuadre del efectivo.
+pub fn render_daily_summary(
+    transactions: &[Transaction],
+    date: NaiveDate,
+    closing: Option<&CashClosing>,
+    rates: &RateTable,
+    settings: &Settings,
+    base_currency: &str,
+) -> Result<Vec<u8>, AppError> {
+    let language = settings.language();
+    let money = |amount| settings.format_money(amount, base_currency, base_currency);
+    let day: Vec<&Transaction> = transactions
+        .iter()
+        .filter(|t| t.transaction_date == date && t.transaction_type != TransactionType::Transferencia)
+        .collect();
+
+    let mut receipt = ReceiptWriter::new(settings.receipt_width_chars);
+    receipt.centered_block(&settings.receipt_header, true);
+    receipt.center(true);
+    receipt.bold(true);
+    receipt.text(&i18n::format(language, "ticket.daily_title", &[("date", &settings.format_date(date))]));
+    receipt.bold(false);
+    receipt.center(false);
+
+    let mut net = Decimal::ZERO;
+    for (kind, key) in [(TransactionType::Ingreso, "report.income"), (TransactionType::Gasto, "report.expenses")] {
+        receipt.separator();
+        let mut total = Decimal::ZERO;
+        let mut count = 0;
+        let mut by_method = Vec::new();
+        for method in PaymentMethod::ALL {
+            let mut amount = Decimal::ZERO;
+            for t in day.iter().filter(|t| t.transaction_type == kind && t.payment_method == method) {
+                amount += rates.to_base(t.amount, &t.currency)?;
+                count += 1;
+            }
+            if !amount.is_zero() {
+                by_method.push((method, amount));
+            }
+            total += amount;
+        }
+        receipt.bold(true);
+        receipt.row(&format!("{} ({})", i18n::text(language, key), count), &money(total));
+        receipt.bold(false);
+        for (method, amount) in by_method {
+            receipt.row(&format!("  {}", payment_label(method, language)), &money(amount));
+        }
+        net += if kind == TransactionType::Ingreso { total } else { -total };
+    }
+    receipt.separator();
+    receipt.bold(true);
+    receipt.row(i18n::text(language, "report.net"), &money(net));
+    receipt.bold(false);
+    if let Some(closing) = closing {
+        receipt.separator();
+        receipt.row(i18n::text(language, "cash_closing.expected"), &money(closing.expected_cash));
+        receipt.row(i18n::text(language, "cash_closing.counted"), &money(closing.counted_cash));
+        receipt.bold(true);
+        receipt.row(i18n::text(language, "cash_closing.difference"), &money(closing.difference));
+        receipt.bold(false);
+    }
+    receipt.blank();
+    receipt.centered_block(&settings.receipt_footer, false);
+    Ok(receipt.finish())
+}
+
+// --- Impresora ---
+
+fn printer_error(e: std::io::Error) -> AppError {
+    error!("Receipt printer failed: {}", e);
+    AppError::Io(format!("No se pudo imprimir el ticket: {}", e))
+}
+
+/// Envía el ticket a la impresora configurada. Bloquea hasta que la impresora lo
+/// acepta o vence `PRINTER_TIMEOUT`.
+fn send_to_printer(settings: &Settings, bytes: &[u8]) -> Result<(), AppError> {
+    let address = settings.receipt_printer_address.as_str();
+    match settings.receipt_printer {
+        ReceiptPrinter::Disabled => {
+            Err(AppError::validation("No hay ninguna impresora de tickets configurada en los ajustes."))
+        }
+        ReceiptPrinter::Network => {
+            let target = if address.contains(':') { address.to_owned() } else { format!("{}:{}", address, DEFAULT_PRINTER_PORT) };
+            let socket = target
+                .to_socket_addrs()
+                .map_err(printer_error)?
+                .next()
+                .ok_or_else(|| AppError::invalid_field("receipt_printer_address", "No se encontró la impresora de tickets."))?;
+            let mut stream = TcpStream::connect_timeout(&socket, PRINTER_TIMEOUT).map_err(printer_error)?;
+            stream.set_write_timeout(Some(PRINTER_TIMEOUT)).map_err(printer_error)?;
+            stream.write_all(bytes).and_then(|_| stream.flush()).map_err(printer_error)
+        }
+        ReceiptPrinter::Device => {
+            let mut device = OpenOptions::new().write(true).open(address).map_err(printer_error)?;
+            device.write_all(bytes).and_then(|_| device.flush()).map_err(printer_error)
+        }
+    }
+}
+
+async fn print(settings: Settings, bytes: Vec<u8>) -> Result<(), AppError> {
+    let len = bytes.len();
+    tokio::task::spawn_blocking(move || send_to_printer(&settings, &bytes))
+        .await
+        .map_err(|e| AppError::Internal(format!("Error al imprimir el ticket: {}", e)))??;
+    debug!("Sent {} bytes to the receipt printer.", len);
+    Ok(())
+}
+
+// --- Comandos Tauri ---
+
+/// Comando para imprimir el ticket de una transacción en la impresora de tickets.
+#[tauri::command]
+pub async fn print_receipt_command(state: State<'_, AppState>, transaction_id: String) -> Result<(), AppError> {
+    debug!("Received print_receipt_command for transaction {}", transaction_id);
+    let (bytes, settings) = {
+        let db = state.db().await?;
+        let transaction = db
+            .get_transaction(&transaction_id)?
+            .ok_or_else(|| AppError::NotFound(format!("Transacción con ID {} no encontrada.", transaction_id)))?;
+        let settings = settings::load_settings(&db);
+        (render_transaction_receipt(&transaction, &settings, &currencies::get_base_currency(&db)?), settings)
+    };
+    print(settings, bytes).await?;
+    info!("Printed receipt for transaction {}.", transaction_id);
+    Ok(())
+}
+
+/// Comando para imprimir el resumen del día `date` (hoy si no se indica) en la
+/// impresora de tickets, con el cierre de caja si ya se hizo.
+#[tauri::command]
+pub async fn print_daily_summary_command(state: State<'_, AppState>, date: Option<NaiveDate>) -> Result<(), AppError> {
+    debug!("Received print_daily_summary_command: {:?}", date);
+    let date = date.unwrap_or_else(periods::today);
+    let (bytes, settings) = {
+        let db = state.db().await?;
+        let settings = settings::load_settings(&db);
+        let base_currency = currencies::get_base_currency(&db)?;
+        let closing = cash_closing::get_cash_closing(db.connection(), date)?;
+        let transactions = db.list_transactions()?;
+        let bytes =
+            render_daily_summary(&transactions, date, closing.as_ref(), &RateTable::load(&db)?, &settings, &base_currency)?;
+        (bytes, settings)
+    };
+    print(settings, bytes).await?;
+    info!("Printed daily summary for {}.", date);
+    Ok(())
+}
@@ -0,0 +1,105 @@
+// src-tauri/src/transfers.rs
+
+use chrono::NaiveDate;
+use rust_decimal::Decimal;
+use std::collections::HashMap;
+use tauri::{AppHandle, State};
+use log::{debug, info};
+
+use crate::accounts;
+use crate::audit;
+use crate::currencies;
+use crate::error::AppError;
+use crate::events;
+use crate::history::{Change, HistoryEntry};
+use crate::merge;
+use crate::money;
+use crate::payments::PaymentMethod;
+use crate::periods;
+use crate::storage::TransactionRepository;
+use crate::stores;
+use crate::validation::{self, validate_notes, validate_transaction_date};
+use crate::{AppState, Transaction, TransactionType};
+
+/// Comando para registrar una transferencia de dinero entre dos tiendas (p. ej. de la
+/// caja al banco) con una sola transacción de tipo `Transferencia`. No cuenta como
+/// ingreso ni como gasto: resta del balance de `from_store` y suma al de `to_store`, y
+/// lo mismo con el saldo de las cuentas `from_account_id` y `to_account_id` si se indican.
+/// Sin `description` se usa «Transferencia de <origen> a <destino>».
+#[tauri::command]
+pub async fn add_transfer_command(
+    state: State<'_, AppState>,
+    app: AppHandle,
+    amount: Decimal,
+    from_store: String,
+    to_store: String,
+    description: Option<String>,
+    currency: Option<String>,
+    transaction_date: Option<NaiveDate>,
+    notes: Option<String>,
+    payment_method: Option<PaymentMethod>,
+    from_account_id: Option<String>,
+    to_account_id: Option<String>,
+) -> Result<Transaction, AppError> {
+    debug!("Received add_transfer_command: {} from '{}' to '{}'", amount, from_store, to_store);
+    money::validate_amount("amount", amount, "El monto debe ser positivo.")?;
+    let from_store = stores::non_empty(Some(from_store))
+        .ok_or_else(|| AppError::invalid_field("from_store", "Indica la tienda de origen."))?;
+    let to_store = stores::non_empty(Some(to_store))
+        .ok_or_else(|| AppError::invalid_field("to_store", "Indica la tienda de destino."))?;
+    if from_store == to_store {
+        return Err(AppError::invalid_field("to_store", "El origen y el destino deben ser tiendas distintas."));
+    }
+
+    let db = state.db().await?;
+    let currency = match currency {
+        Some(code) => currencies::normalize_currency_code(&code)?,
+        None => currencies::get_base_currency(&db)?,
+    };
+    let transaction_date = validate_transaction_date(transaction_date.unwrap_or_else(periods::today))?;
+    let account_id = accounts::resolve_account_id(db.connection(), "from_account_id", from_account_id)?;
+    let transfer_account_id = accounts::resolve_account_id(db.connection(), "to_account_id", to_account_id)?;
+    let now = periods::now_timestamp();
+    let transaction = Transaction {
+        id: uuid::Uuid::new_v4().to_string(),
+        transaction_type: TransactionType::Transferencia,
+        amount,
+        description: stores::non_empty(description)
+            .unwrap_or_else(|| format!("Transferencia de {} a {}", from_store, to_store)),
+        store_name: from_store,
+        timestamp: periods::timestamp_for_date(transaction_date),
+        category: None,
+        subcategory: None,
+        currency,
+        receipt_paths: Vec::new(),
+        deleted_at: None,
+        tax_rate: None,
+        tax_amount: None,
+        tags: Vec::new(),
+        external_id: None,
+        transaction_date,
+        created_at: now,
+        updated_at: now,
+        notes: validate_notes(notes.unwrap_or_default())?,
+        custom_fields: HashMap::new(),
+        payment_method: payment_method.unwrap_or_default(),
+        contact_id: None,
+        transfer_store: Some(to_store),
+        account_id,
+        transfer_account_id,
+        user_id: None,
+        revision: merge::initial_revision(),
+        device_id: String::new(),
+        sequence: 0,
+    };
+    validation::enforce_policy(&db, &transaction)?;
+    db.insert_transaction(&transaction)?;
+
+    let changes = vec![Change::Insert(transaction.clone())];
+    audit::record_changes(db.connection(), "add_transfer_command", &changes);
+    events::emit_transaction_changes(&app, &changes);
+    state.history.lock().unwrap().record(HistoryEntry::new("Añadir transferencia", changes));
+    info!("Transfer {} recorded: {} {} from '{}' to '{}'.", transaction.id, transaction.amount,
+          transaction.currency, transaction.store_name, transaction.transfer_store.as_deref().unwrap_or_default());
+    Ok(transaction)
+}
@@ -0,0 +1,92 @@
+// src-tauri/src/trash.rs
+
+use tauri::{AppHandle, State};
+use log::{debug, error, info};
+
+use crate::attachments;
+use crate::audit;
+use crate::error::AppError;
+use crate::events;
+use crate::history::{Change, HistoryEntry};
+use crate::periods;
+use crate::storage::{SqliteStorage, TransactionRepository};
+use crate::{AppState, Transaction};
+
+const SECONDS_PER_DAY: u64 = 24 * 60 * 60;
+
+/// Mueve una transacción activa a la papelera y devuelve el cambio para el historial.
+/// Los adjuntos se conservan hasta que la transacción se purga.
+pub fn move_to_trash(db: &SqliteStorage, transaction: Transaction) -> Result<Change, AppError> {
+    let deleted_at = periods::now_timestamp();
+    let mut trashed = transaction.clone();
+    trashed.deleted_at = Some(deleted_at);
+    if !db.soft_delete_transaction(&transaction.id, deleted_at)? {
+        return Err(AppError::NotFound(format!("Transacción con ID {} no encontrada.", transaction.id)));
+    }
+    Ok(Change::Update { before: transaction, after: trashed })
+}
+
+// --- Comandos Tauri ---
+
+/// Comando para listar las transacciones de la papelera, de la más reciente a la más antigua.
+#[tauri::command]
+pub async fn list_trash_command(state: State<'_, AppState>) -> Result<Vec<Transaction>, AppError> {
+    debug!("Received list_trash_command.");
+    state.db().await?.list_deleted_transactions()
+}
+
+/// Comando para sacar una transacción de la papelera.
+#[tauri::command]
+pub async fn restore_transaction_command(
+    state: State<'_, AppState>,
+    app: AppHandle,
+    id: String,
+) -> Result<Transaction, AppError> {
+    debug!("Received restore_transaction_command for ID: {}", id);
+    let db = state.db().await?;
+    let trashed = db
+        .list_deleted_transactions()?
+        .into_iter()
+        .find(|t| t.id == id)
+        .ok_or_else(|| {
+            error!("Transaction {} not found in trash.", id);
+            AppError::NotFound(format!("La transacción con ID {} no está en la papelera.", id))
+        })?;
+
+    db.restore_transaction(&id)?;
+    let mut restored = trashed.clone();
+    restored.deleted_at = None;
+    let changes = vec![Change::Update { before: trashed, after: restored.clone() }];
+    audit::record_changes(db.connection(), "restore_transaction_command", &changes);
+    events::emit_transaction_changes(&app, &changes);
+    state.history.lock().unwrap().record(HistoryEntry::new("Restaurar transacción", changes));
+    info!("Transaction {} restored from trash.", id);
+    Ok(restored)
+}
+
+/// Comando para vaciar la papelera. Con `older_than_days` solo se borran las
+/// transacciones eliminadas hace más de ese número de días. Este borrado es
+/// definitivo e incluye los adjuntos. Devuelve cuántas transacciones se borraron.
+#[tauri::command]
+pub async fn purge_trash_command(
+    state: State<'_, AppState>,
+    app: AppHandle,
+    older_than_days: Option<u64>,
+) -> Result<usize, AppError> {
+    debug!("Received purge_trash_command (older_than_days: {:?})", older_than_days);
+    let cutoff = match older_than_days {
+        Some(days) => periods::now_timestamp().saturating_sub(days.saturating_mul(SECONDS_PER_DAY)),
+        // Se guarda como INTEGER con signo en SQLite.
+        None => i64::MAX as u64,
+    };
+
+    let db = state.db().await?;
+    let purged = db.purge_deleted_before(cutoff)?;
+    for transaction in &purged {
+        attachments::remove_transaction_attachments(&transaction.id);
+        audit::record(db.connection(), "purge_trash_command", Some(&transaction.id), audit::snapshot(transaction), None);
+        events::emit_transaction_deleted(&app, &transaction.id, true);
+    }
+    info!("Purged {} transactions from trash.", purged.len());
+    Ok(purged.len())
+}
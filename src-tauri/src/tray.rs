@@ -0,0 +1,185 @@
+// src-tauri/src/tray.rs
+
+use serde::Serialize;
+use std::sync::atomic::{AtomicBool, Ordering};
+use tauri::menu::{Menu, MenuEvent, MenuItem, PredefinedMenuItem};
+use tauri::tray::{MouseButton, MouseButtonState, TrayIconBuilder, TrayIconEvent};
+use tauri::{AppHandle, Listener, Manager, Window, WindowEvent};
+use log::{debug, info, warn};
+
+use crate::dashboard;
+use crate::error::AppError;
+use crate::events;
+use crate::periods::{self, Period};
+use crate::settings::{self, Settings, SETTINGS_CHANGED_EVENT};
+use crate::windows;
+use crate::AppState;
+
+const TRAY_ID: &str = "main";
+const APP_NAME: &str = "Contabilidad IA App";
+const MENU_NEW_TRANSACTION: &str = "new_transaction";
+const MENU_MONTH_REPORT: &str = "month_report";
+const MENU_QUIT: &str = "quit";
+
+/// Evento para que la ventana principal muestre el informe del mes. Carga: `MonthReportRequest`.
+pub const OPEN_MONTH_REPORT_EVENT: &str = "tray://open-month-report";
+
+/// Copia del ajuste `minimize_to_tray`, para consultarlo al cerrar la ventana sin
+/// esperar a la base de datos.
+static MINIMIZE_TO_TRAY: AtomicBool = AtomicBool::new(false);
+
+#[derive(Debug, Clone, Serialize)]
+pub struct MonthReportRequest {
+    /// Mes en formato `AAAA-MM`.
+    pub month: String,
+}
+
+/// Texto del tooltip: el resultado neto del mes en curso con el formato configurado.
+async fn tooltip_text(state: &AppState) -> Result<String, AppError> {
+    let db = state.db().await?;
+    let settings = settings::load_settings(&db);
+    let summary = dashboard::dashboard_summary(&db, Period::Mensual, periods::now_timestamp())?;
+    Ok(format!(
+        "{}\nBalance del mes: {}",
+        APP_NAME,
+        settings.format_money(summary.totals.net, &summary.base_currency, &summary.base_currency)
+    ))
+}
+
+/// Recalcula el tooltip en segundo plano. Con los datos bloqueados solo muestra el nombre.
+fn refresh_tooltip(app: &AppHandle) {
+    let app = app.clone();
+    tauri::async_runtime::spawn(async move {
+        let text = tooltip_text(&app.state::<AppState>()).await.unwrap_or_else(|e| {
+            debug!("Tray balance unavailable: {}", e);
+            APP_NAME.to_string()
+        });
+        if let Some(tray) = app.tray_by_id(TRAY_ID) {
+            if let Err(e) = tray.set_tooltip(Some(&text)) {
+                warn!("Failed to update tray tooltip: {}", e);
+            }
+        }
+    });
+}
+
+/// Muestra y enfoca la ventana principal (también si estaba oculta en la bandeja).
+fn show_main_window(app: &AppHandle) {
+    if let Err(e) = windows::focus_window(app, windows::MAIN_WINDOW_LABEL) {
+        warn!("Failed to show main window: {}", e);
+    }
+}
+
+fn open_month_report(app: &AppHandle) {
+    show_main_window(app);
+    let month = periods::today().format("%Y-%m").to_string();
+    events::emit(app, OPEN_MONTH_REPORT_EVENT, MonthReportRequest { month });
+}
+
+fn handle_menu_event(app: &AppHandle, event: MenuEvent) {
+    match event.id().as_ref() {
+        MENU_NEW_TRANSACTION => {
+            let app = app.clone();
+            tauri::async_runtime::spawn(async move {
+                if let Err(e) = windows::open_quick_entry_window_command(app).await {
+                    warn!("Failed to open quick entry from tray: {}", e);
+                }
+            });
+        }
+        MENU_MONTH_REPORT => open_month_report(app),
+        MENU_QUIT => {
+            info!("Quit requested from tray.");
+            app.exit(0);
+        }
+        other => debug!("Unknown tray menu item: {}", other),
+    }
+}
+
+/// Crea el icono de la bandeja con su menú y mantiene el tooltip al día con los
+/// eventos de cambio de datos.
+pub fn setup_tray(app: &AppHandle) -> tauri::Result<()> {
+    let menu = Menu::with_items(
+        app,
+        &[
+            &MenuItem::with_id(app, MENU_NEW_TRANSACTION, "Nueva transacción", true, None::<&str>)?,
+            &MenuItem::with_id(app, MENU_MONTH_REPORT, "Abrir informe del mes", true, None::<&str>)?,
+            &PredefinedMenuItem::separator(app)?,
+            &MenuItem::with_id(app, MENU_QUIT, "Salir", true, None::<&str>)?,
+        ],
+    )?;
+    let mut builder = TrayIconBuilder::with_id(TRAY_ID)
+        .tooltip(APP_NAME)
+        .menu(&menu)
+        .show_menu_on_left_click(false)
+        .on_menu_event(handle_menu_event)
+        .on_tray_icon_event(|tray, event| {
+            if let TrayIconEvent::Click { button: MouseButton::Left, button_state: MouseButtonState::Up, .. } = event {
+                show_main_window(tray.app_handle());
+            }
+        });
+    if let Some(icon) = app.default_window_icon() {
+        builder = builder.icon(icon.clone());
+    }
+    builder.build(app)?;
+
+    for event in [
+        events::TRANSACTION_CREATED_EVENT,
+        events::TRANSACTION_UPDATED_EVENT,
+        events::TRANSACTION_DELETED_EVENT,
+        events::CURRENCY_CHANGED_EVENT,
+        events::DATA_RELOADED_EVENT,
+    ] {
+        let handle = app.clone();
+        app.listen_any(event, move |_| refresh_tooltip(&handle));
+    }
+    let handle = app.clone();
+    app.listen_any(SETTINGS_CHANGED_EVENT, move |event| {
+        match serde_json::from_str::<Settings>(event.payload()) {
+            Ok(settings) => MINIMIZE_TO_TRAY.store(settings.minimize_to_tray, Ordering::SeqCst),
+            Err(e) => warn!("Invalid settings event payload: {}", e),
+        }
+        refresh_tooltip(&handle);
+    });
+
+    // El ajuste inicial se lee en segundo plano para no esperar a la base de datos.
+    let handle = app.clone();
+    tauri::async_runtime::spawn(async move {
+        if let Ok(db) = handle.state::<AppState>().db().await {
+            MINIMIZE_TO_TRAY.store(settings::load_settings(&db).minimize_to_tray, Ordering::SeqCst);
+        }
+    });
+    refresh_tooltip(app);
+    Ok(())
+}
+
+/// Con `minimize_to_tray`, cerrar la ventana principal la oculta en la bandeja en
+/// lugar de salir de la aplicación.
+pub fn handle_window_event(window: &Window, event: &WindowEvent) {
+    if let WindowEvent::CloseRequested { api, .. } = event {
+        if window.label() == windows::MAIN_WINDOW_LABEL && MINIMIZE_TO_TRAY.load(Ordering::SeqCst) {
+            api.prevent_close();
+            if let Err(e) = window.hide() {
+                warn!("Failed to hide main window: {}", e);
+            }
+        }
+    }
+}
+
+// --- Comandos Tauri ---
+
+/// Comando para mostrar la ventana principal con el informe del mes en curso, como
+/// la opción "Abrir informe del mes" de la bandeja.
+#[tauri::command]
+pub async fn open_month_report_command(app: AppHandle) -> Result<(), AppError> {
+    debug!("Received open_month_report_command.");
+    open_month_report(&app);
+    Ok(())
+}
+
+/// Comando para salir de la aplicación aunque `minimize_to_tray` esté activo. Los
+/// cambios pendientes se guardan al salir.
+#[tauri::command]
+pub async fn quit_app_command(app: AppHandle) -> Result<(), AppError> {
+    info!("Received quit_app_command.");
+    app.exit(0);
+    Ok(())
+}
@@ -0,0 +1,492 @@
+// src-tauri/src/users.rs
+
+use aes_gcm::aead::rand_core::RngCore;
+use aes_gcm::aead::OsRng;
+use argon2::Argon2;
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use rusqlite::{params, Connection, OptionalExtension, Row};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use tauri::{AppHandle, State};
+use log::{debug, error, info, warn};
+
+use crate::audit;
+use crate::currencies::RateTable;
+use crate::error::AppError;
+use crate::events::{self, ChangeAction};
+use crate::periods;
+use crate::reports::{self, GroupTotals};
+use crate::storage::{db_error, TransactionRepository};
+use crate::validation;
+use crate::{AppState, Transaction};
+
+// Perfiles ligeros para los empleados que comparten el equipo del mostrador. No son
+// cuentas con permisos: solo identifican quién registra cada transacción y cada
+// cambio del log de auditoría. La sesión es del proceso y se pierde al cerrar la
+// aplicación o al cambiar de espacio de trabajo.
+
+const SALT_LEN: usize = 16;
+const HASH_LEN: usize = 32;
+const MIN_PIN_LEN: usize = 4;
+const MAX_PIN_LEN: usize = 8;
+/// Intentos fallidos seguidos tras los que se bloquea el inicio de sesión del usuario.
+const MAX_FAILED_LOGINS: u32 = 5;
+const LOGIN_LOCKOUT: Duration = Duration::from_secs(60);
+
+/// Nombre del grupo de transacciones sin usuario en el desglose por usuario.
+const NO_USER: &str = "Sin usuario";
+/// Nombre del grupo de un usuario que ya se eliminó.
+const DELETED_USER: &str = "Usuario eliminado";
+
+/// Usuario con la sesión abierta.
+static SESSION: Mutex<Option<User>> = Mutex::new(None);
+/// Intentos fallidos seguidos por usuario y momento del último.
+static FAILED_LOGINS: Mutex<Option<HashMap<String, (u32, Instant)>>> = Mutex::new(None);
+
+/// Empleado que usa la aplicación. El PIN nunca sale de la base de datos.
+#[derive(Debug, Clone, Serialize)]
+pub struct User {
+    pub id: String,
+    pub name: String,
+    pub created_at: u64,
+    pub updated_at: u64,
+}
+
+/// Datos de `create_user_command` y `update_user_command`. Al modificar, sin `pin`
+/// se conserva el que tenía.
+#[derive(Clone, Deserialize)]
+pub struct UserInput {
+    pub name: String,
+    #[serde(default)]
+    pub pin: Option<String>,
+}
+
+const USER_COLUMNS: &str = "id, name, created_at, updated_at";
+
+fn row_to_user(row: &Row) -> rusqlite::Result<User> {
+    Ok(User {
+        id: row.get(0)?,
+        name: row.get(1)?,
+        created_at: row.get::<_, i64>(2)? as u64,
+        updated_at: row.get::<_, i64>(3)? as u64,
+    })
+}
+
+pub fn get_user(conn: &Connection, id: &str) -> Result<Option<User>, AppError> {
+    conn.query_row(&format!("SELECT {} FROM users WHERE id = ?1", USER_COLUMNS), params![id], row_to_user)
+        .optional()
+        .map_err(db_error)
+}
+
+/// Usuarios ordenados por nombre.
+pub fn list_users(conn: &Connection) -> Result<Vec<User>, AppError> {
+    let mut stmt = conn
+        .prepare(&format!("SELECT {} FROM users ORDER BY name COLLATE NOCASE", USER_COLUMNS))
+        .map_err(db_error)?;
+    let rows = stmt.query_map([], row_to_user).map_err(db_error)?;
+    rows.collect::<rusqlite::Result<Vec<_>>>().map_err(db_error)
+}
+
+// --- Sesión ---
+
+/// Usuario con la sesión abierta, si lo hay.
+pub fn current_user() -> Option<User> {
+    SESSION.lock().unwrap().clone()
+}
+
+pub fn current_user_id() -> Option<String> {
+    SESSION.lock().unwrap().as_ref().map(|u| u.id.clone())
+}
+
+pub fn current_user_name() -> Option<String> {
+    SESSION.lock().unwrap().as_ref().map(|u| u.name.clone())
+}
+
+/// Cierra la sesión abierta, si la hay.
+pub fn end_session() {
+    if let Some(user) = SESSION.lock().unwrap().take() {
+        info!("Session of user {} ended.", user.id);
+    }
+}
+
+/// Deja solo las transacciones que registró `user_id`; sin usuario no filtra nada.
+pub fn retain_user(transactions: &mut Vec<Transaction>, user_id: Option<&str>) {
+    if let Some(user_id) = user_id.map(str::trim).filter(|u| !u.is_empty()) {
+        transactions.retain(|t| t.user_id.as_deref() == Some(user_id));
+    }
+}
+
+// --- PIN ---
+
+fn hash_pin(pin: &str, salt: &[u8]) -> Result<String, AppError> {
+    let mut hash = [0u8; HASH_LEN];
+    Argon2::default().hash_password_into(pin.as_bytes(), salt, &mut hash).map_err(|e| {
+        error!("Argon2 PIN hashing failed: {}", e);
+        AppError::Internal(format!("No se pudo proteger el PIN: {}", e))
+    })?;
+    Ok(STANDARD.encode(hash))
+}
+
+/// Sal nueva y hash del PIN, ambos en base64.
+fn new_pin_hash(pin: &str) -> Result<(String, String), AppError> {
+    let mut salt = [0u8; SALT_LEN];
+    OsRng.fill_bytes(&mut salt);
+    Ok((hash_pin(pin, &salt)?, STANDARD.encode(salt)))
+}
+
+/// Comprueba `pin` con el hash guardado del usuario.
+fn verify_pin(conn: &Connection, id: &str, pin: &str) -> Result<bool, AppError> {
+    let (stored_hash, salt): (String, String) = conn
+        .query_row("SELECT pin_hash, pin_salt FROM users WHERE id = ?1", params![id], |row| {
+            Ok((row.get(0)?, row.get(1)?))
+        })
+        .map_err(db_error)?;
+    let salt = STANDARD.decode(salt).map_err(|e| {
+        error!("Invalid PIN salt for user {}: {}", id, e);
+        AppError::Internal("El PIN guardado del usuario está dañado.".to_string())
+    })?;
+    Ok(hash_pin(pin, &salt)? == stored_hash)
+}
+
+/// El PIN tiene que ser de 4 a 8 cifras.
+fn validate_pin(pin: &str) -> Result<(), AppError> {
+    let len = pin.chars().count();
+    if !(MIN_PIN_LEN..=MAX_PIN_LEN).contains(&len) || !pin.chars().all(|c| c.is_ascii_digit()) {
+        return Err(AppError::field_error(
+            "pin",
+            validation::INVALID_FORMAT,
+            format!("El PIN debe tener entre {} y {} cifras.", MIN_PIN_LEN, MAX_PIN_LEN),
+        ));
+    }
+    Ok(())
+}
+
+/// Valida y normaliza los datos de un usuario. Al crear (`except_id` vacío) el PIN es
+/// obligatorio.
+fn resolve_input(conn: &Connection, input: UserInput, except_id: Option<&str>) -> Result<UserInput, AppError> {
+    let name = input.name.trim().to_owned();
+    if name.is_empty() {
+        return Err(AppError::invalid_field("name", "El nombre del usuario no puede estar vacío."));
+    }
+    let exists: bool = conn
+        .query_row(
+            "SELECT EXISTS(SELECT 1 FROM users WHERE name = ?1 COLLATE NOCASE AND id IS NOT ?2)",
+            params![name, except_id],
+            |row| row.get(0),
+        )
+        .map_err(db_error)?;
+    if exists {
+        return Err(AppError::Conflict(format!("Ya existe un usuario llamado '{}'.", name)));
+    }
+    let pin = input.pin.map(|p| p.trim().to_owned()).filter(|p| !p.is_empty());
+    match &pin {
+        Some(pin) => validate_pin(pin)?,
+        None if except_id.is_none() => {
+            return Err(AppError::field_error("pin", validation::REQUIRED, "Indica el PIN del usuario."));
+        }
+        None => {}
+    }
+    Ok(UserInput { name, pin })
+}
+
+/// Error si el usuario ha fallado demasiadas veces seguidas hace poco.
+fn check_lockout(id: &str) -> Result<(), AppError> {
+    let failed = FAILED_LOGINS.lock().unwrap();
+    if let Some((count, last)) = failed.as_ref().and_then(|f| f.get(id)) {
+        if *count >= MAX_FAILED_LOGINS && last.elapsed() < LOGIN_LOCKOUT {
+            warn!("Login of user {} blocked after {} failed attempts.", id, count);
+            return Err(AppError::invalid_field(
+                "pin",
+                "Demasiados intentos fallidos. Espera un minuto antes de volver a intentarlo.",
+            ));
+        }
+    }
+    Ok(())
+}
+
+fn record_login_attempt(id: &str, success: bool) {
+    let mut failed = FAILED_LOGINS.lock().unwrap();
+    let failed = failed.get_or_insert_with(HashMap::new);
+    if success {
+        failed.remove(id);
+        return;
+    }
+    let entry = failed.entry(id.to_owned()).or_insert((0, Instant::now()));
+    if entry.1.elapsed() >= LOGIN_LOCKOUT {
+        entry.0 = 0;
+    }
+    entry.0 += 1;
+    entry.1 = Instant::now();
+}
+
+// --- Ventas por usuario ---
+
+/// Ingresos y gastos registrados por un usuario, en la moneda base.
+#[derive(Debug, Clone, Serialize)]
+pub struct UserRevenue {
+    /// `None` para las transacciones sin usuario.
+    pub user_id: Option<String>,
+    pub name: String,
+    #[serde(flatten)]
+    pub totals: GroupTotals,
+}
+
+/// Desglose por usuario de las transacciones del rango `[from, to]`, de mayor a menor
+/// ingreso.
+pub fn build_user_revenue(
+    transactions: &[Transaction],
+    users: &[User],
+    rates: &RateTable,
+    from: Option<u64>,
+    to: Option<u64>,
+) -> Result<Vec<UserRevenue>, AppError> {
+    let names: HashMap<&str, &str> = users.iter().map(|u| (u.id.as_str(), u.name.as_str())).collect();
+    let mut by_user: BTreeMap<Option<&str>, GroupTotals> = BTreeMap::new();
+    for transaction in transactions.iter().filter(|t| reports::in_range(t.timestamp, from, to)) {
+        let amount = rates.to_base(transaction.amount, &transaction.currency)?;
+        by_user
+            .entry(transaction.user_id.as_deref())
+            .or_default()
+            .add(&transaction.transaction_type, amount);
+    }
+
+    let mut revenue: Vec<UserRevenue> = by_user
+        .into_iter()
+        .map(|(id, totals)| UserRevenue {
+            user_id: id.map(str::to_owned),
+            name: match id {
+                Some(id) => names.get(id).copied().unwrap_or(DELETED_USER).to_string(),
+                None => NO_USER.to_string(),
+            },
+            totals,
+        })
+        .collect();
+    revenue.sort_by(|a, b| b.totals.income.cmp(&a.totals.income).then_with(|| a.name.cmp(&b.name)));
+    Ok(revenue)
+}
+
+// --- Comandos Tauri ---
+
+/// Comando para listar los usuarios de la aplicación.
+#[tauri::command]
+pub async fn list_users_command(state: State<'_, AppState>) -> Result<Vec<User>, AppError> {
+    debug!("Received list_users_command.");
+    list_users(state.db().await?.connection())
+}
+
+/// Comando para añadir un usuario con su PIN.
+#[tauri::command]
+pub async fn create_user_command(state: State<'_, AppState>, app: AppHandle, user: UserInput) -> Result<User, AppError> {
+    debug!("Received create_user_command: {}", user.name);
+    let db = state.db().await?;
+    let user = resolve_input(db.connection(), user, None)?;
+    let (pin_hash, pin_salt) = new_pin_hash(user.pin.as_deref().unwrap_or_default())?;
+    let id = uuid::Uuid::new_v4().to_string();
+    let now = periods::now_timestamp() as i64;
+    db.connection()
+        .execute(
+            "INSERT INTO users (id, name, pin_hash, pin_salt, created_at, updated_at) VALUES (?1, ?2, ?3, ?4, ?5, ?5)",
+            params![id, user.name, pin_hash, pin_salt, now],
+        )
+        .map_err(db_error)?;
+    let created = get_user(db.connection(), &id)?
+        .ok_or_else(|| AppError::Internal(format!("No se encontró el usuario recién creado {}.", id)))?;
+    audit::record(db.connection(), "create_user_command", Some(&id), None, audit::snapshot(&created));
+    events::emit_entity(&app, events::USER_CHANGED_EVENT, ChangeAction::Created, &id);
+    info!("User '{}' created.", created.name);
+    Ok(created)
+}
+
+/// Comando para cambiar el nombre o el PIN de un usuario.
+#[tauri::command]
+pub async fn update_user_command(
+    state: State<'_, AppState>,
+    app: AppHandle,
+    id: String,
+    user: UserInput,
+) -> Result<User, AppError> {
+    debug!("Received update_user_command for ID {}: {}", id, user.name);
+    let db = state.db().await?;
+    let Some(before) = get_user(db.connection(), &id)? else {
+        error!("User {} not found for update.", id);
+        return Err(AppError::NotFound(format!("Usuario con ID {} no encontrado.", id)));
+    };
+    let user = resolve_input(db.connection(), user, Some(&id))?;
+    let now = periods::now_timestamp() as i64;
+    let tx = db.connection().unchecked_transaction().map_err(db_error)?;
+    tx.execute("UPDATE users SET name = ?2, updated_at = ?3 WHERE id = ?1", params![id, user.name, now])
+        .map_err(db_error)?;
+    if let Some(pin) = &user.pin {
+        let (pin_hash, pin_salt) = new_pin_hash(pin)?;
+        tx.execute("UPDATE users SET pin_hash = ?2, pin_salt = ?3 WHERE id = ?1", params![id, pin_hash, pin_salt])
+            .map_err(db_error)?;
+    }
+    tx.commit().map_err(db_error)?;
+    let updated = get_user(db.connection(), &id)?
+        .ok_or_else(|| AppError::NotFound(format!("Usuario con ID {} no encontrado.", id)))?;
+    {
+        let mut session = SESSION.lock().unwrap();
+        if session.as_ref().is_some_and(|u| u.id == id) {
+            *session = Some(updated.clone());
+        }
+    }
+    audit::record(
+        db.connection(),
+        "update_user_command",
+        Some(&id),
+        audit::snapshot(&before),
+        audit::snapshot(&updated),
+    );
+    events::emit_entity(&app, events::USER_CHANGED_EVENT, ChangeAction::Updated, &id);
+    Ok(updated)
+}
+
+/// Comando para eliminar un usuario. Sus transacciones y registros de auditoría
+/// conservan el ID y en los informes aparecen como de un usuario eliminado.
+#[tauri::command]
+pub async fn delete_user_command(state: State<'_, AppState>, app: AppHandle, id: String) -> Result<(), AppError> {
+    debug!("Received delete_user_command: {}", id);
+    let db = state.db().await?;
+    let Some(before) = get_user(db.connection(), &id)? else {
+        error!("User {} not found for deletion.", id);
+        return Err(AppError::NotFound(format!("Usuario con ID {} no encontrado.", id)));
+    };
+    db.connection().execute("DELETE FROM users WHERE id = ?1", params![id]).map_err(db_error)?;
+    audit::record(db.connection(), "delete_user_command", Some(&id), audit::snapshot(&before), None);
+    let ended = {
+        let mut session = SESSION.lock().unwrap();
+        let ended = session.as_ref().is_some_and(|u| u.id == id);
+        if ended {
+            *session = None;
+        }
+        ended
+    };
+    if ended {
+        events::emit(&app, events::SESSION_CHANGED_EVENT, None::<User>);
+    }
+    events::emit_entity(&app, events::USER_CHANGED_EVENT, ChangeAction::Deleted, &id);
+    info!("User '{}' deleted.", before.name);
+    Ok(())
+}
+
+/// Comando para abrir la sesión de un usuario con su PIN. Las transacciones que se
+/// registren a partir de ahora quedan a su nombre.
+#[tauri::command]
+pub async fn login_command(
+    state: State<'_, AppState>,
+    app: AppHandle,
+    user_id: String,
+    pin: String,
+) -> Result<User, AppError> {
+    debug!("Received login_command for user {}", user_id);
+    let db = state.db().await?;
+    let Some(user) = get_user(db.connection(), &user_id)? else {
+        error!("User {} not found for login.", user_id);
+        return Err(AppError::NotFound(format!("Usuario con ID {} no encontrado.", user_id)));
+    };
+    check_lockout(&user_id)?;
+    let valid = verify_pin(db.connection(), &user_id, pin.trim())?;
+    record_login_attempt(&user_id, valid);
+    if !valid {
+        warn!("Wrong PIN for user {}.", user_id);
+        return Err(AppError::invalid_field("pin", "El PIN no es correcto."));
+    }
+    *SESSION.lock().unwrap() = Some(user.clone());
+    audit::record(db.connection(), "login_command", Some(&user_id), None, None);
+    events::emit(&app, events::SESSION_CHANGED_EVENT, Some(user.clone()));
+    info!("User '{}' logged in.", user.name);
+    Ok(user)
+}
+
+/// Comando para cerrar la sesión del usuario actual.
+#[tauri::command]
+pub async fn logout_command(state: State<'_, AppState>, app: AppHandle) -> Result<(), AppError> {
+    debug!("Received logout_command.");
+    let Some(user) = current_user() else {
+        return Ok(());
+    };
+    let db = state.db().await?;
+    audit::record(db.connection(), "logout_command", Some(&user.id), None, None);
+    end_session();
+    events::emit(&app, events::SESSION_CHANGED_EVENT, None::<User>);
+    Ok(())
+}
+
+/// Comando para saber qué usuario tiene la sesión abierta (`null` si ninguno).
+#[tauri::command]
+pub async fn get_current_user_command() -> Result<Option<User>, AppError> {
+    debug!("Received get_current_user_command.");
+    Ok(current_user())
+}
+
+/// Comando para obtener los ingresos y gastos registrados por cada usuario entre
+/// `from` y `to` (segundos Unix, inclusivos y opcionales).
+#[tauri::command]
+pub async fn get_revenue_by_user_command(
+    state: State<'_, AppState>,
+    from: Option<u64>,
+    to: Option<u64>,
+) -> Result<Vec<UserRevenue>, AppError> {
+    debug!("Received get_revenue_by_user_command: from={:?}, to={:?}", from, to);
+    if let (Some(from), Some(to)) = (from, to) {
+        if from > to {
+            error!("Invalid report range: from={} > to={}", from, to);
+            return Err(AppError::invalid_field("from", "La fecha inicial no puede ser posterior a la fecha final."));
+        }
+    }
+
+    let db = state.db().await?;
+    let rates = RateTable::load(&db)?;
+    let users = list_users(db.connection())?;
+    build_user_revenue(&db.list_transactions()?, &users, &rates, from, to)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::storage::SqliteStorage;
+
+    #[test]
+    fn validate_pin_accepts_only_four_to_eight_digits() {
+        validate_pin("0000").unwrap();
+        validate_pin("12345678").unwrap();
+        for pin in ["123", "123456789", "12a4", "12 4"] {
+            assert!(validate_pin(pin).is_err(), "{} should be rejected", pin);
+        }
+    }
+
+    #[test]
+    fn verify_pin_checks_the_salted_hash() {
+        let db = SqliteStorage::open_in_memory().unwrap();
+        let (pin_hash, pin_salt) = new_pin_hash("1234").unwrap();
+        db.connection()
+            .execute(
+                "INSERT INTO users (id, name, pin_hash, pin_salt, created_at, updated_at) VALUES ('u1', 'Ana', ?1, ?2, 0, 0)",
+                params![pin_hash, pin_salt],
+            )
+            .unwrap();
+        assert!(verify_pin(db.connection(), "u1", "1234").unwrap());
+        assert!(!verify_pin(db.connection(), "u1", "4321").unwrap());
+    }
+
+    #[test]
+    fn too_many_failed_logins_lock_the_user_until_a_success() {
+        // Cada prueba usa su propio ID: `FAILED_LOGINS` es global.
+        let id = uuid::Uuid::new_v4().to_string();
+        for _ in 1..MAX_FAILED_LOGINS {
+            record_login_attempt(&id, false);
+        }
+        check_lockout(&id).unwrap();
+
+        record_login_attempt(&id, false);
+        assert!(matches!(check_lockout(&id), Err(AppError::Validation { .. })));
+        check_lockout("otro-usuario").unwrap();
+
+        record_login_attempt(&id, true);
+        check_lockout(&id).unwrap();
+    }
+}
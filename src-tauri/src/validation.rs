@@ -0,0 +1,271 @@
+// src-tauri/src/validation.rs
+
+use chrono::{Datelike, NaiveDate};
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use tauri::State;
+use log::{debug, error};
+
+use crate::error::AppError;
+use crate::money;
+use crate::periods;
+use crate::settings;
+use crate::storage::SqliteStorage;
+use crate::{AppState, Transaction, TransactionType};
+
+// Códigos de `ValidationError::code`. Son estables: el frontend los usa para elegir
+// el mensaje traducido y el estilo del campo.
+pub const INVALID: &str = "invalid";
+pub const REQUIRED: &str = "required";
+pub const NOT_POSITIVE: &str = "not_positive";
+pub const TOO_MANY_DECIMALS: &str = "too_many_decimals";
+pub const TOO_LONG: &str = "too_long";
+pub const TOO_MANY: &str = "too_many";
+pub const DUPLICATED: &str = "duplicated";
+pub const FUTURE_DATE: &str = "future_date";
+pub const OUT_OF_RANGE: &str = "out_of_range";
+pub const INVALID_FORMAT: &str = "invalid_format";
+pub const NOT_FOUND: &str = "not_found";
+
+/// Fecha más antigua admitida para una transacción.
+const MIN_TRANSACTION_YEAR: i32 = 1970;
+/// Longitud máxima de las notas, en caracteres.
+const MAX_NOTES_LENGTH: usize = 2000;
+/// Número máximo de campos personalizados por transacción.
+const MAX_CUSTOM_FIELDS: usize = 20;
+/// Longitud máxima del nombre y del valor de un campo personalizado.
+const MAX_CUSTOM_FIELD_NAME_LENGTH: usize = 40;
+const MAX_CUSTOM_FIELD_VALUE_LENGTH: usize = 200;
+
+/// Error de un campo concreto. `field` es el nombre del argumento (o una ruta como
+/// `rows[3].amount` en las importaciones) y `message`, el texto en español.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ValidationError {
+    pub field: String,
+    pub code: String,
+    pub message: String,
+}
+
+impl ValidationError {
+    pub fn new(field: &str, code: &str, message: impl Into<String>) -> Self {
+        ValidationError { field: field.to_owned(), code: code.to_owned(), message: message.into() }
+    }
+}
+
+/// Acumula los errores de validación de una operación para devolverlos todos a la
+/// vez, en lugar de solo el primero.
+#[derive(Debug, Default)]
+pub struct Validator {
+    errors: Vec<ValidationError>,
+}
+
+impl Validator {
+    pub fn new() -> Self {
+        Validator::default()
+    }
+
+    pub fn add(&mut self, field: &str, code: &str, message: impl Into<String>) {
+        self.errors.push(ValidationError::new(field, code, message));
+    }
+
+    /// Devuelve el valor de `result`, o `None` si es un error de validación, que se
+    /// guarda. Los demás errores (base de datos, etc.) se devuelven tal cual.
+    pub fn check<T>(&mut self, result: Result<T, AppError>) -> Result<Option<T>, AppError> {
+        match result {
+            Ok(value) => Ok(Some(value)),
+            Err(AppError::Validation { message, field, errors }) => {
+                if errors.is_empty() {
+                    self.add(field.as_deref().unwrap_or_default(), INVALID, message);
+                } else {
+                    self.errors.extend(errors);
+                }
+                Ok(None)
+            }
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Como `check`, pero atribuye los errores a `prefix` + su campo (p. ej. la fila
+    /// de una importación).
+    pub fn check_prefixed<T>(&mut self, prefix: &str, result: Result<T, AppError>) -> Result<Option<T>, AppError> {
+        let start = self.errors.len();
+        let value = self.check(result)?;
+        for error in &mut self.errors[start..] {
+            error.field = if error.field.is_empty() { prefix.to_owned() } else { format!("{}.{}", prefix, error.field) };
+        }
+        Ok(value)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.errors.is_empty()
+    }
+
+    /// `Ok` si no hay errores; si no, un error `validation` con todos ellos.
+    pub fn finish(self) -> Result<(), AppError> {
+        if self.errors.is_empty() {
+            Ok(())
+        } else {
+            Err(AppError::invalid_fields(self.errors))
+        }
+    }
+}
+
+/// Comprobaciones configurables en los ajustes que se aplican a toda transacción que
+/// se crea o se modifica (a mano, por lotes, con plantillas, transferencias, la API
+/// local o importaciones).
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ValidationPolicy {
+    /// Importe máximo de una transacción, en su propia moneda. Sin límite si no se indica.
+    pub max_transaction_amount: Option<Decimal>,
+    /// Rechazar transacciones con fecha futura.
+    pub disallow_future_dates: bool,
+    /// Exigir categoría en los ingresos y gastos (las transferencias no tienen). No se
+    /// aplica a las importaciones de extractos, que se clasifican después.
+    pub require_category: bool,
+}
+
+impl Default for ValidationPolicy {
+    fn default() -> Self {
+        ValidationPolicy { max_transaction_amount: None, disallow_future_dates: true, require_category: false }
+    }
+}
+
+impl ValidationPolicy {
+    /// Valida los límites configurados. El importe máximo debe ser un importe válido.
+    pub fn validate(&self) -> Result<(), AppError> {
+        if let Some(max) = self.max_transaction_amount {
+            money::validate_amount(
+                "validation_policy.max_transaction_amount",
+                max,
+                "El importe máximo debe ser positivo.",
+            )?;
+        }
+        Ok(())
+    }
+
+    /// `Ok` si `transaction` cumple la política; si no, un error `validation` con cada
+    /// regla incumplida.
+    pub fn check(&self, transaction: &Transaction) -> Result<(), AppError> {
+        let mut validator = Validator::new();
+        if let Some(max) = self.max_transaction_amount.filter(|max| transaction.amount > *max) {
+            validator.add(
+                "amount",
+                OUT_OF_RANGE,
+                format!("El importe supera el máximo permitido por transacción ({} {}).", max, transaction.currency),
+            );
+        }
+        if self.disallow_future_dates && transaction.transaction_date > periods::today() {
+            error!("Future transaction date: {}", transaction.transaction_date);
+            validator.add("transaction_date", FUTURE_DATE, "La fecha de la transacción no puede ser futura.");
+        }
+        if self.require_category
+            && transaction.transaction_type != TransactionType::Transferencia
+            && transaction.category.is_none()
+        {
+            validator.add("category", REQUIRED, "Indica la categoría de la transacción.");
+        }
+        validator.finish()
+    }
+}
+
+/// Política de validación de los ajustes guardados.
+pub fn load_policy(db: &SqliteStorage) -> ValidationPolicy {
+    settings::load_settings(db).validation_policy
+}
+
+/// Aplica la política de validación guardada a `transaction`.
+pub fn enforce_policy(db: &SqliteStorage, transaction: &Transaction) -> Result<(), AppError> {
+    load_policy(db).check(transaction)
+}
+
+/// Texto obligatorio, sin espacios al principio ni al final.
+pub fn required_text(field: &str, value: &str, message: &str) -> Result<String, AppError> {
+    let value = value.trim();
+    if value.is_empty() {
+        return Err(AppError::field_error(field, REQUIRED, message));
+    }
+    Ok(value.to_owned())
+}
+
+/// Rechaza fechas anteriores a `MIN_TRANSACTION_YEAR`. Las fechas futuras dependen de
+/// la política de validación (`ValidationPolicy::disallow_future_dates`).
+pub fn validate_transaction_date(date: NaiveDate) -> Result<NaiveDate, AppError> {
+    if date.year() < MIN_TRANSACTION_YEAR {
+        error!("Transaction date too old: {}", date);
+        return Err(AppError::field_error(
+            "transaction_date",
+            OUT_OF_RANGE,
+            format!("La fecha de la transacción no puede ser anterior a {}.", MIN_TRANSACTION_YEAR),
+        ));
+    }
+    Ok(date)
+}
+
+pub fn validate_notes(notes: String) -> Result<String, AppError> {
+    let notes = notes.trim().to_owned();
+    if notes.chars().count() > MAX_NOTES_LENGTH {
+        error!("Notes too long: {} chars", notes.chars().count());
+        return Err(AppError::field_error(
+            "notes",
+            TOO_LONG,
+            format!("Las notas admiten como máximo {} caracteres.", MAX_NOTES_LENGTH),
+        ));
+    }
+    Ok(notes)
+}
+
+/// Quita los espacios de nombres y valores y descarta los campos sin valor.
+pub fn validate_custom_fields(fields: HashMap<String, String>) -> Result<HashMap<String, String>, AppError> {
+    let mut resolved = HashMap::new();
+    for (name, value) in fields {
+        let (name, value) = (name.trim().to_owned(), value.trim().to_owned());
+        if value.is_empty() {
+            continue;
+        }
+        if name.is_empty() {
+            return Err(AppError::field_error(
+                "custom_fields",
+                REQUIRED,
+                "Los campos personalizados necesitan un nombre.",
+            ));
+        }
+        if name.chars().count() > MAX_CUSTOM_FIELD_NAME_LENGTH || value.chars().count() > MAX_CUSTOM_FIELD_VALUE_LENGTH {
+            error!("Custom field too long: '{}'", name);
+            return Err(AppError::field_error(
+                "custom_fields",
+                TOO_LONG,
+                format!(
+                    "Los nombres de campo admiten {} caracteres y los valores {}.",
+                    MAX_CUSTOM_FIELD_NAME_LENGTH, MAX_CUSTOM_FIELD_VALUE_LENGTH
+                ),
+            ));
+        }
+        if resolved.insert(name.clone(), value).is_some() {
+            return Err(AppError::field_error(
+                "custom_fields",
+                DUPLICATED,
+                format!("El campo '{}' está repetido.", name),
+            ));
+        }
+    }
+    if resolved.len() > MAX_CUSTOM_FIELDS {
+        return Err(AppError::field_error(
+            "custom_fields",
+            TOO_MANY,
+            format!("Una transacción admite como máximo {} campos personalizados.", MAX_CUSTOM_FIELDS),
+        ));
+    }
+    Ok(resolved)
+}
+
+// --- Comandos Tauri ---
+
+/// Comando para obtener la política de validación vigente, para que el formulario
+/// aplique las mismas reglas antes de enviar. Se cambia con `update_settings_command`.
+#[tauri::command]
+pub async fn get_validation_policy_command(state: State<'_, AppState>) -> Result<ValidationPolicy, AppError> {
+    debug!("Received get_validation_policy_command.");
+    Ok(load_policy(&state.db().await?))
+}
@@ -0,0 +1,437 @@
+// src-tauri/src/webhooks.rs
+
+use aes_gcm::aead::rand_core::RngCore;
+use aes_gcm::aead::OsRng;
+use base64::Engine;
+use hmac::{Hmac, Mac};
+use reqwest::{Client, StatusCode, Url};
+use rusqlite::{params, Connection, Row};
+use serde::Serialize;
+use serde_json::Value;
+use sha2::Sha256;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Mutex, OnceLock};
+use std::time::{Duration, Instant};
+use tauri::{AppHandle, Manager, State};
+use tokio::sync::Notify;
+use log::{debug, info, warn};
+
+use crate::budgets::BUDGET_ALERT_EVENT;
+use crate::error::AppError;
+use crate::events;
+use crate::keychain;
+use crate::periods;
+use crate::settings;
+use crate::storage::db_error;
+use crate::sync;
+use crate::AppState;
+
+/// Nombre del secreto con el que se firman los envíos.
+const SECRET_NAME: &str = "webhook_secret";
+/// Eventos que se reenvían a los webhooks.
+const WEBHOOK_EVENTS: &[&str] = &[
+    events::TRANSACTION_CREATED_EVENT,
+    events::TRANSACTION_UPDATED_EVENT,
+    events::TRANSACTION_DELETED_EVENT,
+    BUDGET_ALERT_EVENT,
+];
+/// Número máximo de URLs configuradas.
+const MAX_WEBHOOKS: usize = 10;
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+/// Cada cuánto se reintentan los envíos pendientes aunque no haya eventos nuevos.
+const WORKER_INTERVAL: Duration = Duration::from_secs(30);
+/// Espera antes del primer reintento; se duplica con cada fallo hasta `MAX_BACKOFF_SECS`.
+const BASE_BACKOFF_SECS: u64 = 30;
+const MAX_BACKOFF_SECS: u64 = 6 * 60 * 60;
+/// Intentos tras los que un envío se da por fallido.
+const MAX_ATTEMPTS: u32 = 10;
+/// Eventos que se guardan en memoria a la espera del trabajador, como máximo.
+const MAX_BUFFERED_EVENTS: usize = 1000;
+
+/// Eventos emitidos que aún no se han pasado a la cola de la base de datos. Se
+/// guardan aquí porque quien emite suele tener la base de datos bloqueada.
+static BUFFER: Mutex<Vec<WebhookPayload>> = Mutex::new(Vec::new());
+static WAKE: OnceLock<Notify> = OnceLock::new();
+/// `true` mientras se procesan los envíos, para no hacerlo dos veces.
+static PROCESSING: AtomicBool = AtomicBool::new(false);
+
+/// Cuerpo JSON que recibe cada webhook.
+#[derive(Debug, Clone, Serialize)]
+pub struct WebhookPayload {
+    /// ID del evento; se repite en los reintentos, para detectar duplicados.
+    pub id: String,
+    /// `transaction.created`, `transaction.updated`, `transaction.deleted`,
+    /// `budget.alert` o `webhook.test`.
+    pub event: String,
+    pub created_at: u64,
+    pub data: Value,
+}
+
+impl WebhookPayload {
+    fn new(event: String, data: Value) -> Self {
+        WebhookPayload { id: uuid::Uuid::new_v4().to_string(), event, created_at: periods::now_timestamp(), data }
+    }
+}
+
+/// Envío guardado en la cola de reintentos.
+#[derive(Debug, Clone, Serialize)]
+pub struct WebhookDelivery {
+    pub id: String,
+    pub url: String,
+    pub event: String,
+    /// `pending` mientras se sigue reintentando; `failed` si se agotaron los intentos
+    /// o el servidor rechazó el envío.
+    pub status: String,
+    pub attempts: u32,
+    pub next_attempt_at: u64,
+    pub last_error: Option<String>,
+    pub created_at: u64,
+}
+
+/// Resultado de `test_webhook_command`.
+#[derive(Debug, Clone, Serialize)]
+pub struct WebhookTestResult {
+    pub status: u16,
+    pub duration_ms: u64,
+}
+
+/// `transaction://created` → `transaction.created`.
+fn public_event_name(event: &str) -> String {
+    event.replace("://", ".")
+}
+
+/// Apunta `event` para enviarlo a los webhooks si es de los que se reenvían. Lo llama
+/// `events::emit`; el envío lo hace el trabajador en segundo plano.
+pub fn notify<S: Serialize>(event: &str, payload: &S) {
+    if !WEBHOOK_EVENTS.contains(&event) {
+        return;
+    }
+    let data = match serde_json::to_value(payload) {
+        Ok(data) => data,
+        Err(e) => {
+            warn!("Could not serialize webhook payload for {}: {}", event, e);
+            return;
+        }
+    };
+    {
+        let mut buffer = BUFFER.lock().unwrap();
+        if buffer.len() >= MAX_BUFFERED_EVENTS {
+            warn!("Webhook buffer full; dropping {} event.", event);
+            return;
+        }
+        buffer.push(WebhookPayload::new(public_event_name(event), data));
+    }
+    WAKE.get_or_init(Notify::new).notify_one();
+}
+
+/// Valida y normaliza las URLs de los ajustes: solo `http` o `https`, sin repetir.
+pub fn validate_urls(urls: &[String]) -> Result<Vec<String>, AppError> {
+    if urls.len() > MAX_WEBHOOKS {
+        return Err(AppError::invalid_field(
+            "webhook_urls",
+            format!("Como máximo se pueden configurar {} webhooks.", MAX_WEBHOOKS),
+        ));
+    }
+    let mut valid: Vec<String> = Vec::new();
+    for url in urls.iter().map(|u| u.trim()).filter(|u| !u.is_empty()) {
+        let parsed = Url::parse(url)
+            .map_err(|_| AppError::invalid_field("webhook_urls", format!("La dirección {} no es válida.", url)))?;
+        if !matches!(parsed.scheme(), "http" | "https") {
+            return Err(AppError::invalid_field("webhook_urls", format!("La dirección {} debe empezar por http o https.", url)));
+        }
+        if !valid.iter().any(|v| v == url) {
+            valid.push(url.to_owned());
+        }
+    }
+    Ok(valid)
+}
+
+/// Secreto de firma; se genera y se guarda en el llavero la primera vez.
+fn signing_secret() -> Result<String, AppError> {
+    if let Some(secret) = keychain::get_secret(SECRET_NAME)?.filter(|s| !s.is_empty()) {
+        return Ok(secret);
+    }
+    let secret = generate_secret();
+    keychain::set_secret(SECRET_NAME, &secret)?;
+    info!("Webhook signing secret generated.");
+    Ok(secret)
+}
+
+fn generate_secret() -> String {
+    let mut bytes = [0u8; 32];
+    OsRng.fill_bytes(&mut bytes);
+    base64::engine::general_purpose::URL_SAFE_NO_PAD.encode(bytes)
+}
+
+/// Firma `<timestamp>.<cuerpo>` con HMAC-SHA256, en hexadecimal.
+fn sign(secret: &str, timestamp: u64, body: &str) -> String {
+    let mut mac = Hmac::<Sha256>::new_from_slice(secret.as_bytes()).expect("HMAC acepta claves de cualquier tamaño");
+    mac.update(timestamp.to_string().as_bytes());
+    mac.update(b".");
+    mac.update(body.as_bytes());
+    sync::hex(&mac.finalize().into_bytes())
+}
+
+/// Fallo de un envío. `retry` es `false` si repetirlo no serviría (p. ej. un 404).
+struct DeliveryError {
+    message: String,
+    retry: bool,
+}
+
+/// Envía `body` a `url` con las cabeceras de firma: `X-Webhook-Event`,
+/// `X-Webhook-Id`, `X-Webhook-Timestamp` y `X-Webhook-Signature: sha256=<hex>`.
+async fn deliver(client: &Client, secret: &str, url: &str, payload_id: &str, event: &str, body: &str) -> Result<StatusCode, DeliveryError> {
+    let timestamp = periods::now_timestamp();
+    let response = client
+        .post(url)
+        .header("Content-Type", "application/json")
+        .header("X-Webhook-Event", event)
+        .header("X-Webhook-Id", payload_id)
+        .header("X-Webhook-Timestamp", timestamp.to_string())
+        .header("X-Webhook-Signature", format!("sha256={}", sign(secret, timestamp, body)))
+        .body(body.to_owned())
+        .send()
+        .await
+        .map_err(|e| DeliveryError { message: format!("No se pudo conectar: {}", e), retry: true })?;
+    let status = response.status();
+    if status.is_success() {
+        return Ok(status);
+    }
+    let retry = status.is_server_error() || matches!(status, StatusCode::REQUEST_TIMEOUT | StatusCode::TOO_MANY_REQUESTS);
+    Err(DeliveryError { message: format!("El servidor respondió {}", status), retry })
+}
+
+fn http_client() -> Result<Client, AppError> {
+    Client::builder()
+        .timeout(REQUEST_TIMEOUT)
+        .build()
+        .map_err(|e| AppError::Internal(format!("No se pudo crear el cliente HTTP: {}", e)))
+}
+
+fn backoff_secs(attempts: u32) -> u64 {
+    BASE_BACKOFF_SECS.saturating_mul(1 << attempts.min(20)).min(MAX_BACKOFF_SECS)
+}
+
+// --- Cola ---
+
+fn row_to_delivery(row: &Row) -> rusqlite::Result<WebhookDelivery> {
+    Ok(WebhookDelivery {
+        id: row.get(0)?,
+        url: row.get(1)?,
+        event: row.get(2)?,
+        status: row.get(3)?,
+        attempts: row.get(4)?,
+        next_attempt_at: row.get::<_, i64>(5)? as u64,
+        last_error: row.get(6)?,
+        created_at: row.get::<_, i64>(7)? as u64,
+    })
+}
+
+const DELIVERY_COLUMNS: &str = "id, url, event, status, attempts, next_attempt_at, last_error, created_at";
+
+pub fn list_deliveries(conn: &Connection) -> Result<Vec<WebhookDelivery>, AppError> {
+    let mut stmt = conn
+        .prepare(&format!("SELECT {} FROM webhook_deliveries ORDER BY created_at DESC, id", DELIVERY_COLUMNS))
+        .map_err(db_error)?;
+    let rows = stmt.query_map([], row_to_delivery).map_err(db_error)?;
+    rows.collect::<rusqlite::Result<Vec<_>>>().map_err(db_error)
+}
+
+/// Pasa los eventos en memoria a la cola, uno por URL configurada.
+fn enqueue_buffered(conn: &Connection, urls: &[String]) -> Result<usize, AppError> {
+    let events: Vec<WebhookPayload> = std::mem::take(&mut *BUFFER.lock().unwrap());
+    if urls.is_empty() {
+        return Ok(0);
+    }
+    let tx = conn.unchecked_transaction().map_err(db_error)?;
+    for event in &events {
+        let body = serde_json::to_string(event).map_err(|e| AppError::Internal(e.to_string()))?;
+        for url in urls {
+            tx.execute(
+                "INSERT INTO webhook_deliveries (id, payload_id, url, event, body, status, attempts, next_attempt_at, created_at)
+                 VALUES (?1, ?2, ?3, ?4, ?5, 'pending', 0, ?6, ?6)",
+                params![uuid::Uuid::new_v4().to_string(), event.id, url, event.event, body, event.created_at as i64],
+            )
+            .map_err(db_error)?;
+        }
+    }
+    tx.commit().map_err(db_error)?;
+    Ok(events.len() * urls.len())
+}
+
+/// Envío pendiente cuyo turno ha llegado.
+struct DueDelivery {
+    id: String,
+    payload_id: String,
+    url: String,
+    event: String,
+    body: String,
+    attempts: u32,
+}
+
+fn due_deliveries(conn: &Connection, now: u64) -> Result<Vec<DueDelivery>, AppError> {
+    let mut stmt = conn
+        .prepare(
+            "SELECT id, payload_id, url, event, body, attempts FROM webhook_deliveries
+             WHERE status = 'pending' AND next_attempt_at <= ?1 ORDER BY created_at, id",
+        )
+        .map_err(db_error)?;
+    let rows = stmt
+        .query_map(params![now as i64], |row| {
+            Ok(DueDelivery {
+                id: row.get(0)?,
+                payload_id: row.get(1)?,
+                url: row.get(2)?,
+                event: row.get(3)?,
+                body: row.get(4)?,
+                attempts: row.get(5)?,
+            })
+        })
+        .map_err(db_error)?;
+    rows.collect::<rusqlite::Result<Vec<_>>>().map_err(db_error)
+}
+
+fn record_result(conn: &Connection, delivery: &DueDelivery, result: &Result<StatusCode, DeliveryError>) -> Result<(), AppError> {
+    match result {
+        Ok(_) => {
+            conn.execute("DELETE FROM webhook_deliveries WHERE id = ?1", params![delivery.id]).map_err(db_error)?;
+        }
+        Err(e) => {
+            let attempts = delivery.attempts + 1;
+            let status = if e.retry && attempts < MAX_ATTEMPTS { "pending" } else { "failed" };
+            conn.execute(
+                "UPDATE webhook_deliveries SET status = ?2, attempts = ?3, next_attempt_at = ?4, last_error = ?5 WHERE id = ?1",
+                params![
+                    delivery.id,
+                    status,
+                    attempts,
+                    (periods::now_timestamp() + backoff_secs(attempts - 1)) as i64,
+                    e.message
+                ],
+            )
+            .map_err(db_error)?;
+        }
+    }
+    Ok(())
+}
+
+/// Pasa los eventos nuevos a la cola y envía los que tocan. La base de datos solo se
+/// bloquea mientras se lee o se actualiza la cola, no durante los envíos.
+async fn process_queue(app: &AppHandle) -> Result<(), AppError> {
+    if PROCESSING.swap(true, Ordering::SeqCst) {
+        return Ok(());
+    }
+    let result = process_queue_inner(app).await;
+    PROCESSING.store(false, Ordering::SeqCst);
+    result
+}
+
+async fn process_queue_inner(app: &AppHandle) -> Result<(), AppError> {
+    let state = app.state::<AppState>();
+    if state.is_locked() {
+        return Ok(());
+    }
+    let due = {
+        let db = state.db().await?;
+        let urls = settings::load_settings(&db).webhook_urls;
+        let queued = enqueue_buffered(db.connection(), &urls)?;
+        if queued > 0 {
+            debug!("Queued {} webhook deliveries.", queued);
+        }
+        due_deliveries(db.connection(), periods::now_timestamp())?
+    };
+    if due.is_empty() {
+        return Ok(());
+    }
+
+    let secret = signing_secret()?;
+    let client = http_client()?;
+    for delivery in due {
+        let result = deliver(&client, &secret, &delivery.url, &delivery.payload_id, &delivery.event, &delivery.body).await;
+        match &result {
+            Ok(status) => debug!("Webhook {} delivered to {} ({}).", delivery.event, delivery.url, status),
+            Err(e) => warn!("Webhook {} to {} failed: {}", delivery.event, delivery.url, e.message),
+        }
+        let db = state.db().await?;
+        record_result(db.connection(), &delivery, &result)?;
+    }
+    Ok(())
+}
+
+/// Trabajador en segundo plano que envía los eventos a los webhooks configurados en
+/// cuanto se emiten y reintenta los fallidos con esperas crecientes.
+pub fn spawn_worker(app: AppHandle) {
+    tauri::async_runtime::spawn(async move {
+        let wake = WAKE.get_or_init(Notify::new);
+        loop {
+            let _ = tokio::time::timeout(WORKER_INTERVAL, wake.notified()).await;
+            if let Err(e) = process_queue(&app).await {
+                warn!("No se pudo procesar la cola de webhooks: {}", e);
+            }
+        }
+    });
+}
+
+// --- Comandos Tauri ---
+
+/// Comando para enviar a `url` un evento `webhook.test` firmado, sin pasar por la
+/// cola. Devuelve el código HTTP de la respuesta o un error `network` si no fue 2xx.
+#[tauri::command]
+pub async fn test_webhook_command(url: String) -> Result<WebhookTestResult, AppError> {
+    debug!("Received test_webhook_command: {}", url);
+    let url = validate_urls(&[url])?
+        .pop()
+        .ok_or_else(|| AppError::invalid_field("url", "Indica la dirección del webhook."))?;
+    let payload = WebhookPayload::new(
+        "webhook.test".to_string(),
+        serde_json::json!({ "message": "Prueba de webhook de Contabilidad IA" }),
+    );
+    let body = serde_json::to_string(&payload).map_err(|e| AppError::Internal(e.to_string()))?;
+    let started = Instant::now();
+    let status = deliver(&http_client()?, &signing_secret()?, &url, &payload.id, &payload.event, &body)
+        .await
+        .map_err(|e| AppError::Network(format!("El webhook falló: {}", e.message)))?;
+    info!("Test webhook to {} answered {}.", url, status);
+    Ok(WebhookTestResult { status: status.as_u16(), duration_ms: started.elapsed().as_millis() as u64 })
+}
+
+/// Comando para obtener el secreto con el que se firman los webhooks, para
+/// comprobar la firma en el servicio que los recibe.
+#[tauri::command]
+pub async fn get_webhook_secret_command() -> Result<String, AppError> {
+    debug!("Received get_webhook_secret_command.");
+    signing_secret()
+}
+
+/// Comando para sustituir el secreto de firma por uno nuevo.
+#[tauri::command]
+pub async fn regenerate_webhook_secret_command() -> Result<String, AppError> {
+    debug!("Received regenerate_webhook_secret_command.");
+    let secret = generate_secret();
+    keychain::set_secret(SECRET_NAME, &secret)?;
+    info!("Webhook signing secret regenerated.");
+    Ok(secret)
+}
+
+/// Comando para ver la cola de envíos: los pendientes de reintentar y los fallidos.
+/// Con `retry_failed` vuelve a poner los fallidos en la cola.
+#[tauri::command]
+pub async fn list_webhook_deliveries_command(
+    state: State<'_, AppState>,
+    retry_failed: Option<bool>,
+) -> Result<Vec<WebhookDelivery>, AppError> {
+    debug!("Received list_webhook_deliveries_command: retry_failed={:?}", retry_failed);
+    let db = state.db().await?;
+    if retry_failed.unwrap_or(false) {
+        let retried = db
+            .connection()
+            .execute(
+                "UPDATE webhook_deliveries SET status = 'pending', attempts = 0, next_attempt_at = ?1 WHERE status = 'failed'",
+                params![periods::now_timestamp() as i64],
+            )
+            .map_err(db_error)?;
+        info!("{} failed webhook deliveries queued again.", retried);
+        WAKE.get_or_init(Notify::new).notify_one();
+    }
+    list_deliveries(db.connection())
+}
@@ -0,0 +1,52 @@
+// src-tauri/src/windows.rs
+
+use tauri::{AppHandle, Manager, WebviewUrl, WebviewWindowBuilder};
+use log::{debug, info};
+
+use crate::error::AppError;
+
+/// Etiqueta de la ventana principal (la de `tauri.conf.json`).
+pub const MAIN_WINDOW_LABEL: &str = "main";
+/// Etiqueta de la ventana de entrada rápida. El frontend la reconoce por el
+/// parámetro `window` de la URL.
+pub const QUICK_ENTRY_WINDOW_LABEL: &str = "quick-entry";
+
+fn window_error(e: tauri::Error) -> AppError {
+    AppError::Internal(format!("Error al abrir la ventana: {}", e))
+}
+
+/// Muestra y enfoca la ventana `label` si ya está abierta. Devuelve `false` si no existe.
+pub fn focus_window(app: &AppHandle, label: &str) -> Result<bool, AppError> {
+    let Some(window) = app.get_webview_window(label) else {
+        return Ok(false);
+    };
+    window.unminimize().map_err(window_error)?;
+    window.show().map_err(window_error)?;
+    window.set_focus().map_err(window_error)?;
+    Ok(true)
+}
+
+// --- Comandos Tauri ---
+
+/// Comando para abrir la ventana de entrada rápida: pequeña, siempre visible por
+/// encima de las demás y sin el resto de la interfaz. Si ya está abierta, solo se
+/// enfoca. Comparte el estado con la ventana principal, y ambas se mantienen al día
+/// con los eventos de `events`.
+#[tauri::command]
+pub async fn open_quick_entry_window_command(app: AppHandle) -> Result<(), AppError> {
+    debug!("Received open_quick_entry_window_command.");
+    if focus_window(&app, QUICK_ENTRY_WINDOW_LABEL)? {
+        return Ok(());
+    }
+    let url = format!("index.html?window={}", QUICK_ENTRY_WINDOW_LABEL);
+    WebviewWindowBuilder::new(&app, QUICK_ENTRY_WINDOW_LABEL, WebviewUrl::App(url.into()))
+        .title("Nueva transacción")
+        .inner_size(420.0, 520.0)
+        .resizable(false)
+        .always_on_top(true)
+        .skip_taskbar(true)
+        .build()
+        .map_err(window_error)?;
+    info!("Quick entry window opened.");
+    Ok(())
+}
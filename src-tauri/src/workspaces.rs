@@ -0,0 +1,234 @@
+// src-tauri/src/workspaces.rs
+
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+use tauri::{AppHandle, State};
+use log::{debug, error, info, warn};
+
+use crate::attachments;
+use crate::audit;
+use crate::encryption;
+use crate::error::AppError;
+use crate::events;
+use crate::history;
+use crate::i18n;
+use crate::periods;
+use crate::storage::{self, SqliteStorage};
+use crate::users;
+use crate::AppState;
+
+const REGISTRY_FILE_NAME: &str = "workspaces.json";
+const WORKSPACES_DIR_NAME: &str = "workspaces";
+/// El espacio de trabajo por defecto usa el directorio de datos de la aplicación,
+/// así que las instalaciones anteriores a los espacios de trabajo lo conservan todo.
+pub const DEFAULT_WORKSPACE_ID: &str = "default";
+const DEFAULT_WORKSPACE_NAME: &str = "Principal";
+
+/// Un libro contable independiente (p. ej. una empresa), con su propia base de
+/// datos, copias de seguridad y justificantes.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Workspace {
+    pub id: String,
+    pub name: String,
+    pub created_at: u64,
+}
+
+impl Workspace {
+    /// Directorio de datos del espacio de trabajo.
+    pub fn data_dir(&self) -> PathBuf {
+        if self.id == DEFAULT_WORKSPACE_ID {
+            storage::get_app_data_dir()
+        } else {
+            storage::get_app_data_dir().join(WORKSPACES_DIR_NAME).join(&self.id)
+        }
+    }
+}
+
+/// Espacio de trabajo tal como se muestra en el frontend.
+#[derive(Debug, Clone, Serialize)]
+pub struct WorkspaceInfo {
+    #[serde(flatten)]
+    pub workspace: Workspace,
+    pub active: bool,
+    pub encrypted: bool,
+}
+
+/// Contenido de `workspaces.json`: los espacios de trabajo y cuál está activo.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct Registry {
+    active: String,
+    workspaces: Vec<Workspace>,
+}
+
+impl Default for Registry {
+    fn default() -> Self {
+        Registry {
+            active: DEFAULT_WORKSPACE_ID.to_string(),
+            workspaces: vec![Workspace {
+                id: DEFAULT_WORKSPACE_ID.to_string(),
+                name: DEFAULT_WORKSPACE_NAME.to_string(),
+                created_at: 0,
+            }],
+        }
+    }
+}
+
+fn registry_path() -> PathBuf {
+    storage::get_app_data_dir().join(REGISTRY_FILE_NAME)
+}
+
+fn load_registry() -> Result<Registry, AppError> {
+    let path = registry_path();
+    if !path.exists() {
+        return Ok(Registry::default());
+    }
+    let data = std::fs::read_to_string(&path)
+        .map_err(|e| AppError::Io(format!("Error al leer {}: {}", path.display(), e)))?;
+    serde_json::from_str(&data).map_err(|e| {
+        error!("Invalid workspace registry {}: {}", path.display(), e);
+        AppError::Internal(format!("El archivo de espacios de trabajo está dañado: {}", e))
+    })
+}
+
+fn save_registry(registry: &Registry) -> Result<(), AppError> {
+    let data = serde_json::to_vec_pretty(registry)
+        .map_err(|e| AppError::Internal(format!("Error al serializar los espacios de trabajo: {}", e)))?;
+    storage::write_atomic(&registry_path(), &data)
+}
+
+fn info_for(workspace: &Workspace, active_id: &str) -> WorkspaceInfo {
+    WorkspaceInfo {
+        workspace: workspace.clone(),
+        active: workspace.id == active_id,
+        encrypted: encryption::is_encrypted_file(&storage::database_path_in(&workspace.data_dir())),
+    }
+}
+
+/// Lee el espacio de trabajo activo al arrancar y fija su directorio de datos.
+/// Si el registro no se puede leer se usa el espacio por defecto.
+pub fn activate_saved_workspace() -> Workspace {
+    let registry = load_registry().unwrap_or_else(|e| {
+        warn!("No se pudieron leer los espacios de trabajo; se usa el principal: {}", e);
+        Registry::default()
+    });
+    let workspace = registry
+        .workspaces
+        .iter()
+        .find(|w| w.id == registry.active)
+        .cloned()
+        .unwrap_or_else(|| Registry::default().workspaces.remove(0));
+    storage::set_data_dir(workspace.data_dir());
+    info!("Espacio de trabajo activo: {} ({})", workspace.name, workspace.id);
+    workspace
+}
+
+// --- Comandos Tauri ---
+
+/// Comando para listar los espacios de trabajo.
+#[tauri::command]
+pub async fn list_workspaces_command(state: State<'_, AppState>) -> Result<Vec<WorkspaceInfo>, AppError> {
+    debug!("Received list_workspaces_command.");
+    let active_id = state.workspace.lock().unwrap().id.clone();
+    let registry = load_registry()?;
+    Ok(registry.workspaces.iter().map(|w| info_for(w, &active_id)).collect())
+}
+
+/// Comando para crear un espacio de trabajo vacío. No cambia el activo.
+#[tauri::command]
+pub async fn create_workspace_command(state: State<'_, AppState>, name: String) -> Result<WorkspaceInfo, AppError> {
+    debug!("Received create_workspace_command: '{}'", name);
+    let name = name.trim();
+    if name.is_empty() {
+        error!("Create workspace: empty name.");
+        return Err(AppError::invalid_field("name", "El nombre del espacio de trabajo no puede estar vacío."));
+    }
+
+    let mut registry = load_registry()?;
+    if registry.workspaces.iter().any(|w| w.name.eq_ignore_ascii_case(name)) {
+        return Err(AppError::Conflict(format!("Ya existe un espacio de trabajo llamado '{}'.", name)));
+    }
+    let workspace = Workspace {
+        id: uuid::Uuid::new_v4().to_string(),
+        name: name.to_owned(),
+        created_at: periods::now_timestamp(),
+    };
+    // Crea el directorio y la base de datos con el esquema actual.
+    SqliteStorage::open(&storage::database_path_in(&workspace.data_dir()))?;
+
+    registry.workspaces.push(workspace.clone());
+    save_registry(&registry)?;
+    info!("Workspace created: {} ({})", workspace.name, workspace.id);
+
+    let active_id = state.workspace.lock().unwrap().id.clone();
+    Ok(info_for(&workspace, &active_id))
+}
+
+/// Comando para cambiar de espacio de trabajo. Si el nuevo está cifrado, la
+/// aplicación queda bloqueada hasta `unlock_data_command`. El historial de
+/// deshacer/rehacer se vacía porque pertenece al espacio anterior.
+#[tauri::command]
+pub async fn switch_workspace_command(
+    state: State<'_, AppState>,
+    app: AppHandle,
+    id: String,
+) -> Result<WorkspaceInfo, AppError> {
+    info!("Received switch_workspace_command: {}", id);
+    let mut registry = load_registry()?;
+    let workspace = registry
+        .workspaces
+        .iter()
+        .find(|w| w.id == id)
+        .cloned()
+        .ok_or_else(|| AppError::NotFound(format!("Espacio de trabajo {} no encontrado.", id)))?;
+
+    // Se usa el mutex directamente: también se puede cambiar desde un espacio bloqueado.
+    let mut db = state.db.lock().await;
+    if state.workspace.lock().unwrap().id == workspace.id {
+        return Ok(info_for(&workspace, &workspace.id));
+    }
+    if !state.is_locked() {
+        db.flush()?;
+    }
+
+    let data_dir = workspace.data_dir();
+    let db_path = storage::database_path_in(&data_dir);
+    let locked = encryption::is_encrypted_file(&db_path);
+    let opened = if locked {
+        SqliteStorage::open_in_memory()?
+    } else {
+        let backup_path = storage::sibling_path(&db_path, ".bak");
+        let (opened, recovery) = storage::open_with_recovery(&db_path, &backup_path)?;
+        match recovery {
+            Some(report) => warn!("La base de datos del espacio {} se restauró desde la copia: {:?}", workspace.id, report),
+            None => storage::refresh_backup(&opened, &backup_path),
+        }
+        opened
+    };
+
+    storage::set_data_dir(data_dir);
+    *db = opened;
+    // Los usuarios son de cada espacio de trabajo.
+    users::end_session();
+    i18n::apply_settings(&db);
+    state.set_locked(locked);
+    {
+        let mut history = state.history.lock().unwrap();
+        history.clear();
+        if !locked {
+            history.set_max_depth(history::load_history_depth(&db));
+        }
+    }
+    *state.workspace.lock().unwrap() = workspace.clone();
+    if !locked {
+        if let Err(e) = attachments::cleanup_orphan_attachments(&db) {
+            warn!("No se pudieron limpiar los adjuntos huérfanos: {}", e);
+        }
+        audit::record(db.connection(), "switch_workspace_command", Some(&workspace.id), None, audit::snapshot(&workspace));
+    }
+
+    registry.active = workspace.id.clone();
+    save_registry(&registry)?;
+    events::emit_data_reloaded(&app);
+    info!("Switched to workspace {} ({}).", workspace.name, workspace.id);
+    Ok(info_for(&workspace, &workspace.id))
+}
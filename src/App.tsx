@@ -2,6 +2,7 @@ import { useState, useEffect, useCallback } from 'react';
 import { invoke } from '@tauri-apps/api/core'; // Tauri v2 API
 import { open } from '@tauri-apps/plugin-shell'; // Tauri v2 API
 import { getCurrentWindow } from '@tauri-apps/api/window'; // Importado para el evento tauri://ready y control de ventana
+import { listen } from '@tauri-apps/api/event';
 
 // Declare global interface for __TAURI_IPC__ to resolve TypeScript error
 // This tells TypeScript that `__TAURI_IPC__` might exist on the `window` object.
@@ -15,12 +16,45 @@ declare global {
 interface Transaction {
   id: string;
   type: 'Ingreso' | 'Gasto';
-  amount: number;
+  amount: string; // Importe decimal exacto serializado como texto por Rust
   description: string;
   store_name: string;
   timestamp: number;
+  transaction_date: string; // AAAA-MM-DD, la fecha indicada por el usuario
+  created_at: number;
+  updated_at: number;
+  notes?: string;
+  custom_fields?: Record<string, string>;
+  payment_method?: "Efectivo" | "Tarjeta" | "Transferencia" | "Bizum" | "Otro";
+  contact_id?: string | null; // Cliente o proveedor (ver src-tauri/src/contacts.rs)
+  tax_rate?: string | null; // Tipo de IVA en porcentaje
+  tax_amount?: string | null; // Cuota de IVA incluida en amount
+  tags?: string[];
 }
 
+// Tienda con sus datos (ver `Store` en src-tauri/src/stores.rs)
+interface Store {
+  name: string;
+  address: string | null;
+  nif: string | null;
+  default_category: string | null;
+  transaction_count: number;
+}
+
+// Error que devuelven los comandos de Rust (ver `AppError` en src-tauri/src/error.rs)
+interface AppError {
+  code: string;
+  message: string;
+  field: string | null;
+  duplicate_ids?: string[]; // Solo en los errores 'duplicate'
+  retry_after_secs?: number | null; // Solo en los errores 'rate_limited'
+  request_id?: string; // Solo en los errores 'queued'
+}
+
+// Extrae el mensaje legible de un error de `invoke`
+const errorMessage = (e: unknown): string =>
+  typeof e === 'object' && e !== null && 'message' in e ? (e as AppError).message : String(e);
+
 // Función de formato de moneda síncrona en JavaScript
 const formatCurrencyJs = (amount: number): string => {
   const s = amount.toFixed(2);
@@ -50,6 +84,14 @@ const formatCurrencyJs = (amount: number): string => {
   }
 };
 
+// Balance de una tienda devuelto por get_store_balances_command (importes en la moneda base)
+interface StoreBalance {
+  store_name: string;
+  income: string;
+  expenses: string;
+  net: string;
+  transaction_count: number;
+}
 
 function App() {
   const [transactions, setTransactions] = useState<Transaction[]>([]);
@@ -57,6 +99,7 @@ function App() {
   const [selectedStoreIndex, setSelectedStoreIndex] = useState(0);
   const [currentTab, setCurrentTab] = useState('input');
   const [statusMessage, setStatusMessage] = useState('');
+  const [pendingAlerts, setPendingAlerts] = useState(0);
 
   // Estados para la nueva transacción
   const [newAmount, setNewAmount] = useState('');
@@ -88,7 +131,7 @@ function App() {
   const [storeToEdit, setStoreToEdit] = useState<string | null>(null);
   const [storeToDelete, setStoreToDelete] = useState<string | null>(null);
   const [newStoreName, setNewStoreName] = useState('');
-  const [storeInfoMap, setStoreInfoMap] = useState<Record<string, number>>({});
+  const [storeBalances, setStoreBalances] = useState<StoreBalance[]>([]);
   
   // Estado para el resumen de IA
   const [iaSummary, setIaSummary] = useState<string | null>(null);
@@ -151,34 +194,34 @@ ${JSON.stringify(transactions, null, 2)}
       console.log('Frontend: Transactions state updated. Current transactions length:', result.length);
     } catch (e: any) {
       console.error('Frontend: Error fetching transactions:', e);
-      setStatusMessage(`Error al cargar transacciones: ${e}`);
+      setStatusMessage(`Error al cargar transacciones: ${errorMessage(e)}`);
     }
   }, []);
 
   const fetchUniqueStores = useCallback(async () => {
     console.log('Frontend: Calling get_unique_stores...');
     try {
-      const result: string[] = await invoke('get_unique_stores');
-      console.log('Frontend: get_unique_stores successful, received:', result);
+      const stores: Store[] = await invoke('get_unique_stores');
+      console.log('Frontend: get_unique_stores successful, received:', stores);
       // Asegurarse de que 'Todas las Tiendas' esté siempre al principio si existe
-      const filteredResult = result.filter(s => s !== 'Todas las Tiendas');
+      const filteredResult = stores.map(s => s.name).filter(s => s !== 'Todas las Tiendas');
       setAllStores(['Todas las Tiendas', ...filteredResult.sort()]);
       console.log('Frontend: allStores state updated to:', ['Todas las Tiendas', ...filteredResult.sort()]);
     } catch (e: any) {
       console.error('Frontend: Error fetching unique stores:', e);
-      setStatusMessage(`Error al cargar tiendas: ${e}`);
+      setStatusMessage(`Error al cargar tiendas: ${errorMessage(e)}`);
     }
   }, []);
 
   const fetchStoreInfo = useCallback(async () => {
-    console.log('Frontend: Calling get_store_info_command...');
+    console.log('Frontend: Calling get_store_balances_command...');
     try {
-      const result: Record<string, number> = await invoke("get_store_info_command");
-      console.log('Frontend: get_store_info_command successful, received:', result);
-      setStoreInfoMap(result);
+      const result: { stores: StoreBalance[] } = await invoke("get_store_balances_command");
+      console.log('Frontend: get_store_balances_command successful, received:', result);
+      setStoreBalances(result.stores);
     } catch (e) {
       console.error("Frontend: Error al cargar info de tiendas:", e);
-      setStatusMessage(`Error al cargar información de tiendas: ${e}`);
+      setStatusMessage(`Error al cargar información de tiendas: ${errorMessage(e)}`);
     }
   }, []);
 
@@ -206,6 +249,41 @@ ${JSON.stringify(transactions, null, 2)}
     initializeAppData();
   }, [appWindow, fetchTransactions, fetchUniqueStores, fetchStoreInfo]); // Dependencias para useCallback
 
+  // Recargar cuando otra ventana (p. ej. la de entrada rápida) modifica los datos
+  useEffect(() => {
+    const changeEvents = [
+      'transaction://created', 'transaction://updated', 'transaction://deleted',
+      'store://updated', 'store://renamed', 'store://merged', 'store://deleted', 'data://reloaded',
+    ];
+    const unlisteners = changeEvents.map((event) => listen(event, () => {
+      fetchTransactions();
+      fetchUniqueStores();
+      fetchStoreInfo();
+    }));
+    return () => {
+      unlisteners.forEach((unlisten) => unlisten.then((fn) => fn()));
+    };
+  }, [fetchTransactions, fetchUniqueStores, fetchStoreInfo]);
+
+  // "Abrir informe del mes" desde la bandeja del sistema
+  useEffect(() => {
+    const unlisten = listen('tray://open-month-report', () => setCurrentTab('summary'));
+    return () => {
+      unlisten.then((fn) => fn());
+    };
+  }, []);
+
+  // Contador de avisos pendientes (recurrentes, facturas vencidas, presupuestos superados)
+  useEffect(() => {
+    invoke<unknown[]>('get_pending_alerts_command')
+      .then((alerts) => setPendingAlerts(alerts.length))
+      .catch((e) => console.error('Frontend: Error fetching pending alerts:', e));
+    const unlisten = listen<number>('alerts://changed', (event) => setPendingAlerts(event.payload));
+    return () => {
+      unlisten.then((fn) => fn());
+    };
+  }, []);
+
   // Actualizar tiendas únicas cuando cambian las transacciones (sin dependencias asíncronas)
   useEffect(() => {
     console.log('Frontend: allStores state or selectedStoreIndex changed. Current allStores:', allStores);
@@ -248,7 +326,7 @@ ${JSON.stringify(transactions, null, 2)}
       console.log('Frontend: Calling add_transaction_command with:', { newType, amountNum, newDescription, newStore });
       const newTrans: Transaction = await invoke('add_transaction_command', {
         transactionTypeStr: newType,
-        amount: amountNum,
+        amount: newAmount.trim(),
         description: newDescription.trim(),
         storeName: newStore.trim(),
       });
@@ -262,7 +340,7 @@ ${JSON.stringify(transactions, null, 2)}
       setNewType('Ingreso');
     } catch (e: any) {
       console.error('Frontend: Error adding transaction:', e);
-      setStatusMessage(`Error al añadir transacción: ${e}`);
+      setStatusMessage(`Error al añadir transacción: ${errorMessage(e)}`);
     } finally {
       // Siempre refrescar tiendas e información de tiendas después de añadir, incluso si hubo un error
       fetchUniqueStores();
@@ -273,7 +351,7 @@ ${JSON.stringify(transactions, null, 2)}
   const handleEditTransaction = (transaction: Transaction) => {
     console.log('Frontend: handleEditTransaction called for ID:', transaction.id);
     setEditingTransaction(transaction);
-    setEditAmount(transaction.amount.toString());
+    setEditAmount(transaction.amount);
     setEditDescription(transaction.description);
     setEditStore(transaction.store_name);
     setEditType(transaction.type);
@@ -302,7 +380,7 @@ ${JSON.stringify(transactions, null, 2)}
       const updatedTrans: Transaction = await invoke('update_transaction_command', {
         id: editingTransaction.id,
         transactionTypeStr: editType,
-        amount: amountNum,
+        amount: editAmount.trim(),
         description: editDescription.trim(),
         storeName: editStore.trim(),
       });
@@ -315,7 +393,7 @@ ${JSON.stringify(transactions, null, 2)}
       setEditingTransaction(null);
     } catch (e: any) {
       console.error('Frontend: Error updating transaction:', e);
-      setStatusMessage(`Error al actualizar transacción: ${e}`);
+      setStatusMessage(`Error al actualizar transacción: ${errorMessage(e)}`);
     } finally {
       fetchUniqueStores();
       fetchStoreInfo();
@@ -344,7 +422,7 @@ ${JSON.stringify(transactions, null, 2)}
       setDeletingTransactionId(null);
     } catch (e: any) {
       console.error('Frontend: Error deleting transaction:', e);
-      setStatusMessage(`Error al eliminar transacción: ${e}`);
+      setStatusMessage(`Error al eliminar transacción: ${errorMessage(e)}`);
     } finally {
       fetchUniqueStores();
       fetchStoreInfo();
@@ -393,7 +471,7 @@ ${JSON.stringify(transactions, null, 2)}
       setNewStoreName('');
     } catch (e: any) {
       console.error("Frontend: Error al renombrar tienda:", e);
-      setStatusMessage(`Error al renombrar tienda: ${e}`);
+      setStatusMessage(`Error al renombrar tienda: ${errorMessage(e)}`);
     } finally {
       // Siempre refrescar tiendas e información de tiendas después de renombrar, incluso si hubo un error
       fetchUniqueStores();
@@ -424,7 +502,7 @@ ${JSON.stringify(transactions, null, 2)}
       setStoreToDelete(null);
     } catch (e: any) {
       console.error("Frontend: Error al eliminar tienda:", e);
-      setStatusMessage(`Error al eliminar tienda: ${e}`);
+      setStatusMessage(`Error al eliminar tienda: ${errorMessage(e)}`);
     } finally {
       // Siempre refrescar tiendas e información de tiendas después de eliminar, incluso si hubo un error
       fetchUniqueStores();
@@ -451,8 +529,8 @@ ${JSON.stringify(transactions, null, 2)}
       setStatusMessage('Consulta a la IA completada.');
     } catch (e: any) {
       console.error('Frontend: Error calling AI:', e);
-      setAiError(`Error al consultar IA: ${e}`);
-      setStatusMessage(`Error al consultar IA: ${e}`);
+      setAiError(`Error al consultar IA: ${errorMessage(e)}`);
+      setStatusMessage(`Error al consultar IA: ${errorMessage(e)}`);
     } finally {
       setAiLoading(false);
     }
@@ -464,11 +542,11 @@ ${JSON.stringify(transactions, null, 2)}
 
   const totalIngresos = filteredTransactions
     .filter(t => t.type === 'Ingreso')
-    .reduce((sum, t) => sum + t.amount, 0);
+    .reduce((sum, t) => sum + Number(t.amount), 0);
 
   const totalGastos = filteredTransactions
     .filter(t => t.type === 'Gasto')
-    .reduce((sum, t) => sum + t.amount, 0);
+    .reduce((sum, t) => sum + Number(t.amount), 0);
 
   const balance = totalIngresos - totalGastos;
 
@@ -516,6 +594,11 @@ ${JSON.stringify(transactions, null, 2)}
         {/* Header */}
         <h1 className="text-3xl font-bold text-blue-400 mb-6">
           📊 Gestor de Contabilidad Multi-Tienda con IA
+          {pendingAlerts > 0 && (
+            <span className="ml-3 align-middle bg-red-600 text-white text-sm font-bold rounded-full px-3 py-1" title="Avisos pendientes">
+              🔔 {pendingAlerts}
+            </span>
+          )}
         </h1>
 
         {/* Tabs */}
@@ -662,6 +745,12 @@ ${JSON.stringify(transactions, null, 2)}
                 >
                   ✅ Registrar Transacción
                 </button>
+                <button
+                  onClick={() => invoke('open_quick_entry_window_command').catch((e) => setStatusMessage(`Error al abrir la entrada rápida: ${errorMessage(e)}`))}
+                  className="ml-4 bg-gray-600 hover:bg-gray-700 text-white font-bold py-3 px-6 rounded-lg shadow-md transition-colors text-lg"
+                >
+                  ⚡ Entrada rápida
+                </button>
               </div>
             </div>
           )}
@@ -734,7 +823,7 @@ ${JSON.stringify(transactions, null, 2)}
                           <td className="px-6 py-4 whitespace-nowrap text-sm text-gray-300">{getLocalFormattedDate(t.timestamp)}</td>
                           <td className="px-6 py-4 whitespace-nowrap text-sm">
                             <span className={`font-semibold ${t.type === 'Ingreso' ? 'text-green-300' : 'text-red-300'}`}>
-                              {t.type} {formatCurrencyJs(t.amount)}
+                              {t.type} {formatCurrencyJs(Number(t.amount))}
                             </span>
                           </td>
                           <td className="px-6 py-4 whitespace-nowrap text-sm text-gray-300">{t.description} ({t.store_name})</td>
@@ -771,12 +860,15 @@ ${JSON.stringify(transactions, null, 2)}
             <div>
               <h2 className="text-2xl font-bold text-gray-200 mb-6">🏪 Gestor de Tiendas</h2>
               <div className="space-y-4">
-                {Object.entries(storeInfoMap).length > 0 ? (
-                  Object.entries(storeInfoMap).map(([store, count]) => (
+                {storeBalances.length > 0 ? (
+                  storeBalances.map(({ store_name: store, income, expenses, net, transaction_count }) => (
                     <div key={store} className="bg-gray-700 rounded-lg p-4 flex justify-between items-center shadow-sm">
                       <div>
                         <p className="text-lg text-gray-100 font-semibold">{store}</p>
-                        <p className="text-sm text-gray-400">Transacciones: {count}</p>
+                        <p className="text-sm text-gray-400">Transacciones: {transaction_count}</p>
+                        <p className="text-sm text-gray-400">
+                          Ingresos: {formatCurrencyJs(Number(income))} · Gastos: {formatCurrencyJs(Number(expenses))} · Balance: {formatCurrencyJs(Number(net))}
+                        </p>
                       </div>
                       <div className="flex space-x-3">
                         <button
@@ -0,0 +1,86 @@
+import { useState, useEffect } from 'react';
+import { invoke } from '@tauri-apps/api/core';
+import { getCurrentWindow } from '@tauri-apps/api/window';
+
+// Ventana de entrada rápida (ver `open_quick_entry_window_command` en src-tauri/src/windows.rs).
+// Los cambios llegan a la ventana principal por los eventos `transaction://*`.
+
+interface Store {
+  name: string;
+}
+
+const errorMessage = (e: unknown): string =>
+  typeof e === 'object' && e !== null && 'message' in e ? (e as { message: string }).message : String(e);
+
+function QuickEntry() {
+  const [type, setType] = useState<'Ingreso' | 'Gasto'>('Gasto');
+  const [amount, setAmount] = useState('');
+  const [description, setDescription] = useState('');
+  const [store, setStore] = useState('');
+  const [stores, setStores] = useState<string[]>([]);
+  const [statusMessage, setStatusMessage] = useState('');
+
+  useEffect(() => {
+    invoke<Store[]>('get_unique_stores')
+      .then((result) => setStores(result.map((s) => s.name)))
+      .catch((e) => setStatusMessage(`Error al cargar tiendas: ${errorMessage(e)}`));
+    const closeOnEscape = (e: KeyboardEvent) => {
+      if (e.key === 'Escape') getCurrentWindow().close();
+    };
+    window.addEventListener('keydown', closeOnEscape);
+    return () => window.removeEventListener('keydown', closeOnEscape);
+  }, []);
+
+  const handleSubmit = async (e: React.FormEvent) => {
+    e.preventDefault();
+    if (!amount.trim() || !description.trim() || !store.trim()) {
+      setStatusMessage('Todos los campos son obligatorios.');
+      return;
+    }
+    try {
+      await invoke('add_transaction_command', {
+        transactionTypeStr: type,
+        amount: amount.trim(),
+        description: description.trim(),
+        storeName: store.trim(),
+      });
+      setStatusMessage(`✅ ${type} de ${amount.trim()} registrado.`);
+      setAmount('');
+      setDescription('');
+    } catch (e) {
+      setStatusMessage(`Error: ${errorMessage(e)}`);
+    }
+  };
+
+  const inputClass = 'block w-full py-2 px-3 rounded-md bg-gray-700 border-gray-600 text-gray-100 focus:outline-none';
+
+  return (
+    <form onSubmit={handleSubmit} className="min-h-screen bg-gray-900 text-gray-100 p-4 space-y-3">
+      <h1 className="text-xl font-bold text-blue-400">⚡ Nueva transacción</h1>
+      <div className="flex space-x-2">
+        {(['Gasto', 'Ingreso'] as const).map((t) => (
+          <button
+            key={t}
+            type="button"
+            onClick={() => setType(t)}
+            className={`flex-1 py-2 rounded-md font-semibold ${type === t ? 'bg-blue-600 text-white' : 'bg-gray-700 text-blue-200'}`}
+          >
+            {t}
+          </button>
+        ))}
+      </div>
+      <input autoFocus className={inputClass} placeholder="Monto" inputMode="decimal" value={amount} onChange={(e) => setAmount(e.target.value)} />
+      <input className={inputClass} placeholder="Descripción" value={description} onChange={(e) => setDescription(e.target.value)} />
+      <input className={inputClass} placeholder="Tienda" list="quick-entry-stores" value={store} onChange={(e) => setStore(e.target.value)} />
+      <datalist id="quick-entry-stores">
+        {stores.map((s) => <option key={s} value={s} />)}
+      </datalist>
+      <button type="submit" className="w-full bg-green-600 hover:bg-green-700 text-white font-bold py-2 rounded-lg">
+        ✅ Registrar
+      </button>
+      {statusMessage && <p className="text-sm text-gray-300">{statusMessage}</p>}
+    </form>
+  );
+}
+
+export default QuickEntry;
@@ -1,10 +1,14 @@
 import React from 'react';
 import ReactDOM from 'react-dom/client';
 import App from './App';
+import QuickEntry from './QuickEntry';
 import './index.css';
 
+// Las ventanas secundarias cargan el mismo index.html con `?window=<etiqueta>`.
+const windowKind = new URLSearchParams(window.location.search).get('window');
+
 ReactDOM.createRoot(document.getElementById('root') as HTMLElement).render(
   <React.StrictMode>
-    <App />
+    {windowKind === 'quick-entry' ? <QuickEntry /> : <App />}
   </React.StrictMode>,
 );